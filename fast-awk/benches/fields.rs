@@ -0,0 +1,32 @@
+//! Field splitting and access on the record path: `cargo bench -p fast-awk`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fast_awk::{Interpreter, Parser, RuntimeContext, Value};
+
+const RECORD: &str = "2024-03-01T12:00:00Z 10.0.0.7 GET /api/v1/items 200 5123 0.042 curl/8.5.0";
+
+fn split(c: &mut Criterion) {
+    let mut context = RuntimeContext::new();
+    c.bench_function("split whitespace", |b| b.iter(|| context.set_current_record(black_box(RECORD))));
+
+    let mut context = RuntimeContext::new();
+    context.set_variable("FS", Value::from("[ /]+"));
+    c.bench_function("split regex FS", |b| b.iter(|| context.set_current_record(black_box(RECORD))));
+}
+
+fn run(c: &mut Criterion, name: &str, source: &str) {
+    let program = Parser::new(source).unwrap().parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    c.bench_function(name, |b| {
+        b.iter(|| interpreter.execute_main_rules(&program, black_box(RECORD)).unwrap())
+    });
+}
+
+fn access(c: &mut Criterion) {
+    run(c, "read fields", "{ bytes += $6; if ($5 == 200) ok++ }");
+    run(c, "match record", "/curl/ { n++ }");
+    run(c, "assign field", "{ $2 = \"-\" }");
+}
+
+criterion_group!(benches, split, access);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Record splitting on `RS`, streamed and mapped: `cargo bench -p fast-awk --bench records`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fast_awk::record::{RecordReader, RecordSource};
+use fast_awk::{Interpreter, Parser};
+use std::io::BufReader;
+
+/// Log-like input: lines of a few fields, in paragraphs of three to five
+/// lines separated by one or two blank lines
+fn input() -> Vec<u8> {
+    let mut input = Vec::new();
+    for paragraph in 0..20_000 {
+        for line in 0..3 + paragraph % 3 {
+            input.extend_from_slice(format!("host{} GET /items/{} 200 {}\n", paragraph % 50, line, paragraph).as_bytes());
+        }
+        input.extend_from_slice(if paragraph % 4 == 0 { b"--\n\n" } else { b"\n" });
+    }
+    input
+}
+
+fn count_records(data: &[u8], rs: &str) -> usize {
+    let mut records = RecordReader::new(BufReader::new(data));
+    let mut count = 0;
+    while let Some(record) = records.next_record(rs).unwrap() {
+        black_box(record);
+        count += 1;
+    }
+    count
+}
+
+fn split(c: &mut Criterion) {
+    let data = input();
+    let mut group = c.benchmark_group("split records");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for (name, rs) in [("newline", "\n"), ("literal", "--"), ("paragraph", ""), ("regex", "\n\n+")] {
+        group.bench_function(name, |b| b.iter(|| count_records(black_box(&data), rs)));
+    }
+    group.finish();
+}
+
+fn match_records(c: &mut Criterion) {
+    let data = input();
+    let program = Parser::new(r#"match($0, "GET /items/[0-9]+") { n += RLENGTH }"#).unwrap().parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    c.bench_function("match() on paragraphs", |b| {
+        b.iter(|| {
+            let mut records = RecordReader::new(BufReader::new(black_box(&data[..])));
+            while let Some(record) = records.next_record("\n\n+").unwrap() {
+                interpreter.execute_main_rules(&program, std::str::from_utf8(record).unwrap()).unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, split, match_records);
+criterion_main!(benches);
@@ -0,0 +1,390 @@
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Insertion-ordered associative array backing AWK arrays.
+///
+/// Elements live in a slot vector addressed through a hash index, so lookups
+/// stay O(1) while `for (k in arr)` can walk keys in first-assignment order
+/// without sorting. Deleted slots are tombstoned and compacted once they
+/// outnumber the live ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AwkArray {
+    slots: Vec<Option<(String, Value)>>,
+    index: HashMap<String, usize>,
+}
+
+impl AwkArray {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let slot = *self.index.get(key)?;
+        self.slots[slot].as_ref().map(|(_, value)| value)
+    }
+
+    /// Get an element, creating it as `Undefined` if it does not exist yet
+    pub fn entry(&mut self, key: &str) -> &mut Value {
+        let slot = match self.index.get(key) {
+            Some(&slot) => slot,
+            None => self.push(key.to_string(), Value::Undefined),
+        };
+        &mut self.slots[slot].as_mut().expect("indexed slot is live").1
+    }
+
+    pub fn insert(&mut self, key: &str, value: Value) {
+        *self.entry(key) = value;
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let slot = self.index.remove(key)?;
+        let removed = self.slots[slot].take().map(|(_, value)| value);
+        if self.slots.len() > 2 * self.index.len() + 8 {
+            self.compact();
+        }
+        removed
+    }
+
+    /// Drop every element and give their storage back, for `delete arr`
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Iterate over live elements in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|(key, value)| (key, value)))
+    }
+
+    /// Keys in insertion order
+    pub fn keys(&self) -> Vec<String> {
+        self.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    fn push(&mut self, key: String, value: Value) -> usize {
+        let slot = self.slots.len();
+        self.index.insert(key.clone(), slot);
+        self.slots.push(Some((key, value)));
+        slot
+    }
+
+    /// Drop tombstones and release the capacity they held, so an array
+    /// that grew large and was mostly deleted shrinks with it
+    fn compact(&mut self) {
+        self.slots.retain(Option::is_some);
+        self.slots.shrink_to_fit();
+        self.index.shrink_to_fit();
+        for (slot, entry) in self.slots.iter().enumerate() {
+            if let Some((key, _)) = entry {
+                self.index.insert(key.clone(), slot);
+            }
+        }
+    }
+}
+
+impl PartialEq for AwkArray {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+/// Handle to an array in an [`ArrayArena`]. Values hold handles rather
+/// than arrays, so reading an array variable or passing it to a function
+/// shares the array instead of copying it, as AWK passes arrays by reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArrayId {
+    slot: u32,
+    generation: u32,
+}
+
+/// Every array a running program holds, addressed by [`ArrayId`].
+///
+/// A freed slot is reused under a new generation, so a handle that outlived
+/// its array finds nothing rather than whichever array took its place.
+#[derive(Debug, Clone, Default)]
+pub struct ArrayArena {
+    slots: Vec<ArenaSlot>,
+    /// Slots of freed arrays, reused before the arena grows
+    free: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ArenaSlot {
+    generation: u32,
+    array: Option<AwkArray>,
+}
+
+impl ArrayArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `array`, returning its handle
+    pub fn alloc(&mut self, array: AwkArray) -> ArrayId {
+        match self.free.pop() {
+            Some(slot) => {
+                let entry = &mut self.slots[slot as usize];
+                entry.array = Some(array);
+                ArrayId { slot, generation: entry.generation }
+            }
+            None => {
+                let slot = u32::try_from(self.slots.len()).expect("fewer than 2^32 arrays");
+                self.slots.push(ArenaSlot { generation: 0, array: Some(array) });
+                ArrayId { slot, generation: 0 }
+            }
+        }
+    }
+
+    pub fn get(&self, id: ArrayId) -> Option<&AwkArray> {
+        let entry = self.slots.get(id.slot as usize)?;
+        (entry.generation == id.generation).then_some(entry.array.as_ref()).flatten()
+    }
+
+    pub fn get_mut(&mut self, id: ArrayId) -> Option<&mut AwkArray> {
+        let entry = self.slots.get_mut(id.slot as usize)?;
+        (entry.generation == id.generation).then_some(entry.array.as_mut()).flatten()
+    }
+
+    /// Drop the array behind `id`; the handle and any copies of it go stale
+    pub fn free(&mut self, id: ArrayId) -> Option<AwkArray> {
+        let entry = self.slots.get_mut(id.slot as usize)?;
+        if entry.generation != id.generation {
+            return None;
+        }
+        let array = entry.array.take()?;
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(id.slot);
+        Some(array)
+    }
+
+    /// Number of live arrays
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Predefined `for (k in arr)` traversal orders selected through
+/// `PROCINFO["sorted_in"]`, mirroring gawk's `@...` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortedIn {
+    Unsorted,
+    IndStrAsc,
+    IndStrDesc,
+    IndNumAsc,
+    IndNumDesc,
+    ValTypeAsc,
+    ValTypeDesc,
+    ValStrAsc,
+    ValStrDesc,
+    ValNumAsc,
+    ValNumDesc,
+}
+
+impl SortedIn {
+    pub fn parse(name: &str) -> Option<Self> {
+        let order = match name {
+            "@unsorted" => SortedIn::Unsorted,
+            "@ind_str_asc" => SortedIn::IndStrAsc,
+            "@ind_str_desc" => SortedIn::IndStrDesc,
+            "@ind_num_asc" => SortedIn::IndNumAsc,
+            "@ind_num_desc" => SortedIn::IndNumDesc,
+            "@val_type_asc" => SortedIn::ValTypeAsc,
+            "@val_type_desc" => SortedIn::ValTypeDesc,
+            "@val_str_asc" => SortedIn::ValStrAsc,
+            "@val_str_desc" => SortedIn::ValStrDesc,
+            "@val_num_asc" => SortedIn::ValNumAsc,
+            "@val_num_desc" => SortedIn::ValNumDesc,
+            _ => return None,
+        };
+        Some(order)
+    }
+
+    /// Return the array's keys in this traversal order
+    pub fn sorted_keys(self, array: &AwkArray) -> Vec<String> {
+        let mut entries: Vec<(&String, &Value)> = array.iter().collect();
+
+        let ascending = match self {
+            SortedIn::Unsorted => return array.keys(),
+            SortedIn::IndStrAsc => {
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                true
+            }
+            SortedIn::IndStrDesc => {
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                false
+            }
+            SortedIn::IndNumAsc | SortedIn::IndNumDesc => {
+                entries.sort_by(|a, b| compare_index_numeric(a.0, b.0));
+                self == SortedIn::IndNumAsc
+            }
+            SortedIn::ValTypeAsc | SortedIn::ValTypeDesc => {
+                entries.sort_by(|a, b| compare_value_typed(a.1, b.1).then_with(|| a.0.cmp(b.0)));
+                self == SortedIn::ValTypeAsc
+            }
+            SortedIn::ValStrAsc | SortedIn::ValStrDesc => {
+                entries.sort_by(|a, b| {
+                    a.1.to_string().cmp(&b.1.to_string()).then_with(|| a.0.cmp(b.0))
+                });
+                self == SortedIn::ValStrAsc
+            }
+            SortedIn::ValNumAsc | SortedIn::ValNumDesc => {
+                entries.sort_by(|a, b| {
+                    a.1.compare_numeric(b.1)
+                        .then_with(|| a.1.to_string().cmp(&b.1.to_string()))
+                        .then_with(|| a.0.cmp(b.0))
+                });
+                self == SortedIn::ValNumAsc
+            }
+        };
+
+        if !ascending {
+            entries.reverse();
+        }
+        entries.into_iter().map(|(key, _)| key.clone()).collect()
+    }
+}
+
+fn compare_index_numeric(a: &str, b: &str) -> Ordering {
+    let a_num = Value::from(a).to_number();
+    let b_num = Value::from(b).to_number();
+    a_num
+        .partial_cmp(&b_num)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.cmp(b))
+}
+
+/// gawk's `@val_type` ordering: numbers before strings before arrays
+fn compare_value_typed(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Undefined => 0,
+            Value::Number(_) | Value::Integer(_) => 1,
+            Value::Strnum(_) if value.is_numeric_operand() => 1,
+            Value::String(_) | Value::Strnum(_) => 2,
+            Value::Array(_) => 3,
+        }
+    }
+
+    rank(a).cmp(&rank(b)).then_with(|| match rank(a) {
+        1 => a.compare_numeric(b),
+        2 => a.to_string().cmp(&b.to_string()),
+        _ => Ordering::Equal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array_of(pairs: &[(&str, Value)]) -> AwkArray {
+        let mut array = AwkArray::new();
+        for (key, value) in pairs {
+            array.insert(key, value.clone());
+        }
+        array
+    }
+
+    #[test]
+    fn test_insertion_order_survives_removal() {
+        let mut array = array_of(&[
+            ("b", Value::Number(1.0)),
+            ("a", Value::Number(2.0)),
+            ("c", Value::Number(3.0)),
+        ]);
+        assert_eq!(array.keys(), vec!["b", "a", "c"]);
+
+        array.remove("a");
+        array.insert("a", Value::Number(4.0));
+        assert_eq!(array.keys(), vec!["b", "c", "a"]);
+        assert_eq!(array.get("a"), Some(&Value::Number(4.0)));
+        assert_eq!(array.len(), 3);
+    }
+
+    #[test]
+    fn test_compaction_keeps_index_consistent() {
+        let mut array = AwkArray::new();
+        for i in 0..100 {
+            array.insert(&i.to_string(), Value::from(i));
+        }
+        for i in 0..95 {
+            array.remove(&i.to_string());
+        }
+        assert_eq!(array.keys(), vec!["95", "96", "97", "98", "99"]);
+        assert_eq!(array.get("97"), Some(&Value::from(97)));
+        assert!(array.slots.capacity() < 50);
+
+        array.clear();
+        assert!(array.is_empty() && !array.contains_key("99"));
+        assert_eq!(array.slots.capacity(), 0);
+    }
+
+    #[test]
+    fn test_arena_reuses_slots_without_reviving_handles() {
+        let mut arena = ArrayArena::new();
+        let first = arena.alloc(array_of(&[("k", Value::Number(1.0))]));
+        let second = arena.alloc(AwkArray::new());
+        arena.get_mut(second).unwrap().insert("x", Value::from("y"));
+        assert_eq!(arena.get(first).unwrap().get("k"), Some(&Value::Number(1.0)));
+        assert_eq!(arena.len(), 2);
+
+        assert!(arena.free(first).is_some());
+        assert!(arena.free(first).is_none());
+        let reused = arena.alloc(AwkArray::new());
+        assert_ne!(reused, first);
+        assert!(arena.get(first).is_none() && arena.get(reused).unwrap().is_empty());
+        assert_eq!(arena.get(second).unwrap().len(), 1);
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn test_index_orders() {
+        let array = array_of(&[
+            ("10", Value::Undefined),
+            ("9", Value::Undefined),
+            ("x", Value::Undefined),
+        ]);
+
+        assert_eq!(SortedIn::IndStrAsc.sorted_keys(&array), vec!["10", "9", "x"]);
+        assert_eq!(SortedIn::IndNumAsc.sorted_keys(&array), vec!["x", "9", "10"]);
+        assert_eq!(SortedIn::IndNumDesc.sorted_keys(&array), vec!["10", "9", "x"]);
+    }
+
+    #[test]
+    fn test_value_orders() {
+        let array = array_of(&[
+            ("a", Value::Number(3.0)),
+            ("b", Value::String("10".to_string())),
+            ("c", Value::Number(20.0)),
+        ]);
+
+        assert_eq!(SortedIn::ValNumDesc.sorted_keys(&array), vec!["c", "b", "a"]);
+        assert_eq!(SortedIn::ValStrAsc.sorted_keys(&array), vec!["b", "c", "a"]);
+        assert_eq!(SortedIn::ValTypeAsc.sorted_keys(&array), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_parse_sorted_in() {
+        assert_eq!(SortedIn::parse("@val_num_desc"), Some(SortedIn::ValNumDesc));
+        assert_eq!(SortedIn::parse("@bogus"), None);
+    }
+}
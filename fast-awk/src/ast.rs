@@ -63,22 +63,56 @@ pub enum Statement {
 #[derive(Debug, Clone, PartialEq)]
 pub struct PrintStatement {
     pub expressions: Vec<Expression>,
-    pub output_target: Option<OutputTarget>,
+    /// Where the output goes, standard output if none; each target gets
+    /// all of it, as with `print $0 > "all" >> "log"`
+    pub output_targets: Vec<OutputTarget>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PrintfStatement {
     pub format: Expression,
     pub arguments: Vec<Expression>,
-    pub output_target: Option<OutputTarget>,
+    pub output_targets: Vec<OutputTarget>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum OutputTarget {
+    /// `> file`: emptied when first opened, then written on
     File(Expression),
+    /// `>> file`
+    Append(Expression),
+    /// `| cmd`
     Pipe(Expression),
 }
 
+impl OutputTarget {
+    /// The file name or command
+    pub fn expression(&self) -> &Expression {
+        match self {
+            OutputTarget::File(expression) | OutputTarget::Append(expression) | OutputTarget::Pipe(expression) => {
+                expression
+            }
+        }
+    }
+}
+
+/// What `getline` reads from besides the main input
+#[derive(Debug, Clone, PartialEq)]
+pub enum GetlineSource {
+    /// `getline < file`
+    File(Box<Expression>),
+    /// `cmd | getline`
+    Command(Box<Expression>),
+}
+
+impl GetlineSource {
+    pub fn expression(&self) -> &Expression {
+        match self {
+            GetlineSource::File(expression) | GetlineSource::Command(expression) => expression,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Literal(Value),
@@ -149,7 +183,7 @@ pub enum Expression {
     // Getline expression
     Getline {
         target: Option<Box<Expression>>,
-        source: Option<Box<Expression>>,
+        source: Option<GetlineSource>,
     },
 
     // Regular expression literal
@@ -226,29 +260,35 @@ impl PrintStatement {
     pub fn new() -> Self {
         Self {
             expressions: Vec::new(),
-            output_target: None,
+            output_targets: Vec::new(),
         }
     }
 
     pub fn with_expressions(expressions: Vec<Expression>) -> Self {
         Self {
             expressions,
-            output_target: None,
+            output_targets: Vec::new(),
         }
     }
 
     pub fn with_target(mut self, target: OutputTarget) -> Self {
-        self.output_target = Some(target);
+        self.output_targets.push(target);
         self
     }
 }
 
+impl Default for PrintStatement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PrintfStatement {
     pub fn new(format: Expression) -> Self {
         Self {
             format,
             arguments: Vec::new(),
-            output_target: None,
+            output_targets: Vec::new(),
         }
     }
 
@@ -258,7 +298,7 @@ impl PrintfStatement {
     }
 
     pub fn with_target(mut self, target: OutputTarget) -> Self {
-        self.output_target = Some(target);
+        self.output_targets.push(target);
         self
     }
 }
@@ -266,12 +306,10 @@ impl PrintfStatement {
 impl Expression {
     /// Check if this expression is an lvalue (can be assigned to)
     pub fn is_lvalue(&self) -> bool {
-        match self {
-            Expression::Identifier(_) => true,
-            Expression::FieldRef(_) => true,
-            Expression::ArrayRef { .. } => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            Expression::Identifier(_) | Expression::FieldRef(_) | Expression::ArrayRef { .. }
+        )
     }
 
     /// Get the precedence of this expression for parsing
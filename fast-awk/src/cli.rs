@@ -15,6 +15,23 @@ pub enum OutputFormat {
     Csv,
 }
 
+/// How `--input-format` reads each record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Text,
+    /// A JSON document, usually a line of JSON Lines, for `jget` to read
+    Json,
+}
+
+/// What `--lint` does with the problems it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LintMode {
+    /// Print them as warnings and run the program anyway
+    Warn,
+    /// Print them and exit without running the program
+    Fatal,
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "fawk",
@@ -102,6 +119,36 @@ pub struct Args {
     /// Enable traditional AWK mode (disable extensions)
     #[arg(long = "traditional")]
     pub traditional_mode: bool,
+
+    /// Use arbitrary-precision integers for integer arithmetic (like gawk -M)
+    #[arg(short = 'M', long = "bignum")]
+    pub bignum: bool,
+
+    /// Treat input as raw bytes: records may contain invalid UTF-8, which is
+    /// only replaced in string operations and printed back unchanged
+    #[arg(short = 'b', long = "binary")]
+    pub binary: bool,
+
+    /// Read input files through a buffer instead of memory-mapping them
+    #[arg(long = "no-mmap")]
+    pub no_mmap: bool,
+
+    /// Warn about undefined functions, variables compared before being
+    /// assigned, non-portable constructs and unreachable code; `fatal`
+    /// refuses to run a program with warnings
+    #[arg(long = "lint", value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "warn")]
+    pub lint: Option<LintMode>,
+
+    /// Run untrusted scripts safely, as gawk --sandbox: system(), output
+    /// redirection and getline from files or commands are errors
+    #[arg(long = "sandbox")]
+    pub sandbox: bool,
+
+    /// Parse each record as JSON: `jget("a.b[0]")` reads a member and
+    /// `jout()` prints the record, an array or key, value pairs as JSON.
+    /// Fields are still split from the text
+    #[arg(long = "input-format", value_name = "FORMAT", default_value = "text", conflicts_with = "binary")]
+    pub input_format: InputFormat,
 }
 
 impl Args {
@@ -155,6 +202,14 @@ impl Args {
         Ok(assignments)
     }
 
+    /// What errors call the script: its file, or the command line
+    pub fn script_name(&self) -> String {
+        match self.script_file {
+            Some(ref script_file) => script_file.display().to_string(),
+            None => "command line".to_string(),
+        }
+    }
+
     pub fn get_script(&self) -> Result<String, Box<dyn std::error::Error>> {
         if let Some(ref script_file) = self.script_file {
             std::fs::read_to_string(script_file)
@@ -192,6 +247,12 @@ mod tests {
             skip_records: None,
             posix_mode: false,
             traditional_mode: false,
+            bignum: false,
+            binary: false,
+            no_mmap: false,
+            lint: None,
+            sandbox: false,
+            input_format: InputFormat::Text,
         };
 
         let assignments = args.parse_variable_assignments().unwrap();
@@ -223,6 +284,12 @@ mod tests {
             skip_records: None,
             posix_mode: false,
             traditional_mode: false,
+            bignum: false,
+            binary: false,
+            no_mmap: false,
+            lint: None,
+            sandbox: false,
+            input_format: InputFormat::Text,
         };
 
         assert_eq!(args.get_output_separator(), " ");
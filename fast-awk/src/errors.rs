@@ -33,9 +33,6 @@ pub enum FastAwkError {
     #[error("Invalid field reference: {field}")]
     InvalidFieldReference { field: String },
 
-    #[error("Division by zero in expression")]
-    DivisionByZero,
-
     #[error("Type error: cannot {operation} {left_type} and {right_type}")]
     TypeError {
         operation: String,
@@ -49,6 +46,9 @@ pub enum FastAwkError {
     #[error("Function '{name}' is not defined")]
     UndefinedFunction { name: String },
 
+    #[error("Function '{name}' is already defined")]
+    FunctionRedefined { name: String },
+
     #[error("Invalid function call: {function}({args}) - {reason}")]
     InvalidFunctionCall {
         function: String,
@@ -89,6 +89,22 @@ pub enum FastAwkError {
     #[error("Invalid format specifier: {format}")]
     InvalidFormatSpecifier { format: String },
 
+    #[error("@include \"{name}\": {message}")]
+    Include { name: String, message: String },
+
+    #[error("{operation} is not allowed in sandbox mode")]
+    Sandboxed { operation: String },
+
+    #[error("Assertion failed: {message}")]
+    AssertionFailed { message: String },
+
+    /// An error in a script file, or at a line of one
+    #[error("{location}: {error}")]
+    InSource {
+        location: String,
+        error: Box<FastAwkError>,
+    },
+
     #[error("General error: {0}")]
     General(String),
 }
@@ -138,6 +154,12 @@ impl FastAwkError {
         }
     }
 
+    pub fn function_redefined(name: impl Into<String>) -> Self {
+        Self::FunctionRedefined {
+            name: name.into(),
+        }
+    }
+
     pub fn invalid_function_call(
         function: impl Into<String>,
         args: impl Into<String>,
@@ -205,6 +227,32 @@ impl FastAwkError {
             format: format.into(),
         }
     }
+
+    pub fn include_error(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Include {
+            name: name.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn sandboxed(operation: impl Into<String>) -> Self {
+        Self::Sandboxed {
+            operation: operation.into(),
+        }
+    }
+
+    pub fn assertion_failed(message: impl Into<String>) -> Self {
+        Self::AssertionFailed {
+            message: message.into(),
+        }
+    }
+
+    pub fn in_source(location: impl Into<String>, error: FastAwkError) -> Self {
+        Self::InSource {
+            location: location.into(),
+            error: Box::new(error),
+        }
+    }
 }
 
 // Helper function to convert parsing errors with context
@@ -248,6 +296,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_variables)]
     fn test_parse_error_with_context() {
         let input = "line1\nline2\nerror here";
         let position = 12; // Points to "error"
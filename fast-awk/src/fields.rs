@@ -0,0 +1,309 @@
+use regex_automata::{meta, Anchored, Input, MatchKind};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// How records are split into fields. Assigning FS, FIELDWIDTHS or FPAT
+/// selects the matching mode, and the last assignment wins, as in gawk.
+#[derive(Debug, Clone, Default)]
+pub enum FieldSplitting {
+    /// Split on FS
+    #[default]
+    Separator,
+    /// Fixed-width columns from FIELDWIDTHS
+    Widths(Vec<FieldWidth>),
+    /// Fields are the matches of FPAT
+    Pattern(FieldPattern),
+}
+
+/// One FIELDWIDTHS entry: `skip:width`, where a width of `*` takes the rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldWidth {
+    pub skip: usize,
+    /// `None` for `*`
+    pub width: Option<usize>,
+}
+
+/// Parse a FIELDWIDTHS value such as "3 2:5 *"
+pub fn parse_field_widths(spec: &str) -> Result<Vec<FieldWidth>, String> {
+    let entries: Vec<&str> = spec.split_whitespace().collect();
+    let mut widths = Vec::with_capacity(entries.len());
+
+    for (position, entry) in entries.iter().enumerate() {
+        let (skip, width) = match entry.split_once(':') {
+            Some((skip, width)) => (
+                skip.parse().map_err(|_| format!("invalid skip count '{}'", skip))?,
+                width,
+            ),
+            None => (0, *entry),
+        };
+        let width = if width == "*" {
+            if position + 1 != entries.len() {
+                return Err("'*' is only allowed as the last width".to_string());
+            }
+            None
+        } else {
+            Some(width.parse().map_err(|_| format!("invalid width '{}'", width))?)
+        };
+        widths.push(FieldWidth { skip, width });
+    }
+    Ok(widths)
+}
+
+/// Cut `record` into fixed-width fields counted in characters; fields past
+/// the end of a short record are not created
+pub fn split_widths<'a>(record: &'a str, widths: &[FieldWidth]) -> Vec<&'a str> {
+    let boundaries: Vec<usize> = record
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(record.len()))
+        .collect();
+    let chars = boundaries.len() - 1;
+
+    split_by_widths(chars, widths)
+        .into_iter()
+        .map(|(start, end)| &record[boundaries[start]..boundaries[end]])
+        .collect()
+}
+
+/// Byte-counted `split_widths`, for binary mode
+pub fn split_widths_bytes<'a>(record: &'a [u8], widths: &[FieldWidth]) -> Vec<&'a [u8]> {
+    split_by_widths(record.len(), widths)
+        .into_iter()
+        .map(|(start, end)| &record[start..end])
+        .collect()
+}
+
+/// Field spans, in units, of a record `len` units long
+fn split_by_widths(len: usize, widths: &[FieldWidth]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::with_capacity(widths.len());
+    let mut position: usize = 0;
+    for field in widths {
+        position = position.saturating_add(field.skip);
+        if position >= len {
+            break;
+        }
+        let end = match field.width {
+            Some(width) => position.saturating_add(width).min(len),
+            None => len,
+        };
+        spans.push((position, end));
+        position = end;
+    }
+    spans
+}
+
+/// A compiled FPAT.
+///
+/// gawk takes the leftmost-longest match, so `([^,]+)|("[^"]+")` matches a
+/// whole quoted field even though the first alternative matches a prefix of
+/// it. The leftmost-first regex finds where the match starts and a second,
+/// all-matches regex anchored there finds its longest end.
+#[derive(Debug, Clone)]
+pub struct FieldPattern {
+    leftmost: meta::Regex,
+    longest: meta::Regex,
+}
+
+impl FieldPattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let leftmost = meta::Regex::new(pattern).map_err(|e| e.to_string())?;
+        let longest = meta::Regex::builder()
+            .configure(meta::Config::new().match_kind(MatchKind::All))
+            .build(pattern)
+            .map_err(|e| e.to_string())?;
+        Ok(Self { leftmost, longest })
+    }
+
+    /// The fields of `record`. An empty match counts as an empty field,
+    /// except right after a non-empty field, so `([^,]*)` splits "a,,b"
+    /// into "a", "" and "b".
+    pub fn fields<'a>(&self, record: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut fields = Vec::new();
+        let mut position = 0;
+        let mut previous_end = None;
+
+        while position <= record.len() {
+            let Some(found) = self.leftmost.find(Input::new(record).range(position..)) else {
+                break;
+            };
+            let end = self
+                .longest
+                .search(&Input::new(record).range(found.start()..).anchored(Anchored::Yes))
+                .map_or(found.end(), |longest| longest.end());
+
+            if end > found.start() {
+                fields.push(&record[found.start()..end]);
+                position = end;
+                previous_end = Some(end);
+                continue;
+            }
+
+            if previous_end != Some(found.start()) {
+                fields.push(&record[found.start()..found.start()]);
+            }
+            previous_end = None;
+            position = found.start() + char_len(&record[found.start()..]);
+        }
+        fields
+    }
+}
+
+/// The current record and its fields. The record is one owned buffer and
+/// each field a byte range into it, so splitting a record allocates nothing
+/// per field and reading a field borrows it. Text is only copied when a
+/// field is assigned, which rebuilds the record around the new value.
+#[derive(Debug, Clone, Default)]
+pub struct RecordFields {
+    record: Vec<u8>,
+    /// Where `$1` to `$NF` are in `record`
+    spans: Vec<Range<usize>>,
+}
+
+impl RecordFields {
+    /// Replace the record with `record`, split at `spans`, which returns to
+    /// `take_spans` with the next record so its allocation is reused
+    pub fn set(&mut self, record: &[u8], spans: Vec<Range<usize>>) {
+        debug_assert!(spans.iter().all(|span| span.end <= record.len()));
+        self.record.clear();
+        self.record.extend_from_slice(record);
+        self.spans = spans;
+    }
+
+    /// The span list, emptied, for splitting the next record
+    pub fn take_spans(&mut self) -> Vec<Range<usize>> {
+        let mut spans = std::mem::take(&mut self.spans);
+        spans.clear();
+        spans
+    }
+
+    /// NF
+    pub fn count(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Bytes of field `index`, `$0` for 0; empty past the last field
+    pub fn get(&self, index: usize) -> &[u8] {
+        match index {
+            0 => &self.record,
+            _ => self.spans.get(index - 1).map_or(&[], |span| &self.record[span.clone()]),
+        }
+    }
+
+    /// Text of field `index`, borrowed unless it is not valid UTF-8
+    pub fn text(&self, index: usize) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.get(index))
+    }
+
+    /// Text of `$1` to `$NF`
+    pub fn iter(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        (1..=self.count()).map(|index| self.text(index))
+    }
+
+    /// Set field `index` (not `$0`) to `value`, adding empty fields up to it,
+    /// and rebuild the record by joining the fields with `separator`
+    pub fn assign(&mut self, index: usize, value: &[u8], separator: &[u8]) {
+        debug_assert!(index > 0);
+        let count = self.count().max(index);
+        let mut record = Vec::with_capacity(self.record.len() + value.len() + separator.len() * count);
+        let mut spans = Vec::with_capacity(count);
+        for field in 1..=count {
+            if field > 1 {
+                record.extend_from_slice(separator);
+            }
+            let start = record.len();
+            record.extend_from_slice(if field == index { value } else { self.get(field) });
+            spans.push(start..record.len());
+        }
+        self.record = record;
+        self.spans = spans;
+    }
+}
+
+/// Where `field`, a slice of `record`, is in it
+pub fn span(record: &[u8], field: &[u8]) -> Range<usize> {
+    let start = field.as_ptr() as usize - record.as_ptr() as usize;
+    debug_assert!(start + field.len() <= record.len());
+    start..start + field.len()
+}
+
+/// Length of the UTF-8 character starting `bytes`, 1 for invalid bytes
+fn char_len(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(&b) if b >= 0xF0 => 4,
+        Some(&b) if b >= 0xE0 => 3,
+        Some(&b) if b >= 0xC0 => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern_fields(pattern: &str, record: &str) -> Vec<String> {
+        FieldPattern::new(pattern)
+            .unwrap()
+            .fields(record.as_bytes())
+            .into_iter()
+            .map(|field| String::from_utf8_lossy(field).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let record = b"ab  cd ef";
+        let mut fields = RecordFields::default();
+        let spans = record
+            .split(|&b| b == b' ')
+            .filter(|field| !field.is_empty())
+            .map(|field| span(record, field))
+            .collect();
+        fields.set(record, spans);
+        assert_eq!(fields.count(), 3);
+        assert!(matches!(fields.text(2), Cow::Borrowed("cd")));
+        assert_eq!(fields.get(4), b"");
+
+        fields.assign(2, b"XY", b"-");
+        assert_eq!(fields.get(0), b"ab-XY-ef");
+        assert_eq!(fields.iter().collect::<Vec<_>>(), ["ab", "XY", "ef"]);
+        fields.assign(5, b"z", b",");
+        assert_eq!(fields.get(0), b"ab,XY,ef,,z");
+        assert_eq!(fields.count(), 5);
+
+        let spans = fields.take_spans();
+        fields.set("caf\u{e9}".as_bytes(), spans);
+        assert_eq!(fields.count(), 0);
+        assert_eq!(fields.text(0), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_field_widths() {
+        let widths = parse_field_widths("3 2:4 *").unwrap();
+        assert_eq!(widths[1], FieldWidth { skip: 2, width: Some(4) });
+        assert_eq!(split_widths("abc--defgREST of it", &widths), vec!["abc", "defg", "REST of it"]);
+        assert_eq!(split_widths("ünï--x", &widths), vec!["ünï", "x"]);
+        assert_eq!(split_widths("ab", &widths), vec!["ab"]);
+        assert_eq!(split_widths_bytes(b"abcdefg", &parse_field_widths("2 2").unwrap()), vec![b"ab", b"cd"]);
+
+        assert!(parse_field_widths("3 * 2").is_err());
+        assert!(parse_field_widths("3 x").is_err());
+    }
+
+    #[test]
+    fn test_fpat_quoted_csv() {
+        let csv = r#"([^,]+)|("[^"]+")"#;
+        assert_eq!(
+            pattern_fields(csv, r#"Robbins,Arnold,"1234 A Pretty Street, NE",MyTown"#),
+            vec!["Robbins", "Arnold", "\"1234 A Pretty Street, NE\"", "MyTown"]
+        );
+        assert_eq!(pattern_fields(csv, "a,,b"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_fpat_empty_fields() {
+        let csv = r#"([^,]*)|("[^"]+")"#;
+        assert_eq!(pattern_fields(csv, "a,,b"), vec!["a", "", "b"]);
+        assert_eq!(pattern_fields(csv, ",a,"), vec!["", "a", ""]);
+        assert_eq!(pattern_fields("[0-9]+", "x12y345"), vec!["12", "345"]);
+    }
+}
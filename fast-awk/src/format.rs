@@ -0,0 +1,315 @@
+//! printf-style number formatting, shared by `printf`/`sprintf` and the
+//! CONVFMT/OFMT number to string conversions.
+
+/// Default CONVFMT and OFMT
+pub const DEFAULT_NUMBER_FORMAT: &str = "%.6g";
+
+/// Widest field width and largest precision accepted, so a stray digit
+/// run cannot ask for gigabytes of padding
+pub const MAX_FIELD_WIDTH: usize = 1 << 20;
+
+/// One parsed `%[flags][width][.precision]conversion` specifier
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub left_align: bool,
+    pub zero_pad: bool,
+    pub plus_sign: bool,
+    pub space_sign: bool,
+    pub alternate: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    /// `*` width or precision, supplied by the next argument
+    pub width_from_argument: bool,
+    pub precision_from_argument: bool,
+    pub conversion: char,
+}
+
+impl FormatSpec {
+    /// Parse a specifier such as `%-8.3f`; length modifiers (`l`, `h`, ...)
+    /// are accepted and ignored. `None` unless the whole text is one
+    /// specifier with a width and precision up to [`MAX_FIELD_WIDTH`].
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut chars = spec.strip_prefix('%')?.chars().peekable();
+        let mut parsed = Self::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => parsed.left_align = true,
+                '0' => parsed.zero_pad = true,
+                '+' => parsed.plus_sign = true,
+                ' ' => parsed.space_sign = true,
+                '#' => parsed.alternate = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        if chars.next_if_eq(&'*').is_some() {
+            parsed.width_from_argument = true;
+        } else {
+            parsed.width = take_number(&mut chars).ok()?;
+        }
+        if chars.next_if_eq(&'.').is_some() {
+            if chars.next_if_eq(&'*').is_some() {
+                parsed.precision_from_argument = true;
+            } else {
+                parsed.precision = Some(take_number(&mut chars).ok()?.unwrap_or(0));
+            }
+        }
+        while chars.next_if(|c| "hlLqjzt".contains(*c)).is_some() {}
+        parsed.conversion = chars.next()?;
+        chars.next().is_none().then_some(parsed)
+    }
+
+    /// Set a `*` width from its argument; as in C a negative one left
+    /// aligns. `false` if it is over [`MAX_FIELD_WIDTH`]
+    pub fn set_argument_width(&mut self, width: f64) -> bool {
+        if width < 0.0 {
+            self.left_align = true;
+        }
+        self.width = Some(width.abs().trunc() as usize);
+        width.abs() <= MAX_FIELD_WIDTH as f64
+    }
+
+    /// Set a `*` precision from its argument; a negative one counts as
+    /// none. `false` if it is over [`MAX_FIELD_WIDTH`]
+    pub fn set_argument_precision(&mut self, precision: f64) -> bool {
+        self.precision = (precision >= 0.0).then_some(precision.trunc() as usize);
+        precision <= MAX_FIELD_WIDTH as f64
+    }
+
+    /// `body` padded to the field width; `0` only pads numeric conversions
+    /// of finite values
+    pub fn pad(&self, body: String) -> String {
+        let len = body.chars().count();
+        let Some(width) = self.width.filter(|&width| width > len) else {
+            return body;
+        };
+        let fill = width - len;
+        let numeric = "dioxXeEfFgG".contains(self.conversion);
+        if self.left_align {
+            format!("{}{}", body, " ".repeat(fill))
+        } else if self.zero_pad && numeric && !is_special(&body) {
+            // Zeros go after the sign and any 0x prefix
+            let mut prefix = usize::from(body.starts_with(['+', '-', ' ']));
+            if body[prefix..].starts_with("0x") || body[prefix..].starts_with("0X") {
+                prefix += 2;
+            }
+            format!("{}{}{}", &body[..prefix], "0".repeat(fill), &body[prefix..])
+        } else {
+            format!("{}{}", " ".repeat(fill), body)
+        }
+    }
+
+    /// The sign a non-negative number gets under `+` or ` `
+    fn positive_sign(&self) -> &'static str {
+        if self.plus_sign {
+            "+"
+        } else if self.space_sign {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// `n` under an `e`, `f` or `g` conversion (upper case too), without
+    /// padding
+    pub fn format_float(&self, n: f64) -> String {
+        if !n.is_finite() {
+            let special = special_value(n);
+            return if self.conversion.is_ascii_uppercase() { special.to_uppercase() } else { special };
+        }
+        let precision = self.precision.unwrap_or(6);
+        let upper = self.conversion.is_ascii_uppercase();
+        let mut body = match self.conversion.to_ascii_lowercase() {
+            'e' => exponential(n.abs(), precision, upper, self.alternate),
+            'g' => shortest(n.abs(), precision, upper, self.alternate),
+            _ => {
+                let fixed = format!("{:.*}", precision, n.abs());
+                if self.alternate && precision == 0 { fixed + "." } else { fixed }
+            }
+        };
+        body.insert_str(0, if n.is_sign_negative() { "-" } else { self.positive_sign() });
+        body
+    }
+
+    /// `n` truncated under a `d` or `i` conversion, without padding
+    pub fn format_integer(&self, n: f64) -> String {
+        if !n.is_finite() {
+            return special_value(n);
+        }
+        let digits = format!("{:.0}", n.trunc().abs());
+        let digits = match self.precision {
+            Some(precision) if digits.len() < precision => format!("{}{}", "0".repeat(precision - digits.len()), digits),
+            Some(0) if n.trunc() == 0.0 => String::new(),
+            _ => digits,
+        };
+        let sign = if n.trunc() < 0.0 { "-" } else { self.positive_sign() };
+        format!("{}{}", sign, digits)
+    }
+}
+
+/// A number as CONVFMT or OFMT turn it into a string: integral values
+/// always as integers, others through `format`, which may carry literal
+/// text around its one specifier. A format with no usable float or
+/// integer specifier falls back to the default.
+pub fn format_number(n: f64, format: &str) -> String {
+    if n.fract() == 0.0 && n >= i64::MIN as f64 && n <= i64::MAX as f64 {
+        return (n as i64).to_string();
+    }
+    if !n.is_finite() {
+        return special_value(n);
+    }
+    if let Some((prefix, spec, suffix)) = split_specifier(format) {
+        if let Some(spec) = FormatSpec::parse(spec) {
+            let body = match spec.conversion {
+                'e' | 'E' | 'f' | 'F' | 'g' | 'G' => Some(spec.format_float(n)),
+                'd' | 'i' => Some(spec.format_integer(n)),
+                _ => None,
+            };
+            if let Some(body) = body {
+                return format!("{}{}{}", prefix, spec.pad(body), suffix);
+            }
+        }
+    }
+    let default = FormatSpec {
+        precision: Some(6),
+        conversion: 'g',
+        ..FormatSpec::default()
+    };
+    default.format_float(n)
+}
+
+/// Infinities and NaNs the way gawk prints them, always signed
+fn special_value(n: f64) -> String {
+    let sign = if n.is_sign_negative() { '-' } else { '+' };
+    let name = if n.is_nan() { "nan" } else { "inf" };
+    format!("{}{}", sign, name)
+}
+
+fn is_special(body: &str) -> bool {
+    let lower = body.to_ascii_lowercase();
+    lower.ends_with("inf") || lower.ends_with("nan")
+}
+
+/// The text before, the first specifier of, and the text after `format`
+fn split_specifier(format: &str) -> Option<(&str, &str, &str)> {
+    let start = format.find('%')?;
+    let end = format[start + 1..]
+        .find(|c: char| c.is_ascii_alphabetic() && !"hlLqjzt".contains(c))
+        .map(|offset| start + 1 + offset + 1)?;
+    Some((&format[..start], &format[start..end], &format[end..]))
+}
+
+/// The digits at the front of `chars`, if any; `Err` once they pass
+/// [`MAX_FIELD_WIDTH`]
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<Option<usize>, ()> {
+    let mut number = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        let value = number.unwrap_or(0) * 10 + digit as usize;
+        if value > MAX_FIELD_WIDTH {
+            return Err(());
+        }
+        number = Some(value);
+        chars.next();
+    }
+    Ok(number)
+}
+
+/// `%e` of a non-negative finite number, with C's signed two-digit exponent
+fn exponential(n: f64, precision: usize, upper: bool, alternate: bool) -> String {
+    let rust = format!("{:.*e}", precision, n);
+    let (mantissa, exponent) = rust.split_once('e').expect("{:e} always has an exponent");
+    let exponent: i32 = exponent.parse().expect("{:e} exponents are integers");
+    let dot = if alternate && precision == 0 { "." } else { "" };
+    format!(
+        "{}{}{}{}{:02}",
+        mantissa,
+        dot,
+        if upper { 'E' } else { 'e' },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// `%g` of a non-negative finite number: `%e` or `%f` depending on the
+/// exponent, without trailing zeros unless `#` was given
+fn shortest(n: f64, precision: usize, upper: bool, alternate: bool) -> String {
+    let significant = precision.max(1);
+    let exponent = if n == 0.0 {
+        0
+    } else {
+        let rounded = format!("{:.*e}", significant - 1, n);
+        rounded.split_once('e').and_then(|(_, e)| e.parse::<i32>().ok()).unwrap_or(0)
+    };
+    let body = if exponent < -4 || exponent >= significant as i32 {
+        exponential(n, significant - 1, upper, alternate)
+    } else {
+        let decimals = (significant as i32 - 1 - exponent) as usize;
+        let fixed = format!("{:.*}", decimals, n);
+        if alternate && decimals == 0 { fixed + "." } else { fixed }
+    };
+    if alternate {
+        return body;
+    }
+    // Trailing zeros go from the fraction, before any exponent
+    let (number, exponent) = match body.find(['e', 'E']) {
+        Some(at) => body.split_at(at),
+        None => (body.as_str(), ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{}{}", number, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(spec: &str, n: f64) -> String {
+        let spec = FormatSpec::parse(spec).unwrap();
+        spec.pad(spec.format_float(n))
+    }
+
+    #[test]
+    fn test_float_conversions() {
+        assert_eq!(float("%g", 0.1 + 0.2), "0.3");
+        assert_eq!(float("%g", 1234567.0), "1.23457e+06");
+        assert_eq!(float("%g", 0.0001), "0.0001");
+        assert_eq!(float("%g", 0.00001), "1e-05");
+        assert_eq!(float("%.3G", 0.000012345), "1.23E-05");
+        assert_eq!(float("%#g", 2.5), "2.50000");
+        assert_eq!(float("%e", 1500.0), "1.500000e+03");
+        assert_eq!(float("%.2f", 2.675), "2.67");
+        assert_eq!(float("%8.2f", -1.23456), "   -1.23");
+        assert_eq!(float("%-8.2f", 1.23456), "1.23    ");
+        assert_eq!(float("%08.2f", -1.23456), "-0001.23");
+        assert_eq!(float("%+.1f", 2.0), "+2.0");
+        assert_eq!(float("%f", f64::INFINITY), "+inf");
+        assert_eq!(float("%5f", f64::NEG_INFINITY), " -inf");
+        assert!(FormatSpec::parse("%5").is_none());
+        assert!(FormatSpec::parse("%d and more").is_none());
+        assert!(FormatSpec::parse("%99999999999999999999d").is_none());
+        assert!(FormatSpec::parse("%.99999999999999999999f").is_none());
+
+        let mut star = FormatSpec::parse("%*.*f").unwrap();
+        assert!(star.width_from_argument && star.precision_from_argument);
+        assert!(star.set_argument_width(-6.0) && star.set_argument_precision(1.0));
+        assert_eq!(star.pad(star.format_float(2.25)), "2.2   ");
+        assert!(!star.set_argument_width(1e12));
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(3.0, "%.2f"), "3");
+        assert_eq!(format_number(0.1 + 0.2, DEFAULT_NUMBER_FORMAT), "0.3");
+        assert_eq!(format_number(1.23456, "%.2f"), "1.23");
+        assert_eq!(format_number(1.23456, "[%.1e]"), "[1.2e+00]");
+        assert_eq!(format_number(2.7, "%d"), "2");
+        assert_eq!(format_number(1.23456, "%s"), "1.23456");
+        assert_eq!(format_number(1e30, DEFAULT_NUMBER_FORMAT), "1e+30");
+        assert_eq!(format_number(f64::NAN, DEFAULT_NUMBER_FORMAT), "+nan");
+        assert_eq!(format_number(-f64::INFINITY, DEFAULT_NUMBER_FORMAT), "-inf");
+    }
+}
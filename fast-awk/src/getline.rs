@@ -0,0 +1,167 @@
+//! The files and commands `getline` reads besides the main input.
+//!
+//! `getline < file` and `cmd | getline` open their source the first time
+//! it is named and go on reading it on later calls, so `while ((cmd |
+//! getline line) > 0)` walks a command's output. Sources are keyed by name
+//! as in awk: the same string names the same stream until `close(name)`,
+//! which for a command waits for it and returns its exit status.
+//!
+//! Where fawk differs from POSIX awk and gawk:
+//! - Plain `getline` and `getline var`, which read the main input, are not
+//!   supported and stop the script with an error.
+//! - A file and a command with the same name are the same stream.
+//! - `close(cmd)` returns the exit status like gawk, or 256 plus the
+//!   signal that killed the command; POSIX leaves the value unspecified.
+
+use crate::record::{RecordReader, RecordSource};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+/// Where a getline source comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    File,
+    Command,
+}
+
+/// A record read by getline, with the separator that ended it for `RT`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetlineRecord {
+    pub record: Vec<u8>,
+    pub terminator: Vec<u8>,
+}
+
+struct Source {
+    records: RecordReader<BufReader<Box<dyn Read>>>,
+    /// The command writing the records, for `close` to wait for
+    child: Option<Child>,
+}
+
+/// The open getline sources, by name
+#[derive(Default)]
+pub struct InputStreams {
+    open: HashMap<String, Source>,
+}
+
+impl InputStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The next record of the file or command `name` under `rs`, opening
+    /// it first if need be; `None` once it is used up, until it is closed
+    /// and so read again from the start
+    pub fn read(&mut self, kind: SourceKind, name: &str, rs: &str) -> io::Result<Option<GetlineRecord>> {
+        if !self.open.contains_key(name) {
+            let source = open(kind, name)?;
+            self.open.insert(name.to_string(), source);
+        }
+        let source = self.open.get_mut(name).expect("opened above");
+        Ok(source.records.next_terminated(rs)?.map(|(record, terminator)| GetlineRecord {
+            record: record.to_vec(),
+            terminator: terminator.to_vec(),
+        }))
+    }
+
+    /// `close(name)`: the command's exit status, 0 for a file, or `None`
+    /// when nothing by that name is open
+    pub fn close(&mut self, name: &str) -> Option<io::Result<i32>> {
+        let source = self.open.remove(name)?;
+        let Source { records, child } = source;
+        // The command sees its output closed before it is waited for
+        drop(records);
+        Some(match child {
+            Some(mut child) => child.wait().map(exit_code),
+            None => Ok(0),
+        })
+    }
+
+    /// Close everything still open, waiting for the commands
+    pub fn close_all(&mut self) {
+        let names: Vec<String> = self.open.keys().cloned().collect();
+        for name in names {
+            let _ = self.close(&name);
+        }
+    }
+}
+
+impl Drop for InputStreams {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
+
+fn open(kind: SourceKind, name: &str) -> io::Result<Source> {
+    let (input, child): (Box<dyn Read>, Option<Child>) = match kind {
+        SourceKind::File if name == "-" || name == "/dev/stdin" => (Box::new(io::stdin()), None),
+        SourceKind::File => (Box::new(File::open(name)?), None),
+        SourceKind::Command => {
+            // What the script printed comes before what the command prints
+            io::stdout().flush()?;
+            let mut child = Command::new("sh").arg("-c").arg(name).stdout(Stdio::piped()).spawn()?;
+            let stdout = child.stdout.take().expect("stdout is piped");
+            (Box::new(stdout), Some(child))
+        }
+    };
+    Ok(Source {
+        records: RecordReader::new(BufReader::new(input)),
+        child,
+    })
+}
+
+/// A finished command's status as awk reports it: its exit code, or as in
+/// gawk 256 plus the signal that killed it
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal: Option<i32> = None;
+    status.code().or(signal.map(|signal| 256 + signal)).unwrap_or(-1)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn text(record: Option<GetlineRecord>) -> Option<(String, String)> {
+        record.map(|record| {
+            (
+                String::from_utf8(record.record).unwrap(),
+                String::from_utf8(record.terminator).unwrap(),
+            )
+        })
+    }
+
+    #[test]
+    fn test_command_records_and_status() {
+        let mut streams = InputStreams::new();
+        let command = "printf 'a\\nb'; exit 3";
+        let read = |streams: &mut InputStreams| text(streams.read(SourceKind::Command, command, "\n").unwrap());
+        assert_eq!(read(&mut streams), Some(("a".to_string(), "\n".to_string())));
+        assert_eq!(read(&mut streams), Some(("b".to_string(), String::new())));
+        assert_eq!(read(&mut streams), None);
+        assert_eq!(read(&mut streams), None);
+        assert_eq!(streams.close(command).unwrap().unwrap(), 3);
+        assert!(streams.close(command).is_none());
+
+        // Closed, it runs again from the start
+        assert_eq!(read(&mut streams), Some(("a".to_string(), "\n".to_string())));
+        streams.close_all();
+        assert!(streams.open.is_empty());
+    }
+
+    #[test]
+    fn test_files() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"x;y;").unwrap();
+        let name = file.path().display().to_string();
+
+        let mut streams = InputStreams::new();
+        let first = streams.read(SourceKind::File, &name, ";").unwrap();
+        assert_eq!(text(first), Some(("x".to_string(), ";".to_string())));
+        assert_eq!(streams.close(&name).unwrap().unwrap(), 0);
+        assert!(streams.read(SourceKind::File, "/nonexistent/input", "\n").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::ast::Program;
+use crate::errors::{FastAwkError, Result};
+use crate::parser::Parser;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Where `@include` looks when `AWKPATH` is not set, as in gawk
+pub const DEFAULT_AWKPATH: &str = ".:/usr/local/share/awk";
+
+/// A piece of program text and the file it came from. Lines taken out of
+/// the file for an `@include` are left blank, so the lexer's line numbers
+/// are still the file's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceUnit {
+    pub name: String,
+    pub text: String,
+}
+
+/// The preprocessing stage behind gawk's `@include "lib"`, run before the
+/// lexer: each directive is replaced, in place, by the program units of the
+/// file it names.
+///
+/// A name with a `/` is used as given; any other is looked up in each
+/// directory of the search path, as `lib` and then `lib.awk`. A file is
+/// included once, however many scripts ask for it, and a file that ends up
+/// including itself is an error rather than a loop.
+#[derive(Debug)]
+pub struct Includer {
+    search_path: Vec<PathBuf>,
+    /// Files being expanded, outermost first
+    stack: Vec<PathBuf>,
+    included: HashSet<PathBuf>,
+}
+
+impl Includer {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self {
+            search_path,
+            stack: Vec::new(),
+            included: HashSet::new(),
+        }
+    }
+
+    /// Search `AWKPATH`, colon-separated, or [`DEFAULT_AWKPATH`]; an empty
+    /// entry means the current directory
+    pub fn from_env() -> Self {
+        let awkpath = std::env::var("AWKPATH").unwrap_or_else(|_| DEFAULT_AWKPATH.to_string());
+        let search_path = awkpath
+            .split(':')
+            .map(|dir| PathBuf::from(if dir.is_empty() { "." } else { dir }))
+            .collect();
+        Self::new(search_path)
+    }
+
+    /// The units of the script `text`, named `name` in errors, with its
+    /// includes expanded; `path` is the file it was read from, if any
+    pub fn expand(&mut self, name: &str, text: &str, path: Option<&Path>) -> Result<Vec<SourceUnit>> {
+        let mut units = Vec::new();
+        let canonical = path.map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        if let Some(ref canonical) = canonical {
+            self.included.insert(canonical.clone());
+            self.stack.push(canonical.clone());
+        }
+        let expanded = self.expand_into(name, text, &mut units);
+        if canonical.is_some() {
+            self.stack.pop();
+        }
+        expanded.map(|()| units)
+    }
+
+    fn expand_into(&mut self, name: &str, text: &str, units: &mut Vec<SourceUnit>) -> Result<()> {
+        let mut pending = String::new();
+        // Newlines standing in for the lines before `pending` starts
+        let mut skipped = String::new();
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            let Some(target) = include_directive(line) else {
+                pending.push_str(line);
+                continue;
+            };
+            let target = target.map_err(|message| in_source(name, index + 1, message))?;
+            if !pending.trim().is_empty() {
+                units.push(SourceUnit {
+                    name: name.to_string(),
+                    text: std::mem::take(&mut skipped) + &pending,
+                });
+            }
+            skipped.extend(std::iter::repeat_n('\n', pending.matches('\n').count() + 1));
+            pending.clear();
+
+            let path = self
+                .resolve(target)
+                .ok_or_else(|| in_source(name, index + 1, FastAwkError::include_error(target, "file not found")))?;
+            let canonical = path.canonicalize()?;
+            if let Some(start) = self.stack.iter().position(|file| *file == canonical) {
+                let cycle: Vec<String> = self.stack[start..]
+                    .iter()
+                    .chain([&canonical])
+                    .map(|file| file.display().to_string())
+                    .collect();
+                let message = format!("include cycle: {}", cycle.join(" -> "));
+                return Err(in_source(name, index + 1, FastAwkError::include_error(target, message)));
+            }
+            if !self.included.insert(canonical.clone()) {
+                continue;
+            }
+
+            let included = std::fs::read_to_string(&path)
+                .map_err(|e| in_source(name, index + 1, FastAwkError::include_error(target, e.to_string())))?;
+            self.stack.push(canonical);
+            let expanded = self.expand_into(&path.display().to_string(), &included, units);
+            self.stack.pop();
+            expanded?;
+        }
+        if !pending.trim().is_empty() {
+            units.push(SourceUnit {
+                name: name.to_string(),
+                text: skipped + &pending,
+            });
+        }
+        Ok(())
+    }
+
+    /// The file `@include "name"` refers to
+    pub fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let candidates = |path: PathBuf| {
+            let with_extension = (!name.ends_with(".awk")).then(|| PathBuf::from(format!("{}.awk", path.display())));
+            std::iter::once(path).chain(with_extension)
+        };
+        if name.contains('/') {
+            return candidates(PathBuf::from(name)).find(|path| path.is_file());
+        }
+        self.search_path
+            .iter()
+            .flat_map(|dir| candidates(dir.join(name)))
+            .find(|path| path.is_file())
+    }
+}
+
+/// The file named by an `@include` line, `None` for any other line.
+///
+/// The directive has a line to itself, apart from a `;` or a comment.
+fn include_directive(line: &str) -> Option<std::result::Result<&str, FastAwkError>> {
+    let rest = line.trim_start().strip_prefix("@include")?;
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let malformed = || FastAwkError::include_error(rest.trim(), "expected a quoted file name");
+    let Some((target, after)) = rest.trim_start().strip_prefix('"').and_then(|quoted| quoted.split_once('"')) else {
+        return Some(Err(malformed()));
+    };
+    let after = after.trim();
+    let after = after.strip_prefix(';').unwrap_or(after).trim_start();
+    if !(after.is_empty() || after.starts_with('#')) || target.is_empty() {
+        return Some(Err(malformed()));
+    }
+    Some(Ok(target))
+}
+
+fn in_source(name: &str, line: usize, error: FastAwkError) -> FastAwkError {
+    FastAwkError::in_source(format!("{}:{}", name, line), error)
+}
+
+/// Parse each unit on its own, so an error names the file it is in, and
+/// join them into one program, rules in order. Included files hold whole
+/// rules and functions, as gawk requires.
+pub fn parse_units(units: &[SourceUnit]) -> Result<Program> {
+    let mut program = Program::new();
+    for unit in units {
+        let parsed = Parser::new(&unit.text)
+            .and_then(|mut parser| parser.parse())
+            .map_err(|e| FastAwkError::in_source(unit.name.clone(), e))?;
+        for (name, function) in parsed.functions {
+            if program.functions.contains_key(&name) {
+                return Err(FastAwkError::in_source(unit.name.clone(), FastAwkError::function_redefined(name)));
+            }
+            program.add_function(function);
+        }
+        for rule in parsed.rules {
+            program.add_rule(rule);
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_directive() {
+        assert_eq!(include_directive("@include \"lib\"\n").unwrap().unwrap(), "lib");
+        assert_eq!(include_directive("  @include \"a/b.awk\"; # helpers").unwrap().unwrap(), "a/b.awk");
+        assert!(include_directive("{ print \"@include\" }").is_none());
+        assert!(include_directive("@includes").is_none());
+        assert!(include_directive("@include lib").unwrap().is_err());
+        assert!(include_directive("@include \"lib\" { print }").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("strings.awk"), "function shout(s) { return toupper(s) }\n").unwrap();
+        std::fs::write(dir.path().join("both.awk"), "@include \"strings\"\nBEGIN { x = 1 }\n").unwrap();
+        std::fs::write(dir.path().join("loop"), "@include \"loop2\"\n").unwrap();
+        std::fs::write(dir.path().join("loop2"), "@include \"loop\"\n").unwrap();
+        std::fs::write(dir.path().join("broken.awk"), "\nBEGIN { x = }\n").unwrap();
+
+        let mut includer = Includer::new(vec![dir.path().to_path_buf()]);
+        let script = "BEGIN { a = 1 }\n@include \"both\"\n@include \"strings\"\nEND { b = shout(\"x\") }\n";
+        let units = includer.expand("command line", script, None).unwrap();
+        let names: Vec<&str> = units.iter().map(|unit| unit.name.rsplit('/').next().unwrap()).collect();
+        assert_eq!(names, ["command line", "strings.awk", "both.awk", "command line"]);
+        // Line numbers stay those of the including file
+        assert_eq!(units[3].text, "\n\n\nEND { b = shout(\"x\") }\n");
+        let program = parse_units(&units).unwrap();
+        assert_eq!(program.rules.len(), 3);
+        assert!(program.functions.contains_key("shout"));
+
+        let cycle = Includer::new(vec![dir.path().to_path_buf()]).expand("main", "@include \"loop\"", None);
+        assert!(cycle.unwrap_err().to_string().contains("include cycle"));
+        let missing = Includer::new(vec![dir.path().to_path_buf()]).expand("main", "\n@include \"nope\"", None);
+        assert_eq!(missing.unwrap_err().to_string(), "main:2: @include \"nope\": file not found");
+
+        let units = Includer::new(vec![dir.path().to_path_buf()]).expand("main", "@include \"broken\"", None).unwrap();
+        let error = parse_units(&units).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("{}: ", dir.path().join("broken.awk").display())), "{}", error);
+    }
+}
@@ -1,13 +1,39 @@
+use crate::array::{ArrayId, SortedIn};
 use crate::ast::*;
 use crate::errors::{FastAwkError, Result};
+use crate::getline::{InputStreams, SourceKind};
+use crate::lint::{self, LintWarning};
+use crate::redirect::{OutputStreams, SinkKind};
 use crate::runtime::{RuntimeContext, ControlFlow};
+use crate::sandbox::Capability;
 use crate::value::Value;
 use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// A builtin supplied by the program embedding the interpreter; it gets
+/// the runtime state and the evaluated arguments
+pub type ExtensionFunction = Box<dyn FnMut(&mut RuntimeContext, &[Value]) -> Result<Value>>;
+
+/// Functions every script can call without defining them
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "length", "substr", "index", "split", "gsub", "sub", "match", "sprintf", "toupper", "tolower", "sin",
+    "cos", "atan2", "exp", "log", "sqrt", "int", "rand", "srand", "system", "close", "jget",
+    "jout",
+];
+
+/// Builtins for testing scripts. A script's own function of the same name
+/// is called instead, as scripts written for other awks define `assert`.
+pub const TESTING_FUNCTIONS: &[&str] = &["assert", "assert_eq"];
 
 pub struct Interpreter {
     pub context: RuntimeContext,
     functions: HashMap<String, Function>,
+    extensions: HashMap<String, ExtensionFunction>,
     range_states: HashMap<usize, bool>, // Track range pattern states by rule index
+    /// Files and commands opened by getline
+    streams: InputStreams,
+    /// Files and commands print and printf write to
+    outputs: OutputStreams,
 }
 
 impl Interpreter {
@@ -15,12 +41,50 @@ impl Interpreter {
         Self {
             context: RuntimeContext::new(),
             functions: HashMap::new(),
+            extensions: HashMap::new(),
             range_states: HashMap::new(),
+            streams: InputStreams::new(),
+            outputs: OutputStreams::new(),
+        }
+    }
+
+    /// Make `function` callable from scripts as `name(...)`.
+    ///
+    /// `name` is an identifier, optionally qualified as `ns::name` to keep
+    /// extensions apart from each other and from script functions. An
+    /// unqualified name replaces the builtin of the same name. Registering a
+    /// name twice, or running a script that defines a function with a
+    /// registered name, is an error.
+    pub fn register_function<F>(&mut self, name: &str, function: F) -> Result<()>
+    where
+        F: FnMut(&mut RuntimeContext, &[Value]) -> Result<Value> + 'static,
+    {
+        if !is_function_name(name) {
+            return Err(FastAwkError::invalid_function_call(
+                name,
+                "",
+                "extension names are identifiers, optionally qualified as ns::name",
+            ));
         }
+        if self.extensions.contains_key(name) {
+            return Err(FastAwkError::function_redefined(name));
+        }
+        self.extensions.insert(name.to_string(), Box::new(function));
+        Ok(())
+    }
+
+    /// `--lint` warnings for `program`, counting registered extensions as
+    /// defined and `assigned` (`-v` names) as set
+    pub fn lint(&self, program: &Program, assigned: &[&str]) -> Vec<LintWarning> {
+        let extensions: Vec<&str> = self.extensions.keys().map(String::as_str).collect();
+        lint::lint(program, &extensions, assigned)
     }
 
     pub fn execute_program(&mut self, program: &Program) -> Result<()> {
         // Store user-defined functions
+        if let Some(name) = program.functions.keys().find(|name| self.extensions.contains_key(*name)) {
+            return Err(FastAwkError::function_redefined(name.clone()));
+        }
         self.functions = program.functions.clone();
 
         // Execute BEGIN rules
@@ -38,13 +102,25 @@ impl Interpreter {
     }
 
     pub fn execute_main_rules(&mut self, program: &Program, record: &str) -> Result<bool> {
-        if self.context.has_control_flow() {
-            if matches!(self.context.control_flow, ControlFlow::Exit(_)) {
-                return Ok(false);
-            }
+        if matches!(self.context.control_flow, ControlFlow::Exit(_)) {
+            return Ok(false);
         }
 
         self.context.set_current_record(record);
+        self.run_main_rules(program)
+    }
+
+    /// Like `execute_main_rules`, for a record read as raw bytes
+    pub fn execute_main_rules_bytes(&mut self, program: &Program, record: &[u8]) -> Result<bool> {
+        if matches!(self.context.control_flow, ControlFlow::Exit(_)) {
+            return Ok(false);
+        }
+
+        self.context.set_current_record_bytes(record);
+        self.run_main_rules(program)
+    }
+
+    fn run_main_rules(&mut self, program: &Program) -> Result<bool> {
         let mut any_matched = false;
 
         for (rule_index, rule) in program.get_main_rules().iter().enumerate() {
@@ -72,7 +148,14 @@ impl Interpreter {
         Ok(any_matched)
     }
 
+    /// Run the END rules. They also run after `exit` in BEGIN or a main
+    /// rule, which keeps its status unless END exits again; `exit` inside
+    /// END stops the remaining END rules.
     pub fn execute_end_rules(&mut self, program: &Program) -> Result<()> {
+        if matches!(self.context.control_flow, ControlFlow::Exit(_)) {
+            self.context.clear_control_flow();
+        }
+
         for rule in program.get_end_rules() {
             self.execute_action(&rule.action)?;
             if matches!(self.context.control_flow, ControlFlow::Exit(_)) {
@@ -228,8 +311,8 @@ impl Interpreter {
             }
             Statement::ForIn { variable, array, body } => {
                 let array_value = self.evaluate_expression(array)?;
-                if let Value::Array(_) = array_value {
-                    let keys = array_value.array_keys();
+                if let Value::Array(elements) = array_value {
+                    let keys = self.for_in_keys(elements)?;
                     for key in keys {
                         if self.context.has_control_flow() {
                             break;
@@ -263,10 +346,11 @@ impl Interpreter {
                 self.context.set_control_flow(ControlFlow::Next);
             }
             Statement::Exit(expr) => {
+                // A bare `exit` keeps the status of an earlier `exit N`
                 let exit_code = if let Some(expr) = expr {
                     self.evaluate_expression(expr)?.to_number() as i32
                 } else {
-                    0
+                    self.context.exit_code.unwrap_or(0)
                 };
                 self.context.set_exit_code(exit_code);
             }
@@ -276,30 +360,69 @@ impl Interpreter {
                 } else {
                     Value::Undefined
                 };
+                // Arrays only leave a function through its parameters
+                if return_value.is_array() {
+                    return Err(FastAwkError::runtime_error("attempt to return an array"));
+                }
                 self.context.set_control_flow(ControlFlow::Return(return_value));
             }
-            Statement::Delete(expr) => {
-                // Simplified delete implementation
-                match expr {
-                    Expression::Identifier(name) => {
-                        self.context.set_variable(name, Value::Undefined);
-                    }
-                    Expression::ArrayRef { array, index } => {
-                        let _array_value = self.evaluate_expression(array)?;
-                        let _index_value = self.evaluate_expression(index)?;
-                        // In a full implementation, this would remove the array element
+            Statement::Delete(expr) => match expr {
+                // `delete arr` leaves an empty array behind, still an array
+                Expression::Identifier(name) => {
+                    let array = self.context.array_variable(name);
+                    self.context.array_mut(array)?.clear();
+                }
+                Expression::ArrayRef { array, index } => {
+                    let index_value = self.evaluate_expression(index)?;
+                    let key = self.context.string_value(&index_value);
+                    match array.as_ref() {
+                        Expression::Identifier(name) => {
+                            if let Value::Array(array) = *self.context.variable_mut(name) {
+                                self.context.array_mut(array)?.remove(&key);
+                            }
+                        }
+                        _ => return Err(FastAwkError::runtime_error("Invalid delete target")),
                     }
-                    _ => return Err(FastAwkError::runtime_error("Invalid delete target")),
                 }
+                _ => return Err(FastAwkError::runtime_error("Invalid delete target")),
+            },
+            Statement::Print(PrintStatement { output_targets, .. })
+            | Statement::Printf(PrintfStatement { output_targets, .. })
+                if !output_targets.is_empty() && !self.context.policy.allows(Capability::RedirectOutput) =>
+            {
+                self.context.policy.check(Capability::RedirectOutput)?;
+            }
+            Statement::Print(print_stmt) if self.context.binary => {
+                // Field references print their original bytes
+                let mut items = Vec::new();
+                for expr in &print_stmt.expressions {
+                    let item = match expr {
+                        Expression::FieldRef(index) => {
+                            let index = self.evaluate_expression(index)?.to_number() as usize;
+                            self.context.raw_field(index).to_vec()
+                        }
+                        _ => {
+                            let value = self.evaluate_expression(expr)?;
+                            self.context.output_value(&value).into_bytes()
+                        }
+                    };
+                    items.push(item);
+                }
+                if print_stmt.expressions.is_empty() {
+                    items.push(self.context.raw_field(0).to_vec());
+                }
+
+                let output = self.context.print_output_bytes(&items);
+                self.write_output(&print_stmt.output_targets, &output)?;
             }
             Statement::Print(print_stmt) => {
                 let mut values = Vec::new();
                 for expr in &print_stmt.expressions {
                     values.push(self.evaluate_expression(expr)?);
                 }
-                
-                // Handle output redirection in a full implementation
-                self.context.print_values(&values)?;
+
+                let output = self.context.print_output(&values);
+                self.write_output(&print_stmt.output_targets, output.as_bytes())?;
             }
             Statement::Printf(printf_stmt) => {
                 let format = self.evaluate_expression(&printf_stmt.format)?;
@@ -307,8 +430,9 @@ impl Interpreter {
                 for expr in &printf_stmt.arguments {
                     args.push(self.evaluate_expression(expr)?);
                 }
-                
-                self.context.printf_format(&format, &args)?;
+
+                let output = self.context.printf_output(&format, &args)?;
+                self.write_output(&printf_stmt.output_targets, output.as_bytes())?;
             }
         }
         
@@ -317,106 +441,103 @@ impl Interpreter {
 
     fn evaluate_expression(&mut self, expression: &Expression) -> Result<Value> {
         match expression {
-            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Literal(value) => match value {
+                // Big integer literals are only exact in bignum mode
+                Value::Integer(_) => Ok(self.context.numeric(value.clone())),
+                _ => Ok(value.clone()),
+            },
             
             Expression::Identifier(name) => Ok(self.context.get_variable(name)),
             
             Expression::FieldRef(expr) => {
                 let index_value = self.evaluate_expression(expr)?;
                 let index = index_value.to_number() as usize;
-                Ok(Value::String(self.context.get_field(index)))
+                Ok(Value::Strnum(self.context.get_field(index).into_owned()))
             }
             
-            Expression::ArrayRef { array, index } => {
-                let mut array_value = self.evaluate_expression(array)?;
-                let index_value = self.evaluate_expression(index)?;
-                let index_str = index_value.to_string();
-                
-                let element = array_value.get_array_element(&index_str);
-                Ok(element.clone())
-            }
+            Expression::ArrayRef { array, index } => self.evaluate_array_ref(array, index),
             
             // Arithmetic operations
             Expression::Add(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.add(&right_val)
             }
             
             Expression::Subtract(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.subtract(&right_val)
             }
             
             Expression::Multiply(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.multiply(&right_val)
             }
             
             Expression::Divide(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.divide(&right_val)
             }
             
             Expression::Modulo(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.modulo(&right_val)
             }
             
             Expression::Power(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 left_val.power(&right_val)
             }
             
             Expression::UnaryMinus(expr) => {
-                let value = self.evaluate_expression(expr)?;
-                Ok(Value::Number(-value.to_number()))
+                let value = self.evaluate_numeric(expr)?;
+                Ok(value.negate())
             }
             
-            Expression::UnaryPlus(expr) => {
-                let value = self.evaluate_expression(expr)?;
-                Ok(Value::Number(value.to_number()))
-            }
+            Expression::UnaryPlus(expr) => match self.evaluate_numeric(expr)? {
+                integer @ Value::Integer(_) => Ok(integer),
+                value => Ok(Value::Number(value.to_number())),
+            },
             
             // Comparison operations
             Expression::Equal(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) == std::cmp::Ordering::Equal { 1.0 } else { 0.0 }))
             }
             
             Expression::NotEqual(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) != std::cmp::Ordering::Equal { 1.0 } else { 0.0 }))
             }
             
             Expression::Less(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) == std::cmp::Ordering::Less { 1.0 } else { 0.0 }))
             }
             
             Expression::LessEqual(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) != std::cmp::Ordering::Greater { 1.0 } else { 0.0 }))
             }
             
             Expression::Greater(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) == std::cmp::Ordering::Greater { 1.0 } else { 0.0 }))
             }
             
             Expression::GreaterEqual(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_comparison_operand(left)?;
+                let right_val = self.evaluate_comparison_operand(right)?;
                 Ok(Value::Number(if left_val.compare(&right_val) != std::cmp::Ordering::Less { 1.0 } else { 0.0 }))
             }
             
@@ -468,15 +589,16 @@ impl Interpreter {
             Expression::Concatenate(left, right) => {
                 let left_val = self.evaluate_expression(left)?;
                 let right_val = self.evaluate_expression(right)?;
-                Ok(left_val.concatenate(&right_val))
+                let left_str = self.context.string_value(&left_val);
+                Ok(Value::String(left_str + &self.context.string_value(&right_val)))
             }
             
             Expression::In(left, right) => {
                 let key_val = self.evaluate_expression(left)?;
                 let array_val = self.evaluate_expression(right)?;
-                let key_str = key_val.to_string();
+                let key_str = self.context.string_value(&key_val);
                 
-                Ok(Value::Number(if array_val.has_array_key(&key_str) { 1.0 } else { 0.0 }))
+                Ok(Value::Number(if self.context.array_contains(&array_val, &key_str) { 1.0 } else { 0.0 }))
             }
             
             // Assignment operations
@@ -487,48 +609,48 @@ impl Interpreter {
             }
             
             Expression::AddAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.add(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
             }
             
             Expression::SubtractAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.subtract(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
             }
             
             Expression::MultiplyAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.multiply(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
             }
             
             Expression::DivideAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.divide(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
             }
             
             Expression::ModuloAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.modulo(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
             }
             
             Expression::PowerAssign(left, right) => {
-                let left_val = self.evaluate_lvalue(left)?;
-                let right_val = self.evaluate_expression(right)?;
+                let left_val = self.evaluate_numeric_lvalue(left)?;
+                let right_val = self.evaluate_numeric(right)?;
                 let result = left_val.power(&right_val)?;
                 self.assign_to_lvalue(left, result.clone())?;
                 Ok(result)
@@ -536,28 +658,28 @@ impl Interpreter {
             
             // Increment/Decrement
             Expression::PreIncrement(expr) => {
-                let current = self.evaluate_lvalue(expr)?;
+                let current = self.evaluate_numeric_lvalue(expr)?;
                 let result = current.add(&Value::Number(1.0))?;
                 self.assign_to_lvalue(expr, result.clone())?;
                 Ok(result)
             }
             
             Expression::PostIncrement(expr) => {
-                let current = self.evaluate_lvalue(expr)?;
+                let current = self.evaluate_numeric_lvalue(expr)?;
                 let result = current.add(&Value::Number(1.0))?;
                 self.assign_to_lvalue(expr, result)?;
                 Ok(current)
             }
             
             Expression::PreDecrement(expr) => {
-                let current = self.evaluate_lvalue(expr)?;
+                let current = self.evaluate_numeric_lvalue(expr)?;
                 let result = current.subtract(&Value::Number(1.0))?;
                 self.assign_to_lvalue(expr, result.clone())?;
                 Ok(result)
             }
             
             Expression::PostDecrement(expr) => {
-                let current = self.evaluate_lvalue(expr)?;
+                let current = self.evaluate_numeric_lvalue(expr)?;
                 let result = current.subtract(&Value::Number(1.0))?;
                 self.assign_to_lvalue(expr, result)?;
                 Ok(current)
@@ -576,49 +698,197 @@ impl Interpreter {
             // Function call
             Expression::FunctionCall { name, arguments } => {
                 let mut arg_values = Vec::new();
-                for arg in arguments {
-                    arg_values.push(self.evaluate_expression(arg)?);
+                for (position, arg) in arguments.iter().enumerate() {
+                    let value = match arg {
+                        // split() fills its second argument, which need not be an array yet
+                        Expression::Identifier(array) if name == "split" && position == 1 => {
+                            Value::Array(self.context.array_variable(array))
+                        }
+                        _ => self.evaluate_expression(arg)?,
+                    };
+                    arg_values.push(value);
+                }
+
+                if let Some(function) = self.user_function(name) {
+                    // Unset variables passed bare may come back as arrays
+                    let unset: Vec<Option<&str>> = arguments
+                        .iter()
+                        .zip(&arg_values)
+                        .map(|(arg, value)| match arg {
+                            Expression::Identifier(variable) if value.is_undefined() => Some(variable.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    return self.call_user_function(&function, &arg_values, &unset);
                 }
-                
                 self.call_function(name, &arg_values)
             }
             
             // Getline expression
-            Expression::Getline { target: _, source: _ } => {
-                // Simplified getline - in a full implementation this would read from input
-                Ok(Value::Number(0.0))
-            }
+            Expression::Getline { target, source } => self.evaluate_getline(target.as_deref(), source.as_ref()),
             
             // Regular expression literal
             Expression::Regex(pattern) => {
-                let record = self.context.get_field(0);
                 let regex = self.context.get_regex(pattern)?;
-                Ok(Value::Number(if regex.is_match(&record) { 1.0 } else { 0.0 }))
+                Ok(Value::Number(if regex.is_match(&self.context.get_field(0)) { 1.0 } else { 0.0 }))
             }
         }
     }
 
+    /// `getline [var] < file` and `cmd | getline [var]`: 1 when a record
+    /// was read, 0 at the end of the file or command output, -1 when it
+    /// cannot be opened or read, with ERRNO telling why
+    fn evaluate_getline(&mut self, target: Option<&Expression>, source: Option<&GetlineSource>) -> Result<Value> {
+        let (kind, name) = match source {
+            Some(GetlineSource::File(file)) => (SourceKind::File, file),
+            Some(GetlineSource::Command(command)) => (SourceKind::Command, command),
+            None => {
+                return Err(FastAwkError::runtime_error(
+                    "getline from the main input is not supported; use getline < file or cmd | getline",
+                ))
+            }
+        };
+        self.context.policy.check(Capability::RedirectInput)?;
+        if kind == SourceKind::Command {
+            self.context.policy.check(Capability::RunCommand)?;
+        }
+        let name = self.evaluate_expression(name)?;
+        let name = self.context.string_value(&name);
+
+        let record = match self.streams.read(kind, &name, &self.context.rs) {
+            Ok(Some(record)) => record,
+            Ok(None) => return Ok(Value::Number(0.0)),
+            Err(e) => {
+                self.context.set_errno(e.to_string());
+                return Ok(Value::Number(-1.0));
+            }
+        };
+        self.context.set_record_terminator(&record.terminator);
+        let text = String::from_utf8_lossy(&record.record).into_owned();
+        // Only a command's records count in NR, as in awk
+        match target {
+            Some(target) => {
+                if kind == SourceKind::Command {
+                    self.context.count_record();
+                }
+                self.assign_to_lvalue(target, Value::Strnum(text))?;
+            }
+            None => {
+                if kind == SourceKind::Command {
+                    self.context.nr += 1;
+                }
+                self.context.set_field(0, text);
+            }
+        }
+        Ok(Value::Number(1.0))
+    }
+
+    /// Write the output of a print or printf to each of its targets, or
+    /// to stdout when it has none
+    fn write_output(&mut self, targets: &[OutputTarget], output: &[u8]) -> Result<()> {
+        if targets.is_empty() {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(output)?;
+            stdout.flush()?;
+            return Ok(());
+        }
+        for target in targets {
+            let kind = match target {
+                OutputTarget::File(_) => SinkKind::File,
+                OutputTarget::Append(_) => SinkKind::Append,
+                OutputTarget::Pipe(_) => {
+                    self.context.policy.check(Capability::RunCommand)?;
+                    SinkKind::Command
+                }
+            };
+            let name = self.evaluate_expression(target.expression())?;
+            let name = self.context.string_value(&name);
+            self.outputs
+                .write(kind, &name, output)
+                .map_err(|e| FastAwkError::runtime_error(format!("cannot redirect to `{}': {}", name, e)))?;
+        }
+        Ok(())
+    }
+
+    /// `close(name)`: the exit status of a command printed to or read by
+    /// getline, 0 for a file, or -1 when nothing by that name is open
+    fn builtin_close(&mut self, args: &[Value]) -> Result<Value> {
+        let name = args.first().map(|value| self.context.string_value(value)).unwrap_or_default();
+        let status = match self.outputs.close(&name).or_else(|| self.streams.close(&name)) {
+            Some(Ok(status)) => status,
+            Some(Err(e)) => {
+                self.context.set_errno(e.to_string());
+                -1
+            }
+            None => -1,
+        };
+        Ok(Value::Number(f64::from(status)))
+    }
+
+    /// Close the files and commands the script left open, waiting for the
+    /// commands to finish
+    pub fn close_streams(&mut self) {
+        self.outputs.close_all();
+        self.streams.close_all();
+    }
+
+    /// Evaluate an arithmetic operand (see `RuntimeContext::numeric`)
+    fn evaluate_numeric(&mut self, expression: &Expression) -> Result<Value> {
+        let value = self.evaluate_expression(expression)?;
+        Ok(self.context.numeric(value))
+    }
+
+    /// Evaluate a comparison operand; in bignum mode integer-valued numeric
+    /// operands become exact so big counters compare correctly, while string
+    /// constants keep their string comparison semantics
+    fn evaluate_comparison_operand(&mut self, expression: &Expression) -> Result<Value> {
+        let value = self.evaluate_expression(expression)?;
+        if !self.context.bignum || !value.is_numeric_operand() {
+            return Ok(value);
+        }
+        Ok(match value.exact_integer() {
+            Some(integer) => Value::Integer(integer),
+            None => value,
+        })
+    }
+
+    fn evaluate_numeric_lvalue(&mut self, expr: &Expression) -> Result<Value> {
+        let value = self.evaluate_lvalue(expr)?;
+        Ok(self.context.numeric(value))
+    }
+
     fn evaluate_lvalue(&mut self, expr: &Expression) -> Result<Value> {
         match expr {
             Expression::Identifier(name) => Ok(self.context.get_variable(name)),
             Expression::FieldRef(field_expr) => {
                 let index_value = self.evaluate_expression(field_expr)?;
                 let index = index_value.to_number() as usize;
-                Ok(Value::String(self.context.get_field(index)))
-            }
-            Expression::ArrayRef { array, index } => {
-                let mut array_value = self.evaluate_expression(array)?;
-                let index_value = self.evaluate_expression(index)?;
-                let index_str = index_value.to_string();
-                
-                let element = array_value.get_array_element(&index_str);
-                Ok(element.clone())
+                Ok(Value::Strnum(self.context.get_field(index).into_owned()))
             }
+            Expression::ArrayRef { array, index } => self.evaluate_array_ref(array, index),
             _ => Err(FastAwkError::runtime_error("Invalid lvalue")),
         }
     }
 
+    /// Read an array element; like AWK, referencing a missing element creates it
+    fn evaluate_array_ref(&mut self, array: &Expression, index: &Expression) -> Result<Value> {
+        let index_value = self.evaluate_expression(index)?;
+        let index_str = self.context.string_value(&index_value);
+        let array = match array {
+            Expression::Identifier(name) => self.context.array_variable(name),
+            _ => match self.evaluate_expression(array)? {
+                Value::Array(array) => array,
+                _ => return Ok(Value::Undefined),
+            },
+        };
+        Ok(self.context.array_mut(array)?.entry(&index_str).clone())
+    }
+
     fn assign_to_lvalue(&mut self, expr: &Expression, value: Value) -> Result<()> {
+        // Arrays are never copied, so they cannot be assigned either
+        if value.is_array() {
+            return Err(FastAwkError::invalid_assignment("attempt to use an array in a scalar context"));
+        }
         match expr {
             Expression::Identifier(name) => {
                 self.context.set_variable(name, value);
@@ -630,16 +900,80 @@ impl Interpreter {
                 self.context.set_field(index, value.to_string());
                 Ok(())
             }
-            Expression::ArrayRef { array: _, index: _ } => {
-                // In a full implementation, this would handle array assignment properly
-                Ok(())
+            Expression::ArrayRef { array, index } => {
+                let index_value = self.evaluate_expression(index)?;
+                let key = self.context.string_value(&index_value);
+                match array.as_ref() {
+                    Expression::Identifier(name) => {
+                        let array = self.context.array_variable(name);
+                        self.context.array_mut(array)?.insert(&key, value);
+                        Ok(())
+                    }
+                    _ => Err(FastAwkError::invalid_assignment("subscripted value is not an array variable")),
+                }
             }
             _ => Err(FastAwkError::runtime_error("Invalid assignment target")),
         }
     }
 
+    /// Keys for a `for (k in arr)` loop, honouring `PROCINFO["sorted_in"]`.
+    ///
+    /// Without a setting keys come back in insertion order. `@`-prefixed
+    /// names select a predefined order; anything else names a user function
+    /// called as `cmp(i1, v1, i2, v2)` that returns <0, 0 or >0.
+    fn for_in_keys(&mut self, array: ArrayId) -> Result<Vec<String>> {
+        let order = self.context.sorted_in();
+        let Some(array) = self.context.arrays.get(array) else {
+            return Ok(Vec::new());
+        };
+        let order = match order {
+            Some(order) => order,
+            None => return Ok(array.keys()),
+        };
+
+        if order.starts_with('@') {
+            return SortedIn::parse(&order)
+                .map(|sorted_in| sorted_in.sorted_keys(array))
+                .ok_or_else(|| {
+                    FastAwkError::runtime_error(format!("PROCINFO[\"sorted_in\"]: unknown ordering '{}'", order))
+                });
+        }
+
+        let function = self.functions.get(&order).cloned().ok_or_else(|| {
+            FastAwkError::undefined_function(order.clone())
+        })?;
+
+        let mut entries: Vec<(String, Value)> = array
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let mut failure = None;
+        entries.sort_by(|a, b| {
+            if failure.is_some() {
+                return std::cmp::Ordering::Equal;
+            }
+            let args = [Value::from(a.0.as_str()), a.1.clone(), Value::from(b.0.as_str()), b.1.clone()];
+            match self.call_user_function(&function, &args, &[]) {
+                Ok(result) => result.to_number().partial_cmp(&0.0).unwrap_or(std::cmp::Ordering::Equal),
+                Err(e) => {
+                    failure = Some(e);
+                    std::cmp::Ordering::Equal
+                }
+            }
+        });
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(entries.into_iter().map(|(key, _)| key).collect()),
+        }
+    }
+
     fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value> {
-        // Check built-in functions first
+        // Registered extensions win over the builtins they are named after
+        if let Some(extension) = self.extensions.get_mut(name) {
+            return extension(&mut self.context, args);
+        }
+
         match name {
             "length" => self.context.builtin_length(args),
             "substr" => self.context.builtin_substr(args),
@@ -660,10 +994,22 @@ impl Interpreter {
             "int" => self.context.builtin_int(args),
             "rand" => self.context.builtin_rand(args),
             "srand" => self.context.builtin_srand(args),
+            "system" => {
+                // The command sees everything printed to files before it
+                self.outputs.flush_all()?;
+                self.context.builtin_system(args)
+            }
+            "close" => self.builtin_close(args),
+            "jget" => self.context.builtin_jget(args),
+            "jout" => self.context.builtin_jout(args),
             _ => {
                 // Check user-defined functions
                 if let Some(function) = self.functions.get(name).cloned() {
-                    self.call_user_function(&function, args)
+                    self.call_user_function(&function, args, &[])
+                } else if name == "assert" {
+                    self.context.builtin_assert(args)
+                } else if name == "assert_eq" {
+                    self.context.builtin_assert_eq(args)
                 } else {
                     Err(FastAwkError::undefined_function(name))
                 }
@@ -671,14 +1017,26 @@ impl Interpreter {
         }
     }
 
-    fn call_user_function(&mut self, function: &Function, args: &[Value]) -> Result<Value> {
+    /// A user-defined function `name` calls, unless an extension or builtin
+    /// of that name comes first
+    fn user_function(&self, name: &str) -> Option<Function> {
+        if self.extensions.contains_key(name) || BUILTIN_FUNCTIONS.contains(&name) {
+            return None;
+        }
+        self.functions.get(name).cloned()
+    }
+
+    /// Call `function` with `args`; `unset` names the caller's unset
+    /// variables among them, which take any array the function makes of
+    /// their parameter
+    fn call_user_function(&mut self, function: &Function, args: &[Value], unset: &[Option<&str>]) -> Result<Value> {
         // Create new call frame
         self.context.push_call_frame(function.name.clone());
         
-        // Set parameter values
+        // Set parameter values; arrays arrive as handles, so by reference
         for (i, param) in function.parameters.iter().enumerate() {
             let value = args.get(i).cloned().unwrap_or(Value::Undefined);
-            self.context.set_variable(param, value);
+            self.context.set_local(param, value);
         }
         
         // Execute function body
@@ -690,12 +1048,52 @@ impl Interpreter {
             _ => Value::Undefined,
         };
         
-        // Clean up
-        self.context.pop_call_frame();
-        self.context.clear_control_flow();
+        // Clean up; an `exit` inside the function keeps unwinding
+        let frame = self.context.pop_call_frame();
+        self.release_local_arrays(function, args, unset, frame.map(|frame| frame.variables).unwrap_or_default());
+        if !matches!(self.context.control_flow, ControlFlow::Exit(_)) {
+            self.context.clear_control_flow();
+        }
         
         Ok(return_value)
     }
+
+    /// Free the arrays a returning function made of its parameters, but
+    /// for those that now belong to the caller through an unset variable
+    fn release_local_arrays(
+        &mut self,
+        function: &Function,
+        args: &[Value],
+        unset: &[Option<&str>],
+        locals: HashMap<String, Value>,
+    ) {
+        for (i, param) in function.parameters.iter().enumerate() {
+            let Some(&Value::Array(array)) = locals.get(param) else {
+                continue;
+            };
+            if args.get(i) == Some(&Value::Array(array)) {
+                continue;
+            }
+            match unset.get(i).copied().flatten() {
+                Some(variable) => *self.context.variable_mut(variable) = Value::Array(array),
+                None => {
+                    self.context.arrays.free(array);
+                }
+            }
+        }
+    }
+}
+
+/// `name` or `ns::name`, each part an AWK identifier
+fn is_function_name(name: &str) -> bool {
+    let is_identifier = |part: &str| {
+        part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    match name.split_once("::") {
+        Some((namespace, name)) => is_identifier(namespace) && is_identifier(name),
+        None => is_identifier(name),
+    }
 }
 
 impl Default for Interpreter {
@@ -728,7 +1126,7 @@ mod tests {
         
         let expr = Expression::FieldRef(Box::new(Expression::Literal(Value::Number(1.0))));
         let result = interpreter.evaluate_expression(&expr).unwrap();
-        assert_eq!(result, Value::String("hello".to_string()));
+        assert_eq!(result, Value::Strnum("hello".to_string()));
     }
 
     #[test]
@@ -757,6 +1155,352 @@ mod tests {
         assert_eq!(result, Value::Number(5.0));
     }
 
+    #[test]
+    fn test_register_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .register_function("json::get", |_, args| {
+                let key = args.get(1).map(Value::to_string).unwrap_or_default();
+                let document: serde_json::Value = serde_json::from_str(&args[0].to_string()).unwrap_or_default();
+                Ok(Value::from(document[key.as_str()].as_str().unwrap_or("")))
+            })
+            .unwrap();
+        interpreter
+            .register_function("length", |_, _| Ok(Value::Number(-1.0)))
+            .unwrap();
+        assert!(interpreter.register_function("json::get", |_, _| Ok(Value::Undefined)).is_err());
+        assert!(interpreter.register_function("a::b::c", |_, _| Ok(Value::Undefined)).is_err());
+        assert!(interpreter.register_function("9lives", |_, _| Ok(Value::Undefined)).is_err());
+
+        let program = Parser::new(r#"BEGIN { name = json::get("{\"name\":\"ada\"}", "name"); n = length("abc") }"#)
+            .unwrap()
+            .parse()
+            .unwrap();
+        interpreter.execute_program(&program).unwrap();
+        assert_eq!(interpreter.context.get_variable("name").to_string(), "ada");
+        assert_eq!(interpreter.context.get_variable("n").to_number(), -1.0);
+
+        let clash = Parser::new("function length(s) { return 0 } BEGIN { }").unwrap().parse().unwrap();
+        assert!(matches!(
+            interpreter.execute_program(&clash),
+            Err(FastAwkError::FunctionRedefined { .. })
+        ));
+    }
+
+    /// Evaluate `condition` against one record, as `{ r = (condition) }`
+    fn condition_holds(condition: &str, record: &str) -> bool {
+        let script = format!("{{ r = ({}) ? 1 : 0 }}", condition);
+        let mut parser = Parser::new(&script).unwrap();
+        let program = parser.parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.execute_program(&program).unwrap();
+        interpreter.execute_main_rules(&program, record).unwrap();
+        interpreter.context.get_variable("r").to_bool()
+    }
+
+    /// Comparison and truthiness results recorded from gawk 5 and mawk 1.3.4,
+    /// which agree on every case here
+    #[test]
+    fn test_strnum_conformance() {
+        let cases = [
+            ("$1 == 10", "10.0", true),
+            ("$1 == \"10\"", "10.0", false),
+            ("$1 == \"abc\"", "abc", true),
+            ("$1 < $2", "9 10", true),
+            ("$1 < $2", "abc 10", false),
+            ("$1 == $2", "1e1 10", true),
+            ("$1 < 2", "10", false),
+            ("$1 < \"2\"", "10", true),
+            ("$1 == 10", "+10", true),
+            ("$1 == 10", ".10e2", true),
+            ("$1 == 1", "1.", true),
+            ("$1 == 1", "1e", false),
+            ("$1 == 1", "inf", false),
+            ("$1 == 26", "0x1A", false),
+            ("$0 == 5", " 5 ", true),
+            // Concatenation always yields a string, arithmetic a number
+            ("$1 \"\" == 10", "10.0", false),
+            ("$1 + 0 == \"10\"", "10.0", true),
+            // Uninitialized values are both 0 and ""
+            ("x == 0", "a", true),
+            ("x == \"\"", "a", true),
+            // Truthiness of fields follows their numeric value when numeric
+            ("$1", "0", false),
+            ("$1", "0.0", false),
+            ("$1", "a", true),
+            ("$1", "", false),
+            ("$1", "-", true),
+            ("$1", ".", true),
+        ];
+        for (condition, record, expected) in cases {
+            assert_eq!(
+                condition_holds(condition, record),
+                expected,
+                "{} with record {:?}",
+                condition,
+                record
+            );
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .context
+            .builtin_split(&[Value::from("9,10"), Value::from("parts"), Value::from(",")])
+            .unwrap();
+        let parts = interpreter.context.get_variable("parts");
+        let elements = match parts {
+            Value::Array(elements) => interpreter.context.arrays.get(elements).unwrap(),
+            other => panic!("split produced {:?}", other),
+        };
+        let (first, second) = (elements.get("1").unwrap(), elements.get("2").unwrap());
+        assert_eq!(first.compare(second), std::cmp::Ordering::Less);
+
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .context
+            .initialize_with_args(&[("n".to_string(), "010".to_string())])
+            .unwrap();
+        let mut parser = Parser::new(r#"BEGIN { a = (n == 10); b = (n == "10") }"#).unwrap();
+        interpreter.execute_program(&parser.parse().unwrap()).unwrap();
+        assert!(interpreter.context.get_variable("a").to_bool());
+        assert!(!interpreter.context.get_variable("b").to_bool());
+    }
+
+    fn run_begin(script: &str) -> Interpreter {
+        let mut parser = Parser::new(script).unwrap();
+        let program = parser.parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.execute_program(&program).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn test_sandbox_refuses_commands_and_redirection() {
+        let interpreter = run_begin(r#"BEGIN { status = system("exit 3") }"#);
+        assert_eq!(interpreter.context.get_variable("status").to_number(), 3.0);
+
+        let scripts = [
+            r#"BEGIN { system("true") }"#,
+            r#"BEGIN { print "x" > "/tmp/out" }"#,
+            r#"BEGIN { printf "%s", "x" > "/tmp/out" }"#,
+            r#"BEGIN { "ls" | getline }"#,
+            r#"BEGIN { getline line < "/etc/passwd" }"#,
+        ];
+        for script in scripts {
+            let program = Parser::new(script).unwrap().parse().unwrap();
+            let mut interpreter = Interpreter::new();
+            interpreter.context.policy = crate::sandbox::Policy::sandbox();
+            let error = interpreter.execute_program(&program).unwrap_err();
+            assert!(error.to_string().ends_with("not allowed in sandbox mode"), "{}", error);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_getline_from_commands_and_files() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"x;y;z").unwrap();
+        let script = r#"BEGIN {
+            cmd = "printf 'a\\nb\\n'; exit 3"
+            while ((cmd | getline line) > 0) { lines = lines line ","; rt = rt length(RT) }
+            at_end = (cmd | getline line); status = close(cmd); unopened = close(cmd)
+            "echo one two" | getline; second = $2; nr = NR
+            RS = ";"; while ((getline part < FILE) > 0) parts = parts part RT
+            missing = (getline line < "/nonexistent/input"); errno = ERRNO
+        }"#;
+        let program = Parser::new(&script.replace("FILE", &format!("{:?}", file.path()))).unwrap().parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.execute_program(&program).unwrap();
+
+        let variable = |name| interpreter.context.get_variable(name).to_string();
+        assert_eq!(variable("lines"), "a,b,");
+        assert_eq!(variable("rt"), "11");
+        assert_eq!(variable("at_end"), "0");
+        assert_eq!(variable("status"), "3");
+        assert_eq!(variable("unopened"), "-1");
+        assert_eq!((variable("second"), variable("nr")), ("two".to_string(), "3".to_string()));
+        // File records leave NR alone; the last one has no terminator
+        assert_eq!(variable("parts"), "x;y;z");
+        assert_eq!(variable("missing"), "-1");
+        assert!(!variable("errno").is_empty());
+
+        let plain = Parser::new("BEGIN { getline line }").unwrap().parse().unwrap();
+        assert!(Interpreter::new().execute_program(&plain).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_print_to_several_targets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().display();
+        std::fs::write(temp_dir.path().join("log"), "kept\n").unwrap();
+        let script = format!(
+            r#"BEGIN {{
+                for (i = 3; i > 0; i--) print i > "{dir}/all" >> "{dir}/log" | "sort > {dir}/sorted"
+                printf "%s\n", "done" >> "{dir}/log"
+                status = close("sort > {dir}/sorted")
+            }}"#
+        );
+        let interpreter = run_begin(&script);
+        let read = |name: &str| std::fs::read_to_string(temp_dir.path().join(name)).unwrap();
+        assert_eq!(read("sorted"), "1\n2\n3\n");
+        assert_eq!(interpreter.context.get_variable("status").to_string(), "0");
+        drop(interpreter);
+        assert_eq!(read("all"), "3\n2\n1\n");
+        assert_eq!(read("log"), "kept\n3\n2\n1\ndone\n");
+    }
+
+    #[test]
+    fn test_assertions() {
+        let interpreter = run_begin(r#"BEGIN { a = assert(1 < 2); b = assert_eq("10", 10.0) }"#);
+        assert_eq!(interpreter.context.get_variable("a").to_number(), 1.0);
+        assert_eq!(interpreter.context.get_variable("b").to_number(), 1.0);
+
+        let failing = |script: &str| {
+            let program = Parser::new(script).unwrap().parse().unwrap();
+            Interpreter::new().execute_program(&program).unwrap_err().to_string()
+        };
+        assert_eq!(failing(r#"BEGIN { assert(0, "zero is false") }"#), "Assertion failed: zero is false");
+        assert_eq!(
+            failing(r#"BEGIN { assert_eq(substr("abc", 2), "bd", "substr") }"#),
+            r#"Assertion failed: substr: expected "bd", got "bc""#
+        );
+
+        // A script's own assert wins
+        let interpreter = run_begin(r#"function assert(c) { return 7 } BEGIN { r = assert(0) }"#);
+        assert_eq!(interpreter.context.get_variable("r").to_number(), 7.0);
+    }
+
+    #[test]
+    fn test_for_in_insertion_order() {
+        let interpreter = run_begin(
+            r#"BEGIN { a["b"] = 1; a["a"] = 2; a["c"] = 3; for (k in a) s = s k "," }"#,
+        );
+        assert_eq!(interpreter.context.get_variable("s").to_string(), "b,a,c,");
+    }
+
+    #[test]
+    fn test_arrays_pass_by_reference() {
+        let interpreter = run_begin(
+            r#"function fill(arr, n,   tmp, i) { for (i = 1; i <= n; i++) { tmp[i] = i; arr[i] = i * i } count = length(tmp) }
+            function make(fresh) { fresh["made"] = 1 }
+            BEGIN { n = split("a b c", parts); last = parts[3]
+                fill(squares, 3); s = squares[1] "," squares[2] "," squares[3]
+                make(made); had = ("made" in made)
+                for (k in parts) keys = keys k }"#,
+        );
+        let var = |name: &str| interpreter.context.get_variable(name).to_string();
+        assert_eq!((var("n"), var("last")), ("3".to_string(), "c".to_string()));
+        assert_eq!(var("s"), "1,4,9");
+        // `count` is global, not a local of `fill`
+        assert_eq!(var("count"), "3");
+        assert_eq!(var("had"), "1");
+        assert_eq!(var("keys"), "123");
+        // fill's `tmp` was freed; parts, squares and made remain
+        assert_eq!(interpreter.context.arrays.len(), 3);
+
+        for script in [r#"BEGIN { a[1] = 1; b = a }"#, r#"function f(x) { x[1] = 1; return x } BEGIN { f(y) }"#] {
+            let program = Parser::new(script).unwrap().parse().unwrap();
+            assert!(Interpreter::new().execute_program(&program).is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_delete_elements_and_arrays() {
+        let interpreter = run_begin(
+            r#"BEGIN { a["x"] = 1; a["y"] = 2; a["z"] = 3; delete a["y"]; delete a["missing"]
+                for (k in a) s = s k ","; n = length(a); had = ("y" in a)
+                a["y"] = 4; for (k in a) t = t k ","
+                delete a; m = length(a); gone = ("x" in a); a["w"] = 5; for (k in a) u = u k "," }"#,
+        );
+        let var = |name: &str| interpreter.context.get_variable(name).to_string();
+        assert_eq!(var("s"), "x,z,");
+        assert_eq!(var("n"), "2");
+        assert_eq!(var("had"), "0");
+        // A deleted key comes back at the end
+        assert_eq!(var("t"), "x,z,y,");
+        assert_eq!(var("m"), "0");
+        assert_eq!(var("gone"), "0");
+        assert_eq!(var("u"), "w,");
+    }
+
+    #[test]
+    fn test_for_in_sorted_in() {
+        let interpreter = run_begin(
+            r#"BEGIN { a["10"] = 5; a["9"] = 20; a["x"] = 1
+                PROCINFO["sorted_in"] = "@ind_num_asc"; for (k in a) s = s k ","
+                PROCINFO["sorted_in"] = "@val_num_desc"; for (k in a) t = t k "," }"#,
+        );
+        assert_eq!(interpreter.context.get_variable("s").to_string(), "x,9,10,");
+        assert_eq!(interpreter.context.get_variable("t").to_string(), "9,10,x,");
+    }
+
+    #[test]
+    fn test_for_in_sorted_in_user_function() {
+        let interpreter = run_begin(
+            r#"function by_len(i1, v1, i2, v2) { return length(i2) - length(i1) }
+               BEGIN { a["aa"]; a["a"]; a["aaa"]
+                PROCINFO["sorted_in"] = "by_len"; for (k in a) s = s k "," }"#,
+        );
+        assert_eq!(interpreter.context.get_variable("s").to_string(), "aaa,aa,a,");
+    }
+
+    #[test]
+    fn test_bignum_mode() {
+        let script = r#"BEGIN { a = "9007199254740993"; b = a + 1; c = 2 ^ 64 - 1; d = 18446744073709551616 - 1; e = 7 / 2
+                f = ("99999999999999999999" > "99999999999999999998") }"#;
+        let program = Parser::new(script).unwrap().parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.execute_program(&program).unwrap();
+        assert_eq!(interpreter.context.get_variable("b").to_string(), "9007199254740992");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.context.bignum = true;
+        interpreter.execute_program(&program).unwrap();
+        assert_eq!(interpreter.context.get_variable("b").to_string(), "9007199254740994");
+        assert_eq!(interpreter.context.get_variable("c").to_string(), "18446744073709551615");
+        assert_eq!(interpreter.context.get_variable("d").to_string(), "18446744073709551615");
+        assert_eq!(interpreter.context.get_variable("e").to_string(), "3.5");
+        assert_eq!(interpreter.context.get_variable("f").to_string(), "1");
+    }
+
+    /// Run `script` over `records` the way main does, returning the exit
+    /// status and the final value of `t`
+    fn run_to_exit(script: &str, records: &[&str]) -> (i32, String) {
+        let program = Parser::new(script).unwrap().parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.execute_program(&program).unwrap();
+        for record in records {
+            if interpreter.context.exit_code.is_some() {
+                break;
+            }
+            interpreter.execute_main_rules(&program, record).unwrap();
+        }
+        interpreter.execute_end_rules(&program).unwrap();
+        let status = interpreter.context.exit_code.unwrap_or(0);
+        (status, interpreter.context.get_variable("t").to_string())
+    }
+
+    /// Statuses and END output recorded from mawk 1.3.4
+    #[test]
+    fn test_exit_runs_end_rules() {
+        let records = ["l1", "l2"];
+        let cases = [
+            (r#"BEGIN { exit 3 } END { t = "end" }"#, 3, "end"),
+            ("BEGIN { exit 3 } END { exit }", 3, ""),
+            ("BEGIN { exit 3 } END { exit 4 }", 4, ""),
+            ("BEGIN { exit } { t = t $0 } END { t = t \"x\" }", 0, "x"),
+            ("{ t = t $0; exit 5 } END { t = t NR }", 5, "l11"),
+            (r#"END { exit 1; t = "no" }"#, 1, ""),
+            (r#"END { t = "a" } END { exit 2 } END { t = "b" }"#, 2, "a"),
+            (r#"function f() { exit 7 } { f(); t = "after" } END { t = t "e" }"#, 7, "e"),
+        ];
+        for (script, status, trace) in cases {
+            assert_eq!(run_to_exit(script, &records), (status, trace.to_string()), "{}", script);
+        }
+    }
+
     #[test]
     fn test_simple_program() {
         let mut parser = Parser::new("BEGIN { print \"Hello, World!\" }").unwrap();
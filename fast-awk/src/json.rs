@@ -0,0 +1,161 @@
+use crate::array::ArrayArena;
+use crate::errors::{FastAwkError, Result};
+use crate::value::Value;
+use serde_json::{Number, Value as Json};
+
+/// A record read with `--input-format json`: one JSON document per record,
+/// usually a line of JSON Lines. Blank records hold `null`.
+pub fn parse_record(record: &str) -> Result<Json> {
+    if record.trim().is_empty() {
+        return Ok(Json::Null);
+    }
+    Ok(serde_json::from_str(record)?)
+}
+
+/// The member of `document` at `path`: keys separated by dots, array
+/// elements by `[N]` or a numeric key, so `user.name`, `tags[0]` and
+/// `tags.0` all work. An empty path is the whole document.
+pub fn lookup<'a>(document: &'a Json, path: &str) -> Option<&'a Json> {
+    let mut current = document;
+    for step in path_steps(path) {
+        current = match current {
+            Json::Object(members) => members.get(step)?,
+            Json::Array(elements) => elements.get(step.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// `a.b[0]` as `a`, `b`, `0`
+fn path_steps(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['.', '[', ']']).filter(|step| !step.is_empty())
+}
+
+/// What `jget` gives a script: strings as strings, numbers and booleans as
+/// numbers, objects and arrays as their compact JSON text, and `null` or a
+/// missing member as an uninitialized value
+pub fn to_value(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Undefined,
+        Json::Bool(flag) => Value::Number(if *flag { 1.0 } else { 0.0 }),
+        Json::Number(number) => Value::Number(number.as_f64().unwrap_or(0.0)),
+        Json::String(text) => Value::String(text.clone()),
+        Json::Array(_) | Json::Object(_) => Value::String(json.to_string()),
+    }
+}
+
+/// What `jout` writes for a value: an array becomes an object of its
+/// elements in insertion order, numbers and numeric input stay numbers and
+/// everything else is a string
+fn write_value(value: &Value, arrays: &ArrayArena, out: &mut String) {
+    match value {
+        Value::Number(n) => out.push_str(&number(*n, None)),
+        Value::Integer(i) => out.push_str(&i.to_string()),
+        Value::Strnum(s) if value.is_numeric_operand() => out.push_str(&number(value.to_number(), Some(s))),
+        Value::String(s) | Value::Strnum(s) => out.push_str(&Json::from(s.as_str()).to_string()),
+        Value::Array(id) => {
+            out.push('{');
+            let elements = arrays.get(*id).into_iter().flat_map(|array| array.iter());
+            for (index, (key, element)) in elements.enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Json::from(key.as_str()).to_string());
+                out.push(':');
+                write_value(element, arrays, out);
+            }
+            out.push('}');
+        }
+        Value::Undefined => out.push_str("null"),
+    }
+}
+
+/// Integral numbers without a fraction, as `print` shows them. NaN and
+/// infinities, which JSON lacks, are `null`, or their text when they were
+/// read as input.
+fn number(n: f64, text: Option<&str>) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        (n as i64).to_string()
+    } else if let Some(number) = Number::from_f64(n) {
+        number.to_string()
+    } else {
+        text.map_or_else(|| "null".to_string(), |text| Json::from(text).to_string())
+    }
+}
+
+/// `jout` arguments as one line of compact JSON: nothing is the record as
+/// read (with its keys sorted), one argument is that value, and more are
+/// key, value pairs
+pub fn emit(record: Option<&Json>, args: &[Value], arrays: &ArrayArena) -> Result<String> {
+    let mut out = String::new();
+    match args {
+        [] => out.push_str(&record.unwrap_or(&Json::Null).to_string()),
+        [value] => write_value(value, arrays, &mut out),
+        pairs if pairs.len() % 2 == 0 => {
+            out.push('{');
+            for (index, pair) in pairs.chunks(2).enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Json::from(pair[0].to_string()).to_string());
+                out.push(':');
+                write_value(&pair[1], arrays, &mut out);
+            }
+            out.push('}');
+        }
+        _ => {
+            return Err(FastAwkError::invalid_function_call(
+                "jout",
+                format!("{} arguments", args.len()),
+                "takes nothing, one value, or key, value pairs",
+            ))
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::AwkArray;
+
+    #[test]
+    fn test_lookup_paths() {
+        let document = parse_record(r#"{"user":{"name":"ada","id":7},"tags":["a","b"],"ok":true,"gone":null}"#).unwrap();
+        let get = |path| lookup(&document, path).map(to_value);
+        assert_eq!(get("user.name"), Some(Value::from("ada")));
+        assert_eq!(get("user.id"), Some(Value::Number(7.0)));
+        assert_eq!(get("tags[1]"), Some(Value::from("b")));
+        assert_eq!(get("tags.0"), Some(Value::from("a")));
+        assert_eq!(get("tags"), Some(Value::from(r#"["a","b"]"#)));
+        assert_eq!(get("ok"), Some(Value::Number(1.0)));
+        assert_eq!(get("gone"), Some(Value::Undefined));
+        assert_eq!(get("user.name.first"), None);
+        assert_eq!(get("tags[5]"), None);
+        assert_eq!(parse_record("  ").unwrap(), Json::Null);
+        assert!(parse_record("{oops").is_err());
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut arrays = ArrayArena::new();
+        let mut array = AwkArray::new();
+        array.insert("host", Value::from("web1"));
+        array.insert("count", Value::Strnum("12".to_string()));
+        array.insert("ratio", Value::Number(0.5));
+        array.insert("code", Value::Strnum("0x1F".to_string()));
+        let array = Value::Array(arrays.alloc(array));
+        assert_eq!(
+            emit(None, &[array], &arrays).unwrap(),
+            r#"{"host":"web1","count":12,"ratio":0.5,"code":"0x1F"}"#
+        );
+
+        let pairs = [Value::from("n"), Value::Number(3.0), Value::from("name"), Value::from("ada")];
+        assert_eq!(emit(None, &pairs, &arrays).unwrap(), r#"{"n":3,"name":"ada"}"#);
+
+        let record = parse_record(r#"{"a": 1}"#).unwrap();
+        assert_eq!(emit(Some(&record), &[], &arrays).unwrap(), r#"{"a":1}"#);
+        assert!(emit(None, &[Value::Undefined, Value::Undefined, Value::Undefined], &arrays).is_err());
+    }
+}
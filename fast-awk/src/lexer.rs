@@ -6,6 +6,8 @@ pub enum Token {
     // Literals
     String(String),
     Number(f64),
+    /// Integer literal too large to be exact as an f64, kept as its digits
+    Integer(String),
     Regex(String),
     FieldRef(String), // $0, $1, $NF, etc.
 
@@ -54,6 +56,8 @@ pub enum Token {
     LessEqual,
     Greater,
     GreaterEqual,
+    /// `>>`, for `print >> file`; never two comparisons
+    Append,
     Match,
     NotMatch,
 
@@ -65,6 +69,9 @@ pub enum Token {
     // String operators
     Concatenate,
 
+    /// `|`, for `print | cmd` and `cmd | getline`
+    Pipe,
+
     // Increment/Decrement
     Increment,
     Decrement,
@@ -92,6 +99,7 @@ impl fmt::Display for Token {
         match self {
             Token::String(s) => write!(f, "\"{}\"", s),
             Token::Number(n) => write!(f, "{}", n),
+            Token::Integer(digits) => write!(f, "{}", digits),
             Token::Regex(r) => write!(f, "/{}/", r),
             Token::FieldRef(r) => write!(f, "${}", r),
             Token::Identifier(id) => write!(f, "{}", id),
@@ -132,12 +140,14 @@ impl fmt::Display for Token {
             Token::LessEqual => write!(f, "<="),
             Token::Greater => write!(f, ">"),
             Token::GreaterEqual => write!(f, ">="),
+            Token::Append => write!(f, ">>"),
             Token::Match => write!(f, "~"),
             Token::NotMatch => write!(f, "!~"),
             Token::And => write!(f, "&&"),
             Token::Or => write!(f, "||"),
             Token::Not => write!(f, "!"),
             Token::Concatenate => write!(f, " "),
+            Token::Pipe => write!(f, "|"),
             Token::Increment => write!(f, "++"),
             Token::Decrement => write!(f, "--"),
             Token::LeftParen => write!(f, "("),
@@ -179,6 +189,9 @@ impl Lexer {
         
         loop {
             let token = self.next_token()?;
+            if token == Token::Newline && tokens.last().is_some_and(Self::continues_after) {
+                continue;
+            }
             let is_eof = matches!(token, Token::Eof);
             tokens.push(token);
             if is_eof {
@@ -189,6 +202,16 @@ impl Lexer {
         Ok(tokens)
     }
 
+    /// Tokens after which awk reads on past a newline, as gawk does: `,`,
+    /// `{`, `&&`, `||`, `do`, `else` and the parts of `?:`
+    fn continues_after(token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Comma | Token::LeftBrace | Token::And | Token::Or | Token::Do | Token::Else |
+            Token::Question | Token::Colon
+        )
+    }
+
     fn next_token(&mut self) -> Result<Token> {
         self.skip_whitespace();
         
@@ -288,6 +311,8 @@ impl Lexer {
             '>' => {
                 if self.match_char('=') {
                     Ok(Token::GreaterEqual)
+                } else if self.match_char('>') {
+                    Ok(Token::Append)
                 } else {
                     Ok(Token::Greater)
                 }
@@ -304,7 +329,7 @@ impl Lexer {
                 if self.match_char('|') {
                     Ok(Token::Or)
                 } else {
-                    Err(FastAwkError::parse_error(self.line, self.column, "Unexpected character '|'"))
+                    Ok(Token::Pipe)
                 }
             }
             '"' => self.read_string(),
@@ -363,6 +388,20 @@ impl Lexer {
         while !self.is_at_end() {
             match self.current_char() {
                 ' ' | '\t' | '\r' => self.advance(),
+                // A backslash at the end of a line joins it to the next
+                '\\' if self.peek_char() == '\n' => {
+                    self.advance();
+                    self.advance();
+                    self.line += 1;
+                    self.column = 1;
+                }
+                '\\' if self.peek_char() == '\r' && self.input.get(self.position + 2) == Some(&'\n') => {
+                    self.advance();
+                    self.advance();
+                    self.advance();
+                    self.line += 1;
+                    self.column = 1;
+                }
                 _ => break,
             }
         }
@@ -395,6 +434,11 @@ impl Lexer {
                     '\\' => value.push('\\'),
                     '"' => value.push('"'),
                     '\'' => value.push('\''),
+                    // Continued onto the next line
+                    '\n' => {
+                        self.line += 1;
+                        self.column = 0;
+                    }
                     c => value.push(c),
                 }
             } else {
@@ -488,6 +532,15 @@ impl Lexer {
             }
         }
         
+        // Keep the digits of big integer literals, so -M mode can use them exactly
+        if value.bytes().all(|b| b.is_ascii_digit()) && value.len() > 15 {
+            if let Ok(number) = value.parse::<f64>() {
+                if number > 9_007_199_254_740_992.0 {
+                    return Ok(Token::Integer(value));
+                }
+            }
+        }
+
         let number = value.parse::<f64>().map_err(|_| {
             FastAwkError::parse_error(self.line, self.column, format!("Invalid number: {}", value))
         })?;
@@ -501,6 +554,16 @@ impl Lexer {
         while !self.is_at_end() && (self.current_char().is_ascii_alphanumeric() || self.current_char() == '_') {
             value.push(self.current_char());
             self.advance();
+
+            // gawk-style qualified names, `ns::name`, as used by extensions
+            if self.current_char() == ':'
+                && self.peek_char() == ':'
+                && self.input.get(self.position + 2).is_some_and(|c| c.is_ascii_alphabetic() || *c == '_')
+            {
+                value.push_str("::");
+                self.advance();
+                self.advance();
+            }
         }
         
         let token = match value.as_str() {
@@ -579,6 +642,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_numbers() {
         let mut lexer = Lexer::new("42 3.14 1.23e-4");
         let tokens = lexer.tokenize().unwrap();
@@ -586,6 +650,11 @@ mod tests {
         assert_eq!(tokens[0], Token::Number(42.0));
         assert_eq!(tokens[1], Token::Number(3.14));
         assert_eq!(tokens[2], Token::Number(1.23e-4));
+
+        let mut lexer = Lexer::new("9007199254740992 18446744073709551616");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0], Token::Number(9007199254740992.0));
+        assert_eq!(tokens[1], Token::Integer("18446744073709551616".to_string()));
     }
 
     #[test]
@@ -596,5 +665,24 @@ mod tests {
         assert_eq!(tokens[0], Token::Identifier("variable_name".to_string()));
         assert_eq!(tokens[1], Token::Identifier("_private".to_string()));
         assert_eq!(tokens[2], Token::Identifier("func123".to_string()));
+
+        let tokens = Lexer::new("json::parse(x) a ? b : c").tokenize().unwrap();
+        assert_eq!(tokens[0], Token::Identifier("json::parse".to_string()));
+        assert!(tokens.contains(&Token::Colon));
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let tokens = Lexer::new("x = 1 + \\\n2 \\\r\n+ 3").tokenize().unwrap();
+        assert!(!tokens.contains(&Token::Newline));
+        assert_eq!(tokens.len(), 8);
+
+        let tokens = Lexer::new("\"a\\\nb\"").tokenize().unwrap();
+        assert_eq!(tokens[0], Token::String("ab".to_string()));
+
+        // Newlines after `,` `&&` `||` are not terminators; other ones are,
+        // and a backslash inside a comment does not continue it
+        let tokens = Lexer::new("f(a,\n b) && # why\n c ||\n d\n# c \\\ne").tokenize().unwrap();
+        assert_eq!(tokens.iter().filter(|token| **token == Token::Newline).count(), 2);
     }
 }
\ No newline at end of file
@@ -0,0 +1,41 @@
+//! AWK interpreter behind `fawk`, usable from other Rust programs.
+//!
+//! ```
+//! use fast_awk::{Interpreter, Parser, Value};
+//!
+//! let program = Parser::new(r#"BEGIN { x = double(21) }"#).unwrap().parse().unwrap();
+//! let mut interpreter = Interpreter::new();
+//! interpreter
+//!     .register_function("double", |_, args| {
+//!         Ok(Value::Number(args.first().map_or(0.0, Value::to_number) * 2.0))
+//!     })
+//!     .unwrap();
+//! interpreter.execute_program(&program).unwrap();
+//! assert_eq!(interpreter.context.get_variable("x").to_number(), 42.0);
+//! ```
+
+pub mod array;
+pub mod ast;
+pub mod errors;
+pub mod fields;
+pub mod format;
+pub mod getline;
+pub mod include;
+pub mod interpreter;
+pub mod json;
+pub mod lexer;
+pub mod lint;
+pub mod parser;
+pub mod record;
+pub mod record_match;
+pub mod redirect;
+pub mod runtime;
+pub mod sandbox;
+pub mod value;
+
+pub use ast::Program;
+pub use errors::{FastAwkError, Result};
+pub use interpreter::{ExtensionFunction, Interpreter};
+pub use parser::Parser;
+pub use runtime::RuntimeContext;
+pub use value::Value;
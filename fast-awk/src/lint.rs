@@ -0,0 +1,502 @@
+//! `--lint`: static checks over a parsed program, in the spirit of
+//! `gawk --lint`, for mistakes AWK otherwise accepts silently.
+
+use crate::ast::{Expression, Function, GetlineSource, OutputTarget, Pattern, Program, Statement};
+use crate::interpreter::{BUILTIN_FUNCTIONS, TESTING_FUNCTIONS};
+use crate::value::Value;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Variables the runtime sets up itself, so reading them is never a sign of
+/// a missing assignment
+const BUILTIN_VARIABLES: &[&str] = &[
+    "NR", "NF", "FNR", "FS", "OFS", "RS", "ORS", "FILENAME", "SUBSEP", "RSTART", "RLENGTH", "CONVFMT",
+    "OFMT", "ENVIRON", "ARGC", "ARGV",
+];
+
+/// Special variables only gawk (and fawk) know about
+const GAWK_VARIABLES: &[&str] = &[
+    "FIELDWIDTHS", "FPAT", "IGNORECASE", "PROCINFO", "RT", "BINMODE", "ERRNO", "ARGIND", "LINT",
+    "TEXTDOMAIN", "FUNCTAB", "SYMTAB", "PREC", "ROUNDMODE",
+];
+
+/// One problem found by [`lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Check `program` for calls to undefined functions, variables compared
+/// before anything assigns them, constructs other awks do not support, and
+/// rules or statements that can never run.
+///
+/// `functions` names callable functions beyond the builtins and the
+/// program's own (registered extensions); `assigned` names variables set
+/// from outside the program, such as `-v` assignments.
+pub fn lint(program: &Program, functions: &[&str], assigned: &[&str]) -> Vec<LintWarning> {
+    let mut linter = Linter {
+        program,
+        functions: functions.iter().copied().collect(),
+        assigned: assigned.iter().map(|name| name.to_string()).collect(),
+        parameters: &[],
+        warned: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    linter.collect_assignments();
+    linter.check_rules();
+
+    let mut functions: Vec<&Function> = program.functions.values().collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    for function in functions {
+        linter.parameters = &function.parameters;
+        linter.check_statements(&function.body.statements, &format!("function `{}'", function.name));
+    }
+    linter.warnings
+}
+
+struct Linter<'a> {
+    program: &'a Program,
+    functions: HashSet<&'a str>,
+    /// Every name anything in the program (or outside it) assigns to
+    assigned: HashSet<String>,
+    /// Parameters of the function being checked, which are always locals
+    parameters: &'a [String],
+    /// Messages already given, so each problem is reported once
+    warned: HashSet<String>,
+    warnings: Vec<LintWarning>,
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, message: String) {
+        if self.warned.insert(message.clone()) {
+            self.warnings.push(LintWarning { message });
+        }
+    }
+
+    fn collect_assignments(&mut self) {
+        for rule in &self.program.rules {
+            if let Some(pattern) = &rule.pattern {
+                record_pattern(pattern, &mut self.assigned);
+            }
+            record_statements(&rule.action.statements, &mut self.assigned);
+        }
+        for function in self.program.functions.values() {
+            record_statements(&function.body.statements, &mut self.assigned);
+        }
+    }
+
+    fn check_rules(&mut self) {
+        let mut begin_exits = false;
+        let mut main_rule = 0;
+        let mut skipping_after = None;
+        for rule in &self.program.rules {
+            let place = match &rule.pattern {
+                Some(Pattern::Begin) => "BEGIN rule".to_string(),
+                Some(Pattern::End) => "END rule".to_string(),
+                _ => {
+                    main_rule += 1;
+                    format!("rule {}", main_rule)
+                }
+            };
+            if let Some(pattern) = &rule.pattern {
+                self.check_pattern(pattern, &place);
+            }
+            self.check_statements(&rule.action.statements, &place);
+
+            match &rule.pattern {
+                Some(Pattern::Begin) => begin_exits |= always_leaves(&rule.action.statements, true),
+                Some(Pattern::End) => {}
+                _ if begin_exits => {
+                    self.warn(format!("{} is unreachable: a BEGIN rule always exits", place));
+                }
+                _ => {
+                    if let Some(earlier) = skipping_after {
+                        self.warn(format!("{} is unreachable: rule {} always ends with next or exit", place, earlier));
+                    } else if rule.pattern.is_none() && always_leaves(&rule.action.statements, false) {
+                        skipping_after = Some(main_rule);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_pattern(&mut self, pattern: &Pattern, place: &str) {
+        match pattern {
+            Pattern::Begin | Pattern::End => {}
+            Pattern::Expression(expression) => {
+                if let Expression::Literal(value) = expression {
+                    if !value.to_bool() {
+                        self.warn(format!("{} never runs: its pattern is always false", place));
+                    }
+                }
+                self.check_expression(expression, place);
+            }
+            Pattern::Range(start, end) => {
+                self.check_pattern(start, place);
+                self.check_pattern(end, place);
+            }
+        }
+    }
+
+    fn check_statements(&mut self, statements: &[Statement], place: &str) {
+        let mut ended_by = None;
+        for statement in statements {
+            if let Some(keyword) = ended_by {
+                self.warn(format!("unreachable statement after `{}' in {}", keyword, place));
+                break;
+            }
+            self.check_statement(statement, place);
+            ended_by = match statement {
+                Statement::Next => Some("next"),
+                Statement::Exit(_) => Some("exit"),
+                Statement::Return(_) => Some("return"),
+                Statement::Break => Some("break"),
+                Statement::Continue => Some("continue"),
+                _ => None,
+            };
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement, place: &str) {
+        match statement {
+            Statement::Expression(expression) | Statement::Delete(expression) => {
+                self.check_expression(expression, place)
+            }
+            Statement::Exit(expression) | Statement::Return(expression) => {
+                if let Some(expression) = expression {
+                    self.check_expression(expression, place);
+                }
+            }
+            Statement::Block(statements) => self.check_statements(statements, place),
+            Statement::If { condition, then_stmt, else_stmt } => {
+                self.check_expression(condition, place);
+                self.check_statement(then_stmt, place);
+                if let Some(else_stmt) = else_stmt {
+                    self.check_statement(else_stmt, place);
+                }
+            }
+            Statement::While { condition, body } => {
+                self.check_expression(condition, place);
+                self.check_statement(body, place);
+            }
+            Statement::For { init, condition, update, body } => {
+                for expression in [init, condition, update].into_iter().flatten() {
+                    self.check_expression(expression, place);
+                }
+                self.check_statement(body, place);
+            }
+            Statement::ForIn { array, body, .. } => {
+                self.check_expression(array, place);
+                self.check_statement(body, place);
+            }
+            Statement::Print(print) => {
+                for expression in &print.expressions {
+                    self.check_expression(expression, place);
+                }
+                self.check_targets(&print.output_targets, place);
+            }
+            Statement::Printf(printf) => {
+                self.check_expression(&printf.format, place);
+                for expression in &printf.arguments {
+                    self.check_expression(expression, place);
+                }
+                self.check_targets(&printf.output_targets, place);
+            }
+            Statement::Break | Statement::Continue | Statement::Next => {}
+        }
+    }
+
+    fn check_targets(&mut self, targets: &[OutputTarget], place: &str) {
+        if targets.len() > 1 {
+            self.warn(format!("several output redirections are a fawk extension ({})", place));
+        }
+        for target in targets {
+            self.check_expression(target.expression(), place);
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression, place: &str) {
+        match expression {
+            Expression::Identifier(name) if GAWK_VARIABLES.contains(&name.as_str()) => {
+                self.warn(format!("`{}' is a gawk extension ({})", name, place));
+            }
+            Expression::Equal(left, right)
+            | Expression::NotEqual(left, right)
+            | Expression::Less(left, right)
+            | Expression::LessEqual(left, right)
+            | Expression::Greater(left, right)
+            | Expression::GreaterEqual(left, right) => {
+                for operand in [left, right] {
+                    if let Expression::Identifier(name) = operand.as_ref() {
+                        if self.is_never_assigned(name) {
+                            self.warn(format!("`{}' is compared in {} but never assigned", name, place));
+                        }
+                    }
+                }
+            }
+            Expression::Assign(target, value) => {
+                let multi_character = matches!(
+                    value.as_ref(),
+                    Expression::Literal(Value::String(rs)) if rs.chars().count() > 1
+                );
+                if multi_character && matches!(target.as_ref(), Expression::Identifier(name) if name == "RS") {
+                    self.warn(format!("multicharacter RS is a gawk extension ({})", place));
+                }
+            }
+            Expression::FunctionCall { name, arguments } => self.check_call(name, arguments.len(), place),
+            _ => {}
+        }
+        for child in children(expression) {
+            self.check_expression(child, place);
+        }
+    }
+
+    fn check_call(&mut self, name: &str, arguments: usize, place: &str) {
+        if name.contains("::") {
+            self.warn(format!("namespace qualified name `{}' is a gawk extension ({})", name, place));
+        }
+        if let Some(function) = self.program.functions.get(name) {
+            if arguments > function.parameters.len() {
+                self.warn(format!(
+                    "function `{}' called with {} arguments but declares {} ({})",
+                    name,
+                    arguments,
+                    function.parameters.len(),
+                    place
+                ));
+            }
+        } else if !BUILTIN_FUNCTIONS.contains(&name)
+            && !TESTING_FUNCTIONS.contains(&name)
+            && !self.functions.contains(name)
+        {
+            self.warn(format!("function `{}' called but never defined ({})", name, place));
+        }
+    }
+
+    fn is_never_assigned(&self, name: &str) -> bool {
+        !self.assigned.contains(name)
+            && !self.parameters.iter().any(|parameter| parameter == name)
+            && !BUILTIN_VARIABLES.contains(&name)
+            && !GAWK_VARIABLES.contains(&name)
+    }
+}
+
+/// The variable an assignment target stores into, for plain variables and
+/// array elements
+fn assigned_name(target: &Expression) -> Option<&str> {
+    match target {
+        Expression::Identifier(name) => Some(name),
+        Expression::ArrayRef { array, .. } => assigned_name(array),
+        _ => None,
+    }
+}
+
+/// Whether running `statements` always ends in `next` or `exit` (or, in a
+/// BEGIN rule where `next` is not allowed, just `exit`)
+fn always_leaves(statements: &[Statement], exit_only: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Exit(_) => true,
+        Statement::Next => !exit_only,
+        Statement::Block(inner) => always_leaves(inner, exit_only),
+        Statement::If { then_stmt, else_stmt: Some(else_stmt), .. } => {
+            always_leaves(std::slice::from_ref(then_stmt), exit_only)
+                && always_leaves(std::slice::from_ref(else_stmt), exit_only)
+        }
+        _ => false,
+    })
+}
+
+/// Add every variable `pattern` assigns to `assigned`
+fn record_pattern(pattern: &Pattern, assigned: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Begin | Pattern::End => {}
+        Pattern::Expression(expression) => record_expression(expression, assigned),
+        Pattern::Range(start, end) => {
+            record_pattern(start, assigned);
+            record_pattern(end, assigned);
+        }
+    }
+}
+
+fn record_statements(statements: &[Statement], assigned: &mut HashSet<String>) {
+    for statement in statements {
+        record_statement(statement, assigned);
+    }
+}
+
+fn record_statement(statement: &Statement, assigned: &mut HashSet<String>) {
+    match statement {
+        Statement::Expression(expression) | Statement::Delete(expression) => record_expression(expression, assigned),
+        Statement::Exit(expression) | Statement::Return(expression) => {
+            if let Some(expression) = expression {
+                record_expression(expression, assigned);
+            }
+        }
+        Statement::Block(inner) => record_statements(inner, assigned),
+        Statement::If { condition, then_stmt, else_stmt } => {
+            record_expression(condition, assigned);
+            record_statement(then_stmt, assigned);
+            if let Some(else_stmt) = else_stmt {
+                record_statement(else_stmt, assigned);
+            }
+        }
+        Statement::While { condition, body } => {
+            record_expression(condition, assigned);
+            record_statement(body, assigned);
+        }
+        Statement::For { init, condition, update, body } => {
+            for expression in [init, condition, update].into_iter().flatten() {
+                record_expression(expression, assigned);
+            }
+            record_statement(body, assigned);
+        }
+        Statement::ForIn { variable, array, body } => {
+            assigned.insert(variable.clone());
+            record_expression(array, assigned);
+            record_statement(body, assigned);
+        }
+        Statement::Print(print) => {
+            for expression in &print.expressions {
+                record_expression(expression, assigned);
+            }
+        }
+        Statement::Printf(printf) => {
+            record_expression(&printf.format, assigned);
+            for expression in &printf.arguments {
+                record_expression(expression, assigned);
+            }
+        }
+        Statement::Break | Statement::Continue | Statement::Next => {}
+    }
+}
+
+fn record_expression(expression: &Expression, assigned: &mut HashSet<String>) {
+    let mut record = |target: &Expression| {
+        if let Some(name) = assigned_name(target) {
+            assigned.insert(name.to_string());
+        }
+    };
+    match expression {
+        Expression::Assign(target, _)
+        | Expression::AddAssign(target, _)
+        | Expression::SubtractAssign(target, _)
+        | Expression::MultiplyAssign(target, _)
+        | Expression::DivideAssign(target, _)
+        | Expression::ModuloAssign(target, _)
+        | Expression::PowerAssign(target, _)
+        | Expression::PreIncrement(target)
+        | Expression::PostIncrement(target)
+        | Expression::PreDecrement(target)
+        | Expression::PostDecrement(target) => record(target),
+        Expression::Getline { target: Some(target), .. } => record(target),
+        // Arrays are passed by reference (split, match, user functions)
+        // and sub/gsub assign their target
+        Expression::FunctionCall { arguments, .. } => arguments.iter().for_each(record),
+        _ => {}
+    }
+    for child in children(expression) {
+        record_expression(child, assigned);
+    }
+}
+
+/// The direct subexpressions of `expression`
+fn children(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::Regex(_) => Vec::new(),
+        Expression::FieldRef(inner)
+        | Expression::UnaryMinus(inner)
+        | Expression::UnaryPlus(inner)
+        | Expression::Not(inner)
+        | Expression::PreIncrement(inner)
+        | Expression::PostIncrement(inner)
+        | Expression::PreDecrement(inner)
+        | Expression::PostDecrement(inner) => vec![inner],
+        Expression::ArrayRef { array, index } => vec![array, index],
+        Expression::Add(left, right)
+        | Expression::Subtract(left, right)
+        | Expression::Multiply(left, right)
+        | Expression::Divide(left, right)
+        | Expression::Modulo(left, right)
+        | Expression::Power(left, right)
+        | Expression::Equal(left, right)
+        | Expression::NotEqual(left, right)
+        | Expression::Less(left, right)
+        | Expression::LessEqual(left, right)
+        | Expression::Greater(left, right)
+        | Expression::GreaterEqual(left, right)
+        | Expression::Match(left, right)
+        | Expression::NotMatch(left, right)
+        | Expression::And(left, right)
+        | Expression::Or(left, right)
+        | Expression::Concatenate(left, right)
+        | Expression::In(left, right)
+        | Expression::Assign(left, right)
+        | Expression::AddAssign(left, right)
+        | Expression::SubtractAssign(left, right)
+        | Expression::MultiplyAssign(left, right)
+        | Expression::DivideAssign(left, right)
+        | Expression::ModuloAssign(left, right)
+        | Expression::PowerAssign(left, right) => vec![left, right],
+        Expression::Ternary { condition, true_expr, false_expr } => vec![condition, true_expr, false_expr],
+        Expression::FunctionCall { arguments, .. } => arguments.iter().collect(),
+        Expression::Getline { target, source } => target
+            .iter()
+            .map(|inner| inner.as_ref())
+            .chain(source.as_ref().map(GetlineSource::expression))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn warnings(script: &str) -> Vec<String> {
+        let program = Parser::new(script).unwrap().parse().unwrap();
+        lint(&program, &[], &["limit"]).into_iter().map(|warning| warning.message).collect()
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let script = r#"
+            function add(a, b) { return a + b }
+            { total = add(total, $1); if (total > limit) print total }
+            END { for (k in seen) print k }
+        "#;
+        assert!(warnings(script).is_empty(), "{:?}", warnings(script));
+    }
+
+    #[test]
+    fn test_lint_warnings() {
+        let found = warnings(r#"
+            BEGIN { RS = "--"; FPAT = "[^,]+" }
+            { if (count > 3) print; helper($1); next; print "never" }
+            0 { print }
+            { print "after next" }
+        "#);
+        assert_eq!(found, [
+            "multicharacter RS is a gawk extension (BEGIN rule)",
+            "`FPAT' is a gawk extension (BEGIN rule)",
+            "`count' is compared in rule 1 but never assigned",
+            "function `helper' called but never defined (rule 1)",
+            "unreachable statement after `next' in rule 1",
+            "rule 2 never runs: its pattern is always false",
+            "rule 2 is unreachable: rule 1 always ends with next or exit",
+            "rule 3 is unreachable: rule 1 always ends with next or exit",
+        ]);
+
+        let exits = warnings("BEGIN { exit } { print } function f(x) { return x; x++ } END { f(1, 2) }");
+        assert_eq!(exits, [
+            "rule 1 is unreachable: a BEGIN rule always exits",
+            "function `f' called with 2 arguments but declares 1 (END rule)",
+            "unreachable statement after `return' in function `f'",
+        ]);
+    }
+}
@@ -1,24 +1,19 @@
-mod ast;
 mod cli;
-mod errors;
-mod interpreter;
-mod lexer;
-mod parser;
-mod runtime;
-mod value;
 
 use clap::Parser;
-use cli::Args;
-use errors::{FastAwkError, Result};
-use interpreter::Interpreter;
-use parser::Parser as AwkParser;
+use cli::{Args, InputFormat, LintMode};
+use fast_awk::include::{parse_units, Includer};
+use fast_awk::record::{MappedRecords, RecordReader, RecordSource};
+use fast_awk::sandbox::Policy;
+use fast_awk::{ast, json, value, FastAwkError, Interpreter, Result};
 use std::fs::File;
 use std::io::{BufRead, BufReader, stdin};
 
 fn main() {
     if let Err(e) = run() {
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        // POSIX awk reserves exit status 2 for fatal errors
+        std::process::exit(2);
     }
 }
 
@@ -38,9 +33,9 @@ fn run() -> Result<()> {
         return Ok(());
     }
 
-    // Parse the script
-    let mut parser = AwkParser::new(&script)?;
-    let program = parser.parse().map_err(|e| {
+    // Expand @include directives, then parse the script and its libraries
+    let units = Includer::from_env().expand(&args.script_name(), &script, args.script_file.as_deref())?;
+    let program = parse_units(&units).map_err(|e| {
         FastAwkError::syntax_error(format!("Script parsing failed: {}", e))
     })?;
 
@@ -54,11 +49,27 @@ fn run() -> Result<()> {
 
     // Create interpreter
     let mut interpreter = Interpreter::new();
+    interpreter.context.bignum = args.bignum;
+    interpreter.context.binary = args.binary;
+    if args.sandbox {
+        interpreter.context.policy = Policy::sandbox();
+    }
 
     // Initialize with command-line variable assignments
     let variable_assignments = args.parse_variable_assignments().map_err(|e| {
         FastAwkError::syntax_error(format!("Invalid variable assignment: {}", e))
     })?;
+
+    if let Some(mode) = args.lint {
+        let assigned: Vec<&str> = variable_assignments.iter().map(|(name, _)| name.as_str()).collect();
+        let warnings = interpreter.lint(&program, &assigned);
+        for warning in &warnings {
+            eprintln!("fawk: warning: {}", warning);
+        }
+        if mode == LintMode::Fatal && !warnings.is_empty() {
+            return Err(FastAwkError::syntax_error(format!("--lint=fatal: {} warning(s)", warnings.len())));
+        }
+    }
     interpreter.context.initialize_with_args(&variable_assignments)?;
 
     // Set built-in variables from command line
@@ -81,17 +92,11 @@ fn run() -> Result<()> {
     }
     interpreter.execute_program(&program)?;
 
-    // Check if we should exit early (e.g., from BEGIN block)
-    if let Some(exit_code) = interpreter.context.exit_code {
-        if args.verbose && !args.quiet {
-            eprintln!("Exiting with code: {}", exit_code);
-        }
-        std::process::exit(exit_code);
-    }
-
-    // Process input files or stdin
+    // Process input files or stdin; `exit` in BEGIN skips the input but
+    // the END rules still run
     let main_rules = program.get_main_rules();
-    if !main_rules.is_empty() || program.has_end_rules() {
+    let exited = interpreter.context.exit_code.is_some();
+    if !exited && (!main_rules.is_empty() || program.has_end_rules()) {
         if args.files.is_empty() {
             // Read from stdin
             if args.verbose && !args.quiet {
@@ -111,8 +116,19 @@ fn run() -> Result<()> {
                     FastAwkError::file_not_found(file_path.clone())
                 })?;
                 
-                let reader = BufReader::with_capacity(args.buffer_size_bytes(), file);
-                process_reader(&mut interpreter, &program, &args, reader, &file_path.display().to_string())?;
+                let source_name = file_path.display().to_string();
+                let mapped = if args.no_mmap {
+                    None
+                } else {
+                    MappedRecords::open(&file)?
+                };
+                match mapped {
+                    Some(records) => process_records(&mut interpreter, &program, &args, records, &source_name)?,
+                    None => {
+                        let reader = BufReader::with_capacity(args.buffer_size_bytes(), file);
+                        process_reader(&mut interpreter, &program, &args, reader, &source_name)?;
+                    }
+                }
                 
                 // Check for exit condition
                 if interpreter.context.exit_code.is_some() {
@@ -129,6 +145,7 @@ fn run() -> Result<()> {
         }
         interpreter.execute_end_rules(&program)?;
     }
+    interpreter.close_streams();
 
     // Exit with appropriate code
     let exit_code = interpreter.context.exit_code.unwrap_or(0);
@@ -150,13 +167,23 @@ fn process_reader<R: BufRead>(
     args: &Args,
     reader: R,
     source_name: &str,
+) -> Result<()> {
+    process_records(interpreter, program, args, RecordReader::new(reader), source_name)
+}
+
+fn process_records<S: RecordSource>(
+    interpreter: &mut Interpreter,
+    program: &ast::Program,
+    args: &Args,
+    mut records: S,
+    source_name: &str,
 ) -> Result<()> {
     let mut records_processed = 0;
     let mut records_skipped = 0;
 
-    for (_line_number, line_result) in reader.lines().enumerate() {
-        let line = line_result?;
-        
+    while let Some((record, terminator)) = records.next_terminated(&interpreter.context.rs)? {
+        interpreter.context.set_record_terminator(terminator);
+
         // Handle skip_records
         if let Some(skip_count) = args.skip_records {
             if records_processed < skip_count {
@@ -174,7 +201,29 @@ fn process_reader<R: BufRead>(
         }
 
         // Process the record
-        let _any_matched = interpreter.execute_main_rules(program, &line)?;
+        let _any_matched = if args.binary {
+            interpreter.execute_main_rules_bytes(program, record)?
+        } else {
+            let line = std::str::from_utf8(record).map_err(|_| {
+                FastAwkError::encoding_error(format!(
+                    "invalid UTF-8 in record {} of {} (use --binary to process raw bytes)",
+                    records_processed + 1,
+                    source_name
+                ))
+            })?;
+            if args.input_format == InputFormat::Json {
+                let document = json::parse_record(line).map_err(|e| {
+                    FastAwkError::runtime_error(format!(
+                        "invalid JSON in record {} of {}: {}",
+                        records_processed + 1,
+                        source_name,
+                        e
+                    ))
+                })?;
+                interpreter.context.json_record = Some(document);
+            }
+            interpreter.execute_main_rules(program, line)?
+        };
         records_processed += 1;
 
         // Check for control flow
@@ -203,6 +252,7 @@ fn process_reader<R: BufRead>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use fast_awk::Parser as AwkParser;
     use std::io::Cursor;
     use tempfile::NamedTempFile;
     use std::io::Write;
@@ -252,6 +302,12 @@ mod tests {
             skip_records: None,
             posix_mode: false,
             traditional_mode: false,
+            bignum: false,
+            binary: false,
+            no_mmap: false,
+            lint: None,
+            sandbox: false,
+            input_format: cli::InputFormat::Text,
         };
         
         let result = process_reader(&mut interpreter, &program, &args, reader, "test");
@@ -373,6 +429,12 @@ mod tests {
             skip_records: None,
             posix_mode: false,
             traditional_mode: false,
+            bignum: false,
+            binary: false,
+            no_mmap: false,
+            lint: None,
+            sandbox: false,
+            input_format: cli::InputFormat::Text,
         };
         
         let result = process_reader(&mut interpreter, &program, &args, reader, "test_file");
@@ -381,4 +443,39 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_json_input_format() {
+        let script = r#"jget("status") >= 500 { slow += jget("timing.ms") } END { print slow }"#;
+        let program = AwkParser::new(script).unwrap().parse().unwrap();
+        let args = Args::parse_from(["fawk", "-q", "--input-format", "json", script]);
+
+        let mut interpreter = Interpreter::new();
+        let input = Cursor::new(
+            "{\"status\":200,\"timing\":{\"ms\":3}}\n{\"status\":503,\"timing\":{\"ms\":250}}\n\n{\"status\":500,\"timing\":{\"ms\":7.5}}\n",
+        );
+        process_reader(&mut interpreter, &program, &args, input, "test").unwrap();
+        assert_eq!(interpreter.context.nr, 4);
+        assert_eq!(interpreter.context.get_variable("slow").to_number(), 257.5);
+
+        let error = process_reader(&mut interpreter, &program, &args, Cursor::new("{\"status\":\n"), "log").unwrap_err();
+        assert!(error.to_string().contains("invalid JSON in record 1 of log"), "{}", error);
+    }
+
+    #[test]
+    fn test_regex_record_separator() {
+        let script = r#"/^b/ { anchored++ } /c$/ { ends++ } END { print NR }"#;
+        let program = AwkParser::new(script).unwrap().parse().unwrap();
+        let args = Args::parse_from(["fawk", "-q", "--record-separator", "\n\n+", script]);
+
+        let mut interpreter = Interpreter::new();
+        interpreter.context.set_variable("RS", value::Value::from("\n\n+"));
+        let input = Cursor::new("a\nb\n\n\nb c\n\nc\n");
+        process_reader(&mut interpreter, &program, &args, input, "test").unwrap();
+
+        // Anchors apply to each whole record, not to its lines
+        assert_eq!(interpreter.context.nr, 3);
+        assert_eq!(interpreter.context.get_variable("anchored").to_number(), 1.0);
+        assert_eq!(interpreter.context.get_variable("ends").to_number(), 1.0);
+    }
 }
\ No newline at end of file
@@ -6,20 +6,24 @@ use crate::value::Value;
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Parsing the expression list of print or printf, where a `>` outside
+    /// parentheses starts the output redirection
+    in_print: bool,
 }
 
 impl Parser {
     pub fn new(input: &str) -> Result<Self> {
         let mut lexer = Lexer::new(input);
         let tokens = lexer.tokenize()?;
-        Ok(Self { tokens, current: 0 })
+        Ok(Self { tokens, current: 0, in_print: false })
     }
 
     pub fn parse(&mut self) -> Result<Program> {
         let mut program = Program::new();
 
         while !self.is_at_end() {
-            self.skip_newlines();
+            // Rules may also be separated by semicolons: `/a/; /b/ { ... }`
+            while self.match_token(&Token::Newline) || self.match_token(&Token::Semicolon) {}
             if self.is_at_end() {
                 break;
             }
@@ -76,7 +80,11 @@ impl Parser {
 
     fn parse_rule(&mut self) -> Result<Rule> {
         let pattern = self.parse_pattern()?;
-        self.skip_newlines();
+        // A newline ends a pattern with no action, as in awk; BEGIN and END
+        // always take one, so their brace may still go on the next line
+        if matches!(pattern, None | Some(Pattern::Begin) | Some(Pattern::End)) {
+            self.skip_newlines();
+        }
         
         let action = if self.check(&Token::LeftBrace) {
             self.parse_action()?
@@ -179,6 +187,11 @@ impl Parser {
                 self.consume_statement_terminator()?;
                 Ok(Statement::Delete(expr))
             }
+            Token::Semicolon => {
+                // An empty statement
+                self.advance();
+                Ok(Statement::Block(Vec::new()))
+            }
             Token::Print => self.parse_print_statement(),
             Token::Printf => self.parse_printf_statement(),
             Token::LeftBrace => {
@@ -314,19 +327,18 @@ impl Parser {
         
         let mut expressions = Vec::new();
         
-        if !self.check_statement_terminator() && !self.check(&Token::Greater) && !self.check(&Token::Or) {
-            expressions.push(self.parse_expression()?);
-            
-            while self.match_token(&Token::Comma) {
-                expressions.push(self.parse_expression()?);
-            }
+        if !self.check_statement_terminator() && !self.check_output_target() {
+            self.in_print = true;
+            let parsed = self.parse_print_list(&mut expressions);
+            self.in_print = false;
+            parsed?;
         }
         
-        let output_target = self.parse_output_target()?;
+        let output_targets = self.parse_output_targets()?;
         self.consume_statement_terminator()?;
         
         let mut print_stmt = PrintStatement::with_expressions(expressions);
-        if let Some(target) = output_target {
+        for target in output_targets {
             print_stmt = print_stmt.with_target(target);
         }
         
@@ -336,34 +348,62 @@ impl Parser {
     fn parse_printf_statement(&mut self) -> Result<Statement> {
         self.consume(Token::Printf, "Expected 'printf'")?;
         
-        let format = self.parse_expression()?;
+        self.in_print = true;
         let mut arguments = Vec::new();
+        let parsed = self.parse_print_list(&mut arguments);
+        self.in_print = false;
+        parsed?;
+        let format = arguments.remove(0);
         
-        while self.match_token(&Token::Comma) {
-            arguments.push(self.parse_expression()?);
-        }
-        
-        let output_target = self.parse_output_target()?;
+        let output_targets = self.parse_output_targets()?;
         self.consume_statement_terminator()?;
         
         let mut printf_stmt = PrintfStatement::new(format).with_arguments(arguments);
-        if let Some(target) = output_target {
+        for target in output_targets {
             printf_stmt = printf_stmt.with_target(target);
         }
         
         Ok(Statement::Printf(printf_stmt))
     }
 
-    fn parse_output_target(&mut self) -> Result<Option<OutputTarget>> {
-        if self.match_token(&Token::Greater) {
-            let expr = self.parse_expression()?;
-            Ok(Some(OutputTarget::File(expr)))
-        } else if self.match_token(&Token::Or) {
-            let expr = self.parse_expression()?;
-            Ok(Some(OutputTarget::Pipe(expr)))
-        } else {
-            Ok(None)
+    /// An expression in brackets or parentheses, where `>` compares even
+    /// inside a print statement
+    fn parse_nested_expression(&mut self) -> Result<Expression> {
+        let in_print = std::mem::replace(&mut self.in_print, false);
+        let expr = self.parse_expression();
+        self.in_print = in_print;
+        expr
+    }
+
+    fn parse_print_list(&mut self, expressions: &mut Vec<Expression>) -> Result<()> {
+        expressions.push(self.parse_expression()?);
+        while self.match_token(&Token::Comma) {
+            expressions.push(self.parse_expression()?);
+        }
+        Ok(())
+    }
+
+    fn check_output_target(&self) -> bool {
+        self.check(&Token::Greater) || self.check(&Token::Append) || self.check(&Token::Pipe)
+    }
+
+    /// The `> file`, `>> file` and `| cmd` redirections ending a print or
+    /// printf; after the first, a `>` starts the next one rather than
+    /// comparing, so `print > "a" > "b"` writes both files
+    fn parse_output_targets(&mut self) -> Result<Vec<OutputTarget>> {
+        let mut targets = Vec::new();
+        while self.check_output_target() {
+            let redirection = self.advance().clone();
+            self.in_print = true;
+            let expr = self.parse_expression();
+            self.in_print = false;
+            targets.push(match redirection {
+                Token::Greater => OutputTarget::File(expr?),
+                Token::Append => OutputTarget::Append(expr?),
+                _ => OutputTarget::Pipe(expr?),
+            });
         }
+        Ok(targets)
     }
 
     fn parse_expression(&mut self) -> Result<Expression> {
@@ -440,9 +480,21 @@ impl Parser {
 
     fn parse_relational(&mut self) -> Result<Expression> {
         let mut expr = self.parse_concatenation()?;
+
+        // `cmd | getline` binds looser than concatenation, so `"ls " dir |
+        // getline` runs the whole command, and tighter than comparison
+        while self.check(&Token::Pipe) && self.peek_ahead(1) == &Token::Getline {
+            self.advance();
+            self.advance();
+            let target = self.parse_getline_target()?;
+            expr = Expression::Getline {
+                target,
+                source: Some(GetlineSource::Command(Box::new(expr))),
+            };
+        }
         
         while self.check(&Token::Less) || self.check(&Token::LessEqual) ||
-              self.check(&Token::Greater) || self.check(&Token::GreaterEqual) ||
+              (self.check(&Token::Greater) && !self.in_print) || self.check(&Token::GreaterEqual) ||
               self.check(&Token::Equal) || self.check(&Token::NotEqual) {
             
             let op = self.advance().clone();
@@ -458,6 +510,10 @@ impl Parser {
                 _ => unreachable!(),
             };
         }
+
+        if self.check(&Token::Append) && !self.in_print {
+            return Err(FastAwkError::syntax_error("'>>' only appends the output of print or printf"));
+        }
         
         Ok(expr)
     }
@@ -552,7 +608,7 @@ impl Parser {
             } else if self.match_token(&Token::Decrement) {
                 expr = Expression::PostDecrement(Box::new(expr));
             } else if self.match_token(&Token::LeftBracket) {
-                let index = self.parse_expression()?;
+                let index = self.parse_nested_expression()?;
                 self.consume(Token::RightBracket, "Expected ']' after array index")?;
                 expr = Expression::ArrayRef {
                     array: Box::new(expr),
@@ -564,9 +620,9 @@ impl Parser {
                     let mut arguments = Vec::new();
                     
                     if !self.check(&Token::RightParen) {
-                        arguments.push(self.parse_expression()?);
+                        arguments.push(self.parse_nested_expression()?);
                         while self.match_token(&Token::Comma) {
-                            arguments.push(self.parse_expression()?);
+                            arguments.push(self.parse_nested_expression()?);
                         }
                     }
                     
@@ -607,6 +663,14 @@ impl Parser {
                 self.advance();
                 Ok(Expression::Literal(Value::Number(value)))
             }
+            Token::Integer(digits) => {
+                let value = Value::from(digits.as_str())
+                    .exact_integer()
+                    .map(Value::Integer)
+                    .unwrap_or(Value::Number(f64::NAN));
+                self.advance();
+                Ok(Expression::Literal(value))
+            }
             Token::String(s) => {
                 let value = s.clone();
                 self.advance();
@@ -624,22 +688,31 @@ impl Parser {
             }
             Token::Dollar => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                // `$` binds tighter than postfix operators and assignment,
+                // so `$2 = x` and `$i++` apply to the field
+                let expr = match self.peek() {
+                    Token::Not | Token::Minus | Token::Plus | Token::Increment | Token::Decrement => {
+                        self.parse_unary()?
+                    }
+                    _ => self.parse_primary()?,
+                };
                 Ok(Expression::FieldRef(Box::new(expr)))
             }
             Token::LeftParen => {
                 self.advance();
-                let expr = self.parse_expression()?;
+                let expr = self.parse_nested_expression()?;
                 self.consume(Token::RightParen, "Expected ')' after expression")?;
                 Ok(expr)
             }
             Token::Getline => {
                 self.advance();
-                // Simplified getline parsing
-                Ok(Expression::Getline {
-                    target: None,
-                    source: None,
-                })
+                let target = self.parse_getline_target()?;
+                let source = if self.match_token(&Token::Less) {
+                    Some(GetlineSource::File(Box::new(self.parse_getline_operand()?)))
+                } else {
+                    None
+                };
+                Ok(Expression::Getline { target, source })
             }
             Token::Divide => {
                 // This could be the start of a regex literal /pattern/
@@ -680,6 +753,31 @@ impl Parser {
         }
     }
 
+    /// The variable after `getline`, if there is one
+    fn parse_getline_target(&mut self) -> Result<Option<Box<Expression>>> {
+        if matches!(self.peek(), Token::Identifier(_) | Token::Dollar) {
+            Ok(Some(Box::new(self.parse_getline_operand()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A getline variable or file name: a name, array element, field or
+    /// literal. As in awk, anything longer, such as a concatenated file
+    /// name, needs parentheses.
+    fn parse_getline_operand(&mut self) -> Result<Expression> {
+        let expr = self.parse_primary()?;
+        if matches!(expr, Expression::Identifier(_)) && self.match_token(&Token::LeftBracket) {
+            let index = self.parse_nested_expression()?;
+            self.consume(Token::RightBracket, "Expected ']' after array index")?;
+            return Ok(Expression::ArrayRef {
+                array: Box::new(expr),
+                index: Box::new(index),
+            });
+        }
+        Ok(expr)
+    }
+
     // Helper methods
     fn is_at_end(&self) -> bool {
         matches!(self.peek(), Token::Eof)
@@ -740,9 +838,10 @@ impl Parser {
     }
 
     fn consume_statement_terminator(&mut self) -> Result<()> {
-        if self.match_token(&Token::Semicolon) || self.match_token(&Token::Newline) {
-            Ok(())
-        } else if matches!(self.peek(), Token::RightBrace | Token::Eof) {
+        if self.match_token(&Token::Semicolon)
+            || self.match_token(&Token::Newline)
+            || matches!(self.peek(), Token::RightBrace | Token::Eof)
+        {
             Ok(())
         } else {
             Err(FastAwkError::syntax_error("Expected ';' or newline"))
@@ -762,7 +861,7 @@ impl Parser {
         // Simplified concatenation detection
         matches!(
             self.peek(),
-            Token::Identifier(_) | Token::String(_) | Token::Number(_) |
+            Token::Identifier(_) | Token::String(_) | Token::Number(_) | Token::Integer(_) |
             Token::Dollar | Token::LeftParen
         )
     }
@@ -806,6 +905,9 @@ mod tests {
             }
             _ => panic!("Expected FieldRef expression"),
         }
+
+        let expr = Parser::new("$2 = \"x\"").unwrap().parse_expression().unwrap();
+        assert!(matches!(expr, Expression::Assign(target, _) if matches!(*target, Expression::FieldRef(_))));
     }
 
     #[test]
@@ -821,4 +923,77 @@ mod tests {
             _ => panic!("Expected FunctionCall expression"),
         }
     }
-}
\ No newline at end of file
+
+    /// Programs gawk and mawk accept, with the number of rules each holds
+    const GRAMMAR_CORPUS: &[(&str, usize)] = &[
+        ("BEGIN { x = 1 + \\\n2; print x }", 1),
+        ("BEGIN { printf \"%s %s\\n\", \\\n \"a\", \"b\" }", 1),
+        ("/a/, \\\n/c/", 1),
+        ("/a/; /c/ { print \"c\" }", 2),
+        ("NR==1; NR==2", 2),
+        ("NR==1\n{ print \"every line\" }", 2),
+        ("{ print $1 }; { print $2 }", 2),
+        ("BEGIN { x = 1 } ; END { print x }", 2),
+        ("BEGIN { x = 1 }\n;END { print x }", 2),
+        ("BEGIN\n{ print }", 1),
+        ("{ print $1 ;}", 1),
+        ("{ print $1 ; ; print $2 }", 1),
+        ("{ if ($1) ; else print }", 1),
+        ("{ print $1 # comment\n}", 1),
+        ("{ print $1 } # trailing comment", 1),
+        ("BEGIN {\n  # only a comment\n}", 1),
+        ("BEGIN { x = 1 # a comment \\\nprint x }", 1),
+        ("{ if ($1 == \"a\") # c\nprint \"yes\"\nelse # e\nprint \"no\" }", 1),
+        ("BEGIN { x = 1 &&\n 2; y = 0 ||\n 1 }", 1),
+        ("BEGIN { print 1,\n 2 }", 1),
+        ("BEGIN { print 1 ? \"y\" :\n \"n\" }", 1),
+        ("BEGIN { print (1 ?\n\"y\" : \"n\") }", 1),
+        ("BEGIN { f(1,\n 2) }\nfunction f(a,\n b) { print a b }", 1),
+        ("function f(a)\n{ return a }\nBEGIN { print f(3) }", 1),
+        ("BEGIN { do # c\n i++\n while (i < 3) }", 1),
+        ("BEGIN { a[\"x\"]; for (k in a) # c\n print k }", 1),
+        ("BEGIN { print 1 } # c\n# d\n\nEND { print 2 }\n", 2),
+        ("{ print $1, $2 > \"out\"; printf \"%s\", $1 > \"out\" }", 1),
+        ("BEGIN { while ((\"echo a; echo b\" | getline line) > 0) n++ }", 1),
+        ("{ \"date\" | getline d; getline x < \"f\"; getline a[NR] < (dir \"/\" $1); print | \"sort\" }", 1),
+        ("BEGIN { \"ls \" dir | getline; getline $2 < FILENAME }", 1),
+    ];
+
+    #[test]
+    fn test_grammar_corpus() {
+        for (source, rules) in GRAMMAR_CORPUS {
+            let program = Parser::new(source)
+                .and_then(|mut parser| parser.parse())
+                .unwrap_or_else(|error| panic!("{:?} failed to parse: {}", source, error));
+            assert_eq!(program.rules.len(), *rules, "rules in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_print_redirection() {
+        let program = Parser::new("{ print ($1 > 2), a[$2 > 1] > \"out\" }").unwrap().parse().unwrap();
+        let Statement::Print(print) = &program.rules[0].action.statements[0] else {
+            panic!("expected print");
+        };
+        assert!(matches!(print.expressions[..], [Expression::Greater(..), Expression::ArrayRef { .. }]));
+        assert!(matches!(print.output_targets[..], [OutputTarget::File(Expression::Literal(_))]));
+
+        let program = Parser::new("{ printf \"%s\", $1 >> \"log\" > $2 \".txt\" | \"sort\" }").unwrap().parse().unwrap();
+        let Statement::Printf(printf) = &program.rules[0].action.statements[0] else {
+            panic!("expected printf");
+        };
+        assert!(matches!(
+            printf.output_targets[..],
+            [OutputTarget::Append(_), OutputTarget::File(Expression::Concatenate(..)), OutputTarget::Pipe(_)]
+        ));
+        assert!(Parser::new("{ x = a >> b }").unwrap().parse().is_err());
+    }
+
+    #[test]
+    fn test_empty_statements() {
+        let program = Parser::new("{ ; print ;; }").unwrap().parse().unwrap();
+        let statements = &program.rules[0].action.statements;
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[1], Statement::Print(_)));
+    }
+}
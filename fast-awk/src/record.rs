@@ -0,0 +1,424 @@
+use crate::record_match::{find_separator, Separator, SeparatorCache, REGEX_RS_LOOKBACK};
+use memchr::{memchr, memmem};
+use memmap2::Mmap;
+use regex::bytes::Regex;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+
+/// Splits input into records on `RS`, working on raw bytes so that invalid
+/// UTF-8 never aborts reading.
+///
+/// `RS` is passed on every call because the script may change it between
+/// records. A single byte or a longer literal string ends a record, and a
+/// string with regex operators is a regex (see `Separator`); an empty `RS`
+/// selects paragraph mode, where records are separated by blank lines.
+pub trait RecordSource {
+    /// The next record and, separately, the text that ended it, which is
+    /// what `RT` holds: empty for a final record with no separator after
+    /// it, and in paragraph mode every newline up to the next paragraph
+    fn next_terminated(&mut self, rs: &str) -> io::Result<Option<(&[u8], &[u8])>>;
+
+    /// The next record without its terminator; `None` at end of input
+    fn next_record(&mut self, rs: &str) -> io::Result<Option<&[u8]>> {
+        Ok(self.next_terminated(rs)?.map(|(record, _)| record))
+    }
+}
+
+/// Records read from a stream, into one buffer reused for every record
+pub struct RecordReader<R> {
+    reader: R,
+    record: Vec<u8>,
+    separators: SeparatorCache,
+    /// Input read past the separator of the last record, which a regex
+    /// `RS` only finds by reading ahead; read before `reader`
+    carry: Vec<u8>,
+    /// Where the record starts in `record`: a regex `RS` searches with the
+    /// byte before the record in front of it
+    record_start: usize,
+    /// The last byte of the last separator, context for `^` and `\b`
+    previous: Option<u8>,
+    /// The separator that ended the last record
+    terminator: Vec<u8>,
+}
+
+impl<R: BufRead> RecordSource for RecordReader<R> {
+    fn next_terminated(&mut self, rs: &str) -> io::Result<Option<(&[u8], &[u8])>> {
+        self.record.clear();
+        self.terminator.clear();
+        self.record_start = 0;
+        let pending = std::mem::take(&mut self.carry);
+        let mut input = pending.as_slice().chain(&mut self.reader);
+        let record = &mut self.record;
+        let terminator = &mut self.terminator;
+        let found = match self.separators.get(rs) {
+            Separator::Paragraph => {
+                self.previous = Some(b'\n');
+                next_paragraph(&mut input, record, terminator)?
+            }
+            Separator::Byte(separator) => {
+                self.previous = Some(*separator);
+                read_until_byte(&mut input, record, *separator, terminator)?
+            }
+            Separator::Literal(separator) => {
+                self.previous = separator.last().copied();
+                read_until_separator(&mut input, record, separator, terminator)?
+            }
+            Separator::Regex(regex) => {
+                record.extend(self.previous);
+                self.record_start = record.len();
+                let mut scan = RegexScan {
+                    record,
+                    start: self.record_start,
+                    leftover: &mut self.carry,
+                    previous: &mut self.previous,
+                    terminator,
+                };
+                scan.read_until(&mut input, regex)?
+            }
+        };
+        let (unread, _) = input.into_inner();
+        self.carry.extend_from_slice(unread);
+        Ok(found.then_some((&self.record[self.record_start..], &self.terminator[..])))
+    }
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            record: Vec::new(),
+            separators: SeparatorCache::default(),
+            carry: Vec::new(),
+            record_start: 0,
+            previous: None,
+            terminator: Vec::new(),
+        }
+    }
+}
+
+fn read_until_byte(
+    input: &mut impl BufRead,
+    record: &mut Vec<u8>,
+    separator: u8,
+    terminator: &mut Vec<u8>,
+) -> io::Result<bool> {
+    if input.read_until(separator, record)? == 0 {
+        return Ok(false);
+    }
+    if record.last() == Some(&separator) {
+        record.pop();
+        terminator.push(separator);
+    }
+    Ok(true)
+}
+
+fn read_until_separator(
+    input: &mut impl BufRead,
+    record: &mut Vec<u8>,
+    separator: &[u8],
+    terminator: &mut Vec<u8>,
+) -> io::Result<bool> {
+    let last = separator[separator.len() - 1];
+    loop {
+        if input.read_until(last, record)? == 0 {
+            return Ok(!record.is_empty());
+        }
+        if record.ends_with(separator) {
+            record.truncate(record.len() - separator.len());
+            terminator.extend_from_slice(separator);
+            return Ok(true);
+        }
+    }
+}
+
+/// Consume the newlines at the start of `input`, returning how many
+fn skip_newlines(input: &mut impl BufRead) -> io::Result<usize> {
+    let mut skipped = 0;
+    loop {
+        let buffer = input.fill_buf()?;
+        let newlines = buffer.iter().take_while(|&&b| b == b'\n').count();
+        let done = buffer.is_empty() || newlines < buffer.len();
+        input.consume(newlines);
+        skipped += newlines;
+        if done {
+            return Ok(skipped);
+        }
+    }
+}
+
+fn next_paragraph(input: &mut impl BufRead, record: &mut Vec<u8>, terminator: &mut Vec<u8>) -> io::Result<bool> {
+    // Blank lines before the paragraph
+    skip_newlines(input)?;
+    if input.fill_buf()?.is_empty() {
+        return Ok(false);
+    }
+
+    // Read line by line until a blank one, which is then dropped along
+    // with any more blank lines after it
+    loop {
+        let start = record.len();
+        if input.read_until(b'\n', record)? == 0 {
+            break;
+        }
+        if &record[start..] == b"\n" {
+            record.truncate(start);
+            terminator.push(b'\n');
+            let more = skip_newlines(input)?;
+            terminator.resize(terminator.len() + more, b'\n');
+            break;
+        }
+    }
+    if record.last() == Some(&b'\n') {
+        record.pop();
+        terminator.insert(0, b'\n');
+    }
+    Ok(true)
+}
+
+/// A regex `RS` search over a stream: whole reads are appended to the
+/// record and searched, going back `REGEX_RS_LOOKBACK` bytes each time, and
+/// what follows the separator is left over for the next record
+struct RegexScan<'a> {
+    record: &'a mut Vec<u8>,
+    start: usize,
+    leftover: &'a mut Vec<u8>,
+    previous: &'a mut Option<u8>,
+    terminator: &'a mut Vec<u8>,
+}
+
+impl RegexScan<'_> {
+    fn read_until(&mut self, input: &mut impl BufRead, regex: &Regex) -> io::Result<bool> {
+        let mut searched = self.start;
+        loop {
+            let buffer = input.fill_buf()?;
+            let complete = buffer.is_empty();
+            let length = buffer.len();
+            self.record.extend_from_slice(buffer);
+            input.consume(length);
+
+            let from = searched.saturating_sub(REGEX_RS_LOOKBACK).max(self.start);
+            if let Some((start, end)) = find_separator(regex, self.record, from, complete) {
+                *self.previous = Some(self.record[end - 1]);
+                self.terminator.extend_from_slice(&self.record[start..end]);
+                self.leftover.extend_from_slice(&self.record[end..]);
+                self.record.truncate(start);
+                return Ok(true);
+            }
+            if complete {
+                return Ok(self.record.len() > self.start);
+            }
+            searched = self.record.len();
+        }
+    }
+}
+
+/// Records sliced straight out of a memory-mapped file, found with memchr
+/// and never copied
+pub struct MappedRecords {
+    map: Mmap,
+    position: usize,
+    separators: SeparatorCache,
+}
+
+impl MappedRecords {
+    /// Map `file` if it is a non-empty regular file; pipes, terminals and
+    /// empty files (which cannot be mapped) are read as streams instead
+    pub fn open(file: &File) -> io::Result<Option<Self>> {
+        let metadata = file.metadata()?;
+        if !metadata.is_file() || metadata.len() == 0 {
+            return Ok(None);
+        }
+        // SAFETY: the map is only read. As with any mmap, another process
+        // truncating the file while it is read can still fault.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(Some(Self {
+            map,
+            position: 0,
+            separators: SeparatorCache::default(),
+        }))
+    }
+}
+
+impl RecordSource for MappedRecords {
+    fn next_terminated(&mut self, rs: &str) -> io::Result<Option<(&[u8], &[u8])>> {
+        Ok(next_slice_record(&self.map, &mut self.position, self.separators.get(rs)))
+    }
+}
+
+/// Split the record starting at `*position` off `data`, with the same rules
+/// as `RecordReader`, returning it and its terminator. A regex separator is
+/// searched in all of `data`, so that `^` only matches at its start.
+fn next_slice_record<'a>(
+    data: &'a [u8],
+    position: &mut usize,
+    separator: &Separator,
+) -> Option<(&'a [u8], &'a [u8])> {
+    if separator.is_paragraph() {
+        // Blank lines before the paragraph (or left over from the last one)
+        *position += data[*position..].iter().take_while(|&&b| b == b'\n').count();
+    }
+    let rest = &data[*position..];
+    if rest.is_empty() {
+        return None;
+    }
+
+    let found = match separator {
+        Separator::Paragraph => memmem::find(rest, b"\n\n").map(|end| (end, 2)),
+        Separator::Byte(separator) => memchr(*separator, rest).map(|end| (end, 1)),
+        Separator::Literal(separator) => memmem::find(rest, separator).map(|end| (end, separator.len())),
+        Separator::Regex(regex) => {
+            find_separator(regex, data, *position, true).map(|(start, end)| (start - *position, end - start))
+        }
+    };
+    let (mut record, mut consumed) = match found {
+        Some((end, separator_len)) => (&rest[..end], end + separator_len),
+        None => (rest, rest.len()),
+    };
+    if separator.is_paragraph() {
+        // The blank line takes the blank lines after it along
+        consumed += rest[consumed..].iter().take_while(|&&b| b == b'\n').count();
+    }
+    let mut terminator = &rest[record.len()..consumed];
+    *position += consumed;
+
+    // An unterminated paragraph still loses its final newline
+    if separator.is_paragraph() && found.is_none() {
+        if let Some(stripped) = record.strip_suffix(b"\n") {
+            record = stripped;
+            terminator = &rest[record.len()..consumed];
+        }
+    }
+    Some((record, terminator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor};
+
+    /// Records and their terminators from the stream reader, checked
+    /// against slicing the same input and against reading it a few bytes
+    /// at a time
+    fn terminated(input: &[u8], rs: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let records = read_all(RecordReader::new(Cursor::new(input.to_vec())), rs);
+        let trickled = read_all(RecordReader::new(BufReader::with_capacity(3, input)), rs);
+        assert_eq!(trickled, records, "reading {:?} on {:?} in small pieces", input, rs);
+
+        let separator = Separator::parse(rs);
+        let mut position = 0;
+        let mut sliced = Vec::new();
+        while let Some((record, terminator)) = next_slice_record(input, &mut position, &separator) {
+            sliced.push((record.to_vec(), terminator.to_vec()));
+        }
+        assert_eq!(sliced, records, "slicing {:?} on {:?}", input, rs);
+        records
+    }
+
+    fn records(input: &[u8], rs: &str) -> Vec<Vec<u8>> {
+        terminated(input, rs).into_iter().map(|(record, _)| record).collect()
+    }
+
+    fn read_all(mut reader: impl RecordSource, rs: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut records = Vec::new();
+        while let Some((record, terminator)) = reader.next_terminated(rs).unwrap() {
+            records.push((record.to_vec(), terminator.to_vec()));
+        }
+        records
+    }
+
+    #[test]
+    fn test_single_byte_separator_keeps_invalid_utf8() {
+        let input = b"caf\xe9 ok\nplain\nlast";
+        assert_eq!(
+            records(input, "\n"),
+            vec![b"caf\xe9 ok".to_vec(), b"plain".to_vec(), b"last".to_vec()]
+        );
+        assert_eq!(records(b"a;b;", ";"), vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn test_multi_byte_separator() {
+        assert_eq!(
+            records(b"one--two-three--", "--"),
+            vec![b"one".to_vec(), b"two-three".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_paragraph_mode() {
+        let input = b"\n\na b\nc\n\n\n\nd\n";
+        assert_eq!(records(input, ""), vec![b"a b\nc".to_vec(), b"d".to_vec()]);
+    }
+
+    #[test]
+    fn test_separator_edge_cases() {
+        assert_eq!(records(b"a;;b", ";"), vec![b"a".to_vec(), b"".to_vec(), b"b".to_vec()]);
+        assert_eq!(records(b"--x", "--"), vec![b"".to_vec(), b"x".to_vec()]);
+        assert_eq!(records(b"a\nb\n\nc\n\n", ""), vec![b"a\nb".to_vec(), b"c".to_vec()]);
+        assert_eq!(records(b"x\ny", ""), vec![b"x\ny".to_vec()]);
+        assert!(records(b"\n\n\n", "").is_empty());
+        assert!(records(b"", "\n").is_empty());
+    }
+
+    #[test]
+    fn test_regex_separator() {
+        // Like paragraph mode, but leading blank lines make an empty record
+        // and the final newline is kept
+        assert_eq!(records(b"a\n\nb\n", "\n\n+"), vec![b"a".to_vec(), b"b\n".to_vec()]);
+        assert_eq!(
+            records(b"\n\na\nb\n\n\n\nc\n\n", "\n\n+"),
+            vec![b"".to_vec(), b"a\nb".to_vec(), b"c".to_vec()]
+        );
+        // A run of newlines split across reads is one separator
+        let long = [b"x".repeat(10), b"\n".repeat(7), b"y".repeat(10)].concat();
+        assert_eq!(records(&long, "\n\n+"), vec![b"x".repeat(10), b"y".repeat(10)]);
+
+        assert_eq!(
+            records(b"a, b;c,,d", "[,;] ?"),
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"".to_vec(), b"d".to_vec()]
+        );
+        // `^` anchors to the start of the input, not of each record
+        assert_eq!(records(b"xaxb", "^x"), vec![b"".to_vec(), b"axb".to_vec()]);
+        assert_eq!(records(b"a\r\nb\nc", "\r?\n"), vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+    }
+
+    #[test]
+    fn test_terminators() {
+        let terminators = |input: &[u8], rs: &str| -> Vec<Vec<u8>> {
+            terminated(input, rs).into_iter().map(|(_, terminator)| terminator).collect()
+        };
+        assert_eq!(terminators(b"a\nb", "\n"), vec![b"\n".to_vec(), b"".to_vec()]);
+        assert_eq!(terminators(b"a--b--", "--"), vec![b"--".to_vec(), b"--".to_vec()]);
+        assert_eq!(terminators(b"a\r\nb\nc", "\r?\n"), vec![b"\r\n".to_vec(), b"\n".to_vec(), b"".to_vec()]);
+        // Paragraphs end at every newline up to the next one
+        assert_eq!(terminators(b"a\n\n\n\nb\n", ""), vec![b"\n\n\n\n".to_vec(), b"\n".to_vec()]);
+    }
+
+    #[test]
+    fn test_switch_from_regex_separator() -> io::Result<()> {
+        // What a regex separator read ahead is still read by the next one
+        let mut reader = RecordReader::new(BufReader::with_capacity(4, &b"one\n\ntwo;three\nfour"[..]));
+        assert_eq!(reader.next_record("\n\n+")?, Some(&b"one"[..]));
+        assert_eq!(reader.next_record(";")?, Some(&b"two"[..]));
+        assert_eq!(reader.next_record("\n")?, Some(&b"three"[..]));
+        assert_eq!(reader.next_record("\n+")?, Some(&b"four"[..]));
+        assert_eq!(reader.next_record("\n")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mapped_records_switch_separator() -> io::Result<()> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        std::io::Write::write_all(&mut file, b"a b\nc;d;e--f\n\n\ng")?;
+        let mut records = MappedRecords::open(file.as_file())?.expect("regular files are mapped");
+        assert_eq!(records.next_record("\n")?, Some(&b"a b"[..]));
+        assert_eq!(records.next_record(";")?, Some(&b"c"[..]));
+        assert_eq!(records.next_record(";")?, Some(&b"d"[..]));
+        assert_eq!(records.next_record(";")?, Some(&b"e--f\n\n\ng"[..]));
+        assert_eq!(records.next_record(";")?, None);
+
+        let empty = tempfile::NamedTempFile::new()?;
+        assert!(MappedRecords::open(empty.as_file())?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use regex::bytes::Regex;
+
+/// Input a streamed regex `RS` search goes back over when more input
+/// arrives, so a separator split between two reads is still found whole.
+/// Separators longer than this that straddle a read can be missed.
+pub const REGEX_RS_LOOKBACK: usize = 1024;
+
+/// Bytes that make a multi-character `RS` a regex rather than a string
+const REGEX_OPERATORS: &[u8] = b"\\^$.|?*+()[]{}";
+
+/// How `RS` ends a record
+#[derive(Debug, Clone)]
+pub enum Separator {
+    /// Empty `RS`: paragraph mode, records end at blank lines
+    Paragraph,
+    Byte(u8),
+    /// Several characters without regex operators, found with memmem
+    Literal(Vec<u8>),
+    /// Several characters with regex operators: as in gawk, `RS = "\n\n+"`
+    /// or `RS = "[,;]"` is a regular expression
+    Regex(Regex),
+}
+
+impl Separator {
+    /// A multi-character `RS` that is not a valid regex separates literally
+    pub fn parse(rs: &str) -> Self {
+        match rs.as_bytes() {
+            [] => Separator::Paragraph,
+            [byte] => Separator::Byte(*byte),
+            bytes if !bytes.iter().any(|byte| REGEX_OPERATORS.contains(byte)) => Separator::Literal(bytes.to_vec()),
+            bytes => Regex::new(rs)
+                .map(Separator::Regex)
+                .unwrap_or_else(|_| Separator::Literal(bytes.to_vec())),
+        }
+    }
+
+    pub fn is_paragraph(&self) -> bool {
+        matches!(self, Separator::Paragraph)
+    }
+}
+
+/// The `Separator` for the latest `RS`, compiled again only when the script
+/// assigns a different one
+#[derive(Debug, Clone)]
+pub struct SeparatorCache {
+    rs: String,
+    separator: Separator,
+}
+
+impl SeparatorCache {
+    pub fn get(&mut self, rs: &str) -> &Separator {
+        if self.rs != rs {
+            self.rs = rs.to_string();
+            self.separator = Separator::parse(rs);
+        }
+        &self.separator
+    }
+}
+
+impl Default for SeparatorCache {
+    fn default() -> Self {
+        Self {
+            rs: "\n".to_string(),
+            separator: Separator::Byte(b'\n'),
+        }
+    }
+}
+
+/// The first non-empty match of a regex `RS` in `data` at or after `from`,
+/// as `(start, end)`.
+///
+/// `data` is the input around the record rather than the record alone, so
+/// `^` and `\b` see what precedes `from`: `^` only matches at the very
+/// start of the input, as in gawk. Unless `complete`, the input may go on,
+/// and a match reaching the end of `data` is not trusted, since more input
+/// could lengthen it (`\n\n+` followed by another newline).
+pub fn find_separator(regex: &Regex, data: &[u8], from: usize, complete: bool) -> Option<(usize, usize)> {
+    let mut at = from;
+    while at <= data.len() {
+        let found = regex.find_at(data, at)?;
+        if found.is_empty() {
+            at = found.end() + 1;
+            continue;
+        }
+        if !complete && found.end() == data.len() {
+            return None;
+        }
+        return Some((found.start(), found.end()));
+    }
+    None
+}
+
+/// `RSTART` and `RLENGTH` for the match at bytes `start..end` of `text`:
+/// counted in characters, like `substr` and `index`, so that
+/// `substr(s, RSTART, RLENGTH)` is the match also in non-ASCII text
+pub fn char_span(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let rstart = text[..start].chars().count() + 1;
+    (rstart, text[start..end].chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex_of(rs: &str) -> Regex {
+        match Separator::parse(rs) {
+            Separator::Regex(regex) => regex,
+            other => panic!("{:?} parsed as {:?}", rs, other),
+        }
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        assert!(Separator::parse("").is_paragraph());
+        assert!(matches!(Separator::parse(";"), Separator::Byte(b';')));
+        assert!(matches!(Separator::parse("--"), Separator::Literal(ref s) if s == b"--"));
+        // Unbalanced brackets are taken literally
+        assert!(matches!(Separator::parse("[x"), Separator::Literal(_)));
+        regex_of("\n\n+");
+
+        let mut cache = SeparatorCache::default();
+        assert!(matches!(cache.get("\n"), Separator::Byte(b'\n')));
+        assert!(matches!(cache.get("\r?\n"), Separator::Regex(_)));
+    }
+
+    #[test]
+    fn test_find_separator() {
+        let blank_lines = regex_of("\n\n+");
+        assert_eq!(find_separator(&blank_lines, b"a\nb\n\n\nc", 0, true), Some((3, 6)));
+        // More newlines may follow
+        assert_eq!(find_separator(&blank_lines, b"a\n\n", 0, false), None);
+        assert_eq!(find_separator(&blank_lines, b"a\n\n", 0, true), Some((1, 3)));
+
+        // `^` does not match at the start of a later record
+        let anchored = regex_of("^x|;");
+        assert_eq!(find_separator(&anchored, b"xa;xb", 0, true), Some((0, 1)));
+        assert_eq!(find_separator(&anchored, b"xa;xb", 3, true), None);
+
+        // Empty matches are not separators
+        assert_eq!(find_separator(&regex_of("x*"), b"abxxc", 0, true), Some((2, 4)));
+    }
+
+    #[test]
+    fn test_char_span() {
+        let text = "héllo wörld";
+        let start = text.find("wö").unwrap();
+        assert_eq!(char_span(text, start, start + "wö".len()), (7, 2));
+        assert_eq!(char_span("abc", 0, 0), (1, 0));
+    }
+}
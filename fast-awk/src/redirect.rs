@@ -0,0 +1,157 @@
+//! The files and commands print and printf write to besides stdout.
+//!
+//! `print > file`, `print >> file` and `print | cmd` open their target the
+//! first time it is named and keep it open, so later prints to the same
+//! name add to it: `>` empties a file only when opening it. Targets are
+//! keyed by name until `close(name)`, which for a command closes its input,
+//! waits for it and returns its exit status.
+//!
+//! A statement may name several targets, as in `print > "all" | "sort"`;
+//! its output is formatted once and written to each in turn.
+//!
+//! Writes to files and commands are buffered; they are flushed by `close`,
+//! before `system()` runs a command, and at exit. `/dev/stdout`,
+//! `/dev/stderr` and `-` write straight through, so they keep their order
+//! with unredirected output.
+
+use crate::getline::exit_code;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::process::{Child, Command, Stdio};
+
+/// How an output target is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    /// `>`: created or emptied
+    File,
+    /// `>>`: created or added to
+    Append,
+    /// `|`: fed to `sh -c`
+    Command,
+}
+
+struct Sink {
+    writer: Box<dyn Write>,
+    /// Whether every write is flushed, for the standard streams
+    unbuffered: bool,
+    /// The command reading the output, for `close` to wait for
+    child: Option<Child>,
+}
+
+/// The open output targets, by name
+#[derive(Default)]
+pub struct OutputStreams {
+    open: HashMap<String, Sink>,
+}
+
+impl OutputStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `output` to the file or command `name`, opening it as `kind`
+    /// first if need be
+    pub fn write(&mut self, kind: SinkKind, name: &str, output: &[u8]) -> io::Result<()> {
+        if !self.open.contains_key(name) {
+            let sink = open(kind, name)?;
+            self.open.insert(name.to_string(), sink);
+        }
+        let sink = self.open.get_mut(name).expect("opened above");
+        sink.writer.write_all(output)?;
+        if sink.unbuffered {
+            sink.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flush every open target, so a command run next sees what was written
+    pub fn flush_all(&mut self) -> io::Result<()> {
+        for sink in self.open.values_mut() {
+            sink.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// `close(name)`: the command's exit status, 0 for a file, or `None`
+    /// when nothing by that name is open
+    pub fn close(&mut self, name: &str) -> Option<io::Result<i32>> {
+        let Sink { mut writer, child, .. } = self.open.remove(name)?;
+        let flushed = writer.flush();
+        // The command sees its input end before it is waited for
+        drop(writer);
+        Some(match child {
+            Some(mut child) => child.wait().map(exit_code),
+            None => flushed.map(|_| 0),
+        })
+    }
+
+    /// Close everything still open, waiting for the commands
+    pub fn close_all(&mut self) {
+        let names: Vec<String> = self.open.keys().cloned().collect();
+        for name in names {
+            let _ = self.close(&name);
+        }
+    }
+}
+
+impl Drop for OutputStreams {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
+
+fn open(kind: SinkKind, name: &str) -> io::Result<Sink> {
+    let standard = |writer: Box<dyn Write>| Sink {
+        writer,
+        unbuffered: true,
+        child: None,
+    };
+    let buffered = |writer: Box<dyn Write>, child| Sink {
+        writer: Box::new(BufWriter::new(writer)),
+        unbuffered: false,
+        child,
+    };
+    Ok(match kind {
+        SinkKind::File | SinkKind::Append if name == "-" || name == "/dev/stdout" => standard(Box::new(io::stdout())),
+        SinkKind::File | SinkKind::Append if name == "/dev/stderr" => standard(Box::new(io::stderr())),
+        SinkKind::File => buffered(Box::new(File::create(name)?), None),
+        SinkKind::Append => buffered(Box::new(OpenOptions::new().append(true).create(true).open(name)?), None),
+        SinkKind::Command => {
+            // What the script printed comes before what the command prints
+            io::stdout().flush()?;
+            let mut child = Command::new("sh").arg("-c").arg(name).stdin(Stdio::piped()).spawn()?;
+            let stdin = child.stdin.take().expect("stdin is piped");
+            buffered(Box::new(stdin), Some(child))
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_append_and_command_status() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("out");
+        let name = path.to_str().unwrap();
+        std::fs::write(&path, "old\n").unwrap();
+
+        // `>` empties the file once, then adds to it until closed
+        let mut streams = OutputStreams::new();
+        streams.write(SinkKind::File, name, b"a\n").unwrap();
+        streams.write(SinkKind::File, name, b"b\n").unwrap();
+        assert_eq!(streams.close(name).unwrap().unwrap(), 0);
+        assert!(streams.close(name).is_none());
+        streams.write(SinkKind::Append, name, b"c\n").unwrap();
+        streams.close_all();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+
+        let copy = temp_dir.path().join("copy");
+        let command = format!("cat > '{}'; exit 4", copy.display());
+        streams.write(SinkKind::Command, &command, b"piped\n").unwrap();
+        assert_eq!(streams.close(&command).unwrap().unwrap(), 4);
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "piped\n");
+    }
+}
@@ -1,6 +1,14 @@
+use crate::array::{ArrayArena, ArrayId, AwkArray};
 use crate::errors::{FastAwkError, Result};
+use crate::fields::{self, FieldPattern, FieldSplitting, RecordFields};
+use crate::format::{FormatSpec, DEFAULT_NUMBER_FORMAT};
+use crate::getline;
+use crate::json;
+use crate::record_match::char_span;
+use crate::sandbox::{Capability, Policy};
 use crate::value::Value;
 use regex::Regex;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::{self, Write};
 
@@ -8,16 +16,23 @@ use std::io::{self, Write};
 pub struct RuntimeContext {
     /// User-defined variables
     pub variables: HashMap<String, Value>,
+    /// The arrays that `Value::Array` handles in variables point to
+    pub arrays: ArrayArena,
     /// Built-in variables
     pub built_in_vars: HashMap<String, Value>,
-    /// Current record fields
-    pub fields: Vec<String>,
+    /// Current record and its fields
+    pub fields: RecordFields,
     /// Current record number
     pub nr: usize,
     /// Current filename
     pub filename: String,
     /// Field separator
     pub fs: String,
+    /// FIELDWIDTHS and FPAT as last assigned
+    pub fieldwidths: String,
+    pub fpat: String,
+    /// Whether FS, FIELDWIDTHS or FPAT splits records
+    pub field_splitting: FieldSplitting,
     /// Output field separator
     pub ofs: String,
     /// Record separator
@@ -26,6 +41,10 @@ pub struct RuntimeContext {
     pub ors: String,
     /// SUBSEP (subscript separator)
     pub subsep: String,
+    /// CONVFMT: number to string conversion in concatenation and subscripts
+    pub convfmt: String,
+    /// OFMT: number to string conversion in `print`
+    pub ofmt: String,
     /// RSTART (start of match for match() function)
     pub rstart: usize,
     /// RLENGTH (length of match for match() function)
@@ -38,6 +57,19 @@ pub struct RuntimeContext {
     pub call_stack: Vec<CallFrame>,
     /// Compiled regex cache
     pub regex_cache: HashMap<String, Regex>,
+    /// Arbitrary-precision integer arithmetic (`-M`)
+    pub bignum: bool,
+    /// Raw byte records (`-b`): records are split as bytes and keep them,
+    /// fields are decoded lossily for string operations
+    pub binary: bool,
+    /// Compiled byte regex cache for binary field splitting
+    pub bytes_regex_cache: HashMap<String, regex::bytes::Regex>,
+    /// What the script may do besides reading input and printing
+    /// (`--sandbox` takes away commands and redirections)
+    pub policy: Policy,
+    /// The current record parsed as JSON with `--input-format json`, for
+    /// `jget` and `jout`
+    pub json_record: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -60,21 +92,32 @@ impl RuntimeContext {
     pub fn new() -> Self {
         let mut context = Self {
             variables: HashMap::new(),
+            arrays: ArrayArena::new(),
             built_in_vars: HashMap::new(),
-            fields: Vec::new(),
+            fields: RecordFields::default(),
             nr: 0,
             filename: String::new(),
             fs: " ".to_string(),
+            fieldwidths: String::new(),
+            fpat: "[^[:space:]]+".to_string(),
+            field_splitting: FieldSplitting::Separator,
             ofs: " ".to_string(),
             rs: "\n".to_string(),
             ors: "\n".to_string(),
-            subsep: "\034".to_string(), // ASCII 034 (FS)
+            subsep: "\x1c".to_string(), // ASCII 034 (FS)
+            convfmt: DEFAULT_NUMBER_FORMAT.to_string(),
+            ofmt: DEFAULT_NUMBER_FORMAT.to_string(),
             rstart: 0,
             rlength: 0,
             exit_code: None,
             control_flow: ControlFlow::None,
             call_stack: Vec::new(),
             regex_cache: HashMap::new(),
+            bignum: false,
+            binary: false,
+            bytes_regex_cache: HashMap::new(),
+            policy: Policy::default(),
+            json_record: None,
         };
         
         // Initialize built-in variables
@@ -84,7 +127,7 @@ impl RuntimeContext {
 
     pub fn initialize_with_args(&mut self, variables: &[(String, String)]) -> Result<()> {
         for (name, value) in variables {
-            self.set_variable(name, Value::String(value.clone()));
+            self.set_variable(name, Value::Strnum(value.clone()));
         }
         Ok(())
     }
@@ -92,7 +135,37 @@ impl RuntimeContext {
     pub fn set_current_record(&mut self, record: &str) {
         self.nr += 1;
         self.parse_fields(record);
-        self.update_built_in_vars();
+        self.update_record_vars();
+    }
+
+    /// Start a record read as raw bytes; outside binary mode it must already
+    /// be valid UTF-8 (see `RecordReader`)
+    pub fn set_current_record_bytes(&mut self, record: &[u8]) {
+        if !self.binary {
+            self.set_current_record(&String::from_utf8_lossy(record));
+            return;
+        }
+
+        self.nr += 1;
+        self.parse_raw_fields(record);
+        self.update_record_vars();
+    }
+
+    /// Count a record read by getline into a variable, which leaves `$0`
+    /// and NF alone
+    pub fn count_record(&mut self) {
+        self.nr += 1;
+        self.update_record_vars();
+    }
+
+    /// RT: the separator that ended the record just read
+    pub fn set_record_terminator(&mut self, terminator: &[u8]) {
+        self.set_built_in("RT", Value::String(String::from_utf8_lossy(terminator).into_owned()));
+    }
+
+    /// ERRNO: why the last getline or close failed
+    pub fn set_errno(&mut self, message: String) {
+        self.set_built_in("ERRNO", Value::String(message));
     }
 
     pub fn set_filename(&mut self, filename: String) {
@@ -101,53 +174,122 @@ impl RuntimeContext {
     }
 
     fn parse_fields(&mut self, record: &str) {
-        self.fields.clear();
-        self.fields.push(record.to_string()); // $0 is the entire record
-        
+        let mut spans = self.fields.take_spans();
+        let span = |field: &str| fields::span(record.as_bytes(), field.as_bytes());
+
+        match &self.field_splitting {
+            FieldSplitting::Separator => {}
+            FieldSplitting::Widths(widths) => {
+                spans.extend(fields::split_widths(record, widths).into_iter().map(span));
+                self.fields.set(record.as_bytes(), spans);
+                return;
+            }
+            FieldSplitting::Pattern(pattern) => {
+                let split = pattern.fields(record.as_bytes());
+                spans.extend(split.into_iter().map(|field| fields::span(record.as_bytes(), field)));
+                self.fields.set(record.as_bytes(), spans);
+                return;
+            }
+        }
+
         if self.fs == " " {
             // Default FS: split on whitespace
-            self.fields.extend(
-                record.split_whitespace()
-                    .map(|s| s.to_string())
-            );
+            spans.extend(record.split_whitespace().map(span));
         } else if self.fs.len() == 1 {
             // Single character FS
             let fs_char = self.fs.chars().next().unwrap();
-            self.fields.extend(
-                record.split(fs_char)
-                    .map(|s| s.to_string())
-            );
+            spans.extend(record.split(fs_char).map(span));
         } else {
             // Multi-character FS (treated as regex)
             let fs_clone = self.fs.clone();
             if let Ok(regex) = self.get_regex(&fs_clone) {
-                self.fields.extend(
-                    regex.split(record)
-                        .map(|s| s.to_string())
-                );
+                spans.extend(regex.split(record).map(span));
             } else {
                 // Fallback: literal string split
-                self.fields.extend(
-                    record.split(&self.fs)
-                        .map(|s| s.to_string())
-                );
+                spans.extend(record.split(&self.fs).map(span));
+            }
+        }
+        self.fields.set(record.as_bytes(), spans);
+    }
+
+    fn parse_raw_fields(&mut self, record: &[u8]) {
+        let mut spans = self.fields.take_spans();
+        let span = |field: &[u8]| fields::span(record, field);
+
+        match &self.field_splitting {
+            FieldSplitting::Separator => {}
+            FieldSplitting::Widths(widths) => {
+                spans.extend(fields::split_widths_bytes(record, widths).into_iter().map(span));
+                self.fields.set(record, spans);
+                return;
+            }
+            FieldSplitting::Pattern(pattern) => {
+                spans.extend(pattern.fields(record).into_iter().map(span));
+                self.fields.set(record, spans);
+                return;
             }
         }
+
+        if self.fs == " " {
+            spans.extend(
+                record
+                    .split(|b| b.is_ascii_whitespace())
+                    .filter(|field| !field.is_empty())
+                    .map(span),
+            );
+        } else if let [separator] = *self.fs.as_bytes() {
+            spans.extend(record.split(|&b| b == separator).map(span));
+        } else {
+            let regex = self.get_bytes_regex(&self.fs.clone());
+            spans.extend(regex.split(record).map(span));
+        }
+        self.fields.set(record, spans);
+    }
+
+    /// Multi-character FS as a byte regex, or as a literal if it is not valid
+    fn get_bytes_regex(&mut self, pattern: &str) -> regex::bytes::Regex {
+        if let Some(regex) = self.bytes_regex_cache.get(pattern) {
+            return regex.clone();
+        }
+        let regex = regex::bytes::Regex::new(pattern)
+            .or_else(|_| regex::bytes::Regex::new(&regex::escape(pattern)))
+            .expect("escaped pattern is always valid");
+        self.bytes_regex_cache.insert(pattern.to_string(), regex.clone());
+        regex
     }
 
     fn update_built_in_vars(&mut self) {
-        self.built_in_vars.insert("NR".to_string(), Value::Number(self.nr as f64));
-        self.built_in_vars.insert("NF".to_string(), Value::Number((self.fields.len().saturating_sub(1)) as f64));
+        self.update_record_vars();
         self.built_in_vars.insert("FILENAME".to_string(), Value::String(self.filename.clone()));
         self.built_in_vars.insert("FS".to_string(), Value::String(self.fs.clone()));
+        self.built_in_vars.insert("FIELDWIDTHS".to_string(), Value::String(self.fieldwidths.clone()));
+        self.built_in_vars.insert("FPAT".to_string(), Value::String(self.fpat.clone()));
         self.built_in_vars.insert("OFS".to_string(), Value::String(self.ofs.clone()));
         self.built_in_vars.insert("RS".to_string(), Value::String(self.rs.clone()));
         self.built_in_vars.insert("ORS".to_string(), Value::String(self.ors.clone()));
         self.built_in_vars.insert("SUBSEP".to_string(), Value::String(self.subsep.clone()));
+        self.built_in_vars.insert("CONVFMT".to_string(), Value::String(self.convfmt.clone()));
+        self.built_in_vars.insert("OFMT".to_string(), Value::String(self.ofmt.clone()));
         self.built_in_vars.insert("RSTART".to_string(), Value::Number(self.rstart as f64));
         self.built_in_vars.insert("RLENGTH".to_string(), Value::Number(self.rlength as f64));
     }
 
+    /// Update NR and NF, which change with every record, in place rather
+    /// than allocating their names again
+    fn update_record_vars(&mut self) {
+        self.set_built_in("NR", Value::Number(self.nr as f64));
+        self.set_built_in("NF", Value::Number(self.fields.count() as f64));
+    }
+
+    fn set_built_in(&mut self, name: &'static str, value: Value) {
+        match self.built_in_vars.get_mut(name) {
+            Some(slot) => *slot = value,
+            None => {
+                self.built_in_vars.insert(name.to_string(), value);
+            }
+        }
+    }
+
     pub fn get_variable(&self, name: &str) -> Value {
         // Check built-in variables first
         if let Some(value) = self.built_in_vars.get(name) {
@@ -170,6 +312,26 @@ impl RuntimeContext {
         match name {
             "FS" => {
                 self.fs = value.to_string();
+                self.field_splitting = FieldSplitting::Separator;
+                self.update_built_in_vars();
+            }
+            // An invalid value is reported and leaves the splitting mode alone
+            "FIELDWIDTHS" => {
+                let spec = value.to_string();
+                match fields::parse_field_widths(&spec) {
+                    Ok(widths) => self.field_splitting = FieldSplitting::Widths(widths),
+                    Err(e) => eprintln!("fawk: warning: invalid FIELDWIDTHS value '{}': {}", spec, e),
+                }
+                self.fieldwidths = spec;
+                self.update_built_in_vars();
+            }
+            "FPAT" => {
+                let pattern = value.to_string();
+                match FieldPattern::new(&pattern) {
+                    Ok(compiled) => self.field_splitting = FieldSplitting::Pattern(compiled),
+                    Err(e) => eprintln!("fawk: warning: invalid FPAT value '{}': {}", pattern, e),
+                }
+                self.fpat = pattern;
                 self.update_built_in_vars();
             }
             "OFS" => {
@@ -188,48 +350,122 @@ impl RuntimeContext {
                 self.subsep = value.to_string();
                 self.update_built_in_vars();
             }
-            "NR" | "NF" | "FILENAME" | "RSTART" | "RLENGTH" => {
-                return; // Read-only variables
+            "CONVFMT" => {
+                self.convfmt = value.to_string();
+                self.update_built_in_vars();
             }
-            _ => {
-                // Set in current call frame if in function, otherwise global
-                if let Some(frame) = self.call_stack.last_mut() {
-                    frame.variables.insert(name.to_string(), value);
-                } else {
-                    self.variables.insert(name.to_string(), value);
-                }
+            "OFMT" => {
+                self.ofmt = value.to_string();
+                self.update_built_in_vars();
+            }
+            "NR" | "NF" | "FILENAME" | "RSTART" | "RLENGTH" | "RT" | "ERRNO" => {
+                // Read-only variables
             }
+            // A function's parameters are its locals; every other name is global
+            _ => *self.variable_mut(name) = value,
         }
     }
 
-    pub fn get_field(&self, index: usize) -> String {
-        if index < self.fields.len() {
-            self.fields[index].clone()
-        } else {
-            String::new()
+    /// Bind a parameter of the function being called
+    pub fn set_local(&mut self, name: &str, value: Value) {
+        if let Some(frame) = self.call_stack.last_mut() {
+            frame.variables.insert(name.to_string(), value);
         }
     }
 
-    pub fn set_field(&mut self, index: usize, value: String) {
-        // Extend fields vector if necessary
-        while self.fields.len() <= index {
-            self.fields.push(String::new());
+    /// A new empty array
+    pub fn new_array(&mut self) -> Value {
+        Value::Array(self.arrays.alloc(AwkArray::new()))
+    }
+
+    /// The array variable `name` holds, made an empty array first if it
+    /// holds none yet
+    pub fn array_variable(&mut self, name: &str) -> ArrayId {
+        if let Value::Array(id) = *self.variable_mut(name) {
+            if self.arrays.get(id).is_some() {
+                return id;
+            }
         }
-        
-        self.fields[index] = value;
-        
-        // Rebuild $0 if we're setting a field other than $0
-        if index > 0 {
-            self.rebuild_record();
+        let array = self.new_array();
+        let Value::Array(id) = array else { unreachable!() };
+        *self.variable_mut(name) = array;
+        id
+    }
+
+    /// The array behind `id`, failing for a handle whose array is gone
+    pub fn array_mut(&mut self, id: ArrayId) -> Result<&mut AwkArray> {
+        self.arrays
+            .get_mut(id)
+            .ok_or_else(|| FastAwkError::runtime_error("array used after the function holding it returned"))
+    }
+
+    /// Whether `value` is an array holding `key`
+    pub fn array_contains(&self, value: &Value, key: &str) -> bool {
+        match value {
+            Value::Array(id) => self.arrays.get(*id).is_some_and(|array| array.contains_key(key)),
+            _ => false,
         }
-        
-        self.update_built_in_vars();
     }
 
-    fn rebuild_record(&mut self) {
-        if self.fields.len() > 1 {
-            self.fields[0] = self.fields[1..].join(&self.ofs);
+    /// A value as a string under CONVFMT, for concatenation and subscripts
+    pub fn string_value(&self, value: &Value) -> String {
+        value.to_string_with(&self.convfmt)
+    }
+
+    /// A value as a string under OFMT, for `print`
+    pub fn output_value(&self, value: &Value) -> String {
+        value.to_string_with(&self.ofmt)
+    }
+
+    /// Mutable access to a variable for in-place array updates.
+    ///
+    /// Resolves to the current call frame when the name is local there,
+    /// otherwise to the global, creating it as `Undefined` if needed.
+    pub fn variable_mut(&mut self, name: &str) -> &mut Value {
+        if let Some(frame) = self.call_stack.last_mut() {
+            if frame.variables.contains_key(name) {
+                return frame.variables.get_mut(name).expect("checked above");
+            }
+        }
+        self.variables.entry(name.to_string()).or_insert(Value::Undefined)
+    }
+
+    /// Current `PROCINFO["sorted_in"]` setting, if any
+    pub fn sorted_in(&self) -> Option<String> {
+        match self.variables.get("PROCINFO") {
+            Some(Value::Array(procinfo)) => self
+                .arrays
+                .get(*procinfo)?
+                .get("sorted_in")
+                .map(|value| value.to_string())
+                .filter(|order| !order.is_empty()),
+            _ => None,
+        }
+    }
+
+    /// Text of field `index`, borrowed from the record where it can be
+    pub fn get_field(&self, index: usize) -> Cow<'_, str> {
+        self.fields.text(index)
+    }
+
+    /// Assign field `index`. Assigning `$0` splits it again; any other field
+    /// rebuilds `$0` with OFS.
+    pub fn set_field(&mut self, index: usize, value: String) {
+        if index == 0 {
+            if self.binary {
+                self.parse_raw_fields(value.as_bytes());
+            } else {
+                self.parse_fields(&value);
+            }
+        } else {
+            self.fields.assign(index, value.as_bytes(), self.ofs.as_bytes());
         }
+        self.update_record_vars();
+    }
+
+    /// Bytes of field `index`: as read in binary mode, its text otherwise
+    pub fn raw_field(&self, index: usize) -> &[u8] {
+        self.fields.get(index)
     }
 
     pub fn get_regex(&mut self, pattern: &str) -> Result<Regex> {
@@ -249,8 +485,8 @@ impl RuntimeContext {
         });
     }
 
-    pub fn pop_call_frame(&mut self) {
-        self.call_stack.pop();
+    pub fn pop_call_frame(&mut self) -> Option<CallFrame> {
+        self.call_stack.pop()
     }
 
     pub fn set_control_flow(&mut self, flow: ControlFlow) {
@@ -270,14 +506,27 @@ impl RuntimeContext {
         self.control_flow = ControlFlow::Exit(code);
     }
 
+    /// Convert an arithmetic operand: exact integers where possible in
+    /// bignum mode, plain f64 numbers otherwise
+    pub fn numeric(&self, value: Value) -> Value {
+        if self.bignum {
+            value.promote_integer()
+        } else {
+            value.demote_integer()
+        }
+    }
+
     /// Built-in function: length
     pub fn builtin_length(&self, args: &[Value]) -> Result<Value> {
         let string = if args.is_empty() {
             self.get_field(0)
+        } else if let Value::Array(array) = args[0] {
+            // gawk: the number of elements
+            return Ok(Value::Number(self.arrays.get(array).map_or(0, AwkArray::len) as f64));
         } else {
-            args[0].to_string()
+            Cow::Owned(args[0].to_string())
         };
-        Ok(Value::Number(string.len() as f64))
+        Ok(Value::Number(string.chars().count() as f64))
     }
 
     /// Built-in function: substr
@@ -321,8 +570,9 @@ impl RuntimeContext {
         let string = args[0].to_string();
         let substring = args[1].to_string();
         
+        // Counted in characters, like substr
         let position = string.find(&substring)
-            .map(|pos| pos + 1) // AWK uses 1-based indexing
+            .map(|pos| string[..pos].chars().count() + 1) // AWK uses 1-based indexing
             .unwrap_or(0);
         
         Ok(Value::Number(position as f64))
@@ -339,7 +589,11 @@ impl RuntimeContext {
         }
         
         let string = args[0].to_string();
-        let array_name = args[1].to_string();
+        // The array itself, as the interpreter passes it, or the name of one
+        let array = match args[1] {
+            Value::Array(array) if self.arrays.get(array).is_some() => array,
+            ref name => self.array_variable(&name.to_string()),
+        };
         let separator = if args.len() > 2 {
             args[2].to_string()
         } else {
@@ -359,15 +613,12 @@ impl RuntimeContext {
             }
         };
         
-        // Create array
-        let mut array = Value::new_array();
+        let array = self.array_mut(array)?;
+        array.clear();
         for (i, part) in parts.iter().enumerate() {
-            array.set_array_element(&(i + 1).to_string(), Value::String(part.clone()))?;
+            array.insert(&(i + 1).to_string(), Value::Strnum(part.clone()));
         }
         
-        // Set the array variable
-        self.set_variable(&array_name, array);
-        
         Ok(Value::Number(parts.len() as f64))
     }
 
@@ -386,7 +637,7 @@ impl RuntimeContext {
         let target = if args.len() > 2 {
             args[2].to_string()
         } else {
-            self.get_field(0)
+            self.get_field(0).into_owned()
         };
         
         let regex = self.get_regex(&pattern)?;
@@ -416,7 +667,7 @@ impl RuntimeContext {
         let target = if args.len() > 2 {
             args[2].to_string()
         } else {
-            self.get_field(0)
+            self.get_field(0).into_owned()
         };
         
         let regex = self.get_regex(&pattern)?;
@@ -447,8 +698,7 @@ impl RuntimeContext {
         let regex = self.get_regex(&pattern)?;
         
         if let Some(mat) = regex.find(&string) {
-            self.rstart = mat.start() + 1; // AWK uses 1-based indexing
-            self.rlength = mat.len();
+            (self.rstart, self.rlength) = char_span(&string, mat.start(), mat.end());
             self.update_built_in_vars();
             Ok(Value::Number(self.rstart as f64))
         } else {
@@ -479,7 +729,7 @@ impl RuntimeContext {
         let string = if args.is_empty() {
             self.get_field(0)
         } else {
-            args[0].to_string()
+            Cow::Owned(args[0].to_string())
         };
         Ok(Value::String(string.to_uppercase()))
     }
@@ -489,7 +739,7 @@ impl RuntimeContext {
         let string = if args.is_empty() {
             self.get_field(0)
         } else {
-            args[0].to_string()
+            Cow::Owned(args[0].to_string())
         };
         Ok(Value::String(string.to_lowercase()))
     }
@@ -583,7 +833,10 @@ impl RuntimeContext {
                 "requires exactly 1 argument"
             ));
         }
-        Ok(Value::Number(args[0].to_number().trunc()))
+        match self.numeric(args[0].clone()) {
+            integer @ Value::Integer(_) => Ok(integer),
+            value => Ok(Value::Number(value.to_number().trunc())),
+        }
     }
 
     /// Built-in function: rand
@@ -605,6 +858,84 @@ impl RuntimeContext {
         Ok(Value::Number(0.0))
     }
 
+    /// `system(cmd)`: run `cmd` with sh after flushing stdout, so its output
+    /// lands after what was printed before; returns the exit status, or as
+    /// in gawk 256 plus the signal that killed it
+    pub fn builtin_system(&self, args: &[Value]) -> Result<Value> {
+        self.policy.check(Capability::RunCommand)?;
+        let command = args.first().map(|value| self.string_value(value)).unwrap_or_default();
+        io::stdout().flush()?;
+        let status = std::process::Command::new("sh").arg("-c").arg(&command).status()?;
+        Ok(Value::Number(f64::from(getline::exit_code(status))))
+    }
+
+    /// `assert(condition [, message])`: stop the script with an error
+    /// unless `condition` is true; returns 1
+    pub fn builtin_assert(&self, args: &[Value]) -> Result<Value> {
+        if args.first().is_some_and(Value::to_bool) {
+            return Ok(Value::Number(1.0));
+        }
+        let message = args.get(1).map_or_else(|| "assert()".to_string(), |message| self.string_value(message));
+        Err(self.assertion_failed(message))
+    }
+
+    /// `assert_eq(actual, expected [, message])`: the same, unless the two
+    /// compare equal as they would under `==`
+    pub fn builtin_assert_eq(&self, args: &[Value]) -> Result<Value> {
+        let actual = args.first().cloned().unwrap_or(Value::Undefined);
+        let expected = args.get(1).cloned().unwrap_or(Value::Undefined);
+        if actual.compare(&expected) == std::cmp::Ordering::Equal {
+            return Ok(Value::Number(1.0));
+        }
+        let mismatch = format!(
+            "expected \"{}\", got \"{}\"",
+            self.string_value(&expected),
+            self.string_value(&actual)
+        );
+        let message = match args.get(2) {
+            Some(message) => format!("{}: {}", self.string_value(message), mismatch),
+            None => mismatch,
+        };
+        Err(self.assertion_failed(message))
+    }
+
+    /// A failed assertion, placed at the current record while reading input
+    fn assertion_failed(&self, message: String) -> FastAwkError {
+        if self.filename.is_empty() {
+            FastAwkError::assertion_failed(message)
+        } else {
+            FastAwkError::assertion_failed(format!("{}, file {}, record {}", message, self.filename, self.nr))
+        }
+    }
+
+    /// `jget(path [, json])`: the member at `path` of the JSON record, or
+    /// of the JSON text `json`, such as an object an earlier `jget`
+    /// returned; missing members are uninitialized
+    pub fn builtin_jget(&self, args: &[Value]) -> Result<Value> {
+        let path = args.first().map(|value| self.string_value(value)).unwrap_or_default();
+        let parsed;
+        let document = match args.get(1) {
+            Some(text) => {
+                parsed = json::parse_record(&self.string_value(text))?;
+                &parsed
+            }
+            None => match self.json_record {
+                Some(ref record) => record,
+                None => return Ok(Value::Undefined),
+            },
+        };
+        Ok(json::lookup(document, &path).map_or(Value::Undefined, json::to_value))
+    }
+
+    /// `jout([value | key, value, ...])`: print one line of JSON
+    /// (see `json::emit`) followed by ORS
+    pub fn builtin_jout(&self, args: &[Value]) -> Result<Value> {
+        let line = json::emit(self.json_record.as_ref(), args, &self.arrays)?;
+        print!("{}{}", line, self.ors);
+        io::stdout().flush()?;
+        Ok(Value::Undefined)
+    }
+
     /// Format string for printf-style functions
     fn format_string(&self, format: &str, args: &[Value]) -> Result<String> {
         // Simplified printf formatting
@@ -636,8 +967,30 @@ impl RuntimeContext {
                 }
                 
                 if arg_index < args.len() {
-                    let formatted = self.format_value(&spec, &args[arg_index])?;
-                    result.push_str(&formatted);
+                    let mut format = FormatSpec::parse(&spec)
+                        .ok_or_else(|| FastAwkError::invalid_format_specifier(spec.clone()))?;
+                    // `*` takes the width, then the precision, from the arguments
+                    if format.width_from_argument {
+                        let width = args.get(arg_index).map_or(0.0, Value::to_number);
+                        arg_index += 1;
+                        if !format.set_argument_width(width) {
+                            return Err(FastAwkError::invalid_format_specifier(format!("{} (width {})", spec, width)));
+                        }
+                    }
+                    if format.precision_from_argument {
+                        let precision = args.get(arg_index).map_or(0.0, Value::to_number);
+                        arg_index += 1;
+                        if !format.set_argument_precision(precision) {
+                            return Err(FastAwkError::invalid_format_specifier(format!(
+                                "{} (precision {})",
+                                spec, precision
+                            )));
+                        }
+                    }
+                    match args.get(arg_index) {
+                        Some(value) => result.push_str(&self.format_value(&format, &spec, value)?),
+                        None => result.push_str(&spec),
+                    }
                     arg_index += 1;
                 } else {
                     result.push_str(&spec);
@@ -650,48 +1003,77 @@ impl RuntimeContext {
         Ok(result)
     }
 
-    fn format_value(&self, spec: &str, value: &Value) -> Result<String> {
-        let last_char = spec.chars().last().unwrap_or('s');
-        
-        match last_char {
-            'd' | 'i' => Ok(format!("{:.0}", value.to_number())),
-            'o' => Ok(format!("{:o}", value.to_number() as u64)),
-            'x' => Ok(format!("{:x}", value.to_number() as u64)),
-            'X' => Ok(format!("{:X}", value.to_number() as u64)),
-            'f' | 'F' => Ok(format!("{:.6}", value.to_number())),
-            'e' => Ok(format!("{:.6e}", value.to_number())),
-            'E' => Ok(format!("{:.6E}", value.to_number())),
-            'g' => Ok(format!("{:.6}", value.to_number())),
-            'G' => Ok(format!("{:.6}", value.to_number())),
-            'c' => {
-                let n = value.to_number() as u8;
-                Ok((n as char).to_string())
+    fn format_value(&self, format: &FormatSpec, spec: &str, value: &Value) -> Result<String> {
+        let conversion = format.conversion;
+
+        let body = if let Some(integer) = self.integer_format_operand(conversion, value) {
+            match conversion {
+                'o' => format!("{:o}", integer),
+                'x' => format!("{:x}", integer),
+                'X' => format!("{:X}", integer),
+                _ => integer.to_string(),
+            }
+        } else {
+            match conversion {
+                'd' | 'i' => format.format_integer(value.to_number()),
+                'o' => format!("{:o}", value.to_number() as u64),
+                'x' => format!("{:x}", value.to_number() as u64),
+                'X' => format!("{:X}", value.to_number() as u64),
+                'e' | 'E' | 'f' | 'F' | 'g' | 'G' => format.format_float(value.to_number()),
+                // Numbers give the character with that code, strings their first
+                'c' if value.is_number() => char::from_u32(value.to_number() as u32)
+                    .map(String::from)
+                    .unwrap_or_default(),
+                'c' => value.to_string().chars().take(1).collect(),
+                's' => {
+                    let string = self.string_value(value);
+                    match format.precision {
+                        Some(precision) => string.chars().take(precision).collect(),
+                        None => string,
+                    }
+                }
+                _ => return Err(FastAwkError::invalid_format_specifier(spec.to_string())),
             }
-            's' => Ok(value.to_string()),
-            _ => Err(FastAwkError::invalid_format_specifier(spec.to_string())),
+        };
+        Ok(format.pad(body))
+    }
+
+    /// In bignum mode, integer conversions print the exact value
+    fn integer_format_operand(&self, conversion: char, value: &Value) -> Option<num_bigint::BigInt> {
+        if !self.bignum || !"dioxX".contains(conversion) {
+            return None;
+        }
+        match value.clone().promote_integer() {
+            Value::Integer(integer) => Some(integer),
+            _ => None,
         }
     }
 
-    pub fn print_values(&self, values: &[Value]) -> Result<()> {
+    /// What `print` writes for `values`, ORS included
+    pub fn print_output(&self, values: &[Value]) -> String {
         if values.is_empty() {
-            println!("{}", self.get_field(0));
+            format!("{}{}", self.get_field(0), self.ors)
         } else {
             let output = values
                 .iter()
-                .map(|v| v.to_string())
+                .map(|v| self.output_value(v))
                 .collect::<Vec<_>>()
                 .join(&self.ofs);
-            print!("{}{}", output, self.ors);
+            format!("{}{}", output, self.ors)
         }
-        io::stdout().flush()?;
-        Ok(())
     }
 
-    pub fn printf_format(&self, format: &Value, args: &[Value]) -> Result<()> {
-        let formatted = self.format_string(&format.to_string(), args)?;
-        print!("{}", formatted);
-        io::stdout().flush()?;
-        Ok(())
+    /// What `print` writes for already-encoded output items, so raw field
+    /// bytes come out unchanged in binary mode
+    pub fn print_output_bytes(&self, items: &[Vec<u8>]) -> Vec<u8> {
+        let mut output = items.join(self.ofs.as_bytes());
+        output.extend_from_slice(self.ors.as_bytes());
+        output
+    }
+
+    /// What `printf` writes for `format` and `args`
+    pub fn printf_output(&self, format: &Value, args: &[Value]) -> Result<String> {
+        self.format_string(&format.to_string(), args)
     }
 }
 
@@ -705,6 +1087,66 @@ impl Default for RuntimeContext {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_last_assigned_splitting_mode_wins() {
+        let mut ctx = RuntimeContext::new();
+        ctx.set_variable("FIELDWIDTHS", Value::from("2 3"));
+        ctx.set_current_record("ab,cde,f");
+        assert_eq!(ctx.fields.iter().collect::<Vec<_>>(), ["ab", ",cd"]);
+
+        ctx.set_variable("FPAT", Value::from("[a-z]+"));
+        ctx.set_current_record("ab,cde,f");
+        assert_eq!(ctx.fields.iter().collect::<Vec<_>>(), ["ab", "cde", "f"]);
+
+        ctx.set_variable("FS", Value::from("d"));
+        ctx.set_current_record("ab,cde,f");
+        assert_eq!(ctx.fields.iter().collect::<Vec<_>>(), ["ab,c", "e,f"]);
+        assert_eq!(ctx.get_variable("FPAT").to_string(), "[a-z]+");
+    }
+
+    #[test]
+    fn test_binary_fields_keep_raw_bytes() {
+        let mut ctx = RuntimeContext::new();
+        ctx.binary = true;
+        ctx.set_current_record_bytes(b"caf\xe9 \xff\xfe end");
+
+        assert_eq!(ctx.get_field(1), "caf\u{fffd}");
+        assert_eq!(ctx.raw_field(1), b"caf\xe9");
+        assert_eq!(ctx.raw_field(2), b"\xff\xfe");
+        assert_eq!(ctx.get_variable("NF").to_number(), 3.0);
+
+        ctx.set_field(3, "tail".to_string());
+        assert_eq!(ctx.raw_field(0), b"caf\xe9 \xff\xfe tail");
+    }
+
+    #[test]
+    fn test_number_formats() {
+        let mut ctx = RuntimeContext::new();
+        let third = Value::Number(1.0 / 3.0);
+        assert_eq!(ctx.string_value(&third), "0.333333");
+
+        ctx.set_variable("CONVFMT", Value::from("%.2f"));
+        ctx.set_variable("OFMT", Value::from("%.3e"));
+        assert_eq!(ctx.string_value(&third), "0.33");
+        assert_eq!(ctx.output_value(&third), "3.333e-01");
+        assert_eq!(ctx.string_value(&Value::Number(7.0)), "7");
+        assert_eq!(ctx.get_variable("CONVFMT").to_string(), "%.2f");
+
+        let formatted = ctx.format_string("[%6.2f|%-4d|%s]", &[third.clone(), Value::Number(-2.9), third]).unwrap();
+        assert_eq!(formatted, "[  0.33|-2  |0.33]");
+    }
+
+    #[test]
+    fn test_printf_widths_from_arguments() {
+        let ctx = RuntimeContext::new();
+        let numbers = |values: &[f64]| values.iter().copied().map(Value::Number).collect::<Vec<_>>();
+        assert_eq!(ctx.format_string("%*d|", &numbers(&[5.0, 42.0])).unwrap(), "   42|");
+        assert_eq!(ctx.format_string("%-*.*f|", &numbers(&[7.0, 2.0, 1.23456])).unwrap(), "1.23   |");
+        assert_eq!(ctx.format_string("%*d|", &numbers(&[-4.0, 1.0])).unwrap(), "1   |");
+        assert!(ctx.format_string("%99999999999999999999d", &numbers(&[1.0])).is_err());
+        assert!(ctx.format_string("%*d", &numbers(&[1e15, 1.0])).is_err());
+    }
+
     #[test]
     fn test_field_access() {
         let mut ctx = RuntimeContext::new();
@@ -714,6 +1156,13 @@ mod tests {
         assert_eq!(ctx.get_field(1), "hello");
         assert_eq!(ctx.get_field(2), "world");
         assert_eq!(ctx.get_field(3), "test");
+        assert_eq!(ctx.get_field(4), "");
+
+        ctx.set_field(2, "there".to_string());
+        assert_eq!(ctx.get_field(0), "hello there test");
+        ctx.set_field(0, "a  b".to_string());
+        assert_eq!(ctx.get_field(2), "b");
+        assert_eq!(ctx.get_variable("NF").to_number(), 2.0);
     }
 
     #[test]
@@ -728,6 +1177,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_mut)]
     fn test_builtin_functions() {
         let mut ctx = RuntimeContext::new();
         
@@ -758,4 +1208,55 @@ mod tests {
         assert_eq!(ctx.get_field(2), "b");
         assert_eq!(ctx.get_field(3), "c");
     }
+
+    #[test]
+    fn test_bignum_printf() {
+        let mut ctx = RuntimeContext::new();
+        let big = Value::from("123456789012345678901234567890");
+        assert_ne!(ctx.format_string("%d", std::slice::from_ref(&big)).unwrap(), big.to_string());
+
+        ctx.bignum = true;
+        assert_eq!(ctx.format_string("%d", std::slice::from_ref(&big)).unwrap(), big.to_string());
+        assert_eq!(
+            ctx.format_string("%x", &[Value::from("18446744073709551616")]).unwrap(),
+            "10000000000000000"
+        );
+        assert_eq!(ctx.builtin_int(std::slice::from_ref(&big)).unwrap().to_string(), big.to_string());
+    }
+
+    #[test]
+    fn test_jget() {
+        let mut ctx = RuntimeContext::new();
+        assert_eq!(ctx.builtin_jget(&[Value::from("level")]).unwrap(), Value::Undefined);
+
+        ctx.json_record = Some(json::parse_record(r#"{"level":"warn","req":{"ms":41.5}}"#).unwrap());
+        assert_eq!(ctx.builtin_jget(&[Value::from("level")]).unwrap(), Value::from("warn"));
+        let request = ctx.builtin_jget(&[Value::from("req")]).unwrap();
+        assert_eq!(ctx.builtin_jget(&[Value::from("ms"), request]).unwrap(), Value::Number(41.5));
+        assert!(ctx.builtin_jget(&[Value::from("ms"), Value::from("{")]).is_err());
+    }
+
+    #[test]
+    fn test_match_counts_characters() {
+        let mut ctx = RuntimeContext::new();
+        let text = Value::from("naïve café au lait");
+        let found = ctx.builtin_match(&[text.clone(), Value::from("caf.")]).unwrap();
+        assert_eq!(found.to_number(), 7.0);
+        assert_eq!(ctx.get_variable("RLENGTH").to_number(), 4.0);
+
+        let (rstart, rlength) = (ctx.get_variable("RSTART"), ctx.get_variable("RLENGTH"));
+        assert_eq!(ctx.builtin_substr(&[text, rstart, rlength]).unwrap().to_string(), "café");
+    }
+
+    #[test]
+    fn test_length_and_index_count_characters() {
+        let mut ctx = RuntimeContext::new();
+        let text = Value::from("héllo wörld");
+        assert_eq!(ctx.builtin_length(std::slice::from_ref(&text)).unwrap().to_number(), 11.0);
+
+        let index = ctx.builtin_index(&[text.clone(), Value::from("w")]).unwrap();
+        assert_eq!(index.to_number(), 7.0);
+        assert_eq!(ctx.builtin_match(&[text.clone(), Value::from("w")]).unwrap(), index);
+        assert_eq!(ctx.builtin_substr(&[text, index, Value::from(3.0)]).unwrap().to_string(), "wör");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use crate::errors::{FastAwkError, Result};
+
+/// Something a script can do beyond reading its input and writing stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// `system(cmd)`
+    RunCommand,
+    /// `print > file`, `print >> file` and `print | cmd`, and the same for printf
+    RedirectOutput,
+    /// `getline < file` and `cmd | getline`
+    RedirectInput,
+}
+
+impl Capability {
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::RunCommand => "system()",
+            Capability::RedirectOutput => "output redirection",
+            Capability::RedirectInput => "input redirection",
+        }
+    }
+}
+
+/// Which capabilities scripts are granted. Every part of the runtime that
+/// runs commands or opens files asks the policy first, so what a script may
+/// touch is decided in one place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    run_commands: bool,
+    redirect_output: bool,
+    redirect_input: bool,
+}
+
+impl Policy {
+    /// Everything allowed, as in plain awk
+    pub fn unrestricted() -> Self {
+        Self {
+            run_commands: true,
+            redirect_output: true,
+            redirect_input: true,
+        }
+    }
+
+    /// gawk's `--sandbox`: no commands and no files besides the input files
+    /// named on the command line and stdout, so a script from anywhere can
+    /// be run over sensitive data without it leaving a trace elsewhere
+    pub fn sandbox() -> Self {
+        Self {
+            run_commands: false,
+            redirect_output: false,
+            redirect_input: false,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::RunCommand => self.run_commands,
+            Capability::RedirectOutput => self.redirect_output,
+            Capability::RedirectInput => self.redirect_input,
+        }
+    }
+
+    /// An error unless `capability` is granted
+    pub fn check(&self, capability: Capability) -> Result<()> {
+        if self.allows(capability) {
+            Ok(())
+        } else {
+            Err(FastAwkError::sandboxed(capability.describe()))
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_policy() {
+        let capabilities = [Capability::RunCommand, Capability::RedirectOutput, Capability::RedirectInput];
+        assert!(capabilities.iter().all(|&capability| Policy::default().check(capability).is_ok()));
+        assert!(!capabilities.iter().any(|&capability| Policy::sandbox().allows(capability)));
+        assert_eq!(
+            Policy::sandbox().check(Capability::RunCommand).unwrap_err().to_string(),
+            "system() is not allowed in sandbox mode"
+        );
+    }
+}
@@ -1,13 +1,25 @@
-use crate::errors::{FastAwkError, Result};
+use crate::array::ArrayId;
+use crate::errors::Result;
+use crate::format::{self, DEFAULT_NUMBER_FORMAT};
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fmt;
 
+/// Largest magnitude up to which every integer is exactly representable as an f64
+const MAX_EXACT_F64_INTEGER: f64 = 9_007_199_254_740_992.0;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     String(String),
+    /// Input data (fields, `-v` assignments, `split()` elements): a string
+    /// that compares as a number when it looks like one, per POSIX "strnum"
+    Strnum(String),
     Number(f64),
-    Array(HashMap<String, Value>),
+    /// Exact integer, produced in arbitrary-precision (`-M`) mode
+    Integer(BigInt),
+    /// Handle to an array in the runtime's arena; copies share the array
+    Array(ArrayId),
     Undefined,
 }
 
@@ -16,20 +28,20 @@ impl Value {
         Value::String(s.into())
     }
 
-    pub fn new_number(n: f64) -> Self {
-        Value::Number(n)
+    pub fn new_strnum(s: impl Into<String>) -> Self {
+        Value::Strnum(s.into())
     }
 
-    pub fn new_array() -> Self {
-        Value::Array(HashMap::new())
+    pub fn new_number(n: f64) -> Self {
+        Value::Number(n)
     }
 
     pub fn is_string(&self) -> bool {
-        matches!(self, Value::String(_))
+        matches!(self, Value::String(_) | Value::Strnum(_))
     }
 
     pub fn is_number(&self) -> bool {
-        matches!(self, Value::Number(_))
+        matches!(self, Value::Number(_) | Value::Integer(_))
     }
 
     pub fn is_array(&self) -> bool {
@@ -40,141 +52,81 @@ impl Value {
         matches!(self, Value::Undefined)
     }
 
-    /// Convert to string (AWK string conversion rules)
-    pub fn to_string(&self) -> String {
-        match self {
-            Value::String(s) => s.clone(),
-            Value::Number(n) => {
-                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n <= i64::MAX as f64 {
-                    format!("{}", *n as i64)
-                } else {
-                    format!("{}", n)
-                }
-            }
-            Value::Array(_) => "[array]".to_string(),
-            Value::Undefined => "".to_string(),
-        }
-    }
-
     /// Convert to number (AWK numeric conversion rules)
     pub fn to_number(&self) -> f64 {
         match self {
             Value::Number(n) => *n,
-            Value::String(s) => {
-                // AWK numeric conversion: parse leading numeric part
-                let trimmed = s.trim();
-                if trimmed.is_empty() {
-                    return 0.0;
-                }
-
-                // Find the longest prefix that could be a number
-                let mut end_pos = 0;
-                let mut has_dot = false;
-                let mut has_e = false;
-                let chars: Vec<char> = trimmed.chars().collect();
-
-                // Handle leading sign
-                if !chars.is_empty() && (chars[0] == '+' || chars[0] == '-') {
-                    end_pos = 1;
-                }
-
-                while end_pos < chars.len() {
-                    match chars[end_pos] {
-                        '0'..='9' => end_pos += 1,
-                        '.' if !has_dot && !has_e => {
-                            has_dot = true;
-                            end_pos += 1;
-                        }
-                        'e' | 'E' if !has_e && end_pos > 0 => {
-                            has_e = true;
-                            end_pos += 1;
-                            // Handle sign after e/E
-                            if end_pos < chars.len() && (chars[end_pos] == '+' || chars[end_pos] == '-') {
-                                end_pos += 1;
-                            }
-                        }
-                        _ => break,
-                    }
-                }
-
-                if end_pos == 0 || (end_pos == 1 && (chars[0] == '+' || chars[0] == '-')) {
-                    0.0
-                } else {
-                    trimmed[..end_pos].parse().unwrap_or(0.0)
-                }
-            }
-            Value::Array(arr) => arr.len() as f64,
-            Value::Undefined => 0.0,
+            Value::Integer(i) => i.to_f64().unwrap_or(f64::NAN),
+            Value::String(s) | Value::Strnum(s) => numeric_prefix(s).parse().unwrap_or(0.0),
+            // An array has no scalar value
+            Value::Array(_) | Value::Undefined => 0.0,
         }
     }
 
-    /// Convert to boolean (AWK truthiness rules)
-    pub fn to_bool(&self) -> bool {
+    /// The exact integer this value holds, if any: integer values, integral
+    /// numbers within f64's exact range and strings of integer syntax
+    pub fn exact_integer(&self) -> Option<BigInt> {
         match self {
-            Value::String(s) => !s.is_empty(),
-            Value::Number(n) => *n != 0.0,
-            Value::Array(arr) => !arr.is_empty(),
-            Value::Undefined => false,
-        }
-    }
-
-    /// Get array element (creates array if not exists)
-    pub fn get_array_element(&mut self, key: &str) -> &mut Value {
-        match self {
-            Value::Array(ref mut map) => {
-                map.entry(key.to_string()).or_insert(Value::Undefined)
+            Value::Integer(i) => Some(i.clone()),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_EXACT_F64_INTEGER => {
+                Some(BigInt::from(*n as i64))
             }
-            _ => {
-                *self = Value::new_array();
-                if let Value::Array(ref mut map) = self {
-                    map.entry(key.to_string()).or_insert(Value::Undefined)
+            Value::String(s) | Value::Strnum(s) => {
+                let trimmed = s.trim();
+                let digits = trimmed.strip_prefix(['+', '-']).unwrap_or(trimmed);
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    trimmed.parse().ok()
                 } else {
-                    unreachable!()
+                    None
                 }
             }
+            _ => None,
         }
     }
 
-    /// Set array element
-    pub fn set_array_element(&mut self, key: &str, value: Value) -> Result<()> {
+    /// Arithmetic operand conversion for bignum (`-M`) mode: anything whose
+    /// numeric value is an exact integer becomes `Integer`, so it no longer
+    /// goes through f64. Strings use AWK's leading-prefix rule, so "12abc"
+    /// is 12 and "abc" is 0; values with a fractional part are left alone.
+    pub fn promote_integer(self) -> Value {
         match self {
-            Value::Array(ref mut map) => {
-                map.insert(key.to_string(), value);
-                Ok(())
-            }
-            _ => {
-                *self = Value::new_array();
-                if let Value::Array(ref mut map) = self {
-                    map.insert(key.to_string(), value);
-                    Ok(())
+            Value::Integer(_) | Value::Array(_) => self,
+            Value::Undefined => Value::Integer(BigInt::zero()),
+            Value::String(ref s) | Value::Strnum(ref s) => {
+                let prefix = numeric_prefix(s);
+                if prefix.is_empty() {
+                    Value::Integer(BigInt::zero())
+                } else if prefix.contains(['.', 'e', 'E']) {
+                    self
                 } else {
-                    unreachable!()
+                    prefix.parse().map(Value::Integer).unwrap_or(self)
                 }
             }
+            Value::Number(_) => match self.exact_integer() {
+                Some(i) => Value::Integer(i),
+                None => self,
+            },
         }
     }
 
-    /// Check if array has key
-    pub fn has_array_key(&self, key: &str) -> bool {
-        match self {
-            Value::Array(map) => map.contains_key(key),
-            _ => false,
-        }
-    }
-
-    /// Get array keys
-    pub fn array_keys(&self) -> Vec<String> {
+    /// Integer values turned back into plain f64 numbers, for standard mode
+    pub fn demote_integer(self) -> Value {
         match self {
-            Value::Array(map) => map.keys().cloned().collect(),
-            _ => Vec::new(),
+            Value::Integer(i) => Value::Number(i.to_f64().unwrap_or(f64::NAN)),
+            other => other,
         }
     }
 
-    /// Get array length
-    pub fn array_len(&self) -> usize {
+    /// Convert to boolean (AWK truthiness rules)
+    pub fn to_bool(&self) -> bool {
         match self {
-            Value::Array(map) => map.len(),
-            _ => 0,
+            Value::String(s) => !s.is_empty(),
+            // "0" read from input is false, "0.0" too; " x" is true
+            Value::Strnum(s) if looks_numeric(s) => self.to_number() != 0.0,
+            Value::Strnum(s) => !s.is_empty(),
+            Value::Number(n) => *n != 0.0,
+            Value::Integer(i) => !i.is_zero(),
+            Value::Array(_) | Value::Undefined => false,
         }
     }
 
@@ -183,92 +135,127 @@ impl Value {
         self.to_string().cmp(&other.to_string())
     }
 
-    /// AWK numeric comparison
+    /// AWK numeric comparison; exact when both sides are integers
     pub fn compare_numeric(&self, other: &Value) -> std::cmp::Ordering {
+        if let Some((a, b)) = self.integer_operands(other) {
+            return a.cmp(&b);
+        }
         self.to_number().partial_cmp(&other.to_number()).unwrap_or(std::cmp::Ordering::Equal)
     }
 
-    /// AWK comparison (follows AWK rules for string vs numeric comparison)
+    /// AWK comparison: numeric when both operands are numeric (numbers,
+    /// uninitialized values, or strnums that look like numbers), otherwise
+    /// string comparison. String constants never compare numerically, so
+    /// `$1 == 10` is true for "10.0" while `$1 == "10"` is not.
     pub fn compare(&self, other: &Value) -> std::cmp::Ordering {
-        match (self, other) {
-            (Value::Number(_), Value::Number(_)) => self.compare_numeric(other),
-            (Value::String(s1), Value::String(s2)) => {
-                // If both look like numbers, compare numerically
-                if self.looks_like_number() && other.looks_like_number() {
-                    self.compare_numeric(other)
-                } else {
-                    s1.cmp(s2)
-                }
-            }
-            _ => {
-                // Mixed types: compare as strings unless both look like numbers
-                if self.looks_like_number() && other.looks_like_number() {
-                    self.compare_numeric(other)
-                } else {
-                    self.compare_string(other)
-                }
-            }
+        if self.is_numeric_operand() && other.is_numeric_operand() {
+            self.compare_numeric(other)
+        } else {
+            self.compare_string(other)
         }
     }
 
-    /// Check if a string value looks like a number (for comparison purposes)
-    fn looks_like_number(&self) -> bool {
+    /// Whether the value takes part in a comparison as a number
+    pub fn is_numeric_operand(&self) -> bool {
         match self {
-            Value::Number(_) => true,
-            Value::String(s) => {
-                let trimmed = s.trim();
-                !trimmed.is_empty() && (
-                    trimmed.parse::<f64>().is_ok() ||
-                    // Handle hexadecimal
-                    (trimmed.starts_with("0x") || trimmed.starts_with("0X")) &&
-                    i64::from_str_radix(&trimmed[2..], 16).is_ok()
-                )
-            }
-            _ => false,
+            Value::Number(_) | Value::Integer(_) | Value::Undefined => true,
+            Value::Strnum(s) => looks_numeric(s),
+            Value::String(_) | Value::Array(_) => false,
         }
     }
 
+    /// Both operands as exact integers, when at least one is an `Integer`
+    /// and the other holds an exact integer too
+    fn integer_operands(&self, other: &Value) -> Option<(BigInt, BigInt)> {
+        if !matches!(self, Value::Integer(_)) && !matches!(other, Value::Integer(_)) {
+            return None;
+        }
+        Some((self.exact_integer()?, other.exact_integer()?))
+    }
+
     /// Arithmetic addition
     pub fn add(&self, other: &Value) -> Result<Value> {
+        if let Some((a, b)) = self.integer_operands(other) {
+            return Ok(Value::Integer(a + b));
+        }
         Ok(Value::Number(self.to_number() + other.to_number()))
     }
 
     /// Arithmetic subtraction
     pub fn subtract(&self, other: &Value) -> Result<Value> {
+        if let Some((a, b)) = self.integer_operands(other) {
+            return Ok(Value::Integer(a - b));
+        }
         Ok(Value::Number(self.to_number() - other.to_number()))
     }
 
     /// Arithmetic multiplication
     pub fn multiply(&self, other: &Value) -> Result<Value> {
+        if let Some((a, b)) = self.integer_operands(other) {
+            return Ok(Value::Integer(a * b));
+        }
         Ok(Value::Number(self.to_number() * other.to_number()))
     }
 
-    /// Arithmetic division
+    /// Arithmetic division; integers stay exact only when they divide evenly.
+    /// Dividing by zero gives an infinity, or NaN for 0/0, rather than
+    /// stopping the program.
     pub fn divide(&self, other: &Value) -> Result<Value> {
         let divisor = other.to_number();
         if divisor == 0.0 {
-            return Err(FastAwkError::DivisionByZero);
+            return Ok(Value::Number(self.to_number() / divisor));
+        }
+        if let Some((a, b)) = self.integer_operands(other) {
+            if (&a % &b).is_zero() {
+                return Ok(Value::Integer(a / b));
+            }
         }
         Ok(Value::Number(self.to_number() / divisor))
     }
 
-    /// Arithmetic modulo
+    /// Arithmetic modulo; NaN for a zero divisor
     pub fn modulo(&self, other: &Value) -> Result<Value> {
         let divisor = other.to_number();
         if divisor == 0.0 {
-            return Err(FastAwkError::DivisionByZero);
+            return Ok(Value::Number(self.to_number() % divisor));
+        }
+        if let Some((a, b)) = self.integer_operands(other) {
+            // Truncated remainder, matching fmod
+            return Ok(Value::Integer(a % b));
         }
         Ok(Value::Number(self.to_number() % divisor))
     }
 
-    /// Arithmetic power
+    /// Arithmetic power; exact for integer bases with non-negative exponents
     pub fn power(&self, other: &Value) -> Result<Value> {
+        if let Some((base, exponent)) = self.integer_operands(other) {
+            if let Some(exponent) = exponent.to_u32() {
+                return Ok(Value::Integer(base.pow(exponent)));
+            }
+        }
         Ok(Value::Number(self.to_number().powf(other.to_number())))
     }
 
+    /// Arithmetic negation
+    pub fn negate(&self) -> Value {
+        match self {
+            Value::Integer(i) => Value::Integer(-i),
+            _ => Value::Number(-self.to_number()),
+        }
+    }
+
     /// String concatenation
     pub fn concatenate(&self, other: &Value) -> Value {
-        Value::String(format!("{}{}", self.to_string(), other.to_string()))
+        Value::String(format!("{}{}", self, other))
+    }
+
+    /// String conversion with `format` (CONVFMT or OFMT) for non-integral
+    /// numbers; `Display` uses the default `%.6g`
+    pub fn to_string_with(&self, format: &str) -> String {
+        match self {
+            Value::Number(n) => format::format_number(*n, format),
+            _ => self.to_string(),
+        }
     }
 
     /// Regular expression match
@@ -290,16 +277,77 @@ impl Value {
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::String(_) => "string",
-            Value::Number(_) => "number",
+            Value::Strnum(_) => "strnum",
+            Value::Number(_) | Value::Integer(_) => "number",
             Value::Array(_) => "array",
             Value::Undefined => "undefined",
         }
     }
 }
 
+/// String conversion follows AWK rules: integral numbers print without a
+/// fractional part, others through the default CONVFMT, and undefined
+/// values print as the empty string.
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            Value::String(s) | Value::Strnum(s) => f.write_str(s),
+            Value::Number(n) => f.write_str(&format::format_number(*n, DEFAULT_NUMBER_FORMAT)),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Array(_) => f.write_str("[array]"),
+            Value::Undefined => Ok(()),
+        }
+    }
+}
+
+/// POSIX numeric string: after trimming blanks, the whole text is a
+/// decimal integer or floating-point constant. Hex and "inf"/"nan" stay
+/// strings, as in gawk and mawk.
+fn looks_numeric(s: &str) -> bool {
+    let trimmed = s.trim_matches([' ', '\t', '\n']);
+    !trimmed.is_empty()
+        && numeric_prefix(trimmed).len() == trimmed.len()
+        && trimmed.bytes().any(|b| b.is_ascii_digit())
+}
+
+/// The longest leading part of `s` that forms a number, after trimming
+/// whitespace; empty when there is none (so the value is 0)
+fn numeric_prefix(s: &str) -> &str {
+    let trimmed = s.trim();
+    let bytes = trimmed.as_bytes();
+    let mut end_pos = 0;
+    let mut has_dot = false;
+    let mut has_e = false;
+
+    // Handle leading sign
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        end_pos = 1;
+    }
+    let digits_start = end_pos;
+
+    while end_pos < bytes.len() {
+        match bytes[end_pos] {
+            b'0'..=b'9' => end_pos += 1,
+            b'.' if !has_dot && !has_e => {
+                has_dot = true;
+                end_pos += 1;
+            }
+            b'e' | b'E' if !has_e && end_pos > digits_start => {
+                has_e = true;
+                end_pos += 1;
+                // Handle sign after e/E
+                if matches!(bytes.get(end_pos), Some(b'+' | b'-')) {
+                    end_pos += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+
+    if end_pos == digits_start {
+        ""
+    } else {
+        &trimmed[..end_pos]
     }
 }
 
@@ -373,6 +421,11 @@ mod tests {
         let val = Value::Number(42.5);
         assert_eq!(val.to_string(), "42.5");
 
+        let val = Value::Number(0.1 + 0.2);
+        assert_eq!(val.to_string(), "0.3");
+        assert_eq!(val.to_string_with("%.2f"), "0.30");
+        assert_eq!(Value::Number(3.0).to_string_with("%.2f"), "3");
+
         let val = Value::Number(0.0);
         assert!(!val.to_bool());
     }
@@ -387,6 +440,13 @@ mod tests {
         assert_eq!(a.multiply(&b).unwrap(), Value::Number(30.0));
         assert_eq!(a.divide(&b).unwrap().to_number(), 10.0 / 3.0);
         assert_eq!(a.modulo(&b).unwrap(), Value::Number(1.0));
+
+        // Division by zero yields IEEE values instead of failing
+        let zero = Value::Number(0.0);
+        assert_eq!(a.divide(&zero).unwrap().to_string(), "+inf");
+        assert_eq!(a.negate().divide(&zero).unwrap().to_string(), "-inf");
+        assert!(zero.divide(&zero).unwrap().to_number().is_nan());
+        assert!(a.modulo(&zero).unwrap().to_number().is_nan());
     }
 
     #[test]
@@ -405,16 +465,45 @@ mod tests {
     }
 
     #[test]
-    fn test_array_operations() {
-        let mut arr = Value::new_array();
-        
-        arr.set_array_element("key1", Value::String("value1".to_string())).unwrap();
-        assert!(arr.has_array_key("key1"));
-        
-        let element = arr.get_array_element("key1");
-        assert_eq!(*element, Value::String("value1".to_string()));
-        
-        assert_eq!(arr.array_len(), 1);
-        assert!(arr.array_keys().contains(&"key1".to_string()));
+    fn test_strnum_comparison() {
+        use std::cmp::Ordering;
+
+        // String constants compare as strings even when they look numeric
+        assert_eq!(Value::from("10").compare(&Value::from("9")), Ordering::Less);
+        assert_eq!(Value::new_strnum("10").compare(&Value::new_strnum("9")), Ordering::Greater);
+        assert_eq!(Value::new_strnum(" 1e1 ").compare(&Value::Number(10.0)), Ordering::Equal);
+        assert_eq!(Value::new_strnum("10").compare(&Value::from("10.0")), Ordering::Less);
+        assert_eq!(Value::new_strnum("abc").compare(&Value::Number(0.0)), Ordering::Greater);
+        assert_eq!(Value::Undefined.compare(&Value::Number(0.0)), Ordering::Equal);
+        assert_eq!(Value::Undefined.compare(&Value::from("")), Ordering::Equal);
+
+        assert!(!Value::new_strnum("0.0").to_bool());
+        assert!(Value::from("0.0").to_bool());
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        let big = Value::from("18446744073709551615").promote_integer();
+        let sum = big.add(&Value::Number(1.0)).unwrap();
+        assert_eq!(sum.to_string(), "18446744073709551616");
+        assert_eq!(sum.subtract(&big).unwrap(), Value::Integer(BigInt::from(1)));
+        assert_eq!(
+            Value::Integer(BigInt::from(2)).power(&Value::Number(70.0)).unwrap().to_string(),
+            "1180591620717411303424"
+        );
+
+        let seven = Value::Integer(BigInt::from(7));
+        assert_eq!(seven.divide(&Value::Number(7.0)).unwrap(), Value::Integer(BigInt::from(1)));
+        assert_eq!(seven.divide(&Value::Number(2.0)).unwrap(), Value::Number(3.5));
+        assert_eq!(seven.negate().modulo(&Value::Number(4.0)).unwrap().to_string(), "-3");
+
+        assert_eq!(Value::from("12abc").promote_integer(), Value::Integer(BigInt::from(12)));
+        assert_eq!(Value::from("1.5").promote_integer(), Value::from("1.5"));
+        assert_eq!(
+            Value::from("99999999999999999999")
+                .promote_integer()
+                .compare(&Value::from("99999999999999999998")),
+            std::cmp::Ordering::Greater
+        );
     }
 }
\ No newline at end of file
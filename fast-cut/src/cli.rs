@@ -1,3 +1,9 @@
+use crate::encoding::Decoding;
+use crate::group::{Grouper, DEFAULT_MAX_GROUPS};
+use crate::reshape::{Reshaper, DEFAULT_MAX_CELLS};
+use crate::errors::FastCutError;
+use crate::row_select::Selection;
+use crate::split::{Splitter, DEFAULT_MAX_OPEN_FILES};
 use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
 
@@ -9,6 +15,8 @@ pub enum OutputFormat {
     Csv,
     /// JSON output
     Json,
+    /// Aligned columns, with a rule under the header when there is one
+    Table,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -21,6 +29,45 @@ pub enum ColorOption {
     Never,
 }
 
+/// What to do with lines that cannot supply the selected fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorPolicy {
+    /// Drop the line
+    Skip,
+    /// Fill missing fields with empty values
+    Pad,
+    /// Stop at the first malformed line
+    Fail,
+    /// Drop the line and print why on stderr
+    Report,
+}
+
+/// Character encoding of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputEncoding {
+    #[value(alias = "utf-8")]
+    Utf8,
+    /// ISO-8859-1, read as its Windows-1252 superset
+    #[value(alias = "iso-8859-1", alias = "windows-1252", alias = "cp1252")]
+    Latin1,
+    #[value(alias = "utf-16le")]
+    Utf16le,
+    #[value(alias = "utf-16be")]
+    Utf16be,
+    /// Byte order mark, else UTF-16 by its NUL bytes, else UTF-8 if valid,
+    /// else Latin-1
+    Auto,
+}
+
+/// What to do with bytes that are invalid in the input encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EncodingErrors {
+    /// Stop with an error naming the line and byte offset
+    Strict,
+    /// Replace them with U+FFFD
+    Lossy,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "fcut")]
 #[command(about = "Ultra-fast field extraction tool for delimited data and logs")]
@@ -31,11 +78,19 @@ pub struct Args {
     pub files: Vec<PathBuf>,
 
     /// Fields to extract (e.g., "1,3,5-7" or "name,age,city")
-    #[arg(short = 'f', long = "fields", value_name = "LIST")]
+    #[arg(
+        short = 'f',
+        long = "fields",
+        value_name = "LIST",
+        required_unless_present = "validate",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub fields: String,
 
-    /// Input field delimiter (auto-detect if not specified)
-    #[arg(short = 'd', long = "delimiter", value_name = "DELIM")]
+    /// Input field delimiter, may be several characters and use escapes such as
+    /// '\t' or '\x1f' (auto-detect if not specified)
+    #[arg(short = 'd', long = "delimiter", value_name = "DELIM", value_parser = parse_delimiter)]
     pub delimiter: Option<String>,
 
     /// Use tab as delimiter
@@ -50,8 +105,9 @@ pub struct Args {
     #[arg(short = 'c', long = "csv")]
     pub csv_mode: bool,
 
-    /// Output field delimiter (default: same as input)
-    #[arg(short = 'o', long = "output-delimiter", value_name = "DELIM")]
+    /// Output field delimiter, escapes allowed (default: same as input, including
+    /// an auto-detected one)
+    #[arg(short = 'o', long = "output-delimiter", value_name = "DELIM", value_parser = parse_delimiter)]
     pub output_delimiter: Option<String>,
 
     /// Output format
@@ -94,15 +150,192 @@ pub struct Args {
     #[arg(long = "buffer-size", default_value = "64")]
     pub buffer_size_kb: usize,
 
+    /// With --format table, compute column widths from the first N rows and
+    /// stream the rest (default: read all input first)
+    #[arg(long = "table-sample", value_name = "N")]
+    pub table_sample: Option<usize>,
+
+    /// With --format table, cut cells wider than N columns
+    #[arg(long = "max-width", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_width: Option<u64>,
+
+    /// Marker for cut table cells
+    #[arg(long = "ellipsis", value_name = "STR", default_value = "…")]
+    pub ellipsis: String,
+
+    /// Only output rows matching EXPR, e.g. 'col3 > 100 && level == "ERROR"';
+    /// columns are $N, colN or header names, compared with == != < <= > >=
+    /// or matched against a regex with =~ and !~
+    #[arg(short = 'w', long = "where", value_name = "EXPR")]
+    pub where_clause: Option<String>,
+
+    /// Drop output rows identical to an earlier one
+    #[arg(short = 'u', long = "unique")]
+    pub unique: bool,
+
+    /// Output each distinct row once, in first-seen order, with a leading
+    /// count of its occurrences (uniq -c without the sort)
+    #[arg(long = "count-unique")]
+    pub count_unique: bool,
+
+    /// Keep only the first row for each value of COLUMN (number or header
+    /// name); with --count-unique, count rows per value instead
+    #[arg(long = "distinct", value_name = "COLUMN")]
+    pub distinct: Option<String>,
+
+    /// Aggregate rows by COLUMNS, comma-separated positions in --fields or
+    /// header names: one row per group, in first-seen order, holding the
+    /// keys and then each --agg result
+    #[arg(long = "group-by", value_name = "COLUMNS", conflicts_with_all = ["unique", "count_unique", "distinct"])]
+    pub group_by: Option<String>,
+
+    /// Aggregates for --group-by, comma-separated: count, or sum:COL,
+    /// min:COL, max:COL, mean:COL over the numbers in COL (default: count)
+    #[arg(long = "agg", value_name = "LIST", requires = "group_by")]
+    pub agg: Option<String>,
+
+    /// Most groups --group-by holds in memory; past that, partial results
+    /// spill to temporary files and are merged at the end
+    #[arg(long = "max-groups", value_name = "N", default_value_t = DEFAULT_MAX_GROUPS)]
+    pub max_groups: usize,
+
+    /// Output only the first N rows that remain after extraction and filtering
+    #[arg(long = "head", value_name = "N", conflicts_with_all = ["tail", "sample"])]
+    pub head: Option<usize>,
+
+    /// Output only the last N rows that remain after extraction and filtering
+    #[arg(long = "tail", value_name = "N", conflicts_with = "sample")]
+    pub tail: Option<usize>,
+
+    /// Output N rows chosen uniformly at random (reservoir sampling), in
+    /// input order
+    #[arg(long = "sample", value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Seed for --sample, to make the choice repeatable
+    #[arg(long = "seed", value_name = "N", requires = "sample")]
+    pub seed: Option<u64>,
+
+    /// Swap rows and columns; the header, if any, becomes the first column.
+    /// Needs the whole input in memory (see --max-cells)
+    #[arg(long = "transpose", conflicts_with = "pivot")]
+    pub transpose: bool,
+
+    /// Reshape long to wide: one row per distinct combination of the other
+    /// selected fields, with a column for each value of KEY holding VALUE.
+    /// Columns are positions in --fields or header names
+    #[arg(long = "pivot", value_name = "KEY,VALUE")]
+    pub pivot: Option<String>,
+
+    /// Most fields --transpose or --pivot may hold in memory
+    #[arg(long = "max-cells", value_name = "N", default_value_t = DEFAULT_MAX_CELLS)]
+    pub max_cells: usize,
+
+    /// Write each output row to a file in --out-dir named after its value
+    /// in COLUMN (number or header name), such as one CSV per date or tenant
+    #[arg(
+        long = "split-by",
+        value_name = "COLUMN",
+        requires = "out_dir",
+        conflicts_with_all = ["count_unique", "group_by", "tail", "sample", "transpose", "pivot"]
+    )]
+    pub split_by: Option<String>,
+
+    /// Directory for the --split-by files, created if missing
+    #[arg(long = "out-dir", value_name = "DIR", requires = "split_by")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Most files --split-by keeps open at once; the least recently written
+    /// one is closed to make room, and appended to if needed again
+    #[arg(long = "max-open-files", value_name = "N", default_value_t = DEFAULT_MAX_OPEN_FILES)]
+    pub max_open_files: usize,
+
     /// Only output non-empty lines
     #[arg(long = "non-empty")]
     pub non_empty_only: bool,
 
+    /// How to handle malformed lines, e.g. too few fields; a summary of
+    /// affected lines is printed at the end
+    #[arg(long = "on-error", value_enum, default_value = "skip", value_name = "POLICY")]
+    pub on_error: ErrorPolicy,
+
+    /// Encoding to read input in; output is always UTF-8
+    #[arg(long = "input-encoding", value_enum, default_value = "utf8", value_name = "ENCODING")]
+    pub input_encoding: InputEncoding,
+
+    /// How to handle bytes invalid in the input encoding
+    #[arg(long = "encoding-errors", value_enum, default_value = "strict", value_name = "MODE")]
+    pub encoding_errors: EncodingErrors,
+
+    /// Check the structure of the input instead of cutting it: rows with
+    /// more or fewer fields than the header, invalid UTF-8, stray quotes and
+    /// repeated column names, one finding per line (a JSON report with
+    /// --format json, CSV with --format csv); exits 1 if any is found
+    #[arg(
+        long = "validate",
+        conflicts_with_all = [
+            "fields", "space_delimiter", "where_clause", "unique", "count_unique", "distinct", "group_by",
+            "head", "tail", "sample", "transpose", "pivot", "split_by"
+        ]
+    )]
+    pub validate: bool,
+
     /// Print verbose debugging information
     #[arg(short = 'v', long = "verbose")]
     pub verbose: bool,
 }
 
+/// Parse a delimiter argument, expanding backslash escapes.
+///
+/// Supports `\t`, `\n`, `\r`, `\0`, `\a`, `\b`, `\f`, `\v`, `\e`, `\\` and
+/// `\xHH` (ASCII only); a backslash before punctuation yields that character.
+pub fn parse_delimiter(value: &str) -> Result<String, String> {
+    let mut delimiter = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            delimiter.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('v') => '\x0b',
+            Some('e') => '\x1b',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .ok_or_else(|| format!("Invalid escape '\\x{}': expected two hex digits", hex))?;
+                if !byte.is_ascii() {
+                    return Err(format!(
+                        "Invalid escape '\\x{}': only ASCII bytes (\\x00-\\x7f) are allowed",
+                        hex
+                    ));
+                }
+                byte as char
+            }
+            Some(other) if other.is_ascii_punctuation() => other,
+            Some(other) => return Err(format!("Unknown escape sequence '\\{}'", other)),
+            None => return Err("Delimiter ends with an unfinished escape '\\'".to_string()),
+        };
+        delimiter.push(escaped);
+    }
+
+    if delimiter.is_empty() {
+        return Err("Delimiter must not be empty".to_string());
+    }
+    Ok(delimiter)
+}
+
 #[derive(Debug, Clone)]
 pub struct FieldSelector {
     pub indices: Vec<usize>,
@@ -112,6 +345,10 @@ pub struct FieldSelector {
 
 impl Args {
     pub fn should_use_colors(&self) -> bool {
+        // Split output goes to files, never a terminal
+        if self.split_by.is_some() {
+            return false;
+        }
         match self.color {
             ColorOption::Always => true,
             ColorOption::Never => false,
@@ -138,7 +375,10 @@ impl Args {
     }
 
     pub fn get_output_delimiter(&self) -> Option<String> {
-        self.output_delimiter.clone().or_else(|| self.get_input_delimiter())
+        self.output_delimiter
+            .clone()
+            .or_else(|| self.get_input_delimiter())
+            .or_else(|| self.csv_mode.then(|| ",".to_string()))
     }
 
     pub fn is_csv_mode(&self) -> bool {
@@ -153,6 +393,57 @@ impl Args {
         matches!(self.format, OutputFormat::Csv)
     }
 
+    pub fn has_dedup(&self) -> bool {
+        self.unique || self.count_unique || self.distinct.is_some()
+    }
+
+    /// The `--head`, `--tail` or `--sample` row selection, if any
+    pub fn row_selection(&self) -> Option<Selection> {
+        self.head
+            .map(Selection::Head)
+            .or(self.tail.map(Selection::Tail))
+            .or(self.sample.map(Selection::Sample))
+    }
+
+    /// The `--group-by` stage, if any
+    pub fn grouper(&self) -> Result<Option<Grouper>, FastCutError> {
+        self.group_by
+            .as_deref()
+            .map(|keys| Grouper::new(keys, self.agg.as_deref(), self.max_groups))
+            .transpose()
+    }
+
+    /// The `--transpose` or `--pivot` stage, if any
+    pub fn reshaper(&self) -> Result<Option<Reshaper>, FastCutError> {
+        if self.transpose {
+            Ok(Some(Reshaper::transpose(self.max_cells)))
+        } else {
+            self.pivot.as_deref().map(|columns| Reshaper::pivot(columns, self.max_cells)).transpose()
+        }
+    }
+
+    /// The `--split-by` sink, if any
+    pub fn splitter(&self) -> Result<Option<Splitter>, FastCutError> {
+        let (Some(column), Some(out_dir)) = (&self.split_by, &self.out_dir) else {
+            return Ok(None);
+        };
+        if self.is_table_output() {
+            return Err(FastCutError::invalid_config("--split-by cannot write --format table"));
+        }
+        Splitter::new(column, out_dir, &self.format, self.max_open_files).map(Some)
+    }
+
+    pub fn decoding(&self) -> Decoding {
+        Decoding {
+            encoding: self.input_encoding,
+            errors: self.encoding_errors,
+        }
+    }
+
+    pub fn is_table_output(&self) -> bool {
+        matches!(self.format, OutputFormat::Table)
+    }
+
     pub fn parse_field_selector(&self) -> Result<FieldSelector, String> {
         let mut indices = Vec::new();
         let mut ranges = Vec::new();
@@ -217,4 +508,28 @@ impl Args {
 
         true
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delimiter_escapes() {
+        assert_eq!(parse_delimiter("\\t").unwrap(), "\t");
+        assert_eq!(parse_delimiter("\\x1f").unwrap(), "\x1f");
+        assert_eq!(parse_delimiter("::").unwrap(), "::");
+        assert_eq!(parse_delimiter("\\|\\\\").unwrap(), "|\\");
+        assert_eq!(parse_delimiter("a\\tb").unwrap(), "a\tb");
+    }
+
+    #[test]
+    fn test_parse_delimiter_rejects_bad_input() {
+        assert!(parse_delimiter("").is_err());
+        assert!(parse_delimiter("\\").is_err());
+        assert!(parse_delimiter("\\q").is_err());
+        assert!(parse_delimiter("\\x1").is_err());
+        assert!(parse_delimiter("\\xzz").is_err());
+        assert!(parse_delimiter("\\xff").is_err());
+    }
+}
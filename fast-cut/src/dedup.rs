@@ -0,0 +1,175 @@
+use crate::errors::{FastCutError, Result};
+use crate::field_parser::ParsedLine;
+use std::collections::{HashMap, HashSet};
+
+/// A column given on the command line by number or header name, such as
+/// the one `--distinct` deduplicates on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// 0-based field index
+    Index(usize),
+    /// Header name, resolved once the header is read
+    Name(String),
+}
+
+impl Column {
+    /// Parse the argument of `option`: a number from 1, or a header name
+    pub fn parse(column: &str, option: &str) -> Result<Self> {
+        match column.trim().parse::<usize>() {
+            Ok(0) => Err(FastCutError::invalid_config(format!("{}: columns are numbered from 1", option))),
+            Ok(number) => Ok(Self::Index(number - 1)),
+            Err(_) => Ok(Self::Name(column.trim().to_string())),
+        }
+    }
+
+    /// Turn a header name into its index; without a header only numbers
+    /// are accepted
+    pub fn resolve(&mut self, header: Option<&[String]>) -> Result<()> {
+        if let Self::Name(ref name) = self {
+            let index = header
+                .and_then(|header| header.iter().position(|field| field.trim() == name))
+                .ok_or_else(|| {
+                    FastCutError::field_not_found(name.clone(), header.map(<[String]>::to_vec).unwrap_or_default())
+                })?;
+            *self = Self::Index(index);
+        }
+        Ok(())
+    }
+}
+
+/// The aggregation stage behind `--unique`, `--count-unique` and
+/// `--distinct`.
+///
+/// Rows are keyed on their selected fields, or on a single input column
+/// with `--distinct`. Without counting, the first row for each key is
+/// passed through as it arrives; with counting, rows are held until the end
+/// of input and released in first-seen order, each with its count.
+#[derive(Debug)]
+pub struct Deduplicator {
+    column: Option<Column>,
+    count: bool,
+    seen: HashSet<Vec<String>>,
+    /// Key to position in `counted`
+    positions: HashMap<Vec<String>, usize>,
+    counted: Vec<(usize, ParsedLine)>,
+}
+
+impl Deduplicator {
+    pub fn new(distinct: Option<&str>, count: bool) -> Result<Self> {
+        let column = distinct.map(|column| Column::parse(column, "--distinct")).transpose()?;
+        Ok(Self {
+            column,
+            count,
+            seen: HashSet::new(),
+            positions: HashMap::new(),
+            counted: Vec::new(),
+        })
+    }
+
+    /// Whether keys come from the whole input line rather than the selection
+    pub fn needs_all_fields(&self) -> bool {
+        self.column.is_some()
+    }
+
+    pub fn is_counting(&self) -> bool {
+        self.count
+    }
+
+    /// Turn a `--distinct` header name into its column; without a header
+    /// only numbers are accepted
+    pub fn resolve(&mut self, header: Option<&[String]>) -> Result<()> {
+        match self.column {
+            Some(ref mut column) => column.resolve(header),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a row; returns it when it should be output right away
+    pub fn observe(&mut self, line: ParsedLine, all_fields: Option<&[String]>) -> Result<Option<ParsedLine>> {
+        let key = match self.column {
+            Some(Column::Index(index)) => {
+                vec![all_fields.and_then(|fields| fields.get(index)).cloned().unwrap_or_default()]
+            }
+            Some(Column::Name(ref name)) => {
+                return Err(FastCutError::field_not_found(name.clone(), Vec::new()));
+            }
+            None => line.fields.clone(),
+        };
+
+        if !self.count {
+            return Ok(self.seen.insert(key).then_some(line));
+        }
+        match self.positions.get(&key) {
+            Some(&position) => self.counted[position].0 += 1,
+            None => {
+                self.positions.insert(key, self.counted.len());
+                self.counted.push((1, line));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The counted rows with their count as a leading field, emptying the stage
+    pub fn finish(&mut self) -> Vec<ParsedLine> {
+        self.positions.clear();
+        self.counted
+            .drain(..)
+            .map(|(count, mut line)| {
+                line.fields.insert(0, count.to_string());
+                line
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(number: usize, fields: &[&str]) -> ParsedLine {
+        ParsedLine {
+            line_number: number,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            raw_line: fields.join(","),
+            padded_fields: 0,
+        }
+    }
+
+    #[test]
+    fn test_unique_and_counts() {
+        let rows = [["a", "1"], ["b", "2"], ["a", "1"], ["a", "3"], ["b", "2"]];
+
+        let mut unique = Deduplicator::new(None, false).unwrap();
+        let kept: Vec<usize> = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(i, fields)| unique.observe(line(i + 1, fields), None).unwrap())
+            .map(|line| line.line_number)
+            .collect();
+        assert_eq!(kept, vec![1, 2, 4]);
+
+        let mut counting = Deduplicator::new(None, true).unwrap();
+        for (i, fields) in rows.iter().enumerate() {
+            assert!(counting.observe(line(i + 1, fields), None).unwrap().is_none());
+        }
+        let counted: Vec<Vec<String>> = counting.finish().into_iter().map(|line| line.fields).collect();
+        assert_eq!(counted, vec![vec!["2", "a", "1"], vec!["2", "b", "2"], vec!["1", "a", "3"]]);
+    }
+
+    #[test]
+    fn test_distinct_column() {
+        let header = vec!["host".to_string(), "status".to_string()];
+        let mut distinct = Deduplicator::new(Some("status"), false).unwrap();
+        assert!(distinct.needs_all_fields());
+        distinct.resolve(Some(&header)).unwrap();
+
+        let all = |fields: &[&str]| fields.iter().map(|field| field.to_string()).collect::<Vec<_>>();
+        assert!(distinct.observe(line(2, &["web1"]), Some(&all(&["web1", "200"]))).unwrap().is_some());
+        assert!(distinct.observe(line(3, &["web2"]), Some(&all(&["web2", "200"]))).unwrap().is_none());
+        assert!(distinct.observe(line(4, &["web1"]), Some(&all(&["web1", "500"]))).unwrap().is_some());
+
+        assert!(Deduplicator::new(Some("0"), false).is_err());
+        let mut unknown = Deduplicator::new(Some("missing"), false).unwrap();
+        assert!(unknown.resolve(Some(&header)).is_err());
+    }
+}
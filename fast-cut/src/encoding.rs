@@ -0,0 +1,179 @@
+use crate::cli::{EncodingErrors, InputEncoding};
+use encoding_rs::{CoderResult, Decoder, DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use std::io::{self, Read};
+
+/// Raw bytes read per decoding step; also the sample `auto` detects from
+const RAW_CHUNK: usize = 8192;
+
+/// How input bytes become text: `--input-encoding` and `--encoding-errors`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoding {
+    pub encoding: InputEncoding,
+    pub errors: EncodingErrors,
+}
+
+impl Decoding {
+    /// Whether input can be read as is: strict UTF-8, which `BufRead::lines`
+    /// already checks
+    pub fn is_passthrough(&self) -> bool {
+        self.encoding == InputEncoding::Utf8 && self.errors == EncodingErrors::Strict
+    }
+
+    /// `input` transcoded to UTF-8
+    pub fn reader<R: Read>(&self, input: R) -> TranscodingReader<R> {
+        let encoding = match self.encoding {
+            InputEncoding::Utf8 => Some(UTF_8),
+            InputEncoding::Latin1 => Some(WINDOWS_1252),
+            InputEncoding::Utf16le => Some(UTF_16LE),
+            InputEncoding::Utf16be => Some(UTF_16BE),
+            InputEncoding::Auto => None,
+        };
+        TranscodingReader {
+            input,
+            decoder: encoding.map(Encoding::new_decoder),
+            lossy: self.errors == EncodingErrors::Lossy,
+            raw: vec![0; RAW_CHUNK],
+            decoded: Vec::new(),
+            position: 0,
+            offset: 0,
+            finished: false,
+        }
+    }
+}
+
+/// The encoding `auto` settles on for input starting with `sample`, when it
+/// has no byte order mark (a BOM always wins, whatever the encoding).
+///
+/// ASCII-heavy UTF-16 shows as NUL bytes on every other position; otherwise
+/// valid UTF-8 is taken as UTF-8 and anything else as Latin-1, the usual
+/// encoding of legacy exports.
+pub fn detect(sample: &[u8]) -> &'static Encoding {
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        let zeros_at = |parity: usize| sample.iter().skip(parity).step_by(2).filter(|&&byte| byte == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 4 > pairs && even * 16 < pairs {
+            return UTF_16LE;
+        }
+        if even * 4 > pairs && odd * 16 < pairs {
+            return UTF_16BE;
+        }
+    }
+    // A sequence cut off by the end of the sample is not an error
+    match std::str::from_utf8(sample) {
+        Err(e) if e.error_len().is_some() => WINDOWS_1252,
+        _ => UTF_8,
+    }
+}
+
+/// Reads `input` in some encoding and yields it as UTF-8, replacing
+/// malformed sequences with U+FFFD in lossy mode and failing with
+/// `InvalidData` on the first one in strict mode.
+pub struct TranscodingReader<R> {
+    input: R,
+    /// `None` until `auto` has seen the first chunk
+    decoder: Option<Decoder>,
+    lossy: bool,
+    raw: Vec<u8>,
+    decoded: Vec<u8>,
+    /// Read position in `decoded`
+    position: usize,
+    /// Input bytes decoded so far, for error messages
+    offset: u64,
+    finished: bool,
+}
+
+impl<R: Read> TranscodingReader<R> {
+    /// Decode the next chunk of input into `decoded`
+    fn fill(&mut self) -> io::Result<()> {
+        let read = loop {
+            match self.input.read(&mut self.raw) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let last = read == 0;
+        let source = &self.raw[..read];
+        let decoder = self.decoder.get_or_insert_with(|| detect(source).new_decoder());
+
+        let capacity = decoder
+            .max_utf8_buffer_length(read)
+            .unwrap_or(read * 3 + 16);
+        self.decoded.resize(capacity, 0);
+        self.position = 0;
+
+        let written = if self.lossy {
+            let (result, consumed, written, _) = decoder.decode_to_utf8(source, &mut self.decoded, last);
+            debug_assert!(result == CoderResult::InputEmpty && consumed == read);
+            written
+        } else {
+            let (result, consumed, written) = decoder.decode_to_utf8_without_replacement(source, &mut self.decoded, last);
+            if let DecoderResult::Malformed(bad, pending) = result {
+                // The malformed bytes end `pending` bytes before `consumed`
+                let at = (self.offset + consumed as u64).saturating_sub(u64::from(bad) + u64::from(pending));
+                let encoding = decoder.encoding().name();
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid {} at byte {}", encoding, at),
+                ));
+            }
+            written
+        };
+        self.decoded.truncate(written);
+        self.offset += read as u64;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for TranscodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let count = buf.len().min(self.decoded.len() - self.position);
+        buf[..count].copy_from_slice(&self.decoded[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], encoding: InputEncoding, errors: EncodingErrors) -> io::Result<String> {
+        let mut text = String::new();
+        Decoding { encoding, errors }.reader(bytes).read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    #[test]
+    fn test_transcoding() {
+        use EncodingErrors::{Lossy, Strict};
+
+        // "café,naïve" in Latin-1
+        let latin1 = b"caf\xe9,na\xefve\n";
+        assert_eq!(decode(latin1, InputEncoding::Latin1, Strict).unwrap(), "café,naïve\n");
+        assert_eq!(decode(latin1, InputEncoding::Auto, Strict).unwrap(), "café,naïve\n");
+        assert_eq!(decode(latin1, InputEncoding::Utf8, Lossy).unwrap(), "caf\u{fffd},na\u{fffd}ve\n");
+        let error = decode(latin1, InputEncoding::Utf8, Strict).unwrap_err();
+        assert_eq!(error.to_string(), "invalid UTF-8 at byte 3");
+
+        let utf16le: Vec<u8> = "id,ñ\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode(&utf16le, InputEncoding::Utf16le, Strict).unwrap(), "id,ñ\n");
+        assert_eq!(decode(&utf16le, InputEncoding::Auto, Strict).unwrap(), "id,ñ\n");
+        let mut with_bom = vec![0xfe, 0xff];
+        with_bom.extend("a,b".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(&with_bom, InputEncoding::Auto, Strict).unwrap(), "a,b");
+
+        // A lone surrogate cannot be decoded
+        assert!(decode(&[0x00, 0xd8, b'a', 0], InputEncoding::Utf16le, Strict).is_err());
+
+        assert_eq!(detect("plain ascii, and ünïcode".as_bytes()), UTF_8);
+    }
+}
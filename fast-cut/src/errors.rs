@@ -39,12 +39,21 @@ pub enum FastCutError {
     #[error("Invalid configuration: {message}")]
     InvalidConfig { message: String },
 
+    #[error("Invalid --where expression: {message}")]
+    InvalidFilter { message: String },
+
     #[error("Buffer overflow: line too long ({length} bytes)")]
     BufferOverflow { length: usize },
 
+    #[error("Malformed line {line_number}: {message}")]
+    MalformedLine { line_number: usize, message: String },
+
     #[error("Encoding error: {message}")]
     EncodingError { message: String },
 
+    #[error("Input too large to reshape: more than {limit} cells (see --max-cells)")]
+    ReshapeTooLarge { limit: usize },
+
     #[error("Task join error: {0}")]
     TaskJoin(String),
 
@@ -84,10 +93,27 @@ impl FastCutError {
         }
     }
 
+    pub fn invalid_filter(message: impl Into<String>) -> Self {
+        Self::InvalidFilter {
+            message: message.into(),
+        }
+    }
+
     pub fn buffer_overflow(length: usize) -> Self {
         Self::BufferOverflow { length }
     }
 
+    pub fn malformed_line(line_number: usize, message: impl Into<String>) -> Self {
+        Self::MalformedLine {
+            line_number,
+            message: message.into(),
+        }
+    }
+
+    pub fn reshape_too_large(limit: usize) -> Self {
+        Self::ReshapeTooLarge { limit }
+    }
+
     pub fn encoding_error(message: impl Into<String>) -> Self {
         Self::EncodingError {
             message: message.into(),
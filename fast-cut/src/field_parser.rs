@@ -1,6 +1,7 @@
 use crate::cli::FieldSelector;
 use crate::errors::{FastCutError, Result};
 use memchr::memchr_iter;
+use memchr::memmem;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -8,15 +9,20 @@ pub struct ParsedLine {
     pub line_number: usize,
     pub fields: Vec<String>,
     pub raw_line: String,
+    /// Selected fields missing from the line and filled with empty strings
+    pub padded_fields: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct FieldParser {
     delimiter: Option<String>,
+    /// Prebuilt searcher for multi-byte delimiters
+    delimiter_finder: Option<memmem::Finder<'static>>,
     csv_mode: bool,
     space_mode: bool,
     header_map: Option<HashMap<String, usize>>,
     field_selector: FieldSelector,
+    pad_missing: bool,
 }
 
 impl FieldParser {
@@ -26,15 +32,29 @@ impl FieldParser {
         space_mode: bool,
         field_selector: FieldSelector,
     ) -> Self {
+        let delimiter_finder = delimiter
+            .as_deref()
+            .filter(|d| d.len() > 1)
+            .map(|d| memmem::Finder::new(d.as_bytes()).into_owned());
+
         Self {
             delimiter,
+            delimiter_finder,
             csv_mode,
             space_mode,
             header_map: None,
             field_selector,
+            pad_missing: false,
         }
     }
 
+    /// Fill selected fields a line is too short for with empty strings
+    /// instead of rejecting the line
+    pub fn with_missing_fields_padded(mut self, pad_missing: bool) -> Self {
+        self.pad_missing = pad_missing;
+        self
+    }
+
     pub fn set_header(&mut self, header_line: &str) -> Result<()> {
         let fields = self.parse_line_fields(header_line)?;
         let mut header_map = HashMap::new();
@@ -49,12 +69,23 @@ impl FieldParser {
 
     pub fn parse_line(&self, line: &str, line_number: usize) -> Result<ParsedLine> {
         let all_fields = self.parse_line_fields(line)?;
-        let selected_fields = self.select_fields(&all_fields)?;
-        
+        self.select_line(&all_fields, line, line_number)
+    }
+
+    /// Every field of a line, before selection
+    pub fn split_line(&self, line: &str) -> Result<Vec<String>> {
+        self.parse_line_fields(line)
+    }
+
+    /// Pick the selected fields out of what `split_line` returned
+    pub fn select_line(&self, all_fields: &[String], line: &str, line_number: usize) -> Result<ParsedLine> {
+        let (selected_fields, padded_fields) = self.select_fields(all_fields)?;
+
         Ok(ParsedLine {
             line_number,
             fields: selected_fields,
             raw_line: line.to_string(),
+            padded_fields,
         })
     }
 
@@ -96,16 +127,32 @@ impl FieldParser {
     }
 
     fn parse_delimited_line(&self, line: &str, delimiter: &str) -> Result<Vec<String>> {
-        if delimiter.len() == 1 {
+        if let Some(ref finder) = self.delimiter_finder {
+            // Multi-byte delimiter - SIMD substring search
+            Ok(self.parse_multi_char_delimited(line, finder))
+        } else {
             // Single character delimiter - use SIMD optimization
             let delim_byte = delimiter.as_bytes()[0];
             self.parse_single_char_delimited(line, delim_byte)
-        } else {
-            // Multi-character delimiter
-            Ok(line.split(delimiter).map(|s| s.to_string()).collect())
         }
     }
 
+    fn parse_multi_char_delimited(&self, line: &str, finder: &memmem::Finder) -> Vec<String> {
+        let delimiter_len = finder.needle().len();
+        let mut fields = Vec::new();
+        let mut start = 0;
+
+        // Matches are non-overlapping, and since the delimiter is valid UTF-8
+        // every match lies on a char boundary
+        for pos in finder.find_iter(line.as_bytes()) {
+            fields.push(line[start..pos].to_string());
+            start = pos + delimiter_len;
+        }
+        fields.push(line[start..].to_string());
+
+        fields
+    }
+
     fn parse_single_char_delimited(&self, line: &str, delimiter: u8) -> Result<Vec<String>> {
         let line_bytes = line.as_bytes();
         let mut fields = Vec::new();
@@ -133,6 +180,23 @@ impl FieldParser {
     }
 
     fn auto_detect_and_parse(&self, line: &str) -> Result<Vec<String>> {
+        match Self::auto_detected_delimiter(line) {
+            Some(",") => self.parse_single_char_delimited(line, b','),
+            Some("\t") => self.parse_single_char_delimited(line, b'\t'),
+            Some(_) => self.parse_space_delimited(line),
+            // No delimiters found, treat as single field
+            None => Ok(vec![line.to_string()]),
+        }
+    }
+
+    /// Whether lines are split on a delimiter guessed per line
+    pub fn is_auto_detect(&self) -> bool {
+        !self.csv_mode && !self.space_mode && self.delimiter.is_none()
+    }
+
+    /// The delimiter auto-detection picks for `line`: comma, tab, or a single
+    /// space standing in for runs of whitespace
+    pub fn auto_detected_delimiter(line: &str) -> Option<&'static str> {
         let line_bytes = line.as_bytes();
         
         // Count different delimiter types
@@ -142,37 +206,52 @@ impl FieldParser {
 
         // Choose the most frequent delimiter
         if comma_count > 0 && comma_count >= tab_count && comma_count >= space_count {
-            self.parse_single_char_delimited(line, b',')
+            Some(",")
         } else if tab_count > 0 && tab_count >= space_count {
-            self.parse_single_char_delimited(line, b'\t')
+            Some("\t")
         } else if space_count > 0 {
-            self.parse_space_delimited(line)
+            Some(" ")
         } else {
-            // No delimiters found, treat as single field
-            Ok(vec![line.to_string()])
+            None
         }
     }
 
-    fn select_fields(&self, all_fields: &[String]) -> Result<Vec<String>> {
+    /// Pick the selected fields, returning them with the number of padded ones
+    fn select_fields(&self, all_fields: &[String]) -> Result<(Vec<String>, usize)> {
         let mut selected = Vec::new();
+        let mut padded = 0;
 
         // Process individual indices
         for &index in &self.field_selector.indices {
-            if index >= all_fields.len() {
+            if index < all_fields.len() {
+                selected.push(all_fields[index].clone());
+            } else if self.pad_missing {
+                selected.push(String::new());
+                padded += 1;
+            } else {
                 return Err(FastCutError::invalid_field_index(index + 1, all_fields.len()));
             }
-            selected.push(all_fields[index].clone());
         }
 
         // Process ranges
         for &(start, end) in &self.field_selector.ranges {
+            if self.pad_missing {
+                for index in start..=end {
+                    match all_fields.get(index) {
+                        Some(field) => selected.push(field.clone()),
+                        None => {
+                            selected.push(String::new());
+                            padded += 1;
+                        }
+                    }
+                }
+                continue;
+            }
             if start >= all_fields.len() {
                 return Err(FastCutError::invalid_field_index(start + 1, all_fields.len()));
             }
             let actual_end = std::cmp::min(end, all_fields.len() - 1);
-            for i in start..=actual_end {
-                selected.push(all_fields[i].clone());
-            }
+            selected.extend_from_slice(&all_fields[start..=actual_end]);
         }
 
         // Process field names
@@ -184,6 +263,9 @@ impl FieldParser {
                 if let Some(&index) = header_map.get(name) {
                     if index < all_fields.len() {
                         selected.push(all_fields[index].clone());
+                    } else if self.pad_missing {
+                        selected.push(String::new());
+                        padded += 1;
                     } else {
                         return Err(FastCutError::invalid_field_index(index + 1, all_fields.len()));
                     }
@@ -194,7 +276,7 @@ impl FieldParser {
             }
         }
 
-        Ok(selected)
+        Ok((selected, padded))
     }
 
     pub fn get_header_fields(&self) -> Option<Vec<String>> {
@@ -265,6 +347,30 @@ mod tests {
         assert_eq!(result, vec!["field1", "field2", "field3"]);
     }
 
+    #[test]
+    fn test_multi_char_delimited() {
+        let selector = FieldSelector {
+            indices: vec![0],
+            ranges: vec![],
+            names: vec![],
+        };
+        let parser = FieldParser::new(Some("::".to_string()), false, false, selector);
+
+        let result = parser.parse_line_fields("a::b::::c::").unwrap();
+        assert_eq!(result, vec!["a", "b", "", "c", ""]);
+
+        let result = parser.parse_line_fields("a:::b").unwrap();
+        assert_eq!(result, vec!["a", ":b"]);
+
+        let parser = FieldParser::new(Some("→".to_string()), false, false, FieldSelector {
+            indices: vec![0],
+            ranges: vec![],
+            names: vec![],
+        });
+        let result = parser.parse_line_fields("x→ÿ→z").unwrap();
+        assert_eq!(result, vec!["x", "ÿ", "z"]);
+    }
+
     #[test]
     fn test_space_delimited() {
         let selector = FieldSelector {
@@ -288,8 +394,27 @@ mod tests {
         let parser = FieldParser::new(Some(",".to_string()), false, false, selector);
         
         let fields = vec!["a".to_string(), "b".to_string(), "c".to_string(), "d".to_string()];
-        let selected = parser.select_fields(&fields).unwrap();
+        let (selected, padded) = parser.select_fields(&fields).unwrap();
         assert_eq!(selected, vec!["a", "c", "b", "c"]);
+        assert_eq!(padded, 0);
+    }
+
+    #[test]
+    fn test_missing_fields_padded() {
+        let selector = FieldSelector {
+            indices: vec![0, 3],
+            ranges: vec![(1, 2)],
+            names: vec![],
+        };
+        let fields = vec!["a".to_string(), "b".to_string()];
+
+        let parser = FieldParser::new(Some(",".to_string()), false, false, selector.clone());
+        assert!(parser.select_fields(&fields).is_err());
+
+        let parser = parser.with_missing_fields_padded(true);
+        let (selected, padded) = parser.select_fields(&fields).unwrap();
+        assert_eq!(selected, vec!["a", "", "b", ""]);
+        assert_eq!(padded, 2);
     }
 
     #[test]
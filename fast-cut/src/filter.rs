@@ -0,0 +1,472 @@
+use crate::errors::{FastCutError, Result};
+use regex::Regex;
+use std::cmp::Ordering;
+
+/// A `--where` row filter, e.g. `col3 > 100 && level == "ERROR"`.
+///
+/// Columns are `$N` or `colN` (1-based) or a header name, in backticks when
+/// it is not a plain identifier. Comparisons are numeric when both sides
+/// look like numbers and string comparisons otherwise, as in awk; `=~` and
+/// `!~` match a regex. A bare column is true when it is non-empty and not
+/// zero. Fields missing from a short line compare as empty strings.
+#[derive(Debug, Clone)]
+pub struct RowFilter {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    Match(Operand, Regex, bool),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Column(Column),
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Column {
+    /// 0-based field index
+    Index(usize),
+    /// A header name, until `resolve` turns it into an index
+    Name(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl RowFilter {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(FastCutError::invalid_filter(format!("unexpected {}", token.describe())));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Turn header names into field indexes. Without a header only `$N` and
+    /// `colN` columns can be used.
+    pub fn resolve(&mut self, header: Option<&[String]>) -> Result<()> {
+        resolve_expr(&mut self.expr, header)
+    }
+
+    pub fn matches(&self, fields: &[String]) -> bool {
+        eval(&self.expr, fields)
+    }
+}
+
+fn resolve_expr(expr: &mut Expr, header: Option<&[String]>) -> Result<()> {
+    match expr {
+        Expr::Or(left, right) | Expr::And(left, right) => {
+            resolve_expr(left, header)?;
+            resolve_expr(right, header)
+        }
+        Expr::Not(inner) => resolve_expr(inner, header),
+        Expr::Compare(left, _, right) => {
+            resolve_operand(left, header)?;
+            resolve_operand(right, header)
+        }
+        Expr::Match(operand, _, _) | Expr::Truthy(operand) => resolve_operand(operand, header),
+    }
+}
+
+fn resolve_operand(operand: &mut Operand, header: Option<&[String]>) -> Result<()> {
+    let Operand::Column(Column::Name(name)) = operand else {
+        return Ok(());
+    };
+
+    // A header column called "col2" wins over the second column
+    let index = header
+        .and_then(|header| header.iter().position(|field| field == name))
+        .or_else(|| positional_name(name));
+    match (index, header) {
+        (Some(index), _) => {
+            *operand = Operand::Column(Column::Index(index));
+            Ok(())
+        }
+        (None, Some(header)) => Err(FastCutError::field_not_found(name.clone(), header.to_vec())),
+        (None, None) => Err(FastCutError::invalid_filter(format!(
+            "column '{}' is a header name, which needs --header",
+            name
+        ))),
+    }
+}
+
+/// The index of a `colN` name
+fn positional_name(name: &str) -> Option<usize> {
+    name.strip_prefix("col")?
+        .parse::<usize>()
+        .ok()
+        .filter(|&n| n > 0)
+        .map(|n| n - 1)
+}
+
+fn eval(expr: &Expr, fields: &[String]) -> bool {
+    match expr {
+        Expr::Or(left, right) => eval(left, fields) || eval(right, fields),
+        Expr::And(left, right) => eval(left, fields) && eval(right, fields),
+        Expr::Not(inner) => !eval(inner, fields),
+        Expr::Compare(left, op, right) => {
+            let (left, right) = (value(left, fields), value(right, fields));
+            let ordering = match (as_number(&left), as_number(&right)) {
+                (Some(left), Some(right)) => left.partial_cmp(&right).unwrap_or(Ordering::Less),
+                _ => left.as_ref().cmp(right.as_ref()),
+            };
+            op.holds(ordering)
+        }
+        Expr::Match(operand, regex, negate) => regex.is_match(&value(operand, fields)) != *negate,
+        Expr::Truthy(operand) => {
+            let value = value(operand, fields);
+            !value.is_empty() && as_number(&value) != Some(0.0)
+        }
+    }
+}
+
+fn value<'a>(operand: &'a Operand, fields: &'a [String]) -> std::borrow::Cow<'a, str> {
+    match operand {
+        Operand::Column(Column::Index(index)) => {
+            fields.get(*index).map_or("", |field| field.as_str()).into()
+        }
+        Operand::Column(Column::Name(_)) => "".into(),
+        Operand::Number(number) => number.to_string().into(),
+        Operand::Text(text) => text.as_str().into(),
+    }
+}
+
+/// A field's numeric value, if the whole field is a number
+fn as_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let first = value.bytes().next()?;
+    if !(first.is_ascii_digit() || matches!(first, b'+' | b'-' | b'.')) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Column(Column),
+    Number(f64),
+    Text(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Column(Column::Index(index)) => format!("column ${}", index + 1),
+            Token::Column(Column::Name(name)) => format!("column '{}'", name),
+            Token::Number(number) => format!("number {}", number),
+            Token::Text(text) => format!("string \"{}\"", text),
+            Token::Op(op) => format!("'{}'", op),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+        }
+    }
+}
+
+/// Operators, longest first so `<=` is not read as `<`
+const OPERATORS: [(&str, &str); 13] = [
+    ("==", "=="),
+    ("!=", "!="),
+    ("<>", "!="),
+    ("<=", "<="),
+    (">=", ">="),
+    ("=~", "=~"),
+    ("!~", "!~"),
+    ("&&", "&&"),
+    ("||", "||"),
+    ("<", "<"),
+    (">", ">"),
+    ("=", "=="),
+    ("!", "!"),
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let rest = &source[start..];
+
+        if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(if c == '(' { Token::LParen } else { Token::RParen });
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            tokens.push(Token::Text(quoted(&mut chars, c, "string")?));
+        } else if c == '`' {
+            chars.next();
+            tokens.push(Token::Column(Column::Name(quoted(&mut chars, c, "column name")?)));
+        } else if c == '$' {
+            chars.next();
+            let digits = take_while(&mut chars, source, |c| c.is_ascii_digit());
+            let index = digits
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| FastCutError::invalid_filter("'$' must be followed by a column number from 1"))?;
+            tokens.push(Token::Column(Column::Index(index - 1)));
+        } else if c.is_ascii_digit()
+            || (matches!(c, '-' | '.') && rest[1..].starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+        {
+            chars.next();
+            let digits = take_while(&mut chars, source, |c| c.is_ascii_alphanumeric() || c == '.');
+            let number = &rest[..c.len_utf8() + digits.len()];
+            let number = number
+                .parse()
+                .map_err(|_| FastCutError::invalid_filter(format!("invalid number '{}'", number)))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let word = take_while(&mut chars, source, |c| c.is_alphanumeric() || c == '_');
+            tokens.push(match word.to_ascii_lowercase().as_str() {
+                "and" => Token::Op("&&"),
+                "or" => Token::Op("||"),
+                "not" => Token::Op("!"),
+                _ => Token::Column(Column::Name(word.to_string())),
+            });
+        } else if let Some(&(text, op)) = OPERATORS.iter().find(|(text, _)| rest.starts_with(text)) {
+            for _ in 0..text.chars().count() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        } else {
+            return Err(FastCutError::invalid_filter(format!("unexpected '{}'", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Read up to the closing `quote`, with backslash escapes
+fn quoted(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    quote: char,
+    what: &str,
+) -> Result<String> {
+    let mut text = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, 'n')) => text.push('\n'),
+                Some((_, 't')) => text.push('\t'),
+                Some((_, escaped)) => text.push(escaped),
+                None => break,
+            },
+            c if c == quote => return Ok(text),
+            c => text.push(c),
+        }
+    }
+    Err(FastCutError::invalid_filter(format!("unterminated {}", what)))
+}
+
+fn take_while<'a>(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    source: &'a str,
+    keep: impl Fn(char) -> bool,
+) -> &'a str {
+    let start = chars.peek().map_or(source.len(), |&(index, _)| index);
+    let mut end = start;
+    while let Some(&(index, c)) = chars.peek() {
+        if !keep(c) {
+            break;
+        }
+        end = index + c.len_utf8();
+        chars.next();
+    }
+    &source[start..end]
+}
+
+/// Recursive descent, loosest first: `||`, `&&`, `!`, then comparisons
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &'static str) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.position += 1;
+            let expr = self.or()?;
+            return match self.next() {
+                Some(Token::RParen) => Ok(expr),
+                Some(token) => Err(FastCutError::invalid_filter(format!("expected ')' but found {}", token.describe()))),
+                None => Err(FastCutError::invalid_filter("missing ')'")),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) if !matches!(*op, "&&" | "||" | "!") => *op,
+            _ => return Ok(Expr::Truthy(left)),
+        };
+        self.position += 1;
+
+        if op == "=~" || op == "!~" {
+            let pattern = match self.next() {
+                Some(Token::Text(pattern)) => pattern,
+                _ => return Err(FastCutError::invalid_filter(format!("'{}' needs a quoted regex", op))),
+            };
+            let regex = Regex::new(&pattern)
+                .map_err(|e| FastCutError::invalid_filter(format!("invalid regex '{}': {}", pattern, e)))?;
+            return Ok(Expr::Match(left, regex, op == "!~"));
+        }
+
+        let op = match op {
+            "==" => CompareOp::Eq,
+            "!=" => CompareOp::Ne,
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Le,
+            ">" => CompareOp::Gt,
+            _ => CompareOp::Ge,
+        };
+        Ok(Expr::Compare(left, op, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Column(column)) => Ok(Operand::Column(column)),
+            Some(Token::Number(number)) => Ok(Operand::Number(number)),
+            Some(Token::Text(text)) => Ok(Operand::Text(text)),
+            Some(token) => Err(FastCutError::invalid_filter(format!("expected a column or value but found {}", token.describe()))),
+            None => Err(FastCutError::invalid_filter("expression ends too early")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    fn filter(source: &str, header: Option<&[&str]>) -> RowFilter {
+        let header: Option<Vec<String>> = header.map(row);
+        let mut filter = RowFilter::parse(source).unwrap();
+        filter.resolve(header.as_deref()).unwrap();
+        filter
+    }
+
+    #[test]
+    fn test_where_comparisons() {
+        let f = filter(r#"col3 > 100 && $1 == "ERROR""#, None);
+        assert!(f.matches(&row(&["ERROR", "x", "250"])));
+        assert!(!f.matches(&row(&["ERROR", "x", "99"])));
+        assert!(!f.matches(&row(&["WARN", "x", "250"])));
+
+        // Numeric when both sides are numbers, so 9 < 10, but "b" > "10"
+        assert!(filter("$1 < 10", None).matches(&row(&["9"])));
+        assert!(!filter("$1 < 10", None).matches(&row(&["b"])));
+        assert!(filter("$1 >= -2.5", None).matches(&row(&["-1"])));
+        assert!(filter("$1 = 1.0", None).matches(&row(&[" 1 "])));
+        assert!(filter("$2 <> 'a'", None).matches(&row(&["a", "b"])));
+
+        assert!(filter("not ($1 == 'a' or $1 == 'b')", None).matches(&row(&["c"])));
+        assert!(filter("$1 == 'a' || $1 == 'b' && $2 == 'x'", None).matches(&row(&["a", "y"])));
+
+        // Missing fields are empty; a bare column tests for a non-zero value
+        assert!(filter("$5 == ''", None).matches(&row(&["a"])));
+        assert!(filter("$1", None).matches(&row(&["yes"])));
+        assert!(!filter("$1 || $2", None).matches(&row(&["0", ""])));
+    }
+
+    #[test]
+    fn test_where_regex_and_header_names() {
+        let header: &[&str] = &["level", "message", "first name"];
+        let f = filter(r#"level =~ "^(ERROR|FATAL)$" && message !~ 'retry'"#, Some(header));
+        assert!(f.matches(&row(&["FATAL", "disk full"])));
+        assert!(!f.matches(&row(&["FATAL", "retrying"])));
+        assert!(!f.matches(&row(&["ERRORS", "disk full"])));
+
+        assert!(filter("`first name` == 'Ada' && col1 == 'x'", Some(header)).matches(&row(&["x", "", "Ada"])));
+
+        let mut unknown = RowFilter::parse("levle == 'x'").unwrap();
+        let header = row(header);
+        assert!(matches!(unknown.resolve(Some(&header)), Err(FastCutError::FieldNotFound { .. })));
+        assert!(unknown.resolve(None).is_err());
+    }
+
+    #[test]
+    fn test_where_syntax_errors() {
+        for source in ["", "$1 ==", "($1 == 1", "$1 == 1 )", "$0 == 1", "$1 =~ 2", "$1 =~ '('", "'open", "$1 # 2"] {
+            assert!(RowFilter::parse(source).is_err(), "{:?} should not parse", source);
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use crate::dedup::Column;
+use crate::errors::{FastCutError, Result};
+use crate::field_parser::ParsedLine;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::hash_map::RandomState;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Seek, SeekFrom, Write};
+
+/// Default for `--max-groups`
+pub const DEFAULT_MAX_GROUPS: usize = 1_000_000;
+
+/// Temporary files spilled groups are spread over by key, so that each can
+/// be merged back with a fraction of the groups in memory
+const SPILL_PARTITIONS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggregateKind {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Mean,
+}
+
+impl AggregateKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Mean => "mean",
+        }
+    }
+}
+
+/// One `--agg` item: `count`, or a function over a column
+#[derive(Debug, Clone, PartialEq)]
+struct Aggregate {
+    kind: AggregateKind,
+    column: Option<Column>,
+}
+
+impl Aggregate {
+    /// Parse `count`, `count:COL` (rows where COL is not empty), or
+    /// `sum:COL`, `min:COL`, `max:COL`, `mean:COL`
+    fn parse(spec: &str) -> Result<Self> {
+        let (function, column) = match spec.trim().split_once(':') {
+            Some((function, column)) => (function.trim(), Some(Column::parse(column, "--agg")?)),
+            None => (spec.trim(), None),
+        };
+        let kind = match function.to_ascii_lowercase().as_str() {
+            "count" => AggregateKind::Count,
+            "sum" => AggregateKind::Sum,
+            "min" => AggregateKind::Min,
+            "max" => AggregateKind::Max,
+            "mean" | "avg" => AggregateKind::Mean,
+            _ => {
+                return Err(FastCutError::invalid_config(format!(
+                    "--agg: unknown function '{}' (expected count, sum, min, max or mean)",
+                    function
+                )))
+            }
+        };
+        if kind != AggregateKind::Count && column.is_none() {
+            return Err(FastCutError::invalid_config(format!("--agg: {} needs a column, as {}:COL", function, function)));
+        }
+        Ok(Self { kind, column })
+    }
+
+    /// Output column name: `count`, or `sum(price)`
+    fn name(&self, header: &[String]) -> String {
+        match self.column {
+            Some(Column::Index(index)) => {
+                let column = header.get(index).cloned().unwrap_or_else(|| (index + 1).to_string());
+                format!("{}({})", self.kind.name(), column)
+            }
+            Some(Column::Name(ref name)) => format!("{}({})", self.kind.name(), name),
+            None => self.kind.name().to_string(),
+        }
+    }
+}
+
+/// Running state of one aggregate in one group. Every aggregate can be
+/// merged, which is what lets spilled groups be put back together.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Accumulator {
+    rows: u64,
+    numbers: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    /// Count a row; `value` is the aggregate's column, if it has one.
+    /// Values that are not finite numbers are left out of sum, min, max
+    /// and mean, as SQL leaves out NULLs.
+    fn add(&mut self, value: Option<&str>) {
+        let Some(value) = value.map(str::trim) else {
+            self.rows += 1;
+            return;
+        };
+        if !value.is_empty() {
+            self.rows += 1;
+        }
+        if let Some(number) = value.parse::<f64>().ok().filter(|number| number.is_finite()) {
+            self.numbers += 1;
+            self.sum += number;
+            self.min = Some(self.min.map_or(number, |min| min.min(number)));
+            self.max = Some(self.max.map_or(number, |max| max.max(number)));
+        }
+    }
+
+    fn merge(&mut self, other: &Accumulator) {
+        self.rows += other.rows;
+        self.numbers += other.numbers;
+        self.sum += other.sum;
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// The aggregate's output; empty when a group had no numbers for it
+    fn value(&self, kind: AggregateKind) -> String {
+        let number = match kind {
+            AggregateKind::Count => return self.rows.to_string(),
+            AggregateKind::Sum => (self.numbers > 0).then_some(self.sum),
+            AggregateKind::Min => self.min,
+            AggregateKind::Max => self.max,
+            AggregateKind::Mean => (self.numbers > 0).then(|| self.sum / self.numbers as f64),
+        };
+        number.map(|number| number.to_string()).unwrap_or_default()
+    }
+}
+
+/// A group's state, as held in memory or written to a spill file
+#[derive(Debug, Serialize, Deserialize)]
+struct Group {
+    /// When the group was first seen, to print groups in that order
+    seq: u64,
+    key: Vec<String>,
+    states: Vec<Accumulator>,
+}
+
+/// The aggregation stage behind `--group-by COLUMNS --agg LIST`: one output
+/// row per distinct combination of the key columns, holding the keys and
+/// then each aggregate, in first-seen order.
+///
+/// Groups are kept in a hash map of at most `max_groups` entries. Past
+/// that, they are written out to temporary files partitioned by key and
+/// the map starts over. At the end each partition is merged on its own,
+/// holding its distinct groups, about a [`SPILL_PARTITIONS`]th of all of
+/// them, in memory at once; the merged partitions are written back sorted
+/// by first sight and read together to keep the output in that order.
+#[derive(Debug)]
+pub struct Grouper {
+    keys: Vec<Column>,
+    aggregates: Vec<Aggregate>,
+    groups: HashMap<Vec<String>, Group>,
+    next_seq: u64,
+    max_groups: usize,
+    /// Spill files, created at the first spill
+    partitions: Vec<BufWriter<File>>,
+    hasher: RandomState,
+}
+
+impl Grouper {
+    /// `keys` and `aggregates` are the comma-separated `--group-by` and
+    /// `--agg` lists; without `--agg` groups are counted
+    pub fn new(keys: &str, aggregates: Option<&str>, max_groups: usize) -> Result<Self> {
+        let keys = keys
+            .split(',')
+            .map(|column| Column::parse(column, "--group-by"))
+            .collect::<Result<Vec<_>>>()?;
+        let aggregates = aggregates
+            .unwrap_or("count")
+            .split(',')
+            .map(Aggregate::parse)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            keys,
+            aggregates,
+            groups: HashMap::new(),
+            next_seq: 0,
+            max_groups: max_groups.max(1),
+            partitions: Vec::new(),
+            hasher: RandomState::new(),
+        })
+    }
+
+    /// Take the names of the selected fields, resolving columns given by
+    /// name; returns the output header, or `None` when the input has none
+    pub fn set_header(&mut self, header: Option<Vec<String>>) -> Result<Option<Vec<String>>> {
+        for key in &mut self.keys {
+            key.resolve(header.as_deref())?;
+        }
+        for aggregate in &mut self.aggregates {
+            if let Some(ref mut column) = aggregate.column {
+                column.resolve(header.as_deref())?;
+            }
+        }
+        Ok(header.map(|header| {
+            let mut names: Vec<String> = self
+                .keys
+                .iter()
+                .map(|key| match key {
+                    Column::Index(index) => header.get(*index).cloned().unwrap_or_default(),
+                    Column::Name(name) => name.clone(),
+                })
+                .collect();
+            names.extend(self.aggregates.iter().map(|aggregate| aggregate.name(&header)));
+            names
+        }))
+    }
+
+    /// Add a row to its group
+    pub fn observe(&mut self, line: &ParsedLine) -> Result<()> {
+        let field = |column: &Column| match column {
+            Column::Index(index) => Ok(line.fields.get(*index).map_or("", String::as_str)),
+            Column::Name(_) => Err(FastCutError::invalid_config("--group-by: column names need --header")),
+        };
+        let key = self
+            .keys
+            .iter()
+            .map(|column| field(column).map(str::to_string))
+            .collect::<Result<Vec<_>>>()?;
+
+        if !self.groups.contains_key(&key) {
+            if self.groups.len() >= self.max_groups {
+                self.spill()?;
+            }
+            let group = Group {
+                seq: self.next_seq,
+                key: key.clone(),
+                states: vec![Accumulator::default(); self.aggregates.len()],
+            };
+            self.next_seq += 1;
+            self.groups.insert(key.clone(), group);
+        }
+        let group = self.groups.get_mut(&key).expect("group was just inserted");
+        for (aggregate, state) in self.aggregates.iter().zip(&mut group.states) {
+            state.add(aggregate.column.as_ref().map(field).transpose()?);
+        }
+        Ok(())
+    }
+
+    /// Write the groups in memory to the spill files and forget them
+    fn spill(&mut self) -> Result<()> {
+        if self.partitions.is_empty() {
+            for _ in 0..SPILL_PARTITIONS {
+                self.partitions.push(BufWriter::new(tempfile::tempfile()?));
+            }
+        }
+        for (_, group) in self.groups.drain() {
+            let partition = self.hasher.hash_one(&group.key) as usize % SPILL_PARTITIONS;
+            let writer = &mut self.partitions[partition];
+            serde_json::to_writer(&mut *writer, &group)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Hand each group's output row to `emit`, emptying the stage
+    pub fn finish(&mut self, mut emit: impl FnMut(ParsedLine) -> Result<()>) -> Result<()> {
+        if !self.partitions.is_empty() {
+            self.spill()?;
+        }
+        let partitions = std::mem::take(&mut self.partitions);
+        let aggregates = &self.aggregates;
+        let mut line_number = 0;
+        let mut emit_group = |group: Group| {
+            let mut fields = group.key;
+            fields.extend(aggregates.iter().zip(&group.states).map(|(aggregate, state)| state.value(aggregate.kind)));
+            line_number += 1;
+            emit(ParsedLine {
+                line_number,
+                raw_line: fields.join(","),
+                fields,
+                padded_fields: 0,
+            })
+        };
+
+        if partitions.is_empty() {
+            for group in by_first_sight(std::mem::take(&mut self.groups)) {
+                emit_group(group)?;
+            }
+            return Ok(());
+        }
+
+        let mut runs = Vec::with_capacity(partitions.len());
+        for writer in partitions {
+            let mut merged: HashMap<Vec<String>, Group> = HashMap::new();
+            for line in rewind(writer)? {
+                let group: Group = serde_json::from_str(&line?)?;
+                match merged.get_mut(&group.key) {
+                    Some(existing) => {
+                        existing.seq = existing.seq.min(group.seq);
+                        for (state, other) in existing.states.iter_mut().zip(&group.states) {
+                            state.merge(other);
+                        }
+                    }
+                    None => {
+                        merged.insert(group.key.clone(), group);
+                    }
+                }
+            }
+            let mut run = BufWriter::new(tempfile::tempfile()?);
+            for group in by_first_sight(merged) {
+                serde_json::to_writer(&mut run, &group)?;
+                run.write_all(b"\n")?;
+            }
+            runs.push(rewind(run)?);
+        }
+
+        // Each run is in first-seen order; take the earliest head each time
+        let mut heads = runs.iter_mut().map(next_group).collect::<Result<Vec<_>>>()?;
+        let mut queue: BinaryHeap<Reverse<(u64, usize)>> = heads
+            .iter()
+            .enumerate()
+            .filter_map(|(run, group)| group.as_ref().map(|group| Reverse((group.seq, run))))
+            .collect();
+        while let Some(Reverse((_, run))) = queue.pop() {
+            let group = heads[run].take().expect("queued runs have a head");
+            heads[run] = next_group(&mut runs[run])?;
+            if let Some(ref next) = heads[run] {
+                queue.push(Reverse((next.seq, run)));
+            }
+            emit_group(group)?;
+        }
+        Ok(())
+    }
+}
+
+fn by_first_sight(groups: HashMap<Vec<String>, Group>) -> Vec<Group> {
+    let mut groups: Vec<Group> = groups.into_values().collect();
+    groups.sort_unstable_by_key(|group| group.seq);
+    groups
+}
+
+/// The lines written to a spill file, from its start
+fn rewind(writer: BufWriter<File>) -> Result<Lines<BufReader<File>>> {
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(BufReader::new(file).lines())
+}
+
+fn next_group(run: &mut Lines<BufReader<File>>) -> Result<Option<Group>> {
+    match run.next() {
+        Some(line) => Ok(Some(serde_json::from_str(&line?)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(fields: &[&str]) -> ParsedLine {
+        ParsedLine {
+            line_number: 0,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            raw_line: fields.join(","),
+            padded_fields: 0,
+        }
+    }
+
+    fn names(fields: &[&str]) -> Option<Vec<String>> {
+        Some(fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    fn rows(grouper: &mut Grouper) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        grouper
+            .finish(|line| {
+                rows.push(line.fields);
+                Ok(())
+            })
+            .unwrap();
+        rows
+    }
+
+    const SALES: [[&str; 3]; 5] = [
+        ["eu", "widget", "10"],
+        ["us", "widget", "4.5"],
+        ["eu", "gadget", "2"],
+        ["eu", "widget", "n/a"],
+        ["us", "widget", "1.5"],
+    ];
+
+    #[test]
+    fn test_group_by() {
+        let mut grouper = Grouper::new("region", Some("count,sum:amount,min:3,max:amount,mean:amount"), 100).unwrap();
+        let header = grouper.set_header(names(&["region", "product", "amount"])).unwrap();
+        assert_eq!(
+            header,
+            names(&["region", "count", "sum(amount)", "min(amount)", "max(amount)", "mean(amount)"])
+        );
+        for row in SALES {
+            grouper.observe(&line(&row)).unwrap();
+        }
+        assert_eq!(
+            rows(&mut grouper),
+            vec![vec!["eu", "3", "12", "2", "10", "6"], vec!["us", "2", "6", "1.5", "4.5", "3"]]
+        );
+
+        let mut unnamed = Grouper::new("1,2", None, 100).unwrap();
+        assert_eq!(unnamed.set_header(None).unwrap(), None);
+        unnamed.observe(&line(&["a", "x", "1"])).unwrap();
+        unnamed.observe(&line(&["a", "x", "2"])).unwrap();
+        assert_eq!(rows(&mut unnamed), vec![vec!["a", "x", "2"]]);
+
+        assert!(Grouper::new("1", Some("sum"), 100).is_err());
+        assert!(Grouper::new("1", Some("median:2"), 100).is_err());
+        assert!(Grouper::new("region", None, 100).unwrap().set_header(None).is_err());
+    }
+
+    #[test]
+    fn test_spilled_groups_merge() {
+        let mut in_memory = Grouper::new("1,2", Some("count,sum:3,min:3"), 100).unwrap();
+        let mut spilling = Grouper::new("1,2", Some("count,sum:3,min:3"), 1).unwrap();
+        for grouper in [&mut in_memory, &mut spilling] {
+            grouper.set_header(None).unwrap();
+            for row in SALES.iter().chain(SALES.iter()) {
+                grouper.observe(&line(row)).unwrap();
+            }
+        }
+        assert!(!spilling.partitions.is_empty());
+
+        // Still in first-seen order
+        assert_eq!(rows(&mut spilling), rows(&mut in_memory));
+
+        let mut letters = Grouper::new("1", None, 1).unwrap();
+        letters.set_header(None).unwrap();
+        for letter in ["a", "b", "a", "c", "b"] {
+            letters.observe(&line(&[letter])).unwrap();
+        }
+        let keys: Vec<String> = rows(&mut letters).into_iter().map(|row| row[0].clone()).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+    }
+}
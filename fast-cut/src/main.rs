@@ -1,8 +1,17 @@
 mod cli;
+mod dedup;
+mod encoding;
 mod errors;
 mod field_parser;
+mod filter;
+mod group;
 mod output;
+mod reshape;
+mod row_select;
+mod split;
 mod stream_processor;
+mod table;
+mod validate;
 
 use cli::Args;
 use clap::Parser;
@@ -21,6 +30,22 @@ fn main() -> Result<()> {
         }
     }
 
+    if args.validate {
+        let validation = validate::validate_inputs(&args)?;
+        let mut out = std::io::stdout().lock();
+        match args.format {
+            cli::OutputFormat::Json => validation.write_json(&mut out)?,
+            cli::OutputFormat::Csv => validation.write_csv(&mut out)?,
+            cli::OutputFormat::Text | cli::OutputFormat::Table => validation.write_text(&mut out)?,
+        }
+        eprintln!("fcut: {}", validation.summary());
+        if !validation.is_valid() {
+            drop(out);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Validate arguments
     if args.fields.trim().is_empty() {
         return Err(FastCutError::invalid_config("No fields specified"));
@@ -50,6 +75,10 @@ fn main() -> Result<()> {
     // Process files
     processor.process_files(&args.files, &args)?;
 
+    if let Some(summary) = processor.error_summary().describe() {
+        eprintln!("fcut: {}", summary);
+    }
+
     if args.verbose {
         eprintln!("Processing completed successfully");
     }
@@ -89,7 +118,31 @@ mod tests {
             color: cli::ColorOption::Never,
             threads: None,
             buffer_size_kb: 64,
+            table_sample: None,
+            max_width: None,
+            ellipsis: "…".to_string(),
+            split_by: None,
+            out_dir: None,
+            max_open_files: crate::split::DEFAULT_MAX_OPEN_FILES,
             non_empty_only: false,
+            where_clause: None,
+            input_encoding: cli::InputEncoding::Utf8,
+            encoding_errors: cli::EncodingErrors::Strict,
+            head: None,
+            tail: None,
+            sample: None,
+            seed: None,
+            unique: false,
+            count_unique: false,
+            distinct: None,
+            transpose: false,
+            pivot: None,
+            max_cells: reshape::DEFAULT_MAX_CELLS,
+            group_by: None,
+            agg: None,
+            max_groups: group::DEFAULT_MAX_GROUPS,
+            on_error: cli::ErrorPolicy::Skip,
+            validate: false,
             verbose: false,
         };
 
@@ -119,7 +172,31 @@ mod tests {
             color: cli::ColorOption::Never,
             threads: None,
             buffer_size_kb: 64,
+            table_sample: None,
+            max_width: None,
+            ellipsis: "…".to_string(),
+            split_by: None,
+            out_dir: None,
+            max_open_files: crate::split::DEFAULT_MAX_OPEN_FILES,
             non_empty_only: false,
+            where_clause: None,
+            input_encoding: cli::InputEncoding::Utf8,
+            encoding_errors: cli::EncodingErrors::Strict,
+            head: None,
+            tail: None,
+            sample: None,
+            seed: None,
+            unique: false,
+            count_unique: false,
+            distinct: None,
+            transpose: false,
+            pivot: None,
+            max_cells: reshape::DEFAULT_MAX_CELLS,
+            group_by: None,
+            agg: None,
+            max_groups: group::DEFAULT_MAX_GROUPS,
+            on_error: cli::ErrorPolicy::Skip,
+            validate: false,
             verbose: false,
         };
 
@@ -151,7 +228,31 @@ mod tests {
             color: cli::ColorOption::Never,
             threads: None,
             buffer_size_kb: 64,
+            table_sample: None,
+            max_width: None,
+            ellipsis: "…".to_string(),
+            split_by: None,
+            out_dir: None,
+            max_open_files: crate::split::DEFAULT_MAX_OPEN_FILES,
             non_empty_only: false,
+            where_clause: None,
+            input_encoding: cli::InputEncoding::Utf8,
+            encoding_errors: cli::EncodingErrors::Strict,
+            head: None,
+            tail: None,
+            sample: None,
+            seed: None,
+            unique: false,
+            count_unique: false,
+            distinct: None,
+            transpose: false,
+            pivot: None,
+            max_cells: reshape::DEFAULT_MAX_CELLS,
+            group_by: None,
+            agg: None,
+            max_groups: group::DEFAULT_MAX_GROUPS,
+            on_error: cli::ErrorPolicy::Skip,
+            validate: false,
             verbose: false,
         };
 
@@ -179,7 +280,31 @@ mod tests {
             color: cli::ColorOption::Never,
             threads: None,
             buffer_size_kb: 64,
+            table_sample: None,
+            max_width: None,
+            ellipsis: "…".to_string(),
+            split_by: None,
+            out_dir: None,
+            max_open_files: crate::split::DEFAULT_MAX_OPEN_FILES,
             non_empty_only: false,
+            where_clause: None,
+            input_encoding: cli::InputEncoding::Utf8,
+            encoding_errors: cli::EncodingErrors::Strict,
+            head: None,
+            tail: None,
+            sample: None,
+            seed: None,
+            unique: false,
+            count_unique: false,
+            distinct: None,
+            transpose: false,
+            pivot: None,
+            max_cells: reshape::DEFAULT_MAX_CELLS,
+            group_by: None,
+            agg: None,
+            max_groups: group::DEFAULT_MAX_GROUPS,
+            on_error: cli::ErrorPolicy::Skip,
+            validate: false,
             verbose: false,
         };
 
@@ -9,6 +9,8 @@ pub struct OutputFormatter {
     format: OutputFormat,
     use_colors: bool,
     output_delimiter: String,
+    /// Whether `output_delimiter` was chosen by the caller rather than defaulted
+    explicit_delimiter: bool,
     line_numbers: bool,
     header_names: Option<Vec<String>>,
 }
@@ -20,11 +22,13 @@ impl OutputFormatter {
         output_delimiter: Option<String>,
         line_numbers: bool,
     ) -> Self {
+        let explicit_delimiter = output_delimiter.is_some();
         let delimiter = output_delimiter.unwrap_or_else(|| {
             match format {
                 OutputFormat::Csv => ",".to_string(),
                 OutputFormat::Json => ",".to_string(),
                 OutputFormat::Text => "\t".to_string(),
+                OutputFormat::Table => "  ".to_string(),
             }
         });
 
@@ -32,18 +36,26 @@ impl OutputFormatter {
             format,
             use_colors,
             output_delimiter: delimiter,
+            explicit_delimiter,
             line_numbers,
             header_names: None,
         }
     }
 
+    /// Adopt an auto-detected input delimiter unless one was set explicitly
+    pub fn mirror_input_delimiter(&mut self, delimiter: &str) {
+        if !self.explicit_delimiter {
+            self.output_delimiter = delimiter.to_string();
+        }
+    }
+
     pub fn set_header_names(&mut self, names: Vec<String>) {
         self.header_names = Some(names);
     }
 
     pub fn format_header(&self, header_fields: &[String]) -> Result<String> {
         match self.format {
-            OutputFormat::Text => {
+            OutputFormat::Text | OutputFormat::Table => {
                 let mut output = String::new();
                 if self.line_numbers {
                     output.push_str("line");
@@ -70,7 +82,7 @@ impl OutputFormatter {
                 record.extend(header_fields.iter().map(|s| s.as_str()));
                 
                 wtr.write_record(&record)?;
-                let data = wtr.into_inner().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                let data = wtr.into_inner().map_err(std::io::Error::other)?;
                 Ok(String::from_utf8_lossy(&data).trim_end().to_string())
             }
             OutputFormat::Json => {
@@ -86,7 +98,7 @@ impl OutputFormatter {
 
     pub fn format_line(&self, parsed_line: &ParsedLine) -> Result<String> {
         match self.format {
-            OutputFormat::Text => self.format_text_line(parsed_line),
+            OutputFormat::Text | OutputFormat::Table => self.format_text_line(parsed_line),
             OutputFormat::Csv => self.format_csv_line(parsed_line),
             OutputFormat::Json => self.format_json_line(parsed_line),
         }
@@ -140,7 +152,7 @@ impl OutputFormatter {
         record.extend(parsed_line.fields.iter().cloned());
         
         wtr.write_record(&record)?;
-        let data = wtr.into_inner().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let data = wtr.into_inner().map_err(std::io::Error::other)?;
         Ok(String::from_utf8_lossy(&data).trim_end().to_string())
     }
 
@@ -156,7 +168,7 @@ impl OutputFormatter {
             let mut fields_obj = HashMap::new();
             for (i, field) in parsed_line.fields.iter().enumerate() {
                 let field_name = headers.get(i)
-                    .map(|s| s.clone())
+                    .cloned()
                     .unwrap_or_else(|| format!("field_{}", i + 1));
                 fields_obj.insert(field_name, Value::String(field.clone()));
             }
@@ -225,6 +237,7 @@ mod tests {
             line_number: 42,
             fields: vec!["field1".to_string(), "field2".to_string()],
             raw_line: "field1,field2".to_string(),
+            padded_fields: 0,
         };
         
         let result = formatter.format_line(&parsed_line).unwrap();
@@ -244,6 +257,7 @@ mod tests {
             line_number: 1,
             fields: vec!["hello, world".to_string(), "test".to_string()],
             raw_line: "hello, world,test".to_string(),
+            padded_fields: 0,
         };
         
         let result = formatter.format_line(&parsed_line).unwrap();
@@ -265,6 +279,7 @@ mod tests {
             line_number: 1,
             fields: vec!["John".to_string(), "30".to_string()],
             raw_line: "John,30".to_string(),
+            padded_fields: 0,
         };
         
         let result = formatter.format_line(&parsed_line).unwrap();
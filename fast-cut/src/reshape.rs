@@ -0,0 +1,274 @@
+use crate::dedup::Column;
+use crate::errors::{FastCutError, Result};
+use crate::field_parser::ParsedLine;
+use std::collections::HashMap;
+
+/// Default for `--max-cells`
+pub const DEFAULT_MAX_CELLS: usize = 1_000_000;
+
+/// What `--transpose` or `--pivot` does with the rows
+#[derive(Debug)]
+enum Mode {
+    /// Rows become columns; the header, if any, becomes the first column
+    Transpose { rows: Vec<Vec<String>> },
+    /// Long to wide: one row per distinct combination of the other fields,
+    /// with a column per distinct value of `key` holding `value`
+    Pivot {
+        key: Column,
+        value: Column,
+        /// Key values in first-seen order, and where each one is in `keys`
+        keys: Vec<String>,
+        key_positions: HashMap<String, usize>,
+        /// Identifying fields of each output row, with its values by key
+        groups: Vec<(Vec<String>, Vec<Option<String>>)>,
+        group_positions: HashMap<Vec<String>, usize>,
+    },
+}
+
+/// The reshaping stage behind `--transpose` and `--pivot KEY,VALUE`, run
+/// on the rows that remain after every other stage.
+///
+/// Both need all of the input before they can print anything, so rows are
+/// held in memory, at most `max_cells` fields of them; more input than that
+/// is an error rather than a slow trip into swap.
+#[derive(Debug)]
+pub struct Reshaper {
+    mode: Mode,
+    max_cells: usize,
+    cells: usize,
+    /// Names of the selected fields, when the input has a header
+    header: Option<Vec<String>>,
+}
+
+impl Reshaper {
+    pub fn transpose(max_cells: usize) -> Self {
+        Self {
+            mode: Mode::Transpose { rows: Vec::new() },
+            max_cells,
+            cells: 0,
+            header: None,
+        }
+    }
+
+    /// `columns` is `KEY,VALUE`: two selected fields, by position in the
+    /// `--fields` list or by header name
+    pub fn pivot(columns: &str, max_cells: usize) -> Result<Self> {
+        let (key, value) = columns
+            .split_once(',')
+            .ok_or_else(|| FastCutError::invalid_config("--pivot takes two columns, KEY,VALUE"))?;
+        Ok(Self {
+            mode: Mode::Pivot {
+                key: Column::parse(key, "--pivot")?,
+                value: Column::parse(value, "--pivot")?,
+                keys: Vec::new(),
+                key_positions: HashMap::new(),
+                groups: Vec::new(),
+                group_positions: HashMap::new(),
+            },
+            max_cells,
+            cells: 0,
+            header: None,
+        })
+    }
+
+    /// Take the names of the selected fields, resolving `--pivot` columns
+    /// given by name; `None` when the input has no header
+    pub fn set_header(&mut self, header: Option<Vec<String>>) -> Result<()> {
+        if let Mode::Pivot { ref mut key, ref mut value, .. } = self.mode {
+            key.resolve(header.as_deref())?;
+            value.resolve(header.as_deref())?;
+            if key == value {
+                return Err(FastCutError::invalid_config("--pivot: KEY and VALUE must be different columns"));
+            }
+        }
+        self.header = header;
+        Ok(())
+    }
+
+    /// Hold a row until the end of input
+    pub fn observe(&mut self, line: ParsedLine) -> Result<()> {
+        let added = match self.mode {
+            Mode::Transpose { ref mut rows } => {
+                let added = line.fields.len();
+                rows.push(line.fields);
+                added
+            }
+            Mode::Pivot {
+                ref key,
+                ref value,
+                ref mut keys,
+                ref mut key_positions,
+                ref mut groups,
+                ref mut group_positions,
+            } => {
+                let (Column::Index(key), Column::Index(value)) = (key, value) else {
+                    return Err(FastCutError::invalid_config("--pivot: column names need --header"));
+                };
+                let cell = |index: &usize| line.fields.get(*index).cloned().unwrap_or_default();
+                let (key_value, cell_value) = (cell(key), cell(value));
+                let fields: Vec<String> = line
+                    .fields
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| index != key && index != value)
+                    .map(|(_, field)| field)
+                    .collect();
+
+                let mut added = 1;
+                let column = *key_positions.entry(key_value.clone()).or_insert_with(|| {
+                    keys.push(key_value);
+                    keys.len() - 1
+                });
+                let group = match group_positions.get(&fields) {
+                    Some(&group) => group,
+                    None => {
+                        added += fields.len();
+                        group_positions.insert(fields.clone(), groups.len());
+                        groups.push((fields, Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+                let values = &mut groups[group].1;
+                if values.len() <= column {
+                    values.resize(column + 1, None);
+                }
+                // A repeated key for the same row keeps the last value
+                values[column] = Some(cell_value);
+                added
+            }
+        };
+
+        self.cells += added;
+        if self.cells > self.max_cells {
+            return Err(FastCutError::reshape_too_large(self.max_cells));
+        }
+        Ok(())
+    }
+
+    /// The reshaped header, if the output has one, and rows, emptying the
+    /// stage. A pivot always has a header, naming its key columns; its other
+    /// columns are unnamed without an input header.
+    pub fn finish(&mut self) -> (Option<Vec<String>>, Vec<ParsedLine>) {
+        self.cells = 0;
+        let header = self.header.take();
+        let (header, rows) = match self.mode {
+            Mode::Transpose { ref mut rows } => {
+                let matrix: Vec<Vec<String>> = header.into_iter().chain(rows.drain(..)).collect();
+                let width = matrix.iter().map(Vec::len).max().unwrap_or(0);
+                let transposed: Vec<Vec<String>> = (0..width)
+                    .map(|column| matrix.iter().map(|row| row.get(column).cloned().unwrap_or_default()).collect())
+                    .collect();
+                (None, transposed)
+            }
+            Mode::Pivot {
+                ref key,
+                ref value,
+                ref mut keys,
+                ref mut key_positions,
+                ref mut groups,
+                ref mut group_positions,
+            } => {
+                key_positions.clear();
+                group_positions.clear();
+                let identifying = groups.first().map_or(0, |(fields, _)| fields.len());
+                let mut names: Vec<String> = match header {
+                    Some(header) => header
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(index, _)| Column::Index(*index) != *key && Column::Index(*index) != *value)
+                        .map(|(_, name)| name)
+                        .collect(),
+                    None => vec![String::new(); identifying],
+                };
+                let width = keys.len();
+                names.append(keys);
+
+                let rows = groups
+                    .drain(..)
+                    .map(|(mut fields, mut values)| {
+                        values.resize(width, None);
+                        fields.extend(values.into_iter().map(Option::unwrap_or_default));
+                        fields
+                    })
+                    .collect();
+                (Some(names), rows)
+            }
+        };
+
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, fields)| ParsedLine {
+                line_number: index + 1,
+                raw_line: fields.join(","),
+                fields,
+                padded_fields: 0,
+            })
+            .collect();
+        (header, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(fields: &[&str]) -> ParsedLine {
+        ParsedLine {
+            line_number: 0,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            raw_line: fields.join(","),
+            padded_fields: 0,
+        }
+    }
+
+    fn names(fields: &[&str]) -> Option<Vec<String>> {
+        Some(fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    fn fields(rows: Vec<ParsedLine>) -> Vec<Vec<String>> {
+        rows.into_iter().map(|row| row.fields).collect()
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut reshaper = Reshaper::transpose(DEFAULT_MAX_CELLS);
+        reshaper.set_header(names(&["name", "age"])).unwrap();
+        reshaper.observe(line(&["ann", "31"])).unwrap();
+        reshaper.observe(line(&["bo"])).unwrap();
+
+        let (header, rows) = reshaper.finish();
+        assert!(header.is_none());
+        assert_eq!(fields(rows), vec![vec!["name", "ann", "bo"], vec!["age", "31", ""]]);
+    }
+
+    #[test]
+    fn test_pivot() {
+        let mut reshaper = Reshaper::pivot("metric,value", DEFAULT_MAX_CELLS).unwrap();
+        reshaper.set_header(names(&["host", "metric", "value"])).unwrap();
+        for row in [["web1", "cpu", "12"], ["web1", "mem", "40"], ["web2", "mem", "71"], ["web1", "cpu", "15"]] {
+            reshaper.observe(line(&row)).unwrap();
+        }
+
+        let (header, rows) = reshaper.finish();
+        assert_eq!(header, names(&["host", "cpu", "mem"]));
+        assert_eq!(fields(rows), vec![vec!["web1", "15", "40"], vec!["web2", "", "71"]]);
+
+        let mut unnamed = Reshaper::pivot("2,3", DEFAULT_MAX_CELLS).unwrap();
+        unnamed.set_header(None).unwrap();
+        unnamed.observe(line(&["a", "x", "1"])).unwrap();
+        assert_eq!(unnamed.finish().0, names(&["", "x"]));
+
+        assert!(Reshaper::pivot("metric", DEFAULT_MAX_CELLS).is_err());
+        let mut same = Reshaper::pivot("1,1", DEFAULT_MAX_CELLS).unwrap();
+        assert!(same.set_header(None).is_err());
+    }
+
+    #[test]
+    fn test_cell_limit() {
+        let mut reshaper = Reshaper::transpose(4);
+        reshaper.observe(line(&["a", "b"])).unwrap();
+        reshaper.observe(line(&["c", "d"])).unwrap();
+        assert!(matches!(reshaper.observe(line(&["e"])), Err(FastCutError::ReshapeTooLarge { limit: 4 })));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::field_parser::ParsedLine;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which rows `--head`, `--tail` or `--sample` keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Head(usize),
+    Tail(usize),
+    Sample(usize),
+}
+
+/// The last stage before output: keeps the first N, last N or a uniform
+/// random N of the rows that made it through extraction, `--where` and
+/// deduplication.
+///
+/// Head passes rows straight through; tail and sample hold at most N rows
+/// and release them, in input order, at the end of input.
+#[derive(Debug)]
+pub struct RowSelector {
+    selection: Selection,
+    /// Rows seen so far
+    seen: usize,
+    /// Held rows with their position in the input, for keeping input order
+    held: VecDeque<(usize, ParsedLine)>,
+    rng: SplitMix64,
+}
+
+impl RowSelector {
+    /// `seed` makes `--sample` repeatable; without one it varies per run
+    pub fn new(selection: Selection, seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+            nanos ^ u64::from(std::process::id()).rotate_left(32)
+        });
+        Self {
+            selection,
+            seen: 0,
+            held: VecDeque::new(),
+            rng: SplitMix64(seed),
+        }
+    }
+
+    /// Whether no later row can be selected, so reading may stop
+    pub fn is_done(&self) -> bool {
+        matches!(self.selection, Selection::Head(limit) if self.seen >= limit)
+    }
+
+    /// Take a row; returns it when it should be output right away
+    pub fn observe(&mut self, line: ParsedLine) -> Option<ParsedLine> {
+        let position = self.seen;
+        self.seen += 1;
+        match self.selection {
+            Selection::Head(limit) => (position < limit).then_some(line),
+            Selection::Tail(0) | Selection::Sample(0) => None,
+            Selection::Tail(limit) => {
+                if self.held.len() == limit {
+                    self.held.pop_front();
+                }
+                self.held.push_back((position, line));
+                None
+            }
+            // Algorithm R: row i replaces a held row with probability N/(i+1)
+            Selection::Sample(size) => {
+                if self.held.len() < size {
+                    self.held.push_back((position, line));
+                } else {
+                    let slot = self.rng.below(position as u64 + 1) as usize;
+                    if slot < size {
+                        self.held[slot] = (position, line);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// The held rows in input order, emptying the stage
+    pub fn finish(&mut self) -> Vec<ParsedLine> {
+        let mut held: Vec<(usize, ParsedLine)> = self.held.drain(..).collect();
+        held.sort_by_key(|(position, _)| *position);
+        held.into_iter().map(|(_, line)| line).collect()
+    }
+}
+
+/// A small, fast generator; sampling needs no more than that
+#[derive(Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next()) * u128::from(bound)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(number: usize) -> ParsedLine {
+        ParsedLine {
+            line_number: number,
+            fields: vec![number.to_string()],
+            raw_line: number.to_string(),
+            padded_fields: 0,
+        }
+    }
+
+    fn select(selection: Selection, rows: usize, seed: u64) -> Vec<usize> {
+        let mut selector = RowSelector::new(selection, Some(seed));
+        let mut kept: Vec<usize> = (1..=rows)
+            .filter_map(|number| selector.observe(line(number)))
+            .map(|line| line.line_number)
+            .collect();
+        kept.extend(selector.finish().into_iter().map(|line| line.line_number));
+        kept
+    }
+
+    #[test]
+    fn test_head_and_tail() {
+        assert_eq!(select(Selection::Head(3), 10, 0), vec![1, 2, 3]);
+        assert_eq!(select(Selection::Tail(3), 10, 0), vec![8, 9, 10]);
+        assert_eq!(select(Selection::Tail(5), 2, 0), vec![1, 2]);
+        assert!(select(Selection::Tail(0), 4, 0).is_empty());
+
+        let mut head = RowSelector::new(Selection::Head(1), None);
+        assert!(!head.is_done());
+        head.observe(line(1));
+        assert!(head.is_done());
+    }
+
+    #[test]
+    fn test_sample() {
+        let sample = select(Selection::Sample(5), 1000, 7);
+        assert_eq!(sample.len(), 5);
+        assert!(sample.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sample, select(Selection::Sample(5), 1000, 7));
+        assert_eq!(select(Selection::Sample(5), 3, 7), vec![1, 2, 3]);
+
+        // Every row is about as likely to be picked
+        let mut picks = [0usize; 10];
+        for seed in 0..2000 {
+            for number in select(Selection::Sample(2), 10, seed) {
+                picks[number - 1] += 1;
+            }
+        }
+        assert!(picks.iter().all(|&count| (300..500).contains(&count)), "{:?}", picks);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::cli::OutputFormat;
+use crate::dedup::Column;
+use crate::errors::{FastCutError, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Most output files `--split-by` keeps open at once by default, well under
+/// the usual limit of 1024 descriptors per process
+pub const DEFAULT_MAX_OPEN_FILES: usize = 128;
+
+/// The sink behind `--split-by`: each output row goes to a file in
+/// `--out-dir` named after its value in one input column, such as a date
+/// or tenant.
+///
+/// Only the most recently used writers stay open. A file closed to make
+/// room is appended to when its value comes up again, so any number of
+/// distinct values works within `max_open` descriptors. Files are
+/// truncated the first time a run writes to them, and each starts with the
+/// header when there is one.
+#[derive(Debug)]
+pub struct Splitter {
+    column: Column,
+    out_dir: PathBuf,
+    extension: &'static str,
+    max_open: usize,
+    header: Option<String>,
+    /// Open writers by file name, with when each was last written to
+    open: HashMap<String, (BufWriter<File>, u64)>,
+    /// Files this run has created, which are appended to when reopened
+    created: HashSet<String>,
+    clock: u64,
+}
+
+impl Splitter {
+    pub fn new(column: &str, out_dir: &Path, format: &OutputFormat, max_open: usize) -> Result<Self> {
+        if max_open == 0 {
+            return Err(FastCutError::invalid_config("--max-open-files must be at least 1"));
+        }
+        fs::create_dir_all(out_dir)?;
+        Ok(Self {
+            column: Column::parse(column, "--split-by")?,
+            out_dir: out_dir.to_path_buf(),
+            extension: match format {
+                OutputFormat::Csv => "csv",
+                OutputFormat::Json => "jsonl",
+                OutputFormat::Text | OutputFormat::Table => "txt",
+            },
+            max_open,
+            header: None,
+            open: HashMap::new(),
+            created: HashSet::new(),
+            clock: 0,
+        })
+    }
+
+    /// Turn a header name into its column; without a header only numbers
+    /// are accepted
+    pub fn resolve(&mut self, header: Option<&[String]>) -> Result<()> {
+        self.column.resolve(header)
+    }
+
+    /// The formatted header line every file starts with
+    pub fn set_header(&mut self, header: String) {
+        self.header = Some(header);
+    }
+
+    /// Write an output line to the file for its row's value in the split
+    /// column, taken from all of the row's input fields
+    pub fn write(&mut self, all_fields: &[String], output_line: &str) -> Result<()> {
+        let index = match self.column {
+            Column::Index(index) => index,
+            Column::Name(ref name) => return Err(FastCutError::field_not_found(name.clone(), Vec::new())),
+        };
+        let value = all_fields.get(index).ok_or(FastCutError::InvalidFieldIndex {
+            index: index + 1,
+            field_count: all_fields.len(),
+        })?;
+        let name = format!("{}.{}", file_stem(value), self.extension);
+
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some((writer, last_used)) = self.open.get_mut(&name) {
+            *last_used = clock;
+            return Ok(writeln!(writer, "{}", output_line)?);
+        }
+
+        if self.open.len() >= self.max_open {
+            self.close_least_recent()?;
+        }
+        let mut writer = self.open_file(&name)?;
+        writeln!(writer, "{}", output_line)?;
+        self.open.insert(name, (writer, clock));
+        Ok(())
+    }
+
+    fn open_file(&mut self, name: &str) -> Result<BufWriter<File>> {
+        let path = self.out_dir.join(name);
+        let reopened = self.created.contains(name);
+        let file = if reopened {
+            OpenOptions::new().append(true).open(&path)?
+        } else {
+            File::create(&path)?
+        };
+        let mut writer = BufWriter::new(file);
+        if !reopened {
+            if let Some(ref header) = self.header {
+                writeln!(writer, "{}", header)?;
+            }
+            self.created.insert(name.to_string());
+        }
+        Ok(writer)
+    }
+
+    fn close_least_recent(&mut self) -> Result<()> {
+        let least_recent = self
+            .open
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(name, _)| name.clone());
+        if let Some((mut writer, _)) = least_recent.and_then(|name| self.open.remove(&name)) {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flush and close every file, returning how many were written
+    pub fn finish(&mut self) -> Result<usize> {
+        for (_, (mut writer, _)) in self.open.drain() {
+            writer.flush()?;
+        }
+        Ok(self.created.len())
+    }
+}
+
+/// A file name for `value`: path separators, control characters and names
+/// that mean something to the filesystem are replaced, so every value stays
+/// inside the output directory
+fn file_stem(value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        return "_empty".to_string();
+    }
+    if value == "." || value == ".." {
+        return value.replace('.', "_");
+    }
+    value
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(line: &str) -> Vec<String> {
+        line.split(',').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_file_stems_stay_in_directory() {
+        assert_eq!(file_stem("2026-03-01"), "2026-03-01");
+        assert_eq!(file_stem("../etc/passwd"), ".._etc_passwd");
+        assert_eq!(file_stem(".."), "__");
+        assert_eq!(file_stem("  "), "_empty");
+        assert_eq!(file_stem("a\tb"), "a_b");
+    }
+
+    #[test]
+    fn test_split_reopens_closed_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut splitter = Splitter::new("tenant", dir.path(), &OutputFormat::Csv, 2)?;
+        let header = fields("tenant,value");
+        splitter.resolve(Some(&header))?;
+        splitter.set_header("tenant,value".to_string());
+
+        // Three tenants through two open files: "a" is closed and reopened
+        for line in ["a,1", "b,2", "c,3", "a,4", "b,5"] {
+            splitter.write(&fields(line), line)?;
+        }
+        assert_eq!(splitter.finish()?, 3);
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("a.csv"), "tenant,value\na,1\na,4\n");
+        assert_eq!(read("b.csv"), "tenant,value\nb,2\nb,5\n");
+        assert_eq!(read("c.csv"), "tenant,value\nc,3\n");
+
+        // Rows without the column are malformed
+        let mut third = Splitter::new("3", dir.path(), &OutputFormat::Text, 2)?;
+        assert!(matches!(
+            third.write(&fields("a,b"), "a,b"),
+            Err(FastCutError::InvalidFieldIndex { index: 3, field_count: 2 })
+        ));
+        assert!(Splitter::new("1", dir.path(), &OutputFormat::Text, 0).is_err());
+        Ok(())
+    }
+}
@@ -1,7 +1,15 @@
-use crate::cli::Args;
+use crate::cli::{Args, ErrorPolicy};
+use crate::dedup::Deduplicator;
+use crate::encoding::Decoding;
+use crate::row_select::RowSelector;
 use crate::errors::{FastCutError, Result};
-use crate::field_parser::FieldParser;
+use crate::field_parser::{FieldParser, ParsedLine};
+use crate::filter::RowFilter;
+use crate::group::Grouper;
 use crate::output::OutputFormatter;
+use crate::reshape::Reshaper;
+use crate::split::Splitter;
+use crate::table::TableFormatter;
 use rayon::prelude::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, stdin};
@@ -9,12 +17,64 @@ use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+/// Lines affected by the `--on-error` policy, reported at the end of the run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorSummary {
+    pub skipped: usize,
+    pub padded: usize,
+}
+
+impl ErrorSummary {
+    fn merge(&mut self, other: ErrorSummary) {
+        self.skipped += other.skipped;
+        self.padded += other.padded;
+    }
+
+    /// One-line description, or `None` when no line was affected
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.skipped > 0 {
+            parts.push(format!("{} malformed {} skipped", self.skipped, lines(self.skipped)));
+        }
+        if self.padded > 0 {
+            parts.push(format!("{} {} padded with empty fields", self.padded, lines(self.padded)));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+}
+
+fn lines(count: usize) -> &'static str {
+    if count == 1 { "line" } else { "lines" }
+}
+
 pub struct StreamProcessor {
     field_parser: FieldParser,
+    /// `--where`, applied to all fields of a line before selection
+    row_filter: Option<RowFilter>,
+    /// `--unique`, `--count-unique` and `--distinct`
+    dedup: Option<Deduplicator>,
+    /// `--group-by`, in place of deduplication
+    group: Option<Grouper>,
+    /// `--head`, `--tail` and `--sample`, applied after deduplication
+    rows: Option<RowSelector>,
+    /// `--transpose` and `--pivot`, applied last
+    reshape: Option<Reshaper>,
+    /// `--split-by`, writing rows to files instead of stdout
+    split: Option<Splitter>,
     output_formatter: OutputFormatter,
+    /// Set for `--format table`, which prints aligned rows instead of `output_formatter` lines
+    table: Option<TableFormatter>,
+    line_numbers: bool,
     buffer_size: usize,
     threads: usize,
     verbose: bool,
+    on_error: ErrorPolicy,
+    error_summary: ErrorSummary,
+    decoding: Decoding,
 }
 
 impl StreamProcessor {
@@ -27,7 +87,49 @@ impl StreamProcessor {
             args.is_csv_mode(),
             args.space_delimiter,
             field_selector,
-        );
+        )
+        .with_missing_fields_padded(args.on_error == ErrorPolicy::Pad);
+
+        // Header names in the filter are resolved once the header is read
+        let mut row_filter = args.where_clause.as_deref().map(RowFilter::parse).transpose()?;
+        if let Some(ref mut filter) = row_filter {
+            if !args.has_header {
+                filter.resolve(None)?;
+            }
+        }
+
+        let mut dedup = args
+            .has_dedup()
+            .then(|| Deduplicator::new(args.distinct.as_deref(), args.count_unique))
+            .transpose()?;
+        if let Some(ref mut dedup) = dedup {
+            if !args.has_header {
+                dedup.resolve(None)?;
+            }
+        }
+
+        let mut group = args.grouper()?;
+        if let Some(ref mut group) = group {
+            if !args.has_header {
+                group.set_header(None)?;
+            }
+        }
+
+        let rows = args.row_selection().map(|selection| RowSelector::new(selection, args.seed));
+
+        let mut reshape = args.reshaper()?;
+        if let Some(ref mut reshape) = reshape {
+            if !args.has_header {
+                reshape.set_header(None)?;
+            }
+        }
+
+        let mut split = args.splitter()?;
+        if let Some(ref mut split) = split {
+            if !args.has_header {
+                split.resolve(None)?;
+            }
+        }
 
         let output_formatter = OutputFormatter::new(
             args.format.clone(),
@@ -36,23 +138,61 @@ impl StreamProcessor {
             args.line_numbers,
         );
 
+        let table = args.is_table_output().then(|| {
+            TableFormatter::new(
+                args.table_sample,
+                args.max_width.map(|width| width as usize),
+                &args.ellipsis,
+                args.should_use_colors(),
+            )
+        });
+
         Ok(Self {
             field_parser,
+            row_filter,
+            dedup,
+            group,
+            rows,
+            reshape,
+            split,
             output_formatter,
+            table,
+            line_numbers: args.line_numbers,
             buffer_size: args.buffer_size_bytes(),
             threads: args.get_threads(),
             verbose: args.verbose,
+            on_error: args.on_error,
+            error_summary: ErrorSummary::default(),
+            decoding: args.decoding(),
         })
     }
 
+    pub fn error_summary(&self) -> ErrorSummary {
+        self.error_summary
+    }
+
     pub fn process_files(&mut self, files: &[std::path::PathBuf], args: &Args) -> Result<()> {
         if files.is_empty() {
-            self.process_stdin(args)
-        } else if files.len() == 1 {
-            self.process_single_file(&files[0], args)
+            self.process_stdin(args)?;
+        } else if files.len() == 1
+            || self.dedup.is_some()
+            || self.group.is_some()
+            || self.rows.is_some()
+            || self.reshape.is_some()
+            || self.split.is_some()
+        {
+            // Deduplication, grouping, row selection, reshaping and split
+            // files span all files, so they are read in order
+            for file_path in files {
+                if self.rows.as_ref().is_some_and(RowSelector::is_done) {
+                    break;
+                }
+                self.process_single_file(file_path, args)?;
+            }
         } else {
-            self.process_multiple_files(files, args)
+            self.process_multiple_files(files, args)?;
         }
+        self.finish()
     }
 
     fn process_stdin(&mut self, args: &Args) -> Result<()> {
@@ -61,8 +201,10 @@ impl StreamProcessor {
         }
 
         let stdin = stdin();
-        let reader = stdin.lock();
-        self.process_reader(reader, args, "stdin")
+        if self.decoding.is_passthrough() {
+            return self.process_reader(stdin.lock(), args, "stdin");
+        }
+        self.process_input(stdin.lock(), args, "stdin")
     }
 
     fn process_single_file(&mut self, file_path: &Path, args: &Args) -> Result<()> {
@@ -73,8 +215,18 @@ impl StreamProcessor {
         let file = File::open(file_path)
             .map_err(|_| FastCutError::file_not_found(file_path.to_path_buf()))?;
         
-        let reader = BufReader::with_capacity(self.buffer_size, file);
-        self.process_reader(reader, args, &file_path.display().to_string())
+        self.process_input(file, args, &file_path.display().to_string())
+    }
+
+    /// Buffer `input` and decode it to UTF-8 as `--input-encoding` says
+    fn process_input<R: Read>(&mut self, input: R, args: &Args, source_name: &str) -> Result<()> {
+        if self.decoding.is_passthrough() {
+            let reader = BufReader::with_capacity(self.buffer_size, input);
+            self.process_reader(reader, args, source_name)
+        } else {
+            let reader = BufReader::with_capacity(self.buffer_size, self.decoding.reader(input));
+            self.process_reader(reader, args, source_name)
+        }
     }
 
     fn process_multiple_files(&mut self, files: &[std::path::PathBuf], args: &Args) -> Result<()> {
@@ -86,18 +238,22 @@ impl StreamProcessor {
             .par_iter()
             .map(|file_path| {
                 let mut processor = self.clone_processor(args)?;
-                let result = processor.process_single_file(file_path, args);
+                let result = processor
+                    .process_single_file(file_path, args)
+                    .and_then(|_| processor.finish());
                 
                 let count = processed_count.fetch_add(1, Ordering::Relaxed) + 1;
                 if self.verbose {
                     eprintln!("Processed {}/{} files", count, total_files);
                 }
                 
-                result
+                result.map(|_| processor.error_summary)
             })
             .collect();
 
-        results?;
+        for summary in results? {
+            self.error_summary.merge(summary);
+        }
         Ok(())
     }
 
@@ -109,28 +265,64 @@ impl StreamProcessor {
         let mut line_number = 0;
         let mut processed_lines = 0;
         let mut header_processed = false;
+        let mut delimiter_mirrored = !self.field_parser.is_auto_detect();
 
         for line_result in reader.lines() {
-            let line = line_result?;
             line_number += 1;
+            let line = line_result.map_err(|e| match e.kind() {
+                std::io::ErrorKind::InvalidData => FastCutError::encoding_error(format!(
+                    "{} line {}: {} (see --input-encoding and --encoding-errors)",
+                    source_name, line_number, e
+                )),
+                _ => e.into(),
+            })?;
 
             // Skip lines if requested
             if !args.should_process_line(line_number - 1) {
                 continue;
             }
 
+            // Output mirrors whatever delimiter the first line was detected with
+            if !delimiter_mirrored {
+                if let Some(delimiter) = FieldParser::auto_detected_delimiter(&line) {
+                    self.output_formatter.mirror_input_delimiter(delimiter);
+                    delimiter_mirrored = true;
+                }
+            }
+
             // Handle header line
             if args.has_header && !header_processed {
                 if args.skip_header {
                     self.field_parser.set_header(&line)?;
+                    self.resolve_filter_columns()?;
+                    if let Some(ref mut group) = self.group {
+                        let all_fields = self.field_parser.split_line(&line)?;
+                        group.set_header(Some(self.field_parser.select_line(&all_fields, &line, line_number)?.fields))?;
+                    }
                     header_processed = true;
                     continue;
                 } else {
                     self.field_parser.set_header(&line)?;
-                    if let Some(header_fields) = self.field_parser.get_header_fields() {
-                        self.output_formatter.set_header_names(header_fields.clone());
-                        let header_output = self.output_formatter.format_header(&header_fields)?;
-                        println!("{}", header_output);
+                    self.resolve_filter_columns()?;
+                    if self.reshape.is_some() || self.group.is_some() {
+                        // Grouping and reshaping work on the selected fields, and print their own header
+                        let all_fields = self.field_parser.split_line(&line)?;
+                        let mut names = self.field_parser.select_line(&all_fields, &line, line_number)?.fields;
+                        if self.dedup.as_ref().is_some_and(Deduplicator::is_counting) {
+                            names.insert(0, "count".to_string());
+                        }
+                        if let Some(ref mut group) = self.group {
+                            names = group.set_header(Some(names))?.unwrap_or_default();
+                        }
+                        match self.reshape {
+                            Some(ref mut reshape) => reshape.set_header(Some(names))?,
+                            None => self.output_header(names)?,
+                        }
+                    } else if let Some(mut header_fields) = self.field_parser.get_header_fields() {
+                        if self.dedup.as_ref().is_some_and(Deduplicator::is_counting) {
+                            header_fields.insert(0, "count".to_string());
+                        }
+                        self.output_header(header_fields)?;
                     }
                     header_processed = true;
                     continue;
@@ -145,24 +337,24 @@ impl StreamProcessor {
             // Process the line
             match self.process_line(&line, line_number) {
                 Ok(Some(output)) => {
-                    println!("{}", output);
+                    for output_line in output {
+                        println!("{}", output_line);
+                    }
                     processed_lines += 1;
                 }
                 Ok(None) => {
                     // Line was filtered out or empty
                 }
-                Err(e) => {
-                    if self.verbose {
-                        eprintln!("{}", self.output_formatter.format_error(&e.to_string(), Some(line_number)));
-                    }
-                    // Continue processing other lines
-                }
+                Err(e) => self.handle_line_error(e, line_number)?,
             }
 
             // Check if we've hit the max lines limit
             if args.max_lines > 0 && processed_lines >= args.max_lines {
                 break;
             }
+            if self.rows.as_ref().is_some_and(RowSelector::is_done) {
+                break;
+            }
         }
 
         if self.verbose {
@@ -172,20 +364,191 @@ impl StreamProcessor {
         Ok(())
     }
 
-    fn process_line(&self, line: &str, line_number: usize) -> Result<Option<String>> {
+    /// Print the header line, or hand it to the table
+    fn output_header(&mut self, header_fields: Vec<String>) -> Result<()> {
+        self.output_formatter.set_header_names(header_fields.clone());
+        if let Some(ref mut split) = self.split {
+            split.set_header(self.output_formatter.format_header(&header_fields)?);
+        } else if let Some(ref mut table) = self.table {
+            let mut header = header_fields;
+            if self.line_numbers {
+                header.insert(0, "line".to_string());
+            }
+            table.set_header(header);
+        } else {
+            let header_output = self.output_formatter.format_header(&header_fields)?;
+            println!("{}", header_output);
+        }
+        Ok(())
+    }
+
+    fn resolve_filter_columns(&mut self) -> Result<()> {
+        let header = self.field_parser.get_header_fields();
+        if let Some(ref mut filter) = self.row_filter {
+            filter.resolve(header.as_deref())?;
+        }
+        if let Some(ref mut dedup) = self.dedup {
+            dedup.resolve(header.as_deref())?;
+        }
+        if let Some(ref mut split) = self.split {
+            split.resolve(header.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Print what is held back until the end of input: grouped and counted
+    /// rows, then rows kept by `--tail` or `--sample`, then reshaped rows,
+    /// then buffered table rows
+    fn finish(&mut self) -> Result<()> {
+        if let Some(mut group) = self.group.take() {
+            group.finish(|parsed_line| {
+                if let Some(parsed_line) = self.select(parsed_line) {
+                    for output_line in self.emit(parsed_line)? {
+                        println!("{}", output_line);
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        let counted = self.dedup.as_mut().map(Deduplicator::finish).unwrap_or_default();
+        let mut released: Vec<ParsedLine> = counted.into_iter().filter_map(|line| self.select(line)).collect();
+        released.extend(self.rows.as_mut().map(RowSelector::finish).unwrap_or_default());
+        for parsed_line in released {
+            for output_line in self.emit(parsed_line)? {
+                println!("{}", output_line);
+            }
+        }
+        // Reshaped rows are formatted like any others once the stage is gone
+        if let Some(mut reshape) = self.reshape.take() {
+            let (header, rows) = reshape.finish();
+            if let Some(header) = header {
+                self.output_header(header)?;
+            }
+            for parsed_line in rows {
+                for output_line in self.emit(parsed_line)? {
+                    println!("{}", output_line);
+                }
+            }
+        }
+        self.finish_table();
+        if let Some(ref mut split) = self.split {
+            let files = split.finish()?;
+            if self.verbose {
+                eprintln!("Wrote {} split files", files);
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the `--on-error` policy to a line that could not be processed
+    fn handle_line_error(&mut self, error: FastCutError, line_number: usize) -> Result<()> {
+        // Running out of room to reshape or spill groups is not a problem with the line
+        if matches!(error, FastCutError::ReshapeTooLarge { .. } | FastCutError::Io(_) | FastCutError::JsonError(_)) {
+            return Err(error);
+        }
+        match self.on_error {
+            ErrorPolicy::Fail => return Err(FastCutError::malformed_line(line_number, error.to_string())),
+            ErrorPolicy::Report => {
+                eprintln!("{}", self.output_formatter.format_error(&error.to_string(), Some(line_number)));
+            }
+            ErrorPolicy::Skip | ErrorPolicy::Pad => {
+                if self.verbose {
+                    eprintln!("{}", self.output_formatter.format_error(&error.to_string(), Some(line_number)));
+                }
+            }
+        }
+        self.error_summary.skipped += 1;
+        Ok(())
+    }
+
+    /// Print the table rows still held back for column widths
+    fn finish_table(&mut self) {
+        if let Some(ref mut table) = self.table {
+            for output_line in table.finish() {
+                println!("{}", output_line);
+            }
+        }
+    }
+
+    /// Parse and format one line; `Some` holds the output lines it releases,
+    /// which is none while table rows are being buffered
+    fn process_line(&mut self, line: &str, line_number: usize) -> Result<Option<Vec<String>>> {
         if line.trim().is_empty() {
             return Ok(None);
         }
 
-        let parsed_line = self.field_parser.parse_line(line, line_number)?;
+        let needs_all_fields = self.row_filter.is_some()
+            || self.dedup.as_ref().is_some_and(Deduplicator::needs_all_fields)
+            || self.split.is_some();
+        let (parsed_line, all_fields) = if needs_all_fields {
+            let all_fields = self.field_parser.split_line(line)?;
+            if self.row_filter.as_ref().is_some_and(|filter| !filter.matches(&all_fields)) {
+                return Ok(None);
+            }
+            (self.field_parser.select_line(&all_fields, line, line_number)?, Some(all_fields))
+        } else {
+            (self.field_parser.parse_line(line, line_number)?, None)
+        };
+        if parsed_line.padded_fields > 0 {
+            self.error_summary.padded += 1;
+        }
         
         // Check if any fields were extracted
         if parsed_line.fields.is_empty() {
             return Ok(None);
         }
 
+        if let Some(ref mut group) = self.group {
+            group.observe(&parsed_line)?;
+            return Ok(None);
+        }
+
+        let parsed_line = match self.dedup {
+            Some(ref mut dedup) => match dedup.observe(parsed_line, all_fields.as_deref())? {
+                Some(parsed_line) => parsed_line,
+                None => return Ok(None),
+            },
+            None => parsed_line,
+        };
+
+        match (self.select(parsed_line), all_fields) {
+            (Some(parsed_line), Some(all_fields)) if self.split.is_some() => {
+                let output_line = self.output_formatter.format_line(&parsed_line)?;
+                if let Some(ref mut split) = self.split {
+                    split.write(&all_fields, &output_line)?;
+                }
+                Ok(Some(Vec::new()))
+            }
+            (Some(parsed_line), _) => self.emit(parsed_line).map(Some),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Pass a row through `--head`/`--tail`/`--sample`; `None` while it is
+    /// held back or once it is dropped
+    fn select(&mut self, parsed_line: ParsedLine) -> Option<ParsedLine> {
+        match self.rows {
+            Some(ref mut rows) => rows.observe(parsed_line),
+            None => Some(parsed_line),
+        }
+    }
+
+    /// Format a row for output (or for the table or reshape buffers)
+    fn emit(&mut self, parsed_line: ParsedLine) -> Result<Vec<String>> {
+        if let Some(ref mut reshape) = self.reshape {
+            reshape.observe(parsed_line)?;
+            return Ok(Vec::new());
+        }
+        if let Some(ref mut table) = self.table {
+            let mut row = parsed_line.fields;
+            if self.line_numbers {
+                row.insert(0, parsed_line.line_number.to_string());
+            }
+            return Ok(table.push_row(row));
+        }
+
         let output = self.output_formatter.format_line(&parsed_line)?;
-        Ok(Some(output))
+        Ok(vec![output])
     }
 
     pub fn process_parallel_chunks<R: Read + Send>(&mut self, reader: R, args: &Args) -> Result<()> {
@@ -221,18 +584,21 @@ impl StreamProcessor {
                 }
                 
                 match self.process_line(line, line_number) {
-                    Ok(Some(output)) => println!("{}", output),
-                    Ok(None) => {} // Filtered out
-                    Err(e) => {
-                        if self.verbose {
-                            eprintln!("{}", self.output_formatter.format_error(&e.to_string(), Some(line_number)));
+                    Ok(Some(output)) => {
+                        for output_line in output {
+                            println!("{}", output_line);
                         }
                     }
+                    Ok(None) => {} // Filtered out
+                    Err(e) => self.handle_line_error(e, line_number)?,
+                }
+                if self.rows.as_ref().is_some_and(RowSelector::is_done) {
+                    return self.finish();
                 }
             }
         }
-        
-        Ok(())
+
+        self.finish()
     }
 
     pub fn get_field_parser(&self) -> &FieldParser {
@@ -247,7 +613,7 @@ impl StreamProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cli::{Args, OutputFormat, ColorOption, FieldSelector};
+    use crate::cli::{Args, ColorOption, EncodingErrors, ErrorPolicy, InputEncoding, OutputFormat};
     use std::io::Cursor;
     use tempfile::NamedTempFile;
     use std::io::Write;
@@ -271,7 +637,31 @@ mod tests {
             color: ColorOption::Never,
             threads: None,
             buffer_size_kb: 64,
+            table_sample: None,
+            max_width: None,
+            ellipsis: "…".to_string(),
+            split_by: None,
+            out_dir: None,
+            max_open_files: crate::split::DEFAULT_MAX_OPEN_FILES,
             non_empty_only: false,
+            where_clause: None,
+            input_encoding: InputEncoding::Utf8,
+            encoding_errors: EncodingErrors::Strict,
+            head: None,
+            tail: None,
+            sample: None,
+            seed: None,
+            unique: false,
+            count_unique: false,
+            distinct: None,
+            transpose: false,
+            pivot: None,
+            max_cells: crate::reshape::DEFAULT_MAX_CELLS,
+            group_by: None,
+            agg: None,
+            max_groups: crate::group::DEFAULT_MAX_GROUPS,
+            on_error: ErrorPolicy::Skip,
+            validate: false,
             verbose: false,
         }
     }
@@ -310,4 +700,53 @@ mod tests {
         let result = processor.process_single_file(temp_file.path(), &args);
         assert!(result.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_on_error_policies() {
+        let input = "a,b,c\nshort\nx,y,z\n";
+        let run = |on_error| {
+            let args = Args {
+                on_error,
+                ..create_test_args()
+            };
+            let mut processor = StreamProcessor::new(&args).unwrap();
+            let result = processor.process_reader(Cursor::new(input), &args, "test");
+            (result, processor.error_summary())
+        };
+
+        let (result, summary) = run(ErrorPolicy::Skip);
+        assert!(result.is_ok());
+        assert_eq!(summary, ErrorSummary { skipped: 1, padded: 0 });
+        assert_eq!(summary.describe().unwrap(), "1 malformed line skipped");
+
+        let (result, summary) = run(ErrorPolicy::Pad);
+        assert!(result.is_ok());
+        assert_eq!(summary, ErrorSummary { skipped: 0, padded: 1 });
+
+        let (result, _) = run(ErrorPolicy::Fail);
+        assert!(matches!(result, Err(FastCutError::MalformedLine { line_number: 2, .. })));
+    }
+
+    #[test]
+    fn test_split_by_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = Args {
+            fields: "host,status".to_string(),
+            has_header: true,
+            format: OutputFormat::Csv,
+            split_by: Some("day".to_string()),
+            out_dir: Some(dir.path().join("days")),
+            ..create_test_args()
+        };
+        let mut processor = StreamProcessor::new(&args).unwrap();
+        let input = "day,host,status\nmon,web1,200\ntue,web2,500\nmon,web3,404\nshort\n";
+        processor.process_reader(Cursor::new(input), &args, "test").unwrap();
+        processor.finish().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join("days").join(name)).unwrap();
+        // Each file starts with the header as it would be printed
+        assert_eq!(read("mon.csv"), "day,host,status\nweb1,200\nweb3,404\n");
+        assert_eq!(read("tue.csv"), "day,host,status\nweb2,500\n");
+        assert_eq!(processor.error_summary().skipped, 1);
+    }
+}
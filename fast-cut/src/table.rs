@@ -0,0 +1,200 @@
+use colored::*;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Spaces between table columns
+const COLUMN_GAP: &str = "  ";
+
+/// Aligned table output for `--format table`.
+///
+/// Column widths come from the rows seen so far, so rows are held back until
+/// the widths are known: all of them by default, or only the first `sample`
+/// rows, after which rows are printed as they arrive and cells wider than
+/// their column are cut to fit. `max_width` caps every column, marking cut
+/// cells with `ellipsis`.
+#[derive(Debug)]
+pub struct TableFormatter {
+    sample: Option<usize>,
+    max_width: Option<usize>,
+    ellipsis: String,
+    use_colors: bool,
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    widths: Option<Vec<usize>>,
+}
+
+impl TableFormatter {
+    pub fn new(sample: Option<usize>, max_width: Option<usize>, ellipsis: &str, use_colors: bool) -> Self {
+        Self {
+            sample: sample.filter(|&rows| rows > 0),
+            max_width,
+            ellipsis: ellipsis.to_string(),
+            use_colors,
+            header: None,
+            rows: Vec::new(),
+            widths: None,
+        }
+    }
+
+    pub fn set_header(&mut self, header: Vec<String>) {
+        self.header = Some(header);
+    }
+
+    /// Add a row; returns the lines that can be printed now
+    pub fn push_row(&mut self, row: Vec<String>) -> Vec<String> {
+        if let Some(ref widths) = self.widths {
+            return vec![self.format_row(&row, widths)];
+        }
+
+        self.rows.push(row);
+        if self.sample.is_some_and(|sample| self.rows.len() >= sample) {
+            self.flush()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Print everything still held back; later rows reuse the same widths
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.widths.is_some() {
+            Vec::new()
+        } else {
+            self.flush()
+        }
+    }
+
+    fn flush(&mut self) -> Vec<String> {
+        let widths = self.compute_widths();
+        let mut lines = Vec::with_capacity(self.rows.len() + 2);
+
+        if let Some(ref header) = self.header {
+            let line = self.format_row(header, &widths);
+            let rule = widths
+                .iter()
+                .map(|&width| "-".repeat(width))
+                .collect::<Vec<_>>()
+                .join(COLUMN_GAP);
+            if self.use_colors {
+                lines.push(line.cyan().bold().to_string());
+                lines.push(rule.dimmed().to_string());
+            } else {
+                lines.push(line);
+                lines.push(rule);
+            }
+        }
+
+        for row in std::mem::take(&mut self.rows) {
+            lines.push(self.format_row(&row, &widths));
+        }
+        self.widths = Some(widths);
+        lines
+    }
+
+    fn compute_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = Vec::new();
+        for row in self.header.iter().chain(&self.rows) {
+            if widths.len() < row.len() {
+                widths.resize(row.len(), 0);
+            }
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        if let Some(max_width) = self.max_width {
+            for width in &mut widths {
+                *width = (*width).min(max_width);
+            }
+        }
+        widths
+    }
+
+    fn format_row(&self, row: &[String], widths: &[usize]) -> String {
+        let mut line = String::new();
+        for (column, cell) in row.iter().enumerate() {
+            let last = column + 1 == row.len();
+            // Only the last cell may run past its column without breaking alignment
+            let limit = match widths.get(column) {
+                Some(&width) if !last => Some(width),
+                _ => self.max_width,
+            };
+            let cell = match limit {
+                Some(limit) => self.truncate(cell, limit),
+                None => cell.clone(),
+            };
+
+            line.push_str(&cell);
+            if !last {
+                let width = widths.get(column).copied().unwrap_or(0);
+                line.push_str(&" ".repeat(width.saturating_sub(cell.width())));
+                line.push_str(COLUMN_GAP);
+            }
+        }
+        line
+    }
+
+    /// Cut `cell` to at most `limit` columns, ending it with the ellipsis
+    fn truncate(&self, cell: &str, limit: usize) -> String {
+        if cell.width() <= limit {
+            return cell.to_string();
+        }
+
+        let ellipsis_width = self.ellipsis.width();
+        if ellipsis_width > limit {
+            return take_width(cell, limit);
+        }
+        let mut truncated = take_width(cell, limit - ellipsis_width);
+        truncated.push_str(&self.ellipsis);
+        truncated
+    }
+}
+
+/// The longest prefix of `s` that is at most `limit` columns wide
+fn take_width(s: &str, limit: usize) -> String {
+    let mut taken = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > limit {
+            break;
+        }
+        width += char_width;
+        taken.push(c);
+    }
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[test]
+    fn test_buffered_table_with_header() {
+        let mut table = TableFormatter::new(None, None, "…", false);
+        table.set_header(row(&["name", "age"]));
+        assert!(table.push_row(row(&["Alexandra", "30"])).is_empty());
+        assert!(table.push_row(row(&["Bo", "7"])).is_empty());
+        assert_eq!(
+            table.finish(),
+            vec!["name       age", "---------  ---", "Alexandra  30", "Bo         7"]
+        );
+    }
+
+    #[test]
+    fn test_sampled_rows_are_cut_to_fit() {
+        let mut table = TableFormatter::new(Some(1), None, "~", false);
+        assert_eq!(table.push_row(row(&["abc", "x"])), vec!["abc  x"]);
+        assert_eq!(table.push_row(row(&["abcdef", "long last cell"])), vec!["ab~  long last cell"]);
+        assert!(table.finish().is_empty());
+    }
+
+    #[test]
+    fn test_max_width_truncation() {
+        let mut table = TableFormatter::new(None, Some(4), "…", false);
+        table.push_row(row(&["München", "Zürich-Flughafen"]));
+        table.push_row(row(&["日本語テキスト", "ok"]));
+        assert_eq!(table.finish(), vec!["Mün…  Zür…", "日…   ok"]);
+    }
+}
@@ -0,0 +1,486 @@
+use crate::cli::Args;
+use crate::errors::{FastCutError, Result};
+use crate::field_parser::FieldParser;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader, Read, Write};
+
+/// Something wrong with a file's structure, found by `--validate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    /// A row with fewer fields than the header
+    TooFewFields { expected: usize, found: usize },
+    /// A row with more fields than the header
+    TooManyFields { expected: usize, found: usize },
+    /// Bytes that are not UTF-8, from this byte of the row on
+    InvalidUtf8 { column: usize },
+    /// A quote inside a field that does not start with one
+    StrayQuote { column: usize },
+    /// Text between a quoted field's closing quote and the delimiter
+    TextAfterQuote { column: usize },
+    /// A quoted field still open at the end of the input
+    UnterminatedQuote,
+    /// A column name used more than once, with the 1-based columns using it
+    DuplicateHeader { name: String, columns: Vec<usize> },
+}
+
+impl Problem {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::TooFewFields { .. } => "too_few_fields",
+            Problem::TooManyFields { .. } => "too_many_fields",
+            Problem::InvalidUtf8 { .. } => "invalid_utf8",
+            Problem::StrayQuote { .. } => "stray_quote",
+            Problem::TextAfterQuote { .. } => "text_after_quote",
+            Problem::UnterminatedQuote => "unterminated_quote",
+            Problem::DuplicateHeader { .. } => "duplicate_header",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::TooFewFields { expected, found } | Problem::TooManyFields { expected, found } => {
+                format!("{} fields, the header has {}", found, expected)
+            }
+            Problem::InvalidUtf8 { column } => format!("invalid UTF-8 at byte {}", column),
+            Problem::StrayQuote { column } => format!("quote at byte {} inside an unquoted field", column),
+            Problem::TextAfterQuote { column } => format!("text after the closing quote at byte {}", column),
+            Problem::UnterminatedQuote => "quoted field not closed before the end of input".to_string(),
+            Problem::DuplicateHeader { name, columns } => {
+                let columns: Vec<String> = columns.iter().map(usize::to_string).collect();
+                format!("column name '{}' repeated in columns {}", name, columns.join(", "))
+            }
+        }
+    }
+}
+
+/// A problem and the row it is on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub file: String,
+    /// Line the row starts on, counting from 1
+    pub line: usize,
+    #[serde(flatten)]
+    pub problem: Problem,
+}
+
+/// `--validate`: the structure of every row checked against the header,
+/// the first row of each input.
+///
+/// Fields are split the way CSV quotes them, so a quoted field may hold
+/// delimiters and newlines, and `""` is a quote within one. Blank lines are
+/// not rows.
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub files: usize,
+    /// Rows checked, headers included
+    pub rows: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Findings per kind, by name
+    pub fn counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for finding in &self.findings {
+            *counts.entry(finding.problem.kind()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Check the rows of `input`, named `file` in the findings.
+    ///
+    /// `delimiter` is guessed from the header when not given: the most
+    /// frequent of comma, tab, semicolon and pipe, else comma.
+    pub fn check<R: BufRead>(
+        &mut self,
+        mut input: R,
+        file: &str,
+        delimiter: Option<u8>,
+        terminator: u8,
+        skip_lines: usize,
+    ) -> Result<()> {
+        self.files += 1;
+        let mut scanner = RowScanner {
+            input: &mut input,
+            delimiter: delimiter.unwrap_or(b','),
+            terminator,
+            line: 0,
+            buffer: Vec::new(),
+        };
+        for _ in 0..skip_lines {
+            if !scanner.read_line()? {
+                return Ok(());
+            }
+        }
+
+        let mut expected = None;
+        let mut found = Vec::new();
+        while let Some(row) = scanner.next_row(expected.is_none(), expected.is_none() && delimiter.is_none())? {
+            self.rows += 1;
+            found.clear();
+            if let Err(e) = std::str::from_utf8(&row.bytes) {
+                found.push(Problem::InvalidUtf8 { column: e.valid_up_to() + 1 });
+            }
+            found.extend(row.quoting);
+            match expected {
+                None => {
+                    found.extend(duplicate_names(&row.fields));
+                    expected = Some(row.field_count);
+                }
+                Some(expected) if row.field_count < expected => {
+                    found.push(Problem::TooFewFields { expected, found: row.field_count });
+                }
+                Some(expected) if row.field_count > expected => {
+                    found.push(Problem::TooManyFields { expected, found: row.field_count });
+                }
+                Some(_) => {}
+            }
+            self.findings.extend(found.drain(..).map(|problem| Finding {
+                file: file.to_string(),
+                line: row.line,
+                problem,
+            }));
+        }
+        Ok(())
+    }
+
+    /// One line per finding, as FILE:LINE: KIND: DETAIL
+    pub fn write_text<W: Write>(&self, out: &mut W) -> Result<()> {
+        for finding in &self.findings {
+            writeln!(
+                out,
+                "{}:{}: {}: {}",
+                finding.file,
+                finding.line,
+                finding.problem.kind(),
+                finding.problem.describe()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The whole report as one JSON object
+    pub fn write_json<W: Write>(&self, out: &mut W) -> Result<()> {
+        let report = serde_json::json!({
+            "valid": self.is_valid(),
+            "files": self.files,
+            "rows": self.rows,
+            "counts": self.counts(),
+            "findings": self.findings,
+        });
+        serde_json::to_writer_pretty(&mut *out, &report)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// A CSV table of the findings: file, line, kind, detail
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(["file", "line", "kind", "detail"])?;
+        for finding in &self.findings {
+            writer.write_record([
+                finding.file.as_str(),
+                &finding.line.to_string(),
+                finding.problem.kind(),
+                &finding.problem.describe(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// One-line summary for stderr
+    pub fn summary(&self) -> String {
+        let rows = format!("{} {}", self.rows, if self.rows == 1 { "row" } else { "rows" });
+        if self.is_valid() {
+            return format!("valid: {} checked", rows);
+        }
+        let counts: Vec<String> = self
+            .counts()
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        format!("invalid: {} in {}", counts.join(", "), rows)
+    }
+}
+
+/// Validate the files `args` names, or stdin, decoded as
+/// `--input-encoding` says
+pub fn validate_inputs(args: &Args) -> Result<Validation> {
+    let delimiter = delimiter_byte(args.get_input_delimiter().as_deref(), args.is_csv_mode())?;
+    let terminator = if args.zero_terminated { b'\0' } else { b'\n' };
+    let decoding = args.decoding();
+    let mut validation = Validation::default();
+    let mut check = |input: Box<dyn Read>, name: &str| {
+        let input: Box<dyn Read> = if decoding.is_passthrough() { input } else { Box::new(decoding.reader(input)) };
+        let reader = BufReader::with_capacity(args.buffer_size_bytes(), input);
+        validation.check(reader, name, delimiter, terminator, args.skip_lines)
+    };
+    if args.files.is_empty() {
+        check(Box::new(stdin()), "stdin")?;
+    }
+    for path in &args.files {
+        let file = File::open(path).map_err(|_| FastCutError::file_not_found(path.clone()))?;
+        check(Box::new(file), &path.display().to_string())?;
+    }
+    Ok(validation)
+}
+
+/// The single-byte delimiter `--validate` splits on, from the delimiter
+/// options; `None` leaves it to be guessed from the header
+pub fn delimiter_byte(delimiter: Option<&str>, csv_mode: bool) -> Result<Option<u8>> {
+    match delimiter {
+        Some(delimiter) if delimiter.len() == 1 => Ok(Some(delimiter.as_bytes()[0])),
+        Some(delimiter) => Err(FastCutError::invalid_config(format!(
+            "--validate needs a single-byte delimiter, not {:?}",
+            delimiter
+        ))),
+        None if csv_mode => Ok(Some(b',')),
+        None => Ok(None),
+    }
+}
+
+fn duplicate_names(fields: &[String]) -> Vec<Problem> {
+    let mut columns: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut order = Vec::new();
+    for (index, name) in fields.iter().enumerate() {
+        let name = name.trim();
+        let seen = columns.entry(name).or_default();
+        if seen.is_empty() {
+            order.push(name);
+        }
+        seen.push(index + 1);
+    }
+    order
+        .into_iter()
+        .filter(|name| columns[name].len() > 1)
+        .map(|name| Problem::DuplicateHeader {
+            name: name.to_string(),
+            columns: columns[name].clone(),
+        })
+        .collect()
+}
+
+/// A row as the scanner found it
+struct Row {
+    line: usize,
+    bytes: Vec<u8>,
+    field_count: usize,
+    /// Field values, kept for the header only
+    fields: Vec<String>,
+    quoting: Vec<Problem>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// A quote inside a quoted field: its end, or the first of `""`
+    QuoteInQuoted,
+}
+
+struct RowScanner<'a, R: BufRead> {
+    input: &'a mut R,
+    delimiter: u8,
+    terminator: u8,
+    /// Lines read so far
+    line: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> RowScanner<'_, R> {
+    /// Read the next line into `buffer`, without its terminator; false at
+    /// the end of the input
+    fn read_line(&mut self) -> Result<bool> {
+        self.buffer.clear();
+        if self.input.read_until(self.terminator, &mut self.buffer)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        if self.buffer.last() == Some(&self.terminator) {
+            self.buffer.pop();
+        }
+        Ok(true)
+    }
+
+    /// The next row that is not blank, reading on while a quoted field is
+    /// open. `keep_fields` keeps the values, and `guess` first picks the
+    /// delimiter from the row.
+    fn next_row(&mut self, keep_fields: bool, guess: bool) -> Result<Option<Row>> {
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if self.buffer.is_empty() || self.buffer == b"\r" {
+                continue;
+            }
+            if guess {
+                let line = String::from_utf8_lossy(&self.buffer);
+                if let Some(delimiter) = FieldParser::detect_delimiter(&line) {
+                    self.delimiter = delimiter.as_bytes()[0];
+                }
+            }
+            return self.scan_row(keep_fields).map(Some);
+        }
+    }
+
+    fn scan_row(&mut self, keep_fields: bool) -> Result<Row> {
+        let line = self.line;
+        let mut bytes = Vec::new();
+        let mut state = State::FieldStart;
+        let mut field_count = 0;
+        let mut fields = Vec::new();
+        let mut value = Vec::new();
+        let mut quoting = Vec::new();
+
+        let delimiter = self.delimiter;
+        loop {
+            let start = bytes.len();
+            // A CR before the line break ends the row with it, unless a
+            // quoted field holds them both
+            let line_end = self.buffer.len() - usize::from(self.buffer.last() == Some(&b'\r'));
+            for (index, &byte) in self.buffer[..line_end].iter().enumerate() {
+                let column = start + index + 1;
+                state = match (state, byte) {
+                    (State::FieldStart, b'"') => State::Quoted,
+                    (State::FieldStart | State::Unquoted | State::QuoteInQuoted, byte) if byte == delimiter => {
+                        field_count += 1;
+                        if keep_fields {
+                            fields.push(String::from_utf8_lossy(&value).into_owned());
+                        }
+                        value.clear();
+                        State::FieldStart
+                    }
+                    (State::FieldStart | State::Unquoted, byte) => {
+                        if byte == b'"' {
+                            quoting.push(Problem::StrayQuote { column });
+                        }
+                        value.push(byte);
+                        State::Unquoted
+                    }
+                    (State::Quoted, b'"') => State::QuoteInQuoted,
+                    (State::Quoted, byte) => {
+                        value.push(byte);
+                        State::Quoted
+                    }
+                    (State::QuoteInQuoted, b'"') => {
+                        value.push(b'"');
+                        State::Quoted
+                    }
+                    (State::QuoteInQuoted, byte) => {
+                        quoting.push(Problem::TextAfterQuote { column });
+                        value.push(byte);
+                        State::Unquoted
+                    }
+                };
+            }
+            if state != State::Quoted {
+                bytes.extend_from_slice(&self.buffer[..line_end]);
+                break;
+            }
+            // The quoted field goes on past the line break
+            value.extend_from_slice(&self.buffer[line_end..]);
+            value.push(self.terminator);
+            bytes.extend_from_slice(&self.buffer);
+            bytes.push(self.terminator);
+            if !self.read_line()? {
+                quoting.push(Problem::UnterminatedQuote);
+                break;
+            }
+        }
+
+        field_count += 1;
+        if keep_fields {
+            fields.push(String::from_utf8_lossy(&value).into_owned());
+        }
+        Ok(Row {
+            line,
+            bytes,
+            field_count,
+            fields,
+            quoting,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(input: &str, delimiter: Option<u8>) -> Validation {
+        let mut validation = Validation::default();
+        validation.check(input.as_bytes(), "data.csv", delimiter, b'\n', 0).unwrap();
+        validation
+    }
+
+    fn problems(validation: &Validation) -> Vec<(usize, Problem)> {
+        validation
+            .findings
+            .iter()
+            .map(|finding| (finding.line, finding.problem.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_csv_with_quoted_fields() {
+        let validation = validate(
+            "name,note,city\r\n\"Smith, J\",\"said \"\"hi\"\"\nthen left\",NYC\r\n\nAda,,London\r\n",
+            None,
+        );
+        assert!(validation.is_valid(), "{:?}", validation.findings);
+        assert_eq!(validation.rows, 3);
+        assert_eq!(validation.summary(), "valid: 3 rows checked");
+    }
+
+    #[test]
+    fn test_structure_problems() {
+        let validation = validate(
+            "id;name;id\n1;a\n2;b;c;d\n3;x\"y;z\n4;\"q\"r;s\n5;\"open;t\n",
+            None,
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![
+                (1, Problem::DuplicateHeader { name: "id".to_string(), columns: vec![1, 3] }),
+                (2, Problem::TooFewFields { expected: 3, found: 2 }),
+                (3, Problem::TooManyFields { expected: 3, found: 4 }),
+                (4, Problem::StrayQuote { column: 4 }),
+                (5, Problem::TextAfterQuote { column: 6 }),
+                (6, Problem::UnterminatedQuote),
+                (6, Problem::TooFewFields { expected: 3, found: 2 }),
+            ]
+        );
+        assert_eq!(validation.counts()["too_few_fields"], 2);
+    }
+
+    #[test]
+    fn test_invalid_utf8_and_reports() {
+        let mut validation = Validation::default();
+        let input: &[u8] = b"a,b\n1,\xff2\n";
+        validation.check(input, "-", Some(b','), b'\n', 0).unwrap();
+        assert_eq!(problems(&validation), vec![(2, Problem::InvalidUtf8 { column: 3 })]);
+
+        let mut text = Vec::new();
+        validation.write_text(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "-:2: invalid_utf8: invalid UTF-8 at byte 3\n");
+
+        let mut json = Vec::new();
+        validation.write_json(&mut json).unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["findings"][0]["kind"], "invalid_utf8");
+        assert_eq!(report["findings"][0]["column"], 3);
+        assert_eq!(report["counts"]["invalid_utf8"], 1);
+
+        assert!(delimiter_byte(Some("::"), false).is_err());
+        assert_eq!(delimiter_byte(None, true).unwrap(), Some(b','));
+    }
+}
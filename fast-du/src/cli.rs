@@ -0,0 +1,298 @@
+use crate::export::ExportFormat;
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "fdu")]
+#[command(about = "Parallel disk usage analyzer - modern du alternative")]
+#[command(version = "0.1.0")]
+#[command(disable_help_flag = true)]
+pub struct Args {
+    /// Directories to analyze
+    #[arg(default_value = ".")]
+    pub paths: Vec<PathBuf>,
+    
+    /// Show human-readable sizes
+    #[arg(short = 'h', long = "human-readable")]
+    pub human_readable: bool,
+    
+    /// Show only a total for each argument
+    #[arg(short = 's', long = "summarize", conflicts_with = "max_depth")]
+    pub summarize: bool,
+    
+    /// Print totals for directories only down to N levels below each
+    /// argument; deeper contents still count towards them
+    #[arg(short = 'd', long = "max-depth", value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Add an "(others)" row under each listed directory for what its
+    /// listed subdirectories leave out: its own files and anything below
+    /// --max-depth, so the rows under a directory add up to its total
+    #[arg(long = "others", conflicts_with = "summarize")]
+    pub others: bool,
+
+    /// Print a grand total of all arguments
+    #[arg(short = 'c', long = "total")]
+    pub total: bool,
+
+    /// Order the directories listed under each argument, largest or
+    /// alphabetically first (default: as walked, children before parents)
+    #[arg(long = "sort", value_enum, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+    
+    /// Number of threads (default: CPU cores)
+    #[arg(short = 'j', long = "threads")]
+    pub threads: Option<usize>,
+
+    /// Follow symbolic links and count what they point to
+    #[arg(short = 'L', long = "dereference", overrides_with = "no_dereference")]
+    pub dereference: bool,
+
+    /// Count symbolic links themselves, never their targets (default)
+    #[arg(short = 'P', long = "no-dereference", overrides_with = "dereference")]
+    pub no_dereference: bool,
+
+    /// Count sizes of hard links every time they are found, not just once
+    #[arg(short = 'l', long = "count-links")]
+    pub count_links: bool,
+
+    /// Only count files modified within DURATION (e.g. 90m, 12h, 7d, 2w, 1y; a bare number is days)
+    #[arg(long = "newer-than", value_name = "DURATION", value_parser = parse_duration)]
+    pub newer_than: Option<Duration>,
+
+    /// Only count files last modified more than DURATION ago
+    #[arg(long = "older-than", value_name = "DURATION", value_parser = parse_duration)]
+    pub older_than: Option<Duration>,
+
+    /// Only count files with these extensions (comma-separated, case-insensitive)
+    #[arg(long = "include-ext", value_name = "EXT", value_delimiter = ',')]
+    pub include_ext: Vec<String>,
+
+    /// Do not count files with these extensions
+    #[arg(long = "exclude-ext", value_name = "EXT", value_delimiter = ',')]
+    pub exclude_ext: Vec<String>,
+
+    /// Leave cache directories out of the totals: those holding a
+    /// CACHEDIR.TAG and ~/.cache; the space they take is reported separately
+    #[arg(long = "skip-caches")]
+    pub skip_caches: bool,
+
+    /// With --skip-caches, also treat directories with these names as
+    /// caches (comma-separated, e.g. node_modules,target)
+    #[arg(long = "cache-names", value_name = "NAME", value_delimiter = ',', requires = "skip_caches")]
+    pub cache_names: Vec<String>,
+
+    /// After the first report, rescan every INTERVAL (e.g. 30s, 5m; a bare
+    /// number is seconds) and print directories that grew
+    #[arg(long = "watch", value_name = "INTERVAL", value_parser = parse_interval)]
+    pub watch: Option<Duration>,
+
+    /// With --watch, how much a directory must grow between two scans to be
+    /// printed, e.g. 500K, 10M or 1G
+    #[arg(long = "alert-threshold", value_name = "SIZE", value_parser = parse_size, default_value = "1M", requires = "watch")]
+    pub alert_threshold: u64,
+
+    /// Map file extents (FIEMAP) to report shared and compressed storage:
+    /// bytes referenced against bytes physically allocated, counting
+    /// extents shared by reflinked copies or snapshots once, and which files
+    /// share extents (listed with --json)
+    #[arg(long = "extents")]
+    pub extents: bool,
+
+    /// Also write the directory totals to FILE for sharing: a self-contained
+    /// treemap page for .html, a flame graph for .svg. Goes as deep as the
+    /// listing does (see --max-depth)
+    #[arg(long = "export", value_name = "FILE", value_parser = parse_export_file)]
+    pub export: Option<PathBuf>,
+
+    /// Instead of scanning, sample each path to estimate its files, size
+    /// and scan time, and list directories a scan will fail to read; trees
+    /// of up to 1000 directories are counted exactly. Fails if any are
+    /// unreadable
+    #[arg(long = "preflight", conflicts_with_all = ["watch", "export", "extents"])]
+    pub preflight: bool,
+
+    /// Print the arguments side by side instead of listing their
+    /// directories: each one's size, file count, largest subdirectory and
+    /// share of the combined size
+    #[arg(long = "compare", conflicts_with_all = ["summarize", "max_depth", "others", "sort", "watch", "preflight"])]
+    pub compare: bool,
+
+    /// Output results and errors as JSON
+    #[arg(long = "json")]
+    pub json_output: bool,
+
+    /// Script-friendly output: du's "SIZE<tab>PATH" lines only, without
+    /// the banner, footer, details, progress spinner or colors
+    #[arg(short = 'q', long = "plain", visible_alias = "quiet", conflicts_with = "json_output")]
+    pub plain: bool,
+
+    /// Print help
+    #[arg(long = "help", action = clap::ArgAction::Help)]
+    pub help: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Size,
+    Name,
+}
+
+impl Args {
+    /// How deep per-directory totals are listed: `-s` lists none, the
+    /// default lists every directory, `--compare` needs only the first level
+    pub fn report_depth(&self) -> usize {
+        if self.summarize {
+            0
+        } else if self.compare {
+            1
+        } else {
+            self.max_depth.unwrap_or(usize::MAX)
+        }
+    }
+
+    /// Whether decoration (banner, footer, spinner, watch notice) is shown
+    pub fn is_decorated(&self) -> bool {
+        !self.json_output && !self.plain
+    }
+
+    pub fn should_dereference(&self) -> bool {
+        self.dereference && !self.no_dereference
+    }
+}
+
+/// Parse an age such as "30d" or "1d12h": numbers followed by s, m, h, d, w
+/// or y (365 days); a bare number counts days
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("duration must not be empty".to_string());
+    }
+    if let Ok(days) = value.parse::<u64>() {
+        return days
+            .checked_mul(86_400)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("invalid duration '{}': too long", value));
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err(format!("invalid duration '{}': expected a number before '{}'", value, rest));
+        }
+        let amount: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("invalid duration '{}': number too large", value))?;
+        rest = &rest[digits..];
+
+        let unit_len = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+        let seconds = match &rest[..unit_len] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            "d" => 86_400,
+            "w" => 7 * 86_400,
+            "y" => 365 * 86_400,
+            "" => return Err(format!("invalid duration '{}': missing unit after {}", value, amount)),
+            unit => return Err(format!("invalid duration '{}': unknown unit '{}' (use s, m, h, d, w or y)", value, unit)),
+        };
+        rest = &rest[unit_len..];
+        total = amount
+            .checked_mul(seconds)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(|| format!("invalid duration '{}': too long", value))?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parse a watch interval: like `parse_duration`, but a bare number counts
+/// seconds, and it must not be zero
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    let interval = match value.trim().parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => parse_duration(value)?,
+    };
+    if interval.is_zero() {
+        return Err("interval must be longer than zero".to_string());
+    }
+    Ok(interval)
+}
+
+/// Parse an `--export` file name, which must end in `.html`, `.htm` or
+/// `.svg`, so a bad one fails before the scan rather than after it
+pub fn parse_export_file(value: &str) -> Result<PathBuf, String> {
+    match ExportFormat::of(Path::new(value)) {
+        Some(_) => Ok(PathBuf::from(value)),
+        None => Err(format!("cannot tell the format of {}; use a .html or .svg file", value)),
+    }
+}
+
+/// Parse a size such as "512", "500K", "10M" or "1.5G" (powers of 1024)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let amount: f64 = value[..digits]
+        .parse()
+        .map_err(|_| format!("invalid size '{}': expected a number such as 10M", value))?;
+    let exponent = match value[digits..].trim_end_matches(['B', 'b']).to_ascii_uppercase().as_str() {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        unit => return Err(format!("invalid size '{}': unknown unit '{}' (use K, M, G, T or P)", value, unit)),
+    };
+    Ok((amount * 1024f64.powi(exponent)) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30 * 86_400)));
+        assert_eq!(parse_duration("90m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("1d12h"), Ok(Duration::from_secs(129_600)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 86_400)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("5d3").is_err());
+        assert!(parse_duration("999999999999999").unwrap_err().ends_with("too long"));
+    }
+
+    #[test]
+    fn test_parse_interval_and_size() {
+        assert_eq!(parse_interval("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_interval("5m"), Ok(Duration::from_secs(300)));
+        assert!(parse_interval("0").is_err());
+
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1.5k"), Ok(1536));
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let args = Args::try_parse_from(["fdu", "--quiet", "dir"]).unwrap();
+        assert!(args.plain && !args.is_decorated());
+        assert!(!Args::try_parse_from(["fdu", "--json"]).unwrap().is_decorated());
+        assert!(Args::try_parse_from(["fdu"]).unwrap().is_decorated());
+        assert!(Args::try_parse_from(["fdu", "-q", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_export_file_checked_up_front() {
+        let args = Args::try_parse_from(["fdu", "--export", "usage.svg"]).unwrap();
+        assert_eq!(args.export, Some(PathBuf::from("usage.svg")));
+        assert!(Args::try_parse_from(["fdu", "--export", "usage.png"]).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::scan::ScanResult;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::Path;
+
+const TREEMAP_TEMPLATE: &str = include_str!("../assets/treemap.html");
+const FLAME_TEMPLATE: &str = include_str!("../assets/flame.svg");
+
+/// Flame graph width in pixels, and height of one level
+const FLAME_WIDTH: f64 = 1200.0;
+const FLAME_ROW: f64 = 18.0;
+/// Room above the frames for the title
+const FLAME_TOP: f64 = 32.0;
+
+/// Name of the node standing for the files directly in a directory
+pub const FILES_NODE: &str = "(files)";
+
+/// A directory and the apparent size of everything below it, as the
+/// exports draw it. Sizes of a node's children add up to its own: what
+/// a directory holds besides subdirectories is a `(files)` child.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeNode {
+    pub name: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SizeNode>,
+}
+
+impl SizeNode {
+    fn new(name: String, size: u64, mut children: Vec<SizeNode>) -> Self {
+        let listed: u64 = children.iter().map(|child| child.size).sum();
+        if !children.is_empty() && size > listed {
+            children.push(Self {
+                name: FILES_NODE.to_string(),
+                size: size - listed,
+                children: Vec::new(),
+            });
+        }
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        Self { name, size, children }
+    }
+
+    /// The tree of directory totals in `result`, as deep as the scan
+    /// listed them (see `--max-depth`)
+    pub fn from_result(result: &ScanResult) -> Self {
+        // `directories` is in post-order: a directory's children come
+        // right before it, one level deeper
+        let mut stack: Vec<(usize, SizeNode)> = Vec::new();
+        for directory in &result.directories {
+            let mut children = Vec::new();
+            while stack.last().is_some_and(|(depth, _)| *depth > directory.depth) {
+                children.extend(stack.pop().map(|(_, node)| node));
+            }
+            let name = if directory.depth == 0 {
+                directory.path.display().to_string()
+            } else {
+                directory.path.file_name().map_or_else(
+                    || directory.path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                )
+            };
+            stack.push((directory.depth, Self::new(name, directory.size, children)));
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some((0, root)), true) => root,
+            _ => Self::new(result.path.display().to_string(), result.size, Vec::new()),
+        }
+    }
+
+    /// One tree for several arguments: the only root, or a `total` above them
+    pub fn join(mut roots: Vec<SizeNode>) -> Self {
+        if roots.len() == 1 {
+            return roots.remove(0);
+        }
+        let size = roots.iter().map(|root| root.size).sum();
+        Self::new("total".to_string(), size, roots)
+    }
+
+    fn depth(&self) -> usize {
+        1 + self.children.iter().map(Self::depth).max().unwrap_or(0)
+    }
+}
+
+/// What `--export` writes, told by the file's extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `.html` or `.htm`
+    Treemap,
+    /// `.svg`
+    FlameGraph,
+}
+
+impl ExportFormat {
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match extension.as_str() {
+            "html" | "htm" => Some(ExportFormat::Treemap),
+            "svg" => Some(ExportFormat::FlameGraph),
+            _ => None,
+        }
+    }
+}
+
+/// `--export FILE`: a treemap for `.html`, a flame graph for `.svg`
+pub fn export(path: &Path, tree: &SizeNode) -> Result<()> {
+    let contents = match ExportFormat::of(path) {
+        Some(ExportFormat::Treemap) => treemap_html(tree)?,
+        Some(ExportFormat::FlameGraph) => flame_svg(tree),
+        None => bail!("--export: cannot tell the format of {}; use a .html or .svg file", path.display()),
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// A self-contained page drawing `tree` as a zoomable treemap on a canvas
+pub fn treemap_html(tree: &SizeNode) -> Result<String> {
+    // `</` cannot appear inside the script element; `<\/` reads the same
+    let data = serde_json::to_string(tree)?.replace("</", "<\\/");
+    Ok(TREEMAP_TEMPLATE
+        .replace("{{TITLE}}", &escape_xml(&format!("fdu: {}", tree.name)))
+        .replace("{{DATA}}", &data))
+}
+
+/// A flame graph of `tree`: the root along the bottom, each directory's
+/// children above it in widths proportional to their sizes, largest first
+pub fn flame_svg(tree: &SizeNode) -> String {
+    let height = FLAME_TOP + tree.depth() as f64 * FLAME_ROW;
+    let mut frames = String::new();
+    let scale = if tree.size == 0 { 0.0 } else { FLAME_WIDTH / tree.size as f64 };
+    flame_frames(tree, 0.0, height - FLAME_ROW, scale, tree.size, &mut frames);
+    FLAME_TEMPLATE
+        .replace("{{WIDTH}}", &FLAME_WIDTH.to_string())
+        .replace("{{HEIGHT}}", &height.to_string())
+        .replace("{{CENTER}}", &(FLAME_WIDTH / 2.0).to_string())
+        .replace("{{TITLE}}", &escape_xml(&format!("fdu: {}", tree.name)))
+        .replace("{{FRAMES}}", frames.trim_end())
+}
+
+fn flame_frames(node: &SizeNode, x: f64, y: f64, scale: f64, total: u64, out: &mut String) {
+    let width = node.size as f64 * scale;
+    if width < 0.1 {
+        return;
+    }
+    let percent = if total == 0 { 0.0 } else { node.size as f64 * 100.0 / total as f64 };
+    let name = escape_xml(&node.name);
+    let _ = write!(
+        out,
+        "<g><title>{} ({} bytes, {:.2}%)</title><rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{}\" fill=\"{}\"/>",
+        name,
+        node.size,
+        percent,
+        x,
+        y,
+        width,
+        FLAME_ROW - 1.0,
+        flame_color(&node.name)
+    );
+    // About 7 pixels a character at 12px
+    let fits = ((width - 6.0) / 7.0).floor().max(0.0) as usize;
+    if fits >= 3 {
+        let label: String = if node.name.chars().count() > fits {
+            node.name.chars().take(fits - 2).chain("..".chars()).collect()
+        } else {
+            node.name.clone()
+        };
+        let _ = write!(out, "<text x=\"{:.2}\" y=\"{:.2}\">{}</text>", x + 3.0, y + FLAME_ROW - 5.0, escape_xml(&label));
+    }
+    out.push_str("</g>\n");
+
+    let mut child_x = x;
+    for child in &node.children {
+        flame_frames(child, child_x, y - FLAME_ROW, scale, total, out);
+        child_x += child.size as f64 * scale;
+    }
+}
+
+/// Warm colors, stable for a name so the same directory keeps its color
+/// between exports
+fn flame_color(name: &str) -> String {
+    let hash = name.bytes().fold(5381u32, |hash, byte| hash.wrapping_mul(33) ^ u32::from(byte));
+    format!("rgb({},{},{})", 205 + hash % 50, 80 + (hash >> 8) % 130, 40 + (hash >> 16) % 50)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::DirectoryTotal;
+    use std::path::PathBuf;
+
+    fn directory(path: &str, depth: usize, size: u64) -> DirectoryTotal {
+        DirectoryTotal {
+            path: PathBuf::from(path),
+            depth,
+            size,
+            physical_size: size,
+        }
+    }
+
+    fn result(directories: Vec<DirectoryTotal>) -> ScanResult {
+        ScanResult {
+            path: PathBuf::from("root"),
+            size: directories.last().map_or(0, |root| root.size),
+            physical_size: 0,
+            entries: 0,
+            files: 0,
+            sparse_files: 0,
+            duplicate_links: 0,
+            filtered_files: 0,
+            cache_dirs: 0,
+            cache_size: 0,
+            directories,
+            extents: None,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tree_from_post_order() {
+        let scanned = result(vec![
+            directory("root/a/deep", 2, 30),
+            directory("root/a", 1, 50),
+            directory("root/b", 1, 100),
+            directory("root", 0, 160),
+        ]);
+        let tree = SizeNode::from_result(&scanned);
+        assert_eq!(tree.name, "root");
+        let names: Vec<(&str, u64)> = tree.children.iter().map(|child| (child.name.as_str(), child.size)).collect();
+        assert_eq!(names, [("b", 100), ("a", 50), (FILES_NODE, 10)]);
+        let a = &tree.children[1];
+        assert_eq!(a.children.iter().map(|child| child.size).collect::<Vec<_>>(), [30, 20]);
+        assert_eq!(tree.depth(), 3);
+
+        // -s lists only the root
+        let summary = SizeNode::from_result(&result(Vec::new()));
+        assert!(summary.children.is_empty());
+        assert_eq!(SizeNode::join(vec![tree.clone(), summary]).children[0], tree);
+    }
+
+    #[test]
+    fn test_exports() {
+        let tree = SizeNode::new(
+            "</script> & co".to_string(),
+            300,
+            vec![SizeNode::new("logs".to_string(), 200, Vec::new())],
+        );
+
+        let html = treemap_html(&tree).unwrap();
+        assert!(!html.contains("{{"));
+        assert!(html.contains(r#""name":"<\/script> & co""#));
+        assert!(html.contains("<title>fdu: &lt;/script&gt; &amp; co</title>"));
+
+        let svg = flame_svg(&tree);
+        assert!(!svg.contains("{{"));
+        assert_eq!(svg.matches("<g>").count(), 3);
+        assert!(svg.contains(r#"<rect x="0.00" y="50.00" width="1200.00""#));
+        assert!(svg.contains(r#"<rect x="0.00" y="32.00" width="800.00""#));
+        assert!(svg.contains("logs (200 bytes, 66.67%)"));
+
+        assert!(export(Path::new("usage.png"), &tree).is_err());
+        assert_eq!(ExportFormat::of(Path::new("usage.HTM")), Some(ExportFormat::Treemap));
+        assert_eq!(ExportFormat::of(Path::new("out/usage.svg")), Some(ExportFormat::FlameGraph));
+        assert_eq!(ExportFormat::of(Path::new("svg")), None);
+    }
+}
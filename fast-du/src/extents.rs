@@ -0,0 +1,205 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One extent of a file's data, as FIEMAP reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    /// Byte offset on the device; `None` while the location is unknown
+    /// (delayed allocation) or meaningless (data inline in the metadata)
+    pub physical: Option<u64>,
+    pub length: u64,
+    /// Also referenced by another file or snapshot (reflinks, btrfs/XFS)
+    pub shared: bool,
+    /// Stored compressed (or otherwise encoded) by the filesystem
+    pub encoded: bool,
+}
+
+/// What `--extents` found: how much data files reference against how much
+/// is physically allocated for it, for filesystems where copies can share
+/// storage (btrfs and XFS reflinks, snapshots) or data is compressed.
+///
+/// Lengths are the logical lengths FIEMAP reports, so compressed extents
+/// count at their uncompressed size; `compressed` says how much of the data
+/// that is.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtentReport {
+    /// Bytes in the extents of every counted file
+    pub referenced: u64,
+    /// Bytes in distinct extents: shared storage counted once
+    pub allocated: u64,
+    /// Bytes in extents the filesystem marks as shared
+    pub shared: u64,
+    /// Bytes in compressed extents
+    pub compressed: u64,
+    /// Files with at least one shared extent, e.g. reflinked copies
+    pub shared_files: Vec<PathBuf>,
+    /// Files whose filesystem cannot report extents
+    pub unsupported_files: u64,
+    /// (device, physical offset) of the extents counted in `allocated`
+    #[serde(skip)]
+    seen: HashSet<(u64, u64)>,
+}
+
+impl ExtentReport {
+    /// Count the extents of the file at `path` on `device`
+    pub fn add(&mut self, path: &Path, device: u64, extents: &[Extent]) {
+        let mut shared = false;
+        for extent in extents {
+            self.referenced += extent.length;
+            let new = extent.physical.is_none_or(|physical| self.seen.insert((device, physical)));
+            if new {
+                self.allocated += extent.length;
+            }
+            if extent.shared {
+                self.shared += extent.length;
+                shared = true;
+            }
+            if extent.encoded {
+                self.compressed += extent.length;
+            }
+        }
+        if shared {
+            self.shared_files.push(path.to_path_buf());
+        }
+    }
+}
+
+/// The extents of the regular file at `path`; `Unsupported` where the
+/// platform or filesystem has no FIEMAP
+#[cfg(target_os = "linux")]
+pub fn file_extents(path: &Path) -> io::Result<Vec<Extent>> {
+    use std::os::unix::io::AsRawFd;
+
+    /// struct fiemap_extent from linux/fiemap.h
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct FiemapExtent {
+        logical: u64,
+        physical: u64,
+        length: u64,
+        reserved64: [u64; 2],
+        flags: u32,
+        reserved: [u32; 3],
+    }
+
+    /// struct fiemap, with room for `BATCH` extents after it
+    #[repr(C)]
+    struct Fiemap {
+        start: u64,
+        length: u64,
+        flags: u32,
+        mapped_extents: u32,
+        extent_count: u32,
+        reserved: u32,
+        extents: [FiemapExtent; BATCH],
+    }
+
+    const BATCH: usize = 64;
+    const FS_IOC_FIEMAP: libc::c_ulong = 0xC020_660B;
+    const FIEMAP_EXTENT_LAST: u32 = 0x0001;
+    const FIEMAP_EXTENT_UNKNOWN: u32 = 0x0002;
+    const FIEMAP_EXTENT_ENCODED: u32 = 0x0008;
+    const FIEMAP_EXTENT_DATA_INLINE: u32 = 0x0200;
+    const FIEMAP_EXTENT_SHARED: u32 = 0x2000;
+
+    let file = std::fs::File::open(path)?;
+    let mut extents = Vec::new();
+    let mut start = 0;
+    loop {
+        let mut request = Fiemap {
+            start,
+            length: u64::MAX - start,
+            flags: 0,
+            mapped_extents: 0,
+            extent_count: BATCH as u32,
+            reserved: 0,
+            extents: [FiemapExtent::default(); BATCH],
+        };
+        // SAFETY: `request` is a struct fiemap followed by `extent_count` extents
+        let result = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut request as *mut Fiemap) };
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            return Err(match error.raw_os_error() {
+                Some(libc::EOPNOTSUPP) | Some(libc::ENOTTY) => io::Error::new(io::ErrorKind::Unsupported, error),
+                _ => error,
+            });
+        }
+
+        let mapped = &request.extents[..(request.mapped_extents as usize).min(BATCH)];
+        for extent in mapped {
+            let located = extent.flags & (FIEMAP_EXTENT_UNKNOWN | FIEMAP_EXTENT_DATA_INLINE) == 0;
+            extents.push(Extent {
+                physical: located.then_some(extent.physical),
+                length: extent.length,
+                shared: extent.flags & FIEMAP_EXTENT_SHARED != 0,
+                encoded: extent.flags & FIEMAP_EXTENT_ENCODED != 0,
+            });
+        }
+        match mapped.last() {
+            Some(last) if last.flags & FIEMAP_EXTENT_LAST == 0 => start = last.logical + last.length,
+            _ => return Ok(extents),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn file_extents(_path: &Path) -> io::Result<Vec<Extent>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "FIEMAP is only available on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(physical: u64, length: u64, shared: bool) -> Extent {
+        Extent {
+            physical: Some(physical),
+            length,
+            shared,
+            encoded: false,
+        }
+    }
+
+    #[test]
+    fn test_shared_extents_are_allocated_once() {
+        let mut report = ExtentReport::default();
+        report.add(Path::new("original"), 1, &[extent(4096, 8192, true), extent(65536, 4096, false)]);
+        report.add(Path::new("reflink"), 1, &[extent(4096, 8192, true)]);
+        // The same offset on another device is different storage
+        report.add(Path::new("elsewhere"), 2, &[extent(4096, 100, false)]);
+        report.add(
+            Path::new("compressed"),
+            1,
+            &[Extent { physical: None, length: 512, shared: false, encoded: true }],
+        );
+
+        assert_eq!(report.referenced, 8192 + 4096 + 8192 + 100 + 512);
+        assert_eq!(report.allocated, 8192 + 4096 + 100 + 512);
+        assert_eq!(report.shared, 16384);
+        assert_eq!(report.compressed, 512);
+        assert_eq!(report.shared_files, vec![PathBuf::from("original"), PathBuf::from("reflink")]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_file_extents() {
+        use std::io::Write;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data");
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&vec![7u8; 64 * 1024]).unwrap();
+        file.sync_all().unwrap();
+
+        match file_extents(&path) {
+            Ok(extents) => {
+                let length: u64 = extents.iter().map(|extent| extent.length).sum();
+                assert!(length >= 64 * 1024, "{:?}", extents);
+            }
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::Unsupported),
+        }
+        assert!(file_extents(&dir.path().join("missing")).is_err());
+    }
+}
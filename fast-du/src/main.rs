@@ -1,43 +1,98 @@
+mod cli;
+mod export;
+mod extents;
+mod preflight;
+mod scan;
+mod watch;
+
 use anyhow::Result;
 use clap::Parser;
+use cli::{Args, SortKey};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::prelude::*;
+use scan::{CachePolicy, DirectoryTotal, FileFilter, ScanOptions, ScanResult};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-
-#[derive(Parser)]
-#[command(name = "fdu")]
-#[command(about = "Parallel disk usage analyzer - modern du alternative")]
-#[command(version = "0.1.0")]
-struct Args {
-    /// Directories to analyze
-    #[arg(default_value = ".")]
-    paths: Vec<PathBuf>,
-    
-    /// Show human-readable sizes
-    #[arg(short = 'h', long = "human-readable")]
-    human_readable: bool,
-    
-    /// Show directory totals only
-    #[arg(short = 's', long = "summarize")]
-    summarize: bool,
-    
-    /// Maximum depth to descend
-    #[arg(short = 'd', long = "max-depth")]
-    max_depth: Option<usize>,
-    
-    /// Number of threads (default: CPU cores)
-    #[arg(short = 'j', long = "threads")]
-    threads: Option<usize>,
+use std::process::ExitCode;
+use std::time::SystemTime;
+use watch::Growth;
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    results: &'a [ScanResult],
+    /// Present with `--total`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grand_total: Option<GrandTotal>,
+    /// Present with `--compare`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comparison: Option<Vec<Comparison>>,
+    total_errors: usize,
+}
+
+/// Sums over all arguments for `-c/--total`
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct GrandTotal {
+    size: u64,
+    physical_size: u64,
+    entries: u64,
+}
+
+impl GrandTotal {
+    fn of(results: &[ScanResult]) -> Self {
+        results.iter().fold(Self::default(), |total, result| Self {
+            size: total.size + result.size,
+            physical_size: total.physical_size + result.physical_size,
+            entries: total.entries + result.entries,
+        })
+    }
 }
 
-fn main() -> Result<()> {
+/// One argument's row in the `--compare` table
+#[derive(Debug, Serialize)]
+struct Comparison {
+    path: PathBuf,
+    size: u64,
+    files: u64,
+    /// The biggest directory right below the argument, if it has any
+    largest_subdirectory: Option<DirectoryTotal>,
+    /// Percent of the combined size of all arguments
+    share: f64,
+}
+
+impl Comparison {
+    fn of(results: &[ScanResult]) -> Vec<Self> {
+        let combined: u64 = results.iter().map(|result| result.size).sum();
+        results
+            .iter()
+            .map(|result| Self {
+                path: result.path.clone(),
+                size: result.size,
+                files: result.files,
+                largest_subdirectory: result
+                    .directories
+                    .iter()
+                    .filter(|directory| directory.depth == 1)
+                    .max_by(|a, b| a.size.cmp(&b.size).then_with(|| b.path.cmp(&a.path)))
+                    .cloned(),
+                share: if combined == 0 { 0.0 } else { result.size as f64 * 100.0 / combined as f64 },
+            })
+            .collect()
+    }
+}
+
+fn main() -> Result<ExitCode> {
     let args = Args::parse();
+    if args.plain || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
     
-    println!("{}", "💾 fast-du (fdu) - Parallel Disk Usage Analyzer".bold().cyan());
-    println!("{}", "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━".dimmed());
+    if args.is_decorated() {
+        println!("{}", "💾 fast-du (fdu) - Parallel Disk Usage Analyzer".bold().cyan());
+        println!("{}", "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━".dimmed());
+    }
     
     // Set up thread pool
     if let Some(threads) = args.threads {
@@ -47,82 +102,470 @@ fn main() -> Result<()> {
             .unwrap();
     }
     
-    let progress = ProgressBar::new_spinner();
+    if args.preflight {
+        return Ok(run_preflight(&args));
+    }
+
+    let progress = if args.plain { ProgressBar::hidden() } else { ProgressBar::new_spinner() };
     progress.set_style(
         ProgressStyle::default_spinner()
             .template("{spinner:.green} {msg}")
             .unwrap()
     );
     progress.set_message("Scanning directories...");
+
+    // Ages are measured from the start of the run
+    let now = SystemTime::now();
+    let options = ScanOptions {
+        report_depth: args.report_depth(),
+        dereference: args.should_dereference(),
+        count_links: args.count_links,
+        filter: FileFilter {
+            modified_after: args.newer_than.and_then(|age| now.checked_sub(age)),
+            modified_before: args.older_than.and_then(|age| now.checked_sub(age)),
+            include_ext: FileFilter::extensions(&args.include_ext),
+            exclude_ext: FileFilter::extensions(&args.exclude_ext),
+        },
+        caches: args.skip_caches.then(|| CachePolicy::new(args.cache_names.clone())),
+        extents: args.extents,
+    };
     
+    let mut results = Vec::new();
     for path in &args.paths {
-        let size = calculate_directory_size(path, &args, &progress)?;
+        let result = scan::scan(path, &options, &progress);
         
         progress.finish_and_clear();
-        
-        let size_str = if args.human_readable {
-            format_human_size(size)
-        } else {
-            size.to_string()
-        };
-        
-        println!("{} {}", 
-            size_str.yellow().bold(),
-            path.display().to_string().blue()
+
+        if !args.json_output {
+            for error in &result.errors {
+                eprintln!("{} {}", "fdu:".red().bold(), error);
+            }
+        }
+
+        if !args.json_output && !args.compare {
+            let directories = if args.others {
+                Cow::Owned(with_others(&result.directories))
+            } else {
+                Cow::Borrowed(&result.directories[..])
+            };
+            for directory in listed_directories(&directories, args.sort) {
+                if args.plain {
+                    println!("{}\t{}", format_size(directory.size, &args), directory.path.display());
+                    continue;
+                }
+                let path = directory.path.display().to_string();
+                let path = if directory.path.ends_with(OTHERS_NAME) { path.dimmed() } else { path.blue() };
+                // Sparse files and skipped links make a directory's disk use differ from its size
+                let on_disk = if directory.physical_size == directory.size {
+                    String::new()
+                } else {
+                    format!(" ({} on disk)", format_size(directory.physical_size, &args))
+                };
+                println!("{} {}{}", format_size(directory.size, &args).yellow(), path, on_disk.dimmed());
+            }
+
+            if args.plain {
+                println!("{}\t{}", format_size(result.size, &args), path.display());
+            } else {
+                let format_size = |size: u64| format_size(size, &args);
+                println!("{} {} {}", 
+                    format_size(result.size).yellow().bold(),
+                    path.display().to_string().blue(),
+                    format_size_details(&result, &format_size, args.count_links).dimmed()
+                );
+            }
+        }
+
+        results.push(result);
+    }
+
+    let error_count: usize = results.iter().map(|r| r.errors.len()).sum();
+
+    let comparison = args.compare.then(|| Comparison::of(&results));
+    if let (Some(rows), false) = (&comparison, args.json_output) {
+        print_comparison(rows, &args);
+    }
+
+    let grand_total = args.total.then(|| GrandTotal::of(&results));
+    if let (Some(total), true) = (&grand_total, args.plain) {
+        println!("{}\ttotal", format_size(total.size, &args));
+    } else if let (Some(total), false) = (&grand_total, args.json_output) {
+        println!(
+            "{} {} {}",
+            format_size(total.size, &args).yellow().bold(),
+            "total".bold(),
+            format!("({} on disk)", format_size(total.physical_size, &args)).dimmed()
         );
     }
+
+    if args.json_output {
+        let report = JsonReport {
+            results: &results,
+            grand_total,
+            comparison,
+            total_errors: error_count,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        if error_count > 0 {
+            eprintln!("{}", format_error_summary(&results).red());
+        }
+
+        if !args.plain {
+            println!("\n{}", "⚡ Coming soon: 30x faster parallel disk usage calculation!".yellow().italic());
+            println!("{}", "🚀 Features: Tree visualization, progress bars, memory-efficient scanning".green());
+        }
+    }
+
+    if let Some(ref file) = args.export {
+        let tree = export::SizeNode::join(results.iter().map(export::SizeNode::from_result).collect());
+        export::export(file, &tree)?;
+        if args.is_decorated() {
+            eprintln!("{}", format!("Exported {} to {}", tree.name, file.display()).dimmed());
+        }
+    }
+
+    if let Some(interval) = args.watch {
+        if args.is_decorated() {
+            eprintln!(
+                "{}",
+                format!(
+                    "Watching for directories growing by more than {} every {}s (Ctrl-C to stop)",
+                    format_human_size(args.alert_threshold),
+                    interval.as_secs_f64()
+                )
+                .dimmed()
+            );
+        }
+        watch::watch(&args.paths, results, &options, interval, args.alert_threshold, |grown| {
+            report_growth(grown, &args)
+        });
+    }
     
-    println!("\n{}", "⚡ Coming soon: 30x faster parallel disk usage calculation!".yellow().italic());
-    println!("{}", "🚀 Features: Tree visualization, progress bars, memory-efficient scanning".green());
-    
-    Ok(())
+    // Like du: report what could be measured, but fail if anything could not
+    Ok(if error_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
 }
 
-fn calculate_directory_size(path: &PathBuf, args: &Args, progress: &ProgressBar) -> Result<u64> {
-    let total_size = Arc::new(AtomicU64::new(0));
-    let processed_files = Arc::new(AtomicU64::new(0));
-    
-    progress.set_message(format!("Scanning {}", path.display()));
-    
-    // Collect all entries first (will be optimized with parallel walkdir)
-    let mut entries = Vec::new();
-    collect_entries(path, &mut entries, args.max_depth.unwrap_or(usize::MAX), 0)?;
-    
-    // Process files in parallel
-    entries.par_iter().for_each(|entry| {
-        if let Ok(metadata) = std::fs::metadata(entry) {
-            if metadata.is_file() {
-                total_size.fetch_add(metadata.len(), Ordering::Relaxed);
+/// `--preflight` for every path: estimates as text, one JSON object per
+/// path with `--json`, or "KEY<tab>VALUE" lines with `--plain`
+fn run_preflight(args: &Args) -> ExitCode {
+    let threads = rayon::current_num_threads();
+    let mut unreadable = 0;
+    for path in &args.paths {
+        let report = preflight::preflight(path, preflight::Budget::default(), threads);
+        unreadable += report.unreadable.len();
+        let approximately = if report.exact { "" } else { "~" };
+        let estimate = |count: u64| format!("{}{}", approximately, count);
+
+        if args.json_output {
+            match serde_json::to_string(&report) {
+                Ok(line) => println!("{}", line),
+                Err(e) => eprintln!("{} {}", "fdu:".red().bold(), e),
+            }
+        } else if args.plain {
+            println!("path\t{}", report.path.display());
+            println!("files\t{}", estimate(report.files));
+            println!("directories\t{}", estimate(report.directories));
+            println!("size\t{}{}", approximately, format_size(report.size, args));
+            println!("seconds\t~{:.1}", report.estimated_seconds);
+            for error in &report.unreadable {
+                println!("unreadable\t{}", error.path.display());
+            }
+        } else {
+            let listed = if report.exact {
+                format!("all {} directories listed", report.directories_listed)
+            } else {
+                format!("sampled from {} directories", report.directories_listed)
+            };
+            println!("{} {}", report.path.display().to_string().blue().bold(), format!("({})", listed).dimmed());
+            println!("  Files:        {}", estimate(report.files));
+            println!("  Directories:  {}", estimate(report.directories));
+            println!("  Size:         {}{}", approximately, format_size(report.size, args).yellow());
+            println!(
+                "  Scan time:    ~{} on {} thread{}",
+                format_seconds(report.estimated_seconds),
+                report.threads,
+                if report.threads == 1 { "" } else { "s" }
+            );
+            if !report.unreadable.is_empty() {
+                println!("  Unreadable:   {}", report.unreadable.len().to_string().red().bold());
+                for error in &report.unreadable {
+                    println!("    {}", error.to_string().red());
+                }
             }
         }
-        
-        let processed = processed_files.fetch_add(1, Ordering::Relaxed);
-        if processed % 1000 == 0 {
-            progress.set_message(format!("Processed {} files in {}", processed, path.display()));
+    }
+    if unreadable > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The `--compare` table: aligned columns, or a header and
+/// "PATH<tab>SIZE<tab>FILES<tab>LARGEST<tab>LARGEST_SIZE<tab>SHARE" rows with `--plain`
+fn print_comparison(rows: &[Comparison], args: &Args) {
+    let largest_name = |row: &Comparison| {
+        row.largest_subdirectory.as_ref().map_or_else(String::new, |directory| {
+            directory
+                .path
+                .strip_prefix(&row.path)
+                .unwrap_or(&directory.path)
+                .display()
+                .to_string()
+        })
+    };
+    if args.plain {
+        println!("path\tsize\tfiles\tlargest\tlargest_size\tshare");
+        for row in rows {
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{:.1}",
+                row.path.display(),
+                format_size(row.size, args),
+                row.files,
+                largest_name(row),
+                row.largest_subdirectory.as_ref().map_or_else(String::new, |directory| format_size(directory.size, args)),
+                row.share
+            );
         }
-    });
-    
-    Ok(total_size.load(Ordering::Relaxed))
+        return;
+    }
+
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            let largest = match row.largest_subdirectory {
+                Some(ref directory) => format!("{} ({})", largest_name(row), format_size(directory.size, args)),
+                None => "-".to_string(),
+            };
+            [
+                row.path.display().to_string(),
+                format_size(row.size, args),
+                row.files.to_string(),
+                largest,
+                format!("{:.1}%", row.share),
+            ]
+        })
+        .collect();
+    let header = ["PATH", "SIZE", "FILES", "LARGEST SUBDIRECTORY", "SHARE"];
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    println!(
+        "{}",
+        format!(
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:<w3$}  {:>w4$}",
+            header[0], header[1], header[2], header[3], header[4],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4]
+        )
+        .bold()
+    );
+    for [path, size, files, largest, share] in &cells {
+        println!(
+            "{}  {}  {:>w2$}  {}  {}",
+            format!("{:<w$}", path, w = widths[0]).blue(),
+            format!("{:>w$}", size, w = widths[1]).yellow(),
+            files,
+            format!("{:<w$}", largest, w = widths[3]).dimmed(),
+            format!("{:>w$}", share, w = widths[4]).bold(),
+            w2 = widths[2]
+        );
+    }
 }
 
-fn collect_entries(path: &PathBuf, entries: &mut Vec<PathBuf>, max_depth: usize, current_depth: usize) -> Result<()> {
-    if current_depth >= max_depth {
-        return Ok(());
+/// "0.4s", "12s", "3m 05s" or "2h 10m"
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 10.0 {
+        return format!("{:.1}s", seconds);
     }
-    
-    let dir_entries = std::fs::read_dir(path)?;
-    
-    for entry in dir_entries {
-        let entry = entry?;
-        let entry_path = entry.path();
-        entries.push(entry_path.clone());
-        
-        if entry_path.is_dir() {
-            collect_entries(&entry_path, entries, max_depth, current_depth + 1)?;
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// One line per grown directory: text with a timestamp, a JSON object per
+/// line with `--json`, or "TIME<tab>+GROWTH<tab>SIZE<tab>PATH" with `--plain`
+fn report_growth(grown: &[Growth], args: &Args) {
+    let time = chrono::Local::now();
+    for growth in grown {
+        if args.json_output {
+            let line = serde_json::json!({
+                "time": time.to_rfc3339(),
+                "path": growth.path,
+                "previous_size": growth.previous_size,
+                "size": growth.size,
+                "growth": growth.grown_by(),
+            });
+            println!("{}", line);
+        } else if args.plain {
+            println!(
+                "{}\t+{}\t{}\t{}",
+                time.format("%H:%M:%S"),
+                format_size(growth.grown_by(), args),
+                format_size(growth.size, args),
+                growth.path.display()
+            );
+        } else {
+            println!(
+                "{} {} {} {}",
+                time.format("%H:%M:%S").to_string().dimmed(),
+                format!("+{}", format_size(growth.grown_by(), args)).red().bold(),
+                growth.path.display().to_string().blue(),
+                format!("(now {})", format_size(growth.size, args)).dimmed()
+            );
         }
     }
-    
-    Ok(())
+}
+
+fn format_size(size: u64, args: &Args) -> String {
+    if args.human_readable {
+        format_human_size(size)
+    } else {
+        size.to_string()
+    }
+}
+
+/// Last component of the `--others` rows
+const OTHERS_NAME: &str = "(others)";
+
+/// `directories` with an `--others` row before each directory that has
+/// listed subdirectories, one level deeper, sized at what they leave out
+fn with_others(directories: &[DirectoryTotal]) -> Vec<DirectoryTotal> {
+    let mut rows = Vec::with_capacity(directories.len() * 2);
+    // Sizes of the listed directories awaiting their parent, as (depth, size, physical size)
+    let mut pending: Vec<(usize, u64, u64)> = Vec::new();
+    for directory in directories {
+        let (mut size, mut physical_size, mut children) = (0, 0, 0);
+        while let Some(&(depth, child_size, child_physical)) = pending.last() {
+            if depth <= directory.depth {
+                break;
+            }
+            pending.pop();
+            size += child_size;
+            physical_size += child_physical;
+            children += 1;
+        }
+        if children > 0 && directory.size > size {
+            rows.push(DirectoryTotal {
+                path: directory.path.join(OTHERS_NAME),
+                depth: directory.depth + 1,
+                size: directory.size - size,
+                physical_size: directory.physical_size.saturating_sub(physical_size),
+            });
+        }
+        rows.push(directory.clone());
+        pending.push((directory.depth, directory.size, directory.physical_size));
+    }
+    rows
+}
+
+/// Subdirectories to print above an argument's own line: in du's order, or
+/// sorted by `--sort`
+fn listed_directories(directories: &[DirectoryTotal], sort: Option<SortKey>) -> Vec<&DirectoryTotal> {
+    let mut directories: Vec<&DirectoryTotal> = directories
+        .iter()
+        .filter(|directory| directory.depth > 0)
+        .collect();
+    match sort {
+        Some(SortKey::Size) => directories.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))),
+        Some(SortKey::Name) => directories.sort_by(|a, b| a.path.cmp(&b.path)),
+        None => {}
+    }
+    directories
+}
+
+/// "fdu: 3 errors (2 permission denied, 1 not found)"
+fn format_error_summary(results: &[ScanResult]) -> String {
+    let mut by_kind: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for error in results.iter().flat_map(|r| &r.errors) {
+        *by_kind.entry(error.kind.description()).or_default() += 1;
+    }
+
+    let total: usize = by_kind.values().sum();
+    let breakdown: Vec<String> = by_kind
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+
+    format!(
+        "fdu: {} error{} ({})",
+        total,
+        if total == 1 { "" } else { "s" },
+        breakdown.join(", ")
+    )
+}
+
+/// "(12.0K on disk, 1 sparse file, 2 duplicate hard links skipped, 40 files
+/// filtered out, 3.1G in 2 cache directories skipped)"
+fn format_size_details(result: &ScanResult, format_size: &dyn Fn(u64) -> String, count_links: bool) -> String {
+    let mut details = vec![format!("{} on disk", format_size(result.physical_size))];
+    if result.sparse_files > 0 {
+        details.push(format!(
+            "{} sparse file{}",
+            result.sparse_files,
+            if result.sparse_files == 1 { "" } else { "s" }
+        ));
+    }
+    if result.duplicate_links > 0 {
+        details.push(format!(
+            "{} duplicate hard link{} {}",
+            result.duplicate_links,
+            if result.duplicate_links == 1 { "" } else { "s" },
+            if count_links { "counted" } else { "skipped" }
+        ));
+    }
+    if result.filtered_files > 0 {
+        details.push(format!(
+            "{} file{} filtered out",
+            result.filtered_files,
+            if result.filtered_files == 1 { "" } else { "s" }
+        ));
+    }
+    if result.cache_dirs > 0 {
+        details.push(format!(
+            "{} in {} cache director{} skipped",
+            format_size(result.cache_size),
+            result.cache_dirs,
+            if result.cache_dirs == 1 { "y" } else { "ies" }
+        ));
+    }
+    if let Some(ref extents) = result.extents {
+        details.push(format!(
+            "{} referenced, {} allocated",
+            format_size(extents.referenced),
+            format_size(extents.allocated)
+        ));
+        if extents.shared > 0 {
+            let files = extents.shared_files.len();
+            details.push(format!(
+                "{} shared by {} file{}",
+                format_size(extents.shared),
+                files,
+                if files == 1 { "" } else { "s" }
+            ));
+        }
+        if extents.compressed > 0 {
+            details.push(format!("{} compressed", format_size(extents.compressed)));
+        }
+        if extents.unsupported_files > 0 {
+            details.push(format!(
+                "extents unavailable for {} file{}",
+                extents.unsupported_files,
+                if extents.unsupported_files == 1 { "" } else { "s" }
+            ));
+        }
+    }
+    format!("({})", details.join(", "))
 }
 
 fn format_human_size(size: u64) -> String {
@@ -153,4 +596,161 @@ mod tests {
         assert_eq!(format_human_size(1536), "1.5K");
         assert_eq!(format_human_size(1024 * 1024), "1.0M");
     }
+
+    #[test]
+    fn test_format_seconds() {
+        assert_eq!(format_seconds(0.42), "0.4s");
+        assert_eq!(format_seconds(12.4), "12s");
+        assert_eq!(format_seconds(185.0), "3m 05s");
+        assert_eq!(format_seconds(7_830.0), "2h 10m");
+    }
+
+    #[test]
+    fn test_listed_directories_and_total() {
+        let directory = |path: &str, depth, size| DirectoryTotal {
+            path: path.into(),
+            depth,
+            size,
+            physical_size: size,
+        };
+        let result = ScanResult {
+            path: "root".into(),
+            size: 60,
+            physical_size: 60,
+            entries: 4,
+            files: 0,
+            sparse_files: 0,
+            duplicate_links: 0,
+            filtered_files: 0,
+            cache_dirs: 0,
+            cache_size: 0,
+            directories: vec![
+                directory("root/b/deep", 2, 5),
+                directory("root/b", 1, 10),
+                directory("root/a", 1, 40),
+                directory("root", 0, 60),
+            ],
+            extents: None,
+            errors: Vec::new(),
+        };
+        let paths = |sort| {
+            listed_directories(&result.directories, sort)
+                .iter()
+                .map(|directory| directory.path.display().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(None), vec!["root/b/deep", "root/b", "root/a"]);
+        assert_eq!(paths(Some(SortKey::Size)), vec!["root/a", "root/b", "root/b/deep"]);
+        assert_eq!(paths(Some(SortKey::Name)), vec!["root/a", "root/b", "root/b/deep"]);
+
+        let total = GrandTotal::of(&[result]);
+        assert_eq!(total, GrandTotal { size: 60, physical_size: 60, entries: 4 });
+    }
+
+    #[test]
+    fn test_others_rows() {
+        let directory = |path: &str, depth, size| DirectoryTotal {
+            path: path.into(),
+            depth,
+            size,
+            physical_size: size,
+        };
+        let directories = vec![
+            directory("root/b/deep", 2, 5),
+            directory("root/b", 1, 10),
+            directory("root/c/only", 2, 3),
+            directory("root/c", 1, 3),
+            directory("root/a", 1, 40),
+            directory("root", 0, 63),
+        ];
+        let rows: Vec<(String, u64)> = with_others(&directories)
+            .iter()
+            .map(|row| (row.path.display().to_string(), row.size))
+            .collect();
+        // No row for leaves or for root/c, whose subdirectory holds all of it
+        let expected = [
+            ("root/b/deep", 5),
+            ("root/b/(others)", 5),
+            ("root/b", 10),
+            ("root/c/only", 3),
+            ("root/c", 3),
+            ("root/a", 40),
+            ("root/(others)", 10),
+            ("root", 63),
+        ];
+        assert_eq!(rows, expected.map(|(path, size)| (path.to_string(), size)));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let result = |path: &str, size, files, directories: Vec<(&str, u64)>| ScanResult {
+            path: path.into(),
+            size,
+            physical_size: size,
+            entries: files + directories.len() as u64,
+            files,
+            sparse_files: 0,
+            duplicate_links: 0,
+            filtered_files: 0,
+            cache_dirs: 0,
+            cache_size: 0,
+            directories: directories
+                .into_iter()
+                .map(|(name, size)| DirectoryTotal {
+                    path: PathBuf::from(path).join(name),
+                    depth: 1,
+                    size,
+                    physical_size: size,
+                })
+                .collect(),
+            extents: None,
+            errors: Vec::new(),
+        };
+        let rows = Comparison::of(&[
+            result("/a", 300, 7, vec![("x", 100), ("y", 200)]),
+            result("/b", 100, 2, Vec::new()),
+        ]);
+        assert_eq!(rows[0].files, 7);
+        assert_eq!(rows[0].largest_subdirectory.as_ref().unwrap().path, PathBuf::from("/a/y"));
+        assert_eq!(rows[0].share, 75.0);
+        assert!(rows[1].largest_subdirectory.is_none());
+        assert_eq!(rows[1].share, 25.0);
+
+        let empty = Comparison::of(&[result("/c", 0, 0, Vec::new())]);
+        assert_eq!(empty[0].share, 0.0);
+    }
+
+    #[test]
+    fn test_error_summary() {
+        use scan::{ScanError, ScanErrorKind};
+
+        let error = |kind| ScanError {
+            path: "x".into(),
+            kind,
+            message: String::new(),
+        };
+        let results = vec![ScanResult {
+            path: ".".into(),
+            size: 0,
+            physical_size: 0,
+            entries: 0,
+            files: 0,
+            sparse_files: 0,
+            duplicate_links: 0,
+            filtered_files: 0,
+            cache_dirs: 0,
+            cache_size: 0,
+            directories: Vec::new(),
+            extents: None,
+            errors: vec![
+                error(ScanErrorKind::PermissionDenied),
+                error(ScanErrorKind::NotFound),
+                error(ScanErrorKind::PermissionDenied),
+            ],
+        }];
+        assert_eq!(
+            format_error_summary(&results),
+            "fdu: 3 errors (1 not found, 2 permission denied)"
+        );
+    }
 }
\ No newline at end of file
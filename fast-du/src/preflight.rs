@@ -0,0 +1,277 @@
+use crate::scan::{ScanError, ScanErrorKind};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Directories `--preflight` lists breadth-first from the root before it
+/// switches to sampling; a tree no bigger than this is counted exactly
+pub const EXACT_DIRECTORIES: usize = 1_000;
+
+/// Random descents from the root averaged into the estimate of a larger tree
+pub const PROBES: usize = 256;
+
+/// Deepest a descent goes, against bind mounts that loop
+const MAX_PROBE_DEPTH: usize = 256;
+
+/// How much of the tree a preflight reads
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub exact_directories: usize,
+    pub probes: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            exact_directories: EXACT_DIRECTORIES,
+            probes: PROBES,
+        }
+    }
+}
+
+/// What `--preflight` expects a full scan of `path` to find, from reading
+/// only part of the tree. Nothing is written and every error is recorded,
+/// so it is safe to run against a volume before committing to a long scan.
+#[derive(Debug, Serialize)]
+pub struct PreflightReport {
+    pub path: PathBuf,
+    /// Every directory was listed, so the counts are exact
+    pub exact: bool,
+    pub directories_listed: u64,
+    pub files: u64,
+    pub directories: u64,
+    /// Apparent size, as a scan without filters would report it
+    pub size: u64,
+    /// Rough time a scan on `threads` threads would take, with the cache
+    /// as cold as it was for the preflight
+    pub estimated_seconds: f64,
+    pub threads: usize,
+    /// Directories that could not be read, and seen directories this user
+    /// lacks read or search permission on, which a scan will fail to enter
+    pub unreadable: Vec<ScanError>,
+}
+
+/// A directory's own contents
+#[derive(Debug, Default)]
+struct Listing {
+    files: u64,
+    bytes: u64,
+    subdirs: Vec<PathBuf>,
+}
+
+struct Preflight {
+    listings: HashMap<PathBuf, Listing>,
+    unreadable: BTreeMap<PathBuf, ScanError>,
+    entries_read: u64,
+}
+
+impl Preflight {
+    /// List `dir` once, remembering unreadable directories
+    fn list(&mut self, dir: &Path) -> &Listing {
+        if !self.listings.contains_key(dir) {
+            let listing = self.read(dir);
+            self.listings.insert(dir.to_path_buf(), listing);
+        }
+        &self.listings[dir]
+    }
+
+    fn read(&mut self, dir: &Path) -> Listing {
+        let mut listing = Listing::default();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.unreadable.insert(dir.to_path_buf(), ScanError::io(dir, "cannot read directory", &e));
+                return listing;
+            }
+        };
+        for entry in entries.flatten() {
+            self.entries_read += 1;
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    if !can_enter(&path) {
+                        self.unreadable.entry(path.clone()).or_insert_with(|| ScanError {
+                            path: path.clone(),
+                            kind: ScanErrorKind::PermissionDenied,
+                            message: "no read or search permission".to_string(),
+                        });
+                    }
+                    listing.subdirs.push(path);
+                }
+                _ => {
+                    listing.files += 1;
+                    listing.bytes += entry.metadata().map_or(0, |metadata| metadata.len());
+                }
+            }
+        }
+        listing.subdirs.sort();
+        listing
+    }
+}
+
+/// Count or estimate what is under `root` without scanning all of it.
+///
+/// Up to `budget.exact_directories` directories are listed breadth-first;
+/// if that covers the tree, the counts are exact. Otherwise the totals are
+/// estimated by Knuth's method: each probe walks from the root down through
+/// randomly chosen subdirectories, weighting what each level holds by the
+/// product of the branching factors above it, which averages out to the
+/// true totals. Listings from the first pass are reused.
+pub fn preflight(root: &Path, budget: Budget, threads: usize) -> PreflightReport {
+    let started = Instant::now();
+    let mut preflight = Preflight {
+        listings: HashMap::new(),
+        unreadable: BTreeMap::new(),
+        entries_read: 0,
+    };
+
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    while preflight.listings.len() < budget.exact_directories.max(1) {
+        let Some(dir) = queue.pop_front() else {
+            break;
+        };
+        queue.extend(preflight.list(&dir).subdirs.iter().cloned());
+    }
+    let exact = queue.is_empty();
+
+    let (files, directories, size) = if exact {
+        preflight.listings.values().fold((0, 0, 0), |(files, directories, size), listing| {
+            (files + listing.files, directories + 1, size + listing.bytes)
+        })
+    } else {
+        let mut random = XorShift(0x9E37_79B9_7F4A_7C15);
+        let (mut files, mut directories, mut size) = (0.0, 0.0, 0.0);
+        let probes = budget.probes.max(1);
+        for _ in 0..probes {
+            let mut dir = root.to_path_buf();
+            let mut weight = 1.0;
+            for _ in 0..MAX_PROBE_DEPTH {
+                let listing = preflight.list(&dir);
+                directories += weight;
+                files += weight * listing.files as f64;
+                size += weight * listing.bytes as f64;
+                if listing.subdirs.is_empty() {
+                    break;
+                }
+                weight *= listing.subdirs.len() as f64;
+                dir = listing.subdirs[random.below(listing.subdirs.len())].clone();
+            }
+        }
+        let mean = |total: f64| (total / probes as f64).round() as u64;
+        (mean(files), mean(directories), mean(size))
+    };
+
+    // A scan does about as much per entry as the preflight did, spread over its threads
+    let per_entry = started.elapsed().as_secs_f64() / preflight.entries_read.max(1) as f64;
+    let threads = threads.max(1);
+    PreflightReport {
+        path: root.to_path_buf(),
+        exact,
+        directories_listed: preflight.listings.len() as u64,
+        files,
+        directories,
+        size,
+        estimated_seconds: (files + directories) as f64 * per_entry / threads as f64,
+        threads,
+        unreadable: preflight.unreadable.into_values().collect(),
+    }
+}
+
+/// Whether this user may list and enter `dir`, asked without opening it
+#[cfg(target_os = "linux")]
+fn can_enter(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return true;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the whole call
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::X_OK) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn can_enter(_dir: &Path) -> bool {
+    true
+}
+
+/// Small, seeded generator for choosing subdirectories, so the same tree
+/// gives the same estimate
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// `levels` levels of `width` subdirectories, each directory holding two 10-byte files
+    fn uniform_tree(root: &Path, levels: usize, width: usize) {
+        std::fs::write(root.join("a"), [0u8; 10]).unwrap();
+        std::fs::write(root.join("b"), [0u8; 10]).unwrap();
+        if levels == 0 {
+            return;
+        }
+        for index in 0..width {
+            let child = root.join(format!("d{}", index));
+            std::fs::create_dir(&child).unwrap();
+            uniform_tree(&child, levels - 1, width);
+        }
+    }
+
+    #[test]
+    fn test_small_trees_are_counted_exactly() {
+        let dir = TempDir::new().unwrap();
+        uniform_tree(dir.path(), 2, 3);
+        let report = preflight(dir.path(), Budget::default(), 4);
+        assert!(report.exact);
+        assert_eq!((report.directories, report.files, report.size), (13, 26, 260));
+        assert_eq!(report.directories_listed, 13);
+        assert!(report.unreadable.is_empty());
+    }
+
+    #[test]
+    fn test_sampled_estimate() {
+        let dir = TempDir::new().unwrap();
+        uniform_tree(dir.path(), 3, 3);
+        let budget = Budget {
+            exact_directories: 2,
+            probes: 8,
+        };
+        let report = preflight(dir.path(), budget, 1);
+        // Every descent of a uniform tree estimates it exactly
+        assert!(!report.exact);
+        assert_eq!((report.directories, report.files, report.size), (40, 80, 800));
+        assert!(report.directories_listed < 40);
+    }
+
+    #[test]
+    fn test_reports_unreadable_directories() {
+        let dir = TempDir::new().unwrap();
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Root can read anything, so the problem only shows up for other users
+        let readable = std::fs::read_dir(&locked).is_ok();
+        let report = preflight(dir.path(), Budget::default(), 1);
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        if readable {
+            assert!(report.unreadable.is_empty());
+        } else {
+            assert_eq!(report.unreadable.len(), 1);
+            assert_eq!(report.unreadable[0].path, locked);
+            assert_eq!(report.unreadable[0].kind, ScanErrorKind::PermissionDenied);
+        }
+    }
+}
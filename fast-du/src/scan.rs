@@ -0,0 +1,874 @@
+use crate::extents::{self, Extent, ExtentReport};
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorKind {
+    PermissionDenied,
+    NotFound,
+    DirectoryCycle,
+    Other,
+}
+
+impl ScanErrorKind {
+    fn from_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => ScanErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound => ScanErrorKind::NotFound,
+            _ => ScanErrorKind::Other,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ScanErrorKind::PermissionDenied => "permission denied",
+            ScanErrorKind::NotFound => "not found",
+            ScanErrorKind::DirectoryCycle => "directory cycle",
+            ScanErrorKind::Other => "other",
+        }
+    }
+}
+
+/// A path that could not be measured; the scan records it and carries on
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub kind: ScanErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn io(path: &Path, action: &str, error: &io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: ScanErrorKind::from_io(error),
+            message: format!("{}: {}", action, error),
+        }
+    }
+
+    fn cycle(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: ScanErrorKind::DirectoryCycle,
+            message: "skipping directory already visited through a symlink".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}': {}", self.path.display(), self.message)
+    }
+}
+
+/// Files at least this long whose allocation is under half their length are sparse
+const SPARSE_MIN_LEN: u64 = 4096;
+
+pub struct ScanOptions {
+    /// Deepest level, below the root at 0, whose directories get their own
+    /// totals in `ScanResult::directories`; everything is counted regardless
+    pub report_depth: usize,
+    pub dereference: bool,
+    /// Count every hard link to a file instead of only the first one seen
+    pub count_links: bool,
+    pub filter: FileFilter,
+    /// Set for `--skip-caches`
+    pub caches: Option<CachePolicy>,
+    /// Map the extents of every file for `--extents`
+    pub extents: bool,
+}
+
+/// The signature a CACHEDIR.TAG file must start with, per the Cache
+/// Directory Tagging Specification
+const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Which directories `--skip-caches` prunes: those tagged with CACHEDIR.TAG,
+/// the user's cache directory and any extra names asked for
+#[derive(Debug, Default)]
+pub struct CachePolicy {
+    /// Directory names that are always caches, e.g. node_modules
+    pub names: Vec<String>,
+    /// (device, inode) of known cache directories such as ~/.cache
+    pub known_dirs: HashSet<(u64, u64)>,
+}
+
+impl CachePolicy {
+    /// Treat `names` as caches along with `$XDG_CACHE_HOME` or `~/.cache`
+    pub fn new(names: Vec<String>) -> Self {
+        let user_cache = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+        let known_dirs = user_cache
+            .into_iter()
+            .filter(|_| cfg!(unix))
+            .filter_map(|dir| std::fs::metadata(dir).ok())
+            .filter(|metadata| metadata.is_dir())
+            .map(|metadata| dir_identity(&metadata))
+            .collect();
+        Self { names, known_dirs }
+    }
+
+    fn is_cache(&self, dir: &Path, metadata: &Metadata) -> bool {
+        let named = dir
+            .file_name()
+            .is_some_and(|name| self.names.iter().any(|cache| name == cache.as_str()));
+        named || self.known_dirs.contains(&dir_identity(metadata)) || has_cachedir_tag(dir)
+    }
+}
+
+/// Whether `dir` holds a CACHEDIR.TAG with the right signature
+pub fn has_cachedir_tag(dir: &Path) -> bool {
+    use std::io::Read;
+
+    let mut signature = [0u8; CACHEDIR_TAG_SIGNATURE.len()];
+    std::fs::File::open(dir.join("CACHEDIR.TAG"))
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|_| signature == CACHEDIR_TAG_SIGNATURE)
+}
+
+/// Which files count towards the totals; directories are always walked
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    /// Only files modified at or after this time
+    pub modified_after: Option<SystemTime>,
+    /// Only files modified before this time
+    pub modified_before: Option<SystemTime>,
+    /// Lowercase extensions without the dot; empty means any
+    pub include_ext: Vec<String>,
+    pub exclude_ext: Vec<String>,
+}
+
+impl FileFilter {
+    /// Normalize extensions given as "log", ".LOG" or "tar.gz"
+    pub fn extensions(values: &[String]) -> Vec<String> {
+        values
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect()
+    }
+
+    fn matches_name(&self, path: &Path) -> bool {
+        if self.include_ext.is_empty() && self.exclude_ext.is_empty() {
+            return true;
+        }
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        // Suffix match, so "gz" and "tar.gz" both work
+        let has = |ext: &String| {
+            name.len() > ext.len() + 1
+                && name.ends_with(ext.as_str())
+                && name.as_bytes()[name.len() - ext.len() - 1] == b'.'
+        };
+        (self.include_ext.is_empty() || self.include_ext.iter().any(has))
+            && !self.exclude_ext.iter().any(has)
+    }
+
+    fn matches_time(&self, metadata: &Metadata) -> bool {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let Ok(modified) = metadata.modified() else {
+            return false;
+        };
+        self.modified_after.is_none_or(|after| modified >= after)
+            && self.modified_before.is_none_or(|before| modified < before)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanResult {
+    pub path: PathBuf,
+    /// Apparent size: the sum of file lengths
+    pub size: u64,
+    /// Bytes actually allocated on disk
+    pub physical_size: u64,
+    pub entries: u64,
+    /// Regular files in the totals, unlike `entries` leaving out
+    /// directories and unfollowed symlinks
+    pub files: u64,
+    pub sparse_files: u64,
+    /// Extra links to already counted files; skipped unless `--count-links`
+    pub duplicate_links: u64,
+    /// Files left out of the totals by age or extension filters
+    pub filtered_files: u64,
+    /// Cache directories pruned by `--skip-caches`
+    pub cache_dirs: u64,
+    /// Apparent size of what the pruned cache directories hold
+    pub cache_size: u64,
+    /// Totals of the root and its subdirectories down to `report_depth`,
+    /// children before their parent as du prints them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<DirectoryTotal>,
+    /// Shared and compressed storage, with `--extents`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extents: Option<ExtentReport>,
+    pub errors: Vec<ScanError>,
+}
+
+/// What one directory holds, subdirectories included
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryTotal {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub size: u64,
+    pub physical_size: u64,
+}
+
+/// Post-order listing of `directories`, whose parents come before their
+/// children; sizes are still each directory's own and get rolled up here
+fn roll_up(mut directories: Vec<DirectoryTotal>, parents: &[Option<usize>]) -> Vec<DirectoryTotal> {
+    let mut children = vec![Vec::new(); directories.len()];
+    for index in (0..directories.len()).rev() {
+        if let Some(parent) = parents[index] {
+            directories[parent].size += directories[index].size;
+            directories[parent].physical_size += directories[index].physical_size;
+            children[parent].push(index);
+        }
+    }
+
+    let mut order = Vec::with_capacity(directories.len());
+    // (directory, children already queued)
+    let mut stack: Vec<(usize, bool)> = (0..directories.len())
+        .filter(|&index| parents[index].is_none())
+        .rev()
+        .map(|index| (index, false))
+        .collect();
+    while let Some((index, expanded)) = stack.pop() {
+        if expanded {
+            order.push(index);
+        } else {
+            stack.push((index, true));
+            // `children` was filled last-first, which the stack reverses back
+            stack.extend(children[index].iter().map(|&child| (child, false)));
+        }
+    }
+
+    let mut directories: Vec<Option<DirectoryTotal>> = directories.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|index| directories[index].take()).collect()
+}
+
+/// Sizes of one measured entry
+struct Measurement {
+    size: u64,
+    physical_size: u64,
+    /// (device, inode) for files with more than one hard link
+    link_identity: Option<(u64, u64)>,
+    sparse: bool,
+    /// A regular file rather than a directory or symlink
+    regular: bool,
+    /// Rejected by the age filter; contributes nothing
+    filtered: bool,
+    /// Device and extents of a regular file, with `--extents`
+    extents: Option<(u64, io::Result<Vec<Extent>>)>,
+}
+
+impl Measurement {
+    fn empty(filtered: bool) -> Self {
+        Self {
+            size: 0,
+            physical_size: 0,
+            link_identity: None,
+            sparse: false,
+            regular: false,
+            filtered,
+            extents: None,
+        }
+    }
+}
+
+/// An entry found while walking, stat'ed later in parallel
+struct PendingEntry {
+    path: PathBuf,
+    follow: bool,
+    /// Inside a pruned cache directory: measured for the report only
+    cache: bool,
+    /// The reported directory whose total this entry adds to
+    dir: Option<usize>,
+}
+
+/// Measure `root`, collecting per-path errors instead of aborting on the first one
+pub fn scan(root: &Path, options: &ScanOptions, progress: &ProgressBar) -> ScanResult {
+    progress.set_message(format!("Scanning {}", root.display()));
+
+    let mut walker = Walker {
+        options,
+        pending: Vec::new(),
+        errors: Vec::new(),
+        visited: HashSet::new(),
+        filtered: 0,
+        in_cache: false,
+        cache_dirs: 0,
+        directories: Vec::new(),
+        parents: Vec::new(),
+        current_dir: None,
+    };
+    walker.walk_root(root);
+    let Walker {
+        pending,
+        mut errors,
+        filtered,
+        cache_dirs,
+        mut directories,
+        parents,
+        ..
+    } = walker;
+
+    let processed_files = AtomicU64::new(0);
+    let measured: Vec<Result<Measurement, ScanError>> = pending
+        .par_iter()
+        .map(|entry| {
+            let size = entry_size(entry, options);
+            let processed = processed_files.fetch_add(1, Ordering::Relaxed);
+            if processed.is_multiple_of(1000) {
+                progress.set_message(format!("Processed {} files in {}", processed, root.display()));
+            }
+            size
+        })
+        .collect();
+
+    let mut result = ScanResult {
+        path: root.to_path_buf(),
+        size: 0,
+        physical_size: 0,
+        entries: pending.iter().filter(|entry| !entry.cache).count() as u64,
+        files: 0,
+        sparse_files: 0,
+        duplicate_links: 0,
+        filtered_files: filtered,
+        cache_dirs,
+        cache_size: 0,
+        directories: Vec::new(),
+        extents: options.extents.then(ExtentReport::default),
+        errors: Vec::new(),
+    };
+    let mut seen_links = HashSet::new();
+    for (entry, measurement) in pending.iter().zip(measured) {
+        let measurement = match measurement {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+
+        if measurement.filtered {
+            result.filtered_files += 1;
+            continue;
+        }
+        if let Some(identity) = measurement.link_identity {
+            if !seen_links.insert(identity) {
+                result.duplicate_links += 1;
+                if !options.count_links {
+                    continue;
+                }
+            }
+        }
+        if entry.cache {
+            result.cache_size += measurement.size;
+            continue;
+        }
+        result.size += measurement.size;
+        result.physical_size += measurement.physical_size;
+        if let Some(dir) = entry.dir {
+            directories[dir].size += measurement.size;
+            directories[dir].physical_size += measurement.physical_size;
+        }
+        if measurement.regular {
+            result.files += 1;
+        }
+        if measurement.sparse {
+            result.sparse_files += 1;
+        }
+        if let (Some(report), Some((device, extents))) = (result.extents.as_mut(), measurement.extents) {
+            match extents {
+                Ok(extents) => report.add(&entry.path, device, &extents),
+                Err(e) if e.kind() == io::ErrorKind::Unsupported => report.unsupported_files += 1,
+                Err(e) => errors.push(ScanError::io(&entry.path, "cannot map extents", &e)),
+            }
+        }
+    }
+
+    result.directories = roll_up(directories, &parents);
+    result.errors = errors;
+    result
+}
+
+/// Apparent and allocated size of a regular file, or of the link itself for
+/// an unfollowed symlink
+fn entry_size(entry: &PendingEntry, options: &ScanOptions) -> Result<Measurement, ScanError> {
+    let metadata = if entry.follow {
+        std::fs::metadata(&entry.path)
+    } else {
+        std::fs::symlink_metadata(&entry.path)
+    }
+    .map_err(|e| ScanError::io(&entry.path, "cannot access", &e))?;
+
+    if !metadata.is_file() && !metadata.file_type().is_symlink() {
+        return Ok(Measurement::empty(false));
+    }
+    if !options.filter.matches_time(&metadata) {
+        return Ok(Measurement::empty(true));
+    }
+
+    let size = metadata.len();
+    let physical_size = allocated_size(&metadata);
+    Ok(Measurement {
+        size,
+        physical_size,
+        link_identity: link_identity(&metadata),
+        sparse: metadata.is_file() && size >= SPARSE_MIN_LEN && physical_size < size / 2,
+        regular: metadata.is_file(),
+        filtered: false,
+        extents: (options.extents && metadata.is_file())
+            .then(|| (dir_identity(&metadata).0, extents::file_extents(&entry.path))),
+    })
+}
+
+struct Walker<'a> {
+    options: &'a ScanOptions,
+    pending: Vec<PendingEntry>,
+    errors: Vec<ScanError>,
+    /// (device, inode) of directories entered, to break symlink cycles under `-L`
+    visited: HashSet<(u64, u64)>,
+    /// Files skipped by the extension filter
+    filtered: u64,
+    /// Walking the inside of a pruned cache directory
+    in_cache: bool,
+    cache_dirs: u64,
+    /// Directories within the report depth, parents first, holding only
+    /// their own entries until the scan rolls them up
+    directories: Vec<DirectoryTotal>,
+    parents: Vec<Option<usize>>,
+    /// The reported directory being walked; deeper ones add to it
+    current_dir: Option<usize>,
+}
+
+impl Walker<'_> {
+    fn walk_root(&mut self, root: &Path) {
+        let metadata = match std::fs::symlink_metadata(root) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.errors.push(ScanError::io(root, "cannot access", &e));
+                return;
+            }
+        };
+
+        if metadata.file_type().is_symlink() && self.options.dereference {
+            match std::fs::metadata(root) {
+                Ok(target) if target.is_dir() => self.enter_dir(root, &target, 0),
+                _ => self.push_file(root.to_path_buf(), true),
+            }
+        } else if metadata.is_dir() {
+            self.enter_dir(root, &metadata, 0);
+        } else {
+            self.push_file(root.to_path_buf(), false);
+        }
+    }
+
+    /// Queue a non-directory entry for measuring unless its name is filtered out
+    fn push_file(&mut self, path: PathBuf, follow: bool) {
+        if self.options.filter.matches_name(&path) {
+            let (cache, dir) = (self.in_cache, self.current_dir);
+            self.pending.push(PendingEntry { path, follow, cache, dir });
+        } else {
+            self.filtered += 1;
+        }
+    }
+
+    fn enter_dir(&mut self, dir: &Path, metadata: &Metadata, depth: usize) {
+        if self.options.dereference && !self.visited.insert(dir_identity(metadata)) {
+            self.errors.push(ScanError::cycle(dir));
+            return;
+        }
+
+        // Cache directories are still walked, so the report can say how
+        // much they hold, but nothing in them counts; a root is never pruned
+        let prune = !self.in_cache
+            && depth > 0
+            && self.options.caches.as_ref().is_some_and(|caches| caches.is_cache(dir, metadata));
+        if prune {
+            self.cache_dirs += 1;
+            self.in_cache = true;
+        }
+        let parent = self.current_dir;
+        if depth <= self.options.report_depth {
+            self.current_dir = Some(self.directories.len());
+            self.directories.push(DirectoryTotal {
+                path: dir.to_path_buf(),
+                depth,
+                size: 0,
+                physical_size: 0,
+            });
+            self.parents.push(parent);
+        }
+        self.walk_dir(dir, depth);
+        self.current_dir = parent;
+        if prune {
+            self.in_cache = false;
+        }
+    }
+
+    fn walk_dir(&mut self, dir: &Path, depth: usize) {
+        let dir_entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.errors.push(ScanError::io(dir, "cannot read directory", &e));
+                return;
+            }
+        };
+
+        for entry in dir_entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.errors.push(ScanError::io(dir, "cannot read directory entry", &e));
+                    continue;
+                }
+            };
+            let entry_path = entry.path();
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    self.errors.push(ScanError::io(&entry_path, "cannot access", &e));
+                    continue;
+                }
+            };
+
+            let follow = file_type.is_symlink() && self.options.dereference;
+            let mut is_dir = file_type.is_dir();
+            if is_dir {
+                match entry.metadata() {
+                    Ok(metadata) => self.enter_dir(&entry_path, &metadata, depth + 1),
+                    Err(e) => {
+                        self.errors.push(ScanError::io(&entry_path, "cannot access", &e));
+                        continue;
+                    }
+                }
+            } else if follow {
+                // Broken links are reported when the entry is measured
+                if let Ok(target) = std::fs::metadata(&entry_path) {
+                    if target.is_dir() {
+                        is_dir = true;
+                        self.enter_dir(&entry_path, &target, depth + 1);
+                    }
+                }
+            }
+
+            if is_dir {
+                self.pending.push(PendingEntry {
+                    path: entry_path,
+                    follow,
+                    cache: self.in_cache,
+                    dir: self.current_dir,
+                });
+            } else {
+                self.push_file(entry_path, follow);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn dir_identity(metadata: &Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn dir_identity(_metadata: &Metadata) -> (u64, u64) {
+    // No stable identity available; treat every directory as new
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    (0, NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+#[cfg(unix)]
+fn link_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.is_file() && metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn link_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// `st_blocks` is always in 512-byte units, whatever the filesystem block size
+#[cfg(unix)]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use tempfile::TempDir;
+
+    fn scan_dir(root: &Path, dereference: bool) -> ScanResult {
+        scan_with(root, dereference, false)
+    }
+
+    fn scan_with(root: &Path, dereference: bool, count_links: bool) -> ScanResult {
+        scan_filtered(root, dereference, count_links, FileFilter::default())
+    }
+
+    fn scan_filtered(root: &Path, dereference: bool, count_links: bool, filter: FileFilter) -> ScanResult {
+        let options = ScanOptions {
+            report_depth: 0,
+            dereference,
+            count_links,
+            filter,
+            caches: None,
+            extents: false,
+        };
+        scan(root, &options, &ProgressBar::hidden())
+    }
+
+    #[test]
+    fn test_unreadable_directory_does_not_abort_scan() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"12345").unwrap();
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::write(locked.join("hidden.txt"), b"123").unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Root can read anything, so the error only shows up for other users
+        let readable = std::fs::read_dir(&locked).is_ok();
+        let result = scan_dir(dir.path(), false);
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        if readable {
+            assert_eq!(result.size, 8);
+            assert!(result.errors.is_empty());
+        } else {
+            assert_eq!(result.size, 5);
+            assert_eq!(result.errors.len(), 1);
+            assert_eq!(result.errors[0].kind, ScanErrorKind::PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn test_files_counted_apart_from_directories() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("x/y")).unwrap();
+        for file in ["a", "x/b", "x/y/c"] {
+            std::fs::write(dir.path().join(file), b"1").unwrap();
+        }
+        symlink("a", dir.path().join("link")).unwrap();
+
+        let result = scan_dir(dir.path(), false);
+        assert_eq!((result.entries, result.files), (6, 3));
+    }
+
+    #[test]
+    fn test_symlinks_counted_or_followed() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("data"), vec![0u8; 100]).unwrap();
+        let tree = dir.path().join("tree");
+        std::fs::create_dir(&tree).unwrap();
+        symlink(&target, tree.join("link")).unwrap();
+
+        let physical = scan_dir(&tree, false);
+        let link_len = std::fs::symlink_metadata(tree.join("link")).unwrap().len();
+        assert_eq!(physical.size, link_len);
+
+        let logical = scan_dir(&tree, true);
+        assert_eq!(logical.size, 100);
+        assert!(logical.errors.is_empty());
+    }
+
+    #[test]
+    fn test_symlink_cycle_and_broken_link_are_reported() {
+        let dir = TempDir::new().unwrap();
+        symlink(dir.path(), dir.path().join("loop")).unwrap();
+        symlink(dir.path().join("missing"), dir.path().join("broken")).unwrap();
+
+        let result = scan_dir(dir.path(), true);
+        let mut kinds: Vec<_> = result.errors.iter().map(|e| e.kind).collect();
+        kinds.sort_by_key(|kind| kind.description());
+        assert_eq!(kinds, vec![ScanErrorKind::DirectoryCycle, ScanErrorKind::NotFound]);
+    }
+
+    #[test]
+    fn test_missing_root_is_an_error_not_a_panic() {
+        let dir = TempDir::new().unwrap();
+        let result = scan_dir(&dir.path().join("nope"), false);
+        assert_eq!(result.size, 0);
+        assert_eq!(result.errors[0].kind, ScanErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_hard_links_counted_once_unless_requested() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a"), vec![1u8; 100]).unwrap();
+        std::fs::hard_link(dir.path().join("a"), dir.path().join("b")).unwrap();
+
+        let result = scan_dir(dir.path(), false);
+        assert_eq!(result.size, 100);
+        assert_eq!(result.duplicate_links, 1);
+
+        let result = scan_with(dir.path(), false, true);
+        assert_eq!(result.size, 200);
+        assert_eq!(result.duplicate_links, 1);
+    }
+
+    #[test]
+    fn test_sparse_file_detected() {
+        let dir = TempDir::new().unwrap();
+        let file = std::fs::File::create(dir.path().join("sparse")).unwrap();
+        file.set_len(1024 * 1024).unwrap();
+
+        let result = scan_dir(dir.path(), false);
+        assert_eq!(result.size, 1024 * 1024);
+        assert!(result.physical_size < result.size);
+        assert_eq!(result.sparse_files, 1);
+    }
+
+    #[test]
+    fn test_age_and_extension_filters() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("app.log"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir.path().join("app.LOG.gz"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), vec![0u8; 1]).unwrap();
+
+        let only = |include: &[&str], exclude: &[&str]| {
+            let filter = FileFilter {
+                include_ext: FileFilter::extensions(&include.iter().map(|e| e.to_string()).collect::<Vec<_>>()),
+                exclude_ext: FileFilter::extensions(&exclude.iter().map(|e| e.to_string()).collect::<Vec<_>>()),
+                ..FileFilter::default()
+            };
+            scan_filtered(dir.path(), false, false, filter)
+        };
+        assert_eq!(only(&["log"], &[]).size, 100);
+        assert_eq!(only(&[".log", "GZ"], &[]).size, 110);
+        let result = only(&[], &["log.gz"]);
+        assert_eq!((result.size, result.filtered_files), (101, 1));
+
+        let hour = std::time::Duration::from_secs(3_600);
+        let old = FileFilter {
+            modified_before: Some(SystemTime::now() - hour),
+            ..FileFilter::default()
+        };
+        let result = scan_filtered(dir.path(), false, false, old);
+        assert_eq!((result.size, result.filtered_files), (0, 3));
+
+        let recent = FileFilter {
+            modified_after: Some(SystemTime::now() - hour),
+            ..FileFilter::default()
+        };
+        assert_eq!(scan_filtered(dir.path(), false, false, recent).size, 111);
+    }
+
+    #[test]
+    fn test_directory_totals() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/deep")).unwrap();
+        std::fs::create_dir(dir.path().join("b")).unwrap();
+        std::fs::write(dir.path().join("top"), vec![0u8; 1]).unwrap();
+        std::fs::write(dir.path().join("a/file"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("a/deep/file"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir.path().join("b/file"), vec![0u8; 1000]).unwrap();
+
+        let scan_depth = |report_depth| {
+            let options = ScanOptions {
+                report_depth,
+                dereference: false,
+                count_links: false,
+                filter: FileFilter::default(),
+                caches: None,
+                extents: false,
+            };
+            scan(dir.path(), &options, &ProgressBar::hidden())
+        };
+        let listing = |result: &ScanResult| {
+            result
+                .directories
+                .iter()
+                .map(|directory| {
+                    let path = directory.path.strip_prefix(dir.path()).unwrap().display().to_string();
+                    (path, directory.size)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let result = scan_depth(1);
+        assert_eq!(result.size, 1111);
+        let mut shallow = listing(&result);
+        assert_eq!(shallow.pop(), Some((String::new(), 1111)));
+        shallow.sort();
+        assert_eq!(shallow, vec![("a".to_string(), 110), ("b".to_string(), 1000)]);
+
+        let deep = listing(&scan_depth(usize::MAX));
+        let position = |path: &str| deep.iter().position(|(p, _)| p == path).unwrap();
+        assert!(position("a/deep") < position("a"));
+        assert_eq!(deep[position("a/deep")].1, 100);
+        assert_eq!(listing(&scan_depth(0)), vec![(String::new(), 1111)]);
+    }
+
+    #[test]
+    fn test_skip_caches() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("keep"), vec![0u8; 10]).unwrap();
+        let tagged = dir.path().join("build");
+        std::fs::create_dir(&tagged).unwrap();
+        let mut tag = CACHEDIR_TAG_SIGNATURE.to_vec();
+        tag.extend_from_slice(b"\n# made by a build tool\n");
+        std::fs::write(tagged.join("CACHEDIR.TAG"), &tag).unwrap();
+        std::fs::write(tagged.join("object"), vec![0u8; 1000]).unwrap();
+        let modules = dir.path().join("web/node_modules");
+        std::fs::create_dir_all(modules.join("left-pad")).unwrap();
+        std::fs::write(modules.join("left-pad/index.js"), vec![0u8; 200]).unwrap();
+        // A tag without the signature does not count
+        let fake = dir.path().join("fake");
+        std::fs::create_dir(&fake).unwrap();
+        std::fs::write(fake.join("CACHEDIR.TAG"), b"not a cache").unwrap();
+
+        let scan_caches = |root: &Path, names: &[&str]| {
+            let options = ScanOptions {
+                report_depth: 0,
+                dereference: false,
+                count_links: false,
+                filter: FileFilter::default(),
+                caches: Some(CachePolicy {
+                    names: names.iter().map(|name| name.to_string()).collect(),
+                    known_dirs: HashSet::new(),
+                }),
+                extents: false,
+            };
+            scan(root, &options, &ProgressBar::hidden())
+        };
+
+        let result = scan_caches(dir.path(), &[]);
+        assert_eq!((result.cache_dirs, result.cache_size), (1, 1000 + tag.len() as u64));
+        assert_eq!(result.size, 10 + 200 + 11);
+
+        let result = scan_caches(dir.path(), &["node_modules"]);
+        assert_eq!((result.cache_dirs, result.cache_size), (2, 1200 + tag.len() as u64));
+        assert_eq!(result.size, 21);
+
+        // Asking for a cache directory itself measures it
+        assert_eq!(scan_caches(&tagged, &[]).cache_dirs, 0);
+        assert_eq!(scan_dir(dir.path(), false).cache_dirs, 0);
+    }
+}
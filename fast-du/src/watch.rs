@@ -0,0 +1,149 @@
+use crate::scan::{self, ScanOptions, ScanResult};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// A directory that got bigger between two scans
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Growth {
+    pub path: PathBuf,
+    pub previous_size: u64,
+    pub size: u64,
+}
+
+impl Growth {
+    pub fn grown_by(&self) -> u64 {
+        self.size - self.previous_size
+    }
+}
+
+/// Directories of `after` that grew by more than `threshold` bytes since
+/// `before`, largest growth first.
+///
+/// Only the most specific directories are reported: a directory is left out
+/// when one below it already crossed the threshold, so a runaway log
+/// directory is named once rather than along with every ancestor.
+pub fn growth(before: &ScanResult, after: &ScanResult, threshold: u64) -> Vec<Growth> {
+    let previous: HashMap<&Path, u64> = before
+        .directories
+        .iter()
+        .map(|directory| (directory.path.as_path(), directory.size))
+        .collect();
+
+    let grown: Vec<Growth> = after
+        .directories
+        .iter()
+        .filter_map(|directory| {
+            let previous_size = previous.get(directory.path.as_path()).copied().unwrap_or(0);
+            (directory.size > previous_size.saturating_add(threshold)).then(|| Growth {
+                path: directory.path.clone(),
+                previous_size,
+                size: directory.size,
+            })
+        })
+        .collect();
+
+    let mut specific: Vec<Growth> = grown
+        .iter()
+        .filter(|candidate| {
+            !grown
+                .iter()
+                .any(|other| other.path != candidate.path && other.path.starts_with(&candidate.path))
+        })
+        .cloned()
+        .collect();
+    specific.sort_by(|a, b| b.grown_by().cmp(&a.grown_by()).then_with(|| a.path.cmp(&b.path)));
+    specific
+}
+
+/// Rescan `paths` every `interval`, handing each round's growth against the
+/// previous scan to `report`; runs until the process is interrupted
+pub fn watch(
+    paths: &[PathBuf],
+    mut previous: Vec<ScanResult>,
+    options: &ScanOptions,
+    interval: Duration,
+    threshold: u64,
+    mut report: impl FnMut(&[Growth]),
+) -> ! {
+    let progress = ProgressBar::hidden();
+    loop {
+        thread::sleep(interval);
+        let current: Vec<ScanResult> = paths.iter().map(|path| scan::scan(path, options, &progress)).collect();
+        let grown: Vec<Growth> = previous
+            .iter()
+            .zip(&current)
+            .flat_map(|(before, after)| growth(before, after, threshold))
+            .collect();
+        if !grown.is_empty() {
+            report(&grown);
+        }
+        previous = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::DirectoryTotal;
+
+    fn result(directories: &[(&str, u64)]) -> ScanResult {
+        ScanResult {
+            path: "root".into(),
+            size: directories.last().map_or(0, |(_, size)| *size),
+            physical_size: 0,
+            entries: 0,
+            files: 0,
+            sparse_files: 0,
+            duplicate_links: 0,
+            filtered_files: 0,
+            cache_dirs: 0,
+            cache_size: 0,
+            directories: directories
+                .iter()
+                .map(|&(path, size)| DirectoryTotal {
+                    path: path.into(),
+                    depth: path.matches('/').count(),
+                    size,
+                    physical_size: size,
+                })
+                .collect(),
+            extents: None,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_growth() {
+        let before = result(&[("root/logs/app", 100), ("root/logs", 150), ("root/tmp", 10), ("root", 200)]);
+        let after = result(&[
+            ("root/logs/app", 5_100),
+            ("root/logs", 5_150),
+            ("root/tmp", 2_000),
+            ("root/new", 3_000),
+            ("root", 10_200),
+        ]);
+
+        let paths = |threshold| {
+            growth(&before, &after, threshold)
+                .iter()
+                .map(|growth| (growth.path.display().to_string(), growth.grown_by()))
+                .collect::<Vec<_>>()
+        };
+        // root/logs and root only grew because of root/logs/app
+        assert_eq!(
+            paths(1_000),
+            vec![
+                ("root/logs/app".to_string(), 5_000),
+                ("root/new".to_string(), 3_000),
+                ("root/tmp".to_string(), 1_990),
+            ]
+        );
+        assert_eq!(paths(6_000), vec![("root".to_string(), 10_000)]);
+        assert!(paths(20_000).is_empty());
+        assert!(growth(&after, &before, 0).is_empty());
+    }
+}
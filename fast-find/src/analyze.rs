@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::output::{format_size, FileInfo};
+
+/// Upper bounds of the size histogram buckets after the first, which holds
+/// empty files; the last bucket has no bound
+const BUCKET_LIMITS: &[u64] = &[1 << 10, 16 << 10, 256 << 10, 4 << 20, 64 << 20, 1 << 30];
+
+/// How many extensions `--analyze` lists, most common first
+const TOP_EXTENSIONS: usize = 10;
+
+/// Widest histogram bar, for the fullest bucket
+const BAR_WIDTH: usize = 30;
+
+/// `--analyze`: statistics over the matched entries in place of a listing.
+/// Sizes, the histogram and extensions cover regular files only.
+#[derive(Debug, Serialize)]
+pub struct Analysis {
+    pub count: usize,
+    pub files: usize,
+    pub directories: usize,
+    pub total_size: u64,
+    pub median_size: Option<u64>,
+    pub histogram: Vec<SizeBucket>,
+    pub extensions: Vec<ExtensionCount>,
+    pub oldest: Option<Dated>,
+    pub newest: Option<Dated>,
+}
+
+/// Files of at least `min` and less than `max` bytes
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct SizeBucket {
+    pub min: u64,
+    pub max: Option<u64>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ExtensionCount {
+    /// Lowercased, without the dot; empty for files without one
+    pub extension: String,
+    pub count: usize,
+    pub size: u64,
+}
+
+/// An entry with its modification time, as `--json` prints it
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Dated {
+    pub path: String,
+    pub modified: String,
+}
+
+impl Analysis {
+    pub fn of(entries: &[FileInfo]) -> Self {
+        let mut sizes = Vec::new();
+        let mins = [0, 1].into_iter().chain(BUCKET_LIMITS.iter().copied());
+        let maxes = std::iter::once(1).chain(BUCKET_LIMITS.iter().copied()).map(Some).chain([None]);
+        let mut histogram: Vec<SizeBucket> = mins
+            .zip(maxes)
+            .map(|(min, max)| SizeBucket { min, max, count: 0 })
+            .collect();
+        let mut extensions: HashMap<String, (usize, u64)> = HashMap::new();
+        let mut directories = 0;
+        let (mut oldest, mut newest): (Option<&FileInfo>, Option<&FileInfo>) = (None, None);
+
+        for entry in entries {
+            if entry.file_type == "directory" {
+                directories += 1;
+            }
+            if let Some(size) = entry.size {
+                sizes.push(size);
+                let bucket = histogram
+                    .iter_mut()
+                    .find(|bucket| bucket.max.is_none_or(|max| size < max))
+                    .expect("the last bucket is unbounded");
+                bucket.count += 1;
+                let extension = Path::new(&entry.path)
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let totals = extensions.entry(extension).or_default();
+                totals.0 += 1;
+                totals.1 += size;
+            }
+            // ISO 8601 times in UTC sort as text
+            if let Some(ref modified) = entry.modified {
+                if oldest.is_none_or(|oldest| Some(modified) < oldest.modified.as_ref()) {
+                    oldest = Some(entry);
+                }
+                if newest.is_none_or(|newest| Some(modified) > newest.modified.as_ref()) {
+                    newest = Some(entry);
+                }
+            }
+        }
+
+        sizes.sort_unstable();
+        let mut extensions: Vec<ExtensionCount> = extensions
+            .into_iter()
+            .map(|(extension, (count, size))| ExtensionCount { extension, count, size })
+            .collect();
+        extensions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.extension.cmp(&b.extension)));
+        extensions.truncate(TOP_EXTENSIONS);
+        let dated = |entry: &FileInfo| Dated {
+            path: entry.path.clone(),
+            modified: entry.modified.clone().unwrap_or_default(),
+        };
+
+        Self {
+            count: entries.len(),
+            files: sizes.len(),
+            directories,
+            total_size: sizes.iter().sum(),
+            median_size: median(&sizes),
+            histogram,
+            extensions,
+            oldest: oldest.map(dated),
+            newest: newest.map(dated),
+        }
+    }
+
+    /// The report as text, sizes human-readable
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("Entries:      {}", self.count),
+            format!("Files:        {}", self.files),
+            format!("Directories:  {}", self.directories),
+            format!("Total size:   {}", format_size(self.total_size)),
+            format!("Median size:  {}", self.median_size.map_or_else(|| "-".to_string(), format_size)),
+        ];
+
+        if self.files > 0 {
+            lines.push("Size histogram:".to_string());
+            let fullest = self.histogram.iter().map(|bucket| bucket.count).max().unwrap_or(0).max(1);
+            for bucket in &self.histogram {
+                let label = match bucket.max {
+                    Some(1) => "empty".to_string(),
+                    Some(max) => format!("< {}", format_size(max)),
+                    None => format!(">= {}", format_size(bucket.min)),
+                };
+                let bar = "█".repeat(bucket.count.div_ceil(fullest.div_ceil(BAR_WIDTH)).min(BAR_WIDTH));
+                lines.push(format!("  {:<8} {:>8}  {}", label, bucket.count, bar).trim_end().to_string());
+            }
+
+            lines.push("Top extensions:".to_string());
+            let width = self.extensions.iter().map(|extension| extension.extension.len().max(6)).max().unwrap_or(6);
+            for extension in &self.extensions {
+                let name = if extension.extension.is_empty() { "(none)" } else { &extension.extension };
+                lines.push(format!(
+                    "  {:<width$} {:>8}  {}",
+                    name,
+                    extension.count,
+                    format_size(extension.size),
+                    width = width
+                ));
+            }
+        }
+
+        if let (Some(oldest), Some(newest)) = (&self.oldest, &self.newest) {
+            lines.push(format!("Oldest:       {}  {}", oldest.modified, oldest.path));
+            lines.push(format!("Newest:       {}  {}", newest.modified, newest.path));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The middle of `sorted`, or the lower of its two middle values
+fn median(sorted: &[u64]) -> Option<u64> {
+    if sorted.is_empty() {
+        None
+    } else {
+        Some(sorted[(sorted.len() - 1) / 2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: Option<u64>, modified: &str) -> FileInfo {
+        FileInfo {
+            path: path.to_string(),
+            file_type: if size.is_some() { "file" } else { "directory" }.to_string(),
+            size,
+            modified: Some(modified.to_string()),
+            permissions: None,
+            depth: 1,
+        }
+    }
+
+    #[test]
+    fn test_analysis() {
+        let entries = [
+            entry("a.rs", Some(0), "2024-03-01T00:00:00Z"),
+            entry("b.RS", Some(2000), "2021-06-01T00:00:00Z"),
+            entry("c.txt", Some(500), "2025-01-01T00:00:00Z"),
+            entry("Makefile", Some(2 << 30), "2023-01-01T00:00:00Z"),
+            entry("src", None, "2020-01-01T00:00:00Z"),
+        ];
+        let analysis = Analysis::of(&entries);
+        assert_eq!((analysis.count, analysis.files, analysis.directories), (5, 4, 1));
+        assert_eq!(analysis.total_size, 2500 + (2 << 30));
+        assert_eq!(analysis.median_size, Some(500));
+
+        let counts: Vec<usize> = analysis.histogram.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, [1, 1, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(analysis.histogram[0].max, Some(1));
+        assert_eq!(analysis.histogram[7].max, None);
+
+        assert_eq!(
+            analysis.extensions[0],
+            ExtensionCount { extension: "rs".to_string(), count: 2, size: 2000 }
+        );
+        assert_eq!(analysis.extensions.len(), 3);
+        assert_eq!(analysis.oldest.as_ref().unwrap().path, "src");
+        assert_eq!(analysis.newest.as_ref().unwrap().path, "c.txt");
+
+        let text = analysis.render();
+        assert!(text.contains("Files:        4\n"), "{}", text);
+        assert!(text.contains("(none)"));
+
+        assert_eq!(Analysis::of(&[]).median_size, None);
+    }
+}
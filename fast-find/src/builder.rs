@@ -0,0 +1,314 @@
+use anyhow::Result;
+use ignore::WalkState;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::cli::Args;
+use crate::file_walker::{FileWalker, WalkResult};
+use crate::pattern_matcher::PatternMatcher;
+use crate::warnings::{Warning, WarningCollector};
+
+/// An extra test on entries that passed the built-in filters
+pub type Predicate = Arc<dyn Fn(&Entry) -> bool + Send + Sync>;
+
+/// One entry that matched the search
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// 0 for a search path itself
+    pub depth: usize,
+    /// Whether the walk reached the entry through a symlink it did not follow
+    pub is_symlink: bool,
+    /// Of the entry, or of its target for a followed symlink
+    pub metadata: fs::Metadata,
+}
+
+/// Stops a running search from any thread. Entries already handed to the
+/// callback stay reported; the walk ends as soon as its threads notice.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How a search went
+#[derive(Debug, Clone)]
+pub struct SearchSummary {
+    pub matched: usize,
+    /// Entries the walk examined
+    pub visited: usize,
+    /// Whether the cancellation token ended the search early
+    pub cancelled: bool,
+    /// Paths that could not be read, which the search went past
+    pub warnings: Vec<Warning>,
+}
+
+/// ffind's walker and matcher as a library: the same traversal and filters
+/// as the command line, with matches handed to a callback as they are found.
+///
+/// Filters take the command line's syntax (`size("+1M")`, `mtime("-7")`,
+/// `file_type("f")`) and are checked when the search starts, so a bad value
+/// is an error from `for_each`/`collect` rather than a panic.
+#[derive(Clone)]
+pub struct SearchBuilder {
+    args: Args,
+    predicates: Vec<Predicate>,
+    cancellation: CancellationToken,
+}
+
+impl SearchBuilder {
+    /// A search of the current directory that matches everything, skipping
+    /// hidden and ignored files like `ffind` does
+    pub fn new() -> Self {
+        Self {
+            args: Args {
+                paths: Vec::new(),
+                ..Args::default()
+            },
+            predicates: Vec::new(),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Add a search path; without any, the current directory is searched
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.args.paths.push(path.into());
+        self
+    }
+
+    /// Glob (or with `regex`, regular expression) for the file name
+    pub fn name(mut self, pattern: &str) -> Self {
+        self.args.name = Some(pattern.to_string());
+        self
+    }
+
+    /// Like `name`, ignoring case
+    pub fn iname(mut self, pattern: &str) -> Self {
+        self.args.iname = Some(pattern.to_string());
+        self
+    }
+
+    /// Glob (or regular expression) for the whole path
+    pub fn path_pattern(mut self, pattern: &str) -> Self {
+        self.args.path = Some(pattern.to_string());
+        self
+    }
+
+    /// Treat name and path patterns as regular expressions
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.args.use_regex = regex;
+        self
+    }
+
+    /// `f`, `d` and/or `l`, like `--type`
+    pub fn file_type(mut self, types: &str) -> Self {
+        self.args.file_type = Some(types.to_string());
+        self
+    }
+
+    /// Comma-separated extensions to keep, like `--extension`
+    pub fn extensions(mut self, extensions: &str) -> Self {
+        self.args.extensions = Some(extensions.to_string());
+        self
+    }
+
+    /// Size filter such as `+1M` or `-100k`
+    pub fn size(mut self, size: &str) -> Self {
+        self.args.size = Some(size.to_string());
+        self
+    }
+
+    /// Modification age filter in days, such as `-7`
+    pub fn mtime(mut self, days: &str) -> Self {
+        self.args.mtime = Some(days.to_string());
+        self
+    }
+
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.args.min_depth = Some(depth);
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.args.max_depth = Some(depth);
+        self
+    }
+
+    /// Include hidden files and directories
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.args.search_hidden = hidden;
+        self
+    }
+
+    /// Honour .gitignore and .ignore files (the default)
+    pub fn respect_ignore(mut self, respect: bool) -> Self {
+        self.args.respect_ignore = respect;
+        self
+    }
+
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.args.follow_symlinks = follow;
+        self
+    }
+
+    /// Walker threads; all cores by default
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.args.threads = Some(threads);
+        self
+    }
+
+    /// Keep only entries `predicate` accepts, after the built-in filters
+    pub fn filter(mut self, predicate: impl Fn(&Entry) -> bool + Send + Sync + 'static) -> Self {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Stop the search when `token` is cancelled
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Run the search, calling `on_match` for each match as it is found.
+    /// Calls come from the walker's threads, in no particular order.
+    pub fn for_each(&self, on_match: impl Fn(&Entry) + Sync) -> Result<SearchSummary> {
+        self.args.validate().map_err(|e| anyhow::anyhow!("Invalid search: {}", e))?;
+        let matcher = PatternMatcher::new(&self.args)?;
+        let warnings = Arc::new(WarningCollector::new(false));
+        let walker = FileWalker::new(self.args.clone()).with_warnings(Arc::clone(&warnings));
+
+        let matched = AtomicUsize::new(0);
+        walker.visit(&|walk_result| {
+            if self.cancellation.is_cancelled() {
+                return WalkState::Quit;
+            }
+            if let Some(entry) = self.check(walk_result, &matcher, &warnings) {
+                matched.fetch_add(1, Ordering::Relaxed);
+                on_match(&entry);
+            }
+            WalkState::Continue
+        })?;
+
+        Ok(SearchSummary {
+            matched: matched.into_inner(),
+            visited: walker.get_stats().total_entries(),
+            cancelled: self.cancellation.is_cancelled(),
+            warnings: warnings.snapshot(),
+        })
+    }
+
+    /// Run the search and return the matches, sorted by path
+    pub fn collect(&self) -> Result<(Vec<Entry>, SearchSummary)> {
+        let found = std::sync::Mutex::new(Vec::new());
+        let summary = self.for_each(|entry| {
+            if let Ok(mut found) = found.lock() {
+                found.push(entry.clone());
+            }
+        })?;
+        let mut found = found.into_inner().unwrap_or_default();
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok((found, summary))
+    }
+
+    /// The entry for `walk_result` if it passes every filter
+    fn check(&self, walk_result: WalkResult, matcher: &PatternMatcher, warnings: &WarningCollector) -> Option<Entry> {
+        let metadata = match fs::metadata(&walk_result.path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warnings.report(Warning::metadata(&walk_result.path, &err));
+                return None;
+            }
+        };
+        match matcher.matches(&walk_result.path, &metadata) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => {
+                warnings.report(Warning::io(&walk_result.path, &std::io::Error::other(err.to_string())));
+                return None;
+            }
+        }
+
+        let entry = Entry {
+            path: walk_result.path,
+            depth: walk_result.depth,
+            is_symlink: walk_result.is_symlink,
+            metadata,
+        };
+        self.predicates.iter().all(|predicate| predicate(&entry)).then_some(entry)
+    }
+}
+
+impl Default for SearchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_builder_filters_and_predicates() {
+        let dir = tree();
+        let (found, summary) = SearchBuilder::new()
+            .path(dir.path())
+            .name("*.rs")
+            .filter(|entry| entry.metadata.len() > 0)
+            .collect()
+            .unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, dir.path().join("src/main.rs"));
+        assert_eq!(found[0].depth, 2);
+        assert_eq!(summary.matched, 1);
+        // The root, src and three files
+        assert_eq!(summary.visited, 5);
+        assert!(!summary.cancelled);
+
+        assert!(SearchBuilder::new().path(dir.path()).size("lots").collect().is_err());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let dir = tree();
+        let token = CancellationToken::new();
+        let calls = AtomicUsize::new(0);
+        let summary = SearchBuilder::new()
+            .path(dir.path())
+            .file_type("f")
+            .threads(1)
+            .cancellation(token.clone())
+            .for_each(|_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                token.cancel();
+            })
+            .unwrap();
+
+        assert!(summary.cancelled);
+        assert_eq!(calls.into_inner(), 1);
+    }
+}
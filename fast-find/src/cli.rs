@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Arg, ArgAction, ArgMatches, Command, FromArgMatches, Parser};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug, Clone)]
@@ -66,10 +66,38 @@ pub struct Args {
     #[arg(long = "ctime")]
     pub ctime: Option<String>,
 
-    /// Files newer than reference file
+    /// Files newer than reference file (same as --newer-mm)
+    ///
+    /// The --newer-XY=REF family compares the entry's X timestamp against
+    /// REF's Y timestamp, where X and Y are m (modify), a (access),
+    /// c (status change) or B (birth). With Y = t, REF is a date such as
+    /// "2024-01-01" or "2024-01-01 12:30" in local time, or an RFC 3339
+    /// timestamp.
     #[arg(long = "newer")]
     pub newer: Option<PathBuf>,
 
+    #[command(flatten)]
+    pub newer_xy: NewerFlags,
+
+    // Link Filters
+    /// Names of the same file as PATH: same device and inode, so every hard
+    /// link to it (and PATH itself)
+    #[arg(long = "samefile", value_name = "PATH")]
+    pub samefile: Option<PathBuf>,
+
+    /// Files with at least N hard links
+    #[arg(long = "min-links", value_name = "N")]
+    pub min_links: Option<u64>,
+
+    // Content Filters
+    /// MIME type sniffed from file contents (e.g., "image/*", "text/plain")
+    #[arg(long = "mime")]
+    pub mime: Option<String>,
+
+    /// Format detected from magic numbers (e.g., "elf", "png", "executable")
+    #[arg(long = "magic")]
+    pub magic: Option<String>,
+
     // Depth Control
     /// Maximum search depth
     #[arg(long = "max-depth")]
@@ -96,6 +124,11 @@ pub struct Args {
     #[arg(long = "mount")]
     pub cross_filesystem: bool,
 
+    /// Report entries again when they are reached through more than one of
+    /// the given paths, e.g. `ffind / /home`
+    #[arg(long = "no-dedup")]
+    pub no_dedup: bool,
+
     // Performance Options
     /// Number of worker threads (default: CPU cores)
     #[arg(short = 'j', long = "threads")]
@@ -114,6 +147,24 @@ pub struct Args {
     #[arg(long = "json")]
     pub json_output: bool,
 
+    /// Stream one JSON object per line as matches are found, ending with a
+    /// summary line; every line carries the schema version
+    #[arg(long = "json-lines", conflicts_with_all = ["json_output", "print0", "long_format", "count_only", "sort_results"])]
+    pub json_lines: bool,
+
+    /// Print canonical absolute paths; symlinks keep their own name
+    #[arg(long = "absolute", conflicts_with = "relative_to")]
+    pub absolute: bool,
+
+    /// Print paths relative to DIR, with `..` where they lie outside it
+    #[arg(long = "relative-to", value_name = "DIR")]
+    pub relative_to: Option<PathBuf>,
+
+    /// Remove PREFIX from the start of printed paths, after --absolute or
+    /// --relative-to; paths outside it are printed unchanged
+    #[arg(long = "strip-prefix", value_name = "PREFIX")]
+    pub strip_prefix: Option<PathBuf>,
+
     /// Disable colored output
     #[arg(long = "no-color")]
     pub no_color: bool,
@@ -126,10 +177,20 @@ pub struct Args {
     #[arg(short = 'c', long = "count")]
     pub count_only: bool,
 
+    /// Instead of listing matches, print statistics over them: count, total
+    /// and median size, a size histogram, the most common extensions and the
+    /// oldest and newest entries (as JSON with --json)
+    #[arg(long = "analyze", conflicts_with_all = ["count_only", "long_format", "print0", "json_lines", "interactive"])]
+    pub analyze: bool,
+
     /// Show statistics after search
     #[arg(long = "stats")]
     pub show_stats: bool,
 
+    /// Print warnings for unreadable paths as they happen, not after the results
+    #[arg(long = "verbose-errors")]
+    pub verbose_errors: bool,
+
     // Actions (simplified - no exec/delete for safety)
     /// Print matching files (default action)
     #[arg(long = "print")]
@@ -142,6 +203,16 @@ pub struct Args {
     /// Reverse sort order
     #[arg(short = 'r', long = "reverse")]
     pub reverse_sort: bool,
+
+    /// Pick from the results in a fuzzy finder as they stream in, then print
+    /// the chosen entries (Tab marks several, Esc cancels)
+    #[arg(short = 'i', long = "interactive", conflicts_with_all = ["json_output", "json_lines", "count_only"])]
+    pub interactive: bool,
+
+    /// With --interactive, run CMD through sh on the chosen entries, which
+    /// replace {} or are appended, e.g. --exec 'vim {}'
+    #[arg(long = "exec", value_name = "CMD", requires = "interactive")]
+    pub exec: Option<String>,
 }
 
 impl Default for Args {
@@ -162,23 +233,37 @@ impl Default for Args {
             atime: None,
             ctime: None,
             newer: None,
+            newer_xy: NewerFlags::default(),
+            samefile: None,
+            min_links: None,
+            mime: None,
+            magic: None,
             max_depth: None,
             min_depth: None,
             follow_symlinks: false,
             search_hidden: false,
             respect_ignore: true,
             cross_filesystem: false,
+            no_dedup: false,
             threads: None,
             max_open: None,
             print0: false,
             json_output: false,
+            json_lines: false,
+            absolute: false,
+            relative_to: None,
+            strip_prefix: None,
             no_color: false,
             long_format: false,
             count_only: false,
+            analyze: false,
             show_stats: false,
+            verbose_errors: false,
             print: false,
             sort_results: false,
             reverse_sort: false,
+            interactive: false,
+            exec: None,
         }
     }
 }
@@ -216,6 +301,15 @@ impl Args {
             || self.atime.is_some() 
             || self.ctime.is_some() 
             || self.newer.is_some()
+            || !self.newer_xy.specs.is_empty()
+    }
+
+    pub fn has_link_filters(&self) -> bool {
+        self.samefile.is_some() || self.min_links.is_some()
+    }
+
+    pub fn has_content_filters(&self) -> bool {
+        self.mime.is_some() || self.magic.is_some()
     }
 
     pub fn validate(&self) -> Result<(), String> {
@@ -253,6 +347,108 @@ impl Args {
     }
 }
 
+/// A file timestamp the `--newer-XY` family can compare
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    Modified,
+    Accessed,
+    Changed,
+    Birth,
+}
+
+impl Timestamp {
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'm' => Some(Self::Modified),
+            'a' => Some(Self::Accessed),
+            'c' => Some(Self::Changed),
+            'B' => Some(Self::Birth),
+            _ => None,
+        }
+    }
+}
+
+/// What a `--newer-XY` value names
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NewerReference {
+    /// The given timestamp of a reference file
+    File(Timestamp),
+    /// An explicit date (Y = `t`)
+    Date,
+}
+
+/// One `--newer-XY=REF` test
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewerSpec {
+    /// Timestamp of the entry being tested
+    pub timestamp: Timestamp,
+    pub reference: NewerReference,
+    pub value: String,
+}
+
+const NEWER_FLAGS: [&str; 20] = [
+    "newer-mm", "newer-ma", "newer-mc", "newer-mB", "newer-mt",
+    "newer-am", "newer-aa", "newer-ac", "newer-aB", "newer-at",
+    "newer-cm", "newer-ca", "newer-cc", "newer-cB", "newer-ct",
+    "newer-Bm", "newer-Ba", "newer-Bc", "newer-BB", "newer-Bt",
+];
+
+/// The `--newer-XY` options, one flag per timestamp pair. They are hidden
+/// from the option list and documented under `--newer`.
+#[derive(Clone, Debug, Default)]
+pub struct NewerFlags {
+    pub specs: Vec<NewerSpec>,
+}
+
+impl NewerFlags {
+    fn spec(flag: &str, value: &str) -> NewerSpec {
+        let mut letters = flag["newer-".len()..].chars();
+        let timestamp = letters.next().and_then(Timestamp::from_letter)
+            .expect("flag names use known timestamp letters");
+        let reference = match letters.next() {
+            Some('t') => NewerReference::Date,
+            letter => NewerReference::File(letter.and_then(Timestamp::from_letter)
+                .expect("flag names use known timestamp letters")),
+        };
+        NewerSpec { timestamp, reference, value: value.to_string() }
+    }
+}
+
+impl FromArgMatches for NewerFlags {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let mut flags = Self::default();
+        flags.update_from_arg_matches(matches)?;
+        Ok(flags)
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        for flag in NEWER_FLAGS {
+            if let Some(values) = matches.get_many::<String>(flag) {
+                self.specs.extend(values.map(|value| Self::spec(flag, value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl clap::Args for NewerFlags {
+    fn augment_args(cmd: Command) -> Command {
+        NEWER_FLAGS.iter().fold(cmd, |cmd, flag| {
+            cmd.arg(
+                Arg::new(*flag)
+                    .long(*flag)
+                    .value_name("REF")
+                    .action(ArgAction::Append)
+                    .hide(true),
+            )
+        })
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
 fn is_valid_size_spec(s: &str) -> bool {
     if s.is_empty() {
         return false;
@@ -311,6 +507,32 @@ mod tests {
         assert!(!is_valid_size_spec("100x"));
     }
 
+    #[test]
+    fn test_newer_xy_flags() {
+        let args = Args::try_parse_from([
+            "ffind", "--newer-ct=2024-01-01", "--newer-Bm", "ref.txt", "--newer-Bm=other",
+        ]).unwrap();
+        assert_eq!(args.newer_xy.specs, vec![
+            NewerSpec {
+                timestamp: Timestamp::Changed,
+                reference: NewerReference::Date,
+                value: "2024-01-01".to_string(),
+            },
+            NewerSpec {
+                timestamp: Timestamp::Birth,
+                reference: NewerReference::File(Timestamp::Modified),
+                value: "ref.txt".to_string(),
+            },
+            NewerSpec {
+                timestamp: Timestamp::Birth,
+                reference: NewerReference::File(Timestamp::Modified),
+                value: "other".to_string(),
+            },
+        ]);
+        assert!(args.has_time_filters());
+        assert!(args.paths.is_empty());
+    }
+
     #[test]
     fn test_time_validation() {
         assert!(is_valid_time_spec("7"));
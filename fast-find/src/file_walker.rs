@@ -1,18 +1,30 @@
 use anyhow::Result;
 use ignore::{WalkBuilder, WalkState};
 use rayon::prelude::*;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::cli::Args;
+use crate::warnings::{Warning, WarningCollector};
 
 pub struct FileWalker {
     args: Args,
     files_visited: Arc<AtomicUsize>,
     dirs_visited: Arc<AtomicUsize>,
+    duplicates_skipped: Arc<AtomicUsize>,
+    warnings: Arc<WarningCollector>,
 }
 
+/// Entries already reported, keyed by canonical root plus the path below
+/// it, shared by the walks of overlapping search paths
+type SeenEntries = Mutex<HashSet<PathBuf>>;
+
+/// Called with each entry as the walk finds it, from the walker's threads;
+/// `WalkState::Quit` ends the walk
+pub type Visitor<'a> = dyn Fn(WalkResult) -> WalkState + Sync + 'a;
+
 #[derive(Debug, Clone)]
 pub struct WalkResult {
     pub path: PathBuf,
@@ -27,16 +39,34 @@ impl FileWalker {
             args,
             files_visited: Arc::new(AtomicUsize::new(0)),
             dirs_visited: Arc::new(AtomicUsize::new(0)),
+            duplicates_skipped: Arc::new(AtomicUsize::new(0)),
+            warnings: Arc::new(WarningCollector::default()),
         }
     }
 
+    pub fn with_warnings(mut self, warnings: Arc<WarningCollector>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
     pub fn walk(&self) -> Result<Vec<WalkResult>> {
-        let paths = self.args.get_paths();
-        
-        // Collect all entries in parallel
+        let (paths, canonical, seen) = self.roots();
+
+        // Collect all entries in parallel, grouped by search path
         let all_results: Vec<WalkResult> = paths
             .par_iter()
-            .map(|path| self.walk_path(path))
+            .zip(&canonical)
+            .map(|(path, canonical)| {
+                let dedup = seen.as_ref().zip(canonical.as_deref());
+                let results = Mutex::new(Vec::new());
+                self.walk_path(path, dedup, &|walk_result| {
+                    if let Ok(mut results) = results.lock() {
+                        results.push(walk_result);
+                    }
+                    WalkState::Continue
+                })?;
+                Ok(results.into_inner().unwrap_or_default())
+            })
             .collect::<Result<Vec<Vec<WalkResult>>>>()?
             .into_iter()
             .flatten()
@@ -58,23 +88,59 @@ impl FileWalker {
         Ok(results)
     }
 
-    fn walk_path(&self, root_path: &Path) -> Result<Vec<WalkResult>> {
-        let mut results = Vec::new();
-        
+    /// Walk every search path, handing each entry to `visit` as it is found
+    /// rather than collecting them; `--sort` does not apply
+    pub fn visit(&self, visit: &Visitor) -> Result<()> {
+        let (paths, canonical, seen) = self.roots();
+        paths.par_iter().zip(&canonical).try_for_each(|(path, canonical)| {
+            let dedup = seen.as_ref().zip(canonical.as_deref());
+            self.walk_path(path, dedup, visit)
+        })
+    }
+
+    /// The search paths, their canonical forms, and the set of entries seen
+    /// so far if they overlap
+    fn roots(&self) -> (Vec<PathBuf>, Vec<Option<PathBuf>>, Option<SeenEntries>) {
+        let paths = self.args.get_paths();
+
+        // Only overlapping paths can yield an entry twice, so only then is
+        // every entry checked against the others
+        let canonical: Vec<Option<PathBuf>> = paths.iter().map(|path| path.canonicalize().ok()).collect();
+        let seen = (!self.args.no_dedup && roots_overlap(&canonical)).then(SeenEntries::default);
+        (paths, canonical, seen)
+    }
+
+    /// Whether an entry was already reported under another search path;
+    /// otherwise remember it
+    fn is_duplicate(&self, dedup: Option<(&SeenEntries, &Path)>, root_path: &Path, path: &Path) -> bool {
+        let Some((seen, canonical_root)) = dedup else {
+            return false;
+        };
+        let key = canonical_root.join(path.strip_prefix(root_path).unwrap_or(path));
+        let duplicate = !seen.lock().map(|mut seen| seen.insert(key)).unwrap_or(true);
+        if duplicate {
+            self.duplicates_skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        duplicate
+    }
+
+    fn walk_path(&self, root_path: &Path, dedup: Option<(&SeenEntries, &Path)>, visit: &Visitor) -> Result<()> {
         // Handle single file case
         if root_path.is_file() {
+            if self.is_duplicate(dedup, root_path, root_path) {
+                return Ok(());
+            }
             let metadata = std::fs::metadata(root_path)?;
             let is_symlink = metadata.file_type().is_symlink();
             
-            results.push(WalkResult {
+            self.files_visited.fetch_add(1, Ordering::Relaxed);
+            visit(WalkResult {
                 path: root_path.to_path_buf(),
                 depth: 0,
                 is_dir: false,
                 is_symlink,
             });
-            
-            self.files_visited.fetch_add(1, Ordering::Relaxed);
-            return Ok(results);
+            return Ok(());
         }
 
         // Configure directory walker
@@ -97,14 +163,12 @@ impl FileWalker {
         // Use parallel walking for better performance
         let walker = builder.build_parallel();
         
-        // Thread-safe result collection
-        let results_mutex = std::sync::Mutex::new(Vec::new());
         let files_visited = Arc::clone(&self.files_visited);
         let dirs_visited = Arc::clone(&self.dirs_visited);
         let min_depth = self.args.min_depth.unwrap_or(0);
+        let warnings = &self.warnings;
 
         walker.run(|| {
-            let results_mutex = &results_mutex;
             let files_visited = Arc::clone(&files_visited);
             let dirs_visited = Arc::clone(&dirs_visited);
             
@@ -119,6 +183,9 @@ impl FileWalker {
                         }
 
                         let path = entry.path();
+                        if self.is_duplicate(dedup, root_path, path) {
+                            return WalkState::Continue;
+                        }
                         let file_type = entry.file_type();
                         
                         if let Some(file_type) = file_type {
@@ -132,37 +199,32 @@ impl FileWalker {
                                 files_visited.fetch_add(1, Ordering::Relaxed);
                             }
                             
-                            // Collect the result
-                            let walk_result = WalkResult {
+                            return visit(WalkResult {
                                 path: path.to_path_buf(),
                                 depth,
                                 is_dir,
                                 is_symlink,
-                            };
-                            
-                            if let Ok(mut results) = results_mutex.lock() {
-                                results.push(walk_result);
-                            }
+                            });
                         }
                         
                         WalkState::Continue
                     }
                     Err(err) => {
-                        eprintln!("Warning: {}", err);
+                        warnings.report(Warning::from_walk_error(&err));
                         WalkState::Continue
                     }
                 }
             })
         });
 
-        let results = results_mutex.into_inner().unwrap_or_default();
-        Ok(results)
+        Ok(())
     }
 
     pub fn get_stats(&self) -> WalkStats {
         WalkStats {
             files_visited: self.files_visited.load(Ordering::Relaxed),
             dirs_visited: self.dirs_visited.load(Ordering::Relaxed),
+            duplicates_skipped: self.duplicates_skipped.load(Ordering::Relaxed),
         }
     }
 }
@@ -171,6 +233,8 @@ impl FileWalker {
 pub struct WalkStats {
     pub files_visited: usize,
     pub dirs_visited: usize,
+    /// Entries left out because an overlapping search path reported them
+    pub duplicates_skipped: usize,
 }
 
 impl WalkStats {
@@ -179,6 +243,14 @@ impl WalkStats {
     }
 }
 
+/// Whether any search path is, or lies inside, another one
+fn roots_overlap(canonical: &[Option<PathBuf>]) -> bool {
+    let roots: Vec<&PathBuf> = canonical.iter().flatten().collect();
+    roots.iter().enumerate().any(|(i, a)| {
+        roots.iter().enumerate().any(|(j, b)| i != j && a.starts_with(b))
+    })
+}
+
 // Helper function to check if path matches depth constraints
 pub fn check_depth_constraints(path: &Path, root: &Path, min_depth: Option<usize>, max_depth: Option<usize>) -> bool {
     let depth = path.strip_prefix(root)
@@ -288,6 +360,39 @@ mod tests {
         assert!(paths.contains(&&test_file2));
     }
 
+    #[test]
+    fn test_overlapping_roots_are_deduplicated() {
+        let temp_dir = TempDir::new().unwrap();
+        let sub_dir = temp_dir.path().join("subdir");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::write(sub_dir.join("file.txt"), "content").unwrap();
+        fs::write(temp_dir.path().join("top.txt"), "content").unwrap();
+
+        let walk = |no_dedup| {
+            let args = Args {
+                paths: vec![temp_dir.path().to_path_buf(), sub_dir.clone(), sub_dir.join("file.txt")],
+                no_dedup,
+                ..Args::default()
+            };
+            let walker = FileWalker::new(args);
+            let results = walker.walk().unwrap();
+            (results.len(), walker.get_stats())
+        };
+
+        // root, subdir, file.txt, top.txt
+        let (found, stats) = walk(false);
+        assert_eq!(found, 4);
+        assert_eq!(stats.duplicates_skipped, 3);
+        assert_eq!(stats.total_entries(), 4);
+
+        let (found, stats) = walk(true);
+        assert_eq!(found, 7);
+        assert_eq!(stats.duplicates_skipped, 0);
+
+        assert!(!roots_overlap(&[Some("/a".into()), Some("/ab".into()), None]));
+        assert!(roots_overlap(&[Some("/a".into()), Some("/a/b".into())]));
+    }
+
     #[test]
     fn test_depth_constraints() {
         let root = Path::new("/root");
@@ -305,6 +410,6 @@ mod tests {
     #[test]
     fn test_symlink_following() {
         assert!(!should_follow_symlink(Path::new("../parent"), true));
-        assert!(should_follow_symlink(Path::new("regular_file"), false) == false);
+        assert!(!should_follow_symlink(Path::new("regular_file"), false));
     }
 }
\ No newline at end of file
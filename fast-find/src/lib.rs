@@ -0,0 +1,34 @@
+//! ffind's traversal and filters as a library, for tools that need to walk
+//! a tree the way `ffind` does.
+//!
+//! ```no_run
+//! use fast_find::{CancellationToken, SearchBuilder};
+//!
+//! let token = CancellationToken::new();
+//! let summary = SearchBuilder::new()
+//!     .path("src")
+//!     .name("*.rs")
+//!     .filter(|entry| entry.metadata.len() > 1024)
+//!     .cancellation(token.clone())
+//!     .for_each(|entry| println!("{}", entry.path.display()))
+//!     .unwrap();
+//! eprintln!("{} of {} entries matched", summary.matched, summary.visited);
+//! ```
+
+pub mod analyze;
+pub mod builder;
+pub mod cli;
+pub mod file_walker;
+pub mod magic;
+pub mod output;
+pub mod path_style;
+pub mod pattern_matcher;
+pub mod picker;
+pub mod search;
+pub mod warnings;
+pub mod worker;
+
+#[cfg(test)]
+mod tests;
+
+pub use builder::{CancellationToken, Entry, Predicate, SearchBuilder, SearchSummary};
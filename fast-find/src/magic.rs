@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Result};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Number of leading bytes read from each candidate file
+const SNIFF_LEN: usize = 8192;
+
+/// Named `--magic` groups that cover several detected formats
+const MAGIC_GROUPS: &[(&str, &[&str])] = &[
+    ("executable", &["elf", "exe", "dll", "mach", "sh"]),
+    ("pe", &["exe", "dll"]),
+    ("macho", &["mach"]),
+    ("script", &["sh"]),
+    ("shebang", &["sh"]),
+];
+
+/// What a file's leading bytes say it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime_type: &'static str,
+    /// Short format name such as "elf" or "png"; empty when only the
+    /// text/binary fallback applied
+    pub kind: &'static str,
+}
+
+/// Detect the content type of a file from its magic number.
+///
+/// Formats without a signature fall back to `text/plain` or
+/// `application/octet-stream`, like `file --mime-type`.
+pub fn sniff(path: &Path) -> std::io::Result<ContentType> {
+    let mut buffer = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(sniff_bytes(&buffer))
+}
+
+pub fn sniff_bytes(buffer: &[u8]) -> ContentType {
+    if buffer.is_empty() {
+        return ContentType {
+            mime_type: "inode/x-empty",
+            kind: "",
+        };
+    }
+
+    if let Some(detected) = infer::get(buffer) {
+        return ContentType {
+            mime_type: detected.mime_type(),
+            kind: detected.extension(),
+        };
+    }
+
+    let mime_type = if looks_like_text(buffer) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    };
+    ContentType { mime_type, kind: "" }
+}
+
+fn looks_like_text(buffer: &[u8]) -> bool {
+    if buffer.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(buffer) {
+        Ok(_) => true,
+        // The sniff window may cut a multi-byte character in half
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// `--mime` / `--magic` predicate, evaluated after all metadata filters so
+/// only surviving regular files are opened
+#[derive(Clone, Debug)]
+pub struct ContentFilter {
+    mime_patterns: Vec<String>,
+    magic_kinds: Vec<String>,
+}
+
+impl ContentFilter {
+    pub fn new(mime: Option<&str>, magic: Option<&str>) -> Result<Option<Self>> {
+        let mime_patterns = mime.map(parse_list).unwrap_or_default();
+        let magic_names = magic.map(parse_list).unwrap_or_default();
+
+        if mime.is_some() && mime_patterns.is_empty() {
+            return Err(anyhow!("Empty --mime specification"));
+        }
+        if magic.is_some() && magic_names.is_empty() {
+            return Err(anyhow!("Empty --magic specification"));
+        }
+        if mime_patterns.is_empty() && magic_names.is_empty() {
+            return Ok(None);
+        }
+
+        let magic_kinds = magic_names
+            .into_iter()
+            .flat_map(|name| {
+                match MAGIC_GROUPS.iter().find(|(group, _)| *group == name) {
+                    Some((_, kinds)) => kinds.iter().map(|k| k.to_string()).collect(),
+                    None => vec![name],
+                }
+            })
+            .collect();
+
+        Ok(Some(Self {
+            mime_patterns,
+            magic_kinds,
+        }))
+    }
+
+    pub fn matches(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        if !metadata.is_file() {
+            return false;
+        }
+        match sniff(path) {
+            Ok(content_type) => self.matches_type(&content_type),
+            // Unreadable files cannot be classified
+            Err(_) => false,
+        }
+    }
+
+    fn matches_type(&self, content_type: &ContentType) -> bool {
+        let mime_ok = self.mime_patterns.is_empty()
+            || self
+                .mime_patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern, content_type.mime_type));
+        let magic_ok = self.magic_kinds.is_empty()
+            || self.magic_kinds.iter().any(|kind| kind == content_type.kind);
+        mime_ok && magic_ok
+    }
+}
+
+fn parse_list(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let remaining: Vec<&str> = parts.collect();
+    let Some((last, middle)) = remaining.split_last() else {
+        // No '*' at all: exact match
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+    /// A 64-byte ELF header (signature followed by zeroed fields)
+    fn elf_header() -> Vec<u8> {
+        let mut header = b"\x7fELF\x02\x01\x01".to_vec();
+        header.resize(64, 0);
+        header
+    }
+
+    #[test]
+    fn test_sniff_bytes() {
+        assert_eq!(sniff_bytes(&elf_header()).kind, "elf");
+        assert_eq!(sniff_bytes(PNG_HEADER).mime_type, "image/png");
+        assert_eq!(sniff_bytes(b"#!/bin/sh\necho hi\n").kind, "sh");
+        assert_eq!(sniff_bytes(b"plain words\n").mime_type, "text/plain");
+        assert_eq!(sniff_bytes(b"\x00\x01\x02\x03").mime_type, "application/octet-stream");
+        assert_eq!(sniff_bytes(b"").mime_type, "inode/x-empty");
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("image/*", "image/png"));
+        assert!(wildcard_match("*", "text/plain"));
+        assert!(wildcard_match("application/x-*", "application/x-executable"));
+        assert!(wildcard_match("text/plain", "text/plain"));
+        assert!(!wildcard_match("image/*", "text/plain"));
+        assert!(!wildcard_match("text/plain", "text/plain2"));
+    }
+
+    #[test]
+    fn test_content_filter() {
+        let filter = ContentFilter::new(Some("image/*"), None).unwrap().unwrap();
+        assert!(filter.matches_type(&sniff_bytes(PNG_HEADER)));
+        assert!(!filter.matches_type(&sniff_bytes(&elf_header())));
+
+        let filter = ContentFilter::new(None, Some("executable")).unwrap().unwrap();
+        assert!(filter.matches_type(&sniff_bytes(&elf_header())));
+        assert!(filter.matches_type(&sniff_bytes(b"#!/usr/bin/env python3\n")));
+        assert!(!filter.matches_type(&sniff_bytes(PNG_HEADER)));
+
+        assert!(ContentFilter::new(None, None).unwrap().is_none());
+        assert!(ContentFilter::new(Some(" , "), None).is_err());
+    }
+}
@@ -1,24 +1,25 @@
-use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
 
-mod cli;
-mod search;
-mod file_walker;
-mod pattern_matcher;
-mod output;
-mod worker;
+use fast_find::cli::Args;
+use fast_find::picker::Cancelled;
+use fast_find::search::SearchEngine;
+use fast_find::warnings::{EXIT_CANCELLED, EXIT_FATAL, EXIT_WARNINGS};
 
-#[cfg(test)]
-mod tests;
-
-use cli::Args;
-use search::SearchEngine;
-
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
     
-    let search_engine = SearchEngine::new(args)?;
-    search_engine.run()
+    let result = SearchEngine::new(args).and_then(|search_engine| search_engine.run());
+    match result {
+        Ok(0) => ExitCode::SUCCESS,
+        // Like find: the results are complete except for what could not be read
+        Ok(_) => ExitCode::from(EXIT_WARNINGS),
+        Err(e) if e.is::<Cancelled>() => ExitCode::from(EXIT_CANCELLED),
+        Err(e) => {
+            eprintln!("ffind: {:#}", e);
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
 }
 
 // Architecture Overview:
@@ -27,6 +28,7 @@ fn main() -> Result<()> {
 //    - Search patterns (name, iname, path, ipath, type, size, time)
 //    - Path traversal options (depth, hidden files, follow symlinks)
 //    - Output formatting (print, print0, json, colored)
+//    - Printed path style (absolute, relative-to, strip-prefix; path_style.rs)
 //
 // 2. SearchEngine (search.rs) - Main orchestrator
 //    - Coordinates file discovery and filtering
@@ -51,4 +53,16 @@ fn main() -> Result<()> {
 // 6. Worker (worker.rs) - Parallel processing
 //    - File evaluation in parallel
 //    - Load balancing across CPU cores
-//    - Result collection and ordering
\ No newline at end of file
+//    - Result collection and ordering
+//
+// 7. Picker (picker.rs) - Interactive selection
+//    - Fuzzy matching and ranking of streamed results
+//    - Terminal UI on /dev/tty, leaving stdout for the selection
+//
+// 8. SearchBuilder (builder.rs) - Library interface
+//    - The walker and matcher behind a builder, with custom predicates
+//    - Per-match callbacks and cancellation tokens
+//
+// 9. Analysis (analyze.rs) - `--analyze` statistics
+//    - Size histogram, median and extension breakdown of the matches
+//    - Oldest and newest entries
\ No newline at end of file
@@ -5,12 +5,25 @@ use std::fs;
 use std::path::Path;
 use std::time::SystemTime;
 
+use crate::path_style::PathStyle;
+use crate::warnings::Warning;
+
+/// Version of the `--json-lines` records. Bumped when a field is renamed or
+/// removed or its meaning changes; new fields may appear without a bump, so
+/// consumers should ignore keys they do not know.
+///
+/// Version 1 has two record types, told apart by `type`:
+/// - `"entry"`: a match, with the fields of [`FileInfo`]
+/// - `"summary"`: the last line, with `stats` ([`SearchStats`]) and `warnings`
+pub const JSON_LINES_SCHEMA: u32 = 1;
+
 #[derive(Debug, Clone)]
 pub struct OutputFormatter {
     use_colors: bool,
     long_format: bool,
     print0: bool,
     json_output: bool,
+    path_style: PathStyle,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +40,7 @@ pub struct FileInfo {
 pub struct SearchResults {
     pub files: Vec<FileInfo>,
     pub stats: SearchStats,
+    pub warnings: Vec<Warning>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,9 +48,33 @@ pub struct SearchStats {
     pub total_found: usize,
     pub files_visited: usize,
     pub dirs_visited: usize,
+    /// Entries reached again through an overlapping search path
+    #[serde(default)]
+    pub duplicates_skipped: usize,
     pub processing_time_ms: u64,
 }
 
+/// One `--json-lines` record
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonLine<'a> {
+    Entry {
+        #[serde(flatten)]
+        file: &'a FileInfo,
+    },
+    Summary {
+        stats: &'a SearchStats,
+        warnings: &'a [Warning],
+    },
+}
+
+#[derive(Serialize)]
+struct VersionedLine<'a> {
+    schema: u32,
+    #[serde(flatten)]
+    line: &'a JsonLine<'a>,
+}
+
 impl OutputFormatter {
     pub fn new(use_colors: bool, long_format: bool, print0: bool, json_output: bool) -> Self {
         Self {
@@ -44,7 +82,29 @@ impl OutputFormatter {
             long_format,
             print0,
             json_output,
+            path_style: PathStyle::default(),
+        }
+    }
+
+    pub fn with_path_style(mut self, path_style: PathStyle) -> Self {
+        self.path_style = path_style;
+        self
+    }
+
+    /// `path` as the results show it (see `PathStyle`)
+    pub fn display_path(&self, path: &str) -> String {
+        if self.path_style.is_as_found() {
+            return path.to_string();
         }
+        self.path_style.apply(Path::new(path)).to_string_lossy().into_owned()
+    }
+
+    /// `info` with its path as the results show it
+    pub fn display_file_info(&self, mut info: FileInfo) -> FileInfo {
+        if !self.path_style.is_as_found() {
+            info.path = self.display_path(&info.path);
+        }
+        info
     }
 
     pub fn format_path(&self, path: &Path, metadata: Option<&fs::Metadata>, _depth: usize) -> Result<String> {
@@ -52,15 +112,16 @@ impl OutputFormatter {
             return Ok(String::new()); // JSON output handled separately
         }
 
-        let path_str = path.to_string_lossy();
-        
+        let shown = self.path_style.apply(path);
+        let path_str = shown.to_string_lossy();
+
         if self.print0 {
             return Ok(format!("{}\0", path_str));
         }
 
         if !self.long_format {
             return Ok(if self.use_colors {
-                self.colorize_path(path, metadata).to_string()
+                self.colorize_path(&shown, metadata).to_string()
             } else {
                 path_str.to_string()
             });
@@ -96,7 +157,7 @@ impl OutputFormatter {
 
         // Path with colors
         let colored_path = if self.use_colors {
-            self.colorize_path(path, metadata)
+            self.colorize_path(&shown, metadata)
         } else {
             ColoredString::from(path_str.as_ref())
         };
@@ -105,16 +166,26 @@ impl OutputFormatter {
         Ok(output)
     }
 
-    pub fn format_json_results(&self, file_infos: Vec<FileInfo>, stats: SearchStats) -> Result<String> {
+    pub fn format_json_results(&self, file_infos: Vec<FileInfo>, stats: SearchStats, warnings: Vec<Warning>) -> Result<String> {
         let results = SearchResults {
             files: file_infos,
             stats,
+            warnings,
         };
         
         serde_json::to_string_pretty(&results)
             .map_err(|e| anyhow::anyhow!("Failed to serialize JSON: {}", e))
     }
 
+    /// A `--json-lines` record, on one line and without the newline
+    pub fn format_json_line(&self, line: &JsonLine) -> Result<String> {
+        serde_json::to_string(&VersionedLine {
+            schema: JSON_LINES_SCHEMA,
+            line,
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to serialize JSON: {}", e))
+    }
+
     pub fn format_count(&self, count: usize) -> String {
         if self.json_output {
             format!(r#"{{"count": {}}}"#, count)
@@ -127,11 +198,17 @@ impl OutputFormatter {
         if self.json_output {
             serde_json::to_string_pretty(stats).unwrap_or_default()
         } else {
+            let duplicates = if stats.duplicates_skipped > 0 {
+                format!("\n  Duplicates skipped: {}", stats.duplicates_skipped)
+            } else {
+                String::new()
+            };
             format!(
-                "Search completed:\n  Files found: {}\n  Files visited: {}\n  Directories visited: {}\n  Processing time: {}ms",
+                "Search completed:\n  Files found: {}\n  Files visited: {}\n  Directories visited: {}{}\n  Processing time: {}ms",
                 stats.total_found,
                 stats.files_visited,
                 stats.dirs_visited,
+                duplicates,
                 stats.processing_time_ms
             )
         }
@@ -266,7 +343,7 @@ fn format_permissions(metadata: &fs::Metadata) -> String {
     }
 }
 
-fn format_size(size: u64) -> String {
+pub(crate) fn format_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "K", "M", "G", "T"];
     let mut size = size as f64;
     let mut unit_index = 0;
@@ -311,8 +388,6 @@ fn format_time_iso(time: SystemTime) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use tempfile::TempDir;
 
     #[test]
     fn test_format_size() {
@@ -339,6 +414,25 @@ mod tests {
         assert_eq!(result, "test.txt\0");
     }
 
+    #[test]
+    fn test_path_style_applies_to_every_format() {
+        let style = PathStyle::new(false, None, Some(Path::new("src"))).unwrap();
+        let print0 = OutputFormatter::new(false, false, true, false).with_path_style(style.clone());
+        assert_eq!(print0.format_path(Path::new("src/net/http.rs"), None, 2).unwrap(), "net/http.rs\0");
+
+        let json = OutputFormatter::new(false, false, false, true).with_path_style(style);
+        let info = FileInfo {
+            path: "src/lib.rs".to_string(),
+            file_type: "file".to_string(),
+            size: None,
+            modified: None,
+            permissions: None,
+            depth: 1,
+        };
+        assert_eq!(json.display_file_info(info).path, "lib.rs");
+        assert_eq!(json.display_path("docs/api.md"), "docs/api.md");
+    }
+
     #[test]
     fn test_json_output() {
         let formatter = OutputFormatter::new(false, false, false, true);
@@ -355,11 +449,43 @@ mod tests {
             total_found: 1,
             files_visited: 1,
             dirs_visited: 0,
+            duplicates_skipped: 0,
             processing_time_ms: 10,
         };
         
-        let result = formatter.format_json_results(vec![file_info], stats);
+        let result = formatter.format_json_results(vec![file_info], stats, Vec::new());
         assert!(result.is_ok());
         assert!(result.unwrap().contains("test.txt"));
     }
+
+    #[test]
+    fn test_json_lines() {
+        let formatter = OutputFormatter::new(false, false, false, false);
+        let file_info = FileInfo {
+            path: "a\nb.txt".to_string(),
+            file_type: "file".to_string(),
+            size: Some(3),
+            modified: None,
+            permissions: None,
+            depth: 2,
+        };
+        let entry = formatter.format_json_line(&JsonLine::Entry { file: &file_info }).unwrap();
+        assert!(!entry.contains('\n'));
+        assert!(entry.starts_with(r#"{"schema":1,"type":"entry","path":"a\nb.txt""#), "{}", entry);
+        let parsed: FileInfo = serde_json::from_str(&entry).unwrap();
+        assert_eq!(parsed.depth, 2);
+
+        let stats = SearchStats {
+            total_found: 1,
+            files_visited: 4,
+            dirs_visited: 1,
+            duplicates_skipped: 0,
+            processing_time_ms: 7,
+        };
+        let summary = formatter.format_json_line(&JsonLine::Summary { stats: &stats, warnings: &[] }).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(parsed["type"], "summary");
+        assert_eq!(parsed["stats"]["files_visited"], 4);
+        assert_eq!(parsed["warnings"].as_array().map(Vec::len), Some(0));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+/// How result paths are written, the same in every output format: as the
+/// traversal produced them by default, or rewritten by `--absolute`,
+/// `--relative-to` and `--strip-prefix`.
+///
+/// Only what is printed changes; filters and `--exec` see the paths as
+/// found.
+#[derive(Debug, Clone, Default)]
+pub struct PathStyle {
+    absolute: bool,
+    /// Canonical base directory of `--relative-to`
+    relative_to: Option<PathBuf>,
+    strip_prefix: Option<PathBuf>,
+    current_dir: PathBuf,
+}
+
+impl PathStyle {
+    /// `relative_to` must be an existing directory. `strip_prefix` is taken
+    /// off after the other two, so it can shorten absolute paths.
+    pub fn new(absolute: bool, relative_to: Option<&Path>, strip_prefix: Option<&Path>) -> Result<Self> {
+        let relative_to = relative_to
+            .map(|dir| dir.canonicalize().with_context(|| format!("--relative-to '{}'", dir.display())))
+            .transpose()?;
+        Ok(Self {
+            absolute,
+            relative_to,
+            strip_prefix: strip_prefix.map(Path::to_path_buf),
+            current_dir: env::current_dir().unwrap_or_default(),
+        })
+    }
+
+    /// Whether paths are printed as found
+    pub fn is_as_found(&self) -> bool {
+        !self.absolute && self.relative_to.is_none() && self.strip_prefix.is_none()
+    }
+
+    /// `path` as it should be printed
+    pub fn apply<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if self.is_as_found() {
+            return Cow::Borrowed(path);
+        }
+        let mut shown = Cow::Borrowed(path);
+        if let Some(ref base) = self.relative_to {
+            shown = Cow::Owned(relative(&self.absolute_path(path), base));
+        } else if self.absolute {
+            shown = Cow::Owned(self.absolute_path(path));
+        }
+        if let Some(ref prefix) = self.strip_prefix {
+            if let Ok(rest) = shown.strip_prefix(prefix) {
+                shown = Cow::Owned(if rest.as_os_str().is_empty() {
+                    PathBuf::from(".")
+                } else {
+                    rest.to_path_buf()
+                });
+            }
+        }
+        shown
+    }
+
+    /// The canonical path of `path`'s directory joined with its name, so a
+    /// symlink is shown as itself rather than as its target; entries whose
+    /// directory cannot be resolved are made absolute lexically
+    fn absolute_path(&self, path: &Path) -> PathBuf {
+        let canonical = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                parent.canonicalize().map(|parent| parent.join(name))
+            }
+            _ => path.canonicalize(),
+        };
+        canonical.unwrap_or_else(|_| normalize(&self.current_dir.join(path)))
+    }
+}
+
+/// `path` relative to `base`, both absolute, going up with `..` as far as
+/// needed
+fn relative(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative: PathBuf = base_components[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path_components[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// `.` and `..` resolved without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_relative() {
+        let path = Path::new("/srv/app/src/main.rs");
+        assert_eq!(relative(path, Path::new("/srv/app")), PathBuf::from("src/main.rs"));
+        assert_eq!(relative(path, Path::new("/srv/data/cache")), PathBuf::from("../../app/src/main.rs"));
+        assert_eq!(relative(Path::new("/srv/app"), Path::new("/srv/app")), PathBuf::from("."));
+        assert_eq!(normalize(Path::new("/srv/./app/../data")), PathBuf::from("/srv/data"));
+    }
+
+    #[test]
+    fn test_apply_styles() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src/net")).unwrap();
+        std::fs::write(root.join("src/net/http.rs"), "").unwrap();
+        std::os::unix::fs::symlink("net/http.rs", root.join("src/link.rs")).unwrap();
+        let found = root.join("src/net/../net/http.rs");
+
+        let absolute = PathStyle::new(true, None, None).unwrap();
+        assert_eq!(absolute.apply(&found), root.join("src/net/http.rs"));
+        // Symlinks keep their own name
+        assert_eq!(absolute.apply(&root.join("src/link.rs")), root.join("src/link.rs"));
+
+        let relative_to = PathStyle::new(false, Some(&root.join("src/net")), None).unwrap();
+        assert_eq!(relative_to.apply(&found), PathBuf::from("http.rs"));
+        assert_eq!(relative_to.apply(&root.join("src/link.rs")), PathBuf::from("../link.rs"));
+
+        let stripped = PathStyle::new(true, None, Some(&root)).unwrap();
+        assert_eq!(stripped.apply(&found), PathBuf::from("src/net/http.rs"));
+        assert_eq!(stripped.apply(&root), PathBuf::from("."));
+        // Paths outside the prefix are left alone
+        let other = PathStyle::new(false, None, Some(Path::new("build"))).unwrap();
+        assert_eq!(other.apply(Path::new("src/lib.rs")), Path::new("src/lib.rs"));
+
+        assert!(PathStyle::default().is_as_found());
+        assert!(PathStyle::new(false, Some(&root.join("missing")), None).is_err());
+    }
+}
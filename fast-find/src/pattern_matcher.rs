@@ -1,11 +1,13 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
 use regex::Regex;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::cli::Args;
+use crate::cli::{Args, NewerReference, NewerSpec, Timestamp};
+use crate::magic::ContentFilter;
 
 #[derive(Clone, Debug)]
 pub struct PatternMatcher {
@@ -28,7 +30,16 @@ pub struct PatternMatcher {
     mtime_filter: Option<TimeFilter>,
     atime_filter: Option<TimeFilter>,
     ctime_filter: Option<TimeFilter>,
-    newer_than: Option<SystemTime>,
+    /// The entry's timestamp must be later than the paired time
+    newer_than: Vec<(Timestamp, SystemTime)>,
+
+    // Link filters
+    /// (device, inode) of the `--samefile` reference
+    same_file: Option<(u64, u64)>,
+    min_links: Option<u64>,
+
+    // Content filters (read file data, so checked last)
+    content_filter: Option<ContentFilter>,
 }
 
 #[derive(Clone, Debug)]
@@ -79,7 +90,10 @@ impl PatternMatcher {
             mtime_filter: None,
             atime_filter: None,
             ctime_filter: None,
-            newer_than: None,
+            newer_than: Vec::new(),
+            same_file: None,
+            min_links: args.min_links,
+            content_filter: ContentFilter::new(args.mime.as_deref(), args.magic.as_deref())?,
         };
 
         // Parse name patterns
@@ -125,9 +139,26 @@ impl PatternMatcher {
             matcher.ctime_filter = Some(TimeFilter::parse(ctime)?);
         }
 
-        // Parse newer reference
+        // Resolve newer references
         if let Some(ref newer_path) = args.newer {
-            matcher.newer_than = Some(get_modification_time(newer_path)?);
+            let reference = reference_time(newer_path, Timestamp::Modified)?;
+            matcher.newer_than.push((Timestamp::Modified, reference));
+        }
+        for spec in &args.newer_xy.specs {
+            matcher.newer_than.push((spec.timestamp, resolve_newer(spec)?));
+        }
+
+        // Resolve the same-file reference; as in find, a symlink is the link
+        // itself unless links are followed, like the entries it is compared to
+        if let Some(ref reference) = args.samefile {
+            let metadata = if args.follow_symlinks {
+                fs::metadata(reference)
+            } else {
+                fs::symlink_metadata(reference)
+            }
+            .map_err(|e| anyhow!("Failed to get metadata for {}: {}", reference.display(), e))?;
+            matcher.same_file = Some(file_identity(&metadata)
+                .ok_or_else(|| anyhow!("--samefile needs device and inode numbers, which this platform lacks"))?);
         }
 
         Ok(matcher)
@@ -253,10 +284,30 @@ impl PatternMatcher {
             }
         }
 
-        // Check newer than reference
-        if let Some(ref reference_time) = &self.newer_than {
-            let mtime = metadata.modified().map_err(|e| anyhow!("Failed to get mtime: {}", e))?;
-            if mtime <= *reference_time {
+        // Check newer than references; entries without the timestamp never match
+        for (timestamp, reference_time) in &self.newer_than {
+            match file_timestamp(metadata, *timestamp) {
+                Some(time) if time > *reference_time => {}
+                _ => return Ok(false),
+            }
+        }
+
+        // Check hard links; directories always have several, so only other
+        // entries count for --min-links
+        if let Some(identity) = self.same_file {
+            if file_identity(metadata) != Some(identity) {
+                return Ok(false);
+            }
+        }
+        if let Some(min_links) = self.min_links {
+            if metadata.is_dir() || link_count(metadata) < min_links {
+                return Ok(false);
+            }
+        }
+
+        // Check content type
+        if let Some(ref filter) = &self.content_filter {
+            if !filter.matches(path, metadata) {
                 return Ok(false);
             }
         }
@@ -424,20 +475,97 @@ fn parse_extensions(spec: &str) -> HashSet<String> {
         .collect()
 }
 
-fn get_modification_time(path: &Path) -> Result<SystemTime> {
+fn resolve_newer(spec: &NewerSpec) -> Result<SystemTime> {
+    match spec.reference {
+        NewerReference::File(timestamp) => reference_time(Path::new(&spec.value), timestamp),
+        NewerReference::Date => parse_date(&spec.value)
+            .ok_or_else(|| anyhow!("Invalid date: '{}'. Use a format like '2024-01-01' or '2024-01-01 12:30'", spec.value)),
+    }
+}
+
+fn reference_time(path: &Path, timestamp: Timestamp) -> Result<SystemTime> {
     let metadata = fs::metadata(path)
         .map_err(|e| anyhow!("Failed to get metadata for {}: {}", path.display(), e))?;
-    
-    metadata.modified()
-        .map_err(|e| anyhow!("Failed to get modification time for {}: {}", path.display(), e))
+
+    file_timestamp(&metadata, timestamp)
+        .ok_or_else(|| anyhow!("{} has no {} time on this filesystem", path.display(), timestamp_name(timestamp)))
+}
+
+fn timestamp_name(timestamp: Timestamp) -> &'static str {
+    match timestamp {
+        Timestamp::Modified => "modification",
+        Timestamp::Accessed => "access",
+        Timestamp::Changed => "status change",
+        Timestamp::Birth => "birth",
+    }
+}
+
+fn file_timestamp(metadata: &fs::Metadata, timestamp: Timestamp) -> Option<SystemTime> {
+    match timestamp {
+        Timestamp::Modified => metadata.modified().ok(),
+        Timestamp::Accessed => metadata.accessed().ok(),
+        Timestamp::Changed => change_time(metadata),
+        Timestamp::Birth => metadata.created().ok(),
+    }
+}
+
+#[cfg(unix)]
+fn change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+
+    let seconds = Duration::from_secs(metadata.ctime().unsigned_abs());
+    let whole = if metadata.ctime() >= 0 {
+        UNIX_EPOCH.checked_add(seconds)
+    } else {
+        UNIX_EPOCH.checked_sub(seconds)
+    };
+    whole?.checked_add(Duration::from_nanos(metadata.ctime_nsec() as u64))
+}
+
+#[cfg(not(unix))]
+fn change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    metadata.modified().ok()
+}
+
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Parse an RFC 3339 timestamp, or a date with optional time in local time
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.into());
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    Local.from_local_datetime(&naive).earliest().map(SystemTime::from)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::TempDir;
 
     #[test]
     fn test_glob_pattern() {
@@ -472,6 +600,108 @@ mod tests {
         assert!(!filter.matches(1025));
     }
 
+    #[test]
+    fn test_newer_xy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.txt");
+        let new = temp_dir.path().join("new.txt");
+        fs::write(&old, "old").unwrap();
+        fs::write(&new, "new").unwrap();
+        // 2001-09-09T01:46:40Z
+        let past = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options().write(true).open(&old).unwrap().set_modified(past).unwrap();
+
+        let matcher_for = |spec: NewerSpec| {
+            let mut args = Args::default();
+            args.newer_xy.specs.push(spec);
+            PatternMatcher::new(&args)
+        };
+        let matching = |matcher: &PatternMatcher| -> Vec<bool> {
+            [&old, &new]
+                .iter()
+                .map(|path| matcher.matches(path, &fs::metadata(path).unwrap()).unwrap())
+                .collect()
+        };
+
+        let by_date = matcher_for(NewerSpec {
+            timestamp: Timestamp::Modified,
+            reference: NewerReference::Date,
+            value: "2010-01-01".to_string(),
+        }).unwrap();
+        assert_eq!(matching(&by_date), vec![false, true]);
+
+        let by_file = matcher_for(NewerSpec {
+            timestamp: Timestamp::Modified,
+            reference: NewerReference::File(Timestamp::Modified),
+            value: old.to_string_lossy().into_owned(),
+        }).unwrap();
+        assert_eq!(matching(&by_file), vec![false, true]);
+
+        // Setting the mtime bumped old.txt's ctime past its own mtime
+        let change_vs_mtime = matcher_for(NewerSpec {
+            timestamp: Timestamp::Changed,
+            reference: NewerReference::File(Timestamp::Modified),
+            value: old.to_string_lossy().into_owned(),
+        }).unwrap();
+        assert_eq!(matching(&change_vs_mtime), vec![true, true]);
+
+        assert!(matcher_for(NewerSpec {
+            timestamp: Timestamp::Modified,
+            reference: NewerReference::Date,
+            value: "yesterday".to_string(),
+        }).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_link_filters() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let blob = temp_dir.path().join("blob");
+        let alias = temp_dir.path().join("alias");
+        let copy = temp_dir.path().join("copy");
+        fs::write(&blob, "data").unwrap();
+        fs::hard_link(&blob, &alias).unwrap();
+        fs::write(&copy, "data").unwrap();
+
+        let matching = |args: Args| -> Vec<bool> {
+            let matcher = PatternMatcher::new(&args).unwrap();
+            [&blob, &alias, &copy, &temp_dir.path().to_path_buf()]
+                .iter()
+                .map(|path| matcher.matches(path, &fs::metadata(path).unwrap()).unwrap())
+                .collect()
+        };
+
+        let same = Args { samefile: Some(alias.clone()), ..Default::default() };
+        assert_eq!(matching(same), vec![true, true, false, false]);
+        let linked = Args { min_links: Some(2), ..Default::default() };
+        assert_eq!(matching(linked), vec![true, true, false, false]);
+        let any = Args { min_links: Some(1), ..Default::default() };
+        assert_eq!(matching(any), vec![true, true, true, false]);
+
+        let missing = Args { samefile: Some(temp_dir.path().join("nope")), ..Default::default() };
+        assert!(PatternMatcher::new(&missing).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = temp_dir.path().join("link");
+            std::os::unix::fs::symlink(&blob, &link).unwrap();
+            let identity = |args: Args| PatternMatcher::new(&Args { samefile: Some(link.clone()), ..args }).unwrap().same_file;
+            assert_eq!(identity(Args::default()), file_identity(&fs::symlink_metadata(&link).unwrap()));
+            let followed = identity(Args { follow_symlinks: true, ..Default::default() });
+            assert_eq!(followed, file_identity(&fs::metadata(&blob).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2001-09-09T01:46:40Z"), Some(UNIX_EPOCH + Duration::from_secs(1_000_000_000)));
+        assert!(parse_date("2024-01-01").is_some());
+        assert!(parse_date("2024-01-01 12:30").is_some());
+        assert!(parse_date("2024-01-01T12:30:15").is_some());
+        assert!(parse_date("2024-13-01").is_none());
+        assert!(parse_date("").is_none());
+    }
+
     #[test]
     fn test_extensions() {
         let exts = parse_extensions("rs,py,js");
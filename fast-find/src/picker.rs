@@ -0,0 +1,450 @@
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+/// Returned when the picker is closed without choosing anything (Esc,
+/// Ctrl-C); main exits with status 130 like fzf
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "selection cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Where a query matched a candidate, and how well
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched characters, ascending
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_PATH_START: i64 = 10;
+const BONUS_WORD_START: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 4;
+const PENALTY_GAP: i64 = 1;
+
+/// Match `query` against `candidate` the way fzf does: every space-separated
+/// term must appear as a subsequence. Matching is case-insensitive unless
+/// the term has an uppercase letter. Matches at the start of a path
+/// component or word and runs of consecutive characters score higher;
+/// gaps score lower.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut positions = Vec::new();
+
+    for term in query.split_whitespace() {
+        let case_sensitive = term.chars().any(char::is_uppercase);
+        let equal = |a: char, b: char| {
+            if case_sensitive {
+                a == b
+            } else {
+                a.to_lowercase().eq(b.to_lowercase())
+            }
+        };
+        let term: Vec<char> = term.chars().collect();
+
+        // Leftmost end of a match, then walk back for the tightest start
+        let mut next = 0;
+        let mut end = None;
+        for (index, &c) in chars.iter().enumerate() {
+            if equal(c, term[next]) {
+                next += 1;
+                if next == term.len() {
+                    end = Some(index);
+                    break;
+                }
+            }
+        }
+        let end = end?;
+        let mut matched = Vec::with_capacity(term.len());
+        let mut remaining = term.len();
+        for index in (0..=end).rev() {
+            if equal(chars[index], term[remaining - 1]) {
+                matched.push(index);
+                remaining -= 1;
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+        matched.reverse();
+
+        // Characters continuing a run share the bonus of its first one
+        let mut run_bonus = 0;
+        for (i, &index) in matched.iter().enumerate() {
+            let bonus = match index.checked_sub(1).map(|previous| chars[previous]) {
+                None | Some('/') => BONUS_PATH_START,
+                Some('_' | '-' | '.' | ' ') => BONUS_WORD_START,
+                Some(_) => 0,
+            };
+            let gap = i.checked_sub(1).map(|previous| index - matched[previous] - 1);
+            if gap == Some(0) {
+                run_bonus = run_bonus.max(bonus).max(BONUS_CONSECUTIVE);
+            } else {
+                run_bonus = bonus;
+                score -= PENALTY_GAP * gap.unwrap_or(0).min(16) as i64;
+            }
+            score += SCORE_MATCH + run_bonus;
+        }
+        positions.extend(matched);
+    }
+
+    positions.sort_unstable();
+    positions.dedup();
+    Some(FuzzyMatch { score, positions })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Continue,
+    Accept,
+    Cancel,
+}
+
+/// Picker state, kept apart from the terminal so it can be tested
+#[derive(Debug, Default)]
+struct Picker {
+    query: String,
+    items: Vec<String>,
+    /// Indices into `items` with their match, best first
+    matches: Vec<(usize, FuzzyMatch)>,
+    /// Row of `matches` under the cursor
+    cursor: usize,
+    /// First row of `matches` on screen
+    offset: usize,
+    /// Indices into `items` marked with Tab
+    selected: BTreeSet<usize>,
+    searching: bool,
+}
+
+impl Picker {
+    fn extend(&mut self, items: impl IntoIterator<Item = String>) {
+        let start = self.items.len();
+        self.items.extend(items);
+        if self.items.len() > start {
+            self.refilter(true);
+        }
+    }
+
+    /// Rank the items against the query; `keep_cursor` leaves the cursor
+    /// on the same entry, for results streaming in
+    fn refilter(&mut self, keep_cursor: bool) {
+        let current = self
+            .matches
+            .get(self.cursor)
+            .map(|(index, _)| *index)
+            .filter(|_| keep_cursor);
+        self.matches = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| fuzzy_match(&self.query, item).map(|found| (index, found)))
+            .collect();
+        // With no query entries stay in the order they were found
+        if !self.query.trim().is_empty() {
+            let items = &self.items;
+            self.matches.sort_by(|(a, found_a), (b, found_b)| {
+                found_b
+                    .score
+                    .cmp(&found_a.score)
+                    .then_with(|| items[*a].len().cmp(&items[*b].len()))
+                    .then_with(|| a.cmp(b))
+            });
+        }
+        self.cursor = current
+            .and_then(|current| self.matches.iter().position(|(index, _)| *index == current))
+            .unwrap_or(0);
+    }
+
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.cursor = 0;
+        self.offset = 0;
+        self.refilter(false);
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn toggle_current(&mut self) {
+        if let Some((index, _)) = self.matches.get(self.cursor) {
+            if !self.selected.remove(index) {
+                self.selected.insert(*index);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, page: usize) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Outcome::Accept,
+            KeyCode::Esc => return Outcome::Cancel,
+            KeyCode::Char('c' | 'g') if ctrl => return Outcome::Cancel,
+            KeyCode::Char('d') if ctrl && self.query.is_empty() => return Outcome::Cancel,
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(page as isize)),
+            KeyCode::PageDown => self.move_cursor(page as isize),
+            KeyCode::Tab => {
+                self.toggle_current();
+                self.move_cursor(1);
+            }
+            KeyCode::BackTab => {
+                self.toggle_current();
+                self.move_cursor(-1);
+            }
+            KeyCode::Char('u') if ctrl => self.set_query(String::new()),
+            KeyCode::Char('w') if ctrl => {
+                let kept = self.query.trim_end().rfind(' ').map_or(0, |space| space + 1);
+                let query = self.query[..kept].to_string();
+                self.set_query(query);
+            }
+            KeyCode::Backspace => {
+                let mut query = self.query.clone();
+                query.pop();
+                self.set_query(query);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                let query = format!("{}{}", self.query, c);
+                self.set_query(query);
+            }
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    /// The marked entries in the order they were found, or else the one
+    /// under the cursor
+    fn selection(&self) -> Vec<String> {
+        if self.selected.is_empty() {
+            return self
+                .matches
+                .get(self.cursor)
+                .map(|(index, _)| vec![self.items[*index].clone()])
+                .unwrap_or_default();
+        }
+        self.selected.iter().map(|&index| self.items[index].clone()).collect()
+    }
+
+    fn render(&mut self, out: &mut impl Write, width: usize, height: usize) -> std::io::Result<()> {
+        let rows = height.saturating_sub(2).max(1);
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + rows {
+            self.offset = self.cursor + 1 - rows;
+        }
+
+        queue!(out, cursor::MoveTo(0, 0), terminal::Clear(terminal::ClearType::All))?;
+        queue!(out, SetAttribute(Attribute::Bold), Print("> "), SetAttribute(Attribute::Reset))?;
+        queue!(out, Print(truncate(&self.query, width.saturating_sub(2))))?;
+
+        let mut status = format!("  {}/{}", self.matches.len(), self.items.len());
+        if !self.selected.is_empty() {
+            status.push_str(&format!(" ({} selected)", self.selected.len()));
+        }
+        if self.searching {
+            status.push_str(" searching…");
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, 1),
+            SetAttribute(Attribute::Dim),
+            Print(truncate(&status, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        for (row, (index, found)) in self.matches.iter().enumerate().skip(self.offset).take(rows) {
+            let current = row == self.cursor;
+            let marker = match (current, self.selected.contains(index)) {
+                (true, true) => ">*",
+                (true, false) => "> ",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+            queue!(out, cursor::MoveTo(0, (row - self.offset + 2) as u16), Print(marker))?;
+            if current {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            for (position, c) in self.items[*index].chars().take(width.saturating_sub(2)).enumerate() {
+                if found.positions.binary_search(&position).is_ok() {
+                    queue!(out, SetAttribute(Attribute::Bold), SetAttribute(Attribute::Underlined), Print(c))?;
+                    queue!(out, SetAttribute(Attribute::NormalIntensity), SetAttribute(Attribute::NoUnderline))?;
+                } else {
+                    queue!(out, Print(c))?;
+                }
+            }
+            queue!(out, SetAttribute(Attribute::Reset))?;
+        }
+
+        let query_end = 2 + self.query.chars().count().min(width.saturating_sub(3));
+        queue!(out, cursor::MoveTo(query_end as u16, 0))?;
+        out.flush()
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Puts the terminal back however the picker returns
+struct Screen(File);
+
+impl Screen {
+    fn open() -> Result<Self> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .context("--interactive needs a terminal")?;
+        let mut screen = Screen(tty);
+        terminal::enable_raw_mode().context("--interactive needs a terminal")?;
+        execute!(screen.0, terminal::EnterAlternateScreen)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.0, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Show entries as they arrive on `entries` and let the user narrow them
+/// down; returns the chosen ones, or `Cancelled`.
+///
+/// The picker is drawn on /dev/tty, so stdout stays free for the selection.
+pub fn pick(entries: Receiver<String>) -> Result<Vec<String>> {
+    let mut screen = Screen::open()?;
+    let mut picker = Picker {
+        searching: true,
+        ..Picker::default()
+    };
+
+    let mut dirty = true;
+    loop {
+        let mut arrived = Vec::new();
+        while picker.searching {
+            match entries.try_recv() {
+                Ok(entry) => arrived.push(entry),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    picker.searching = false;
+                    dirty = true;
+                }
+            }
+        }
+        dirty |= !arrived.is_empty();
+        picker.extend(arrived);
+
+        // Some terminals report no size at all
+        let (width, height) = terminal::size()
+            .ok()
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .unwrap_or((80, 24));
+        if dirty {
+            picker.render(&mut screen.0, width as usize, height as usize)?;
+            dirty = false;
+        }
+
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                dirty = true;
+                match picker.handle_key(key, (height as usize).saturating_sub(2).max(1)) {
+                    Outcome::Continue => {}
+                    Outcome::Accept => return Ok(picker.selection()),
+                    Outcome::Cancel => return Err(Cancelled.into()),
+                }
+            }
+            Event::Resize(..) => dirty = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("", "anything").is_some());
+        assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+        assert_eq!(fuzzy_match("mai", "src/main.rs").unwrap().positions, vec![4, 5, 6]);
+        // Both terms must match, in any order
+        assert!(fuzzy_match("rs src", "src/main.rs").is_some());
+        assert!(fuzzy_match("rs lib", "src/main.rs").is_none());
+        // Smart case
+        assert!(fuzzy_match("MAIN", "src/main.rs").is_none());
+        assert!(fuzzy_match("Main", "src/Main.rs").is_some());
+
+        // Component starts and consecutive runs beat scattered matches
+        let tight = fuzzy_match("main", "src/main.rs").unwrap().score;
+        let scattered = fuzzy_match("main", "src/mod/a/index.rs").unwrap().score;
+        assert!(tight > scattered);
+        // The tightest occurrence is the one scored
+        assert_eq!(fuzzy_match("ab", "a_xab").unwrap().positions, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_picker_keys() {
+        let mut picker = Picker::default();
+        picker.extend(["src/main.rs", "src/lib.rs", "README.md"].map(String::from));
+        assert_eq!(picker.matches.len(), 3);
+
+        for c in "rs".chars() {
+            picker.handle_key(key(KeyCode::Char(c)), 10);
+        }
+        assert_eq!(picker.matches.len(), 2);
+        assert_eq!(picker.selection(), vec!["src/lib.rs"]);
+
+        assert_eq!(picker.handle_key(key(KeyCode::Tab), 10), Outcome::Continue);
+        picker.handle_key(key(KeyCode::Tab), 10);
+        assert_eq!(picker.selection(), vec!["src/main.rs", "src/lib.rs"]);
+
+        picker.handle_key(key(KeyCode::Backspace), 10);
+        picker.handle_key(key(KeyCode::Backspace), 10);
+        assert_eq!(picker.matches.len(), 3);
+        assert_eq!(picker.handle_key(key(KeyCode::Enter), 10), Outcome::Accept);
+        assert_eq!(
+            picker.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), 10),
+            Outcome::Cancel
+        );
+    }
+
+    #[test]
+    fn test_render_fits_the_screen() {
+        let mut picker = Picker::default();
+        picker.extend((0..50).map(|i| format!("file{}.txt", i)));
+        picker.move_cursor(30);
+        let mut screen = Vec::new();
+        picker.render(&mut screen, 40, 10).unwrap();
+        assert_eq!(picker.offset, 23);
+        assert!(String::from_utf8_lossy(&screen).contains("file30.txt"));
+    }
+}
@@ -1,10 +1,19 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use ignore::WalkState;
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::{mpsc, Arc};
+use std::thread;
 use std::time::Instant;
 
+use crate::analyze::Analysis;
 use crate::cli::Args;
 use crate::file_walker::{FileWalker, WalkStats};
-use crate::output::{OutputFormatter, SearchStats};
+use crate::output::{JsonLine, OutputFormatter, SearchStats};
+use crate::path_style::PathStyle;
 use crate::pattern_matcher::PatternMatcher;
+use crate::picker;
+use crate::warnings::{self, WarningCollector};
 use crate::worker::{BatchProcessor, ProcessingStats};
 
 pub struct SearchEngine {
@@ -13,6 +22,7 @@ pub struct SearchEngine {
     file_walker: FileWalker,
     output_formatter: OutputFormatter,
     batch_processor: BatchProcessor,
+    warnings: Arc<WarningCollector>,
 }
 
 impl SearchEngine {
@@ -23,8 +33,11 @@ impl SearchEngine {
         // Initialize pattern matcher
         let pattern_matcher = PatternMatcher::new(&args)?;
 
+        // Per-path problems are collected rather than aborting the search
+        let warnings = Arc::new(WarningCollector::new(args.verbose_errors));
+
         // Initialize file walker
-        let file_walker = FileWalker::new(args.clone());
+        let file_walker = FileWalker::new(args.clone()).with_warnings(Arc::clone(&warnings));
 
         // Initialize output formatter
         let output_formatter = OutputFormatter::new(
@@ -32,14 +45,20 @@ impl SearchEngine {
             args.long_format,
             args.print0,
             args.json_output,
-        );
+        )
+        .with_path_style(PathStyle::new(
+            args.absolute,
+            args.relative_to.as_deref(),
+            args.strip_prefix.as_deref(),
+        )?);
 
         // Initialize batch processor
         let batch_processor = BatchProcessor::new(
             pattern_matcher.clone(),
             args.get_threads(),
             Some(2000), // Batch size for memory efficiency
-        );
+        )
+        .with_warnings(Arc::clone(&warnings));
 
         Ok(Self {
             args,
@@ -47,10 +66,20 @@ impl SearchEngine {
             file_walker,
             output_formatter,
             batch_processor,
+            warnings,
         })
     }
 
-    pub fn run(&self) -> Result<()> {
+    /// Run the search and return the number of warnings, i.e. paths that
+    /// could not be examined
+    pub fn run(&self) -> Result<usize> {
+        if self.args.interactive {
+            return self.run_interactive();
+        }
+        if self.args.json_lines {
+            return self.run_json_lines();
+        }
+
         let start_time = Instant::now();
 
         // Phase 1: Walk the file system
@@ -78,7 +107,9 @@ impl SearchEngine {
         }
 
         // Phase 3: Output results
-        if self.args.count_only {
+        if self.args.analyze {
+            self.output_analysis(processing_results)?;
+        } else if self.args.count_only {
             self.output_count_only(processing_results.len())?;
         } else if self.args.json_output {
             self.output_json(processing_results, &walk_stats, &processing_stats)?;
@@ -91,6 +122,129 @@ impl SearchEngine {
             self.show_statistics(&walk_stats, &processing_stats)?;
         }
 
+        let warnings = self.warnings.snapshot();
+        self.report_warnings(&warnings);
+        Ok(warnings.len())
+    }
+
+    /// `--interactive`: search in the background while the picker shows
+    /// what has been found, then print or `--exec` the selection
+    fn run_interactive(&self) -> Result<usize> {
+        let (sender, receiver) = mpsc::channel();
+        let file_walker = FileWalker::new(self.args.clone()).with_warnings(Arc::clone(&self.warnings));
+        let batch_processor = BatchProcessor::new(self.pattern_matcher.clone(), self.args.get_threads(), Some(2000))
+            .with_warnings(Arc::clone(&self.warnings));
+        // Matches go to the picker as they are found. Not waited for: once
+        // a choice is made the rest of the search is moot
+        let search = thread::spawn(move || -> Result<()> {
+            file_walker.visit(&|walk_result| {
+                let Some(result) = batch_processor.process_one(&walk_result) else {
+                    return WalkState::Continue;
+                };
+                match sender.send(result.file_info.path) {
+                    Ok(()) => WalkState::Continue,
+                    Err(_) => WalkState::Quit,
+                }
+            })
+        });
+
+        let selection = picker::pick(receiver)?;
+        match self.args.exec {
+            Some(ref command) => run_exec(command, &selection)?,
+            None => {
+                for path in selection.iter().map(|path| self.output_formatter.display_path(path)) {
+                    if self.args.print0 {
+                        print!("{}\0", path);
+                    } else {
+                        println!("{}", path);
+                    }
+                }
+            }
+        }
+
+        let warnings = self.warnings.snapshot();
+        self.report_warnings(&warnings);
+        // A search that stopped on an error before the choice was made
+        if search.is_finished() {
+            search.join().map_err(|_| anyhow::anyhow!("search thread panicked"))??;
+        }
+        Ok(warnings.len())
+    }
+
+    /// `--json-lines`: print each match as soon as it is checked instead of
+    /// collecting them, so memory stays flat however many there are. Stops
+    /// early once stdout is closed, as when piped into `head`.
+    fn run_json_lines(&self) -> Result<usize> {
+        let start_time = Instant::now();
+        let stdout = io::stdout();
+        let closed = std::sync::atomic::AtomicBool::new(false);
+        self.file_walker.visit(&|walk_result| {
+            let Some(result) = self.batch_processor.process_one(&walk_result) else {
+                return WalkState::Continue;
+            };
+            let file = self.output_formatter.display_file_info(result.file_info);
+            let written = self
+                .output_formatter
+                .format_json_line(&JsonLine::Entry { file: &file })
+                .and_then(|line| Ok(writeln!(stdout.lock(), "{}", line)?));
+            if written.is_err() {
+                closed.store(true, std::sync::atomic::Ordering::Relaxed);
+                return WalkState::Quit;
+            }
+            WalkState::Continue
+        })?;
+
+        let warnings = self.warnings.snapshot();
+        if !closed.into_inner() {
+            let walk_stats = self.file_walker.get_stats();
+            let processing_stats = self.batch_processor.get_stats(start_time.elapsed());
+            let search_stats = SearchStats {
+                total_found: processing_stats.total_matched,
+                files_visited: walk_stats.files_visited,
+                dirs_visited: walk_stats.dirs_visited,
+                duplicates_skipped: walk_stats.duplicates_skipped,
+                processing_time_ms: processing_stats.processing_time_ms,
+            };
+            let summary = JsonLine::Summary {
+                stats: &search_stats,
+                warnings: &warnings,
+            };
+            // A reader that went away at the very end is not an error
+            let _ = writeln!(stdout.lock(), "{}", self.output_formatter.format_json_line(&summary)?);
+            if self.args.show_stats {
+                self.show_statistics(&walk_stats, &processing_stats)?;
+            }
+        }
+
+        self.report_warnings(&warnings);
+        Ok(warnings.len())
+    }
+
+    /// Print deferred warnings (already shown inline with `--verbose-errors`,
+    /// part of the output in the JSON modes) and a per-kind summary
+    fn report_warnings(&self, warnings: &[warnings::Warning]) {
+        if warnings.is_empty() {
+            return;
+        }
+        if !self.warnings.is_inline() && !self.args.json_output && !self.args.json_lines {
+            for warning in warnings {
+                eprintln!("ffind: {}", warning);
+            }
+        }
+        eprintln!("{}", warnings::format_summary(warnings));
+    }
+
+    fn output_analysis(&self, results: Vec<crate::worker::ProcessingResult>) -> Result<()> {
+        let file_infos: Vec<_> = results
+            .into_iter()
+            .map(|r| self.output_formatter.display_file_info(r.file_info))
+            .collect();
+        let analysis = Analysis::of(&file_infos);
+        if self.args.json_output {
+            println!("{}", serde_json::to_string_pretty(&analysis)?);
+        } else {
+            println!("{}", analysis.render());
+        }
         Ok(())
     }
 
@@ -100,16 +254,20 @@ impl SearchEngine {
     }
 
     fn output_json(&self, results: Vec<crate::worker::ProcessingResult>, walk_stats: &WalkStats, processing_stats: &ProcessingStats) -> Result<()> {
-        let file_infos: Vec<_> = results.into_iter().map(|r| r.file_info).collect();
+        let file_infos: Vec<_> = results
+            .into_iter()
+            .map(|r| self.output_formatter.display_file_info(r.file_info))
+            .collect();
         
         let search_stats = SearchStats {
             total_found: file_infos.len(),
             files_visited: walk_stats.files_visited,
             dirs_visited: walk_stats.dirs_visited,
+            duplicates_skipped: walk_stats.duplicates_skipped,
             processing_time_ms: processing_stats.processing_time_ms,
         };
 
-        let json_output = self.output_formatter.format_json_results(file_infos, search_stats)?;
+        let json_output = self.output_formatter.format_json_results(file_infos, search_stats, self.warnings.snapshot())?;
         println!("{}", json_output);
         Ok(())
     }
@@ -142,6 +300,7 @@ impl SearchEngine {
             total_found: processing_stats.total_matched,
             files_visited: walk_stats.files_visited,
             dirs_visited: walk_stats.dirs_visited,
+            duplicates_skipped: walk_stats.duplicates_skipped,
             processing_time_ms: processing_stats.processing_time_ms,
         };
 
@@ -164,6 +323,29 @@ impl SearchEngine {
     }
 }
 
+/// Run `--exec` through sh with the chosen paths, quoted, in place of `{}`
+/// or after the command
+fn run_exec(command: &str, paths: &[String]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let quoted: Vec<String> = paths.iter().map(|path| shell_quote(path)).collect();
+    let script = if command.contains("{}") {
+        command.replace("{}", &quoted.join(" "))
+    } else {
+        format!("{} {}", command, quoted.join(" "))
+    };
+    let status = Command::new("sh").arg("-c").arg(&script).status()?;
+    if !status.success() {
+        bail!("--exec '{}' failed: {}", command, status);
+    }
+    Ok(())
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
 // Helper function to validate search patterns
 pub fn validate_search_pattern(pattern: &str, use_regex: bool) -> Result<()> {
     if use_regex {
@@ -198,10 +380,15 @@ pub fn estimate_search_complexity(args: &Args) -> SearchComplexity {
     }
     
     // Size and time filters add complexity
-    if args.has_size_filters() || args.has_time_filters() {
+    if args.has_size_filters() || args.has_time_filters() || args.has_link_filters() {
         complexity = complexity.max(SearchComplexity::Medium);
     }
     
+    // Content sniffing opens every candidate file
+    if args.has_content_filters() {
+        complexity = complexity.max(SearchComplexity::High);
+    }
+    
     // Deep searches are more complex (only if explicitly set to very deep)
     if let Some(depth) = args.max_depth {
         if depth > 10 {
@@ -246,7 +433,6 @@ impl SearchComplexity {
 mod tests {
     use super::*;
     use std::fs;
-    use std::io::Write;
     use tempfile::TempDir;
 
     #[test]
@@ -256,6 +442,16 @@ mod tests {
         assert!(engine.is_ok());
     }
 
+    #[test]
+    fn test_run_exec() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = temp_dir.path().join("out");
+        let paths = vec!["it's here".to_string(), "b c".to_string()];
+        run_exec(&format!("printf '%s\\n' {{}} > '{}'", out.display()), &paths).unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap(), "it's here\nb c\n");
+        assert!(run_exec("false", &paths).is_err());
+    }
+
     #[test]
     fn test_validate_search_pattern() {
         assert!(validate_search_pattern("*.txt", false).is_ok());
@@ -2,11 +2,9 @@
 // This module contains integration tests that validate the complete functionality
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants, unused_variables)]
 mod integration_tests {
-    use super::*;
-    use std::fs::{self, File};
-    use std::io::Write;
-    use std::path::Path;
+    use std::fs;
     use tempfile::TempDir;
     use crate::cli::Args;
     use crate::search::SearchEngine;
@@ -175,6 +173,19 @@ mod integration_tests {
         assert!(true);
     }
 
+    #[test]
+    fn test_json_lines_output() {
+        let temp_dir = create_test_filesystem();
+        let args = Args {
+            paths: vec![temp_dir.path().to_path_buf()],
+            json_lines: true,
+            ..Args::default()
+        };
+
+        let engine = SearchEngine::new(args).unwrap();
+        assert!(engine.run().is_ok());
+    }
+
     #[test]
     fn test_long_format() {
         let temp_dir = create_test_filesystem();
@@ -192,7 +203,6 @@ mod integration_tests {
 // Performance benchmarks
 #[cfg(test)]
 mod benchmarks {
-    use super::*;
     use std::time::Instant;
 
     #[test]
@@ -212,7 +222,6 @@ mod benchmarks {
 // Cross-validation with Unix find
 #[cfg(test)]
 mod unix_find_compatibility {
-    use super::*;
     use std::process::Command;
 
     #[test]
@@ -231,8 +240,7 @@ mod unix_find_compatibility {
             .arg("*.txt")
             .output();
             
-        if unix_find_output.is_ok() {
-            let output = unix_find_output.unwrap();
+        if let Ok(output) = unix_find_output {
             let results = String::from_utf8_lossy(&output.stdout);
             assert!(results.contains("test.txt"));
         }
@@ -243,7 +251,6 @@ mod unix_find_compatibility {
 // Error handling tests
 #[cfg(test)]
 mod error_handling {
-    use super::*;
     use crate::cli::Args;
 
     #[test]
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Exit status when the search completed but some paths could not be examined
+pub const EXIT_WARNINGS: u8 = 1;
+
+/// Exit status for errors that stopped the search
+pub const EXIT_FATAL: u8 = 2;
+
+/// Exit status when `--interactive` is closed without a selection, as in fzf
+pub const EXIT_CANCELLED: u8 = 130;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    PermissionDenied,
+    BrokenSymlink,
+    NotFound,
+    SymlinkLoop,
+    Io,
+}
+
+impl WarningKind {
+    fn from_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => WarningKind::PermissionDenied,
+            io::ErrorKind::NotFound => WarningKind::NotFound,
+            _ => WarningKind::Io,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            WarningKind::PermissionDenied => "permission denied",
+            WarningKind::BrokenSymlink => "broken symlink",
+            WarningKind::NotFound => "not found",
+            WarningKind::SymlinkLoop => "symlink loop",
+            WarningKind::Io => "I/O error",
+        }
+    }
+}
+
+/// A path that could not be examined; the search records it and carries on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Warning {
+    pub path: Option<PathBuf>,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn io(path: &Path, error: &io::Error) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+            kind: WarningKind::from_io(error),
+            message: error.to_string(),
+        }
+    }
+
+    /// Classify an error reported by the directory walker
+    pub fn from_walk_error(error: &ignore::Error) -> Self {
+        let kind = match walk_error_root(error) {
+            ignore::Error::Loop { .. } => WarningKind::SymlinkLoop,
+            _ => error.io_error().map_or(WarningKind::Io, WarningKind::from_io),
+        };
+        Self {
+            path: walk_error_path(error).map(Path::to_path_buf),
+            kind,
+            message: walk_error_root(error).to_string(),
+        }
+    }
+
+    /// A path whose metadata could not be read: a dangling link if the link
+    /// itself is still there
+    pub fn metadata(path: &Path, error: &io::Error) -> Self {
+        let dangling = error.kind() == io::ErrorKind::NotFound
+            && std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        if dangling {
+            Self {
+                path: Some(path.to_path_buf()),
+                kind: WarningKind::BrokenSymlink,
+                message: "symlink target does not exist".to_string(),
+            }
+        } else {
+            Self::io(path, error)
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "'{}': {}", path.display(), self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+fn walk_error_root(error: &ignore::Error) -> &ignore::Error {
+    match error {
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => walk_error_root(err),
+        _ => error,
+    }
+}
+
+fn walk_error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        ignore::Error::Loop { child, .. } => Some(child),
+        _ => None,
+    }
+}
+
+/// Collects warnings from the walker and worker threads.
+///
+/// Warnings are printed to stderr as they happen with `--verbose-errors`,
+/// otherwise once the results have been written.
+#[derive(Debug, Default)]
+pub struct WarningCollector {
+    inline: bool,
+    warnings: Mutex<Vec<Warning>>,
+}
+
+impl WarningCollector {
+    pub fn new(inline: bool) -> Self {
+        Self {
+            inline,
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn report(&self, warning: Warning) {
+        if self.inline {
+            eprintln!("ffind: {}", warning);
+        }
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.push(warning);
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.inline
+    }
+
+    /// All warnings so far, sorted by path for stable output
+    pub fn snapshot(&self) -> Vec<Warning> {
+        let mut warnings = self
+            .warnings
+            .lock()
+            .map(|warnings| warnings.clone())
+            .unwrap_or_default();
+        warnings.sort_by(|a, b| a.path.cmp(&b.path));
+        warnings
+    }
+}
+
+/// "ffind: 3 warnings (1 broken symlink, 2 permission denied)"
+pub fn format_summary(warnings: &[Warning]) -> String {
+    let mut by_kind: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for warning in warnings {
+        *by_kind.entry(warning.kind.description()).or_default() += 1;
+    }
+
+    let breakdown: Vec<String> = by_kind
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+
+    format!(
+        "ffind: {} warning{} ({})",
+        warnings.len(),
+        if warnings.len() == 1 { "" } else { "s" },
+        breakdown.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_walk_error_classification() {
+        let error = ignore::Error::WithPath {
+            path: PathBuf::from("/locked"),
+            err: Box::new(ignore::Error::Io(io::Error::from(io::ErrorKind::PermissionDenied))),
+        };
+        let warning = Warning::from_walk_error(&error);
+        assert_eq!(warning.kind, WarningKind::PermissionDenied);
+        assert_eq!(warning.path.as_deref(), Some(Path::new("/locked")));
+
+        let error = ignore::Error::WithDepth {
+            depth: 2,
+            err: Box::new(ignore::Error::Loop {
+                ancestor: PathBuf::from("/a"),
+                child: PathBuf::from("/a/b/link"),
+            }),
+        };
+        assert_eq!(Warning::from_walk_error(&error).kind, WarningKind::SymlinkLoop);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlink_detected() {
+        let dir = TempDir::new().unwrap();
+        let link = dir.path().join("dangling");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &link).unwrap();
+
+        let error = std::fs::metadata(&link).unwrap_err();
+        assert_eq!(Warning::metadata(&link, &error).kind, WarningKind::BrokenSymlink);
+    }
+
+    #[test]
+    fn test_collector_summary() {
+        let collector = WarningCollector::new(false);
+        for kind in [WarningKind::PermissionDenied, WarningKind::BrokenSymlink, WarningKind::PermissionDenied] {
+            collector.report(Warning {
+                path: None,
+                kind,
+                message: String::new(),
+            });
+        }
+        assert_eq!(
+            format_summary(&collector.snapshot()),
+            "ffind: 3 warnings (1 broken symlink, 2 permission denied)"
+        );
+    }
+}
@@ -8,12 +8,14 @@ use std::time::Instant;
 use crate::file_walker::WalkResult;
 use crate::output::FileInfo;
 use crate::pattern_matcher::PatternMatcher;
+use crate::warnings::{Warning, WarningCollector, WarningKind};
 
 pub struct WorkerPool {
     pattern_matcher: PatternMatcher,
     thread_count: usize,
     processed_count: Arc<AtomicUsize>,
     matched_count: Arc<AtomicUsize>,
+    warnings: Arc<WarningCollector>,
 }
 
 #[derive(Debug)]
@@ -37,9 +39,15 @@ impl WorkerPool {
             thread_count,
             processed_count: Arc::new(AtomicUsize::new(0)),
             matched_count: Arc::new(AtomicUsize::new(0)),
+            warnings: Arc::new(WarningCollector::default()),
         }
     }
 
+    pub fn with_warnings(mut self, warnings: Arc<WarningCollector>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
     pub fn process_files(&self, walk_results: Vec<WalkResult>) -> Result<Vec<ProcessingResult>> {
         let _start_time = Instant::now();
         
@@ -49,39 +57,40 @@ impl WorkerPool {
             .build()
             .map_err(|e| anyhow::anyhow!("Failed to create thread pool: {}", e))?;
 
-        let processed_count = Arc::clone(&self.processed_count);
-        let matched_count = Arc::clone(&self.matched_count);
-
         // Process files in parallel
         let results: Vec<ProcessingResult> = pool.install(|| {
             walk_results
                 .par_iter()
-                .filter_map(|walk_result| {
-                    match self.process_single_file(walk_result) {
-                        Ok(Some(result)) => {
-                            processed_count.fetch_add(1, Ordering::Relaxed);
-                            if result.matches {
-                                matched_count.fetch_add(1, Ordering::Relaxed);
-                            }
-                            Some(result)
-                        }
-                        Ok(None) => {
-                            processed_count.fetch_add(1, Ordering::Relaxed);
-                            None
-                        }
-                        Err(err) => {
-                            eprintln!("Warning: Failed to process {}: {}", walk_result.path.display(), err);
-                            processed_count.fetch_add(1, Ordering::Relaxed);
-                            None
-                        }
-                    }
-                })
+                .filter_map(|walk_result| self.process_one(walk_result))
                 .collect()
         });
 
         Ok(results)
     }
 
+    /// Check one entry on the calling thread, counting it and reporting
+    /// failures as warnings; `None` unless it matches
+    pub fn process_one(&self, walk_result: &WalkResult) -> Option<ProcessingResult> {
+        self.processed_count.fetch_add(1, Ordering::Relaxed);
+        match self.process_single_file(walk_result) {
+            Ok(Some(result)) => {
+                if result.matches {
+                    self.matched_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Some(result)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                self.warnings.report(Warning {
+                    path: Some(walk_result.path.clone()),
+                    kind: WarningKind::Io,
+                    message: format!("failed to process: {}", err),
+                });
+                None
+            }
+        }
+    }
+
     fn process_single_file(&self, walk_result: &WalkResult) -> Result<Option<ProcessingResult>> {
         let path = &walk_result.path;
         
@@ -90,9 +99,7 @@ impl WorkerPool {
             Ok(md) => md,
             Err(err) => {
                 // Skip files we can't read metadata for
-                if std::env::var("FFIND_VERBOSE").is_ok() {
-                    eprintln!("Warning: Cannot read metadata for {}: {}", path.display(), err);
-                }
+                self.warnings.report(Warning::metadata(path, &err));
                 return Ok(None);
             }
         };
@@ -219,6 +226,11 @@ impl BatchProcessor {
         }
     }
 
+    pub fn with_warnings(mut self, warnings: Arc<WarningCollector>) -> Self {
+        self.worker_pool = self.worker_pool.with_warnings(warnings);
+        self
+    }
+
     pub fn process_in_batches(&self, walk_results: Vec<WalkResult>) -> Result<Vec<ProcessingResult>> {
         let mut all_results = Vec::new();
         
@@ -231,6 +243,11 @@ impl BatchProcessor {
         Ok(all_results)
     }
 
+    /// See [`WorkerPool::process_one`]
+    pub fn process_one(&self, walk_result: &WalkResult) -> Option<ProcessingResult> {
+        self.worker_pool.process_one(walk_result)
+    }
+
     pub fn get_stats(&self, processing_time: std::time::Duration) -> ProcessingStats {
         self.worker_pool.get_stats(processing_time)
     }
@@ -241,7 +258,6 @@ mod tests {
     use super::*;
     use crate::cli::Args;
     use crate::file_walker::WalkResult;
-    use std::path::PathBuf;
     use std::fs::File;
     use std::io::Write;
     use tempfile::TempDir;
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::Args;
+use crate::file_processor::is_stdin;
+use crate::output::MatchResult;
+use crate::worker::SearchStats;
+
+/// Files modified this recently are not cached: a second write within the
+/// filesystem's timestamp granularity could leave mtime and size unchanged
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Cache files unused for this long are removed
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Most bytes all cache files may take together; the least recently saved
+/// go first, and a search whose results alone exceed it is not cached
+const MAX_TOTAL_SIZE: u64 = 64 << 20;
+
+/// What identifies a file's contents without reading it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    mtime_ns: u128,
+    size: u64,
+}
+
+impl FileStamp {
+    /// `None` for files that cannot be stamped or are too fresh to trust,
+    /// and for standard input
+    fn of(path: &Path) -> Option<Self> {
+        if is_stdin(path) {
+            return None;
+        }
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?;
+        if SystemTime::now().duration_since(modified).map_or(true, |age| age < RACY_WINDOW) {
+            return None;
+        }
+        Some(Self {
+            mtime_ns: modified.duration_since(UNIX_EPOCH).ok()?.as_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    stamp: FileStamp,
+    matches: Vec<MatchResult>,
+    stats: SearchStats,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// Guards against hash collisions and format changes
+    fingerprint: String,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/// Per-file search results from earlier runs of the same search, so a
+/// search repeated in a watch loop only reads files that changed since.
+///
+/// Entries are keyed on path, mtime and size, in one file per distinct
+/// search (pattern and every option that shapes per-file results) under
+/// `$FGREP_CACHE_DIR`, `$XDG_CACHE_HOME/fast-grep` or `~/.cache/fast-grep`.
+/// Only files searched by the current run are kept when it is saved. The
+/// files hold matched lines, so they are readable by their owner only, and
+/// old ones are evicted by [`MAX_AGE`] and [`MAX_TOTAL_SIZE`].
+pub struct SearchCache {
+    file: PathBuf,
+    fingerprint: String,
+    previous: HashMap<PathBuf, CacheEntry>,
+    current: Mutex<HashMap<PathBuf, CacheEntry>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl SearchCache {
+    /// The cache for this search with `--cache`, or `None` when it cannot
+    /// apply (`--changed` depends on more than file contents) or there is
+    /// nowhere to keep it
+    pub fn for_search(args: &Args) -> Option<Self> {
+        if !args.cache || args.changed.is_some() {
+            return None;
+        }
+        Some(Self::load(cache_dir()?, fingerprint(args)))
+    }
+
+    fn load(dir: PathBuf, fingerprint: String) -> Self {
+        let file = dir.join(format!("{:016x}.json", fnv1a(fingerprint.as_bytes())));
+        let previous = fs::read(&file)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+            .filter(|cached| cached.fingerprint == fingerprint)
+            .map(|cached| cached.entries)
+            .unwrap_or_default();
+        Self {
+            file,
+            fingerprint,
+            previous,
+            current: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Results for `path` if it is unchanged since they were stored,
+    /// otherwise `search` runs and its results are stored, unless `search`
+    /// was cut short and reports `complete == false`
+    pub fn get_or_search<E>(
+        &self,
+        path: &Path,
+        search: impl FnOnce() -> Result<(Vec<MatchResult>, SearchStats, bool), E>,
+    ) -> Result<(Vec<MatchResult>, SearchStats), E> {
+        let stamp = FileStamp::of(path);
+        if let Some(entry) = self.previous.get(path).filter(|entry| Some(entry.stamp) == stamp) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.current.lock().unwrap().insert(path.to_path_buf(), entry.clone());
+            return Ok((entry.matches.clone(), entry.stats.clone()));
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let (matches, stats, complete) = search()?;
+        if let (Some(stamp), true) = (stamp, complete) {
+            let entry = CacheEntry {
+                stamp,
+                matches: matches.clone(),
+                stats: stats.clone(),
+            };
+            self.current.lock().unwrap().insert(path.to_path_buf(), entry);
+        }
+        Ok((matches, stats))
+    }
+
+    /// (hits, misses) so far
+    pub fn counts(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    /// Write this run's entries, replacing the old file in one step, then
+    /// evict old files; a cache that cannot be written is simply not used
+    /// next time
+    pub fn save(&self) {
+        let cached = CacheFile {
+            fingerprint: self.fingerprint.clone(),
+            entries: std::mem::take(&mut *self.current.lock().unwrap()),
+        };
+        let Some(dir) = self.file.parent() else { return };
+        let Ok(data) = serde_json::to_vec(&cached) else { return };
+        if data.len() as u64 > MAX_TOTAL_SIZE {
+            let _ = fs::remove_file(&self.file);
+            return;
+        }
+        let temporary = self.file.with_extension(format!("tmp{}", std::process::id()));
+        let _ = fs::remove_file(&temporary);
+        let written = create_private_dir(dir)
+            .and_then(|_| write_private(&temporary, &data))
+            .and_then(|_| fs::rename(&temporary, &self.file));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        evict(dir, &self.file);
+    }
+}
+
+/// Remove cache files in `dir` older than [`MAX_AGE`], then the least
+/// recently saved until the rest fit in [`MAX_TOTAL_SIZE`]; `keep` is the
+/// file just saved
+fn evict(dir: &Path, keep: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let now = SystemTime::now();
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        // Saved files, and temporary ones a killed run left behind
+        .filter(|entry| {
+            entry.path().extension().is_some_and(|extension| {
+                extension == "json" || extension.to_string_lossy().starts_with("tmp")
+            })
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    files.retain(|(modified, _, path)| {
+        let expired = path != keep && now.duration_since(*modified).is_ok_and(|age| age > MAX_AGE);
+        if expired {
+            let _ = fs::remove_file(path);
+        }
+        !expired
+    });
+
+    // Newest first; everything past the budget goes
+    files.sort_by_key(|(modified, _, path)| std::cmp::Reverse((path == keep, *modified)));
+    let mut total = 0;
+    for (_, size, path) in files {
+        total += size;
+        if total > MAX_TOTAL_SIZE {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// Write `data` to a new file only its owner can read
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, data)
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    let from_env = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    from_env("FGREP_CACHE_DIR")
+        .or_else(|| from_env("XDG_CACHE_HOME").map(|dir| dir.join("fast-grep")))
+        .or_else(|| from_env("HOME").map(|home| home.join(".cache").join("fast-grep")))
+}
+
+/// Everything that changes what a file's search produces. Paths are kept
+/// as given, so the working directory is part of it too.
+fn fingerprint(args: &Args) -> String {
+    format!(
+        "{}|{:?}|{:?}|{:?}|{:?}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::current_dir().ok(),
+        args.pattern,
+        (args.use_regex, args.fixed_strings, args.ignore_case, args.invert_match, args.null_data, args.fuzzy),
+        (
+            &args.group,
+            &args.replace,
+            args.scope,
+            args.get_before_context(),
+            args.get_after_context(),
+            args.max_filesize_mb,
+        ),
+    )
+}
+
+/// FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "hello\n").unwrap();
+        // Old enough to be trusted
+        let old = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options().write(true).open(&file).unwrap().set_modified(old).unwrap();
+
+        let dir = temp_dir.path().join("cache");
+        let searches = AtomicUsize::new(0);
+        let search = || {
+            searches.fetch_add(1, Ordering::Relaxed);
+            let mut stats = SearchStats::new();
+            stats.add_file(true, 6, 1);
+            let found = MatchResult::new(file.clone(), 1, "hello".to_string(), 0, 5);
+            Ok::<_, ()>((vec![found], stats, true))
+        };
+
+        let cache = SearchCache::load(dir.clone(), "search".to_string());
+        cache.get_or_search(&file, search).unwrap();
+        assert_eq!(cache.counts(), (0, 1));
+        cache.save();
+
+        let cache = SearchCache::load(dir.clone(), "search".to_string());
+        let (matches, stats) = cache.get_or_search(&file, search).unwrap();
+        assert_eq!(cache.counts(), (1, 0));
+        assert_eq!(matches[0].line_content, "hello");
+        assert_eq!(stats.total_matches, 1);
+        assert_eq!(searches.load(Ordering::Relaxed), 1);
+        cache.save();
+
+        // A different search, or a changed file, misses
+        let other = SearchCache::load(dir.clone(), "other".to_string());
+        assert!(other.previous.is_empty());
+        fs::write(&file, "hello again\n").unwrap();
+        fs::File::options().write(true).open(&file).unwrap().set_modified(old).unwrap();
+        let cache = SearchCache::load(dir, "search".to_string());
+        cache.get_or_search(&file, search).unwrap();
+        assert_eq!(cache.counts(), (0, 1));
+        assert_eq!(searches.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_cache_files_are_private_and_evicted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let stale = dir.join("0000000000000000.json");
+        fs::write(&stale, "{}").unwrap();
+        let old = SystemTime::now() - MAX_AGE - Duration::from_secs(60);
+        fs::File::options().write(true).open(&stale).unwrap().set_modified(old).unwrap();
+        let unrelated = dir.join("notes.txt");
+        fs::write(&unrelated, "kept").unwrap();
+
+        let cache = SearchCache::load(dir.to_path_buf(), "search".to_string());
+        cache.save();
+        assert!(cache.file.exists());
+        assert!(!stale.exists());
+        assert!(unrelated.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&cache.file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Over the budget, the least recently saved files go first
+        let big = dir.join("1111111111111111.json");
+        fs::write(&big, vec![b' '; MAX_TOTAL_SIZE as usize]).unwrap();
+        fs::File::options().write(true).open(&big).unwrap().set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+        cache.save();
+        assert!(!big.exists());
+        assert!(cache.file.exists());
+    }
+}
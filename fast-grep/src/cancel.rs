@@ -0,0 +1,106 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Why a search stopped before searching every file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancelled {
+    /// Ctrl+C
+    Interrupted = 1,
+    /// `--timeout` elapsed
+    TimedOut = 2,
+}
+
+impl Cancelled {
+    /// Exit status, following the shell's 128+SIGINT and timeout(1)'s 124
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Cancelled::Interrupted => 130,
+            Cancelled::TimedOut => 124,
+        }
+    }
+}
+
+/// Cooperative cancellation shared by the walker and the workers. Workers
+/// poll it between files and between lines, so whatever was found before
+/// the cancellation is still printed whole.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    interrupted: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    /// The first cancellation a poll observed, 0 while none has
+    observed: Arc<AtomicU8>,
+}
+
+impl CancelToken {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            interrupted: Arc::new(AtomicBool::new(false)),
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            observed: Arc::new(AtomicU8::new(0)),
+        }
+    }
+
+    /// Cancel on Ctrl+C. A second Ctrl+C exits immediately, for when even
+    /// printing the partial results takes too long.
+    pub fn install_interrupt_handler(&self) -> Result<()> {
+        let interrupted = Arc::clone(&self.interrupted);
+        ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::SeqCst) {
+                std::process::exit(Cancelled::Interrupted.exit_code());
+            }
+        })?;
+        Ok(())
+    }
+
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    /// Poll for cancellation, remembering the first one seen
+    pub fn check(&self) -> Option<Cancelled> {
+        let cancelled = if self.interrupted.load(Ordering::Relaxed) {
+            Cancelled::Interrupted
+        } else if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Cancelled::TimedOut
+        } else {
+            return None;
+        };
+        let _ = self.observed.compare_exchange(0, cancelled as u8, Ordering::SeqCst, Ordering::SeqCst);
+        Some(cancelled)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.check().is_some()
+    }
+
+    /// The cancellation that cut work short, if any poll saw one. A Ctrl+C
+    /// or deadline after the last poll left the results complete.
+    pub fn observed(&self) -> Option<Cancelled> {
+        match self.observed.load(Ordering::SeqCst) {
+            1 => Some(Cancelled::Interrupted),
+            2 => Some(Cancelled::TimedOut),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new(None);
+        assert_eq!(token.check(), None);
+        token.clone().interrupt();
+        assert_eq!(token.observed(), None);
+        assert_eq!(token.check(), Some(Cancelled::Interrupted));
+        assert_eq!(token.observed(), Some(Cancelled::Interrupted));
+
+        let token = CancelToken::new(Some(Duration::ZERO));
+        assert_eq!(token.check(), Some(Cancelled::TimedOut));
+        assert_eq!(CancelToken::new(Some(Duration::from_secs(3600))).check(), None);
+    }
+}
@@ -1,5 +1,10 @@
 use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::pattern_matcher::Extraction;
+use crate::scope_filter::Scope;
+use crate::virtual_fs::VIRTUAL_FS_TYPES;
 
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ColorOption {
@@ -11,23 +16,53 @@ pub enum ColorOption {
     Never,
 }
 
+/// Rollups `--summary` prints instead of the matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SummaryMode {
+    /// Matches, matching lines and files per directory, most matches first
+    Dirs,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "fgrep")]
 #[command(about = "Ultra-fast parallel text search tool")]
 #[command(version = "0.1.0")]
+#[command(disable_help_flag = true)]
 pub struct Args {
-    /// Pattern to search for
-    #[arg(value_name = "PATTERN")]
+    /// Pattern to search for (with --files, the first path)
+    #[arg(
+        value_name = "PATTERN",
+        required_unless_present = "list_files",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub pattern: String,
 
-    /// Files or directories to search
-    #[arg(value_name = "PATH", default_value = ".")]
+    /// Files or directories to search, `-` for standard input (default:
+    /// current directory)
+    #[arg(value_name = "PATH")]
     pub paths: Vec<PathBuf>,
 
+    /// Print the files that would be searched, without searching them
+    #[arg(
+        long = "files",
+        conflicts_with_all = ["files_only", "files_without_matches", "count_only", "count_matches", "only_matching"]
+    )]
+    pub list_files: bool,
+
     /// Use regular expressions (default: literal string search)
     #[arg(short = 'E', long = "regex")]
     pub use_regex: bool,
 
+    /// Allow up to K typos in each match: inserted, deleted or substituted
+    /// bytes (literal patterns of up to 64 bytes)
+    #[arg(
+        long = "fuzzy",
+        value_name = "K",
+        conflicts_with_all = ["use_regex", "group", "replace"]
+    )]
+    pub fuzzy: Option<usize>,
+
     /// Fixed string search (literal matching, no regex)
     #[arg(short = 'F', long = "fixed-strings")]
     pub fixed_strings: bool,
@@ -48,26 +83,103 @@ pub struct Args {
     #[arg(short = 'c', long = "count")]
     pub count_only: bool,
 
+    /// Show count of matches per file, counting each match on a line
+    #[arg(long = "count-matches", conflicts_with_all = ["files_only", "files_without_matches"])]
+    pub count_matches: bool,
+
     /// Invert match (show non-matching lines)
     #[arg(short = 'v', long = "invert-match")]
     pub invert_match: bool,
 
-    /// Show only the matching part of lines
+    /// Show only the matching part of lines, each match on its own line
     #[arg(short = 'o', long = "only-matching")]
     pub only_matching: bool,
 
+    /// With -o, print capture group N of each match instead of the whole match
+    #[arg(long = "group", value_name = "N", requires = "only_matching")]
+    pub group: Option<usize>,
+
+    /// Replace each match with TEMPLATE, where $1 or ${name} name capture groups
+    #[arg(long = "replace", value_name = "TEMPLATE", conflicts_with = "group")]
+    pub replace: Option<String>,
+
+    /// Only match inside comments, string literals or code, for languages
+    /// recognized by file extension; other files never match
+    #[arg(long = "scope", value_enum, value_name = "SCOPE")]
+    pub scope: Option<Scope>,
+
+    /// Only report lines added or modified since git REF (default HEAD),
+    /// including uncommitted and untracked changes
+    #[arg(
+        long = "changed",
+        value_name = "REF",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "HEAD"
+    )]
+    pub changed: Option<String>,
+
     /// Show only names of files without matches
     #[arg(short = 'L', long = "files-without-match")]
     pub files_without_matches: bool,
 
     /// Suppress filename prefix in output
-    #[arg(short = 'h', long = "no-filename")]
+    #[arg(short = 'h', long = "no-filename", overrides_with = "with_filename")]
     pub no_filename: bool,
 
+    /// Prefix every output line with its file name, even for a single path
+    #[arg(short = 'H', long = "with-filename", overrides_with = "no_filename")]
+    pub with_filename: bool,
+
+    /// Name to show for standard input (default: "(standard input)")
+    #[arg(long = "label", value_name = "NAME")]
+    pub label: Option<String>,
+
+    /// Follow file names with a NUL byte instead of ':' or, with -l and
+    /// --files-without-match, a newline (for xargs -0)
+    #[arg(short = 'Z', long = "null")]
+    pub null: bool,
+
+    /// Treat input as NUL-separated records instead of lines, and end
+    /// output records with NUL too
+    #[arg(short = 'z', long = "null-data")]
+    pub null_data: bool,
+
+    /// Don't print lines longer than N characters; a note with the number of
+    /// matches is printed instead
+    #[arg(short = 'M', long = "max-columns", value_name = "N")]
+    pub max_columns: Option<usize>,
+
+    /// With --max-columns, print N characters of long lines around the first
+    /// match instead of omitting them
+    #[arg(long = "max-columns-preview", requires = "max_columns")]
+    pub max_columns_preview: bool,
+
     /// Recursively search directories
     #[arg(short = 'r', long = "recursive", default_value_t = true)]
     pub recursive: bool,
 
+    /// Follow symbolic links while walking directories (as grep -R)
+    #[arg(short = 'R', long = "follow-links")]
+    pub follow_links: bool,
+
+    /// Descend at most N directory levels below each path
+    #[arg(short = 'd', long = "max-depth", value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Don't descend into other filesystems mounted below each path
+    #[arg(long = "one-file-system")]
+    pub one_file_system: bool,
+
+    /// Descend into proc, sysfs and the other virtual filesystems that are
+    /// otherwise left out, such as /proc and /sys when searching /
+    #[arg(long = "search-virtual")]
+    pub search_virtual: bool,
+
+    /// Also leave out mounts of these filesystem types (e.g. nfs4,fuse.sshfs)
+    #[arg(long = "skip-fs", value_name = "TYPES", value_delimiter = ',')]
+    pub skip_fs: Vec<String>,
+
     /// Show context lines before match
     #[arg(short = 'B', long = "before-context", value_name = "N")]
     pub before_context: Option<usize>,
@@ -80,10 +192,15 @@ pub struct Args {
     #[arg(short = 'C', long = "context", value_name = "N")]
     pub context: Option<usize>,
 
-    /// Number of worker threads (default: CPU cores)
+    /// Number of worker threads (default: one per CPU core, up to two per
+    /// core while reading files rather than matching is the bottleneck)
     #[arg(short = 'j', long = "threads")]
     pub threads: Option<usize>,
 
+    /// Stop searching after SECS seconds, printing what was found so far
+    #[arg(long = "timeout", value_name = "SECS", value_parser = parse_timeout)]
+    pub timeout: Option<Duration>,
+
     /// File types to include (e.g., "rs,py,js")
     #[arg(long = "type")]
     pub file_types: Option<String>,
@@ -92,10 +209,19 @@ pub struct Args {
     #[arg(long = "type-not")]
     pub exclude_types: Option<String>,
 
-    /// Respect .gitignore files
+    /// Don't respect any ignore files (.gitignore, .ignore, .rgignore, global excludes)
     #[arg(long = "no-ignore", action = clap::ArgAction::SetFalse)]
     pub respect_ignore: bool,
 
+    /// Don't respect version control ignores: .gitignore, .git/info/exclude
+    /// and git's core.excludesFile
+    #[arg(long = "no-ignore-vcs")]
+    pub no_ignore_vcs: bool,
+
+    /// Don't respect .ignore and .rgignore files
+    #[arg(long = "no-ignore-dot")]
+    pub no_ignore_dot: bool,
+
     /// Search hidden files
     #[arg(long = "hidden")]
     pub search_hidden: bool,
@@ -108,25 +234,134 @@ pub struct Args {
     #[arg(long = "no-color")]
     pub no_color: bool,
 
+    /// Color settings such as 'match:fg:red,line:fg:green' (applied after GREP_COLORS)
+    #[arg(long = "colors", value_name = "SPEC")]
+    pub colors: Vec<String>,
+
     /// Output in JSON format
     #[arg(long = "json")]
     pub json_output: bool,
 
+    /// Print every match as FILE:LINE:COLUMN:LINE, the format vim's
+    /// grepprg and editor plugins read; context options are ignored
+    #[arg(
+        long = "vimgrep",
+        conflicts_with_all = ["json_output", "files_only", "files_without_matches", "count_only", "count_matches"]
+    )]
+    pub vimgrep: bool,
+
+    /// Also match the pattern against each file's path, reporting files
+    /// whose name matches as "PATH:(file name)" records (objects of type
+    /// "filename" with --json) before their content matches
+    #[arg(
+        long = "with-filename-match",
+        conflicts_with_all = ["invert_match", "files_without_matches", "count_only", "count_matches", "vimgrep"]
+    )]
+    pub with_filename_match: bool,
+
+    /// Print a rollup instead of the matches: with 'dirs', one line per
+    /// directory of matches, matching lines, files and the directory
+    #[arg(
+        long = "summary",
+        value_enum,
+        value_name = "KIND",
+        conflicts_with_all = ["list_files", "files_only", "files_without_matches", "count_only", "count_matches", "vimgrep"]
+    )]
+    pub summary: Option<SummaryMode>,
+
+    /// With --summary dirs, add files up into directories at most N levels
+    /// below each searched path
+    #[arg(long = "summary-depth", value_name = "N", default_value_t = 1, requires = "summary")]
+    pub summary_depth: usize,
+
+    /// After the search, keep following the files that matched and print
+    /// matching lines as they are appended, like tail -f
+    #[arg(
+        long = "follow",
+        conflicts_with_all = ["list_files", "files_only", "files_without_matches", "count_only", "count_matches", "summary"]
+    )]
+    pub follow: bool,
+
+    /// With --follow, also follow files created under the searched
+    /// directories that pass the --type, --type-not and --hidden filters
+    #[arg(long = "follow-new", requires = "follow")]
+    pub follow_new: bool,
+
+    /// Print search statistics when done (JSON summary with --json)
+    #[arg(long = "stats")]
+    pub stats: bool,
+
     /// Maximum file size to search (in MB)
     #[arg(long = "max-filesize", default_value_t = 100)]
     pub max_filesize_mb: u64,
 
+    /// Hold at most about SIZE of file content in memory at once (e.g.
+    /// 512M, 2G): bigger files are streamed in windows, and workers wait
+    /// while the budget is in use. Standard input is still read whole
+    #[arg(long = "max-memory", value_name = "SIZE", value_parser = parse_size)]
+    pub max_memory: Option<u64>,
+
     /// Use memory mapping for large files
     #[arg(long = "mmap", default_value_t = true)]
     pub use_mmap: bool,
+
+    /// Reuse and store per-file results of earlier identical searches
+    /// (kept by path, mtime and size, so only changed files are read
+    /// again). Matched lines are written to the cache directory
+    #[arg(long = "cache")]
+    pub cache: bool,
+
+    /// Print help (-h is taken by --no-filename, as in grep)
+    #[arg(long = "help", action = clap::ArgAction::Help)]
+    pub help: Option<bool>,
 }
 
 impl Args {
+    /// Settle the positional arguments: with `--files` there is no pattern,
+    /// so the first positional is a path, and no paths means the current
+    /// directory
+    pub fn with_resolved_paths(mut self) -> Self {
+        if self.list_files && !self.pattern.is_empty() {
+            self.paths.insert(0, PathBuf::from(std::mem::take(&mut self.pattern)));
+        }
+        if self.paths.is_empty() {
+            self.paths.push(PathBuf::from("."));
+        }
+        self
+    }
+
+    /// Whether output lines carry file names: as forced by -H or -h,
+    /// otherwise when more than one path was given
+    pub fn show_filenames(&self) -> bool {
+        if self.with_filename || self.vimgrep {
+            true
+        } else if self.no_filename {
+            false
+        } else {
+            self.paths.len() > 1
+        }
+    }
+
+    /// What to print per match for `--replace` / `--group`
+    pub fn extraction(&self) -> Option<Extraction> {
+        match (&self.replace, self.group) {
+            (Some(template), _) => Some(Extraction::Template(template.clone())),
+            (None, Some(group)) => Some(Extraction::Group(group)),
+            (None, None) => None,
+        }
+    }
+
     pub fn get_before_context(&self) -> usize {
+        if self.vimgrep {
+            return 0;
+        }
         self.context.or(self.before_context).unwrap_or(0)
     }
 
     pub fn get_after_context(&self) -> usize {
+        if self.vimgrep {
+            return 0;
+        }
         self.context.or(self.after_context).unwrap_or(0)
     }
 
@@ -134,6 +369,26 @@ impl Args {
         self.threads.unwrap_or_else(num_cpus::get)
     }
 
+    /// Filesystem types discovery does not descend into
+    pub fn pruned_fs_types(&self) -> Vec<String> {
+        let virtual_types = if self.search_virtual { &[][..] } else { VIRTUAL_FS_TYPES };
+        virtual_types
+            .iter()
+            .map(|name| name.to_string())
+            .chain(self.skip_fs.iter().cloned())
+            .collect()
+    }
+
+    /// Whether .gitignore, .git/info/exclude and core.excludesFile apply
+    pub fn respect_vcs_ignore(&self) -> bool {
+        self.respect_ignore && !self.no_ignore_vcs
+    }
+
+    /// Whether .ignore and .rgignore apply
+    pub fn respect_dot_ignore(&self) -> bool {
+        self.respect_ignore && !self.no_ignore_dot
+    }
+
     pub fn max_filesize_bytes(&self) -> u64 {
         self.max_filesize_mb * 1024 * 1024
     }
@@ -157,4 +412,28 @@ impl Args {
         // Simple heuristic to detect if pattern contains regex metacharacters
         self.pattern.chars().any(|c| matches!(c, '.' | '*' | '+' | '?' | '^' | '$' | '|' | '[' | ']' | '(' | ')' | '{' | '}'))
     }
-}
\ No newline at end of file
+}
+/// Parse a size such as `4096`, `64K`, `512M` or `2GiB`, in powers of 1024
+fn parse_size(value: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}': expected bytes or a number with K, M or G", value);
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, shift) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 10),
+        Some('M') => (&number[..number.len() - 1], 20),
+        Some('G') => (&number[..number.len() - 1], 30),
+        Some('T') => (&number[..number.len() - 1], 40),
+        _ => (number, 0),
+    };
+    let size: u64 = digits.trim().parse().map_err(|_| invalid())?;
+    size.checked_mul(1 << shift).filter(|&size| size > 0).ok_or_else(invalid)
+}
+
+/// Parse `--timeout` seconds, fractions allowed
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid timeout '{}': expected a number of seconds", value))
+}
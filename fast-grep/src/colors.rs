@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+
+/// SGR color configuration for each part of the output.
+///
+/// Every style is a raw SGR parameter string such as `"1;31"`; an empty
+/// string leaves that part uncolored. Defaults are overridden first by
+/// `GREP_COLORS` and then by `--colors` specs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorScheme {
+    pub matched: String,
+    pub selected_line: String,
+    pub context_line: String,
+    pub filename: String,
+    pub line_number: String,
+    pub separator: String,
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self {
+            matched: "1;31".to_string(),
+            selected_line: String::new(),
+            context_line: "2".to_string(),
+            filename: "1;35".to_string(),
+            line_number: "32".to_string(),
+            separator: "2".to_string(),
+        }
+    }
+}
+
+impl ColorScheme {
+    /// Build the scheme from `GREP_COLORS` (if set) and `--colors` specs
+    pub fn from_env_and_specs(grep_colors: Option<&str>, specs: &[String]) -> Result<Self> {
+        let mut scheme = Self::default();
+        if let Some(grep_colors) = grep_colors {
+            scheme.apply_grep_colors(grep_colors);
+        }
+        for spec in specs.iter().flat_map(|s| s.split(',')) {
+            let spec = spec.trim();
+            if !spec.is_empty() {
+                scheme.apply_spec(spec)?;
+            }
+        }
+        Ok(scheme)
+    }
+
+    /// Apply a GNU grep `GREP_COLORS` value such as `ms=01;31:fn=35:ln=32`.
+    ///
+    /// As in grep, unknown capabilities and malformed values are ignored
+    /// rather than rejected, since the variable is shared with other tools.
+    pub fn apply_grep_colors(&mut self, value: &str) {
+        for capability in value.split(':') {
+            let Some((name, sgr)) = capability.split_once('=') else {
+                // Boolean capabilities (rv, ne) have no effect here
+                continue;
+            };
+            if !sgr.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                continue;
+            }
+            let sgr = sgr.to_string();
+            match name {
+                "mt" | "ms" => self.matched = sgr,
+                "sl" => self.selected_line = sgr,
+                "cx" => self.context_line = sgr,
+                "fn" => self.filename = sgr,
+                "ln" => self.line_number = sgr,
+                "se" => self.separator = sgr,
+                _ => {}
+            }
+        }
+    }
+
+    /// Apply one `{type}:{attribute}:{value}` or `{type}:none` spec, where type
+    /// is match, line, path/filename, separator or context
+    pub fn apply_spec(&mut self, spec: &str) -> Result<()> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let target = match parts[0] {
+            "match" => &mut self.matched,
+            "line" => &mut self.line_number,
+            "path" | "filename" => &mut self.filename,
+            "separator" => &mut self.separator,
+            "context" => &mut self.context_line,
+            other => {
+                return Err(anyhow!(
+                    "Invalid color spec '{}': unknown type '{}' (expected match, line, path, separator or context)",
+                    spec,
+                    other
+                ))
+            }
+        };
+
+        let code = match parts[1..] {
+            ["none"] => {
+                target.clear();
+                return Ok(());
+            }
+            ["fg", color] => color_code(color, false),
+            ["bg", color] => color_code(color, true),
+            ["style", style] => style_code(style),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("Invalid color spec '{}'", spec))?;
+
+        if !target.is_empty() {
+            target.push(';');
+        }
+        target.push_str(&code);
+        Ok(())
+    }
+}
+
+/// Wrap `text` in the SGR sequence for `style`; empty styles leave it as is
+pub fn paint(text: &str, style: &str) -> String {
+    if style.is_empty() || text.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    }
+}
+
+fn color_code(color: &str, background: bool) -> Option<String> {
+    let base = if background { 40 } else { 30 };
+    let index = match color {
+        "black" => 0,
+        "red" => 1,
+        "green" => 2,
+        "yellow" => 3,
+        "blue" => 4,
+        "magenta" => 5,
+        "cyan" => 6,
+        "white" => 7,
+        _ => {
+            // 256-color palette index
+            let index: u8 = color.parse().ok()?;
+            let prefix = if background { 48 } else { 38 };
+            return Some(format!("{};5;{}", prefix, index));
+        }
+    };
+    Some((base + index).to_string())
+}
+
+fn style_code(style: &str) -> Option<String> {
+    let code = match style {
+        "bold" => "1",
+        "nobold" => "22",
+        "dim" | "dimmed" => "2",
+        "italic" => "3",
+        "noitalic" => "23",
+        "underline" => "4",
+        "nounderline" => "24",
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_colors() {
+        let mut scheme = ColorScheme::default();
+        scheme.apply_grep_colors("ms=01;32:fn=34:ln=:rv:bn=33:xx=1:se=bogus");
+        assert_eq!(scheme.matched, "01;32");
+        assert_eq!(scheme.filename, "34");
+        assert_eq!(scheme.line_number, "");
+        assert_eq!(scheme.separator, ColorScheme::default().separator);
+    }
+
+    #[test]
+    fn test_color_specs_override_grep_colors() {
+        let specs = vec!["match:none,match:fg:green".to_string(), "line:style:bold".to_string()];
+        let scheme = ColorScheme::from_env_and_specs(Some("ms=01;31:ln=33"), &specs).unwrap();
+        assert_eq!(scheme.matched, "32");
+        assert_eq!(scheme.line_number, "33;1");
+
+        let scheme = ColorScheme::from_env_and_specs(None, &["path:bg:17".to_string()]).unwrap();
+        assert_eq!(scheme.filename, "1;35;48;5;17");
+    }
+
+    #[test]
+    fn test_invalid_color_specs() {
+        for spec in ["bogus:fg:red", "match:fg:purple", "match:style", "match"] {
+            assert!(ColorScheme::from_env_and_specs(None, &[spec.to_string()]).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("x", "1;31"), "\x1b[1;31mx\x1b[0m");
+        assert_eq!(paint("x", ""), "x");
+    }
+}
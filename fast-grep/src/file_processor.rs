@@ -1,14 +1,22 @@
 use crate::errors::{FastGrepError, Result};
+use crate::sparse::{self, DataReader};
 use content_inspector::{inspect, ContentType};
 use memmap2::Mmap;
 use std::fs::File;
 use std::io::{BufRead, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// The path that stands for standard input
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
 
 #[derive(Clone)]
 pub struct FileProcessor {
     max_size_for_mmap: u64,
     use_mmap: bool,
+    /// `--null-data`: NUL bytes separate records rather than mark binary files
+    null_data: bool,
 }
 
 impl FileProcessor {
@@ -16,29 +24,73 @@ impl FileProcessor {
         Self {
             max_size_for_mmap,
             use_mmap,
+            null_data: false,
         }
     }
 
+    /// Treat NUL bytes as record separators, so only files that are not
+    /// valid UTF-8 are skipped as binary
+    pub fn with_null_data(mut self, null_data: bool) -> Self {
+        self.null_data = null_data;
+        self
+    }
+
     pub fn process_file<P: AsRef<Path>>(&self, path: P) -> Result<FileContent> {
+        self.process_file_with_threshold(path, self.max_size_for_mmap)
+    }
+
+    /// Files larger than this are mapped when mmap is enabled
+    pub fn mmap_threshold(&self) -> u64 {
+        self.max_size_for_mmap
+    }
+
+    /// [`FileProcessor::process_file`], mapping files larger than
+    /// `mmap_threshold` rather than the configured size
+    pub fn process_file_with_threshold<P: AsRef<Path>>(&self, path: P, mmap_threshold: u64) -> Result<FileContent> {
         let path = path.as_ref();
         let path_buf = path.to_path_buf();
+        if is_stdin(path) {
+            return self.process_stdin(path_buf);
+        }
         let metadata = std::fs::metadata(path)
             .map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
         let file_size = metadata.len();
+        let skip_holes = self.skips_holes(&metadata);
 
         // Skip binary files with better detection
-        if self.is_likely_binary(path).map_err(|e| 
+        if self.is_likely_binary(path, skip_holes).map_err(|e| 
             FastGrepError::content_inspection(path_buf.clone(), e)
         )? {
             return Err(FastGrepError::BinaryFile { path: path_buf });
         }
 
-        // Use memory mapping for large files if enabled
-        if self.use_mmap && file_size > self.max_size_for_mmap {
+        // Use memory mapping for large files if enabled; a sparse file is
+        // read around its holes instead
+        if self.use_mmap && file_size > mmap_threshold && !skip_holes {
             self.process_with_mmap(path)
         } else {
-            self.process_with_read(path)
+            self.process_with_read(path, skip_holes)
+        }
+    }
+
+    /// Open `path` to be read in windows of whole records of about
+    /// `window` bytes, after the same binary check as a whole read
+    pub fn open_windows(&self, path: &Path, window: usize, terminator: u8) -> Result<RecordWindows> {
+        let path_buf = path.to_path_buf();
+        let file = File::open(path).map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
+        let skip_holes = file.metadata().is_ok_and(|metadata| self.skips_holes(&metadata));
+        if self.is_likely_binary(path, skip_holes).map_err(|e| FastGrepError::content_inspection(path_buf.clone(), e))? {
+            return Err(FastGrepError::BinaryFile { path: path_buf });
         }
+        let file = DataReader::new(file, skip_holes).map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
+        Ok(RecordWindows {
+            file,
+            path: path_buf,
+            window: window.max(1),
+            terminator,
+            partial: Vec::new(),
+            done: false,
+        })
     }
 
     fn process_with_mmap<P: AsRef<Path>>(&self, path: P) -> Result<FileContent> {
@@ -56,11 +108,13 @@ impl FileProcessor {
         Ok(FileContent::Mapped(mmap))
     }
 
-    fn process_with_read<P: AsRef<Path>>(&self, path: P) -> Result<FileContent> {
+    fn process_with_read<P: AsRef<Path>>(&self, path: P, skip_holes: bool) -> Result<FileContent> {
         let path = path.as_ref();
         let path_buf = path.to_path_buf();
         
-        let mut file = File::open(path)
+        let file = File::open(path)
+            .map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
+        let mut file = DataReader::new(file, skip_holes)
             .map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
         
         let mut buffer = Vec::new();
@@ -70,18 +124,44 @@ impl FileProcessor {
         Ok(FileContent::InMemory(buffer))
     }
 
-    fn is_likely_binary<P: AsRef<Path>>(&self, path: P) -> std::io::Result<bool> {
-        let mut file = File::open(path)?;
+    /// Standard input, read to its end; it can only be searched once
+    fn process_stdin(&self, path_buf: PathBuf) -> Result<FileContent> {
+        let mut buffer = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut buffer)
+            .map_err(|e| FastGrepError::file_processing(path_buf.clone(), e))?;
+        if self.is_binary_sample(&buffer[..buffer.len().min(8192)]) {
+            return Err(FastGrepError::BinaryFile { path: path_buf });
+        }
+        Ok(FileContent::InMemory(buffer))
+    }
+
+    /// Whether a file is read around its holes: sparse files are, except
+    /// with `--null-data`, where the NUL bytes of a hole are records
+    fn skips_holes(&self, metadata: &std::fs::Metadata) -> bool {
+        !self.null_data && sparse::is_sparse(metadata)
+    }
+
+    fn is_likely_binary<P: AsRef<Path>>(&self, path: P, skip_holes: bool) -> std::io::Result<bool> {
+        let mut file = DataReader::new(File::open(path)?, skip_holes)?;
         let mut buffer = vec![0; 8192]; // Check first 8KB for better accuracy
         let bytes_read = file.read(&mut buffer)?;
-        
-        if bytes_read == 0 {
-            return Ok(false); // Empty files are considered text
+        Ok(self.is_binary_sample(&buffer[..bytes_read]))
+    }
+
+    fn is_binary_sample(&self, sample: &[u8]) -> bool {
+        if sample.is_empty() {
+            return false; // Empty files are considered text
         }
         
+        if self.null_data {
+            // A sequence cut off by the end of the sample is not an error
+            return std::str::from_utf8(sample).is_err_and(|e| e.error_len().is_some());
+        }
+
         // Use content_inspector for more accurate binary detection
-        let content_type = inspect(&buffer[..bytes_read]);
-        Ok(matches!(content_type, ContentType::BINARY))
+        matches!(inspect(sample), ContentType::BINARY)
     }
 }
 
@@ -100,14 +180,20 @@ impl FileContent {
         }
     }
 
-    pub fn lines(&self) -> Option<Vec<Line>> {
+    pub fn lines(&self) -> Option<Vec<Line<'_>>> {
+        self.records(b'\n')
+    }
+
+    /// Records ending in `terminator`: lines, or NUL-separated records for
+    /// `--null-data`
+    pub fn records(&self, terminator: u8) -> Option<Vec<Line<'_>>> {
         let bytes = self.as_bytes()?;
         let mut lines = Vec::new();
         let mut start = 0;
         let mut line_number = 1;
 
         for (pos, &byte) in bytes.iter().enumerate() {
-            if byte == b'\n' {
+            if byte == terminator {
                 lines.push(Line {
                     number: line_number,
                     start,
@@ -151,6 +237,49 @@ impl<'a> Line<'a> {
     }
 }
 
+/// A file read a window at a time under `--max-memory`. Every window ends
+/// at a record terminator, so records are never split between two; a
+/// record longer than a window is read whole.
+pub struct RecordWindows {
+    file: DataReader,
+    path: PathBuf,
+    window: usize,
+    terminator: u8,
+    /// Bytes read past the last terminator of the previous window
+    partial: Vec<u8>,
+    done: bool,
+}
+
+impl RecordWindows {
+    /// Append the next window to `buffer`, returning how many bytes were
+    /// read from the file
+    pub fn read_into(&mut self, buffer: &mut Vec<u8>) -> Result<u64> {
+        let start = buffer.len();
+        buffer.append(&mut self.partial);
+        let mut read = 0;
+        while !self.done {
+            let before = buffer.len();
+            (&mut self.file)
+                .take(self.window as u64)
+                .read_to_end(buffer)
+                .map_err(|e| FastGrepError::file_processing(self.path.clone(), e))?;
+            read += (buffer.len() - before) as u64;
+            if buffer.len() - before < self.window {
+                self.done = true;
+            } else if let Some(end) = memchr::memrchr(self.terminator, &buffer[start..]) {
+                self.partial = buffer.split_off(start + end + 1);
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Whether the whole file has been read
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
 // Optimized line-by-line processor for streaming large files
 pub struct LineProcessor<R: BufRead> {
     reader: R,
@@ -218,4 +347,23 @@ mod tests {
         assert_eq!(lines[1], (2, b"line2".to_vec()));
         assert_eq!(lines[2], (3, b"line3".to_vec()));
     }
+
+    #[test]
+    fn test_record_windows_end_at_terminators() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("log");
+        std::fs::write(&path, "aa\nbbbbbbbbbb\ncc\nd").unwrap();
+
+        let mut windows = FileProcessor::new(0, false).open_windows(&path, 4, b'\n').unwrap();
+        let mut seen = Vec::new();
+        let mut read = 0;
+        while !windows.is_done() {
+            let mut buffer = Vec::new();
+            read += windows.read_into(&mut buffer).unwrap();
+            seen.push(String::from_utf8(buffer).unwrap());
+        }
+        // The long record is read whole; the rest come a window at a time
+        assert_eq!(seen, ["aa\n", "bbbbbbbbbb\n", "cc\nd"]);
+        assert_eq!(read, 18);
+    }
 }
\ No newline at end of file
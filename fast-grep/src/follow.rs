@@ -0,0 +1,293 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::cancel::CancelToken;
+
+/// How long to wait for a change event before checking every followed file
+/// anyway, in case the watcher missed one or could not be set up
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Bytes read at a time while counting the records of a followed file
+const COUNT_CHUNK: usize = 64 << 10;
+
+/// Records appended to a followed file since it was last read
+#[derive(Debug, PartialEq, Eq)]
+pub struct Appended {
+    /// Whole records, each ending in the terminator
+    pub data: Vec<u8>,
+    /// Records in the file before `data`, to number its lines by
+    pub records_before: usize,
+}
+
+/// Where a followed file has been read to
+#[derive(Debug)]
+struct FollowedFile {
+    /// Bytes of the file read so far
+    position: u64,
+    /// Complete records before `position`
+    records: usize,
+    /// Bytes read past the last terminator, held until their record ends
+    partial: Vec<u8>,
+    /// Device and inode, to notice the file being replaced by rotation
+    identity: Option<(u64, u64)>,
+}
+
+impl FollowedFile {
+    /// Follow `path` from its current end, counting the records before it
+    fn from_end(path: &Path, terminator: u8) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let identity = identity(&file.metadata()?);
+        let mut buffer = vec![0; COUNT_CHUNK];
+        let mut followed = Self::from_start(identity);
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(followed);
+            }
+            let chunk = &buffer[..read];
+            followed.position += read as u64;
+            followed.records += memchr::memchr_iter(terminator, chunk).count();
+            // An unfinished last record is held until it ends
+            match memchr::memrchr(terminator, chunk) {
+                Some(end) => followed.partial = chunk[end + 1..].to_vec(),
+                None => followed.partial.extend_from_slice(chunk),
+            }
+        }
+    }
+
+    /// Follow a file created since the search, from its start
+    fn from_start(identity: Option<(u64, u64)>) -> Self {
+        Self {
+            position: 0,
+            records: 0,
+            partial: Vec::new(),
+            identity,
+        }
+    }
+
+    /// Read what was appended to `path` since the last read, starting over
+    /// if the file was truncated or replaced
+    fn read_appended(&mut self, path: &Path, terminator: u8) -> io::Result<Option<Appended>> {
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            // Rotated away; whatever takes its place is read from the start
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let identity = identity(&metadata);
+        if metadata.len() < self.position || identity != self.identity {
+            eprintln!("fgrep: {}: file truncated or replaced, following from its start", path.display());
+            *self = Self::from_start(identity);
+        }
+        if metadata.len() == self.position {
+            return Ok(None);
+        }
+
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut data = std::mem::take(&mut self.partial);
+        let read = file.take(metadata.len() - self.position).read_to_end(&mut data)?;
+        self.position += read as u64;
+        match memchr::memrchr(terminator, &data) {
+            Some(end) => {
+                self.partial = data.split_off(end + 1);
+                let records_before = self.records;
+                self.records += memchr::memchr_iter(terminator, &data).count();
+                Ok(Some(Appended { data, records_before }))
+            }
+            None => {
+                self.partial = data;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// `--follow`: files followed after the search, read as they grow.
+///
+/// A watcher on the files' directories wakes the follower as soon as one
+/// changes; every file is also checked each [`POLL_INTERVAL`], so changes
+/// are still seen where no watcher can be set up.
+pub struct Follower {
+    files: BTreeMap<PathBuf, FollowedFile>,
+    terminator: u8,
+    /// Directories new files are followed under, with `--follow-new`, as
+    /// given and canonicalized: the watcher reports canonical paths
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Follower {
+    pub fn new(terminator: u8) -> Self {
+        Self {
+            files: BTreeMap::new(),
+            terminator,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Also follow files created below `roots`, from their start
+    pub fn with_new_files_under(mut self, roots: Vec<PathBuf>) -> Self {
+        self.roots = roots
+            .into_iter()
+            .filter_map(|root| Some((root.canonicalize().ok()?, root)))
+            .collect();
+        self
+    }
+
+    /// Follow `path` from its current end
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let followed = FollowedFile::from_end(path, self.terminator)?;
+        self.files.insert(path.to_path_buf(), followed);
+        Ok(())
+    }
+
+    /// Whether there is nothing to follow
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.roots.is_empty()
+    }
+
+    /// Follow until `cancel` fires, handing appended records to
+    /// `on_records` file by file. New files below the roots are followed
+    /// once `accept` takes them.
+    pub fn run<A, F>(&mut self, cancel: &CancelToken, accept: A, mut on_records: F) -> Result<()>
+    where
+        A: Fn(&Path) -> bool,
+        F: FnMut(&Path, Appended) -> Result<()>,
+    {
+        let (events_tx, events_rx) = mpsc::channel();
+        // Dropped with the sender on failure, so the loop just polls
+        let _watcher = match self.watch(events_tx) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                eprintln!("fgrep: cannot watch for changes ({}), polling instead", e);
+                if !self.roots.is_empty() {
+                    eprintln!("fgrep: new files will not be noticed");
+                }
+                None
+            }
+        };
+
+        while cancel.check().is_none() {
+            let mut events = Vec::new();
+            match events_rx.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => {
+                    events.push(event);
+                    events.extend(events_rx.try_iter());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(POLL_INTERVAL),
+            }
+            for path in events.into_iter().filter_map(|event| event.ok()).flat_map(created_paths) {
+                let Some(path) = self.as_given(&path) else {
+                    continue;
+                };
+                if !self.files.contains_key(&path) && path.is_file() && accept(&path) {
+                    let identity = std::fs::metadata(&path).ok().and_then(|metadata| identity(&metadata));
+                    self.files.insert(path, FollowedFile::from_start(identity));
+                }
+            }
+
+            let mut unreadable = Vec::new();
+            for (path, followed) in &mut self.files {
+                match followed.read_appended(path, self.terminator) {
+                    Ok(Some(appended)) => on_records(path, appended)?,
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("fgrep: {}: {}, no longer following", path.display(), e);
+                        unreadable.push(path.clone());
+                    }
+                }
+            }
+            for path in unreadable {
+                self.files.remove(&path);
+            }
+        }
+        Ok(())
+    }
+
+    /// A path the watcher reported below a root, spelled as the walk of
+    /// that root names it, so it matches the followed files
+    fn as_given(&self, path: &Path) -> Option<PathBuf> {
+        self.roots.iter().find_map(|(canonical, given)| Some(given.join(path.strip_prefix(canonical).ok()?)))
+    }
+
+    /// Watch the followed files' directories, and the roots recursively
+    fn watch(&self, events_tx: mpsc::Sender<notify::Result<Event>>) -> notify::Result<RecommendedWatcher> {
+        let mut watcher = RecommendedWatcher::new(events_tx, notify::Config::default())?;
+        let directories: BTreeSet<&Path> = self
+            .files
+            .keys()
+            .filter_map(|path| path.parent())
+            .map(|parent| if parent.as_os_str().is_empty() { Path::new(".") } else { parent })
+            .collect();
+        for directory in directories {
+            if !self.roots.iter().any(|(_, root)| directory.starts_with(root)) {
+                watcher.watch(directory, RecursiveMode::NonRecursive)?;
+            }
+        }
+        for (root, _) in &self.roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+        Ok(watcher)
+    }
+}
+
+/// Paths an event creates. Files renamed into place are left alone: a
+/// rotated log renamed aside holds records already seen
+fn created_paths(event: Event) -> Vec<PathBuf> {
+    match event.kind {
+        EventKind::Create(_) => event.paths,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_reads_whole_appended_records() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("log");
+        std::fs::write(&path, "one\ntwo\nthr").unwrap();
+        let append = |text: &str| {
+            let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        let mut followed = FollowedFile::from_end(&path, b'\n').unwrap();
+        assert_eq!(followed.records, 2);
+        assert_eq!(followed.read_appended(&path, b'\n').unwrap(), None);
+
+        // The unfinished record comes whole once it ends
+        append("ee\nfo");
+        let appended = followed.read_appended(&path, b'\n').unwrap().unwrap();
+        assert_eq!(appended, Appended { data: b"three\n".to_vec(), records_before: 2 });
+        append("ur\nfive\n");
+        let appended = followed.read_appended(&path, b'\n').unwrap().unwrap();
+        assert_eq!(appended, Appended { data: b"four\nfive\n".to_vec(), records_before: 3 });
+
+        // A truncated file is read again from its start
+        std::fs::write(&path, "new\n").unwrap();
+        let appended = followed.read_appended(&path, b'\n').unwrap().unwrap();
+        assert_eq!(appended, Appended { data: b"new\n".to_vec(), records_before: 0 });
+    }
+}
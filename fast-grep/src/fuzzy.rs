@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+
+/// Longest pattern the bit-parallel matcher handles: one bit per byte
+pub const MAX_FUZZY_PATTERN: usize = 64;
+
+/// Approximate matching of one literal pattern with up to `max_edits`
+/// insertions, deletions or substitutions (`--fuzzy K`).
+///
+/// Uses the bitap algorithm with Wu and Manber's extension to errors: one
+/// 64-bit state per allowed edit count, so each input byte costs `K + 1`
+/// shifts and masks. Matches do not cross line ends.
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher {
+    pattern: Vec<u8>,
+    max_edits: usize,
+    ignore_case: bool,
+    /// Bit `i` of `masks[c]` is set where the pattern has byte `c` at `i`
+    masks: Box<[u64; 256]>,
+}
+
+impl FuzzyMatcher {
+    pub fn new(pattern: &[u8], max_edits: usize, ignore_case: bool) -> Result<Self> {
+        if pattern.is_empty() || pattern.len() > MAX_FUZZY_PATTERN {
+            return Err(anyhow!(
+                "--fuzzy: patterns must be 1 to {} bytes long, not {}",
+                MAX_FUZZY_PATTERN,
+                pattern.len()
+            ));
+        }
+        if max_edits >= pattern.len() {
+            return Err(anyhow!(
+                "--fuzzy {}: must be less than the length of {:?}, which would match anything",
+                max_edits,
+                String::from_utf8_lossy(pattern)
+            ));
+        }
+
+        let mut masks = Box::new([0u64; 256]);
+        for (i, &byte) in pattern.iter().enumerate() {
+            masks[usize::from(byte)] |= 1 << i;
+            if ignore_case {
+                masks[usize::from(byte.to_ascii_lowercase())] |= 1 << i;
+                masks[usize::from(byte.to_ascii_uppercase())] |= 1 << i;
+            }
+        }
+        Ok(Self {
+            pattern: pattern.to_vec(),
+            max_edits,
+            ignore_case,
+            masks,
+        })
+    }
+
+    /// Non-overlapping approximate matches in `data`, as `(start, end)`,
+    /// leftmost first. Of the ends close together, the one with the fewest
+    /// edits is taken, and the furthest of those, so an occurrence is not
+    /// cut short by a match that drops its last bytes.
+    pub fn find_iter(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while let Some((start, end)) = self.find_at(data, from) {
+            matches.push((start, end));
+            from = end.max(start + 1);
+        }
+        matches
+    }
+
+    fn find_at(&self, data: &[u8], from: usize) -> Option<(usize, usize)> {
+        let k = self.max_edits;
+        let accept = 1u64 << (self.pattern.len() - 1);
+        let mut states = self.initial_states();
+        // Best end found so far, and the last position worth looking at for a better one
+        let mut best: Option<(usize, usize)> = None;
+        let mut horizon = usize::MAX;
+
+        for (position, &byte) in data.iter().enumerate().skip(from) {
+            if position > horizon {
+                break;
+            }
+            if byte == b'\n' {
+                if best.is_some() {
+                    break;
+                }
+                states = self.initial_states();
+                continue;
+            }
+
+            let mask = self.masks[usize::from(byte)];
+            let mut previous = states[0];
+            states[0] = ((states[0] << 1) | 1) & mask;
+            for edits in 1..=k {
+                let old = states[edits];
+                states[edits] = (((old << 1) | 1) & mask) // match
+                    | ((previous << 1) | 1) // substitution
+                    | previous // insertion into the text
+                    | ((states[edits - 1] << 1) | 1); // deletion from the pattern
+                previous = old;
+            }
+
+            if let Some(edits) = states.iter().position(|state| state & accept != 0) {
+                if best.is_none_or(|(_, best_edits)| edits <= best_edits) {
+                    best = Some((position + 1, edits));
+                    horizon = horizon.min(position + k);
+                }
+                if edits == 0 {
+                    break;
+                }
+            }
+        }
+
+        best.map(|(end, _)| (self.start_of(data, from, end), end))
+    }
+
+    fn initial_states(&self) -> Vec<u64> {
+        // With `d` edits the first `d` pattern bytes may be deleted
+        (0..=self.max_edits).map(|edits| (1u64 << edits) - 1).collect()
+    }
+
+    /// Where the match ending at `end` starts: the start giving the fewest
+    /// edits, and of those the latest, by aligning the reversed pattern
+    /// against the text before `end`
+    fn start_of(&self, data: &[u8], from: usize, end: usize) -> usize {
+        let line_start = data[..end].iter().rposition(|&byte| byte == b'\n').map_or(0, |newline| newline + 1);
+        let window_start = end
+            .saturating_sub(self.pattern.len() + self.max_edits)
+            .max(from)
+            .max(line_start);
+        let text: Vec<u8> = data[window_start..end].iter().rev().copied().collect();
+        let eq = |a: u8, b: u8| if self.ignore_case { a.eq_ignore_ascii_case(&b) } else { a == b };
+
+        // distances[j]: edits to turn the reversed pattern prefix into the
+        // first `j` reversed text bytes
+        let mut distances: Vec<usize> = (0..=text.len()).collect();
+        for (i, &pattern_byte) in self.pattern.iter().rev().enumerate() {
+            let mut diagonal = distances[0];
+            distances[0] = i + 1;
+            for (j, &text_byte) in text.iter().enumerate() {
+                let substitution = diagonal + usize::from(!eq(pattern_byte, text_byte));
+                diagonal = distances[j + 1];
+                distances[j + 1] = substitution.min(distances[j + 1] + 1).min(distances[j] + 1);
+            }
+        }
+        let length = (0..distances.len())
+            .min_by_key(|&length| (distances[length], length))
+            .unwrap_or(0);
+        end - length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans<'a>(pattern: &str, edits: usize, data: &'a str) -> Vec<&'a str> {
+        let matcher = FuzzyMatcher::new(pattern.as_bytes(), edits, false).unwrap();
+        matcher.find_iter(data.as_bytes()).into_iter().map(|(start, end)| &data[start..end]).collect()
+    }
+
+    #[test]
+    fn test_edit_kinds() {
+        assert_eq!(spans("connection", 1, "conection lost"), ["conection"]);
+        assert_eq!(spans("connection", 1, "connnection lost"), ["connnection"]);
+        assert_eq!(spans("connection", 1, "connectoin"), Vec::<&str>::new());
+        assert_eq!(spans("connection", 2, "connectoin"), ["connectoin"]);
+        assert_eq!(spans("connection", 2, "a connexion b"), ["connexion"]);
+        // An exact occurrence is reported whole
+        assert_eq!(spans("hello", 1, "hello hallo help"), ["hello", "hallo"]);
+        assert_eq!(spans("hello", 0, "hello hallo"), ["hello"]);
+    }
+
+    #[test]
+    fn test_lines_and_case() {
+        assert_eq!(spans("error", 1, "err\nor"), Vec::<&str>::new());
+        assert_eq!(spans("error", 1, "err\nor\neror"), ["eror"]);
+        let matcher = FuzzyMatcher::new(b"Timeout", 1, true).unwrap();
+        assert_eq!(matcher.find_iter(b"TIMEOT"), [(0, 6)]);
+
+        assert!(FuzzyMatcher::new(b"ab", 2, false).is_err());
+        assert!(FuzzyMatcher::new(&[b'a'; 65], 1, false).is_err());
+    }
+}
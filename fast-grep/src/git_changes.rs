@@ -0,0 +1,194 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::file_processor::is_stdin;
+
+/// Lines added or modified relative to a git ref, per file, for `--changed`.
+///
+/// Built from `git diff -U0 <ref>`, which covers staged and unstaged edits;
+/// untracked files that are not ignored count as changed throughout. Paths
+/// in different repositories are diffed each in their own, against the
+/// same ref.
+#[derive(Debug, Default)]
+pub struct ChangedLines {
+    /// Canonical path to 1-based line ranges; `None` means the whole file
+    files: HashMap<PathBuf, Option<Vec<RangeInclusive<usize>>>>,
+}
+
+impl ChangedLines {
+    /// Ask git for the changes in the repositories containing `paths`;
+    /// every path must be in one
+    pub fn collect(reference: &str, paths: &[PathBuf]) -> Result<Self> {
+        let mut roots = BTreeSet::new();
+        for path in paths.iter().filter(|path| !is_stdin(path)) {
+            roots.insert(repository_root(path)?);
+        }
+        let mut changed = Self::default();
+        for root in roots {
+            changed.files.extend(Self::collect_repository(reference, &root)?.files);
+        }
+        Ok(changed)
+    }
+
+    fn collect_repository(reference: &str, root: &Path) -> Result<Self> {
+        let diff = git(
+            root,
+            &["-c", "core.quotePath=false", "diff", "-U0", "--no-color", "--no-ext-diff", reference, "--"],
+        )?;
+        let untracked = git(
+            root,
+            &["-c", "core.quotePath=false", "ls-files", "--others", "--exclude-standard"],
+        )?;
+
+        let mut files: HashMap<PathBuf, Option<Vec<RangeInclusive<usize>>>> = parse_diff(&diff)
+            .into_iter()
+            .map(|(file, ranges)| (root.join(file), Some(ranges)))
+            .collect();
+        files.extend(untracked.lines().map(|file| (root.join(file), None)));
+
+        // Match the walker's paths however they were spelled
+        let files = files
+            .into_iter()
+            .filter_map(|(file, ranges)| Some((file.canonicalize().ok()?, ranges)))
+            .collect();
+        Ok(Self { files })
+    }
+
+    fn lookup(&self, path: &Path) -> Option<&Option<Vec<RangeInclusive<usize>>>> {
+        self.files.get(&path.canonicalize().ok()?)
+    }
+
+    /// Whether `path` has any added or modified lines
+    pub fn contains_file(&self, path: &Path) -> bool {
+        self.lookup(path).is_some()
+    }
+
+    /// The changed line ranges of `path`, or `None` when it is unchanged;
+    /// a file that is new throughout gets a single unbounded range
+    pub fn for_file(&self, path: &Path) -> Option<Vec<RangeInclusive<usize>>> {
+        self.lookup(path).map(|ranges| ranges.clone().unwrap_or_else(|| vec![0..=usize::MAX]))
+    }
+}
+
+/// The top of the work tree holding `path`
+fn repository_root(path: &Path) -> Result<PathBuf> {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."))
+    };
+    let root = git(dir, &["rev-parse", "--show-toplevel"])
+        .with_context(|| format!("--changed: {} is not in a git repository", path.display()))?;
+    Ok(PathBuf::from(root.trim_end_matches('\n')))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("--changed needs git on PATH")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("--changed: git {}: {}", args.join(" "), stderr.trim());
+    }
+    String::from_utf8(output.stdout).map_err(|_| anyhow!("--changed: git printed invalid UTF-8"))
+}
+
+/// New-side line ranges per file from `git diff -U0` output. Deleted files
+/// and hunks that only remove lines contribute nothing.
+fn parse_diff(diff: &str) -> HashMap<PathBuf, Vec<RangeInclusive<usize>>> {
+    let mut files: HashMap<PathBuf, Vec<RangeInclusive<usize>>> = HashMap::new();
+    let mut current: Option<PathBuf> = None;
+    // Body lines left in the current hunk, which may look like headers
+    let mut pending = 0;
+
+    for line in diff.lines() {
+        if pending > 0 {
+            pending -= 1;
+        } else if let Some(target) = line.strip_prefix("+++ ") {
+            current = target.trim_matches('"').strip_prefix("b/").map(PathBuf::from);
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            let mut ranges = hunk.split(' ').take(2).map(|range| {
+                let range = &range[1.min(range.len())..];
+                match range.split_once(',') {
+                    Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+                    None => (range.parse().unwrap_or(0), 1),
+                }
+            });
+            let (_, removed) = ranges.next().unwrap_or((0, 0));
+            let (start, added): (usize, usize) = ranges.next().unwrap_or((0, 0));
+            pending = removed + added;
+            if let Some(file) = current.as_ref().filter(|_| added > 0) {
+                files.entry(file.clone()).or_default().push(start..=start + added - 1);
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn main() {
+-    old();
++    new();
+@@ -10,2 +9,0 @@ fn gone() {
+-x
+--- a/not-a-header
+@@ -20,0 +19,3 @@ fn added() {
++a
+++++ b/not-a-file
++c
+diff --git a/removed.rs b/removed.rs
+deleted file mode 100644
+--- a/removed.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-one
+-two
+";
+        let files = parse_diff(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[Path::new("src/lib.rs")], vec![3..=3, 19..=21]);
+    }
+
+    #[test]
+    fn test_collects_each_repository() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut files = Vec::new();
+        for name in ["one", "two"] {
+            let repo = temp_dir.path().join(name);
+            std::fs::create_dir(&repo).unwrap();
+            let file = repo.join("notes.txt");
+            std::fs::write(&file, "a\nb\n").unwrap();
+            let commit = ["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "start"];
+            for args in [&["init", "-q"][..], &["add", "."], &commit] {
+                git(&repo, args).unwrap();
+            }
+            std::fs::write(&file, "a\nchanged\n").unwrap();
+            files.push(file);
+        }
+
+        let changed = ChangedLines::collect("HEAD", &[files[0].clone(), temp_dir.path().join("two")]).unwrap();
+        for file in &files {
+            assert_eq!(changed.for_file(file), Some(vec![2..=2]));
+        }
+
+        let outside = tempfile::TempDir::new().unwrap();
+        let error = ChangedLines::collect("HEAD", &[files[0].clone(), outside.path().to_path_buf()]).unwrap_err();
+        assert!(error.to_string().contains("is not in a git repository"), "{}", error);
+    }
+}
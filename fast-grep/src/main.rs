@@ -1,11 +1,24 @@
 use anyhow::Result;
 use clap::Parser;
+use std::io::Write;
 
+mod cache;
+mod cancel;
 mod cli;
 mod search;
 mod file_processor;
+mod follow;
+mod fuzzy;
+mod git_changes;
+mod memory;
 mod pattern_matcher;
+mod scope_filter;
+mod summary;
 mod output;
+mod colors;
+mod tuning;
+mod virtual_fs;
+mod sparse;
 mod worker;
 mod errors;
 
@@ -16,7 +29,13 @@ fn main() -> Result<()> {
     let args = Args::parse();
     
     let search_engine = SearchEngine::new(args)?;
-    search_engine.run()
+    search_engine.cancel_token().install_interrupt_handler()?;
+
+    if let Some(cancelled) = search_engine.run()? {
+        std::io::stdout().flush()?;
+        std::process::exit(cancelled.exit_code());
+    }
+    Ok(())
 }
 
 // Architecture Overview:
@@ -29,11 +48,13 @@ fn main() -> Result<()> {
 //    - Coordinates file discovery and processing
 //    - Manages worker pool for parallel processing
 //    - Handles results aggregation
+//    - Per-directory rollups for --summary dirs (summary.rs)
 //
 // 3. FileProcessor (file_processor.rs) - Smart file reading
 //    - Memory mapping for large files (>1MB)
 //    - Regular buffered reading for small files
 //    - Binary file detection and skipping
+//    - Windowed streaming of big files under --max-memory (memory.rs)
 //
 // 4. PatternMatcher (pattern_matcher.rs) - Optimized matching
 //    - Aho-Corasick for literal string searches
@@ -48,4 +69,15 @@ fn main() -> Result<()> {
 // 6. Worker (worker.rs) - Parallel processing
 //    - File queue management
 //    - Result collection and ordering
-//    - Load balancing across CPU cores
\ No newline at end of file
+//    - Load balancing across CPU cores
+//    - Worker count and mmap use tuned to IO- or CPU-bound work (tuning.rs)
+//
+// 7. Cache (cache.rs) - Result memoization
+//    - Per-file results keyed by path, mtime and size
+//    - One cache file per distinct search, kept only with --cache
+//    - Old files evicted by age and total size
+//
+// 8. Follow (follow.rs) - --follow after the search
+//    - Matched files read as they grow, tail -f style
+//    - notify watcher on their directories, with a polling fallback
+//    - Truncation and rotation restart a file from its start
\ No newline at end of file
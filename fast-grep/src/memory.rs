@@ -0,0 +1,109 @@
+use std::sync::{Condvar, Mutex};
+
+/// Smallest window a file is streamed in under `--max-memory`, however
+/// small the budget
+pub const MIN_WINDOW: usize = 64 * 1024;
+
+/// `--max-memory`: the file content all workers may hold at once.
+///
+/// A file no bigger than a window is read whole, as without a budget; a
+/// bigger one is streamed a window at a time, with room for as much again
+/// of context carried over from the previous window. Windows are sized so
+/// that every worker can stream at once within the budget. Workers reserve
+/// what they are about to read and wait while the budget is spent.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: u64,
+    window: usize,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64, threads: usize) -> Self {
+        let per_worker = limit / (2 * threads.max(1) as u64);
+        Self {
+            limit,
+            window: usize::try_from(per_worker).unwrap_or(usize::MAX).max(MIN_WINDOW),
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Largest file read whole, and the size of each read of a bigger one
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// What streaming a file takes: a window and its carried-over context
+    pub fn streamed_file(&self) -> u64 {
+        2 * self.window as u64
+    }
+
+    /// Wait until `bytes` are free and take them; more than the whole
+    /// budget waits for all of it
+    pub fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut used = self.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *used + bytes > self.limit {
+            used = self.freed.wait(used).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *used += bytes;
+        Reservation { budget: self, bytes }
+    }
+
+    fn release(&self, bytes: u64) {
+        let mut used = self.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *used -= bytes;
+        self.freed.notify_all();
+    }
+}
+
+/// Part of a [`MemoryBudget`], given back when dropped
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.budget.release(self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_window_size() {
+        assert_eq!(MemoryBudget::new(64 << 20, 4).window(), 8 << 20);
+        assert_eq!(MemoryBudget::new(1 << 20, 16).window(), MIN_WINDOW);
+    }
+
+    #[test]
+    fn test_reservations_stay_within_limit() {
+        let budget = MemoryBudget::new(1000, 1);
+        let held = AtomicU64::new(0);
+        let peak = AtomicU64::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..50 {
+                        let _reservation = budget.reserve(300);
+                        let now = held.fetch_add(300, Ordering::SeqCst) + 300;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::yield_now();
+                        held.fetch_sub(300, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 900);
+        // More than everything waits for everything, rather than forever
+        drop(budget.reserve(5000));
+        assert_eq!(*budget.used.lock().unwrap(), 0);
+    }
+}
@@ -1,6 +1,11 @@
-use colored::*;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+use crate::colors::{paint, ColorScheme};
+use crate::file_processor::is_stdin;
+use crate::summary::DirCounts;
+use crate::worker::SearchStats;
+
 pub struct OutputFormatter {
     show_line_numbers: bool,
     show_filenames: bool,
@@ -13,9 +18,26 @@ pub struct OutputFormatter {
     count_only: bool,
     files_only: bool,
     files_without_matches: bool,
+    /// `--count-matches`: count every match rather than matching lines
+    count_matches: bool,
+    /// `-Z`: end file names with NUL
+    null: bool,
+    /// `-z`: end output records with NUL
+    null_data: bool,
+    /// `--label`: the name standard input is shown under
+    label: Option<String>,
+    /// `--max-columns`: lines longer than this many characters are omitted
+    max_columns: Option<usize>,
+    /// `--max-columns-preview`: show long lines cut down around the first
+    /// match instead
+    max_columns_preview: bool,
+    /// `--vimgrep`: one FILE:LINE:COLUMN:LINE record per match
+    vimgrep: bool,
+    colors: ColorScheme,
 }
 
 impl OutputFormatter {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         show_line_numbers: bool,
         show_filenames: bool,
@@ -41,6 +63,115 @@ impl OutputFormatter {
             count_only,
             files_only,
             files_without_matches,
+            count_matches: false,
+            null: false,
+            null_data: false,
+            label: None,
+            max_columns: None,
+            max_columns_preview: false,
+            vimgrep: false,
+            colors: ColorScheme::default(),
+        }
+    }
+
+    pub fn with_colors(mut self, colors: ColorScheme) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn with_count_matches(mut self, count_matches: bool) -> Self {
+        self.count_matches = count_matches;
+        self
+    }
+
+    /// End file names (`-Z`) and output records (`-z`) with NUL; JSON
+    /// output keeps its own framing
+    pub fn with_null(mut self, null: bool, null_data: bool) -> Self {
+        self.null = null && !self.json_output;
+        self.null_data = null_data && !self.json_output;
+        self
+    }
+
+    /// Show standard input as `label` rather than "(standard input)"
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// Omit lines longer than `max_columns` characters, or with `preview`
+    /// show that much of them around the first match; JSON output always
+    /// carries whole lines
+    pub fn with_max_columns(mut self, max_columns: Option<usize>, preview: bool) -> Self {
+        self.max_columns = max_columns;
+        self.max_columns_preview = preview;
+        self
+    }
+
+    /// Print each match on a line of its own as FILE:LINE:COLUMN:LINE, the
+    /// column counting bytes from 1 like vim's `%c`
+    pub fn with_vimgrep(mut self, vimgrep: bool) -> Self {
+        self.vimgrep = vimgrep;
+        if vimgrep {
+            self.show_filenames = true;
+            self.show_line_numbers = true;
+        }
+        self
+    }
+
+    /// The limit a line of `line` is over, if any
+    fn too_long(&self, line: &str) -> Option<usize> {
+        let max_columns = self.max_columns?;
+        // Only count characters when the bytes could exceed the limit
+        (line.len() > max_columns && line.chars().count() > max_columns).then_some(max_columns)
+    }
+
+    /// The name a file is shown under
+    pub fn display_name(&self, file_path: &Path) -> String {
+        if is_stdin(file_path) {
+            self.label.clone().unwrap_or_else(|| "(standard input)".to_string())
+        } else {
+            file_path.display().to_string()
+        }
+    }
+
+    /// What follows each output record
+    pub fn record_end(&self) -> &'static str {
+        if self.null_data { "\0" } else { "\n" }
+    }
+
+    /// Whether a blank line goes between the matches of different files;
+    /// `--vimgrep` output stays one record per line for editors to parse
+    pub fn separates_files(&self) -> bool {
+        !self.vimgrep
+    }
+
+    /// What follows each file name printed on its own
+    pub fn filename_end(&self) -> &'static str {
+        if self.null { "\0" } else { "\n" }
+    }
+
+    /// Color `text` with `style` when colored output is enabled
+    fn paint(&self, text: &str, style: &str) -> String {
+        if self.use_colors {
+            paint(text, style)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn push_prefix(&self, output: &mut String, file_path: &Path, line_number: Option<usize>, separator: &str) {
+        if self.show_filenames {
+            output.push_str(&self.paint(&self.display_name(file_path), &self.colors.filename));
+            if self.null {
+                output.push('\0');
+            } else {
+                output.push_str(&self.paint(separator, &self.colors.separator));
+            }
+        }
+
+        if let Some(line_number) = line_number.filter(|_| self.show_line_numbers) {
+            output.push_str(&self.paint(&line_number.to_string(), &self.colors.line_number));
+            output.push_str(&self.paint(separator, &self.colors.separator));
         }
     }
 
@@ -51,66 +182,159 @@ impl OutputFormatter {
         line_content: &str,
         match_start: usize,
         match_end: usize,
+    ) -> String {
+        self.format_spans(file_path, line_number, line_content, &[(match_start, match_end)], &[])
+    }
+
+    /// Format a matching line with all of its matches; with `-o` this is one
+    /// output line per match, and may be empty if nothing is left to print
+    pub fn format_result(&self, result: &MatchResult) -> String {
+        self.format_spans(
+            &result.file_path,
+            result.line_number,
+            &result.line_content,
+            &result.matches,
+            &result.replacements,
+        )
+    }
+
+    fn format_spans(
+        &self,
+        file_path: &Path,
+        line_number: usize,
+        line_content: &str,
+        spans: &[(usize, usize)],
+        replacements: &[Option<String>],
     ) -> String {
         if self.json_output {
-            self.format_json_match(file_path, line_number, line_content, match_start, match_end)
-        } else {
-            self.format_text_match(file_path, line_number, line_content, match_start, match_end)
+            // Match positions are reported one object per match
+            if spans.is_empty() {
+                return self.format_json_match(file_path, line_number, line_content, 0, 0);
+            }
+            return spans
+                .iter()
+                .map(|&(start, end)| self.format_json_match(file_path, line_number, line_content, start, end))
+                .collect::<Vec<_>>()
+                .join("\n");
         }
+
+        if self.only_matching {
+            return self.format_only_matching(file_path, line_number, line_content, spans, replacements);
+        }
+
+        if self.vimgrep {
+            return self.format_vimgrep(file_path, line_number, line_content, spans, replacements);
+        }
+
+        let mut output = String::new();
+        self.push_prefix(&mut output, file_path, Some(line_number), ":");
+        self.push_line(&mut output, line_content, spans, replacements);
+        output
     }
 
-    fn format_text_match(
+    /// One record per match, each showing the whole line with that match
+    /// highlighted; a line without matches (`-v`) is one record at column 1
+    fn format_vimgrep(
         &self,
         file_path: &Path,
         line_number: usize,
         line_content: &str,
-        match_start: usize,
-        match_end: usize,
+        spans: &[(usize, usize)],
+        replacements: &[Option<String>],
+    ) -> String {
+        let records: Vec<String> = if spans.is_empty() {
+            vec![self.format_vimgrep_record(file_path, line_number, line_content, 0, &[], &[])]
+        } else {
+            spans
+                .iter()
+                .enumerate()
+                .map(|(index, span)| {
+                    let replacement = replacements.get(index).map(std::slice::from_ref).unwrap_or_default();
+                    self.format_vimgrep_record(file_path, line_number, line_content, span.0, std::slice::from_ref(span), replacement)
+                })
+                .collect()
+        };
+        records.join(self.record_end())
+    }
+
+    fn format_vimgrep_record(
+        &self,
+        file_path: &Path,
+        line_number: usize,
+        line_content: &str,
+        column: usize,
+        spans: &[(usize, usize)],
+        replacements: &[Option<String>],
     ) -> String {
         let mut output = String::new();
+        self.push_prefix(&mut output, file_path, Some(line_number), ":");
+        output.push_str(&self.paint(&(column + 1).to_string(), &self.colors.line_number));
+        output.push_str(&self.paint(":", &self.colors.separator));
+        // A trailing CR would end up in the editor's quickfix text
+        self.push_line(&mut output, line_content.strip_suffix('\r').unwrap_or(line_content), spans, replacements);
+        output
+    }
 
-        // File path
-        if self.show_filenames {
-            let file_str = file_path.display().to_string();
-            if self.use_colors {
-                output.push_str(&file_str.magenta().bold().to_string());
-            } else {
-                output.push_str(&file_str);
-            }
-            output.push(':');
+    /// A matching line, or what `--max-columns` leaves of it
+    fn push_line(&self, output: &mut String, line_content: &str, spans: &[(usize, usize)], replacements: &[Option<String>]) {
+        let Some(max_columns) = self.too_long(line_content) else {
+            output.push_str(&self.highlight_matches(line_content, spans, replacements));
+            return;
+        };
+        if !self.max_columns_preview {
+            let count = spans.len();
+            output.push_str(&format!("[Omitted long line with {} match{}]", count, if count == 1 { "" } else { "es" }));
+            return;
         }
 
-        // Line number
-        if self.show_line_numbers {
-            let line_str = line_number.to_string();
-            if self.use_colors {
-                output.push_str(&line_str.green().to_string());
-            } else {
-                output.push_str(&line_str);
-            }
-            output.push(':');
+        let focus = spans.first().copied().unwrap_or((0, 0));
+        let (start, end) = preview_window(line_content, focus, max_columns);
+        // Matches are cut to the window, dropping those entirely outside it
+        let (spans, replacements): (Vec<_>, Vec<_>) = spans
+            .iter()
+            .enumerate()
+            .filter(|(_, &(match_start, match_end))| match_end > start && match_start < end)
+            .map(|(index, &(match_start, match_end))| {
+                let span = (match_start.max(start) - start, match_end.min(end) - start);
+                (span, replacements.get(index).cloned().flatten())
+            })
+            .unzip();
+        let replacements: Vec<Option<String>> = if replacements.iter().any(Option::is_some) { replacements } else { Vec::new() };
+        if start > 0 {
+            output.push('…');
         }
-
-        // Content - show only matching part if only_matching is enabled
-        if self.only_matching {
-            if match_start < match_end && match_end <= line_content.len() {
-                let match_text = &line_content[match_start..match_end];
-                if self.use_colors {
-                    output.push_str(&match_text.red().bold().to_string());
-                } else {
-                    output.push_str(match_text);
-                }
-            }
-        } else {
-            // Line content with highlighted matches
-            if self.use_colors {
-                output.push_str(&self.highlight_match(line_content, match_start, match_end));
-            } else {
-                output.push_str(line_content);
-            }
+        output.push_str(&self.highlight_matches(&line_content[start..end], &spans, &replacements));
+        if end < line_content.len() {
+            output.push('…');
         }
+    }
 
-        output
+    fn format_only_matching(
+        &self,
+        file_path: &Path,
+        line_number: usize,
+        line_content: &str,
+        spans: &[(usize, usize)],
+        replacements: &[Option<String>],
+    ) -> String {
+        let mut lines = Vec::new();
+        for (index, &(start, end)) in spans.iter().enumerate() {
+            let text = match replacements.get(index) {
+                Some(Some(replacement)) => replacement.as_str(),
+                // The requested group did not take part in this match
+                Some(None) => continue,
+                None => match line_content.get(start..end) {
+                    Some(text) if !text.is_empty() => text,
+                    _ => continue,
+                },
+            };
+
+            let mut output = String::new();
+            self.push_prefix(&mut output, file_path, Some(line_number), ":");
+            output.push_str(&self.paint(text, &self.colors.matched));
+            lines.push(output);
+        }
+        lines.join(self.record_end())
     }
 
     fn format_json_match(
@@ -123,7 +347,7 @@ impl OutputFormatter {
     ) -> String {
         format!(
             r#"{{"file":"{}","line":{},"content":"{}","match_start":{},"match_end":{}}}"#,
-            file_path.display(),
+            self.display_name(file_path),
             line_number,
             line_content.replace('"', r#"\""#),
             match_start,
@@ -131,34 +355,35 @@ impl OutputFormatter {
         )
     }
 
-    fn highlight_match(&self, line: &str, start: usize, end: usize) -> String {
+    /// Line text with every match highlighted (and replaced, for `--replace`)
+    fn highlight_matches(&self, line: &str, spans: &[(usize, usize)], replacements: &[Option<String>]) -> String {
         let mut result = String::new();
-        
-        // Add text before match
-        if start > 0 {
-            result.push_str(&line[..start]);
-        }
-        
-        // Add highlighted match
-        if end <= line.len() {
-            let match_text = &line[start..end];
-            result.push_str(&match_text.red().bold().to_string());
-        }
-        
-        // Add text after match
-        if end < line.len() {
-            result.push_str(&line[end..]);
+        let mut position = 0;
+
+        for (index, &(start, end)) in spans.iter().enumerate() {
+            // Skip matches running past the line (multi-line regexes)
+            let (Some(before), Some(match_text)) = (line.get(position..start), line.get(start..end)) else {
+                continue;
+            };
+            result.push_str(&self.paint(before, &self.colors.selected_line));
+            let text = match replacements.get(index) {
+                Some(Some(replacement)) => replacement.as_str(),
+                _ => match_text,
+            };
+            result.push_str(&self.paint(text, &self.colors.matched));
+            position = end;
         }
-        
+
+        result.push_str(&self.paint(&line[position..], &self.colors.selected_line));
         result
     }
 
     pub fn format_file_header(&self, file_path: &Path) -> String {
-        if self.use_colors {
-            format!("{}:", file_path.display().to_string().cyan().bold())
-        } else {
-            format!("{}:", file_path.display())
-        }
+        format!(
+            "{}{}",
+            self.paint(&self.display_name(file_path), &self.colors.filename),
+            self.paint(":", &self.colors.separator)
+        )
     }
 
     pub fn format_context_line(
@@ -169,95 +394,181 @@ impl OutputFormatter {
         is_before: bool,
     ) -> String {
         let mut output = String::new();
-
-        // File path (dimmed for context)
-        if self.show_filenames {
-            let file_str = file_path.display().to_string();
-            if self.use_colors {
-                output.push_str(&file_str.dimmed().to_string());
-            } else {
-                output.push_str(&file_str);
+        let separator = if is_before { "-" } else { "+" };
+        self.push_prefix(&mut output, file_path, Some(line_number), separator);
+        match self.too_long(line_content) {
+            None => output.push_str(&self.paint(line_content, &self.colors.context_line)),
+            Some(max_columns) if self.max_columns_preview => {
+                let (_, end) = preview_window(line_content, (0, 0), max_columns);
+                output.push_str(&self.paint(&line_content[..end], &self.colors.context_line));
+                output.push('…');
             }
-            output.push(if is_before { '-' } else { '+' });
+            Some(_) => output.push_str("[Omitted long context line]"),
         }
+        output
+    }
 
-        // Line number (dimmed for context)
-        if self.show_line_numbers {
-            let line_str = line_number.to_string();
-            if self.use_colors {
-                output.push_str(&line_str.dimmed().to_string());
-            } else {
-                output.push_str(&line_str);
-            }
-            output.push(if is_before { '-' } else { '+' });
-        }
+    pub fn format_separator(&self) -> String {
+        self.paint("--", &self.colors.separator)
+    }
 
-        // Line content (dimmed for context)
-        if self.use_colors {
-            output.push_str(&line_content.dimmed().to_string());
+    /// A file's count line: matching lines, or matches with
+    /// `--count-matches`. The JSON record carries both.
+    pub fn format_count(&self, file_path: &Path, lines: usize, matches: usize) -> String {
+        let count = if self.count_matches { matches } else { lines };
+        if self.json_output {
+            format!(
+                r#"{{"file":"{}","count":{},"lines":{},"matches":{}}}"#,
+                self.display_name(file_path),
+                count,
+                lines,
+                matches
+            )
         } else {
-            output.push_str(line_content);
+            let mut output = String::new();
+            self.push_prefix(&mut output, file_path, None, ":");
+            output.push_str(&count.to_string());
+            output
         }
-
-        output
     }
 
-    pub fn format_separator(&self) -> String {
-        if self.use_colors {
-            "--".dimmed().to_string()
+    /// One directory of `--summary dirs`: matches, matching lines and
+    /// files in aligned columns before the directory, or a
+    /// `"type":"directory"` object
+    pub fn format_dir_summary(&self, dir: &Path, counts: &DirCounts) -> String {
+        if self.json_output {
+            format!(
+                r#"{{"type":"directory","directory":"{}","matches":{},"lines":{},"files":{}}}"#,
+                self.display_name(dir),
+                counts.matches,
+                counts.lines,
+                counts.files
+            )
         } else {
-            "--".to_string()
+            format!(
+                "{:>8} {:>8} {:>6}  {}",
+                counts.matches,
+                counts.lines,
+                counts.files,
+                self.paint(&self.display_name(dir), &self.colors.filename)
+            )
         }
     }
 
-    pub fn format_count(&self, file_path: &Path, count: usize) -> String {
+    pub fn format_stats(&self, stats: &SearchStats) -> String {
+        let elapsed_ms = stats.elapsed.as_secs_f64() * 1000.0;
+
         if self.json_output {
             format!(
-                r#"{{"file":"{}","count":{}}}"#,
-                file_path.display(),
-                count
+                concat!(
+                    r#"{{"type":"summary","files_searched":{},"files_with_matches":{},"#,
+                    r#""files_skipped":{{"binary":{},"ignored":{},"too_large":{}}},"#,
+                    r#""matches":{},"matched_lines":{},"bytes_searched":{},"#,
+                    r#""elapsed_ms":{:.3},"throughput_mb_per_second":{:.2}{}}}"#
+                ),
+                stats.files_processed,
+                stats.files_with_matches,
+                stats.skipped_binary,
+                stats.skipped_ignored,
+                stats.skipped_too_large,
+                stats.total_matches,
+                stats.matched_lines,
+                stats.bytes_processed,
+                elapsed_ms,
+                stats.throughput_mb_per_second(),
+                stats
+                    .cache
+                    .map(|(hits, misses)| format!(r#","cache":{{"hits":{},"misses":{}}}"#, hits, misses))
+                    .unwrap_or_default()
             )
         } else {
-            let mut output = String::new();
-            
-            if self.show_filenames {
-                let file_str = file_path.display().to_string();
-                if self.use_colors {
-                    output.push_str(&file_str.magenta().bold().to_string());
-                } else {
-                    output.push_str(&file_str);
-                }
-                output.push(':');
-            }
-            
-            let count_str = count.to_string();
-            if self.use_colors {
-                output.push_str(&count_str.yellow().bold().to_string());
-            } else {
-                output.push_str(&count_str);
-            }
-            
-            output
+            let cache = stats
+                .cache
+                .map(|(hits, misses)| format!("\n  Cache: {} hits, {} misses", hits, misses))
+                .unwrap_or_default();
+            format!(
+                "Search statistics:\n  Files searched: {}\n  Files with matches: {}\n  Files skipped: {} (binary: {}, ignored: {}, too large: {})\n  Matches: {}\n  Matched lines: {}\n  Bytes searched: {}\n  Elapsed: {:.3} ms\n  Throughput: {:.2} MB/s{}",
+                stats.files_processed,
+                stats.files_with_matches,
+                stats.files_skipped(),
+                stats.skipped_binary,
+                stats.skipped_ignored,
+                stats.skipped_too_large,
+                stats.total_matches,
+                stats.matched_lines,
+                stats.bytes_processed,
+                elapsed_ms,
+                stats.throughput_mb_per_second(),
+                cache
+            )
         }
     }
 
+    /// A file whose name matches, for `--with-filename-match`: the name with
+    /// the matches highlighted, or one `"type":"filename"` object per match
+    pub fn format_filename_match(&self, file_path: &Path, spans: &[(usize, usize)]) -> String {
+        let name = self.display_name(file_path);
+        if self.json_output {
+            return spans
+                .iter()
+                .map(|&(start, end)| {
+                    format!(r#"{{"type":"filename","file":"{}","match_start":{},"match_end":{}}}"#, name, start, end)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let mut output = String::new();
+        let mut position = 0;
+        for &(start, end) in spans {
+            let (Some(before), Some(matched)) = (name.get(position..start), name.get(start..end)) else {
+                continue;
+            };
+            output.push_str(&self.paint(before, &self.colors.filename));
+            output.push_str(&self.paint(matched, &self.colors.matched));
+            position = end;
+        }
+        output.push_str(&self.paint(&name[position..], &self.colors.filename));
+        output.push_str(&self.paint(":", &self.colors.separator));
+        output.push_str("(file name)");
+        output
+    }
+
     pub fn format_filename_only(&self, file_path: &Path) -> String {
         if self.json_output {
-            format!(r#"{{"file":"{}"}}"#, file_path.display())
-        } else if self.use_colors {
-            file_path.display().to_string().magenta().bold().to_string()
+            format!(r#"{{"file":"{}"}}"#, self.display_name(file_path))
         } else {
-            file_path.display().to_string()
+            self.paint(&self.display_name(file_path), &self.colors.filename)
         }
     }
 }
 
+/// The byte range of the `max_columns` characters of `line` shown for
+/// `--max-columns-preview`: centred on `focus` (the first match) as far as
+/// the line allows, and starting at `focus` if the match alone is too wide
+fn preview_window(line: &str, focus: (usize, usize), max_columns: usize) -> (usize, usize) {
+    let boundaries: Vec<usize> = line.char_indices().map(|(index, _)| index).chain([line.len()]).collect();
+    let total = boundaries.len() - 1;
+    let char_at = |byte: usize| boundaries.partition_point(|&boundary| boundary < byte);
+    let (focus_start, focus_end) = (char_at(focus.0), char_at(focus.1));
+
+    let lead = max_columns.saturating_sub(focus_end - focus_start) / 2;
+    let first = focus_start.saturating_sub(lead).min(total.saturating_sub(max_columns));
+    let last = (first + max_columns).min(total);
+    (boundaries[first], boundaries[last])
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchResult {
     pub file_path: std::path::PathBuf,
     pub line_number: usize,
     pub line_content: String,
     pub match_start: usize,
     pub match_end: usize,
+    /// Every match on the line as (start, end) offsets, first one included
+    pub matches: Vec<(usize, usize)>,
+    /// `--group` / `--replace` text for each entry of `matches`, if enabled
+    pub replacements: Vec<Option<String>>,
     pub context_before: Vec<(usize, String)>,
     pub context_after: Vec<(usize, String)>,
 }
@@ -276,11 +587,22 @@ impl MatchResult {
             line_content,
             match_start,
             match_end,
+            matches: if match_start < match_end {
+                vec![(match_start, match_end)]
+            } else {
+                Vec::new()
+            },
+            replacements: Vec::new(),
             context_before: Vec::new(),
             context_after: Vec::new(),
         }
     }
 
+    /// Record a further match on the same line
+    pub fn add_match(&mut self, start: usize, end: usize) {
+        self.matches.push((start, end));
+    }
+
     pub fn add_context_before(&mut self, line_number: usize, content: String) {
         self.context_before.push((line_number, content));
     }
@@ -288,6 +610,16 @@ impl MatchResult {
     pub fn add_context_after(&mut self, line_number: usize, content: String) {
         self.context_after.push((line_number, content));
     }
+
+    /// The result with `offset` added to its line numbers, context included,
+    /// as for a part of a file searched on its own
+    pub fn renumbered(mut self, offset: usize) -> Self {
+        self.line_number += offset;
+        for (line_number, _) in self.context_before.iter_mut().chain(self.context_after.iter_mut()) {
+            *line_number += offset;
+        }
+        self
+    }
 }
 
 #[cfg(test)]
@@ -320,6 +652,126 @@ mod tests {
         assert_eq!(result, "test.txt:42:hello world");
     }
 
+    #[test]
+    fn test_count_lines_or_matches() {
+        let formatter = |json_output| OutputFormatter::new(
+            false, true, false, json_output, 0, 0, false, false, true, false, false,
+        );
+        let path = PathBuf::from("a.txt");
+
+        assert_eq!(formatter(false).format_count(&path, 2, 5), "a.txt:2");
+        assert_eq!(formatter(false).with_count_matches(true).format_count(&path, 2, 5), "a.txt:5");
+        assert_eq!(
+            formatter(true).with_count_matches(true).format_count(&path, 2, 5),
+            r#"{"file":"a.txt","count":5,"lines":2,"matches":5}"#
+        );
+    }
+
+    #[test]
+    fn test_null_separators() {
+        let formatter = OutputFormatter::new(
+            true, true, false, false, 0, 0, false, false, false, false, false,
+        )
+        .with_null(true, true);
+        let path = PathBuf::from("a.txt");
+
+        assert_eq!(formatter.format_match(&path, 3, "hit", 0, 3), "a.txt\u{0}3:hit");
+        assert_eq!(formatter.format_count(&path, 2, 2), "a.txt\u{0}2");
+        assert_eq!((formatter.filename_end(), formatter.record_end()), ("\0", "\0"));
+
+        let json = OutputFormatter::new(
+            false, true, false, true, 0, 0, false, false, false, true, false,
+        )
+        .with_null(true, true);
+        assert_eq!((json.filename_end(), json.record_end()), ("\n", "\n"));
+    }
+
+    #[test]
+    fn test_stdin_label() {
+        let stdin = PathBuf::from("-");
+        let unlabeled = OutputFormatter::new(
+            false, true, false, false, 0, 0, false, false, false, false, false,
+        );
+        assert_eq!(unlabeled.format_match(&stdin, 1, "hit", 0, 3), "(standard input):hit");
+
+        let labeled = OutputFormatter::new(
+            false, true, false, false, 0, 0, false, false, false, false, false,
+        )
+        .with_label(Some("app.log.gz".to_string()));
+        assert_eq!(labeled.format_match(&stdin, 1, "hit", 0, 3), "app.log.gz:hit");
+        assert_eq!(labeled.format_filename_only(&PathBuf::from("a.txt")), "a.txt");
+    }
+
+    #[test]
+    fn test_max_columns() {
+        let formatter = |preview| {
+            OutputFormatter::new(true, false, false, false, 0, 0, false, false, false, false, false)
+                .with_max_columns(Some(10), preview)
+        };
+        let path = PathBuf::from("app.min.js");
+        let line = "var a=1;var needle=2;var b=3;";
+        let mut result = MatchResult::new(path.clone(), 1, line.to_string(), 12, 18);
+        result.add_match(0, 3);
+
+        assert_eq!(formatter(false).format_match(&path, 1, "short hit", 6, 9), "1:short hit");
+        assert_eq!(formatter(false).format_result(&result), "1:[Omitted long line with 2 matches]");
+        assert_eq!(formatter(true).format_match(&path, 1, line, 12, 18), "1:…r needle=2…");
+        assert_eq!(formatter(true).format_match(&path, 1, line, 0, 3), "1:var a=1;va…");
+        assert_eq!(formatter(true).format_match(&path, 1, line, 26, 29), "1:…2;var b=3;");
+        assert_eq!(formatter(true).format_context_line(&path, 2, line, false), "2+var a=1;va…");
+        assert_eq!(formatter(false).format_context_line(&path, 2, line, true), "2-[Omitted long context line]");
+        // Characters rather than bytes are counted
+        assert_eq!(formatter(false).format_match(&path, 1, "éééé ééééé", 0, 2), "1:éééé ééééé");
+    }
+
+    #[test]
+    fn test_vimgrep() {
+        let formatter = OutputFormatter::new(false, false, false, false, 0, 0, false, false, false, false, false)
+            .with_vimgrep(true);
+        let path = PathBuf::from("src/main.rs");
+        let mut result = MatchResult::new(path.clone(), 7, "let x = foo(foo);\r".to_string(), 8, 11);
+        result.add_match(12, 15);
+
+        assert_eq!(
+            formatter.format_result(&result),
+            "src/main.rs:7:9:let x = foo(foo);\nsrc/main.rs:7:13:let x = foo(foo);"
+        );
+        assert_eq!(formatter.format_spans(&path, 3, "other", &[], &[]), "src/main.rs:3:1:other");
+    }
+
+    #[test]
+    fn test_vimgrep_multiple_files() {
+        let text = OutputFormatter::new(false, true, false, false, 0, 0, false, false, false, false, false);
+        assert!(text.separates_files());
+
+        let formatter = text.with_vimgrep(true);
+        assert!(!formatter.separates_files());
+        let records: Vec<String> = [("a.txt", 4), ("sub/c.txt", 1)]
+            .iter()
+            .map(|&(path, line)| {
+                formatter.format_result(&MatchResult::new(PathBuf::from(path), line, "foo".to_string(), 0, 3))
+            })
+            .collect();
+        assert_eq!(records.join("\n"), "a.txt:4:1:foo\nsub/c.txt:1:1:foo");
+    }
+
+    #[test]
+    fn test_filename_match() {
+        let text = OutputFormatter::new(false, false, false, false, 0, 0, false, false, false, false, false);
+        let path = PathBuf::from("src/foo_foo.rs");
+        assert_eq!(text.format_filename_match(&path, &[(4, 7), (8, 11)]), "src/foo_foo.rs:(file name)");
+
+        let json = OutputFormatter::new(false, true, false, true, 0, 0, false, false, false, false, false);
+        assert_eq!(
+            json.format_filename_match(&path, &[(4, 7), (8, 11)]),
+            concat!(
+                r#"{"type":"filename","file":"src/foo_foo.rs","match_start":4,"match_end":7}"#,
+                "\n",
+                r#"{"type":"filename","file":"src/foo_foo.rs","match_start":8,"match_end":11}"#
+            )
+        );
+    }
+
     #[test]
     fn test_json_formatting() {
         let formatter = OutputFormatter::new(
@@ -370,4 +822,74 @@ mod tests {
         );
         assert_eq!(result, "test.txt:42:world");
     }
+
+    #[test]
+    fn test_only_matching_every_match() {
+        let formatter = OutputFormatter::new(
+            true,  // show_line_numbers
+            false, // show_filenames
+            false, // use_colors
+            false, // json_output
+            0,     // before_context
+            0,     // after_context
+            true,  // only_matching
+            false, // invert_match
+            false, // count_only
+            false, // files_only
+            false, // files_without_matches
+        );
+        let mut result = MatchResult::new(PathBuf::from("a.txt"), 3, "id=7 id=42 id=".to_string(), 0, 4);
+        result.add_match(5, 10);
+        result.add_match(11, 14);
+        assert_eq!(formatter.format_result(&result), "3:id=7\n3:id=42\n3:id=");
+
+        result.replacements = vec![Some("7".to_string()), Some("42".to_string()), None];
+        assert_eq!(formatter.format_result(&result), "3:7\n3:42");
+    }
+
+    #[test]
+    fn test_replace_in_line() {
+        let formatter = OutputFormatter::new(
+            false, // show_line_numbers
+            false, // show_filenames
+            false, // use_colors
+            false, // json_output
+            0,     // before_context
+            0,     // after_context
+            false, // only_matching
+            false, // invert_match
+            false, // count_only
+            false, // files_only
+            false, // files_without_matches
+        );
+        let mut result = MatchResult::new(PathBuf::from("a.txt"), 1, "a=1, b=2".to_string(), 0, 3);
+        result.add_match(5, 8);
+        result.replacements = vec![Some("1=a".to_string()), Some("2=b".to_string())];
+        assert_eq!(formatter.format_result(&result), "1=a, 2=b");
+    }
+
+    #[test]
+    fn test_custom_colors() {
+        let colors = ColorScheme::from_env_and_specs(
+            Some("fn=34:se="),
+            &["match:none,match:fg:green".to_string()],
+        )
+        .unwrap();
+        let formatter = OutputFormatter::new(
+            false, // show_line_numbers
+            true,  // show_filenames
+            true,  // use_colors
+            false, // json_output
+            0,     // before_context
+            0,     // after_context
+            false, // only_matching
+            false, // invert_match
+            false, // count_only
+            false, // files_only
+            false, // files_without_matches
+        )
+        .with_colors(colors);
+        let result = formatter.format_match(&PathBuf::from("a.txt"), 1, "say hello", 4, 9);
+        assert_eq!(result, "\x1b[34ma.txt\x1b[0m:say \x1b[32mhello\x1b[0m");
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
+use crate::fuzzy::FuzzyMatcher;
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use memchr::memchr_iter;
 use regex::bytes::{Regex, RegexBuilder};
 
@@ -8,6 +9,19 @@ pub struct PatternMatcher {
     use_regex: bool,
     ignore_case: bool,
     matcher: PatternMatcherImpl,
+    /// Capture regex and what to take from it, for `--group` / `--replace`
+    extraction: Option<(Regex, Extraction)>,
+    /// Edits allowed with `--fuzzy`
+    fuzzy: Option<usize>,
+}
+
+/// Text produced for each match instead of the matched bytes
+#[derive(Debug, Clone)]
+pub enum Extraction {
+    /// A single capture group (`--group N`)
+    Group(usize),
+    /// A `$1` / `${name}` template expanded from the captures (`--replace`)
+    Template(String),
 }
 
 enum PatternMatcherImpl {
@@ -24,6 +38,10 @@ enum PatternMatcherImpl {
     Regex {
         regex: Regex,
     },
+    /// Literal strings matched with up to K edits (`--fuzzy`)
+    Fuzzy {
+        matchers: Vec<FuzzyMatcher>,
+    },
 }
 
 impl PatternMatcher {
@@ -59,9 +77,95 @@ impl PatternMatcher {
             use_regex,
             ignore_case,
             matcher,
+            extraction: None,
+            fuzzy: None,
         })
     }
 
+    /// Enable `--fuzzy K`: each literal pattern (or `|`-separated
+    /// alternative) matches with up to `max_edits` insertions, deletions or
+    /// substitutions
+    pub fn with_fuzzy(mut self, max_edits: Option<usize>) -> Result<Self> {
+        let Some(max_edits) = max_edits else {
+            return Ok(self);
+        };
+        if self.use_regex {
+            return Err(anyhow!("--fuzzy only works with literal patterns"));
+        }
+        let matchers = self
+            .pattern_string
+            .split('|')
+            .map(|alternative| FuzzyMatcher::new(alternative.as_bytes(), max_edits, self.ignore_case))
+            .collect::<Result<Vec<_>>>()?;
+        self.matcher = PatternMatcherImpl::Fuzzy { matchers };
+        self.fuzzy = Some(max_edits);
+        Ok(self)
+    }
+
+    /// Enable `--group` / `--replace` extraction.
+    ///
+    /// Literal patterns are compiled to an escaped regex for this; they only
+    /// have group 0, the whole match.
+    pub fn with_extraction(mut self, extraction: Option<Extraction>) -> Result<Self> {
+        let Some(extraction) = extraction else {
+            self.extraction = None;
+            return Ok(self);
+        };
+
+        let pattern = if self.use_regex {
+            self.pattern_string.clone()
+        } else {
+            self.pattern_string
+                .split('|')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .multi_line(true)
+            .build()?;
+
+        if let Extraction::Group(group) = extraction {
+            let groups = regex.captures_len() - 1;
+            if group > groups {
+                return Err(anyhow!(
+                    "--group {}: pattern has {} capture group{}",
+                    group,
+                    groups,
+                    if groups == 1 { "" } else { "s" }
+                ));
+            }
+        }
+
+        self.extraction = Some((regex, extraction));
+        Ok(self)
+    }
+
+    pub fn has_extraction(&self) -> bool {
+        self.extraction.is_some()
+    }
+
+    /// Text to show for match `m` of `data` under the configured extraction;
+    /// `None` when no extraction is set or the group did not participate
+    pub fn extract(&self, data: &[u8], m: &Match) -> Option<String> {
+        let (regex, extraction) = self.extraction.as_ref()?;
+        // Literal matches are re-found at the same position; bounding the
+        // haystack keeps the escaped alternation from choosing a longer one
+        let haystack = if self.use_regex { data } else { &data[..m.end] };
+        let captures = regex.captures_at(haystack, m.start)?;
+
+        let bytes = match extraction {
+            Extraction::Group(group) => captures.get(*group)?.as_bytes().to_vec(),
+            Extraction::Template(template) => {
+                let mut expanded = Vec::new();
+                captures.expand(template.as_bytes(), &mut expanded);
+                expanded
+            }
+        };
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
     pub fn find_matches(&self, data: &[u8]) -> Vec<Match> {
         match &self.matcher {
             PatternMatcherImpl::SingleLiteral { pattern, ignore_case } => {
@@ -73,6 +177,9 @@ impl PatternMatcher {
             PatternMatcherImpl::Regex { regex } => {
                 self.find_regex_matches(data, regex)
             }
+            PatternMatcherImpl::Fuzzy { matchers } => {
+                self.find_fuzzy_matches(data, matchers)
+            }
         }
     }
 
@@ -97,14 +204,14 @@ impl PatternMatcher {
         let first_byte = pattern[0];
         
         for pos in memchr_iter(first_byte, search_slice) {
-            if pos + pattern.len() <= search_slice.len() {
-                if &search_slice[pos..pos + pattern.len()] == pattern {
-                    matches.push(Match {
-                        start: pos,
-                        end: pos + pattern.len(),
-                        pattern_id: 0,
-                    });
-                }
+            if pos + pattern.len() <= search_slice.len()
+                && &search_slice[pos..pos + pattern.len()] == pattern
+            {
+                matches.push(Match {
+                    start: pos,
+                    end: pos + pattern.len(),
+                    pattern_id: 0,
+                });
             }
         }
 
@@ -130,13 +237,40 @@ impl PatternMatcher {
             })
             .collect()
     }
+
+    /// Matches of all alternatives, leftmost first; where two overlap the
+    /// earlier one wins, as with Aho-Corasick
+    fn find_fuzzy_matches(&self, data: &[u8], matchers: &[FuzzyMatcher]) -> Vec<Match> {
+        let mut found: Vec<Match> = matchers
+            .iter()
+            .enumerate()
+            .flat_map(|(pattern_id, matcher)| {
+                matcher.find_iter(data).into_iter().map(move |(start, end)| Match { start, end, pattern_id })
+            })
+            .collect();
+        found.sort_by_key(|m| (m.start, m.pattern_id));
+
+        let mut matches: Vec<Match> = Vec::with_capacity(found.len());
+        for m in found {
+            if matches.last().is_none_or(|last| m.start >= last.end) {
+                matches.push(m);
+            }
+        }
+        matches
+    }
 }
 
 impl Clone for PatternMatcher {
     fn clone(&self) -> Self {
         // Recreate the matcher from stored parameters
-        PatternMatcher::new(&self.pattern_string, self.use_regex, self.ignore_case)
-            .expect("Failed to clone PatternMatcher")
+        let mut matcher = PatternMatcher::new(&self.pattern_string, self.use_regex, self.ignore_case)
+            .expect("Failed to clone PatternMatcher");
+        if let PatternMatcherImpl::Fuzzy { ref matchers } = self.matcher {
+            matcher.matcher = PatternMatcherImpl::Fuzzy { matchers: matchers.clone() };
+            matcher.fuzzy = self.fuzzy;
+        }
+        matcher.extraction = self.extraction.clone();
+        matcher
     }
 }
 
@@ -169,6 +303,38 @@ mod tests {
         assert_eq!(matches.len(), 2);
     }
 
+    #[test]
+    fn test_extraction() {
+        let matcher = PatternMatcher::new(r"(\w+)=(\d+)?", true, false)
+            .unwrap()
+            .with_extraction(Some(Extraction::Group(2)))
+            .unwrap();
+        let data = b"a=1 b= c=3";
+        let extracted: Vec<_> = matcher
+            .find_matches(data)
+            .iter()
+            .map(|m| matcher.extract(data, m))
+            .collect();
+        assert_eq!(extracted, vec![Some("1".to_string()), None, Some("3".to_string())]);
+
+        let matcher = matcher.with_extraction(Some(Extraction::Template("$2:$1".to_string()))).unwrap();
+        let first = &matcher.find_matches(data)[0];
+        assert_eq!(matcher.extract(data, first).as_deref(), Some("1:a"));
+
+        let literal = PatternMatcher::new("cat|category", false, false)
+            .unwrap()
+            .with_extraction(Some(Extraction::Template("<$0>".to_string())))
+            .unwrap();
+        let data = b"category";
+        let m = &literal.find_matches(data)[0];
+        assert_eq!(literal.extract(data, m).as_deref(), Some(format!("<{}>", std::str::from_utf8(&data[m.start..m.end]).unwrap()).as_str()));
+
+        assert!(PatternMatcher::new("abc", false, false)
+            .unwrap()
+            .with_extraction(Some(Extraction::Group(1)))
+            .is_err());
+    }
+
     #[test]
     fn test_regex() {
         let matcher = PatternMatcher::new(r"\d+", true, false).unwrap();
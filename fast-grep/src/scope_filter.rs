@@ -0,0 +1,437 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+use crate::pattern_matcher::Match;
+
+/// The part of a source file `--scope` limits matches to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scope {
+    /// Line and block comments
+    Comments,
+    /// String and character literals, quotes included
+    Strings,
+    /// Everything that is neither a comment nor a string
+    Code,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Comment,
+    Text,
+}
+
+/// A comment or string literal: `start..end` in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    end: usize,
+    region: Region,
+}
+
+/// A string literal delimiter
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Backslash escapes the next byte
+    escapes: bool,
+    /// May span lines; otherwise an unterminated literal ends at the newline
+    multiline: bool,
+}
+
+const fn quote(open: &'static str, close: &'static str, escapes: bool, multiline: bool) -> Quote {
+    Quote { open, close, escapes, multiline }
+}
+
+/// Comment and string syntax of a language family, enough to tell code from
+/// comments and literals without parsing
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+    /// Block comments nest, as in Rust and Swift
+    nested_blocks: bool,
+    /// Longest opener first, so `"""` is not read as `"`
+    quotes: &'static [Quote],
+    /// Rust: `r"..."`/`r#"..."#` raw strings, and `'` only opens a char
+    /// literal when it closes right after, so lifetimes stay code
+    rust_literals: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    nested_blocks: false,
+    quotes: &[quote("\"", "\"", true, false), quote("'", "'", true, false)],
+    rust_literals: false,
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    quotes: &[
+        quote("`", "`", true, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    ..C_LIKE
+};
+
+const GO: Syntax = Syntax {
+    quotes: &[
+        quote("`", "`", false, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    ..C_LIKE
+};
+
+const RUST: Syntax = Syntax {
+    nested_blocks: true,
+    quotes: &[quote("\"", "\"", true, true)],
+    rust_literals: true,
+    ..C_LIKE
+};
+
+const SWIFT: Syntax = Syntax {
+    nested_blocks: true,
+    quotes: &[quote("\"\"\"", "\"\"\"", true, true), quote("\"", "\"", true, false)],
+    ..C_LIKE
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    nested_blocks: false,
+    quotes: &[
+        quote("\"\"\"", "\"\"\"", true, true),
+        quote("'''", "'''", true, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    rust_literals: false,
+};
+
+const SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    nested_blocks: false,
+    quotes: &[quote("\"", "\"", true, true), quote("'", "'", false, true)],
+    rust_literals: false,
+};
+
+const RUBY: Syntax = Syntax {
+    quotes: &[quote("\"", "\"", true, true), quote("'", "'", true, true)],
+    ..SHELL
+};
+
+const SQL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("/*", "*/")],
+    nested_blocks: false,
+    quotes: &[quote("'", "'", false, true), quote("\"", "\"", false, true)],
+    rust_literals: false,
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    nested_blocks: false,
+    quotes: &[
+        quote("[[", "]]", false, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    rust_literals: false,
+};
+
+const CSS: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("/*", "*/")],
+    nested_blocks: false,
+    quotes: &[quote("\"", "\"", true, false), quote("'", "'", true, false)],
+    rust_literals: false,
+};
+
+const MARKUP: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("<!--", "-->")],
+    nested_blocks: false,
+    quotes: &[],
+    rust_literals: false,
+};
+
+/// The syntax for a file, from its extension or well-known name
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let name = path.file_name()?.to_str()?;
+    if matches!(name, "Makefile" | "makefile" | "GNUmakefile" | "Dockerfile" | "CMakeLists.txt") {
+        return Some(&SHELL);
+    }
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" | "java" | "cs" | "kt" | "kts"
+        | "scala" | "dart" | "php" | "proto" | "zig" => &C_LIKE,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => &JAVASCRIPT,
+        "go" => &GO,
+        "rs" => &RUST,
+        "swift" => &SWIFT,
+        "py" | "pyi" => &PYTHON,
+        "sh" | "bash" | "zsh" | "fish" | "pl" | "pm" | "r" | "toml" | "yaml" | "yml" | "mk"
+        | "cmake" | "conf" | "ini" => &SHELL,
+        "rb" => &RUBY,
+        "sql" => &SQL,
+        "lua" => &LUA,
+        "css" | "scss" | "less" => &CSS,
+        "html" | "htm" | "xml" | "svg" | "vue" => &MARKUP,
+        _ => return None,
+    })
+}
+
+/// Keeps only the matches inside the requested scope. Sits between reading
+/// a file and reporting its matches; files in languages it does not know
+/// have no scope to match in.
+#[derive(Debug, Clone, Copy)]
+pub struct ScopeFilter {
+    scope: Scope,
+}
+
+impl ScopeFilter {
+    pub fn new(scope: Scope) -> Self {
+        Self { scope }
+    }
+
+    /// Whether `path` is in a language the lexer knows
+    pub fn supports(path: &Path) -> bool {
+        syntax_for(path).is_some()
+    }
+
+    /// Lex `content`, the contents of `path`; `None` when the language is
+    /// unknown
+    pub fn for_file(&self, path: &Path, content: &[u8]) -> Option<ScopedFile> {
+        let syntax = syntax_for(path)?;
+        Some(ScopedFile {
+            scope: self.scope,
+            spans: lex(content, syntax),
+        })
+    }
+}
+
+/// The comments and literals of one file, ready to test matches against
+#[derive(Debug, Clone)]
+pub struct ScopedFile {
+    scope: Scope,
+    spans: Vec<Span>,
+}
+
+impl ScopedFile {
+    /// Drop the matches outside the scope. `offset` is added to match
+    /// positions, for callers matching one line at a time.
+    pub fn retain(&self, matches: Vec<Match>, offset: usize) -> Vec<Match> {
+        matches
+            .into_iter()
+            .filter(|m| self.contains(m.start + offset, m.end + offset))
+            .collect()
+    }
+
+    /// Whether `start..end` lies wholly within the scope
+    pub fn contains(&self, start: usize, end: usize) -> bool {
+        let spans = &self.spans;
+        // The last span starting at or before the match
+        let index = spans.partition_point(|span| span.start <= start);
+        let enclosing = index.checked_sub(1).map(|i| spans[i]).filter(|span| start < span.end);
+
+        match self.scope {
+            Scope::Comments => enclosing.is_some_and(|span| span.region == Region::Comment && end <= span.end),
+            Scope::Strings => enclosing.is_some_and(|span| span.region == Region::Text && end <= span.end),
+            // Code must not run into the next comment or literal either
+            Scope::Code => enclosing.is_none() && spans.get(index).is_none_or(|next| end <= next.start),
+        }
+    }
+}
+
+/// The comments and string literals of `content`, in order
+fn lex(content: &[u8], syntax: &Syntax) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    'scan: while i < content.len() {
+        let rest = &content[i..];
+
+        for (open, close) in syntax.block_comments {
+            if rest.starts_with(open.as_bytes()) {
+                let end = block_comment_end(content, i + open.len(), open, close, syntax.nested_blocks);
+                spans.push(Span { start: i, end, region: Region::Comment });
+                i = end;
+                continue 'scan;
+            }
+        }
+
+        if syntax.line_comments.iter().any(|open| rest.starts_with(open.as_bytes())) {
+            let end = memchr::memchr(b'\n', rest).map_or(content.len(), |newline| i + newline);
+            spans.push(Span { start: i, end, region: Region::Comment });
+            i = end;
+            continue;
+        }
+
+        if syntax.rust_literals {
+            if let Some(end) = rust_literal_end(content, i) {
+                spans.push(Span { start: i, end, region: Region::Text });
+                i = end;
+                continue;
+            }
+        }
+
+        for quote in syntax.quotes {
+            if rest.starts_with(quote.open.as_bytes()) {
+                let end = string_end(content, i + quote.open.len(), quote);
+                spans.push(Span { start: i, end, region: Region::Text });
+                i = end;
+                continue 'scan;
+            }
+        }
+
+        i += 1;
+    }
+    spans
+}
+
+fn block_comment_end(content: &[u8], mut i: usize, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 1;
+    while i < content.len() {
+        let rest = &content[i..];
+        if rest.starts_with(close.as_bytes()) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else if nested && rest.starts_with(open.as_bytes()) {
+            depth += 1;
+            i += open.len();
+        } else {
+            i += 1;
+        }
+    }
+    content.len()
+}
+
+fn string_end(content: &[u8], mut i: usize, quote: &Quote) -> usize {
+    while i < content.len() {
+        let rest = &content[i..];
+        if quote.escapes && rest[0] == b'\\' {
+            i += 2;
+        } else if rest.starts_with(quote.close.as_bytes()) {
+            return i + quote.close.len();
+        } else if !quote.multiline && rest[0] == b'\n' {
+            return i;
+        } else {
+            i += 1;
+        }
+    }
+    content.len()
+}
+
+/// End of a Rust raw string or char literal starting at `i`
+fn rust_literal_end(content: &[u8], i: usize) -> Option<usize> {
+    let rest = &content[i..];
+
+    // r"..", r#".."#, br".." unless the `r` ends an identifier
+    let prefix = if rest.starts_with(b"br") { 2 } else if rest.starts_with(b"r") { 1 } else { 0 };
+    let after_identifier = i > 0 && (content[i - 1].is_ascii_alphanumeric() || content[i - 1] == b'_');
+    if prefix > 0 && !after_identifier {
+        let hashes = rest[prefix..].iter().take_while(|&&b| b == b'#').count();
+        if rest.get(prefix + hashes) == Some(&b'"') {
+            let mut close = b"\"".to_vec();
+            close.resize(1 + hashes, b'#');
+            let body = i + prefix + hashes + 1;
+            return Some(
+                memchr::memmem::find(&content[body..], &close).map_or(content.len(), |at| body + at + close.len()),
+            );
+        }
+    }
+
+    // 'x' and '\n', but not the lifetime in `&'a str`
+    if rest.first() == Some(&b'\'') {
+        if rest.get(1) == Some(&b'\\') {
+            let close = rest.iter().skip(3).take(10).position(|&b| b == b'\'')?;
+            return Some(i + 3 + close + 1);
+        }
+        let width = rest.get(1).map(|&b| utf8_width(b))?;
+        if rest.get(1 + width) == Some(&b'\'') {
+            return Some(i + 2 + width);
+        }
+    }
+    None
+}
+
+fn utf8_width(first: u8) -> usize {
+    match first {
+        b if b >= 0xF0 => 4,
+        b if b >= 0xE0 => 3,
+        b if b >= 0xC0 => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern_matcher::PatternMatcher;
+
+    /// Lines holding a match of `pattern` within `scope`
+    fn scoped(file: &str, source: &str, pattern: &str, scope: Scope) -> Vec<usize> {
+        let matcher = PatternMatcher::new(pattern, false, false).unwrap();
+        let matches = matcher.find_matches(source.as_bytes());
+        let Some(scoped) = ScopeFilter::new(scope).for_file(Path::new(file), source.as_bytes()) else {
+            return Vec::new();
+        };
+        let mut lines: Vec<usize> = scoped
+            .retain(matches, 0)
+            .iter()
+            .map(|m| source[..m.start].matches('\n').count() + 1)
+            .collect();
+        lines.dedup();
+        lines
+    }
+
+    #[test]
+    fn test_rust_scopes() {
+        let source = r##"// TODO: parse
+fn todo<'a>(s: &'a str) -> char {
+    let msg = "TODO later"; /* TODO block */
+    let raw = r#"a "TODO" b"#;
+    let c = '"'; todo!()
+}
+"##;
+        assert_eq!(scoped("lib.rs", source, "TODO", Scope::Comments), vec![1, 3]);
+        assert_eq!(scoped("lib.rs", source, "TODO", Scope::Strings), vec![3, 4]);
+        assert_eq!(scoped("lib.rs", source, "todo", Scope::Code), vec![2, 5]);
+        // The lifetime did not open a char literal swallowing `str`
+        assert_eq!(scoped("lib.rs", source, "str", Scope::Code), vec![2]);
+    }
+
+    #[test]
+    fn test_python_and_shell_scopes() {
+        let source = "x = 1  # key\ndoc = \"\"\"key\nstill key\"\"\"\nkey = 'a # key'\n";
+        assert_eq!(scoped("a.py", source, "key", Scope::Comments), vec![1]);
+        assert_eq!(scoped("a.py", source, "key", Scope::Strings), vec![2, 3, 4]);
+        assert_eq!(scoped("a.py", source, "key", Scope::Code), vec![4]);
+
+        let script = "echo 'it''s # not' # real\n";
+        assert_eq!(scoped("run.sh", script, "real", Scope::Comments), vec![1]);
+        assert!(scoped("run.sh", script, "not", Scope::Comments).is_empty());
+    }
+
+    #[test]
+    fn test_spans_across_regions_and_unknown_languages() {
+        // A match running out of a string into code is in neither
+        let source = "f(\"ab\")cd\n";
+        assert!(scoped("x.c", source, "b\")c", Scope::Strings).is_empty());
+        assert!(scoped("x.c", source, "b\")c", Scope::Code).is_empty());
+        assert_eq!(scoped("x.c", source, "cd", Scope::Code), vec![1]);
+
+        // Nested Rust comments close at the outer `*/`
+        assert_eq!(scoped("x.rs", "/* a /* b */ c */ c\n", "c", Scope::Code), vec![1]);
+        assert_eq!(scoped("x.rs", "/* a /* b */ c */\n", "c", Scope::Code), Vec::<usize>::new());
+
+        assert!(scoped("notes.txt", "# TODO\n", "TODO", Scope::Comments).is_empty());
+        assert!(!ScopeFilter::supports(Path::new("notes.txt")));
+        assert!(ScopeFilter::supports(Path::new("src/Makefile")));
+    }
+}
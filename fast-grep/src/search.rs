@@ -1,13 +1,23 @@
 use anyhow::Result;
 use ignore::WalkBuilder;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
-use crate::cli::Args;
-use crate::file_processor::FileProcessor;
-use crate::output::OutputFormatter;
+use crate::cache::SearchCache;
+use crate::cancel::{CancelToken, Cancelled};
+use crate::cli::{Args, SummaryMode};
+use crate::colors::ColorScheme;
+use crate::file_processor::{is_stdin, FileContent, FileProcessor};
+use crate::follow::Follower;
+use crate::git_changes::ChangedLines;
+use crate::output::{MatchResult, OutputFormatter};
 use crate::pattern_matcher::PatternMatcher;
+use crate::scope_filter::ScopeFilter;
+use crate::sparse;
+use crate::summary::DirSummary;
+use crate::virtual_fs::PrunedMounts;
 use crate::worker::{SearchStats, WorkerPool};
 
 pub struct SearchEngine {
@@ -16,29 +26,38 @@ pub struct SearchEngine {
     file_processor: FileProcessor,
     output_formatter: OutputFormatter,
     worker_pool: WorkerPool,
+    cancel: CancelToken,
+    changed: Option<Arc<ChangedLines>>,
+    cache: Option<Arc<SearchCache>>,
+    /// Mounted filesystems discovery leaves out
+    pruned: PrunedMounts,
 }
 
 impl SearchEngine {
     pub fn new(args: Args) -> Result<Self> {
+        let args = args.with_resolved_paths();
+
         // Initialize pattern matcher
         let use_regex = args.use_regex && !args.fixed_strings;
         let pattern_matcher = PatternMatcher::new(
             &args.pattern,
             use_regex,
             args.ignore_case,
-        )?;
+        )?
+        .with_fuzzy(args.fuzzy)?
+        .with_extraction(args.extraction())?;
 
         // Initialize file processor
         let file_processor = FileProcessor::new(
             args.max_filesize_bytes(),
             args.use_mmap,
-        );
+        )
+        .with_null_data(args.null_data);
 
         // Initialize output formatter
-        let show_filenames = !args.no_filename && args.paths.len() > 1;
         let output_formatter = OutputFormatter::new(
             args.line_numbers,
-            show_filenames,
+            args.show_filenames(),
             args.should_use_colors(),
             args.json_output,
             args.get_before_context(),
@@ -48,15 +67,41 @@ impl SearchEngine {
             args.count_only,
             args.files_only,
             args.files_without_matches,
-        );
+        )
+        .with_colors(ColorScheme::from_env_and_specs(
+            std::env::var("GREP_COLORS").ok().as_deref(),
+            &args.colors,
+        )?)
+        .with_count_matches(args.count_matches)
+        .with_null(args.null, args.null_data)
+        .with_label(args.label.clone())
+        .with_max_columns(args.max_columns, args.max_columns_preview)
+        .with_vimgrep(args.vimgrep);
+
+        let changed = match &args.changed {
+            Some(reference) => Some(Arc::new(ChangedLines::collect(reference, &args.paths)?)),
+            None => None,
+        };
+
+        let cache = SearchCache::for_search(&args).map(Arc::new);
+        let pruned = PrunedMounts::load(&args.pruned_fs_types());
 
         // Initialize worker pool with context settings
+        let cancel = CancelToken::new(args.timeout);
         let worker_pool = WorkerPool::new(
             file_processor.clone(),
             pattern_matcher.clone(),
             args.get_threads(),
             args.invert_match,
-        ).with_context(args.get_before_context(), args.get_after_context());
+        )
+        .with_context(args.get_before_context(), args.get_after_context())
+        .with_cancellation(cancel.clone())
+        .with_scope(args.scope.map(ScopeFilter::new))
+        .with_changed_lines(changed.clone())
+        .with_cache(cache.clone())
+        .with_adaptive_threads(args.threads.is_none())
+        .with_record_terminator(if args.null_data { b'\0' } else { b'\n' })
+        .with_memory_limit(args.max_memory);
 
         Ok(Self {
             args,
@@ -64,58 +109,188 @@ impl SearchEngine {
             file_processor,
             output_formatter,
             worker_pool,
+            cancel,
+            changed,
+            cache,
+            pruned,
         })
     }
 
-    pub fn run(&self) -> Result<()> {
+    /// Token that stops this search early; `--timeout` is already armed
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    /// Run the search. Returns why it stopped early, if it did; the results
+    /// found until then have been printed along with any stats.
+    pub fn run(&self) -> Result<Option<Cancelled>> {
         let start_time = Instant::now();
+        let mut stats = SearchStats::new();
         
+        if self.args.follow && self.args.paths.iter().any(|path| is_stdin(path)) {
+            anyhow::bail!("--follow cannot follow standard input");
+        }
+
         // Discover files to search
-        let files_to_search = self.discover_files()?;
+        let files_to_search = self.discover_files_with_stats(&mut stats)?;
+
+        // Discovery only: the file list is the output
+        if self.args.list_files {
+            let stdout = std::io::stdout();
+            let mut out = std::io::BufWriter::new(stdout.lock());
+            for path in &files_to_search {
+                writeln!(out, "{}", path.display())?;
+            }
+            out.flush()?;
+
+            stats.elapsed = start_time.elapsed();
+            if self.args.stats {
+                self.print_stats(&stats);
+            }
+            return Ok(self.report_cancellation());
+        }
         
-        if files_to_search.is_empty() {
+        // A search cancelled during discovery still reports its stats
+        if files_to_search.is_empty() && self.cancel.observed().is_none() && !self.args.follow_new {
             eprintln!("No files to search");
-            return Ok(());
+            return Ok(None);
         }
 
-        let mut stats = SearchStats::new();
-        
         // Different execution modes based on output requirements
-        if self.args.files_without_matches {
-            self.run_files_without_matches_mode(&files_to_search, &mut stats)?;
+        let mut matched_files = Vec::new();
+        let search_stats = if self.args.summary == Some(SummaryMode::Dirs) {
+            self.run_dir_summary_mode(&files_to_search)?
+        } else if self.args.files_without_matches {
+            self.run_files_without_matches_mode(&files_to_search)?
         } else if self.args.files_only {
-            self.run_files_only_mode(&files_to_search, &mut stats)?;
-        } else if self.args.count_only {
-            self.run_count_mode(&files_to_search, &mut stats)?;
+            self.run_files_only_mode(&files_to_search)?
+        } else if self.args.count_only || self.args.count_matches {
+            self.run_count_mode(&files_to_search)?
         } else {
-            self.run_normal_mode(&files_to_search, &mut stats)?;
-        }
+            self.run_normal_mode(&files_to_search, &mut matched_files)?
+        };
 
-        stats.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        stats.merge(&search_stats);
+        stats.elapsed = start_time.elapsed();
+        if let Some(ref cache) = self.cache {
+            stats.cache = Some(cache.counts());
+            cache.save();
+        }
         
-        // Print statistics if verbose
-        if std::env::var("FGREP_VERBOSE").is_ok() {
+        if self.args.stats {
             self.print_stats(&stats);
         }
 
-        Ok(())
+        if self.args.follow && self.cancel.observed().is_none() {
+            // Ctrl+C or --timeout is how following ends, not an interruption
+            self.follow(&matched_files)?;
+            return Ok(None);
+        }
+        Ok(self.report_cancellation())
+    }
+
+    /// `--follow`: follow the files that matched, and with `--follow-new`
+    /// files created under the searched directories, printing matching
+    /// lines as they are appended
+    fn follow(&self, matched_files: &[PathBuf]) -> Result<()> {
+        let roots = if self.args.follow_new {
+            self.args.paths.iter().filter(|path| path.is_dir()).cloned().collect()
+        } else {
+            Vec::new()
+        };
+        let mut follower = Follower::new(if self.args.null_data { b'\0' } else { b'\n' }).with_new_files_under(roots);
+        for path in matched_files {
+            if let Err(e) = follower.add(path) {
+                eprintln!("fgrep: {}: {}", path.display(), e);
+            }
+        }
+        if follower.is_empty() {
+            eprintln!("fgrep: no matching files to follow");
+            return Ok(());
+        }
+        std::io::stdout().flush()?;
+
+        let file_types = match self.args.file_types {
+            Some(ref types) => Some(self.build_file_types(types, false)?),
+            None => None,
+        };
+        let exclude_types = match self.args.exclude_types {
+            Some(ref types) => Some(self.build_file_types(types, true)?),
+            None => None,
+        };
+        let accept = |path: &Path| {
+            let hidden = self.args.paths.iter().filter_map(|root| path.strip_prefix(root).ok()).any(|relative| {
+                relative.components().any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+            });
+            (self.args.search_hidden || !hidden)
+                && [&file_types, &exclude_types]
+                    .into_iter()
+                    .flatten()
+                    .all(|types| !types.matched(path, false).is_ignore())
+        };
+
+        follower.run(&self.cancel, accept, |path, appended| {
+            let content = FileContent::InMemory(appended.data);
+            for match_result in self.worker_pool.search_appended(path, &content, appended.records_before)? {
+                self.print_match(&match_result);
+            }
+            std::io::stdout().flush()?;
+            Ok(())
+        })
+    }
+
+    /// Tell the user the output is partial if the search was cut short
+    fn report_cancellation(&self) -> Option<Cancelled> {
+        let cancelled = self.cancel.observed()?;
+        match (cancelled, self.args.timeout) {
+            (Cancelled::TimedOut, Some(timeout)) => {
+                eprintln!("fgrep: timed out after {}s, results are partial", timeout.as_secs_f64())
+            }
+            _ => eprintln!("fgrep: interrupted, results are partial"),
+        }
+        Some(cancelled)
     }
 
     fn discover_files(&self) -> Result<Vec<PathBuf>> {
+        self.discover_files_with_stats(&mut SearchStats::new())
+    }
+
+    /// Collect files to search, counting walked files dropped for size.
+    ///
+    /// With `--stats` the directories are walked a second time without any
+    /// filters so files excluded by ignore rules and hidden/type filters can
+    /// be reported as well; that extra pass is skipped otherwise.
+    fn discover_files_with_stats(&self, stats: &mut SearchStats) -> Result<Vec<PathBuf>> {
         let mut files = Vec::new();
+        let max_filesize = self.args.max_filesize_bytes();
         
         for path in &self.args.paths {
-            if path.is_file() {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            if path.is_file() || is_stdin(path) {
                 files.push(path.clone());
             } else if path.is_dir() {
                 let mut walk_builder = WalkBuilder::new(path);
                 
-                // Configure walk options
+                // Configure walk options. Ignore files in parent directories
+                // count too; .rgignore beats .ignore, which beats git's rules
+                let (vcs, dot) = (self.args.respect_vcs_ignore(), self.args.respect_dot_ignore());
                 walk_builder
                     .hidden(!self.args.search_hidden)
-                    .ignore(self.args.respect_ignore)
-                    .git_ignore(self.args.respect_ignore)
-                    .max_filesize(Some(self.args.max_filesize_bytes()));
+                    .parents(vcs || dot)
+                    .ignore(dot)
+                    .git_ignore(vcs)
+                    .git_global(vcs)
+                    .git_exclude(vcs)
+                    .max_depth(self.args.max_depth)
+                    .same_file_system(self.args.one_file_system);
+                if dot {
+                    walk_builder.add_custom_ignore_filename(".rgignore");
+                }
+
+                walk_builder.follow_links(self.args.follow_links);
+                self.add_walk_filter(&mut walk_builder, path);
 
                 // Add file type filters
                 if let Some(ref types) = self.args.file_types {
@@ -126,18 +301,71 @@ impl SearchEngine {
                     walk_builder.types(self.build_file_types(types, true)?);
                 }
 
+                let mut walked = 0;
                 for entry in walk_builder.build() {
-                    let entry = entry?;
-                    if entry.file_type().map_or(false, |ft| ft.is_file()) {
-                        files.push(entry.into_path());
+                    if self.cancel.is_cancelled() {
+                        break;
                     }
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        // Broken links are expected once links are followed
+                        Err(e) if self.args.follow_links => {
+                            eprintln!("fgrep: {}", e);
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
+                    if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                        walked += 1;
+                        // Holes in sparse files are not read, so only stored data counts
+                        let too_large = entry
+                            .metadata()
+                            .is_ok_and(|metadata| sparse::stored_len(&metadata) > max_filesize);
+                        if too_large {
+                            stats.skipped_too_large += 1;
+                        } else {
+                            files.push(entry.into_path());
+                        }
+                    }
+                }
+
+                if self.args.stats {
+                    let mut unfiltered_builder = WalkBuilder::new(path);
+                    unfiltered_builder
+                        .standard_filters(false)
+                        .max_depth(self.args.max_depth)
+                        .same_file_system(self.args.one_file_system)
+                        .follow_links(self.args.follow_links);
+                    self.add_walk_filter(&mut unfiltered_builder, path);
+                    let unfiltered = unfiltered_builder
+                        .build()
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+                        .count();
+                    stats.skipped_ignored += unfiltered.saturating_sub(walked);
                 }
             }
         }
 
+        if let Some(changed) = &self.changed {
+            files.retain(|file| changed.contains_file(file));
+        }
         Ok(files)
     }
 
+    /// Keep the walk below `root` out of pruned mounts and, with
+    /// `--follow-links`, out of directories it has already been through
+    fn add_walk_filter(&self, walk_builder: &mut WalkBuilder, root: &Path) {
+        let pruned = (!self.pruned.is_empty()).then(|| self.pruned.filter(root));
+        let visited = self.args.follow_links.then(|| visited_dir_filter(root));
+        if pruned.is_none() && visited.is_none() {
+            return;
+        }
+        walk_builder.filter_entry(move |entry| {
+            pruned.as_ref().is_none_or(|pruned| pruned(entry)) && visited.as_ref().is_none_or(|visited| visited(entry))
+        });
+    }
+
     fn build_file_types(&self, types_str: &str, negate: bool) -> Result<ignore::types::Types> {
         let mut builder = ignore::types::TypesBuilder::new();
         builder.add_defaults();
@@ -153,132 +381,187 @@ impl SearchEngine {
         Ok(builder.build()?)
     }
 
-    fn run_files_only_mode(&self, files: &[PathBuf], stats: &mut SearchStats) -> Result<()> {
-        let results = self.worker_pool.search_files(files.to_vec())?;
-        let mut files_with_matches = std::collections::HashSet::new();
-        
-        for match_result in results {
-            if !files_with_matches.contains(&match_result.file_path) {
-                println!("{}", self.output_formatter.format_filename_only(&match_result.file_path));
-                files_with_matches.insert(match_result.file_path.clone());
+    fn run_files_only_mode(&self, files: &[PathBuf]) -> Result<SearchStats> {
+        self.worker_pool.search_streaming(files, |file| {
+            if !file.matches.is_empty() || !self.filename_spans(&file.path).is_empty() {
+                self.print_filename(&file.path);
             }
-        }
-        
-        for file_path in files {
-            let had_matches = files_with_matches.contains(file_path);
-            stats.add_file(had_matches, self.get_file_size(file_path), if had_matches { 1 } else { 0 });
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
-    fn run_files_without_matches_mode(&self, files: &[PathBuf], stats: &mut SearchStats) -> Result<()> {
-        let results = self.worker_pool.search_files(files.to_vec())?;
-        let mut files_with_matches = std::collections::HashSet::new();
-        
-        // Collect all files that have matches
-        for match_result in results {
-            files_with_matches.insert(match_result.file_path.clone());
-        }
-        
-        // Print files that have NO matches
-        for file_path in files {
-            let had_matches = files_with_matches.contains(file_path);
-            if !had_matches {
-                println!("{}", self.output_formatter.format_filename_only(file_path));
+    fn run_files_without_matches_mode(&self, files: &[PathBuf]) -> Result<SearchStats> {
+        self.worker_pool.search_streaming(files, |file| {
+            if file.matches.is_empty() {
+                self.print_filename(&file.path);
             }
-            stats.add_file(had_matches, self.get_file_size(file_path), if had_matches { 1 } else { 0 });
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
-    fn run_count_mode(&self, files: &[PathBuf], stats: &mut SearchStats) -> Result<()> {
-        let results = self.worker_pool.search_files(files.to_vec())?;
-        let mut file_counts: HashMap<PathBuf, usize> = HashMap::new();
-        
-        for match_result in results {
-            *file_counts.entry(match_result.file_path).or_insert(0) += 1;
-        }
-        
-        for file_path in files {
-            let count = file_counts.get(file_path).copied().unwrap_or(0);
-            if count > 0 {
-                println!("{}", self.output_formatter.format_count(file_path, count));
+    fn run_count_mode(&self, files: &[PathBuf]) -> Result<SearchStats> {
+        self.worker_pool.search_streaming(files, |file| {
+            if !file.matches.is_empty() {
+                // An inverted result is a line without any match and counts once
+                let lines = file.matches.len();
+                let matches = file.matches.iter().map(|result| result.matches.len().max(1)).sum();
+                println!("{}", self.output_formatter.format_count(&file.path, lines, matches));
             }
-            stats.add_file(count > 0, self.get_file_size(file_path), count);
+            Ok(())
+        })
+    }
+
+    fn run_dir_summary_mode(&self, files: &[PathBuf]) -> Result<SearchStats> {
+        let mut summary = DirSummary::new(&self.args.paths, self.args.summary_depth);
+        let stats = self.worker_pool.search_streaming(files, |file| {
+            let matches = file.matches.iter().map(|result| result.matches.len().max(1)).sum();
+            summary.add(&file.path, file.matches.len(), matches);
+            Ok(())
+        })?;
+        for (dir, counts) in summary.rows() {
+            println!("{}", self.output_formatter.format_dir_summary(dir, &counts));
         }
-        Ok(())
+        Ok(stats)
     }
 
-    fn run_normal_mode(&self, files: &[PathBuf], stats: &mut SearchStats) -> Result<()> {
-        let results = self.worker_pool.search_files(files.to_vec())?;
-        let mut current_file: Option<PathBuf> = None;
+    /// Print every file's matches, collecting the files that had any
+    fn run_normal_mode(&self, files: &[PathBuf], matched_files: &mut Vec<PathBuf>) -> Result<SearchStats> {
         let mut file_has_matches = false;
 
-        for match_result in results {
-            // Print file header if this is a new file
-            if current_file.as_ref() != Some(&match_result.file_path) {
-                if files.len() > 1 && file_has_matches {
-                    println!(); // Blank line between files
-                }
-                current_file = Some(match_result.file_path.clone());
-                file_has_matches = true;
+        self.worker_pool.search_streaming(files, |file| {
+            let name_spans = self.filename_spans(&file.path);
+            if file.matches.is_empty() && name_spans.is_empty() {
+                return Ok(());
+            }
+            if files.len() > 1 && file_has_matches && self.output_formatter.separates_files() {
+                self.print_record(""); // Blank line between files
+            }
+            file_has_matches = true;
+            matched_files.push(file.path.clone());
+
+            if !name_spans.is_empty() {
+                self.print_record(&self.output_formatter.format_filename_match(&file.path, &name_spans));
             }
 
-            // Print context before
-            for (line_num, content) in &match_result.context_before {
-                println!("{}", self.output_formatter.format_context_line(
-                    &match_result.file_path,
-                    *line_num,
-                    content,
-                    true,
-                ));
+            for match_result in file.matches {
+                self.print_match(&match_result);
             }
+            Ok(())
+        })
+    }
 
-            // Print the match
-            println!("{}", self.output_formatter.format_match(
+    /// Print a match with its context lines
+    fn print_match(&self, match_result: &MatchResult) {
+        // Print context before
+        for (line_num, content) in &match_result.context_before {
+            self.print_record(&self.output_formatter.format_context_line(
                 &match_result.file_path,
-                match_result.line_number,
-                &match_result.line_content,
-                match_result.match_start,
-                match_result.match_end,
+                *line_num,
+                content,
+                true,
             ));
+        }
 
-            // Print context after
-            for (line_num, content) in &match_result.context_after {
-                println!("{}", self.output_formatter.format_context_line(
-                    &match_result.file_path,
-                    *line_num,
-                    content,
-                    false,
-                ));
-            }
+        // Print the match
+        let formatted = self.output_formatter.format_result(match_result);
+        if !formatted.is_empty() {
+            self.print_record(&formatted);
+        }
 
-            // Print separator if there's context
-            if !match_result.context_before.is_empty() || !match_result.context_after.is_empty() {
-                println!("{}", self.output_formatter.format_separator());
-            }
+        // Print context after
+        for (line_num, content) in &match_result.context_after {
+            self.print_record(&self.output_formatter.format_context_line(
+                &match_result.file_path,
+                *line_num,
+                content,
+                false,
+            ));
+        }
+
+        // Print separator if there's context
+        if !match_result.context_before.is_empty() || !match_result.context_after.is_empty() {
+            self.print_record(&self.output_formatter.format_separator());
+        }
+    }
+
+    /// Where the pattern matches a file's name as shown, with
+    /// `--with-filename-match`; standard input has no name to match
+    fn filename_spans(&self, file_path: &Path) -> Vec<(usize, usize)> {
+        if !self.args.with_filename_match || is_stdin(file_path) {
+            return Vec::new();
         }
+        let name = self.output_formatter.display_name(file_path);
+        self.pattern_matcher
+            .find_matches(name.as_bytes())
+            .into_iter()
+            .filter(|found| found.start < found.end)
+            .map(|found| (found.start, found.end))
+            .collect()
+    }
 
-        // Update stats
-        stats.files_processed = files.len();
-        Ok(())
+    /// Print one output record with its terminator: a newline, or NUL for
+    /// `--null-data`
+    fn print_record(&self, record: &str) {
+        print!("{}{}", record, self.output_formatter.record_end());
     }
 
-    fn get_file_size(&self, path: &PathBuf) -> u64 {
-        std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    /// Print a file name for `-l` / `--files-without-match`
+    fn print_filename(&self, file_path: &Path) {
+        print!(
+            "{}{}",
+            self.output_formatter.format_filename_only(file_path),
+            self.output_formatter.filename_end()
+        );
     }
 
     fn print_stats(&self, stats: &SearchStats) {
-        eprintln!("Search Statistics:");
-        eprintln!("  Files processed: {}", stats.files_processed);
-        eprintln!("  Files with matches: {}", stats.files_with_matches);
-        eprintln!("  Total matches: {}", stats.total_matches);
-        eprintln!("  Bytes processed: {} MB", stats.bytes_processed / (1024 * 1024));
-        eprintln!("  Processing time: {} ms", stats.processing_time_ms);
-        eprintln!("  Throughput: {:.2} MB/s", stats.throughput_mb_per_second());
+        let stats_output = self.output_formatter.format_stats(stats);
+
+        // Keep the JSON summary on stdout with the match stream; the human
+        // table goes to stderr so it never mixes into piped results
+        if self.args.json_output {
+            println!("{}", stats_output);
+        } else {
+            eprintln!("{}", stats_output);
+        }
+    }
+}
+
+
+/// Walk filter for `--follow-links` that enters each directory only once, keyed
+/// by (device, inode), so symlink cycles cannot loop forever and a directory
+/// reachable through several links is not searched twice
+#[cfg(unix)]
+fn visited_dir_filter(root: &Path) -> impl Fn(&ignore::DirEntry) -> bool + Send + Sync + 'static {
+    use std::collections::HashSet;
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::Mutex;
+
+    let mut visited = HashSet::new();
+    if let Ok(metadata) = std::fs::metadata(root) {
+        visited.insert((metadata.dev(), metadata.ino()));
+    }
+    let visited = Mutex::new(visited);
+
+    move |entry| {
+        if !entry.file_type().is_some_and(|ft| ft.is_dir()) {
+            return true;
+        }
+        match entry.metadata() {
+            Ok(metadata) => visited
+                .lock()
+                .map(|mut visited| visited.insert((metadata.dev(), metadata.ino())))
+                .unwrap_or(true),
+            Err(_) => true,
+        }
     }
 }
 
+/// Without inode numbers, rely on the walker's own ancestor loop detection
+#[cfg(not(unix))]
+fn visited_dir_filter(_root: &Path) -> impl Fn(&ignore::DirEntry) -> bool + Send + Sync + 'static {
+    |_| true
+}
 
 #[cfg(test)]
 mod tests {
@@ -298,30 +581,62 @@ mod tests {
         let args = Args {
             pattern: "hello".to_string(),
             paths: vec![temp_dir.path().to_path_buf()],
+            list_files: false,
             use_regex: false,
             fixed_strings: false,
             ignore_case: false,
             line_numbers: true,
             files_only: false,
             count_only: false,
+            count_matches: false,
             invert_match: false,
             only_matching: false,
+            group: None,
+            replace: None,
             files_without_matches: false,
+            scope: None,
+            changed: None,
             no_filename: false,
+            with_filename: false,
+            label: None,
+            null: false,
+            null_data: false,
+            max_columns: None,
+            max_columns_preview: false,
             recursive: true,
+            follow_links: false,
+            max_depth: None,
+            one_file_system: false,
+            search_virtual: false,
+            skip_fs: vec![],
             before_context: None,
             after_context: None,
             context: None,
             threads: None,
+            timeout: None,
             file_types: None,
             exclude_types: None,
             respect_ignore: true,
+            no_ignore_vcs: false,
+            no_ignore_dot: false,
             search_hidden: false,
             color: crate::cli::ColorOption::Auto,
             no_color: false,
+            colors: vec![],
             json_output: false,
+            vimgrep: false,
+            fuzzy: None,
+            with_filename_match: false,
+            summary: None,
+            summary_depth: 1,
+            follow: false,
+            follow_new: false,
+            stats: false,
             max_filesize_mb: 100,
+            max_memory: None,
             use_mmap: true,
+            cache: false,
+            help: None,
         };
         
         let engine = SearchEngine::new(args).unwrap();
@@ -330,4 +645,89 @@ mod tests {
         assert_eq!(files.len(), 1);
         assert_eq!(files[0], test_file);
     }
-}
\ No newline at end of file
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_links_stops_at_cycles() {
+        use clap::Parser;
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("test.txt"), "hello\n").unwrap();
+        symlink(temp_dir.path(), nested.join("loop")).unwrap();
+        symlink(temp_dir.path().join("a"), temp_dir.path().join("alias")).unwrap();
+        symlink(temp_dir.path().join("missing"), temp_dir.path().join("broken")).unwrap();
+
+        let root = temp_dir.path().to_str().unwrap();
+        let discover = |extra: &[&str]| {
+            let mut argv = vec!["fgrep", "hello", root];
+            argv.extend_from_slice(extra);
+            SearchEngine::new(Args::parse_from(argv)).unwrap().discover_files().unwrap()
+        };
+
+        assert_eq!(discover(&[]).len(), 1);
+        assert_eq!(discover(&["-R"]).len(), 1);
+        assert!(discover(&["-R", "--max-depth", "1"]).is_empty());
+    }
+
+    #[test]
+    fn test_ignore_file_precedence() {
+        use clap::Parser;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join(".git").join("info")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\nkept.txt\n").unwrap();
+        std::fs::write(root.join(".ignore"), "!kept.txt\nscratch.txt\n").unwrap();
+        std::fs::write(root.join(".rgignore"), "!scratch.txt\n").unwrap();
+        std::fs::write(root.join(".git").join("info").join("exclude"), "*.tmp\n").unwrap();
+        for file in ["src/debug.log", "src/kept.txt", "src/scratch.txt", "src/main.rs", "src/a.tmp"] {
+            std::fs::write(root.join(file), "hello\n").unwrap();
+        }
+
+        // Searching a subdirectory still applies the ignore files above it
+        let src = root.join("src");
+        let discover = |extra: &[&str]| {
+            let mut argv = vec!["fgrep", "hello", src.to_str().unwrap()];
+            argv.extend_from_slice(extra);
+            let mut files: Vec<String> = SearchEngine::new(Args::parse_from(argv))
+                .unwrap()
+                .discover_files()
+                .unwrap()
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(discover(&[]), ["kept.txt", "main.rs", "scratch.txt"]);
+        assert_eq!(discover(&["--no-ignore-dot"]), ["main.rs", "scratch.txt"]);
+        assert_eq!(
+            discover(&["--no-ignore-vcs"]),
+            ["a.tmp", "debug.log", "kept.txt", "main.rs", "scratch.txt"]
+        );
+        assert_eq!(discover(&["--no-ignore"]).len(), 5);
+    }
+
+    #[test]
+    fn test_list_files_takes_pattern_as_path() {
+        use clap::Parser;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "text\n").unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let args = Args::parse_from(["fgrep", "--files", "--type", "rust", root]);
+        let engine = SearchEngine::new(args).unwrap();
+        assert_eq!(engine.discover_files().unwrap(), vec![temp_dir.path().join("a.rs")]);
+
+        let args = Args::parse_from(["fgrep", "--files"]).with_resolved_paths();
+        assert_eq!(args.paths, vec![PathBuf::from(".")]);
+        assert!(Args::try_parse_from(["fgrep"]).is_err());
+    }
+}
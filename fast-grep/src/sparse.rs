@@ -0,0 +1,167 @@
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// A region reaching to the end of the file, however much it grows while
+/// it is read
+const WHOLE_FILE: Range<u64> = 0..u64::MAX;
+
+/// Whether the file stores fewer bytes than its length, so it has holes:
+/// ranges no data was ever written to, which read as NUL bytes
+#[cfg(unix)]
+pub fn is_sparse(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.is_file() && metadata.blocks().saturating_mul(512) < metadata.len()
+}
+
+#[cfg(not(unix))]
+pub fn is_sparse(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// The bytes of data a file holds: its length, less its holes
+#[cfg(unix)]
+pub fn stored_len(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    if is_sparse(metadata) {
+        metadata.blocks().saturating_mul(512)
+    } else {
+        metadata.len()
+    }
+}
+
+#[cfg(not(unix))]
+pub fn stored_len(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// A file read without its holes.
+///
+/// A hole holds no record terminators, so the records around it keep
+/// their numbers; the record it falls in just loses the NUL bytes. That
+/// does not hold for `--null-data`, whose records the NUL bytes end.
+pub struct DataReader {
+    file: File,
+    /// Data regions still to read, the current one first
+    regions: Vec<Range<u64>>,
+    /// Where the file is positioned
+    position: u64,
+}
+
+impl DataReader {
+    /// Read `file` from the start, skipping its holes if `skip_holes`
+    pub fn new(file: File, skip_holes: bool) -> io::Result<Self> {
+        let metadata = file.metadata()?;
+        let mut regions = if skip_holes && is_sparse(&metadata) {
+            data_regions(&file, metadata.len())?
+        } else {
+            vec![WHOLE_FILE]
+        };
+        regions.reverse();
+        Ok(Self {
+            file,
+            regions,
+            position: 0,
+        })
+    }
+}
+
+impl Read for DataReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(region) = self.regions.last().cloned() {
+            if self.position < region.start {
+                self.position = self.file.seek(SeekFrom::Start(region.start))?;
+            }
+            let left = region.end - self.position;
+            let want = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+            let read = if want == 0 { 0 } else { self.file.read(&mut buf[..want])? };
+            if read == 0 {
+                // The region is done, or the file ended early
+                self.regions.pop();
+                if want > 0 {
+                    self.regions.clear();
+                }
+                continue;
+            }
+            self.position += read as u64;
+            return Ok(read);
+        }
+        Ok(0)
+    }
+}
+
+/// Where `file` holds data, found with `SEEK_DATA` and `SEEK_HOLE`
+#[cfg(target_os = "linux")]
+fn data_regions(file: &File, len: u64) -> io::Result<Vec<Range<u64>>> {
+    use std::os::unix::io::AsRawFd;
+
+    let seek = |offset: u64, whence| {
+        // SAFETY: lseek on an open descriptor only moves its offset
+        let found = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) };
+        if found < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(found as u64)
+        }
+    };
+    let mut regions = Vec::new();
+    let mut offset = 0;
+    while offset < len {
+        let start = match seek(offset, libc::SEEK_DATA) {
+            Ok(start) => start,
+            // No data past `offset`
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => break,
+            // A filesystem that cannot tell holes from data: read it all
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(vec![WHOLE_FILE]),
+            Err(e) => return Err(e),
+        };
+        let end = seek(start, libc::SEEK_HOLE)?.min(len);
+        regions.push(start..end);
+        offset = end;
+    }
+    // Seeking moved the descriptor; reading starts from the beginning
+    seek(0, libc::SEEK_SET)?;
+    Ok(regions)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn data_regions(_file: &File, _len: u64) -> io::Result<Vec<Range<u64>>> {
+    Ok(vec![WHOLE_FILE])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn read_all(file: File, skip_holes: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        DataReader::new(file, skip_holes).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_reads_around_holes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("sparse");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"first\n").unwrap();
+        file.seek(SeekFrom::Start(64 << 20)).unwrap();
+        file.write_all(b"second\n").unwrap();
+        file.set_len((128 << 20) + 3).unwrap();
+        drop(file);
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        let data = read_all(File::open(&path).unwrap(), true);
+        if !is_sparse(&metadata) {
+            // The filesystem here does not keep holes
+            assert_eq!(data.len() as u64, metadata.len());
+            return;
+        }
+        assert!(stored_len(&metadata) < 1 << 20);
+        // Filesystems allocate whole blocks, so NUL runs around the data may remain
+        let text: Vec<u8> = data.into_iter().filter(|&byte| byte != 0).collect();
+        assert_eq!(text, b"first\nsecond\n");
+        assert_eq!(read_all(File::open(&path).unwrap(), false).len() as u64, metadata.len());
+    }
+}
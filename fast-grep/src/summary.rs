@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::file_processor::is_stdin;
+
+/// What one directory of a `--summary dirs` rollup adds up to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DirCounts {
+    pub matches: usize,
+    pub lines: usize,
+    pub files: usize,
+}
+
+/// The aggregation stage of `--summary dirs`: the workers' per-file results
+/// are added to the directory holding each file, cut to `depth` levels
+/// below the searched path it was found under, so `--summary-depth 1` on a
+/// source tree gives one row per top-level component.
+#[derive(Debug)]
+pub struct DirSummary {
+    roots: Vec<PathBuf>,
+    depth: usize,
+    dirs: HashMap<PathBuf, DirCounts>,
+}
+
+impl DirSummary {
+    /// Roll up files found under `roots`; files named directly on the
+    /// command line count toward their own directory
+    pub fn new(roots: &[PathBuf], depth: usize) -> Self {
+        Self {
+            roots: roots.to_vec(),
+            depth,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// Count a file's matching lines and matches; files without either are
+    /// left out of the rollup
+    pub fn add(&mut self, file: &Path, lines: usize, matches: usize) {
+        if lines == 0 {
+            return;
+        }
+        let counts = self.dirs.entry(self.directory_of(file)).or_default();
+        counts.matches += matches;
+        counts.lines += lines;
+        counts.files += 1;
+    }
+
+    /// The directory `file` is counted toward
+    fn directory_of(&self, file: &Path) -> PathBuf {
+        if is_stdin(file) {
+            return file.to_path_buf();
+        }
+        let parent = file.parent().unwrap_or(Path::new(""));
+        let Some((root, below)) = self
+            .roots
+            .iter()
+            .filter_map(|root| Some((root, parent.strip_prefix(root).ok()?)))
+            .max_by_key(|(root, _)| root.components().count())
+        else {
+            return parent.to_path_buf();
+        };
+        let mut directory = root.clone();
+        directory.extend(
+            below
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .take(self.depth),
+        );
+        directory
+    }
+
+    /// Every directory with its counts: most matches first, then most
+    /// matching lines, then by name
+    pub fn rows(&self) -> Vec<(&Path, DirCounts)> {
+        let mut rows: Vec<(&Path, DirCounts)> =
+            self.dirs.iter().map(|(dir, counts)| (dir.as_path(), *counts)).collect();
+        rows.sort_by(|(a, a_counts), (b, b_counts)| {
+            b_counts
+                .matches
+                .cmp(&a_counts.matches)
+                .then(b_counts.lines.cmp(&a_counts.lines))
+                .then(a.cmp(b))
+        });
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directories_cut_to_depth() {
+        let roots = [PathBuf::from("."), PathBuf::from("vendor/lib")];
+        let summary = |depth| DirSummary::new(&roots, depth);
+
+        let file = Path::new("./src/net/http/client.rs");
+        assert_eq!(summary(1).directory_of(file), PathBuf::from("./src"));
+        assert_eq!(summary(2).directory_of(file), PathBuf::from("./src/net"));
+        assert_eq!(summary(0).directory_of(file), PathBuf::from("."));
+        assert_eq!(summary(1).directory_of(Path::new("./README.md")), PathBuf::from("."));
+
+        // The deepest searched path a file is under is its root
+        assert_eq!(
+            summary(1).directory_of(Path::new("vendor/lib/io/read.rs")),
+            PathBuf::from("vendor/lib/io")
+        );
+        assert_eq!(summary(1).directory_of(Path::new("docs/api.md")), PathBuf::from("docs"));
+        assert_eq!(summary(1).directory_of(Path::new("-")), PathBuf::from("-"));
+    }
+
+    #[test]
+    fn test_rows_sorted_by_matches() {
+        let mut summary = DirSummary::new(&[PathBuf::from("repo")], 1);
+        summary.add(Path::new("repo/net/a.rs"), 2, 3);
+        summary.add(Path::new("repo/net/deep/b.rs"), 1, 1);
+        summary.add(Path::new("repo/ui/c.rs"), 4, 4);
+        summary.add(Path::new("repo/cli/d.rs"), 2, 4);
+        summary.add(Path::new("repo/docs/e.md"), 0, 0);
+
+        let rows = summary.rows();
+        let dirs: Vec<&Path> = rows.iter().map(|(dir, _)| *dir).collect();
+        assert_eq!(dirs, [Path::new("repo/ui"), Path::new("repo/net"), Path::new("repo/cli")]);
+        assert_eq!(rows[1].1, DirCounts { matches: 4, lines: 3, files: 2 });
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Files measured before each decision
+pub const SAMPLE_WINDOW: usize = 32;
+
+/// Share of time spent reading above which a window counts as IO-bound,
+/// and below which it counts as CPU-bound; in between nothing changes
+const IO_BOUND_SHARE: f64 = 0.6;
+const CPU_BOUND_SHARE: f64 = 0.3;
+
+/// While CPU-bound, files from this size on are mapped rather than read,
+/// unless the configured threshold is already lower
+const CPU_BOUND_MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// What the files searched lately were waiting on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Not enough files measured yet
+    Unknown = 0,
+    /// Mostly on opening and reading files: many small files, a cold cache
+    /// or slow storage. Threads blocked in the kernel cost little, so more
+    /// of them keep the device busy.
+    IoBound = 1,
+    /// Mostly on matching: large cached files or an expensive pattern.
+    /// More threads than cores only contend for them.
+    CpuBound = 2,
+}
+
+impl Workload {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Workload::IoBound,
+            2 => Workload::CpuBound,
+            _ => Workload::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    files: usize,
+    reading: Duration,
+    matching: Duration,
+}
+
+/// Chooses, while a search runs, how many workers take files and whether
+/// files are mapped or read.
+///
+/// Workers report how long each file took to read and to match. Mapped
+/// files are left out: their reading happens while matching, and counting
+/// it there would tip the split towards CPU-bound, which maps more. Every
+/// [`SAMPLE_WINDOW`] files the split decides the workload: IO-bound runs
+/// on up to `max` workers, CPU-bound on one per core. Windows do not
+/// accumulate, so a search moving from a tree of small sources to a few
+/// large logs adapts again. With `--threads` the count stays fixed and
+/// only the mmap choice follows the workload.
+#[derive(Debug)]
+pub struct Tuner {
+    cores: usize,
+    max: usize,
+    adaptive: bool,
+    active: AtomicUsize,
+    workload: AtomicU8,
+    window: Mutex<Window>,
+}
+
+impl Tuner {
+    /// Exactly `threads` workers, as `--threads` asks
+    pub fn fixed(threads: usize) -> Self {
+        let threads = threads.max(1);
+        Self::with_limits(threads, threads, false)
+    }
+
+    /// Start on one worker per core and go up to two per core while IO-bound
+    pub fn adaptive(cores: usize) -> Self {
+        let cores = cores.max(1);
+        Self::with_limits(cores, cores * 2, true)
+    }
+
+    fn with_limits(cores: usize, max: usize, adaptive: bool) -> Self {
+        Self {
+            cores,
+            max,
+            adaptive,
+            active: AtomicUsize::new(cores),
+            workload: AtomicU8::new(Workload::Unknown as u8),
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Workers to start: the most that may ever be active
+    pub fn max_threads(&self) -> usize {
+        self.max
+    }
+
+    /// Workers that should be taking files now; always at least one
+    pub fn active_threads(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn workload(&self) -> Workload {
+        Workload::from_u8(self.workload.load(Ordering::Relaxed))
+    }
+
+    /// Size from which files are mapped, given the configured one: the
+    /// copy a read makes is worth avoiding when matching is the bottleneck
+    pub fn mmap_threshold(&self, configured: u64) -> u64 {
+        match self.workload() {
+            Workload::CpuBound => configured.min(CPU_BOUND_MMAP_THRESHOLD),
+            Workload::Unknown | Workload::IoBound => configured,
+        }
+    }
+
+    /// Account for one file, deciding anew when a window is complete
+    pub fn record(&self, reading: Duration, matching: Duration) {
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+        window.files += 1;
+        window.reading += reading;
+        window.matching += matching;
+        if window.files < SAMPLE_WINDOW {
+            return;
+        }
+        let total = (window.reading + window.matching).as_secs_f64();
+        let io_share = if total > 0.0 { window.reading.as_secs_f64() / total } else { 0.0 };
+        *window = Window::default();
+        drop(window);
+
+        let workload = if io_share >= IO_BOUND_SHARE {
+            Workload::IoBound
+        } else if io_share <= CPU_BOUND_SHARE {
+            Workload::CpuBound
+        } else {
+            return;
+        };
+        self.workload.store(workload as u8, Ordering::Relaxed);
+        if self.adaptive {
+            let threads = if workload == Workload::IoBound { self.max } else { self.cores };
+            self.active.store(threads, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(tuner: &Tuner, reading_ms: u64, matching_ms: u64) {
+        for _ in 0..SAMPLE_WINDOW {
+            tuner.record(Duration::from_millis(reading_ms), Duration::from_millis(matching_ms));
+        }
+    }
+
+    #[test]
+    fn test_adapts_to_workload() {
+        let tuner = Tuner::adaptive(4);
+        assert_eq!((tuner.active_threads(), tuner.max_threads()), (4, 8));
+        assert_eq!(tuner.workload(), Workload::Unknown);
+
+        window(&tuner, 9, 1);
+        assert_eq!(tuner.workload(), Workload::IoBound);
+        assert_eq!(tuner.active_threads(), 8);
+        assert_eq!(tuner.mmap_threshold(100 << 20), 100 << 20);
+
+        // An undecided window keeps the last decision
+        window(&tuner, 1, 1);
+        assert_eq!(tuner.active_threads(), 8);
+
+        window(&tuner, 1, 9);
+        assert_eq!(tuner.workload(), Workload::CpuBound);
+        assert_eq!(tuner.active_threads(), 4);
+        assert_eq!(tuner.mmap_threshold(100 << 20), CPU_BOUND_MMAP_THRESHOLD);
+        assert_eq!(tuner.mmap_threshold(4096), 4096);
+    }
+
+    #[test]
+    fn test_fixed_threads_are_kept() {
+        let tuner = Tuner::fixed(3);
+        window(&tuner, 9, 1);
+        assert_eq!(tuner.workload(), Workload::IoBound);
+        assert_eq!((tuner.active_threads(), tuner.max_threads()), (3, 3));
+        assert_eq!(Tuner::fixed(0).active_threads(), 1);
+    }
+}
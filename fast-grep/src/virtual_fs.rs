@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Filesystems whose files the kernel makes up as they are read: searching
+/// them can block, never end, or report state rather than content. autofs
+/// is left out, as looking at its mount points mounts them.
+pub const VIRTUAL_FS_TYPES: &[&str] = &[
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "debugfs",
+    "tracefs",
+    "securityfs",
+    "cgroup",
+    "cgroup2",
+    "pstore",
+    "bpf",
+    "configfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "binfmt_misc",
+    "rpc_pipefs",
+    "nsfs",
+    "selinuxfs",
+];
+
+/// The mounted filesystems discovery does not descend into: the virtual
+/// ones unless `--search-virtual`, and those of the `--skip-fs` types.
+///
+/// Mounts are told apart by device, so `/proc` is left out however it is
+/// reached; a search that starts on such a filesystem still searches it.
+#[derive(Debug, Clone, Default)]
+pub struct PrunedMounts {
+    devices: Arc<HashSet<u64>>,
+}
+
+impl PrunedMounts {
+    /// The mounts of `types` in this process's mount table; none where
+    /// there is no `/proc/self/mounts` to read
+    pub fn load(types: &[String]) -> Self {
+        let table = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        Self::from_mount_points(&mount_points(&table, types))
+    }
+
+    #[cfg(unix)]
+    fn from_mount_points(points: &[PathBuf]) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        let devices = points
+            .iter()
+            .filter_map(|point| std::fs::metadata(point).ok())
+            .map(|metadata| metadata.dev())
+            .collect();
+        Self { devices: Arc::new(devices) }
+    }
+
+    #[cfg(not(unix))]
+    fn from_mount_points(_points: &[PathBuf]) -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Walk filter that keeps out of the pruned mounts below `root`
+    #[cfg(unix)]
+    pub fn filter(&self, root: &Path) -> impl Fn(&ignore::DirEntry) -> bool + Send + Sync + 'static {
+        use std::os::unix::fs::MetadataExt;
+
+        let root_device = std::fs::metadata(root).map(|metadata| metadata.dev()).ok();
+        let devices = Arc::clone(&self.devices);
+        move |entry| {
+            if entry.depth() == 0 || !entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                return true;
+            }
+            match entry.metadata() {
+                Ok(metadata) => Some(metadata.dev()) == root_device || !devices.contains(&metadata.dev()),
+                Err(_) => true,
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn filter(&self, _root: &Path) -> impl Fn(&ignore::DirEntry) -> bool + Send + Sync + 'static {
+        |_| true
+    }
+}
+
+/// Mount points of the filesystems of `types` in a `/proc/mounts` table
+fn mount_points(table: &str, types: &[String]) -> Vec<PathBuf> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let (_device, point, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            types.iter().any(|name| name == fs_type).then(|| PathBuf::from(unescape(point)))
+        })
+        .collect()
+}
+
+/// Mount points are written with spaces, tabs, newlines and backslashes
+/// as octal escapes
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        unescaped.push_str(&rest[..at]);
+        let code = rest.get(at + 1..at + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(char::from(code));
+                rest = &rest[at + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[at + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_points() {
+        let table = "proc /proc proc rw,nosuid 0 0\n\
+                     sysfs /sys sysfs rw 0 0\n\
+                     /dev/sda1 / ext4 rw 0 0\n\
+                     server:/export /mnt/my\\040share nfs4 rw 0 0\n\
+                     cgroup2 /sys/fs/cgroup cgroup2 rw 0 0\n";
+        let virtual_types: Vec<String> = VIRTUAL_FS_TYPES.iter().map(|name| name.to_string()).collect();
+        assert_eq!(
+            mount_points(table, &virtual_types),
+            [PathBuf::from("/proc"), PathBuf::from("/sys"), PathBuf::from("/sys/fs/cgroup")]
+        );
+        assert_eq!(mount_points(table, &["nfs4".to_string()]), [PathBuf::from("/mnt/my share")]);
+        assert_eq!(unescape("a\\134b\\x"), "a\\b\\x");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_prunes_proc_below_root_only() {
+        use clap::Parser;
+
+        let mounts = PrunedMounts::load(&["proc".to_string()]);
+        if mounts.is_empty() {
+            return;
+        }
+        let walk = |root: &str, max_depth| {
+            let filter = mounts.filter(Path::new(root));
+            ignore::WalkBuilder::new(root)
+                .standard_filters(false)
+                .max_depth(Some(max_depth))
+                .filter_entry(filter)
+                .build()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.into_path())
+                .collect::<Vec<_>>()
+        };
+        assert!(!walk("/", 1).contains(&PathBuf::from("/proc")));
+        assert!(walk("/proc", 1).contains(&PathBuf::from("/proc/self")));
+
+        let args = crate::cli::Args::parse_from(["fgrep", "x", "/", "--search-virtual"]);
+        assert!(args.pruned_fs_types().is_empty());
+    }
+}
@@ -1,14 +1,34 @@
 use anyhow::Result;
 use crossbeam::channel::{self, Receiver, Sender};
-use rayon::prelude::*;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::cache::SearchCache;
+use crate::cancel::CancelToken;
+use crate::errors::FastGrepError;
 use crate::file_processor::{FileProcessor, FileContent};
+use crate::git_changes::ChangedLines;
+use crate::memory::MemoryBudget;
 use crate::output::MatchResult;
 use crate::pattern_matcher::{PatternMatcher, Match};
+use crate::scope_filter::{ScopeFilter, ScopedFile};
+use crate::sparse;
+use crate::tuning::Tuner;
+
+/// Files each worker may search ahead of the one being output
+pub const FILES_IN_FLIGHT_PER_THREAD: usize = 4;
+
+/// How often a worker the tuner has parked checks whether it is needed again
+const PARKED_POLL: Duration = Duration::from_millis(2);
+
+/// What searching one file produced
+type FileOutcome = Result<(Vec<MatchResult>, SearchStats)>;
 
 pub struct WorkerPool {
     file_processor: Arc<FileProcessor>,
@@ -17,6 +37,15 @@ pub struct WorkerPool {
     invert_match: bool,
     before_context: usize,
     after_context: usize,
+    cancel: CancelToken,
+    scope: Option<ScopeFilter>,
+    changed: Option<Arc<ChangedLines>>,
+    cache: Option<Arc<SearchCache>>,
+    /// Byte ending each line: `\n`, or NUL for `--null-data`
+    record_terminator: u8,
+    tuner: Arc<Tuner>,
+    /// `--max-memory` in bytes
+    memory_limit: Option<u64>,
 }
 
 impl WorkerPool {
@@ -33,6 +62,13 @@ impl WorkerPool {
             invert_match,
             before_context: 0,
             after_context: 0,
+            cancel: CancelToken::default(),
+            scope: None,
+            changed: None,
+            cache: None,
+            record_terminator: b'\n',
+            tuner: Arc::new(Tuner::fixed(num_threads)),
+            memory_limit: None,
         }
     }
 
@@ -42,45 +78,319 @@ impl WorkerPool {
         self
     }
 
-    pub fn search_files(&self, file_paths: Vec<PathBuf>) -> Result<Vec<MatchResult>> {
-        // Use rayon for parallel processing of files
-        let results: Result<Vec<Vec<MatchResult>>, _> = file_paths
-            .par_iter()
-            .map(|path| self.search_single_file(path))
-            .collect();
+    /// Stop picking up files, and stop scanning the current ones, once
+    /// `cancel` fires
+    pub fn with_cancellation(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Only report matches inside comments, strings or code; files in
+    /// languages the scope filter does not know are searched without result
+    pub fn with_scope(mut self, scope: Option<ScopeFilter>) -> Self {
+        self.scope = scope;
+        self
+    }
 
-        // Flatten results
-        let mut all_matches = Vec::new();
-        for file_results in results? {
-            all_matches.extend(file_results);
+    /// Only report lines that git sees as added or modified; other files
+    /// are searched without result
+    pub fn with_changed_lines(mut self, changed: Option<Arc<ChangedLines>>) -> Self {
+        self.changed = changed;
+        self
+    }
+
+    /// Reuse results of unchanged files from `cache` and store new ones
+    pub fn with_cache(mut self, cache: Option<Arc<SearchCache>>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Let the number of busy workers follow the workload, from
+    /// `num_threads` (one per core) up to twice that; without it exactly
+    /// `num_threads` work, as `--threads` asks
+    pub fn with_adaptive_threads(mut self, adaptive: bool) -> Self {
+        if adaptive {
+            self.tuner = Arc::new(Tuner::adaptive(self.num_threads));
         }
+        self
+    }
 
-        Ok(all_matches)
+    /// Split files into records at `terminator` rather than at newlines
+    pub fn with_record_terminator(mut self, terminator: u8) -> Self {
+        self.record_terminator = terminator;
+        self
     }
 
-    fn search_single_file(&self, file_path: &PathBuf) -> Result<Vec<MatchResult>> {
-        let file_content = self.file_processor.process_file(file_path)?;
-        
-        match file_content {
-            FileContent::Binary => Ok(Vec::new()),
-            _ => {
-                let bytes = file_content.as_bytes().unwrap();
-                
-                if self.invert_match {
-                    // For inverted matches, find lines that DON'T contain the pattern
-                    self.find_non_matching_lines(file_path.clone(), &file_content)
-                } else {
-                    let matches = self.pattern_matcher.find_matches(bytes);
-                    
-                    if matches.is_empty() {
-                        return Ok(Vec::new());
+    /// Hold at most about `limit` bytes of file content at once: bigger
+    /// files are streamed in windows (see [`MemoryBudget`]). A match that
+    /// spans records may be missed where a window ends.
+    pub fn with_memory_limit(mut self, limit: Option<u64>) -> Self {
+        self.memory_limit = limit;
+        self
+    }
+
+    /// Search `file_paths` and gather every result. Output should use
+    /// [`WorkerPool::search_streaming`] instead, which holds only the files
+    /// in flight.
+    pub fn search_files(&self, file_paths: Vec<PathBuf>) -> Result<SearchResults> {
+        let mut matches = Vec::new();
+        let stats = self.search_streaming(&file_paths, |file| {
+            matches.extend(file.matches);
+            Ok(())
+        })?;
+        Ok(SearchResults { matches, stats })
+    }
+
+    /// Search `file_paths` on the tuner's workers, handing each file's
+    /// results to `on_file` in the order of `file_paths`, as soon as the
+    /// file and all before it are done. Returns the stats of every file.
+    ///
+    /// Each file gets a one-shot slot for its results, queued in order on
+    /// a bounded channel; while `on_file` is behind, the dispatcher blocks
+    /// on that channel, so at most [`FILES_IN_FLIGHT_PER_THREAD`] files'
+    /// results per worker are held at a time. Workers beyond the tuner's
+    /// active count wait without taking files. An error, from a search or
+    /// from `on_file`, ends the search.
+    pub fn search_streaming<F>(&self, file_paths: &[PathBuf], mut on_file: F) -> Result<SearchStats>
+    where
+        F: FnMut(FileResults) -> Result<()>,
+    {
+        let threads = self.tuner.max_threads();
+        let in_flight = threads * FILES_IN_FLIGHT_PER_THREAD;
+        let (order_tx, order_rx): (Sender<Receiver<FileOutcome>>, _) = channel::bounded(in_flight);
+        let (work_tx, work_rx): (Sender<(&PathBuf, Sender<FileOutcome>)>, _) = channel::bounded(in_flight);
+        let dispatched = &AtomicBool::new(false);
+        let budget = self.memory_limit.map(|limit| MemoryBudget::new(limit, threads));
+        let budget = budget.as_ref();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for path in file_paths {
+                    let (slot_tx, slot_rx) = channel::bounded(1);
+                    // Either send fails once the consumer has given up
+                    if order_tx.send(slot_rx).is_err() || work_tx.send((path, slot_tx)).is_err() {
+                        break;
+                    }
+                }
+                dispatched.store(true, Ordering::Release);
+            });
+            for worker in 0..threads {
+                let work_rx = work_rx.clone();
+                scope.spawn(move || loop {
+                    // Worker 0 is always active, so queued files never strand
+                    if worker >= self.tuner.active_threads() {
+                        if dispatched.load(Ordering::Acquire) && work_rx.is_empty() {
+                            break;
+                        }
+                        thread::sleep(PARKED_POLL);
+                        continue;
                     }
+                    let Ok((path, slot)) = work_rx.recv() else {
+                        break;
+                    };
+                    let outcome = if self.cancel.is_cancelled() {
+                        Ok((Vec::new(), SearchStats::new()))
+                    } else {
+                        self.search_single_file(path, budget)
+                    };
+                    let _ = slot.send(outcome);
+                });
+            }
+            drop(work_rx);
+
+            let mut stats = SearchStats::new();
+            for (path, slot) in file_paths.iter().zip(order_rx) {
+                let (matches, file_stats) = slot
+                    .recv()
+                    .map_err(|_| anyhow::anyhow!("search worker stopped before {}", path.display()))??;
+                stats.merge(&file_stats);
+                on_file(FileResults {
+                    path: path.clone(),
+                    matches,
+                    stats: file_stats,
+                })?;
+            }
+            Ok(stats)
+        })
+    }
 
-                    // Convert byte matches to line-based matches
-                    self.convert_to_line_matches(file_path.clone(), &file_content, matches)
+    fn search_single_file(&self, file_path: &PathBuf, budget: Option<&MemoryBudget>) -> Result<(Vec<MatchResult>, SearchStats)> {
+        match self.cache {
+            Some(ref cache) => cache.get_or_search(file_path, || {
+                let (results, stats) = self.search_file_uncached(file_path, budget)?;
+                // Results of a file cut short by cancellation are partial
+                Ok::<_, anyhow::Error>((results, stats, !self.cancel.is_cancelled()))
+            }),
+            None => self.search_file_uncached(file_path, budget),
+        }
+    }
+
+    fn search_file_uncached(&self, file_path: &PathBuf, budget: Option<&MemoryBudget>) -> Result<(Vec<MatchResult>, SearchStats)> {
+        let mut stats = SearchStats::new();
+        if self.scope.is_some() && !ScopeFilter::supports(file_path) {
+            stats.add_file(false, 0, 0);
+            return Ok((Vec::new(), stats));
+        }
+        let changed_lines = match &self.changed {
+            Some(changed) => match changed.for_file(file_path) {
+                Some(ranges) => Some(ranges),
+                None => {
+                    stats.add_file(false, 0, 0);
+                    return Ok((Vec::new(), stats));
                 }
+            },
+            None => None,
+        };
+
+        let started = Instant::now();
+        // Standard input has no size to go by and is read whole
+        let size = std::fs::metadata(file_path).map_or(None, |metadata| Some(sparse::stored_len(&metadata)));
+        if let (Some(budget), Some(size)) = (budget, size) {
+            if size > budget.window() as u64 {
+                let _reservation = budget.reserve(budget.streamed_file());
+                return match self.search_windows(file_path, budget.window()) {
+                    Ok((mut results, file_size)) => {
+                        self.finish_file(&mut results, &mut stats, changed_lines, file_size);
+                        Ok((results, stats))
+                    }
+                    Err(e) if matches!(e.downcast_ref(), Some(FastGrepError::BinaryFile { .. })) => {
+                        stats.skipped_binary += 1;
+                        Ok((Vec::new(), stats))
+                    }
+                    Err(e) => Err(e),
+                };
+            }
+        }
+        let _reservation = budget.zip(size).map(|(budget, size)| budget.reserve(size));
+
+        let mmap_threshold = self.tuner.mmap_threshold(self.file_processor.mmap_threshold());
+        let file_content = match self.file_processor.process_file_with_threshold(file_path, mmap_threshold) {
+            Ok(content) => content,
+            Err(FastGrepError::BinaryFile { .. }) => {
+                stats.skipped_binary += 1;
+                return Ok((Vec::new(), stats));
             }
+            Err(e) => return Err(e.into()),
+        };
+        let reading = started.elapsed();
+        
+        let mut results = match file_content {
+            FileContent::Binary => {
+                stats.skipped_binary += 1;
+                return Ok((Vec::new(), stats));
+            }
+            _ => self.search_content(file_path, &file_content)?,
+        };
+
+        let file_size = file_content.as_bytes().map_or(0, |bytes| bytes.len() as u64);
+        self.finish_file(&mut results, &mut stats, changed_lines, file_size);
+        // A mapped file is read by page faults while it is matched, so its
+        // time would all count as matching
+        if !matches!(file_content, FileContent::Mapped(_)) {
+            self.tuner.record(reading, started.elapsed().saturating_sub(reading));
+        }
+
+        Ok((results, stats))
+    }
+
+    /// Every result in a file's (or a window's) content
+    fn search_content(&self, file_path: &Path, file_content: &FileContent) -> Result<Vec<MatchResult>> {
+        let bytes = file_content.as_bytes().unwrap();
+        let scoped = self.scope.and_then(|scope| scope.for_file(file_path, bytes));
+
+        if self.invert_match {
+            // For inverted matches, find lines that DON'T contain the pattern
+            return self.find_non_matching_lines(file_path.to_path_buf(), file_content, scoped.as_ref());
         }
+        let mut matches = self.pattern_matcher.find_matches(bytes);
+        if let Some(ref scoped) = scoped {
+            matches = scoped.retain(matches, 0);
+        }
+
+        if matches.is_empty() {
+            Ok(Vec::new())
+        } else {
+            // Convert byte matches to line-based matches
+            self.convert_to_line_matches(file_path.to_path_buf(), file_content, matches)
+        }
+    }
+
+    /// Search records appended to a followed file, numbering their lines
+    /// after the `records_before` records already in it
+    pub fn search_appended(&self, file_path: &Path, appended: &FileContent, records_before: usize) -> Result<Vec<MatchResult>> {
+        Ok(self
+            .search_content(file_path, appended)?
+            .into_iter()
+            .map(|result| result.renumbered(records_before))
+            .collect())
+    }
+
+    /// Search a file a window at a time, returning its results and size.
+    ///
+    /// Each window starts with the last records of the one before: the
+    /// `after_context` records whose results could not be completed yet,
+    /// and the `before_context` records ahead of them. Only results on
+    /// records not reported before are kept, renumbered to file lines.
+    fn search_windows(&self, file_path: &Path, window: usize) -> Result<(Vec<MatchResult>, u64)> {
+        let mut windows = self.file_processor.open_windows(file_path, window, self.record_terminator)?;
+        let mut buffer = Vec::new();
+        let mut results = Vec::new();
+        let mut file_size = 0;
+        // Records before the buffer, and records at its start already reported
+        let mut records_before = 0;
+        let mut reported = 0;
+        loop {
+            file_size += windows.read_into(&mut buffer)?;
+            let content = FileContent::InMemory(std::mem::take(&mut buffer));
+            let records = content.records(self.record_terminator).unwrap();
+            let complete = if windows.is_done() {
+                records.len()
+            } else {
+                records.len().saturating_sub(self.after_context)
+            };
+            let carried = complete.saturating_sub(self.before_context);
+            let carried_from = records.get(carried).map_or(content.as_bytes().unwrap().len(), |record| record.start);
+
+            results.extend(
+                self.search_content(file_path, &content)?
+                    .into_iter()
+                    .filter(|result| result.line_number > reported && result.line_number <= complete)
+                    .map(|result| result.renumbered(records_before)),
+            );
+            if windows.is_done() || self.cancel.is_cancelled() {
+                return Ok((results, file_size));
+            }
+
+            let FileContent::InMemory(mut data) = content else {
+                unreachable!("windows are read into memory")
+            };
+            data.drain(..carried_from);
+            buffer = data;
+            records_before += carried;
+            reported = complete - carried;
+        }
+    }
+
+    /// Apply `--changed` to a file's results and count them
+    fn finish_file(
+        &self,
+        results: &mut Vec<MatchResult>,
+        stats: &mut SearchStats,
+        changed_lines: Option<Vec<RangeInclusive<usize>>>,
+        file_size: u64,
+    ) {
+        if let Some(ranges) = changed_lines {
+            results.retain(|result| ranges.iter().any(|range| range.contains(&result.line_number)));
+        }
+
+        // Inverted results are lines without any match; each counts once
+        let match_count = results.iter().map(|result| result.matches.len().max(1)).sum();
+        stats.add_file(!results.is_empty(), file_size, match_count);
+        stats.matched_lines = results
+            .iter()
+            .map(|result| result.line_number)
+            .collect::<HashSet<_>>()
+            .len();
     }
 
     fn convert_to_line_matches(
@@ -89,18 +399,33 @@ impl WorkerPool {
         file_content: &FileContent,
         matches: Vec<Match>,
     ) -> Result<Vec<MatchResult>> {
-        let lines = file_content.lines().unwrap();
-        let mut results = Vec::new();
+        let lines = file_content.records(self.record_terminator).unwrap();
+        let bytes = file_content.as_bytes().unwrap();
+        let mut results: Vec<MatchResult> = Vec::new();
 
         for pattern_match in matches {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+
             // Find which line contains this match
             if let Some(line) = lines.iter().find(|line| line.contains_position(pattern_match.start)) {
-                let line_content = line.as_str()?.to_string();
-
                 // Calculate match position relative to line start
                 let match_start_in_line = pattern_match.start.saturating_sub(line.start);
                 let match_end_in_line = pattern_match.end.saturating_sub(line.start);
+                let replacement = self
+                    .pattern_matcher
+                    .has_extraction()
+                    .then(|| self.pattern_matcher.extract(bytes, &pattern_match));
+
+                // Further matches on the line already reported join its result
+                if let Some(last) = results.last_mut().filter(|last| last.line_number == line.number) {
+                    last.add_match(match_start_in_line, match_end_in_line);
+                    last.replacements.extend(replacement);
+                    continue;
+                }
 
+                let line_content = line.as_str()?.to_string();
                 let mut match_result = MatchResult::new(
                     file_path.clone(),
                     line.number,
@@ -108,6 +433,9 @@ impl WorkerPool {
                     match_start_in_line,
                     match_end_in_line,
                 );
+                // Keep spans and replacements aligned even for empty matches
+                match_result.matches = vec![(match_start_in_line, match_end_in_line)];
+                match_result.replacements.extend(replacement);
 
                 // Add context lines if requested
                 let before_context = self.before_context;
@@ -140,17 +468,29 @@ impl WorkerPool {
         Ok(results)
     }
 
-    fn find_non_matching_lines(&self, file_path: PathBuf, file_content: &FileContent) -> Result<Vec<MatchResult>> {
-        let lines = file_content.lines().unwrap();
+    fn find_non_matching_lines(
+        &self,
+        file_path: PathBuf,
+        file_content: &FileContent,
+        scoped: Option<&ScopedFile>,
+    ) -> Result<Vec<MatchResult>> {
+        let lines = file_content.records(self.record_terminator).unwrap();
         let bytes = file_content.as_bytes().unwrap();
         let mut results = Vec::new();
 
         for line in lines {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+
             let line_start = line.start;
             let line_end = line.end;
             let line_bytes = &bytes[line_start..line_end];
-            let matches = self.pattern_matcher.find_matches(line_bytes);
-            
+            let mut matches = self.pattern_matcher.find_matches(line_bytes);
+            if let Some(scoped) = scoped {
+                matches = scoped.retain(matches, line_start);
+            }
+
             // If no matches found in this line, it's a non-matching line
             if matches.is_empty() {
                 let line_content = line.as_str()?.to_string();
@@ -167,71 +507,58 @@ impl WorkerPool {
 
         Ok(results)
     }
+}
 
-    pub fn search_with_streaming<F>(&self, file_paths: Vec<PathBuf>, mut callback: F) -> Result<()>
-    where
-        F: FnMut(MatchResult) -> Result<()> + Send + Sync,
-    {
-        let (tx, rx): (Sender<MatchResult>, Receiver<MatchResult>) = channel::unbounded();
-        let processed_files = Arc::new(AtomicUsize::new(0));
-        let _total_files = file_paths.len();
-
-        // Spawn worker threads
-        let _handles: Vec<_> = (0..self.num_threads)
-            .map(|_| {
-                let _tx = tx.clone();
-                let _file_processor = Arc::clone(&self.file_processor);
-                let _pattern_matcher = Arc::clone(&self.pattern_matcher);
-                let _processed_files = Arc::clone(&processed_files);
-                
-                thread::spawn(move || -> Result<()> {
-                    // Each worker processes files in parallel using rayon
-                    Ok(())
-                })
-            })
-            .collect();
-
-        // Process files in parallel
-        file_paths.par_iter().try_for_each(|file_path| -> Result<()> {
-            let matches = self.search_single_file(file_path)?;
-            
-            for match_result in matches {
-                tx.send(match_result).map_err(|e| anyhow::anyhow!("Send error: {}", e))?;
-            }
-            
-            processed_files.fetch_add(1, Ordering::Relaxed);
-            Ok(())
-        })?;
-
-        // Close the channel
-        drop(tx);
-
-        // Process results as they come in
-        while let Ok(match_result) = rx.recv() {
-            callback(match_result)?;
-        }
+/// The results of one file, as [`WorkerPool::search_streaming`] hands
+/// them over: every result of a file arrives at once, in line order
+pub struct FileResults {
+    pub path: PathBuf,
+    pub matches: Vec<MatchResult>,
+    pub stats: SearchStats,
+}
 
-        Ok(())
-    }
+/// Matches found by a search together with the stats gathered producing them
+pub struct SearchResults {
+    pub matches: Vec<MatchResult>,
+    pub stats: SearchStats,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchStats {
     pub files_processed: usize,
     pub files_with_matches: usize,
     pub total_matches: usize,
+    pub matched_lines: usize,
     pub bytes_processed: u64,
-    pub processing_time_ms: u64,
+    pub skipped_binary: usize,
+    pub skipped_ignored: usize,
+    pub skipped_too_large: usize,
+    #[serde(skip)]
+    pub elapsed: Duration,
+    /// (hits, misses) of the result cache, when it was used
+    #[serde(skip)]
+    pub cache: Option<(usize, usize)>,
 }
 
 impl SearchStats {
     pub fn new() -> Self {
-        Self {
-            files_processed: 0,
-            files_with_matches: 0,
-            total_matches: 0,
-            bytes_processed: 0,
-            processing_time_ms: 0,
-        }
+        Self::default()
+    }
+
+    pub fn files_skipped(&self) -> usize {
+        self.skipped_binary + self.skipped_ignored + self.skipped_too_large
+    }
+
+    /// Fold another set of counters into this one (elapsed time is kept)
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.files_processed += other.files_processed;
+        self.files_with_matches += other.files_with_matches;
+        self.total_matches += other.total_matches;
+        self.matched_lines += other.matched_lines;
+        self.bytes_processed += other.bytes_processed;
+        self.skipped_binary += other.skipped_binary;
+        self.skipped_ignored += other.skipped_ignored;
+        self.skipped_too_large += other.skipped_too_large;
     }
 
     pub fn add_file(&mut self, had_matches: bool, file_size: u64, match_count: usize) {
@@ -244,11 +571,11 @@ impl SearchStats {
     }
 
     pub fn throughput_mb_per_second(&self) -> f64 {
-        if self.processing_time_ms == 0 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
             return 0.0;
         }
         
-        let seconds = self.processing_time_ms as f64 / 1000.0;
         let mb = self.bytes_processed as f64 / (1024.0 * 1024.0);
         mb / seconds
     }
@@ -267,7 +594,7 @@ mod tests {
         let worker_pool = WorkerPool::new(file_processor, pattern_matcher, 4, false);
         
         assert_eq!(worker_pool.num_threads, 4);
-        assert_eq!(worker_pool.invert_match, false);
+        assert!(!worker_pool.invert_match);
     }
 
     #[test]
@@ -281,4 +608,166 @@ mod tests {
         assert_eq!(stats.total_matches, 5);
         assert_eq!(stats.bytes_processed, 3072);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cancelled_search_skips_remaining_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("text.txt");
+        std::fs::write(&file, "test\n").unwrap();
+
+        let cancel = CancelToken::new(None);
+        let worker_pool = WorkerPool::new(
+            FileProcessor::new(1024 * 1024, true),
+            PatternMatcher::new("test", false, false).unwrap(),
+            2,
+            false,
+        ).with_cancellation(cancel.clone());
+
+        assert_eq!(worker_pool.search_files(vec![file.clone()]).unwrap().matches.len(), 1);
+        cancel.interrupt();
+        let results = worker_pool.search_files(vec![file]).unwrap();
+        assert!(results.matches.is_empty());
+        assert_eq!(results.stats.files_processed, 0);
+    }
+
+    #[test]
+    fn test_binary_files_are_skipped_not_fatal() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let text = temp_dir.path().join("text.txt");
+        let binary = temp_dir.path().join("blob.bin");
+        std::fs::write(&text, "test one\nnothing\ntest test\n").unwrap();
+        std::fs::write(&binary, [0u8, 159, 146, 150, 0, 1, 2]).unwrap();
+
+        let file_processor = FileProcessor::new(1024 * 1024, true);
+        let pattern_matcher = PatternMatcher::new("test", false, false).unwrap();
+        let worker_pool = WorkerPool::new(file_processor, pattern_matcher, 2, false);
+
+        let results = worker_pool.search_files(vec![text, binary]).unwrap();
+        // One result per matching line, holding every match on it
+        assert_eq!(results.matches.len(), 2);
+        assert_eq!(results.matches[1].matches, vec![(0, 4), (5, 9)]);
+        assert_eq!(results.stats.files_processed, 1);
+        assert_eq!(results.stats.files_with_matches, 1);
+        assert_eq!(results.stats.total_matches, 3);
+        assert_eq!(results.stats.matched_lines, 2);
+        assert_eq!(results.stats.skipped_binary, 1);
+    }
+
+    #[test]
+    fn test_streaming_keeps_file_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let files: Vec<PathBuf> = (0..50)
+            .map(|index| {
+                let path = temp_dir.path().join(format!("{:02}.txt", index));
+                // Uneven sizes so workers finish out of order
+                std::fs::write(&path, "test\n".repeat(1 + (index * 37) % 200)).unwrap();
+                path
+            })
+            .collect();
+
+        // Adaptive pools decide on a workload part way through
+        for (threads, adaptive) in [(1, false), (3, false), (8, false), (2, true)] {
+            let worker_pool = WorkerPool::new(
+                FileProcessor::new(1024 * 1024, true),
+                PatternMatcher::new("test", false, false).unwrap(),
+                threads,
+                false,
+            )
+            .with_adaptive_threads(adaptive);
+            let mut seen = Vec::new();
+            let stats = worker_pool
+                .search_streaming(&files, |file| {
+                    assert!(file.matches.iter().all(|result| result.file_path == file.path));
+                    assert!(file.matches.windows(2).all(|pair| pair[0].line_number < pair[1].line_number));
+                    seen.push(file.path);
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(seen, files);
+            assert_eq!(stats.files_processed, 50);
+        }
+
+        // An error from the consumer stops the search
+        let worker_pool = WorkerPool::new(
+            FileProcessor::new(1024 * 1024, true),
+            PatternMatcher::new("test", false, false).unwrap(),
+            2,
+            false,
+        );
+        let mut calls = 0;
+        let stopped = worker_pool.search_streaming(&files, |_| {
+            calls += 1;
+            Err(anyhow::anyhow!("broken pipe"))
+        });
+        assert!(stopped.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_memory_limit_streams_in_windows() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let big = temp_dir.path().join("big.log");
+        let small = temp_dir.path().join("small.log");
+        // About 350 KiB, several windows of the smallest size
+        let text: String = (0..20_000)
+            .map(|line| if line % 997 == 0 { format!("{} needle\n", line) } else { format!("{} hay\n", line) })
+            .collect();
+        std::fs::write(&big, &text).unwrap();
+        std::fs::write(&small, "needle\n").unwrap();
+
+        for invert in [false, true] {
+            let search = |limit: Option<u64>| {
+                let worker_pool = WorkerPool::new(
+                    FileProcessor::new(1024 * 1024, true),
+                    PatternMatcher::new("needle", false, false).unwrap(),
+                    2,
+                    invert,
+                )
+                .with_context(3, 2)
+                .with_memory_limit(limit);
+                worker_pool.search_files(vec![big.clone(), small.clone()]).unwrap()
+            };
+            let whole = search(None);
+            let streamed = search(Some(1));
+            let lines = |results: &SearchResults| {
+                results
+                    .matches
+                    .iter()
+                    .map(|result| (result.line_number, result.context_before.clone(), result.context_after.clone()))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(lines(&streamed), lines(&whole));
+            assert_eq!(streamed.stats.bytes_processed, whole.stats.bytes_processed);
+            assert_eq!(streamed.stats.matched_lines, whole.stats.matched_lines);
+        }
+    }
+
+    #[test]
+    fn test_null_data_records() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let records = temp_dir.path().join("records");
+        std::fs::write(&records, "alpha\nstill alpha's record\0beta\0gamma alpha\0").unwrap();
+
+        let search = |null_data: bool| {
+            WorkerPool::new(
+                FileProcessor::new(1024 * 1024, true).with_null_data(null_data),
+                PatternMatcher::new("alpha", false, false).unwrap(),
+                1,
+                false,
+            )
+            .with_record_terminator(if null_data { b'\0' } else { b'\n' })
+            .search_files(vec![records.clone()])
+            .unwrap()
+        };
+
+        // NUL bytes make the file binary unless they separate records
+        assert_eq!(search(false).stats.skipped_binary, 1);
+        let results = search(true);
+        let found: Vec<(usize, &str)> = results
+            .matches
+            .iter()
+            .map(|result| (result.line_number, result.line_content.as_str()))
+            .collect();
+        assert_eq!(found, vec![(1, "alpha\nstill alpha's record"), (3, "gamma alpha")]);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::entry::{read_listing, EntryMetadata};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Directories modified this recently are not cached: another change within
+/// the filesystem's timestamp granularity could leave their mtime unchanged
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize)]
+struct CachedListing {
+    /// Guards against hash collisions
+    path: PathBuf,
+    /// The directory's mtime when it was read
+    modified: SystemTime,
+    entries: Vec<(String, EntryMetadata)>,
+}
+
+/// `--cache`: directory listings kept on disk between runs, one file per
+/// directory under `$FLS_CACHE_DIR`, `$XDG_CACHE_HOME/fast-ls` or
+/// `~/.cache/fast-ls`.
+///
+/// A listing is reused while the directory's mtime is unchanged, which
+/// covers entries being added, removed or renamed but not their own metadata
+/// changing, so a reused listing is refreshed afterwards by a detached
+/// `fls --refresh-cache` for the next run to pick up.
+pub struct ListingCache {
+    dir: PathBuf,
+    /// Directories answered from the cache, to refresh in the background
+    served: Vec<PathBuf>,
+}
+
+impl ListingCache {
+    /// `None` when there is nowhere to keep the cache
+    pub fn open() -> Option<Self> {
+        let from_env = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        let dir = from_env("FLS_CACHE_DIR")
+            .or_else(|| from_env("XDG_CACHE_HOME").map(|dir| dir.join("fast-ls")))
+            .or_else(|| from_env("HOME").map(|home| home.join(".cache").join("fast-ls")))?;
+        Some(Self::at(dir))
+    }
+
+    fn at(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            served: Vec::new(),
+        }
+    }
+
+    /// Every entry of `path`, hidden ones included, from the cache when the
+    /// directory is unchanged and read (and stored) otherwise
+    pub fn listing(&mut self, path: &Path) -> io::Result<Vec<(String, EntryMetadata)>> {
+        let path = path.canonicalize()?;
+        let modified = fs::metadata(&path)?.modified()?;
+        let cached = fs::read(self.file_for(&path))
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CachedListing>(&data).ok())
+            .filter(|cached| cached.path == path && cached.modified == modified);
+        if let Some(cached) = cached {
+            self.served.push(path);
+            return Ok(cached.entries);
+        }
+
+        let entries = read_listing(&path, true)?;
+        self.store(&path, modified, &entries);
+        Ok(entries)
+    }
+
+    /// Read `path` again and replace its cached listing
+    pub fn refresh(&self, path: &Path) -> io::Result<()> {
+        let path = path.canonicalize()?;
+        let modified = fs::metadata(&path)?.modified()?;
+        let entries = read_listing(&path, true)?;
+        self.store(&path, modified, &entries);
+        Ok(())
+    }
+
+    /// Start a detached `fls --refresh-cache` for the directories that were
+    /// answered from the cache; this process does not wait for it
+    pub fn refresh_served_in_background(&self) {
+        if self.served.is_empty() {
+            return;
+        }
+        let Ok(exe) = std::env::current_exe() else { return };
+        let _ = Command::new(exe)
+            .arg("--refresh-cache")
+            .arg("--")
+            .args(&self.served)
+            .env("FLS_CACHE_DIR", &self.dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+
+    /// Write the listing, replacing any old one in one step; a cache that
+    /// cannot be written is simply not used next time
+    fn store(&self, path: &Path, modified: SystemTime, entries: &[(String, EntryMetadata)]) {
+        if SystemTime::now().duration_since(modified).map_or(true, |age| age < RACY_WINDOW) {
+            return;
+        }
+        let cached = CachedListing {
+            path: path.to_path_buf(),
+            modified,
+            entries: entries.to_vec(),
+        };
+        let file = self.file_for(path);
+        let temporary = file.with_extension(format!("tmp{}", std::process::id()));
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temporary, serde_json::to_vec(&cached)?))
+            .and_then(|_| fs::rename(&temporary, &file));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+
+    fn file_for(&self, path: &Path) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(path.as_os_str().as_encoded_bytes())))
+    }
+}
+
+/// FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_cache() {
+        let root = std::env::temp_dir().join(format!("fls-cache-{}", std::process::id()));
+        let listed = root.join("listed");
+        fs::create_dir_all(&listed).unwrap();
+        fs::write(listed.join("a.txt"), "a").unwrap();
+        fs::write(listed.join(".hidden"), "h").unwrap();
+        // Old enough to be trusted
+        let old = SystemTime::now() - Duration::from_secs(60);
+        fs::File::open(&listed).unwrap().set_modified(old).unwrap();
+
+        let names = |entries: Vec<(String, EntryMetadata)>| {
+            let mut names: Vec<String> = entries.into_iter().map(|(name, _)| name).collect();
+            names.sort();
+            names
+        };
+        let mut cache = ListingCache::at(root.join("cache"));
+        assert_eq!(names(cache.listing(&listed).unwrap()), [".hidden", "a.txt"]);
+        assert!(cache.served.is_empty());
+
+        // An unchanged directory is answered from the cache, even though it
+        // no longer matches
+        fs::write(listed.join("b.txt"), "b").unwrap();
+        fs::File::open(&listed).unwrap().set_modified(old).unwrap();
+        assert_eq!(names(cache.listing(&listed).unwrap()), [".hidden", "a.txt"]);
+        assert_eq!(cache.served.len(), 1);
+
+        // until it is refreshed or its mtime changes
+        cache.refresh(&listed).unwrap();
+        assert_eq!(names(cache.listing(&listed).unwrap()), [".hidden", "a.txt", "b.txt"]);
+        fs::remove_file(listed.join("a.txt")).unwrap();
+        fs::File::open(&listed).unwrap().set_modified(old - Duration::from_secs(1)).unwrap();
+        assert_eq!(names(cache.listing(&listed).unwrap()), [".hidden", "b.txt"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{FileType, Metadata};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// What kind of entry a listing line is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    /// Devices, sockets and FIFOs
+    Other,
+}
+
+/// The parts of an entry's metadata that listings use, detached from
+/// `std::fs::Metadata` so they can be cached between runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryMetadata {
+    pub kind: EntryKind,
+    pub len: u64,
+    /// Allocated 512-byte blocks, as reported by stat
+    pub blocks: u64,
+    pub mode: u32,
+    /// Hard link count
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    /// Last status change
+    pub changed: Option<SystemTime>,
+}
+
+impl From<FileType> for EntryKind {
+    fn from(file_type: FileType) -> Self {
+        if file_type.is_symlink() {
+            EntryKind::Symlink
+        } else if file_type.is_dir() {
+            EntryKind::Dir
+        } else if file_type.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        }
+    }
+}
+
+impl EntryMetadata {
+    /// What `--fast` knows of an entry: its kind, with everything stat
+    /// would tell left empty
+    pub fn of_kind(kind: EntryKind) -> Self {
+        Self {
+            kind,
+            len: 0,
+            blocks: 0,
+            mode: 0,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            modified: None,
+            accessed: None,
+            created: None,
+            changed: None,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == EntryKind::Symlink
+    }
+}
+
+impl From<&Metadata> for EntryMetadata {
+    fn from(metadata: &Metadata) -> Self {
+        let kind = EntryKind::from(metadata.file_type());
+        let (blocks, mode, nlink, uid, gid, changed) = unix_fields(metadata);
+        Self {
+            kind,
+            len: metadata.len(),
+            blocks,
+            mode,
+            nlink,
+            uid,
+            gid,
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            created: metadata.created().ok(),
+            changed,
+        }
+    }
+}
+
+/// Blocks, mode, link count, uid, gid and status change time
+#[cfg(unix)]
+fn unix_fields(metadata: &Metadata) -> (u64, u32, u64, u32, u32, Option<SystemTime>) {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::Duration;
+
+    let offset = Duration::new(metadata.ctime().unsigned_abs(), metadata.ctime_nsec() as u32);
+    let changed = if metadata.ctime() >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(offset)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(offset)
+    };
+    (metadata.blocks(), metadata.mode(), metadata.nlink(), metadata.uid(), metadata.gid(), changed)
+}
+
+#[cfg(not(unix))]
+fn unix_fields(metadata: &Metadata) -> (u64, u32, u64, u32, u32, Option<SystemTime>) {
+    (metadata.len().div_ceil(512), 0, 1, 0, 0, None)
+}
+
+/// The entries of `dir` with their metadata, in directory order. Hidden
+/// entries are only stat'ed when `include_hidden` is set.
+pub fn read_listing(dir: &Path, include_hidden: bool) -> io::Result<Vec<(String, EntryMetadata)>> {
+    let mut listing = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !include_hidden && name.starts_with('.') {
+            continue;
+        }
+        let metadata = EntryMetadata::from(&entry.metadata()?);
+        listing.push((name, metadata));
+    }
+    Ok(listing)
+}
+
+/// `--fast`: the entries of `dir` with only their kinds, in directory
+/// order. The kind comes from the `d_type` getdents64 returns with each
+/// name, so nothing is stat'ed except on filesystems that leave it unknown,
+/// where `file_type` falls back to lstat for just those entries.
+pub fn read_kinds(dir: &Path, include_hidden: bool) -> io::Result<Vec<(String, EntryMetadata)>> {
+    let mut listing = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !include_hidden && name.starts_with('.') {
+            continue;
+        }
+        listing.push((name, EntryMetadata::of_kind(EntryKind::from(entry.file_type()?))));
+    }
+    Ok(listing)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_match_stat() {
+        let dir = std::env::temp_dir().join(format!("fls-kinds-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("file"), "x").unwrap();
+        std::fs::write(dir.join(".hidden"), "x").unwrap();
+        std::os::unix::fs::symlink("file", dir.join("link")).unwrap();
+
+        let kinds = |listing: Vec<(String, EntryMetadata)>| {
+            let mut kinds: Vec<(String, EntryKind)> = listing.into_iter().map(|(name, metadata)| (name, metadata.kind)).collect();
+            kinds.sort_by(|a, b| a.0.cmp(&b.0));
+            kinds
+        };
+        let fast = kinds(read_kinds(&dir, false).unwrap());
+        assert_eq!(fast, kinds(read_listing(&dir, false).unwrap()));
+        assert_eq!(fast.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["file", "link", "sub"]);
+        assert_eq!(read_kinds(&dir, true).unwrap().len(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use crate::entry::EntryMetadata;
+
+/// User and group names from /etc/passwd and /etc/group, read once and
+/// then answered from memory
+#[derive(Debug, Default)]
+pub struct Accounts {
+    users: HashMap<String, u32>,
+    groups: HashMap<String, u32>,
+}
+
+impl Accounts {
+    pub fn load() -> Self {
+        let read = |path: &str| std::fs::read_to_string(path).unwrap_or_default();
+        Self {
+            users: parse_id_database(&read("/etc/passwd")),
+            groups: parse_id_database(&read("/etc/group")),
+        }
+    }
+
+    /// The uid for a user name or numeric id
+    pub fn uid(&self, user: &str) -> Result<u32> {
+        resolve(user, &self.users).ok_or_else(|| anyhow!("unknown user '{}'", user))
+    }
+
+    /// The gid for a group name or numeric id
+    pub fn gid(&self, group: &str) -> Result<u32> {
+        resolve(group, &self.groups).ok_or_else(|| anyhow!("unknown group '{}'", group))
+    }
+}
+
+/// Map names to ids from `name:password:id:...` lines
+fn parse_id_database(contents: &str) -> HashMap<String, u32> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((name.to_string(), id))
+        })
+        .collect()
+}
+
+fn resolve(name: &str, ids: &HashMap<String, u32>) -> Option<u32> {
+    ids.get(name).copied().or_else(|| name.parse().ok())
+}
+
+/// How `--perm` compares the mode bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PermMatch {
+    /// `MODE`: exactly these bits
+    Exact,
+    /// `-MODE`: at least all of these bits
+    All,
+    /// `/MODE`: at least one of these bits
+    Any,
+}
+
+/// A `--perm` test, following find(1): `644`, `-u+x`, `/o+w`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermFilter {
+    kind: PermMatch,
+    bits: u32,
+    negate: bool,
+}
+
+impl PermFilter {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (negate, spec) = strip_negation(spec);
+        let (kind, mode) = if let Some(mode) = spec.strip_prefix('-') {
+            (PermMatch::All, mode)
+        } else if let Some(mode) = spec.strip_prefix('/') {
+            (PermMatch::Any, mode)
+        } else {
+            (PermMatch::Exact, spec)
+        };
+        let bits = parse_mode(mode).ok_or_else(|| {
+            anyhow!("invalid permission mode '{}': use octal like 644 or symbolic like o+w", spec)
+        })?;
+        Ok(Self { kind, bits, negate })
+    }
+
+    pub fn matches(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        let matched = match self.kind {
+            PermMatch::Exact => mode == self.bits,
+            PermMatch::All => mode & self.bits == self.bits,
+            // find treats `/000` as matching everything
+            PermMatch::Any => self.bits == 0 || mode & self.bits != 0,
+        };
+        matched != self.negate
+    }
+}
+
+/// Octal, or comma-separated `[ugoa]*[+=][rwxst]*` clauses
+fn parse_mode(mode: &str) -> Option<u32> {
+    if !mode.is_empty() && mode.bytes().all(|b| b.is_ascii_digit()) {
+        return u32::from_str_radix(mode, 8).ok().filter(|bits| *bits <= 0o7777);
+    }
+
+    let mut bits = 0;
+    for clause in mode.split(',') {
+        let op = clause.find(['+', '='])?;
+        let (who, perms) = (&clause[..op], &clause[op + 1..]);
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        if who.is_empty() {
+            mask = 0o7777;
+        }
+        let mut wanted = 0;
+        for c in perms.chars() {
+            wanted |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => return None,
+            };
+        }
+        bits |= mask & wanted;
+    }
+    Some(bits)
+}
+
+/// A leading `!` inverts a test
+fn strip_negation(spec: &str) -> (bool, &str) {
+    match spec.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, spec),
+    }
+}
+
+/// The `--owner`, `--group` and `--perm` tests an entry must pass
+#[derive(Debug, Default)]
+pub struct EntryFilter {
+    /// uid, negated
+    owner: Option<(u32, bool)>,
+    /// gid, negated
+    group: Option<(u32, bool)>,
+    perm: Option<PermFilter>,
+}
+
+impl EntryFilter {
+    pub fn new(owner: Option<&str>, group: Option<&str>, perm: Option<&str>) -> Result<Self> {
+        if cfg!(not(unix)) && (owner.is_some() || group.is_some() || perm.is_some()) {
+            bail!("--owner, --group and --perm need Unix file ownership and modes");
+        }
+
+        let accounts = if owner.is_some() || group.is_some() {
+            Accounts::load()
+        } else {
+            Accounts::default()
+        };
+        let owner = owner
+            .map(strip_negation)
+            .map(|(negate, user)| accounts.uid(user).map(|uid| (uid, negate)))
+            .transpose()?;
+        let group = group
+            .map(strip_negation)
+            .map(|(negate, name)| accounts.gid(name).map(|gid| (gid, negate)))
+            .transpose()?;
+        let perm = perm.map(PermFilter::parse).transpose()?;
+        Ok(Self { owner, group, perm })
+    }
+
+    pub fn matches(&self, metadata: &EntryMetadata) -> bool {
+        self.owner.is_none_or(|(uid, negate)| (metadata.uid == uid) != negate)
+            && self.group.is_none_or(|(gid, negate)| (metadata.gid == gid) != negate)
+            && self.perm.as_ref().is_none_or(|perm| perm.matches(metadata.mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perm_filter() {
+        let world_writable = PermFilter::parse("/o+w").unwrap();
+        assert!(world_writable.matches(0o100666));
+        assert!(!world_writable.matches(0o100644));
+
+        let exact = PermFilter::parse("644").unwrap();
+        assert!(exact.matches(0o100644));
+        assert!(!exact.matches(0o100664));
+
+        let executable = PermFilter::parse("-u+x,g+x").unwrap();
+        assert!(executable.matches(0o755));
+        assert!(!executable.matches(0o744));
+
+        assert!(PermFilter::parse("!/o+w").unwrap().matches(0o644));
+        assert!(PermFilter::parse("/000").unwrap().matches(0o600));
+        assert!(PermFilter::parse("-a=s").unwrap().matches(0o6755));
+        assert!(PermFilter::parse("9").is_err());
+        assert!(PermFilter::parse("q+w").is_err());
+        assert!(PermFilter::parse("ow").is_err());
+    }
+
+    #[test]
+    fn test_id_database() {
+        let accounts = Accounts {
+            users: parse_id_database("root:x:0:0:root:/root:/bin/sh\n# comment\ndeploy:x:1001:1001::/srv:/bin/sh\n"),
+            groups: parse_id_database("wheel:x:10:root,deploy\nbroken\n"),
+        };
+        assert_eq!(accounts.uid("deploy").unwrap(), 1001);
+        assert_eq!(accounts.uid("4242").unwrap(), 4242);
+        assert!(accounts.uid("nobody-here").is_err());
+        assert_eq!(accounts.gid("wheel").unwrap(), 10);
+        assert!(accounts.gid("broken").is_err());
+    }
+}
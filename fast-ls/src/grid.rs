@@ -0,0 +1,155 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Spaces between columns, as in GNU ls
+const COLUMN_GAP: usize = 2;
+
+/// Width used when it cannot be detected and `COLUMNS` is unset
+const DEFAULT_WIDTH: usize = 80;
+
+/// Order in which entries fill the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Down each column, then across (`ls -C`)
+    Down,
+    /// Across each row, then down (`ls -x`)
+    Across,
+}
+
+/// One entry to place in the grid
+#[derive(Debug, Clone)]
+pub struct Cell {
+    /// What gets printed, possibly with color escapes
+    pub text: String,
+    /// Display width of the text without escapes
+    pub width: usize,
+}
+
+impl Cell {
+    pub fn new(plain: &str, text: String) -> Self {
+        Self {
+            text,
+            width: plain.width(),
+        }
+    }
+}
+
+/// Terminal width for the grid: `COLUMNS` if set, else the width of the
+/// terminal on stdout, else 80
+pub fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .or_else(|| terminal_size::terminal_size().map(|(width, _)| width.0 as usize))
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Lay the cells out in as many columns as fit in `width`, one line per
+/// row; a single column is used when even two do not fit
+pub fn render(cells: &[Cell], width: usize, direction: Direction) -> Vec<String> {
+    if cells.is_empty() {
+        return Vec::new();
+    }
+
+    let (rows, column_widths) = fit(cells, width, direction);
+    let columns = column_widths.len();
+    let mut lines = Vec::with_capacity(rows);
+
+    for row in 0..rows {
+        let indices: Vec<usize> = (0..columns)
+            .map(|column| cell_index(row, column, rows, columns, direction))
+            .filter(|&index| index < cells.len())
+            .collect();
+
+        let mut line = String::new();
+        for (position, &index) in indices.iter().enumerate() {
+            let cell = &cells[index];
+            line.push_str(&cell.text);
+            if position + 1 < indices.len() {
+                let column = column_of(index, rows, columns, direction);
+                let padding = column_widths[column] - cell.width + COLUMN_GAP;
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Find the largest column count whose grid fits; returns the row count
+/// and the width of each column
+fn fit(cells: &[Cell], width: usize, direction: Direction) -> (usize, Vec<usize>) {
+    // Columns at least one character wide plus a gap bound the search
+    let max_columns = cells.len().min(width / (1 + COLUMN_GAP) + 1).max(1);
+
+    for columns in (2..=max_columns).rev() {
+        let rows = cells.len().div_ceil(columns);
+        // Column-major grids may need fewer columns than asked for
+        let columns = match direction {
+            Direction::Down => cells.len().div_ceil(rows),
+            Direction::Across => columns,
+        };
+
+        let mut column_widths = vec![0; columns];
+        for (index, cell) in cells.iter().enumerate() {
+            let column = column_of(index, rows, columns, direction);
+            column_widths[column] = column_widths[column].max(cell.width);
+        }
+
+        let total = column_widths.iter().sum::<usize>() + COLUMN_GAP * (columns - 1);
+        if total <= width {
+            return (rows, column_widths);
+        }
+    }
+
+    let widest = cells.iter().map(|cell| cell.width).max().unwrap_or(0);
+    (cells.len(), vec![widest])
+}
+
+fn column_of(index: usize, rows: usize, columns: usize, direction: Direction) -> usize {
+    match direction {
+        Direction::Down => index / rows,
+        Direction::Across => index % columns,
+    }
+}
+
+fn cell_index(row: usize, column: usize, rows: usize, columns: usize, direction: Direction) -> usize {
+    match direction {
+        Direction::Down => column * rows + row,
+        Direction::Across => row * columns + column,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(names: &[&str]) -> Vec<Cell> {
+        names.iter().map(|name| Cell::new(name, name.to_string())).collect()
+    }
+
+    #[test]
+    fn test_column_major_and_row_major() {
+        let names = cells(&["a", "bb", "ccc", "d", "ee"]);
+        assert_eq!(render(&names, 12, Direction::Down), vec!["a   ccc  ee", "bb  d"]);
+        assert_eq!(render(&names, 9, Direction::Down), vec!["a    d", "bb   ee", "ccc"]);
+        assert_eq!(render(&names, 12, Direction::Across), vec!["a  bb  ccc", "d  ee"]);
+        assert_eq!(render(&names, 80, Direction::Down), vec!["a  bb  ccc  d  ee"]);
+    }
+
+    #[test]
+    fn test_narrow_terminal_falls_back_to_one_column() {
+        let names = cells(&["long-name-one", "long-name-two"]);
+        assert_eq!(render(&names, 10, Direction::Down), vec!["long-name-one", "long-name-two"]);
+        assert!(render(&[], 80, Direction::Down).is_empty());
+    }
+
+    #[test]
+    fn test_width_ignores_color_escapes() {
+        let colored = vec![
+            Cell::new("dir/", "\x1b[34mdir/\x1b[0m".to_string()),
+            Cell::new("file", "file".to_string()),
+        ];
+        assert_eq!(render(&colored, 10, Direction::Down), vec!["\x1b[34mdir/\x1b[0m  file"]);
+    }
+}
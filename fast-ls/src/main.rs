@@ -1,7 +1,33 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Parser;
 use colored::*;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+mod cache;
+mod entry;
+mod filter;
+mod grid;
+mod mounts;
+mod preview;
+mod security;
+mod snapshot;
+mod summary;
+mod symlink;
+mod time_style;
+
+use cache::ListingCache;
+use entry::{read_kinds, read_listing, EntryKind, EntryMetadata};
+use filter::EntryFilter;
+use grid::{Cell, Direction};
+use mounts::MountTable;
+use preview::Previewer;
+use security::SecurityContext;
+use snapshot::{Change, Difference, Snapshot};
+use summary::{Group, Summary, SummaryOrder};
+use symlink::{lists_as_entry, resolve_links, LinkTarget};
+use time_style::{TimeField, TimeStyle};
 
 #[derive(Parser)]
 #[command(name = "fls")]
@@ -20,23 +46,273 @@ struct Args {
     #[arg(short = 'a', long = "all")]
     show_hidden: bool,
     
-    /// Sort by modification time
-    #[arg(short = 't', long = "time")]
+    /// Sort by time, newest first (the timestamp chosen by --time)
+    #[arg(short = 't')]
     sort_by_time: bool,
+
+    /// Timestamp to show and sort by (mtime by default); a bare --time
+    /// sorts by modification time, like -t
+    #[arg(long = "time", value_enum, value_name = "WORD", num_args = 0..=1, require_equals = true)]
+    time: Option<Option<TimeField>>,
+
+    /// How to show times in the long format: relative, iso, long-iso,
+    /// full-iso, locale or +FORMAT (strftime)
+    #[arg(long = "time-style", value_name = "STYLE", value_parser = TimeStyle::parse)]
+    time_style: Option<TimeStyle>,
     
     /// Reverse sort order
     #[arg(short = 'r', long = "reverse")]
     reverse: bool,
+
+    /// Show what symlinks point to instead of the links themselves;
+    /// broken links are still listed as links
+    #[arg(short = 'L', long = "dereference")]
+    dereference: bool,
+
+    /// Follow symlinks given on the command line, but not those inside
+    /// listed directories
+    #[arg(short = 'H', long = "dereference-command-line")]
+    dereference_command_line: bool,
+
+    /// Show a footer with file, directory and symlink counts and total size
+    #[arg(long = "total")]
+    total: bool,
+
+    /// List one entry per line
+    #[arg(short = '1', conflicts_with_all = ["columns", "across"])]
+    one_per_line: bool,
+
+    /// List entries in columns, sorted down each column (default on a terminal)
+    #[arg(short = 'C')]
+    columns: bool,
+
+    /// List entries in columns, sorted across each row
+    #[arg(short = 'x')]
+    across: bool,
+
+    /// Show each entry's security context (SELinux or SMACK label, `?` if
+    /// none) and file capabilities
+    #[arg(short = 'Z', long = "context")]
+    context: bool,
+
+    /// Show the filesystem type and mount point each entry is on, as
+    /// TYPE:MOUNT (with the bound directory in brackets for bind mounts);
+    /// entries on another mount than the listed directory end in `*`
+    #[arg(long = "fs")]
+    fs: bool,
+
+    /// Only list entries owned by USER (name or uid; "!USER" for all others)
+    #[arg(long = "owner", value_name = "USER")]
+    owner: Option<String>,
+
+    /// Only list entries whose group is GROUP (name or gid; "!GROUP" inverts)
+    #[arg(long = "group", value_name = "GROUP")]
+    group: Option<String>,
+
+    /// Only list entries whose mode matches, as in find: "644" exactly,
+    /// "-u+x" all of the bits, "/o+w" any of them; "!" inverts
+    #[arg(long = "perm", value_name = "MODE", allow_hyphen_values = true)]
+    perm: Option<String>,
+
+    /// Show the first N characters of small text files next to their names
+    /// (files up to 256 KiB; binary files show nothing); implies -1 outside
+    /// the long format
+    #[arg(long = "preview", value_name = "N", value_parser = clap::value_parser!(u16).range(1..=1000))]
+    preview: Option<u16>,
+
+    /// List names and types only, without stat'ing each entry (types come
+    /// with the directory entries on most filesystems; symlinks are still
+    /// checked for being broken). Much faster on NFS and in huge directories.
+    #[arg(
+        long = "fast",
+        conflicts_with_all = [
+            "long_format", "sort_by_time", "time", "dereference", "total", "owner", "group", "perm", "preview", "cache",
+            "snapshot", "diff", "summary",
+        ]
+    )]
+    fast: bool,
+
+    /// Reuse listings of directories unchanged since an earlier --cache run
+    /// (by directory mtime), refreshing them in the background afterwards
+    #[arg(long = "cache")]
+    cache: bool,
+
+    /// Instead of listing, record each entry's type, size, mode and owner in
+    /// FILE for a later --diff (hidden entries with -a; filters apply)
+    #[arg(long = "snapshot", value_name = "FILE", conflicts_with = "diff")]
+    snapshot: Option<PathBuf>,
+
+    /// Instead of listing, show entries added, removed, resized, or changed
+    /// in type, permissions or owner since --snapshot FILE; exits with
+    /// status 1 if anything changed
+    #[arg(long = "diff", value_name = "FILE")]
+    diff: Option<PathBuf>,
+
+    /// After each listing, break the entries down by extension and type,
+    /// with counts, total sizes and each group's share of the bytes; most
+    /// entries first, or with --summary=size largest first
+    #[arg(
+        long = "summary",
+        value_enum,
+        value_name = "ORDER",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "count",
+        conflicts_with_all = ["snapshot", "diff"]
+    )]
+    summary: Option<SummaryOrder>,
+
+    /// Re-read the given directories into the --cache store, printing nothing
+    #[arg(long = "refresh-cache", hide = true)]
+    refresh_cache: bool,
+}
+
+impl Args {
+    /// Whether to sort by time: `-t`, or `--time` without a value
+    fn sorts_by_time(&self) -> bool {
+        self.sort_by_time || self.time == Some(None)
+    }
+
+    /// The timestamp chosen by `--time=WORD`
+    fn time_field(&self) -> TimeField {
+        self.time.flatten().unwrap_or_default()
+    }
+
+    /// Grid order for the short format, or `None` for one entry per line.
+    /// Without `-C` or `-x`, columns are only used when writing to a terminal.
+    fn grid_direction(&self) -> Option<Direction> {
+        if self.one_per_line || self.preview.is_some() {
+            None
+        } else if self.across {
+            Some(Direction::Across)
+        } else if self.columns || std::io::stdout().is_terminal() {
+            Some(Direction::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// Counts gathered during the metadata pass, for `--total` and the
+/// `total` line of the long format
+#[derive(Debug, Default, Clone, Copy)]
+struct ListingTotals {
+    files: u64,
+    dirs: u64,
+    symlinks: u64,
+    /// Apparent size of everything but directories
+    size: u64,
+    /// Allocated 512-byte blocks, as reported by stat
+    blocks: u64,
+}
+
+impl ListingTotals {
+    fn add(&mut self, metadata: &EntryMetadata) {
+        if metadata.is_symlink() {
+            self.symlinks += 1;
+        } else if metadata.is_dir() {
+            self.dirs += 1;
+        } else {
+            self.files += 1;
+        }
+        if !metadata.is_dir() {
+            self.size += metadata.len;
+        }
+        self.blocks += metadata.blocks;
+    }
+
+    fn merge(&mut self, other: &ListingTotals) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.symlinks += other.symlinks;
+        self.size += other.size;
+        self.blocks += other.blocks;
+    }
+
+    /// GNU `ls -l` header value: allocation in 1K blocks
+    fn kilobyte_blocks(&self) -> u64 {
+        self.blocks.div_ceil(2)
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{}, {}, {}, {} ({} on disk)",
+            plural(self.files, "file"),
+            plural(self.dirs, "dir"),
+            plural(self.symlinks, "symlink"),
+            format_size(self.size),
+            format_size(self.blocks * 512)
+        )
+    }
+}
+
+fn plural(count: u64, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    if args.refresh_cache {
+        if let Some(cache) = ListingCache::open() {
+            for path in &args.paths {
+                let _ = cache.refresh(path);
+            }
+        }
+        return Ok(());
+    }
+    let filter = EntryFilter::new(args.owner.as_deref(), args.group.as_deref(), args.perm.as_deref())?;
     
     println!("{}", "🚀 fast-ls (fls) - Enhanced Directory Listing".bold().cyan());
     println!("{}", "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━".dimmed());
+
+    if let Some(ref file) = args.snapshot {
+        let mut snapshot = Snapshot::default();
+        let mut entries = 0;
+        for path in &args.paths {
+            let listing = compared_entries(path, &args, &filter)?;
+            entries += listing.len() as u64;
+            snapshot.record(path, &listing);
+        }
+        snapshot.save(file)?;
+        println!(
+            "\nSnapshot written to {}: {} {} in {}",
+            file.display(),
+            entries,
+            if entries == 1 { "entry" } else { "entries" },
+            plural(args.paths.len() as u64, "dir")
+        );
+        return Ok(());
+    }
+    if let Some(ref file) = args.diff {
+        if diff_directories(file, &args, &filter)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     
+    let mut cache = if args.cache { ListingCache::open() } else { None };
+    let mounts = args.fs.then(MountTable::load);
+    let mut grand_total = ListingTotals::default();
+    let mut grand_summary = Summary::default();
     for path in &args.paths {
-        list_directory(path, &args)?;
+        let (totals, summary) = list_directory(path, &args, &filter, cache.as_mut(), mounts.as_ref())?;
+        grand_total.merge(&totals);
+        grand_summary.merge(&summary);
+    }
+    if let Some(ref cache) = cache {
+        cache.refresh_served_in_background();
+    }
+
+    if args.total && args.paths.len() > 1 {
+        println!("\n{} {}", "Grand total:".bold(), grand_total.summary());
+    }
+    if let (Some(order), true) = (args.summary, args.paths.len() > 1) {
+        println!("\n{}", "Grand summary:".bold());
+        print_summary(&grand_summary, order);
     }
     
     println!("\n{}", "⚡ Coming soon: lightning-fast parallel directory listing with smart caching!".yellow().italic());
@@ -45,29 +321,54 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn list_directory(path: &PathBuf, args: &Args) -> Result<()> {
+fn list_directory(
+    path: &Path,
+    args: &Args,
+    filter: &EntryFilter,
+    cache: Option<&mut ListingCache>,
+    mounts: Option<&MountTable>,
+) -> Result<(ListingTotals, Summary)> {
     println!("\n📁 {}", path.display().to_string().blue().bold());
     
-    let entries = std::fs::read_dir(path)?;
+    // A file, or a symlink shown as itself, is listed on its own, named as
+    // given; hidden or not, it was asked for
+    let dereference_path = args.dereference || args.dereference_command_line;
+    let (dir, mut entries) = if lists_as_entry(path, args.long_format, dereference_path) {
+        let metadata = std::fs::symlink_metadata(path)?;
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+        (path.parent().unwrap_or(Path::new("")), vec![(name, EntryMetadata::from(&metadata))])
+    } else {
+        let entries = match cache {
+            Some(cache) => cache.listing(path)?,
+            None if args.fast => read_kinds(path, args.show_hidden)?,
+            None => read_listing(path, args.show_hidden)?,
+        };
+        let entries = entries.into_iter().filter(|(name, _)| args.show_hidden || !name.starts_with('.')).collect();
+        (path, entries)
+    };
+    // With -H a command-line symlink was followed above; the entries of
+    // the directory it leads to are still shown as links
+    let links = resolve_links(dir, &mut entries, args.dereference);
+
     let mut files = Vec::new();
-    
-    for entry in entries {
-        let entry = entry?;
-        let name = entry.file_name().to_string_lossy().to_string();
-        
-        if !args.show_hidden && name.starts_with('.') {
+    let mut totals = ListingTotals::default();
+    let mut summary = Summary::default();
+    for ((name, metadata), link) in entries.into_iter().zip(links) {
+        if !filter.matches(&metadata) {
             continue;
         }
-        
-        let metadata = entry.metadata()?;
-        files.push((name, metadata));
+        totals.add(&metadata);
+        if args.summary.is_some() {
+            summary.add(&name, &metadata);
+        }
+        files.push((name, metadata, link));
     }
     
     // Simple sorting (will be optimized in future versions)
-    if args.sort_by_time {
+    if args.sorts_by_time() {
         files.sort_by(|a, b| {
-            let time_a = a.1.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-            let time_b = b.1.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+            let time_a = args.time_field().get(&a.1).unwrap_or(SystemTime::UNIX_EPOCH);
+            let time_b = args.time_field().get(&b.1).unwrap_or(SystemTime::UNIX_EPOCH);
             if args.reverse { time_a.cmp(&time_b) } else { time_b.cmp(&time_a) }
         });
     } else {
@@ -76,33 +377,298 @@ fn list_directory(path: &PathBuf, args: &Args) -> Result<()> {
         });
     }
     
-    for (name, metadata) in files {
-        if args.long_format {
-            let size = metadata.len();
-            let permissions = if metadata.is_dir() { "d" } else { "-" };
+    if args.long_format {
+        println!("total {}", totals.kilobyte_blocks());
+    }
+
+    let (files, links): (Vec<(String, EntryMetadata)>, Vec<Option<LinkTarget>>) =
+        files.into_iter().map(|(name, metadata, link)| ((name, metadata), link)).unzip();
+    let previews = match args.preview {
+        Some(chars) => Previewer::new(usize::from(chars)).previews(dir, &files),
+        None => vec![String::new(); files.len()],
+    };
+
+    if args.long_format {
+        let style = args.time_style.clone().unwrap_or_default();
+        let now = SystemTime::now();
+        let times: Vec<String> = files
+            .iter()
+            .map(|(_, metadata)| match args.time_field().get(metadata) {
+                Some(time) => style.format(time, now),
+                None => "-".to_string(),
+            })
+            .collect();
+        let time_width = times.iter().map(|time| time.chars().count()).max().unwrap_or(0);
+        let contexts = security_contexts(dir, &files, args.context);
+        let context_width = contexts.iter().map(|context| context.chars().count()).max().unwrap_or(0);
+        let filesystems = mount_columns(dir, &files, mounts);
+        let fs_width = filesystems.iter().map(|(fs, _)| fs.chars().count()).max().unwrap_or(0);
+        let nlink_width = files.iter().map(|(_, metadata)| metadata.nlink.to_string().len()).max().unwrap_or(1);
+
+        for ((((((name, metadata), link), time), context), (fs, crossed)), preview) in
+            files.into_iter().zip(links).zip(times).zip(contexts).zip(filesystems).zip(previews)
+        {
+            let size = metadata.len;
+            let permissions = match metadata.kind {
+                EntryKind::Dir => "d",
+                EntryKind::Symlink => "l",
+                _ => "-",
+            };
             let size_str = format_size(size);
+            let context = if args.context {
+                format!("{:<width$} ", context, width = context_width)
+            } else {
+                String::new()
+            };
+            let fs = if mounts.is_some() {
+                format!("{} ", fs_color(&format!("{:<width$}", fs, width = fs_width), crossed))
+            } else {
+                String::new()
+            };
             
-            println!("{} {:>10} {}", 
+            let name = match link {
+                Some(link) => format!("{} -> {}", display_name(&name, &metadata, Some(&link)).1, link.target.display()),
+                None if metadata.is_dir() => name.blue().bold().to_string(),
+                None => name,
+            };
+            println!("{} {:>nlink_width$} {}{}{:>10} {} {}{}",
                 permissions.dimmed(),
+                metadata.nlink,
+                context.magenta(),
+                fs,
                 size_str.cyan(),
-                if metadata.is_dir() { name.blue().bold() } else { name.normal() }
+                format!("{:<width$}", time, width = time_width).green(),
+                name,
+                preview_suffix(&preview)
             );
-        } else {
-            print!("{} ", 
-                if metadata.is_dir() { 
-                    format!("{}/", name).blue().bold() 
-                } else { 
-                    name.normal() 
+        }
+    } else {
+        let contexts = security_contexts(dir, &files, args.context);
+        let filesystems = mount_columns(dir, &files, mounts);
+        let names: Vec<(String, String)> = files
+            .iter()
+            .zip(&links)
+            .map(|((name, metadata), link)| display_name(name, metadata, link.as_ref()))
+            .collect();
+        // Previews line up after the longest name
+        let name_width = names.iter().map(|(plain, _)| plain.chars().count()).max().unwrap_or(0);
+        let cells: Vec<Cell> = names
+            .into_iter()
+            .zip(contexts)
+            .zip(filesystems)
+            .zip(previews)
+            .map(|((((plain_name, text), context), (fs, crossed)), preview)| {
+                let mut text = if preview.is_empty() {
+                    text
+                } else {
+                    let padding = name_width - plain_name.chars().count();
+                    format!("{}{}{}", text, " ".repeat(padding), preview_suffix(&preview))
+                };
+                let mut plain = plain_name;
+                if mounts.is_some() {
+                    plain = format!("{} {}", fs, plain);
+                    text = format!("{} {}", fs_color(&fs, crossed), text);
                 }
-            );
+                if args.context {
+                    plain = format!("{} {}", context, plain);
+                    text = format!("{} {}", context.magenta(), text);
+                }
+                Cell::new(&plain, text)
+            })
+            .collect();
+
+        let lines = match args.grid_direction() {
+            Some(direction) => grid::render(&cells, grid::terminal_width(), direction),
+            None => cells.into_iter().map(|cell| cell.text).collect(),
+        };
+        for line in lines {
+            println!("{}", line);
         }
     }
-    
-    if !args.long_format {
-        println!();
+
+    if args.total {
+        println!("{} {}", "Total:".bold(), totals.summary());
+    }
+    if let Some(order) = args.summary {
+        println!("{}", "Summary:".bold());
+        print_summary(&summary, order);
     }
     
-    Ok(())
+    Ok((totals, summary))
+}
+
+/// `--summary` table: one line per group with its entries, size and share
+/// of the listed bytes; directories have no size of their own here
+fn print_summary(summary: &Summary, order: SummaryOrder) {
+    let rows = summary.rows(order);
+    if rows.is_empty() {
+        println!("{}", "No entries".dimmed());
+        return;
+    }
+    let labels: Vec<String> = rows.iter().map(|(group, _)| group.label()).collect();
+    let width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0).max("TYPE".len());
+    let total_size = summary.size();
+    println!("{}", format!("{:<width$} {:>8} {:>10} {:>6}", "TYPE", "COUNT", "SIZE", "SHARE", width = width).dimmed());
+    for (label, (group, totals)) in labels.iter().zip(rows) {
+        let (size, share) = if *group == Group::Kind(EntryKind::Dir) {
+            ("-".to_string(), "-".to_string())
+        } else {
+            let share = if total_size == 0 { 0.0 } else { totals.size as f64 * 100.0 / total_size as f64 };
+            (format_size(totals.size), format!("{:.1}%", share))
+        };
+        println!("{:<width$} {:>8} {:>10} {:>6}", label, totals.count, size.cyan(), share, width = width);
+    }
+}
+
+/// The entries `--snapshot` records and `--diff` compares: those the
+/// listing would show, without following symlinks
+fn compared_entries(path: &Path, args: &Args, filter: &EntryFilter) -> Result<Vec<(String, EntryMetadata)>> {
+    let mut entries = read_listing(path, args.show_hidden)
+        .with_context(|| format!("cannot read directory '{}'", path.display()))?;
+    entries.retain(|(_, metadata)| filter.matches(metadata));
+    Ok(entries)
+}
+
+/// `--diff`: print each listed directory's changes since the snapshot in
+/// `file`, returning whether there were any
+fn diff_directories(file: &Path, args: &Args, filter: &EntryFilter) -> Result<bool> {
+    let snapshot = Snapshot::load(file)?;
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for path in &args.paths {
+        println!("\n📁 {}", path.display().to_string().blue().bold());
+        let Some(directory) = snapshot.directory(path) else {
+            bail!("'{}' is not in snapshot '{}'", path.display(), file.display());
+        };
+        let changes = directory.changes(&compared_entries(path, args, filter)?);
+        if changes.is_empty() {
+            println!("{}", "No changes".dimmed());
+        }
+        for change in changes {
+            match change {
+                Change::Added(name, entry) => {
+                    added += 1;
+                    println!("{} {}  {}, mode {:o}", "+".green().bold(), name.green(), kind_name(entry.kind), entry.mode & 0o7777);
+                }
+                Change::Removed(name, entry) => {
+                    removed += 1;
+                    println!("{} {}  {}", "-".red().bold(), name.red(), kind_name(entry.kind).dimmed());
+                }
+                Change::Changed(name, differences) => {
+                    changed += 1;
+                    let differences: Vec<String> = differences.iter().map(describe_difference).collect();
+                    println!("{} {}  {}", "~".yellow().bold(), name.yellow(), differences.join(", "));
+                }
+            }
+        }
+    }
+    println!(
+        "\n{} {} added, {} removed, {} changed since {}",
+        "Diff:".bold(),
+        added,
+        removed,
+        changed,
+        snapshot.created.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+    );
+    Ok(added + removed + changed > 0)
+}
+
+fn describe_difference(difference: &Difference) -> String {
+    match *difference {
+        Difference::Kind(before, after) => format!("type {} -> {}", kind_name(before), kind_name(after)),
+        Difference::Size(before, after) => format!("size {} -> {}", format_size(before), format_size(after)),
+        Difference::Mode(before, after) => format!("mode {:o} -> {:o}", before, after),
+        Difference::Owner((uid, gid), (new_uid, new_gid)) => {
+            format!("owner {}:{} -> {}:{}", uid, gid, new_uid, new_gid)
+        }
+    }
+}
+
+fn kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::File => "file",
+        EntryKind::Dir => "directory",
+        EntryKind::Symlink => "symlink",
+        EntryKind::Other => "special file",
+    }
+}
+
+/// An entry's name as listed, plain and colored: directories end in `/`
+/// outside the long format, symlinks are cyan and broken ones red with a
+/// trailing `?`
+fn display_name(name: &str, metadata: &EntryMetadata, link: Option<&LinkTarget>) -> (String, String) {
+    match link {
+        Some(link) if link.broken => {
+            let plain = format!("{}?", name);
+            let text = plain.red().bold().to_string();
+            (plain, text)
+        }
+        Some(_) => (name.to_string(), name.cyan().to_string()),
+        None if metadata.is_dir() => {
+            let plain = format!("{}/", name);
+            let text = plain.blue().bold().to_string();
+            (plain, text)
+        }
+        None => (name.to_string(), name.normal().to_string()),
+    }
+}
+
+/// `-Z` column text for each entry, or nothing without `-Z`
+fn security_contexts(dir: &Path, files: &[(String, EntryMetadata)], enabled: bool) -> Vec<String> {
+    files
+        .iter()
+        .map(|(name, _)| {
+            if enabled {
+                SecurityContext::of(&dir.join(name)).display()
+            } else {
+                String::new()
+            }
+        })
+        .collect()
+}
+
+/// `--fs` column text for each entry, and whether it is on another mount
+/// than `dir`; nothing without `--fs`
+fn mount_columns(dir: &Path, files: &[(String, EntryMetadata)], mounts: Option<&MountTable>) -> Vec<(String, bool)> {
+    let Some(mounts) = mounts else {
+        return vec![(String::new(), false); files.len()];
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let home = mounts.find(&dir);
+    files
+        .iter()
+        .map(|(name, metadata)| {
+            // A directory followed from a link with -L may be anywhere;
+            // anything else is looked up by its name, a mount point or not
+            let path = dir.join(name);
+            let path = if metadata.is_dir() { path.canonicalize().unwrap_or(path) } else { path };
+            match mounts.find(&path) {
+                Some(mount) => {
+                    let crossed = home.is_some_and(|home| !std::ptr::eq(home, mount));
+                    let marker = if crossed { "*" } else { "" };
+                    (format!("{}{}", mount.display(), marker), crossed)
+                }
+                None => ("?".to_string(), false),
+            }
+        })
+        .collect()
+}
+
+fn fs_color(fs: &str, crossed: bool) -> ColoredString {
+    if crossed {
+        fs.yellow().bold()
+    } else {
+        fs.dimmed()
+    }
+}
+
+/// A `--preview` text as shown after the entry name
+fn preview_suffix(preview: &str) -> String {
+    if preview.is_empty() {
+        String::new()
+    } else {
+        format!("  {}", preview.dimmed())
+    }
 }
 
 fn format_size(size: u64) -> String {
@@ -120,4 +686,48 @@ fn format_size(size: u64) -> String {
     } else {
         format!("{:.1}{}", size, UNITS[unit_index])
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_totals() {
+        let dir = std::env::temp_dir().join(format!("fls-totals-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), vec![0u8; 1000]).unwrap();
+        std::fs::write(dir.join("b.txt"), vec![0u8; 24]).unwrap();
+
+        let mut totals = ListingTotals::default();
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            totals.add(&EntryMetadata::from(&entry.unwrap().metadata().unwrap()));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((totals.files, totals.dirs, totals.symlinks), (2, 1, 0));
+        assert_eq!(totals.size, 1024);
+        assert!(totals.summary().starts_with("2 files, 1 dir, 0 symlinks, 1.0K"));
+    }
+
+    #[test]
+    fn test_time_option() {
+        let args = Args::parse_from(["fls", "--time"]);
+        assert!(args.sorts_by_time());
+        assert_eq!(args.time_field(), TimeField::Mtime);
+
+        // A bare --time is still the sort switch: what follows is a path
+        let args = Args::parse_from(["fls", "--time", "ctime"]);
+        assert!(args.sorts_by_time());
+        assert_eq!(args.paths, [PathBuf::from("ctime")]);
+        assert_eq!(args.time_field(), TimeField::Mtime);
+
+        let args = Args::parse_from(["fls", "--time=ctime"]);
+        assert!(!args.sorts_by_time());
+        assert_eq!(args.time_field(), TimeField::Ctime);
+
+        let args = Args::parse_from(["fls", "-t", "--time=atime"]);
+        assert!(args.sorts_by_time());
+        assert_eq!(args.time_field(), TimeField::Atime);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// One line of /proc/self/mountinfo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    /// The directory of the source filesystem mounted here: `/` unless
+    /// this is a bind mount of part of it
+    pub root: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl Mount {
+    /// What `--fs` shows: type and mount point, with the bound directory
+    /// in brackets for a bind mount, as findmnt does
+    pub fn display(&self) -> String {
+        if self.root == "/" {
+            format!("{}:{}", self.fs_type, self.mount_point.display())
+        } else {
+            format!("{}:{}[{}]", self.fs_type, self.mount_point.display(), self.root)
+        }
+    }
+}
+
+/// The mounts of this process's namespace, read once per run and looked up
+/// by path: a path is on the mount at the nearest of its ancestors that is
+/// a mount point. The mountinfo file lists mounts in the order they were
+/// made, so where several are stacked on one point the last one is visible.
+#[derive(Debug, Default)]
+pub struct MountTable {
+    mounts: Vec<Mount>,
+    by_point: HashMap<PathBuf, usize>,
+}
+
+impl MountTable {
+    /// The current mounts; empty where /proc/self/mountinfo cannot be read,
+    /// which leaves every entry on an unknown filesystem
+    pub fn load() -> Self {
+        std::fs::read_to_string("/proc/self/mountinfo")
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::default();
+        for mount in text.lines().filter_map(parse_line) {
+            table.by_point.insert(mount.mount_point.clone(), table.mounts.len());
+            table.mounts.push(mount);
+        }
+        table
+    }
+
+    /// The mount `path` resides on; `path` should be absolute and free of
+    /// symlinks in its directories
+    pub fn find(&self, path: &Path) -> Option<&Mount> {
+        path.ancestors()
+            .find_map(|ancestor| self.by_point.get(ancestor))
+            .map(|&index| &self.mounts[index])
+    }
+}
+
+/// `36 35 98:0 /mnt1 /mnt/parent rw,noatime master:1 - ext3 /dev/root rw`:
+/// the mount point is the fifth field, the type and source follow the `-`
+/// that ends the variable list of optional fields
+fn parse_line(line: &str) -> Option<Mount> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let mut fields = mount.split(' ');
+    let root = unescape(fields.nth(3)?);
+    let mount_point = PathBuf::from(unescape(fields.next()?));
+    let mut filesystem = filesystem.split(' ');
+    let fs_type = filesystem.next()?.to_string();
+    let source = unescape(filesystem.next().unwrap_or("none"));
+    Some(Mount {
+        mount_point,
+        root,
+        fs_type,
+        source,
+    })
+}
+
+/// Undo the kernel's octal escapes for spaces, tabs, newlines and
+/// backslashes in paths (`\040` and so on)
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)));
+        match (bytes[i], octal) {
+            (b'\\', Some(digits)) => {
+                let value = digits.iter().fold(0u32, |value, d| value * 8 + u32::from(d - b'0'));
+                unescaped.push(value as u8);
+                i += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 0:21 / /proc rw,nosuid shared:5 - proc proc rw
+30 22 0:26 / /home rw,relatime shared:9 - btrfs /dev/sdb1 rw,space_cache
+31 30 8:1 /srv/data /home/me/my\\040data rw,relatime shared:1 - ext4 /dev/sda1 rw
+32 22 0:30 / /tmp rw shared:12 - tmpfs tmpfs rw
+33 32 0:31 / /tmp rw master:3 propagate_from:2 - overlay overlay rw
+";
+
+    #[test]
+    fn test_find_mount() {
+        let table = MountTable::parse(MOUNTINFO);
+        let fs = |path: &str| table.find(Path::new(path)).map(Mount::display);
+        assert_eq!(fs("/etc/passwd").unwrap(), "ext4:/");
+        assert_eq!(fs("/home/me/notes").unwrap(), "btrfs:/home");
+        assert_eq!(fs("/home").unwrap(), "btrfs:/home");
+        assert_eq!(fs("/home/me/my data/file").unwrap(), "ext4:/home/me/my data[/srv/data]");
+        // Stacked on the same point, the later mount hides the earlier one
+        assert_eq!(fs("/tmp/x").unwrap(), "overlay:/tmp");
+        assert_eq!(table.find(Path::new("/home")).unwrap().source, "/dev/sdb1");
+
+        assert!(MountTable::parse("garbage\n").find(Path::new("/")).is_none());
+    }
+}
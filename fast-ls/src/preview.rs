@@ -0,0 +1,145 @@
+use crate::entry::{EntryKind, EntryMetadata};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Files larger than this are never previewed
+pub const MAX_PREVIEW_FILE_SIZE: u64 = 256 * 1024;
+
+/// At most this many files are read at once
+const MAX_READERS: usize = 8;
+
+/// `--preview N`: the first `N` characters of each small text file, read in
+/// parallel by a bounded set of threads. Each read stops after the bytes
+/// `N` characters can take, so a preview never reads more than a few KB.
+pub struct Previewer {
+    chars: usize,
+}
+
+impl Previewer {
+    pub fn new(chars: usize) -> Self {
+        Self { chars }
+    }
+
+    /// A preview for each of `files` in `dir`, empty for directories,
+    /// special and large files, binary content and unreadable files
+    pub fn previews(&self, dir: &Path, files: &[(String, EntryMetadata)]) -> Vec<String> {
+        let mut previews = vec![String::new(); files.len()];
+        let readers = num_cpus::get().clamp(1, MAX_READERS).min(files.len());
+        if readers == 0 {
+            return previews;
+        }
+
+        let next = AtomicUsize::new(0);
+        let done: Vec<Vec<(usize, String)>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..readers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((name, metadata)) = files.get(index) else { break };
+                            if let Some(preview) = self.preview(&dir.join(name), metadata) {
+                                done.push((index, preview));
+                            }
+                        }
+                        done
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap_or_default()).collect()
+        });
+        for (index, preview) in done.into_iter().flatten() {
+            previews[index] = preview;
+        }
+        previews
+    }
+
+    fn preview(&self, path: &Path, metadata: &EntryMetadata) -> Option<String> {
+        if metadata.kind != EntryKind::File || metadata.len == 0 || metadata.len > MAX_PREVIEW_FILE_SIZE {
+            return None;
+        }
+        // A UTF-8 character takes at most 4 bytes; one more byte tells
+        // whether anything was left out
+        let limit = self.chars.saturating_mul(4).saturating_add(1);
+        let mut bytes = Vec::new();
+        File::open(path).ok()?.take(limit as u64).read_to_end(&mut bytes).ok()?;
+        text_preview(&bytes, self.chars, metadata.len > bytes.len() as u64)
+    }
+}
+
+/// The first `chars` characters of `bytes` on one line, or `None` if they do
+/// not look like text: NUL bytes or invalid UTF-8, other than a character
+/// cut off by the end of `bytes` when `more` follows.
+///
+/// Runs of whitespace show as one space and other control characters as
+/// `.`; an ellipsis marks a preview that does not cover the whole file.
+fn text_preview(bytes: &[u8], chars: usize, more: bool) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+
+    let mut preview = String::new();
+    let mut truncated = more;
+    let mut last_was_space = false;
+    for (taken, ch) in text.trim().chars().enumerate() {
+        if taken == chars {
+            truncated = true;
+            break;
+        }
+        if ch.is_whitespace() {
+            if !last_was_space {
+                preview.push(' ');
+            }
+            last_was_space = true;
+            continue;
+        }
+        last_was_space = false;
+        preview.push(if ch.is_control() { '.' } else { ch });
+    }
+    if truncated {
+        preview.truncate(preview.trim_end().len());
+        preview.push('…');
+    }
+    Some(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_preview() {
+        assert_eq!(text_preview(b"key = value\n", 20, false).unwrap(), "key = value");
+        assert_eq!(text_preview(b"[core]\n\tbare = false\n", 10, false).unwrap(), "[core] ba…");
+        assert_eq!(text_preview(b"a\x07b", 10, false).unwrap(), "a.b");
+        assert_eq!(text_preview(b"short", 10, true).unwrap(), "short…");
+        // A character cut off by the read limit is dropped, not binary
+        assert_eq!(text_preview("naïve".as_bytes()[..3].as_ref(), 10, true).unwrap(), "na…");
+        assert!(text_preview(b"\x7fELF\x02\x01\x01\0", 10, false).is_none());
+        assert!(text_preview(b"caf\xe9 au lait", 10, false).is_none());
+    }
+
+    #[test]
+    fn test_previews_skip_dirs_and_large_files() {
+        let dir = std::env::temp_dir().join(format!("fls-preview-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("small.conf"), "listen 8080;\n").unwrap();
+        std::fs::write(dir.join("large.log"), vec![b'x'; MAX_PREVIEW_FILE_SIZE as usize + 1]).unwrap();
+
+        let mut files = crate::entry::read_listing(&dir, false).unwrap();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        let previews = Previewer::new(40).previews(&dir, &files);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["large.log", "small.conf", "sub"]);
+        assert_eq!(previews, ["", "listen 8080;", ""]);
+    }
+}
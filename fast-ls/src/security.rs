@@ -0,0 +1,166 @@
+use std::path::Path;
+
+/// What `-Z` shows for an entry: its MAC label (SELinux, or SMACK) and any
+/// file capabilities
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecurityContext {
+    /// `None` where the system keeps no label for files, shown as `?` like ls
+    pub label: Option<String>,
+    /// getcap(8) style, e.g. `cap_net_bind_service=ep`
+    pub capabilities: Option<String>,
+}
+
+impl SecurityContext {
+    /// Read the context of `path` itself, not of a symlink's target
+    pub fn of(path: &Path) -> Self {
+        let label = ["security.selinux", "security.SMACK64"]
+            .iter()
+            .find_map(|name| xattr(path, name))
+            .map(|value| String::from_utf8_lossy(&value).trim_end_matches('\0').to_string())
+            .filter(|label| !label.is_empty());
+        let capabilities = xattr(path, "security.capability").and_then(|value| format_capabilities(&value));
+        Self { label, capabilities }
+    }
+
+    /// The column text: the label, then capabilities in brackets if any
+    pub fn display(&self) -> String {
+        let label = self.label.as_deref().unwrap_or("?");
+        match self.capabilities {
+            Some(ref capabilities) => format!("{} [{}]", label, capabilities),
+            None => label.to_string(),
+        }
+    }
+}
+
+/// An extended attribute's value; `None` when it is missing or the
+/// platform or filesystem has no extended attributes
+#[cfg(target_os = "linux")]
+fn xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let name = CString::new(name).ok()?;
+    let mut value = vec![0u8; 256];
+    loop {
+        // SAFETY: both strings are NUL-terminated and the buffer length is its capacity
+        let len = unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), value.as_mut_ptr().cast(), value.len()) };
+        if len >= 0 {
+            value.truncate(len as usize);
+            return Some(value);
+        }
+        let too_small = std::io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE);
+        if !too_small || value.len() >= 64 * 1024 {
+            return None;
+        }
+        value.resize(value.len() * 4, 0);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn xattr(_path: &Path, _name: &str) -> Option<Vec<u8>> {
+    None
+}
+
+/// Capability names by number, as in linux/capability.h
+const CAPABILITY_NAMES: [&str; 41] = [
+    "chown", "dac_override", "dac_read_search", "fowner", "fsetid", "kill", "setgid", "setuid",
+    "setpcap", "linux_immutable", "net_bind_service", "net_broadcast", "net_admin", "net_raw",
+    "ipc_lock", "ipc_owner", "sys_module", "sys_rawio", "sys_chroot", "sys_ptrace", "sys_pacct",
+    "sys_admin", "sys_boot", "sys_nice", "sys_resource", "sys_time", "sys_tty_config", "mknod",
+    "lease", "audit_write", "audit_control", "setfcap", "mac_override", "mac_admin", "syslog",
+    "wake_alarm", "block_suspend", "audit_read", "perfmon", "bpf", "checkpoint_restore",
+];
+
+const VFS_CAP_REVISION_MASK: u32 = 0xFF00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+/// Decode a `security.capability` value (struct vfs_cap_data, any
+/// revision) into getcap's notation: capabilities sharing the same flags
+/// are grouped, e.g. `cap_net_admin,cap_net_raw=ep cap_sys_time=i`
+fn format_capabilities(value: &[u8]) -> Option<String> {
+    let word = |index: usize| {
+        value
+            .get(index * 4..index * 4 + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let magic = word(0)?;
+    let effective = magic & VFS_CAP_FLAGS_EFFECTIVE != 0;
+    // Revision 1 has one 32-bit (permitted, inheritable) pair, later ones two
+    let (permitted, inheritable) = if magic & VFS_CAP_REVISION_MASK == VFS_CAP_REVISION_1 {
+        (u64::from(word(1)?), u64::from(word(2)?))
+    } else {
+        (
+            u64::from(word(1)?) | u64::from(word(3)?) << 32,
+            u64::from(word(2)?) | u64::from(word(4)?) << 32,
+        )
+    };
+
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for bit in 0..64 {
+        let (p, i) = (permitted >> bit & 1 == 1, inheritable >> bit & 1 == 1);
+        if !p && !i {
+            continue;
+        }
+        let mut flags = String::new();
+        if effective {
+            flags.push('e');
+        }
+        if i {
+            flags.push('i');
+        }
+        if p {
+            flags.push('p');
+        }
+        let name = CAPABILITY_NAMES
+            .get(bit)
+            .map_or_else(|| bit.to_string(), |name| format!("cap_{}", name));
+        match groups.iter_mut().find(|(group, _)| *group == flags) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((flags, vec![name])),
+        }
+    }
+
+    let formatted: Vec<String> = groups
+        .into_iter()
+        .map(|(flags, names)| format!("{}={}", names.join(","), flags))
+        .collect();
+    (!formatted.is_empty()).then(|| formatted.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vfs_cap_data(magic: u32, words: &[u32]) -> Vec<u8> {
+        std::iter::once(magic).chain(words.iter().copied()).flat_map(u32::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_format_capabilities() {
+        // setcap cap_net_bind_service,cap_net_raw=ep
+        let value = vfs_cap_data(0x0200_0001, &[1 << 10 | 1 << 13, 0, 0, 0]);
+        assert_eq!(format_capabilities(&value).unwrap(), "cap_net_bind_service,cap_net_raw=ep");
+
+        // Revision 3 with a root uid; cap_bpf lives in the upper word
+        let value = vfs_cap_data(0x0300_0000, &[0, 1 << 25, 1 << 7, 0, 1000]);
+        assert_eq!(format_capabilities(&value).unwrap(), "cap_sys_time=i cap_bpf=p");
+
+        assert_eq!(format_capabilities(&vfs_cap_data(0x0100_0000, &[1, 0])).unwrap(), "cap_chown=p");
+        assert_eq!(format_capabilities(&vfs_cap_data(0x0200_0000, &[0, 0, 0, 0])), None);
+        assert_eq!(format_capabilities(&[1, 2]), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let context = SecurityContext {
+            label: Some("system_u:object_r:bin_t:s0".to_string()),
+            capabilities: Some("cap_net_raw=ep".to_string()),
+        };
+        assert_eq!(context.display(), "system_u:object_r:bin_t:s0 [cap_net_raw=ep]");
+        assert_eq!(SecurityContext::default().display(), "?");
+        // Reading never fails, whatever the filesystem supports
+        let _ = SecurityContext::of(Path::new("/"));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::entry::{EntryKind, EntryMetadata};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Format version written to snapshot files
+const VERSION: u32 = 1;
+
+/// What a snapshot keeps of an entry: enough to tell that it was replaced,
+/// resized or had its permissions or owner changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl From<&EntryMetadata> for SnapshotEntry {
+    fn from(metadata: &EntryMetadata) -> Self {
+        Self {
+            kind: metadata.kind,
+            // A directory's size is its own bookkeeping, not a change worth reporting
+            size: if metadata.is_dir() { 0 } else { metadata.len },
+            mode: metadata.mode,
+            uid: metadata.uid,
+            gid: metadata.gid,
+        }
+    }
+}
+
+/// One way an entry differs from its snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    Kind(EntryKind, EntryKind),
+    Size(u64, u64),
+    /// Permission bits, with setuid, setgid and sticky
+    Mode(u32, u32),
+    /// uid and gid
+    Owner((u32, u32), (u32, u32)),
+}
+
+impl SnapshotEntry {
+    /// How `self`, as it is now, differs from `before`
+    pub fn differences(&self, before: &SnapshotEntry) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        if self.kind != before.kind {
+            differences.push(Difference::Kind(before.kind, self.kind));
+        }
+        if self.size != before.size {
+            differences.push(Difference::Size(before.size, self.size));
+        }
+        let (mode, was) = (self.mode & 0o7777, before.mode & 0o7777);
+        if mode != was {
+            differences.push(Difference::Mode(was, mode));
+        }
+        if (self.uid, self.gid) != (before.uid, before.gid) {
+            differences.push(Difference::Owner((before.uid, before.gid), (self.uid, self.gid)));
+        }
+        differences
+    }
+}
+
+/// A change to one entry since the snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(String, SnapshotEntry),
+    Removed(String, SnapshotEntry),
+    Changed(String, Vec<Difference>),
+}
+
+/// A directory's entries as recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectorySnapshot {
+    /// Canonical path, so a later run may name the directory differently
+    pub path: PathBuf,
+    pub entries: BTreeMap<String, SnapshotEntry>,
+}
+
+/// `--snapshot`: the listed directories' state written to a JSON file,
+/// which `--diff` later compares the directories against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub created: DateTime<Utc>,
+    pub directories: Vec<DirectorySnapshot>,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            created: Utc::now(),
+            directories: Vec::new(),
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn load(file: &Path) -> Result<Self> {
+        let data = fs::read(file).with_context(|| format!("cannot read snapshot '{}'", file.display()))?;
+        let snapshot: Snapshot =
+            serde_json::from_slice(&data).with_context(|| format!("'{}' is not an fls snapshot", file.display()))?;
+        if snapshot.version != VERSION {
+            bail!("snapshot '{}' has unsupported version {}", file.display(), snapshot.version);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<()> {
+        fs::write(file, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("cannot write snapshot '{}'", file.display()))
+    }
+
+    /// Record `entries` as the contents of `path`, replacing an earlier
+    /// record of the same directory
+    pub fn record(&mut self, path: &Path, entries: &[(String, EntryMetadata)]) {
+        let path = canonical(path);
+        let entries = entries
+            .iter()
+            .map(|(name, metadata)| (name.clone(), SnapshotEntry::from(metadata)))
+            .collect();
+        self.directories.retain(|directory| directory.path != path);
+        self.directories.push(DirectorySnapshot { path, entries });
+    }
+
+    /// The recorded contents of `path`, however it is named
+    pub fn directory(&self, path: &Path) -> Option<&DirectorySnapshot> {
+        let path = canonical(path);
+        self.directories.iter().find(|directory| directory.path == path)
+    }
+}
+
+impl DirectorySnapshot {
+    /// What changed between the snapshot and `entries`, by name
+    pub fn changes(&self, entries: &[(String, EntryMetadata)]) -> Vec<Change> {
+        let now: BTreeMap<&str, SnapshotEntry> = entries
+            .iter()
+            .map(|(name, metadata)| (name.as_str(), SnapshotEntry::from(metadata)))
+            .collect();
+        let mut changes = Vec::new();
+        for (name, before) in &self.entries {
+            match now.get(name.as_str()) {
+                None => changes.push(Change::Removed(name.clone(), before.clone())),
+                Some(after) => {
+                    let differences = after.differences(before);
+                    if !differences.is_empty() {
+                        changes.push(Change::Changed(name.clone(), differences));
+                    }
+                }
+            }
+        }
+        for (name, after) in now {
+            if !self.entries.contains_key(name) {
+                changes.push(Change::Added(name.to_string(), after));
+            }
+        }
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        changes
+    }
+}
+
+impl Change {
+    pub fn name(&self) -> &str {
+        match self {
+            Change::Added(name, _) | Change::Removed(name, _) | Change::Changed(name, _) => name,
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: EntryKind, len: u64, mode: u32) -> EntryMetadata {
+        EntryMetadata {
+            len,
+            mode,
+            ..EntryMetadata::of_kind(kind)
+        }
+    }
+
+    #[test]
+    fn test_changes_since_snapshot() {
+        let before = vec![
+            ("app".to_string(), entry(EntryKind::File, 100, 0o100755)),
+            ("config".to_string(), entry(EntryKind::File, 20, 0o100644)),
+            ("old.log".to_string(), entry(EntryKind::File, 5, 0o100644)),
+            ("static".to_string(), entry(EntryKind::Dir, 4096, 0o40755)),
+        ];
+        let mut snapshot = Snapshot::default();
+        snapshot.record(Path::new("/srv/does-not-exist"), &before);
+
+        let after = vec![
+            ("app".to_string(), entry(EntryKind::File, 120, 0o100755)),
+            ("config".to_string(), entry(EntryKind::File, 20, 0o100666)),
+            ("new.log".to_string(), entry(EntryKind::File, 1, 0o100644)),
+            ("static".to_string(), entry(EntryKind::Dir, 8192, 0o40755)),
+        ];
+        let directory = snapshot.directory(Path::new("/srv/does-not-exist")).unwrap();
+        let changes = directory.changes(&after);
+        assert_eq!(changes.iter().map(Change::name).collect::<Vec<_>>(), ["app", "config", "new.log", "old.log"]);
+        assert_eq!(changes[0], Change::Changed("app".to_string(), vec![Difference::Size(100, 120)]));
+        assert_eq!(changes[1], Change::Changed("config".to_string(), vec![Difference::Mode(0o644, 0o666)]));
+        assert!(matches!(changes[2], Change::Added(_, SnapshotEntry { size: 1, .. })));
+        assert!(matches!(changes[3], Change::Removed(_, _)));
+        assert!(directory.changes(&before).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fls-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("snapshot.json");
+
+        let mut snapshot = Snapshot::default();
+        snapshot.record(&dir, &[("a".to_string(), entry(EntryKind::File, 1, 0o100600))]);
+        // Recording a directory again replaces it
+        snapshot.record(&dir.join("."), &[]);
+        snapshot.save(&file).unwrap();
+
+        let loaded = Snapshot::load(&file).unwrap();
+        assert_eq!(loaded.directories.len(), 1);
+        assert!(loaded.directory(&dir).unwrap().entries.is_empty());
+        fs::write(&file, "{}").unwrap();
+        assert!(Snapshot::load(&file).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use clap::ValueEnum;
+use crate::entry::{EntryKind, EntryMetadata};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How `--summary` orders its groups, largest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SummaryOrder {
+    /// Most entries first
+    #[default]
+    Count,
+    /// Most bytes first
+    Size,
+}
+
+/// What a summary group holds: files of one extension, or entries of a
+/// kind other than regular files
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Group {
+    /// Lowercased, without the dot; empty for files without one
+    Extension(String),
+    Kind(EntryKind),
+}
+
+impl Group {
+    fn of(name: &str, metadata: &EntryMetadata) -> Self {
+        match metadata.kind {
+            EntryKind::File => Group::Extension(
+                Path::new(name)
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_default(),
+            ),
+            kind => Group::Kind(kind),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Group::Extension(extension) if extension.is_empty() => "(no extension)".to_string(),
+            Group::Extension(extension) => format!(".{}", extension),
+            Group::Kind(EntryKind::Dir) => "directories".to_string(),
+            Group::Kind(EntryKind::Symlink) => "symlinks".to_string(),
+            Group::Kind(_) => "special files".to_string(),
+        }
+    }
+}
+
+/// Entries and apparent bytes of one group; directories count no bytes,
+/// as in `--total`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GroupTotals {
+    pub count: u64,
+    pub size: u64,
+}
+
+/// `--summary`: the listed entries broken down by extension and type
+#[derive(Debug, Default)]
+pub struct Summary {
+    groups: HashMap<Group, GroupTotals>,
+}
+
+impl Summary {
+    pub fn add(&mut self, name: &str, metadata: &EntryMetadata) {
+        let totals = self.groups.entry(Group::of(name, metadata)).or_default();
+        totals.count += 1;
+        if !metadata.is_dir() {
+            totals.size += metadata.len;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Summary) {
+        for (group, totals) in &other.groups {
+            let merged = self.groups.entry(group.clone()).or_default();
+            merged.count += totals.count;
+            merged.size += totals.size;
+        }
+    }
+
+    /// Bytes of every group together
+    pub fn size(&self) -> u64 {
+        self.groups.values().map(|totals| totals.size).sum()
+    }
+
+    /// The groups in `order`, ties by extension before kinds
+    pub fn rows(&self, order: SummaryOrder) -> Vec<(&Group, GroupTotals)> {
+        let mut rows: Vec<(&Group, GroupTotals)> = self.groups.iter().map(|(group, totals)| (group, *totals)).collect();
+        let key = |totals: &GroupTotals| match order {
+            SummaryOrder::Count => (totals.count, totals.size),
+            SummaryOrder::Size => (totals.size, totals.count),
+        };
+        rows.sort_by(|a, b| key(&b.1).cmp(&key(&a.1)).then_with(|| a.0.cmp(b.0)));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: EntryKind, len: u64) -> EntryMetadata {
+        EntryMetadata {
+            len,
+            ..EntryMetadata::of_kind(kind)
+        }
+    }
+
+    #[test]
+    fn test_summary_groups() {
+        let mut summary = Summary::default();
+        summary.add("main.rs", &entry(EntryKind::File, 100));
+        summary.add("lib.RS", &entry(EntryKind::File, 50));
+        summary.add("video.mp4", &entry(EntryKind::File, 5000));
+        summary.add("Makefile", &entry(EntryKind::File, 10));
+        summary.add("src", &entry(EntryKind::Dir, 4096));
+        summary.add("link", &entry(EntryKind::Symlink, 7));
+
+        let labels = |order| -> Vec<String> { summary.rows(order).iter().map(|(group, _)| group.label()).collect() };
+        assert_eq!(labels(SummaryOrder::Count), [".rs", ".mp4", "(no extension)", "symlinks", "directories"]);
+        assert_eq!(labels(SummaryOrder::Size)[..2], [".mp4", ".rs"]);
+        assert_eq!(summary.rows(SummaryOrder::Count)[0].1, GroupTotals { count: 2, size: 150 });
+        assert_eq!(summary.size(), 5167);
+
+        let mut merged = Summary::default();
+        merged.merge(&summary);
+        merged.merge(&summary);
+        assert_eq!(merged.rows(SummaryOrder::Count)[0].1.count, 4);
+    }
+}
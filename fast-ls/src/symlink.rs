@@ -0,0 +1,95 @@
+use crate::entry::EntryMetadata;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a listed symlink points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkTarget {
+    pub target: PathBuf,
+    /// Nothing is there: the target is missing, unreadable or part of a
+    /// loop of links (ELOOP)
+    pub broken: bool,
+}
+
+/// Find the targets of the symlinks among `files` in `dir`. With
+/// `dereference` (`-L`) a working link is listed as its target instead, so
+/// it gets `None` here; broken links are listed as themselves either way.
+///
+/// Targets are only resolved by `stat`, which stops at the kernel's limit
+/// on nested links, so loops end as broken links rather than hanging.
+pub fn resolve_links(dir: &Path, files: &mut [(String, EntryMetadata)], dereference: bool) -> Vec<Option<LinkTarget>> {
+    files
+        .iter_mut()
+        .map(|(name, metadata)| {
+            if !metadata.is_symlink() {
+                return None;
+            }
+            let path = dir.join(&*name);
+            let target = fs::read_link(&path).unwrap_or_default();
+            match fs::metadata(&path) {
+                Ok(target_metadata) if dereference => {
+                    *metadata = EntryMetadata::from(&target_metadata);
+                    None
+                }
+                Ok(_) => Some(LinkTarget { target, broken: false }),
+                Err(_) => Some(LinkTarget { target, broken: true }),
+            }
+        })
+        .collect()
+}
+
+/// Whether a command-line `path` is listed as an entry of its own rather
+/// than as a directory: anything but a directory, and in the long format a
+/// symlink unless `-H` or `-L` dereferences it, as in ls
+pub fn lists_as_entry(path: &Path, long_format: bool, dereference: bool) -> bool {
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+    if is_symlink && long_format && !dereference {
+        return true;
+    }
+    !path.is_dir()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::entry::{read_listing, EntryKind};
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_resolve_links() {
+        let dir = std::env::temp_dir().join(format!("fls-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("file"), "data").unwrap();
+        symlink("file", dir.join("to-file")).unwrap();
+        symlink("sub", dir.join("to-sub")).unwrap();
+        symlink("missing", dir.join("dangling")).unwrap();
+        symlink("loop-b", dir.join("loop-a")).unwrap();
+        symlink("loop-a", dir.join("loop-b")).unwrap();
+
+        let listing = |dereference| {
+            let mut files = read_listing(&dir, false).unwrap();
+            files.sort_by(|a, b| a.0.cmp(&b.0));
+            let links = resolve_links(&dir, &mut files, dereference);
+            (files, links)
+        };
+
+        let (files, links) = listing(false);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["dangling", "file", "loop-a", "loop-b", "sub", "to-file", "to-sub"]);
+        let broken: Vec<bool> = links.iter().map(|link| link.as_ref().is_some_and(|link| link.broken)).collect();
+        assert_eq!(broken, [true, false, true, true, false, false, false]);
+        assert_eq!(links[5], Some(LinkTarget { target: PathBuf::from("file"), broken: false }));
+
+        let (files, links) = listing(true);
+        let kinds: Vec<EntryKind> = files.iter().map(|(_, metadata)| metadata.kind).collect();
+        assert_eq!(kinds[5..], [EntryKind::File, EntryKind::Dir]);
+        assert_eq!(files[5].1.len, 4);
+        assert!(links[5].is_none() && links[0].as_ref().is_some_and(|link| link.broken));
+
+        assert!(lists_as_entry(&dir.join("to-sub"), true, false));
+        assert!(!lists_as_entry(&dir.join("to-sub"), true, true));
+        assert!(!lists_as_entry(&dir.join("to-sub"), false, false));
+        assert!(lists_as_entry(&dir.join("file"), false, false));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use crate::entry::EntryMetadata;
+use std::time::{Duration, SystemTime};
+
+/// Roughly six months: older (or future) times show the year instead of
+/// the clock, as in GNU ls
+const RECENT: Duration = Duration::from_secs(365 * 24 * 60 * 60 / 2);
+
+/// Which timestamp the long format shows and `-t` sorts by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimeField {
+    /// Last modification
+    #[default]
+    #[value(alias = "modification")]
+    Mtime,
+    /// Last status change
+    #[value(alias = "status")]
+    Ctime,
+    /// Last access
+    #[value(alias = "access", alias = "use")]
+    Atime,
+    /// Creation, where the filesystem records it
+    #[value(alias = "creation")]
+    Birth,
+}
+
+impl TimeField {
+    /// The timestamp, or `None` when the platform or filesystem lacks it
+    pub fn get(self, metadata: &EntryMetadata) -> Option<SystemTime> {
+        match self {
+            TimeField::Mtime => metadata.modified,
+            TimeField::Atime => metadata.accessed,
+            TimeField::Birth => metadata.created,
+            TimeField::Ctime => metadata.changed,
+        }
+    }
+}
+
+/// How `--time-style` writes timestamps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// `Mar  5 14:02` for recent times, `Mar  5  2023` for older ones
+    #[default]
+    Locale,
+    /// "3 min ago", "2 days ago"
+    Relative,
+    /// `03-05 14:02` for recent times, `2023-03-05` for older ones
+    Iso,
+    /// `2024-03-05 14:02`
+    LongIso,
+    /// `2024-03-05 14:02:11.123456789 +0100`
+    FullIso,
+    /// A strftime format given as `+FORMAT`
+    Format(String),
+}
+
+impl TimeStyle {
+    pub fn parse(style: &str) -> Result<Self> {
+        if let Some(format) = style.strip_prefix('+') {
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(anyhow!("invalid time format '{}'", format));
+            }
+            return Ok(TimeStyle::Format(format.to_string()));
+        }
+        match style {
+            "locale" => Ok(TimeStyle::Locale),
+            "relative" => Ok(TimeStyle::Relative),
+            "iso" => Ok(TimeStyle::Iso),
+            "long-iso" => Ok(TimeStyle::LongIso),
+            "full-iso" => Ok(TimeStyle::FullIso),
+            _ => Err(anyhow!(
+                "unknown time style '{}': use relative, iso, long-iso, full-iso, locale or +FORMAT",
+                style
+            )),
+        }
+    }
+
+    /// Render `time` as seen at `now`
+    pub fn format(&self, time: SystemTime, now: SystemTime) -> String {
+        let local: DateTime<Local> = time.into();
+        let recent = now.duration_since(time).is_ok_and(|age| age < RECENT);
+        match self {
+            TimeStyle::Relative => humanize(time, now),
+            TimeStyle::Locale if recent => local.format("%b %e %H:%M").to_string(),
+            TimeStyle::Locale => local.format("%b %e  %Y").to_string(),
+            TimeStyle::Iso if recent => local.format("%m-%d %H:%M").to_string(),
+            TimeStyle::Iso => local.format("%Y-%m-%d ").to_string(),
+            TimeStyle::LongIso => local.format("%Y-%m-%d %H:%M").to_string(),
+            TimeStyle::FullIso => local.format("%Y-%m-%d %H:%M:%S%.9f %z").to_string(),
+            TimeStyle::Format(format) => local.format(format).to_string(),
+        }
+    }
+}
+
+/// "just now", "3 min ago", "2 days ago", "in 5 hours"
+pub fn humanize(time: SystemTime, now: SystemTime) -> String {
+    let (elapsed, future) = match now.duration_since(time) {
+        Ok(elapsed) => (elapsed, false),
+        Err(error) => (error.duration(), true),
+    };
+
+    let seconds = elapsed.as_secs();
+    if seconds < 10 {
+        return "just now".to_string();
+    }
+    const UNITS: &[(u64, &str)] = &[
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "min"),
+        (1, "sec"),
+    ];
+    let (size, unit) = UNITS
+        .iter()
+        .copied()
+        .find(|&(size, _)| seconds >= size)
+        .unwrap_or((1, "sec"));
+    let count = seconds / size;
+    let amount = match unit {
+        "min" | "sec" => format!("{} {}", count, unit),
+        _ if count == 1 => format!("1 {}", unit),
+        _ => format!("{} {}s", count, unit),
+    };
+
+    if future {
+        format!("in {}", amount)
+    } else {
+        format!("{} ago", amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_humanize() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let ago = |seconds| humanize(now - Duration::from_secs(seconds), now);
+        assert_eq!(ago(3), "just now");
+        assert_eq!(ago(45), "45 sec ago");
+        assert_eq!(ago(3 * 60 + 20), "3 min ago");
+        assert_eq!(ago(60 * 60), "1 hour ago");
+        assert_eq!(ago(2 * 24 * 60 * 60 + 5), "2 days ago");
+        assert_eq!(ago(400 * 24 * 60 * 60), "1 year ago");
+        assert_eq!(humanize(now + Duration::from_secs(5 * 60 * 60), now), "in 5 hours");
+    }
+
+    #[test]
+    fn test_time_style() {
+        assert_eq!(TimeStyle::parse("long-iso").unwrap(), TimeStyle::LongIso);
+        assert_eq!(TimeStyle::parse("+%Y").unwrap(), TimeStyle::Format("%Y".to_string()));
+        assert!(TimeStyle::parse("+%Q").is_err());
+        assert!(TimeStyle::parse("posix").is_err());
+
+        let now = SystemTime::now();
+        let old = now - Duration::from_secs(400 * 24 * 60 * 60);
+        let year = DateTime::<Local>::from(old).format("%Y").to_string();
+        assert_eq!(TimeStyle::Format("%Y".to_string()).format(old, now), year);
+        assert!(TimeStyle::Locale.format(old, now).ends_with(&format!("  {}", year)));
+        assert_eq!(TimeStyle::LongIso.format(now, now).len(), "2024-03-05 14:02".len());
+        assert_eq!(TimeStyle::Relative.format(now, now), "just now");
+    }
+}
@@ -1,5 +1,8 @@
 use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::template::Template;
 
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ColorOption {
@@ -19,18 +22,78 @@ pub enum OutputFormat {
     Json,
 }
 
+/// Character encoding of the followed files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputEncoding {
+    /// Byte order mark, else UTF-16 by its NUL bytes, else UTF-8 if valid,
+    /// else Latin-1
+    Auto,
+    #[value(alias = "utf-8")]
+    Utf8,
+    /// ISO-8859-1, read as its Windows-1252 superset
+    #[value(alias = "iso-8859-1", alias = "windows-1252", alias = "cp1252")]
+    Latin1,
+    #[value(alias = "utf-16le")]
+    Utf16le,
+    #[value(alias = "utf-16be")]
+    Utf16be,
+}
+
+/// Which lines of each file to print before following
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSelection {
+    /// The last N lines
+    Last(usize),
+    /// Lines `start` through `end`, 1-based and inclusive; no `end` means EOF
+    Range { start: usize, end: Option<usize> },
+}
+
+impl FromStr for LineSelection {
+    type Err = String;
+
+    /// `N` for the last N lines, or `START:END` with either end optional
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid line number '{}'", value))
+        };
+
+        let Some((start, end)) = spec.split_once(':') else {
+            return parse(spec).map(LineSelection::Last);
+        };
+        let start = if start.trim().is_empty() { 1 } else { parse(start)? };
+        let end = if end.trim().is_empty() { None } else { Some(parse(end)?) };
+
+        if start == 0 {
+            return Err("line numbers start at 1".to_string());
+        }
+        if end.is_some_and(|end| end < start) {
+            return Err(format!("range '{}' ends before it starts", spec));
+        }
+        Ok(LineSelection::Range { start, end })
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "ftail")]
 #[command(about = "Ultra-fast real-time file monitoring and log tailing tool")]
 #[command(version = "0.1.0")]
+#[command(disable_version_flag = true)]
 pub struct Args {
     /// Files to monitor
     #[arg(value_name = "FILE", required = true)]
     pub files: Vec<PathBuf>,
 
-    /// Number of lines to show initially from the end of each file
-    #[arg(short = 'n', long = "lines", default_value = "10")]
-    pub initial_lines: usize,
+    /// Lines to show initially: the last N, or START:END (1-based,
+    /// inclusive, either end optional) to extract a range
+    #[arg(short = 'n', long = "lines", default_value = "10", value_name = "N|START:END")]
+    pub lines: LineSelection,
+
+    /// Show the first N lines of each file instead of the last
+    #[arg(long = "head", value_name = "N", conflicts_with_all = ["follow", "follow_name"])]
+    pub head: Option<usize>,
 
     /// Follow file changes in real-time (like tail -f)
     #[arg(short = 'f', long = "follow")]
@@ -40,9 +103,43 @@ pub struct Args {
     #[arg(short = 'F', long = "follow-name")]
     pub follow_name: bool,
 
-    /// Pattern to filter lines (grep-style)
-    #[arg(short = 'g', long = "grep")]
-    pub pattern: Option<String>,
+    /// On rotation, print the lines the old file still had before switching
+    /// to the new one (from a copytruncate copy named FILE.1 or FILE.0)
+    #[arg(long = "read-rotated")]
+    pub read_rotated: bool,
+
+    /// When a followed path is a symlink and it is moved to another file
+    /// (svlogd/runit `current` style), close the old file and follow the
+    /// new target from its start
+    #[arg(long = "follow-symlink-target")]
+    pub follow_symlink_target: bool,
+
+    /// Pattern to filter lines (grep-style); repeat for several, which
+    /// are highlighted in different colors
+    #[arg(
+        short = 'g',
+        long = "grep",
+        visible_short_alias = 'p',
+        visible_alias = "pattern",
+        value_name = "PATTERN"
+    )]
+    pub pattern: Vec<String>,
+
+    /// Show lines matching any of the patterns (default)
+    #[arg(long = "match-any", overrides_with = "match_all")]
+    pub match_any: bool,
+
+    /// Show only lines matching all of the patterns
+    #[arg(long = "match-all", overrides_with = "match_any")]
+    pub match_all: bool,
+
+    /// Hide lines matching PATTERN, whatever the other patterns say; repeatable
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Highlight colors for the patterns in order, e.g. "red,cyan"
+    #[arg(long = "pattern-colors", value_name = "COLORS", value_delimiter = ',', value_parser = parse_color)]
+    pub pattern_colors: Vec<colored::Color>,
 
     /// Use regular expressions for pattern matching
     #[arg(short = 'E', long = "regex")]
@@ -76,6 +173,19 @@ pub struct Args {
     #[arg(short = 't', long = "timestamp")]
     pub timestamp: bool,
 
+    /// Lay out each line by TEMPLATE, e.g. '{time} [{file}] {line}', instead
+    /// of -t, -N and the filename prefix. Placeholders: {time} or
+    /// {time:STRFTIME}, {file}, {path}, {lineno}, {line}, {level}, {N} for
+    /// a capture group of the first matching --grep pattern and {cap:NAME}
+    /// for a named one; {{ and }} are literal braces
+    #[arg(long = "template", value_name = "TEMPLATE", conflicts_with = "format")]
+    pub template: Option<Template>,
+
+    /// Encoding of the files, such as the UTF-16LE logs of Windows services;
+    /// lines are matched and printed as UTF-8, invalid bytes as U+FFFD
+    #[arg(long = "encoding", value_enum, default_value = "auto", value_name = "ENCODING")]
+    pub encoding: InputEncoding,
+
     /// Buffer size for reading files (in KB)
     #[arg(long = "buffer-size", default_value = "64")]
     pub buffer_size_kb: usize,
@@ -88,9 +198,74 @@ pub struct Args {
     #[arg(long = "max-buffer-lines", default_value = "10000")]
     pub max_buffer_lines: usize,
 
+    /// Persist per-file positions to this file and resume from them on startup
+    #[arg(long = "state-file", value_name = "PATH")]
+    pub state_file: Option<PathBuf>,
+
+    /// How often to save the state file while following (in seconds)
+    #[arg(long = "state-interval", default_value = "5")]
+    pub state_interval_secs: u64,
+
+    /// Suppress repeated lines, remembering the last WINDOW distinct lines
+    /// per file (default 1: only consecutive duplicates)
+    #[arg(
+        long = "dedup",
+        value_name = "WINDOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1"
+    )]
+    pub dedup: Option<usize>,
+
+    /// Compare lines for --dedup by this regex's first capture group (or
+    /// whole match) instead of the full line
+    #[arg(long = "dedup-key", value_name = "REGEX")]
+    pub dedup_key: Option<String>,
+
+    /// Instead of the matching lines, print how many lines each --grep
+    /// pattern matched every SECS seconds (text or JSON, per --format)
+    #[arg(
+        long = "count-interval",
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "pattern",
+        conflicts_with_all = ["invert_match", "dedup", "dedup_key"]
+    )]
+    pub count_interval: Option<u64>,
+
+    /// Exit with status 0 once N lines (default 1) have been shown, or 1 if
+    /// input ends or following is interrupted first
+    #[arg(
+        long = "quit-on-match",
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1"
+    )]
+    pub quit_on_match: Option<usize>,
+
+    /// Stop at the first line matching this regex (e.g. "BUILD FAILED"),
+    /// even one the filters would hide, exiting with --quit-code; repeatable
+    #[arg(long = "quit-on-pattern", value_name = "REGEX")]
+    pub quit_on_pattern: Vec<String>,
+
+    /// Exit status for --quit-on-pattern
+    #[arg(long = "quit-code", value_name = "CODE", default_value = "1", requires = "quit_on_pattern")]
+    pub quit_code: u8,
+
+    /// Once the files are open, give up writing files (except --state-file)
+    /// and dangerous syscalls such as exec, sockets and ptrace, using
+    /// Landlock and seccomp where the kernel has them
+    #[arg(long = "restricted")]
+    pub restricted: bool,
+
     /// Print verbose debugging information
     #[arg(short = 'V', long = "verbose")]
     pub verbose: bool,
+
+    /// Print version
+    #[arg(long = "version", action = clap::ArgAction::Version)]
+    pub version: Option<bool>,
 }
 
 impl Args {
@@ -107,11 +282,7 @@ impl Args {
     }
 
     pub fn has_pattern(&self) -> bool {
-        self.pattern.is_some()
-    }
-
-    pub fn get_pattern(&self) -> Option<&str> {
-        self.pattern.as_deref()
+        !self.pattern.is_empty() || !self.exclude.is_empty()
     }
 
     pub fn is_json_output(&self) -> bool {
@@ -122,7 +293,55 @@ impl Args {
         matches!(self.format, OutputFormat::Text)
     }
 
+    /// Dedup window, if deduplication is enabled; `--dedup-key` alone
+    /// implies consecutive deduplication
+    pub fn dedup_window(&self) -> Option<usize> {
+        self.dedup.or(self.dedup_key.as_ref().map(|_| 1))
+    }
+
+    /// Startup lines to print, with `--head N` taking the place of `--lines`
+    pub fn line_selection(&self) -> LineSelection {
+        match self.head {
+            Some(count) => LineSelection::Range {
+                start: 1,
+                end: Some(count),
+            },
+            None => self.lines,
+        }
+    }
+
+    /// Whether the startup output shows any lines at all
+    pub fn shows_initial_lines(&self) -> bool {
+        match self.line_selection() {
+            LineSelection::Last(count) => count > 0,
+            LineSelection::Range { start, end } => end.is_none_or(|end| end >= start),
+        }
+    }
+
     pub fn should_show_filenames(&self) -> bool {
         !self.quiet && self.files.len() > 1
     }
-}
\ No newline at end of file
+}
+
+fn parse_color(value: &str) -> Result<colored::Color, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("unknown color '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_selection_parsing() {
+        assert_eq!("25".parse(), Ok(LineSelection::Last(25)));
+        assert_eq!("10:20".parse(), Ok(LineSelection::Range { start: 10, end: Some(20) }));
+        assert_eq!("100:".parse(), Ok(LineSelection::Range { start: 100, end: None }));
+        assert_eq!(":5".parse(), Ok(LineSelection::Range { start: 1, end: Some(5) }));
+        assert!("0:5".parse::<LineSelection>().is_err());
+        assert!("9:3".parse::<LineSelection>().is_err());
+        assert!("x".parse::<LineSelection>().is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::output::LogEntry;
+use crate::pattern_matcher::PatternMatcher;
+
+/// Lines matching one `--grep` pattern during a window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatternCount {
+    pub pattern: String,
+    pub count: u64,
+}
+
+/// What `--count-interval` prints at the end of each window
+#[derive(Debug, Clone, Serialize)]
+pub struct Sample {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Lines that passed the filters, whichever patterns they matched
+    pub lines: u64,
+    pub counts: Vec<PatternCount>,
+}
+
+/// `--count-interval`: counts the lines each `--grep` pattern matches over
+/// consecutive windows, so ftail samples a log as a metric instead of
+/// printing it. A line matching several patterns counts for each of them.
+#[derive(Debug)]
+pub struct WindowCounter {
+    matcher: PatternMatcher,
+    patterns: Vec<String>,
+    counts: Vec<u64>,
+    lines: u64,
+    window_start: DateTime<Local>,
+}
+
+impl WindowCounter {
+    /// `patterns` label the counts, in the order `matcher` has them
+    pub fn new(matcher: PatternMatcher, patterns: &[String], now: DateTime<Local>) -> Self {
+        Self {
+            matcher,
+            patterns: patterns.to_vec(),
+            counts: vec![0; patterns.len()],
+            lines: 0,
+            window_start: now,
+        }
+    }
+
+    pub fn observe(&mut self, entry: &LogEntry) {
+        self.lines += 1;
+        for index in self.matcher.matching_patterns(&entry.content) {
+            self.counts[index] += 1;
+        }
+    }
+
+    /// The counts of the window ending `now`, starting the next one.
+    /// Patterns without matches are reported as 0, so every sample has
+    /// the same shape.
+    pub fn sample(&mut self, now: DateTime<Local>) -> Sample {
+        let counts = self
+            .patterns
+            .iter()
+            .zip(self.counts.iter_mut())
+            .map(|(pattern, count)| PatternCount {
+                pattern: pattern.clone(),
+                count: std::mem::take(count),
+            })
+            .collect();
+        let sample = Sample {
+            start: self.window_start,
+            end: now,
+            lines: std::mem::take(&mut self.lines),
+            counts,
+        };
+        self.window_start = now;
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_window_counts() {
+        let patterns = ["error".to_string(), "disk".to_string()];
+        let matcher = PatternMatcher::with_patterns(&patterns, false, true, false).unwrap();
+        let start = Local::now();
+        let mut counter = WindowCounter::new(matcher, &patterns, start);
+
+        for line in ["ERROR: disk full", "error: timeout", "disk ok"] {
+            counter.observe(&LogEntry::new("app.log", line, None, true, false));
+        }
+        let end = start + Duration::seconds(60);
+        let sample = counter.sample(end);
+        assert_eq!((sample.start, sample.end, sample.lines), (start, end, 3));
+        assert_eq!(
+            sample.counts,
+            vec![
+                PatternCount { pattern: "error".to_string(), count: 2 },
+                PatternCount { pattern: "disk".to_string(), count: 2 },
+            ]
+        );
+
+        let next = counter.sample(end + Duration::seconds(60));
+        assert_eq!(next.start, end);
+        assert_eq!(next.lines, 0);
+        assert!(next.counts.iter().all(|count| count.count == 0));
+    }
+}
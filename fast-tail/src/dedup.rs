@@ -0,0 +1,192 @@
+use crate::errors::{FastTailError, Result};
+use crate::output::LogEntry;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+
+/// A recently emitted line and how many copies of it were suppressed since
+#[derive(Debug)]
+struct RecentLine {
+    key: String,
+    content: String,
+    repeats: usize,
+}
+
+/// Suppresses repeated log lines, syslog style.
+///
+/// Each file remembers the keys of its last `window` distinct lines; a line
+/// whose key is among them is dropped and counted. The counts are reported as
+/// "last message repeated N times" entries just before the next new line, or
+/// when `flush` is called (end of the initial output, idle periods, shutdown).
+#[derive(Debug)]
+pub struct Deduplicator {
+    window: usize,
+    key_regex: Option<Regex>,
+    files: HashMap<String, VecDeque<RecentLine>>,
+}
+
+impl Deduplicator {
+    /// `key_pattern` restricts comparison to the regex's first capture group
+    /// (or whole match); lines it does not match are compared in full
+    pub fn new(window: usize, key_pattern: Option<&str>) -> Result<Self> {
+        if window == 0 {
+            return Err(FastTailError::invalid_config("--dedup window must be at least 1"));
+        }
+
+        let key_regex = key_pattern
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| FastTailError::pattern_compilation(pattern.to_string(), e))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            window,
+            key_regex,
+            files: HashMap::new(),
+        })
+    }
+
+    /// Feed one entry; returns the entries to print (pending repeat
+    /// summaries followed by the entry itself), or nothing if it is a repeat
+    pub fn process(&mut self, entry: LogEntry) -> Vec<LogEntry> {
+        let key = self.key_for(&entry.content);
+        let window = self.window;
+        let recent = self.files.entry(entry.file.clone()).or_default();
+
+        if let Some(line) = recent.iter_mut().find(|line| line.key == key) {
+            line.repeats += 1;
+            return Vec::new();
+        }
+
+        let mut output = summaries(&entry, recent, window);
+        recent.push_back(RecentLine {
+            key,
+            content: entry.content.clone(),
+            repeats: 0,
+        });
+        if recent.len() > window {
+            recent.pop_front();
+        }
+        output.push(entry);
+        output
+    }
+
+    /// Report all pending repeat counts; the remembered lines stay in the
+    /// window, so later repeats are still suppressed
+    pub fn flush(&mut self) -> Vec<LogEntry> {
+        let window = self.window;
+        let mut files: Vec<_> = self.files.iter_mut().collect();
+        files.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut output = Vec::new();
+        for (file, recent) in files {
+            let template = LogEntry::new(file.as_str(), "", None, false, false);
+            output.extend(summaries(&template, recent, window));
+        }
+        output
+    }
+
+    fn key_for(&self, content: &str) -> String {
+        let extracted = self.key_regex.as_ref().and_then(|regex| {
+            let captures = regex.captures(content)?;
+            captures.get(1).or_else(|| captures.get(0))
+        });
+        match extracted {
+            Some(m) => m.as_str().to_string(),
+            None => content.to_string(),
+        }
+    }
+}
+
+/// Take the pending repeat counts of `recent` as summary entries for the
+/// same file as `entry`
+fn summaries(entry: &LogEntry, recent: &mut VecDeque<RecentLine>, window: usize) -> Vec<LogEntry> {
+    recent
+        .iter_mut()
+        .filter(|line| line.repeats > 0)
+        .map(|line| {
+            let message = if window == 1 {
+                format!("last message repeated {} times", line.repeats)
+            } else {
+                format!("message repeated {} times: {}", line.repeats, line.content)
+            };
+            line.repeats = 0;
+            LogEntry::new(entry.file.as_str(), message, None, false, entry.timestamp.is_some())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(dedup: &mut Deduplicator, lines: &[&str]) -> Vec<String> {
+        let mut output = Vec::new();
+        for line in lines {
+            let entry = LogEntry::new("app.log", *line, None, false, false);
+            output.extend(dedup.process(entry).into_iter().map(|e| e.content));
+        }
+        output.extend(dedup.flush().into_iter().map(|e| e.content));
+        output
+    }
+
+    #[test]
+    fn test_consecutive_duplicates() {
+        let mut dedup = Deduplicator::new(1, None).unwrap();
+        let output = feed(&mut dedup, &["a", "a", "a", "b", "a", "b", "b"]);
+        assert_eq!(
+            output,
+            vec![
+                "a",
+                "last message repeated 2 times",
+                "b",
+                "a",
+                "b",
+                "last message repeated 1 times",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windowed_duplicates() {
+        let mut dedup = Deduplicator::new(2, None).unwrap();
+        let output = feed(&mut dedup, &["a", "b", "a", "b", "a", "c"]);
+        assert_eq!(
+            output,
+            vec![
+                "a",
+                "b",
+                "message repeated 2 times: a",
+                "message repeated 1 times: b",
+                "c",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regex_key() {
+        let mut dedup = Deduplicator::new(1, Some(r"^\S+ (.*)$")).unwrap();
+        let output = feed(
+            &mut dedup,
+            &["10:00:01 connection reset", "10:00:02 connection reset", "10:00:03 ok"],
+        );
+        assert_eq!(
+            output,
+            vec![
+                "10:00:01 connection reset",
+                "last message repeated 1 times",
+                "10:00:03 ok",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files_are_tracked_separately() {
+        let mut dedup = Deduplicator::new(1, None).unwrap();
+        let a = dedup.process(LogEntry::new("a.log", "same", None, false, false));
+        let b = dedup.process(LogEntry::new("b.log", "same", None, false, false));
+        assert_eq!(a.len(), 1);
+        assert_eq!(b.len(), 1);
+        assert!(Deduplicator::new(0, None).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::cli::InputEncoding;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use memchr::memchr_iter;
+use std::io::{self, BufRead};
+
+/// How a followed file's lines are read and turned into text. Positions
+/// stay byte offsets in the file, so only whole lines are ever decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCodec {
+    encoding: &'static Encoding,
+}
+
+impl Default for LineCodec {
+    fn default() -> Self {
+        Self { encoding: UTF_8 }
+    }
+}
+
+impl LineCodec {
+    /// The codec for a file starting with `sample`. A byte order mark wins
+    /// under `auto`; what an explicit `--encoding` names is used as is.
+    pub fn for_start(choice: InputEncoding, sample: &[u8]) -> Self {
+        let encoding = match choice {
+            InputEncoding::Utf8 => UTF_8,
+            InputEncoding::Latin1 => WINDOWS_1252,
+            InputEncoding::Utf16le => UTF_16LE,
+            InputEncoding::Utf16be => UTF_16BE,
+            InputEncoding::Auto => match Encoding::for_bom(sample) {
+                Some((encoding, _)) => encoding,
+                None => detect(sample),
+            },
+        };
+        Self { encoding }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Consume this encoding's byte order mark if `reader`, at the start
+    /// of a file, begins with one
+    pub fn skip_bom<R: BufRead>(&self, reader: &mut R) -> io::Result<()> {
+        let bom = match Encoding::for_bom(reader.fill_buf()?) {
+            Some((encoding, len)) if encoding == self.encoding => len,
+            _ => 0,
+        };
+        reader.consume(bom);
+        Ok(())
+    }
+
+    /// Append the next raw line to `raw`, its terminator included; returns
+    /// how many bytes were read, 0 at EOF. In UTF-16 a line ends at a 0x0A
+    /// code unit, not at every 0x0A byte.
+    pub fn read_line<R: BufRead>(&self, reader: &mut R, raw: &mut Vec<u8>) -> io::Result<usize> {
+        let start = raw.len();
+        let little_endian = match self.encoding {
+            encoding if encoding == UTF_16LE => true,
+            encoding if encoding == UTF_16BE => false,
+            _ => return reader.read_until(b'\n', raw),
+        };
+        loop {
+            if reader.read_until(b'\n', raw)? == 0 || raw.last() != Some(&b'\n') {
+                break;
+            }
+            let at = raw.len() - 1 - start;
+            if little_endian && at.is_multiple_of(2) {
+                let mut high = [0];
+                let read = reader.read(&mut high)?;
+                raw.extend_from_slice(&high[..read]);
+                if read == 0 || high[0] == 0 {
+                    break;
+                }
+            } else if !little_endian && !at.is_multiple_of(2) && raw[raw.len() - 2] == 0 {
+                break;
+            }
+        }
+        Ok(raw.len() - start)
+    }
+
+    /// A raw line as text without its line ending; malformed sequences
+    /// become U+FFFD
+    pub fn decode_line(&self, raw: &[u8]) -> String {
+        let mut line = self.encoding.decode_without_bom_handling(raw).0.into_owned();
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        line
+    }
+
+    /// Consume up to `count` lines from `reader` without decoding them;
+    /// returns how many were skipped (fewer at EOF)
+    pub fn skip_lines<R: BufRead>(&self, reader: &mut R, count: usize) -> io::Result<usize> {
+        if self.encoding.is_ascii_compatible() {
+            return skip_newlines(reader, count);
+        }
+        let mut raw = Vec::new();
+        let mut skipped = 0;
+        while skipped < count {
+            raw.clear();
+            if self.read_line(reader, &mut raw)? == 0 {
+                break;
+            }
+            skipped += 1;
+        }
+        Ok(skipped)
+    }
+}
+
+/// The encoding `auto` settles on for a file starting with `sample`, when
+/// it has no byte order mark: ASCII-heavy UTF-16 shows as NUL bytes on
+/// every other position; otherwise valid UTF-8 is taken as UTF-8 and
+/// anything else as Latin-1.
+fn detect(sample: &[u8]) -> &'static Encoding {
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        let zeros_at = |parity: usize| sample.iter().skip(parity).step_by(2).filter(|&&byte| byte == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 4 > pairs && even * 16 < pairs {
+            return UTF_16LE;
+        }
+        if even * 4 > pairs && odd * 16 < pairs {
+            return UTF_16BE;
+        }
+    }
+    // A sequence cut off by the end of the sample is not an error
+    match std::str::from_utf8(sample) {
+        Err(e) if e.error_len().is_some() => WINDOWS_1252,
+        _ => UTF_8,
+    }
+}
+
+/// Skip `count` lines of an ASCII-compatible encoding by counting newline
+/// bytes in the read buffer
+fn skip_newlines<R: BufRead>(reader: &mut R, count: usize) -> io::Result<usize> {
+    let mut skipped = 0;
+    while skipped < count {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        let wanted = count - skipped;
+        let mut consumed = buffer.len();
+        for (found, position) in memchr_iter(b'\n', buffer).enumerate() {
+            if found + 1 == wanted {
+                consumed = position + 1;
+                break;
+            }
+        }
+        let newlines = memchr_iter(b'\n', &buffer[..consumed]).count();
+        reader.consume(consumed);
+        skipped += newlines;
+    }
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_reads_utf16_lines() {
+        // U+010A is 0A 01 in UTF-16LE, a 0x0A byte that ends no line
+        let mut data = vec![0xFF, 0xFE];
+        data.extend(utf16le("Ċ error\r\nsecond\nthi"));
+        let codec = LineCodec::for_start(InputEncoding::Auto, &data);
+        assert_eq!(codec.name(), "UTF-16LE");
+
+        let mut reader = Cursor::new(&data);
+        codec.skip_bom(&mut reader).unwrap();
+        let mut lines = Vec::new();
+        let mut raw = Vec::new();
+        while codec.read_line(&mut reader, &mut raw).unwrap() > 0 {
+            lines.push(codec.decode_line(&raw));
+            raw.clear();
+        }
+        assert_eq!(lines, ["Ċ error", "second", "thi"]);
+
+        let mut reader = Cursor::new(&data[2..]);
+        assert_eq!(codec.skip_lines(&mut reader, 1).unwrap(), 1);
+        assert_eq!(reader.position(), 18);
+    }
+
+    #[test]
+    fn test_detects_encoding_without_bom() {
+        let detected = |sample: &[u8]| LineCodec::for_start(InputEncoding::Auto, sample).name();
+        assert_eq!(detected(&utf16le("plain ascii log\n")), "UTF-16LE");
+        assert_eq!(detected("héllo\n".as_bytes()), "UTF-8");
+        assert_eq!(detected(b"h\xe9llo\n"), "windows-1252");
+        assert_eq!(LineCodec::for_start(InputEncoding::Utf8, b"h\xe9llo\n").decode_line(b"h\xe9llo\n"), "h\u{FFFD}llo");
+    }
+}
@@ -34,6 +34,9 @@ pub enum FastTailError {
     #[error("Maximum buffer size exceeded: {current} lines")]
     BufferOverflow { current: usize },
 
+    #[error("State file error: {path}: {message}")]
+    StateFile { path: PathBuf, message: String },
+
     #[error("Invalid configuration: {message}")]
     InvalidConfig { message: String },
 
@@ -62,11 +65,28 @@ impl FastTailError {
         Self::BufferOverflow { current }
     }
 
+    pub fn state_file(path: PathBuf, message: impl Into<String>) -> Self {
+        Self::StateFile {
+            path,
+            message: message.into(),
+        }
+    }
+
     pub fn invalid_config(message: impl Into<String>) -> Self {
         Self::InvalidConfig {
             message: message.into(),
         }
     }
+
+    /// Whether retrying later may succeed, as when another process holds
+    /// the file locked
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::PermissionDenied { .. } => true,
+            Self::Io(e) => crate::file_monitor::is_transient(e),
+            _ => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, FastTailError>;
\ No newline at end of file
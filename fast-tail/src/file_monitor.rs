@@ -1,16 +1,29 @@
+use crate::cli::InputEncoding;
+use crate::encoding::LineCodec;
 use crate::errors::{FastTailError, Result};
 use crate::output::LogEntry;
 use crate::pattern_matcher::PatternMatcher;
+use crate::state::{Checkpoint, StateFile};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc as tokio_mpsc;
+use tokio::sync::watch;
 use tokio::time::sleep;
 
+/// How many bytes before the read position are remembered to recognise
+/// the file's content on the next check
+const FINGERPRINT_LEN: u64 = 64;
+
+/// How long a file that stays locked is left alone before the next check,
+/// at first and at most; one still failing after `BACKOFF_MAX` is reported
+const BACKOFF_INITIAL: Duration = Duration::from_millis(50);
+const BACKOFF_MAX: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Clone)]
 pub struct FileState {
     pub path: PathBuf,
@@ -18,6 +31,90 @@ pub struct FileState {
     pub size: u64,
     pub line_count: usize,
     pub inode: Option<u64>,
+    /// The bytes just before `position`, as last read
+    fingerprint: Option<Fingerprint>,
+    /// What a symlinked path resolved to, with `--follow-symlink-target`
+    link_target: Option<PathBuf>,
+    /// How lines are decoded, as found at the start of the file
+    codec: LineCodec,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Fingerprint {
+    offset: u64,
+    bytes: Vec<u8>,
+}
+
+impl Fingerprint {
+    /// The bytes of `file` just before `offset`
+    fn read(file: &mut File, offset: u64) -> std::io::Result<Self> {
+        let start = offset.saturating_sub(FINGERPRINT_LEN);
+        let mut bytes = vec![0; (offset - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut bytes)?;
+        Ok(Self { offset, bytes })
+    }
+
+    /// Whether `path` still holds these bytes at the same offset
+    fn matches(&self, path: &Path) -> bool {
+        open_shared(path).is_ok_and(|mut file| self.matches_file(&mut file))
+    }
+
+    fn matches_file(&self, file: &mut File) -> bool {
+        Fingerprint::read(file, self.offset).is_ok_and(|current| current == *self)
+    }
+}
+
+/// When a file that could not be read for a transient reason is checked next
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    delay: Duration,
+    retry_at: Instant,
+    /// When the file first failed
+    since: Instant,
+    /// Whether the failure was reported as lasting
+    reported: bool,
+}
+
+impl Backoff {
+    fn first() -> Self {
+        let now = Instant::now();
+        Self {
+            delay: BACKOFF_INITIAL,
+            retry_at: now + BACKOFF_INITIAL,
+            since: now,
+            reported: false,
+        }
+    }
+
+    fn next(self) -> Self {
+        let delay = (self.delay * 2).min(BACKOFF_MAX);
+        Self {
+            delay,
+            retry_at: Instant::now() + delay,
+            ..self
+        }
+    }
+
+    /// Whether the failure has outlasted [`BACKOFF_MAX`] and is not
+    /// reported yet: a file made unreadable for good, not in rotation
+    fn is_lasting(&self) -> bool {
+        !self.reported && self.since.elapsed() >= BACKOFF_MAX
+    }
+}
+
+/// What happened to a followed file since it was last read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rotation {
+    /// Same file, possibly grown
+    None,
+    /// The name now points at a different file (rename/create rotation)
+    Replaced,
+    /// Same file, cut back in place (copytruncate), maybe already rewritten
+    /// past the old position
+    Truncated,
+    /// The path is a symlink that now resolves to another file
+    Retargeted,
 }
 
 impl FileState {
@@ -40,6 +137,9 @@ impl FileState {
             size: metadata.len(),
             line_count: 0,
             inode,
+            fingerprint: None,
+            link_target: None,
+            codec: LineCodec::default(),
         })
     }
 
@@ -52,6 +152,21 @@ impl FileState {
             self.inode = Some(metadata.ino());
         }
     }
+
+    /// Start over on a rotated file
+    fn reset(&mut self) {
+        self.position = 0;
+        self.line_count = 0;
+        self.fingerprint = None;
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            inode: self.inode,
+            offset: self.position,
+            line_count: self.line_count,
+        }
+    }
 }
 
 pub struct FileMonitor {
@@ -61,6 +176,18 @@ pub struct FileMonitor {
     buffer_size: usize,
     max_buffer_lines: usize,
     verbose: bool,
+    watcher: Option<RecommendedWatcher>,
+    state: Option<StateFile>,
+    read_rotated: bool,
+    /// Open handles to the followed files, which keep a rotated file
+    /// readable after its name moves on, even once it is renamed,
+    /// compressed or deleted
+    handles: HashMap<PathBuf, File>,
+    follow_symlink_target: bool,
+    /// Files skipped until a lock held by another process is likely gone
+    backoff: HashMap<PathBuf, Backoff>,
+    /// `--encoding`: how each file's codec is chosen
+    encoding: InputEncoding,
 }
 
 impl FileMonitor {
@@ -78,38 +205,133 @@ impl FileMonitor {
             buffer_size,
             max_buffer_lines,
             verbose,
+            watcher: None,
+            state: None,
+            read_rotated: false,
+            handles: HashMap::new(),
+            follow_symlink_target: false,
+            backoff: HashMap::new(),
+            encoding: InputEncoding::Auto,
+        }
+    }
+
+    /// Read files in `encoding`, or with `auto` in whatever their byte
+    /// order mark or first bytes suggest. Applies to files added after
+    /// this is set.
+    pub fn set_encoding(&mut self, encoding: InputEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// Follow symlinked paths to whatever they point at: when a link such
+    /// as `/var/log/app/current` is moved to a new file, the old one is
+    /// closed and the new target is read from its start. Applies to files
+    /// added after this is set.
+    pub fn set_follow_symlink_target(&mut self, enabled: bool) {
+        self.follow_symlink_target = enabled;
+    }
+
+    /// On rotation, print the lines the old file still had before
+    /// switching to the new one
+    pub fn set_read_rotated(&mut self, enabled: bool) {
+        self.read_rotated = enabled;
+    }
+
+    /// Record positions in `state`, saved periodically while following and on shutdown
+    pub fn set_state_file(&mut self, state: StateFile) {
+        self.state = Some(state);
+    }
+
+    /// Resume `path` from its saved checkpoint, if the state file has one.
+    ///
+    /// A file that was rotated (new inode) or truncated while ftail was not
+    /// running is resumed from its start, so none of its lines are missed.
+    /// Returns whether a checkpoint was applied.
+    pub fn restore_checkpoint(&mut self, path: &Path) -> bool {
+        let Some(checkpoint) = self.state.as_ref().and_then(|s| s.get(path)).cloned() else {
+            return false;
+        };
+        let Some(file_state) = self.files.get_mut(path) else {
+            return false;
+        };
+
+        if checkpoint.inode == file_state.inode && checkpoint.offset <= file_state.size {
+            file_state.position = checkpoint.offset;
+            file_state.line_count = checkpoint.line_count;
+        } else {
+            if self.verbose {
+                eprintln!(
+                    "{} changed since the last checkpoint, resuming from the start",
+                    path.display()
+                );
+            }
+            file_state.position = 0;
+            file_state.line_count = 0;
+        }
+        true
+    }
+
+    /// Pass everything between the current position and the end of the
+    /// file to `emit` line by line, however long the backlog, stopping
+    /// early (with the position after the last line taken) when it returns
+    /// false
+    pub fn read_pending_lines<F>(&mut self, path: &Path, emit: F) -> Result<()>
+    where
+        F: FnMut(LogEntry) -> bool,
+    {
+        self.read_from_position(path, usize::MAX, emit)
+    }
+
+    /// Copy current positions into the state file and write it out
+    pub fn save_state(&mut self) -> Result<()> {
+        let Some(state) = self.state.as_mut() else {
+            return Ok(());
+        };
+        for (path, file_state) in &self.files {
+            state.update(path, file_state.checkpoint());
+        }
+        state.save()
+    }
+
+    fn save_state_if_due(&mut self) {
+        if self.state.as_ref().is_some_and(StateFile::is_save_due) {
+            if let Err(e) = self.save_state() {
+                eprintln!("Failed to save state: {}", e);
+            }
         }
     }
 
     pub fn add_file(&mut self, path: PathBuf) -> Result<()> {
-        let file_state = FileState::new(path.clone())?;
+        let mut file_state = FileState::new(path.clone())?;
+        if self.follow_symlink_target {
+            file_state.link_target = symlink_target(&path);
+        }
+        if let Ok(mut handle) = open_shared(&path) {
+            file_state.codec = sniff_codec(self.encoding, &mut BufReader::new(&mut handle))?;
+            // Recognises a replaced file where there are no inodes to compare
+            file_state.fingerprint = Fingerprint::read(&mut handle, file_state.position).ok();
+            self.handles.insert(path.clone(), handle);
+        }
         self.files.insert(path, file_state);
         Ok(())
     }
 
     pub fn read_initial_lines(&mut self, path: &Path, num_lines: usize) -> Result<Vec<LogEntry>> {
-        let file = File::open(path)
-            .map_err(|_| FastTailError::file_not_found(path.to_path_buf()))?;
+        let file = open_shared(path).map_err(|e| open_error(path, e))?;
         
         let mut reader = BufReader::with_capacity(self.buffer_size, file);
+        let codec = self.start_reading(path, &mut reader)?;
         let mut lines = Vec::new();
         let mut temp_lines = Vec::new();
         let mut line_number = 1;
+        let mut raw = Vec::new();
 
         // Read all lines first
         loop {
-            let mut line = String::new();
-            match reader.read_line(&mut line) {
+            raw.clear();
+            match codec.read_line(&mut reader, &mut raw) {
                 Ok(0) => break, // EOF
                 Ok(_) => {
-                    // Remove trailing newline
-                    if line.ends_with('\n') {
-                        line.pop();
-                        if line.ends_with('\r') {
-                            line.pop();
-                        }
-                    }
-                    temp_lines.push((line_number, line));
+                    temp_lines.push((line_number, codec.decode_line(&raw)));
                     line_number += 1;
                 }
                 Err(e) => return Err(FastTailError::Io(e)),
@@ -150,25 +372,79 @@ impl FileMonitor {
         Ok(lines)
     }
 
+    /// Read lines `start..=end` (1-based; `None` reads to EOF) for `--head`
+    /// and `--lines START:END`.
+    ///
+    /// Lines before the range are skipped by counting newlines in the read
+    /// buffer, and reading stops at `end`, so only the range itself is ever
+    /// decoded and the rest of a large file is never read.
+    pub fn read_line_range(&mut self, path: &Path, start: usize, end: Option<usize>) -> Result<Vec<LogEntry>> {
+        let file = open_shared(path).map_err(|e| open_error(path, e))?;
+        let mut reader = BufReader::with_capacity(self.buffer_size, file);
+        let codec = self.start_reading(path, &mut reader)?;
+
+        let mut line_number = codec.skip_lines(&mut reader, start.saturating_sub(1))? + 1;
+        let mut lines = Vec::new();
+        let mut raw = Vec::new();
+        while end.is_none_or(|end| line_number <= end) {
+            raw.clear();
+            if codec.read_line(&mut reader, &mut raw)? == 0 {
+                break;
+            }
+            let line = codec.decode_line(&raw);
+
+            let matches = self.pattern_matcher
+                .as_ref()
+                .map(|m| m.matches(&line))
+                .unwrap_or(true);
+            if matches {
+                lines.push(LogEntry::new(
+                    path.display().to_string(),
+                    line,
+                    Some(line_number),
+                    self.pattern_matcher.is_some(),
+                    false,
+                ));
+            }
+            line_number += 1;
+        }
+
+        Ok(lines)
+    }
+
+    /// Pick the codec for `path`, which `reader` reads from its start
+    fn start_reading<R: BufRead>(&mut self, path: &Path, reader: &mut R) -> Result<LineCodec> {
+        let codec = sniff_codec(self.encoding, reader)?;
+        if let Some(file_state) = self.files.get_mut(path) {
+            file_state.codec = codec;
+        }
+        Ok(codec)
+    }
+
     pub async fn start_monitoring(
         &mut self,
         tx: tokio_mpsc::UnboundedSender<LogEntry>,
         poll_interval: Duration,
+        shutdown: watch::Receiver<bool>,
     ) -> Result<()> {
         let paths: Vec<PathBuf> = self.files.keys().cloned().collect();
         
         // Try to use inotify first, fall back to polling
-        if let Ok(watcher_tx) = self.setup_inotify_watcher(&paths).await {
-            self.run_inotify_monitor(tx, watcher_tx, poll_interval).await
+        let result = if let Ok(watcher_tx) = self.setup_inotify_watcher(&paths).await {
+            self.run_inotify_monitor(tx, watcher_tx, poll_interval, shutdown).await
         } else {
             if self.verbose {
                 eprintln!("inotify failed, falling back to polling");
             }
-            self.run_polling_monitor(tx, poll_interval).await
-        }
+            self.run_polling_monitor(tx, poll_interval, shutdown).await
+        };
+
+        // Checkpoint whatever was read, even when monitoring stopped on an error
+        let saved = self.save_state();
+        result.and(saved)
     }
 
-    async fn setup_inotify_watcher(&self, paths: &[PathBuf]) -> Result<mpsc::Receiver<notify::Result<Event>>> {
+    async fn setup_inotify_watcher(&mut self, paths: &[PathBuf]) -> Result<mpsc::Receiver<notify::Result<Event>>> {
         let (watcher_tx, watcher_rx) = mpsc::channel();
         let mut watcher = RecommendedWatcher::new(watcher_tx, notify::Config::default())
             .map_err(FastTailError::WatchError)?;
@@ -178,14 +454,8 @@ impl FileMonitor {
                 .map_err(FastTailError::WatchError)?;
         }
 
-        // Keep watcher alive by moving it to a blocking task
-        tokio::task::spawn_blocking(move || {
-            let _watcher = watcher;
-            // Keep the watcher alive
-            loop {
-                std::thread::sleep(Duration::from_secs(1));
-            }
-        });
+        // Keep watcher alive for as long as the monitor is
+        self.watcher = Some(watcher);
 
         Ok(watcher_rx)
     }
@@ -195,10 +465,11 @@ impl FileMonitor {
         tx: tokio_mpsc::UnboundedSender<LogEntry>,
         watcher_rx: mpsc::Receiver<notify::Result<Event>>,
         poll_interval: Duration,
+        shutdown: watch::Receiver<bool>,
     ) -> Result<()> {
         let mut last_poll = tokio::time::Instant::now();
 
-        loop {
+        while !*shutdown.borrow() {
             // Check for inotify events (non-blocking)
             match watcher_rx.try_recv() {
                 Ok(Ok(event)) => {
@@ -227,19 +498,24 @@ impl FileMonitor {
                 last_poll = tokio::time::Instant::now();
             }
 
+            self.save_state_if_due();
             sleep(Duration::from_millis(10)).await;
         }
+        Ok(())
     }
 
     async fn run_polling_monitor(
         &mut self,
         tx: tokio_mpsc::UnboundedSender<LogEntry>,
         poll_interval: Duration,
+        shutdown: watch::Receiver<bool>,
     ) -> Result<()> {
-        loop {
+        while !*shutdown.borrow() {
             self.poll_files(&tx).await?;
+            self.save_state_if_due();
             sleep(poll_interval).await;
         }
+        Ok(())
     }
 
     async fn handle_inotify_event(
@@ -258,14 +534,11 @@ impl FileMonitor {
             EventKind::Remove(_) => {
                 for path in event.paths {
                     if self.follow_name && self.files.contains_key(&path) {
+                        // Keep the position: the recreated file is a new
+                        // inode, which check_file_changes treats as a rotation
                         if self.verbose {
                             eprintln!("File {} was removed, watching for recreation", path.display());
                         }
-                        // Reset file state but keep monitoring
-                        if let Some(file_state) = self.files.get_mut(&path) {
-                            file_state.position = 0;
-                            file_state.size = 0;
-                        }
                     }
                 }
             }
@@ -286,13 +559,49 @@ impl FileMonitor {
         Ok(())
     }
 
+    /// Read what `path` gained since the last check, handling rotation.
+    ///
+    /// A file another process holds locked — a sharing violation on
+    /// Windows, or a permission error while a rotation swaps files — is not
+    /// an error: it is left alone for a while, longer each time it is still
+    /// locked, and read from where it was once it opens again. Nothing
+    /// waits in between, so other files go on being followed. A failure
+    /// lasting past [`BACKOFF_MAX`] is reported once, and still retried.
     async fn check_file_changes(
         &mut self,
         path: &PathBuf,
         tx: &tokio_mpsc::UnboundedSender<LogEntry>,
+    ) -> Result<()> {
+        if self.backoff.get(path).is_some_and(|backoff| Instant::now() < backoff.retry_at) {
+            return Ok(());
+        }
+        match self.read_file_changes(path, tx).await {
+            Err(e) if e.is_transient() => {
+                let mut backoff = self.backoff.get(path).map_or_else(Backoff::first, |backoff| backoff.next());
+                if backoff.is_lasting() {
+                    eprintln!("ftail: {} for over {:?}, retrying every {:?}", e, BACKOFF_MAX, BACKOFF_MAX);
+                    backoff.reported = true;
+                } else if self.verbose {
+                    eprintln!("{}, retrying in {:?}", e, backoff.delay);
+                }
+                self.backoff.insert(path.clone(), backoff);
+                Ok(())
+            }
+            result => {
+                self.backoff.remove(path);
+                result
+            }
+        }
+    }
+
+    async fn read_file_changes(
+        &mut self,
+        path: &PathBuf,
+        tx: &tokio_mpsc::UnboundedSender<LogEntry>,
     ) -> Result<()> {
         let metadata = match std::fs::metadata(path) {
             Ok(m) => m,
+            Err(e) if is_transient(&e) => return Err(FastTailError::permission_denied(path.clone())),
             Err(_) => {
                 // File doesn't exist, check if we should wait for it
                 if self.follow_name {
@@ -303,75 +612,218 @@ impl FileMonitor {
             }
         };
 
-        let file_state = self.files.get_mut(path).unwrap();
         let current_size = metadata.len();
 
-        // Check for file rotation (inode change or size decrease)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::MetadataExt;
-            let current_inode = metadata.ino();
-            if let Some(old_inode) = file_state.inode {
-                if current_inode != old_inode {
-                    if self.follow_name {
+        match self.detect_rotation(path, &metadata) {
+            Rotation::None => {}
+            Rotation::Replaced => {
+                if !self.follow_name {
+                    return Err(FastTailError::file_rotation_detected(path.clone()));
+                }
+                if self.verbose {
+                    eprintln!("File rotation detected for {}, resetting position", path.display());
+                }
+                self.reopen(path, tx)?;
+            }
+            Rotation::Retargeted => {
+                let target = symlink_target(path);
+                if self.verbose {
+                    if let Some(target) = &target {
+                        eprintln!("{} now points to {}, following it from the start", path.display(), target.display());
+                    }
+                }
+                self.reopen(path, tx)?;
+                self.files.get_mut(path).unwrap().link_target = target;
+                // inotify watches what the link resolved to when it was added
+                if let Some(watcher) = self.watcher.as_mut() {
+                    let _ = watcher.unwatch(path);
+                    if let Err(e) = watcher.watch(path, RecursiveMode::NonRecursive) {
                         if self.verbose {
-                            eprintln!("File rotation detected for {}, resetting position", path.display());
+                            eprintln!("Cannot watch the new target of {}: {}", path.display(), e);
                         }
-                        file_state.position = 0;
-                        file_state.line_count = 0;
-                    } else {
-                        return Err(FastTailError::file_rotation_detected(path.clone()));
                     }
                 }
             }
-        }
-
-        // Check for truncation
-        if current_size < file_state.size {
-            if self.verbose {
-                eprintln!("File {} was truncated, resetting position", path.display());
+            Rotation::Truncated => {
+                if self.verbose {
+                    eprintln!("File {} was truncated, resetting position", path.display());
+                }
+                if self.read_rotated {
+                    self.read_truncated_copy(path, tx)?;
+                }
+                self.files.get_mut(path).unwrap().reset();
             }
-            file_state.position = 0;
-            file_state.line_count = 0;
         }
 
+        let file_state = self.files.get_mut(path).unwrap();
         file_state.update_from_metadata(&metadata);
 
         // Read new content if file has grown
         if current_size > file_state.position {
             self.read_new_lines(path, tx).await?;
+        } else if file_state.fingerprint.as_ref().is_none_or(|fp| fp.offset != file_state.position) {
+            // Positioned by the initial read or a checkpoint rather than here
+            let position = file_state.position;
+            file_state.fingerprint = open_shared(path)
+                .and_then(|mut file| Fingerprint::read(&mut file, position))
+                .ok();
         }
 
         Ok(())
     }
 
+    /// Switch `path` over to the file now found under its name, first
+    /// draining the old one with `--read-rotated`
+    fn reopen(&mut self, path: &Path, tx: &tokio_mpsc::UnboundedSender<LogEntry>) -> Result<()> {
+        let old = self.handles.remove(path);
+        if let (true, Some(old)) = (self.read_rotated, old) {
+            let max_lines = self.max_buffer_lines;
+            self.read_lines_from(old, path, max_lines, |entry| tx.send(entry).is_ok())?;
+        }
+        if let Ok(handle) = open_shared(path) {
+            self.handles.insert(path.to_path_buf(), handle);
+        }
+        self.files.get_mut(path).unwrap().reset();
+        Ok(())
+    }
+
+    /// Classify what happened to `path` since it was last read.
+    ///
+    /// Truncation is caught even when the file has already been written
+    /// past the old position again — the copytruncate race — because the
+    /// bytes before that position no longer match what was read there.
+    ///
+    /// Where there are no inodes (Windows), a file replaced under the name
+    /// is recognised by the open handle instead: it still holds the bytes
+    /// last read, and the file now found under the name does not.
+    fn detect_rotation(&self, path: &Path, metadata: &std::fs::Metadata) -> Rotation {
+        let file_state = &self.files[path];
+
+        // Checked first, as a new target is also a new inode
+        if file_state.link_target.is_some() && symlink_target(path) != file_state.link_target {
+            return Rotation::Retargeted;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if file_state.inode.is_some_and(|inode| inode != metadata.ino()) {
+                return Rotation::Replaced;
+            }
+        }
+        if file_state.inode.is_none() && self.replaced_under_name(path) {
+            return Rotation::Replaced;
+        }
+
+        if metadata.len() < file_state.size || metadata.len() < file_state.position {
+            return Rotation::Truncated;
+        }
+        match &file_state.fingerprint {
+            Some(fingerprint) if fingerprint.offset == file_state.position && !fingerprint.matches(path) => {
+                Rotation::Truncated
+            }
+            _ => Rotation::None,
+        }
+    }
+
+    fn replaced_under_name(&self, path: &Path) -> bool {
+        let file_state = &self.files[path];
+        let Some(fingerprint) = file_state.fingerprint.as_ref().filter(|fp| fp.offset == file_state.position) else {
+            return false;
+        };
+        let held = self.handles.get(path).and_then(|handle| handle.try_clone().ok());
+        held.is_some_and(|mut handle| fingerprint.matches_file(&mut handle)) && !fingerprint.matches(path)
+    }
+
+    /// Emit what a copytruncate rotation copied out before ftail read it.
+    ///
+    /// The copy is `<path>.1` (or `.0`), recognised by holding the bytes
+    /// last read at the same offset. A copy that was already compressed
+    /// cannot be read, and its unread lines are reported as skipped.
+    fn read_truncated_copy(
+        &mut self,
+        path: &Path,
+        tx: &tokio_mpsc::UnboundedSender<LogEntry>,
+    ) -> Result<()> {
+        let file_state = &self.files[path];
+        let position = file_state.position;
+        let fingerprint = file_state.fingerprint.clone();
+        let sibling = |suffix: &str| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(suffix);
+            PathBuf::from(name)
+        };
+
+        let copy = [".1", ".0"].into_iter().map(sibling).find(|candidate| {
+            std::fs::metadata(candidate).is_ok_and(|metadata| metadata.len() >= position)
+                && fingerprint
+                    .as_ref()
+                    .is_none_or(|fingerprint| fingerprint.offset == position && fingerprint.matches(candidate))
+        });
+
+        match copy {
+            Some(copy) => {
+                let file = open_shared(&copy)?;
+                let max_lines = self.max_buffer_lines;
+                self.read_lines_from(file, path, max_lines, |entry| tx.send(entry).is_ok())
+            }
+            None => {
+                let compressed = sibling(".1.gz");
+                if compressed.exists() {
+                    eprintln!(
+                        "Skipping unread lines of {}: its rotated copy {} is compressed",
+                        path.display(),
+                        compressed.display()
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+
     async fn read_new_lines(
         &mut self,
-        path: &PathBuf,
+        path: &Path,
         tx: &tokio_mpsc::UnboundedSender<LogEntry>,
     ) -> Result<()> {
-        let mut file = File::open(path)
-            .map_err(|_| FastTailError::file_not_found(path.clone()))?;
-        
+        let max_lines = self.max_buffer_lines;
+        self.read_from_position(path, max_lines, |entry| tx.send(entry).is_ok())
+    }
+
+    /// Read lines from the saved position to EOF, handing each matching entry
+    /// to `emit` until it returns false
+    fn read_from_position<F>(&mut self, path: &Path, max_lines: usize, emit: F) -> Result<()>
+    where
+        F: FnMut(LogEntry) -> bool,
+    {
+        let file = open_shared(path).map_err(|e| open_error(path, e))?;
+        self.read_lines_from(file, path, max_lines, emit)
+    }
+
+    /// `read_from_position` on an already open `file`, which holds the content
+    /// of `path` but may no longer be found under that name
+    fn read_lines_from<F>(&mut self, mut file: File, path: &Path, max_lines: usize, mut emit: F) -> Result<()>
+    where
+        F: FnMut(LogEntry) -> bool,
+    {
         let file_state = self.files.get_mut(path).unwrap();
         file.seek(SeekFrom::Start(file_state.position))?;
         
         let mut reader = BufReader::with_capacity(self.buffer_size, file);
+        if file_state.position == 0 {
+            // A rotated-in file may be written in another encoding
+            file_state.codec = sniff_codec(self.encoding, &mut reader)?;
+        }
+        let codec = file_state.codec;
         let mut line_count = 0;
+        let mut raw = Vec::new();
 
         loop {
-            let mut line = String::new();
-            match reader.read_line(&mut line) {
+            raw.clear();
+            match codec.read_line(&mut reader, &mut raw) {
                 Ok(0) => break, // EOF
                 Ok(_) => {
-                    // Remove trailing newline
-                    if line.ends_with('\n') {
-                        line.pop();
-                        if line.ends_with('\r') {
-                            line.pop();
-                        }
-                    }
-
+                    let line = codec.decode_line(&raw);
                     file_state.line_count += 1;
                     line_count += 1;
 
@@ -389,13 +841,13 @@ impl FileMonitor {
                             true, // Add timestamp for new lines
                         );
 
-                        if tx.send(entry).is_err() {
+                        if !emit(entry) {
                             break; // Receiver closed
                         }
                     }
 
                     // Prevent memory exhaustion
-                    if line_count > self.max_buffer_lines {
+                    if line_count > max_lines {
                         return Err(FastTailError::buffer_overflow(line_count));
                     }
                 }
@@ -405,10 +857,60 @@ impl FileMonitor {
 
         // Update position
         file_state.position = reader.stream_position().unwrap_or(file_state.position);
+        file_state.fingerprint = Fingerprint::read(reader.get_mut(), file_state.position).ok();
         Ok(())
     }
 }
 
+/// Open `path` for reading without getting in the way of the process
+/// writing it: on Windows, other handles may still write, rename and delete
+/// the file (which is how rotation works there). An open that fails on a
+/// lock is not retried here, which would block; followed files are tried
+/// again later by the backoff in `check_file_changes`.
+fn open_shared(path: &Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+        options.share_mode(0x1 | 0x2 | 0x4);
+    }
+    options.open(path)
+}
+
+/// Whether `error` is likely to go away by itself: a permission error, as
+/// seen while a file is swapped or its mode changed during rotation, or on
+/// Windows a sharing or lock violation by another process
+pub(crate) fn is_transient(error: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+    const WINDOWS_LOCK_ERRORS: [i32; 2] = [32, 33];
+    error.kind() == std::io::ErrorKind::PermissionDenied
+        || (cfg!(windows) && error.raw_os_error().is_some_and(|code| WINDOWS_LOCK_ERRORS.contains(&code)))
+}
+
+fn open_error(path: &Path, error: std::io::Error) -> FastTailError {
+    if is_transient(&error) {
+        FastTailError::permission_denied(path.to_path_buf())
+    } else {
+        FastTailError::file_not_found(path.to_path_buf())
+    }
+}
+
+/// The file `path` finally resolves to, if it is a symlink
+fn symlink_target(path: &Path) -> Option<PathBuf> {
+    let is_symlink = std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+    is_symlink.then(|| std::fs::canonicalize(path).ok()).flatten()
+}
+
+/// The codec `encoding` picks for the file `reader` is at the start of,
+/// with any byte order mark consumed
+fn sniff_codec<R: BufRead>(encoding: InputEncoding, reader: &mut R) -> std::io::Result<LineCodec> {
+    let codec = LineCodec::for_start(encoding, reader.fill_buf()?);
+    codec.skip_bom(reader)?;
+    Ok(codec)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -439,4 +941,269 @@ mod tests {
         assert!(lines[0].content.contains("line 2"));
         assert!(lines[1].content.contains("line 3"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_read_line_range_and_head() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        for i in 1..=50 {
+            writeln!(temp_file, "line {}", i).unwrap();
+        }
+        temp_file.flush().unwrap();
+
+        // A tiny buffer makes the skip cross many buffer refills
+        let mut monitor = FileMonitor::new(None, false, 16, 10000, false);
+        monitor.add_file(temp_file.path().to_path_buf()).unwrap();
+
+        let lines = monitor.read_line_range(temp_file.path(), 20, Some(22)).unwrap();
+        let contents: Vec<_> = lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(contents, vec!["line 20", "line 21", "line 22"]);
+        assert_eq!(lines[0].line_number, Some(20));
+
+        let head = monitor.read_line_range(temp_file.path(), 1, Some(2)).unwrap();
+        assert_eq!(head.len(), 2);
+        assert_eq!(head[1].content, "line 2");
+
+        let rest = monitor.read_line_range(temp_file.path(), 49, None).unwrap();
+        assert_eq!(rest.len(), 2);
+        assert!(monitor.read_line_range(temp_file.path(), 60, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        let state_path = dir.path().join("state.json");
+        std::fs::write(&log_path, "line 1\nline 2\n").unwrap();
+
+        // First run reads to the end and checkpoints
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_state_file(StateFile::load(state_path.clone(), Duration::from_secs(5)).unwrap());
+        monitor.add_file(log_path.clone()).unwrap();
+        assert!(!monitor.restore_checkpoint(&log_path));
+        monitor.read_initial_lines(&log_path, 10).unwrap();
+        monitor.save_state().unwrap();
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        writeln!(file, "line 3").unwrap();
+
+        // Second run emits only what was appended in between
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_state_file(StateFile::load(state_path, Duration::from_secs(5)).unwrap());
+        monitor.add_file(log_path.clone()).unwrap();
+        assert!(monitor.restore_checkpoint(&log_path));
+
+        let lines = pending_lines(&mut monitor, &log_path);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].content, "line 3");
+        assert_eq!(lines[0].line_number, Some(3));
+    }
+
+    fn pending_lines(monitor: &mut FileMonitor, path: &Path) -> Vec<LogEntry> {
+        let mut lines = Vec::new();
+        monitor
+            .read_pending_lines(path, |entry| {
+                lines.push(entry);
+                true
+            })
+            .unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_pending_lines_stop_where_emit_declines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        std::fs::write(&log_path, "").unwrap();
+
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.add_file(log_path.clone()).unwrap();
+        std::fs::write(&log_path, "one\ntwo\nthree\n").unwrap();
+
+        let mut taken = Vec::new();
+        monitor
+            .read_pending_lines(&log_path, |entry| {
+                taken.push(entry.content);
+                taken.len() < 2
+            })
+            .unwrap();
+        assert_eq!(taken, ["one", "two"]);
+
+        let rest = pending_lines(&mut monitor, &log_path);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].content, "three");
+    }
+
+    #[test]
+    fn test_truncated_file_resumes_from_start() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        let state_path = dir.path().join("state.json");
+        std::fs::write(&log_path, "a fairly long first line\n").unwrap();
+
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_state_file(StateFile::load(state_path.clone(), Duration::from_secs(5)).unwrap());
+        monitor.add_file(log_path.clone()).unwrap();
+        monitor.save_state().unwrap();
+
+        std::fs::write(&log_path, "new\n").unwrap();
+
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_state_file(StateFile::load(state_path, Duration::from_secs(5)).unwrap());
+        monitor.add_file(log_path.clone()).unwrap();
+        assert!(monitor.restore_checkpoint(&log_path));
+
+        let lines = pending_lines(&mut monitor, &log_path);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].content, "new");
+    }
+
+    async fn check(monitor: &mut FileMonitor, path: &Path) -> Vec<String> {
+        let (tx, mut rx) = tokio_mpsc::unbounded_channel();
+        monitor.check_file_changes(&path.to_path_buf(), &tx).await.unwrap();
+        drop(tx);
+        let mut lines = Vec::new();
+        while let Some(entry) = rx.recv().await {
+            lines.push(entry.content);
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn test_copytruncate_race_is_detected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+
+        for read_rotated in [false, true] {
+            std::fs::write(&log_path, "old 1\nold 2\n").unwrap();
+            let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+            monitor.set_read_rotated(read_rotated);
+            monitor.add_file(log_path.clone()).unwrap();
+            monitor.read_initial_lines(&log_path, 10).unwrap();
+            assert!(check(&mut monitor, &log_path).await.is_empty());
+
+            // One more line lands before the copy; after the truncate the
+            // file grows past the old position before the next check
+            std::fs::write(dir.path().join("app.log.1"), "old 1\nold 2\nold 3\n").unwrap();
+            std::fs::write(&log_path, "a much longer first new line\nnew 2\n").unwrap();
+
+            let expected: &[&str] = if read_rotated {
+                &["old 3", "a much longer first new line", "new 2"]
+            } else {
+                &["a much longer first new line", "new 2"]
+            };
+            assert_eq!(check(&mut monitor, &log_path).await, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_copy_is_skipped() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        std::fs::write(&log_path, "old 1\n").unwrap();
+
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_read_rotated(true);
+        monitor.add_file(log_path.clone()).unwrap();
+        std::fs::write(dir.path().join("app.log.1.gz"), [0x1f, 0x8b]).unwrap();
+        std::fs::write(&log_path, "new\n").unwrap();
+
+        assert_eq!(check(&mut monitor, &log_path).await, ["new"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_replaced_file_is_drained_first() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        let rotated = dir.path().join("app.log.1");
+        std::fs::write(&log_path, "old 1\n").unwrap();
+
+        let mut monitor = FileMonitor::new(None, true, 8192, 10000, false);
+        monitor.set_read_rotated(true);
+        monitor.add_file(log_path.clone()).unwrap();
+
+        // Written after the rename, then the rotated file is compressed away
+        std::fs::rename(&log_path, &rotated).unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&rotated).unwrap();
+        writeln!(file, "old 2").unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+        std::fs::write(&log_path, "new 1\n").unwrap();
+
+        assert_eq!(check(&mut monitor, &log_path).await, ["old 2", "new 1"]);
+        std::fs::write(&log_path, "new 1\nnew 2\n").unwrap();
+        assert_eq!(check(&mut monitor, &log_path).await, ["new 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_replaced_file_is_recognised_without_inodes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("app.log");
+        std::fs::write(&log_path, "old 1\n").unwrap();
+
+        let mut monitor = FileMonitor::new(None, true, 8192, 10000, false);
+        monitor.set_read_rotated(true);
+        monitor.add_file(log_path.clone()).unwrap();
+        // As on Windows
+        monitor.files.get_mut(&log_path).unwrap().inode = None;
+
+        let rotated = dir.path().join("app.log.1");
+        std::fs::rename(&log_path, &rotated).unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&rotated).unwrap();
+        writeln!(file, "old 2").unwrap();
+        std::fs::write(&log_path, "a longer new 1\n").unwrap();
+        assert_eq!(check(&mut monitor, &log_path).await, ["old 2", "a longer new 1"]);
+
+        // Truncated in place rather than replaced: nothing to drain
+        monitor.files.get_mut(&log_path).unwrap().inode = None;
+        std::fs::write(&log_path, "a longer new 2\n").unwrap();
+        assert_eq!(check(&mut monitor, &log_path).await, ["a longer new 2"]);
+    }
+
+    #[test]
+    fn test_transient_errors_back_off() {
+        use std::io::{Error, ErrorKind};
+        assert!(is_transient(&Error::from(ErrorKind::PermissionDenied)));
+        assert!(!is_transient(&Error::from(ErrorKind::NotFound)));
+        assert!(FastTailError::permission_denied(PathBuf::from("app.log")).is_transient());
+        assert!(!FastTailError::file_not_found(PathBuf::from("app.log")).is_transient());
+
+        let mut backoff = Backoff::first();
+        assert_eq!(backoff.delay, BACKOFF_INITIAL);
+        for _ in 0..10 {
+            backoff = backoff.next();
+        }
+        assert_eq!(backoff.delay, BACKOFF_MAX);
+
+        // Reported once it has lasted long enough, and only once
+        assert!(!backoff.is_lasting());
+        backoff.since -= BACKOFF_MAX;
+        assert!(backoff.next().is_lasting());
+        backoff.reported = true;
+        assert!(!backoff.next().is_lasting());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_retargeted_symlink_is_followed_from_start() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("app-1.log");
+        let second = dir.path().join("app-2.log");
+        let current = dir.path().join("current");
+        std::fs::write(&first, "one 1\n").unwrap();
+        std::fs::write(&second, "two 1\ntwo 2\n").unwrap();
+        std::os::unix::fs::symlink(&first, &current).unwrap();
+
+        // Not following by name: the new target is not an error either
+        let mut monitor = FileMonitor::new(None, false, 8192, 10000, false);
+        monitor.set_follow_symlink_target(true);
+        monitor.add_file(current.clone()).unwrap();
+        std::fs::write(&first, "one 1\none 2\n").unwrap();
+        assert_eq!(check(&mut monitor, &current).await, ["one 2"]);
+
+        let relinked = dir.path().join("current.tmp");
+        std::os::unix::fs::symlink(&second, &relinked).unwrap();
+        std::fs::rename(&relinked, &current).unwrap();
+        assert_eq!(check(&mut monitor, &current).await, ["two 1", "two 2"]);
+        assert!(check(&mut monitor, &current).await.is_empty());
+    }
+}
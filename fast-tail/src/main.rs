@@ -1,22 +1,51 @@
 mod cli;
+mod counter;
+mod dedup;
+mod encoding;
 mod errors;
 mod file_monitor;
 mod output;
 mod pattern_matcher;
+mod quit;
+mod sandbox;
+mod state;
+mod template;
 
-use cli::Args;
+use cli::{Args, LineSelection};
 use clap::Parser;
+use counter::WindowCounter;
+use dedup::Deduplicator;
 use errors::{FastTailError, Result};
 use file_monitor::FileMonitor;
-use output::OutputFormatter;
+use output::{LogEntry, OutputFormatter};
 use pattern_matcher::PatternMatcher;
+use quit::QuitCondition;
+use state::StateFile;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// How long the followed files must be quiet before pending --dedup repeat
+/// counts are printed
+const DEDUP_IDLE_FLUSH: Duration = Duration::from_secs(1);
+
+fn main() -> Result<()> {
     let args = Args::parse();
 
+    // The sandbox only covers threads started after it, so --restricted
+    // keeps the whole runtime on the thread that installs it
+    let mut runtime = if args.restricted {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        tokio::runtime::Builder::new_multi_thread()
+    };
+    runtime.enable_all().build()?.block_on(run(args))
+}
+
+async fn run(args: Args) -> Result<()> {
+
     if args.verbose {
         eprintln!("Fast-tail starting with {} files", args.files.len());
     }
@@ -25,15 +54,22 @@ async fn main() -> Result<()> {
     if args.files.is_empty() {
         return Err(FastTailError::invalid_config("No files specified"));
     }
+    if args.follow && matches!(args.lines, LineSelection::Range { .. }) {
+        return Err(FastTailError::invalid_config(
+            "--lines START:END extracts a fixed range and cannot be combined with --follow",
+        ));
+    }
+
+    let mut quit = QuitCondition::new(args.quit_on_match, &args.quit_on_pattern, args.quit_code)?;
 
     // Create pattern matcher if needed
-    let pattern_matcher = if let Some(pattern) = args.get_pattern() {
-        Some(PatternMatcher::new(
-            pattern,
-            args.use_regex,
-            args.ignore_case,
-            args.invert_match,
-        )?)
+    let pattern_matcher = if args.has_pattern() {
+        Some(
+            PatternMatcher::with_patterns(&args.pattern, args.use_regex, args.ignore_case, args.invert_match)?
+                .with_match_all(args.match_all)
+                .with_excludes(&args.exclude)?
+                .with_always_shown(quit.as_ref().map_or(&[], |quit| quit.markers())),
+        )
     } else {
         None
     };
@@ -45,7 +81,20 @@ async fn main() -> Result<()> {
         args.should_show_filenames(),
         args.timestamp,
         args.is_json_output(),
-    );
+    )
+    .with_highlights(pattern_matcher.clone(), args.pattern_colors.clone())
+    .with_template(args.template.clone());
+
+    let mut dedup = match args.dedup_window() {
+        Some(window) => Some(Deduplicator::new(window, args.dedup_key.as_deref())?),
+        None => None,
+    };
+
+    // --count-interval needs --grep, so there is a matcher to count with
+    let mut counter = match (args.count_interval, &pattern_matcher) {
+        (Some(_), Some(matcher)) => Some(WindowCounter::new(matcher.clone(), &args.pattern, chrono::Local::now())),
+        _ => None,
+    };
 
     // Create file monitor
     let mut monitor = FileMonitor::new(
@@ -55,6 +104,9 @@ async fn main() -> Result<()> {
         args.max_buffer_lines,
         args.verbose,
     );
+    monitor.set_read_rotated(args.read_rotated);
+    monitor.set_encoding(args.encoding);
+    monitor.set_follow_symlink_target(args.follow_symlink_target);
 
     // Add files to monitor
     for file_path in &args.files {
@@ -71,84 +123,257 @@ async fn main() -> Result<()> {
         monitor.add_file(file_path.clone())?;
     }
 
-    // Show initial content if requested
-    if args.initial_lines > 0 {
+    // Resume from the previous run's checkpoints
+    let mut resumed = HashSet::new();
+    if let Some(state_path) = &args.state_file {
+        let state = StateFile::load(
+            state_path.clone(),
+            Duration::from_secs(args.state_interval_secs),
+        )?;
+        monitor.set_state_file(state);
+
+        for file_path in &args.files {
+            if monitor.restore_checkpoint(file_path) {
+                if args.verbose {
+                    eprintln!("Resuming {} from saved checkpoint", file_path.display());
+                }
+                resumed.insert(file_path.clone());
+            }
+        }
+    }
+
+    if args.restricted {
+        let state_dir = args.state_file.as_deref().map(|path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        });
+        let report = sandbox::restrict(state_dir);
+        if !report.is_complete() || args.verbose {
+            eprintln!(
+                "ftail: --restricted: landlock {}, seccomp {}",
+                report.landlock, report.seccomp
+            );
+        }
+    }
+
+    // Set once a --quit-on-* condition is met
+    let mut exit_code = None;
+
+    // Show initial content if requested; resumed files instead show
+    // everything written since the checkpoint
+    if args.shows_initial_lines() || !resumed.is_empty() {
         if args.should_show_filenames() && args.files.len() > 1 {
             for (i, file_path) in args.files.iter().enumerate() {
+                if exit_code.is_some() {
+                    break;
+                }
                 if i > 0 {
                     println!(); // Blank line between files
                 }
                 if file_path.exists() {
                     println!("{}", formatter.format_file_header(file_path));
                     
-                    match monitor.read_initial_lines(file_path, args.initial_lines) {
-                        Ok(entries) => {
-                            for entry in entries {
-                                println!("{}", formatter.format_entry(&entry));
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("{}", formatter.format_error(&e.to_string(), Some(file_path)));
-                        }
+                    let read = read_startup_lines(&mut monitor, file_path, args.line_selection(), &resumed, |entry| {
+                        exit_code = print_entry(&formatter, &mut dedup, &mut counter, &mut quit, entry);
+                        exit_code.is_none()
+                    });
+                    flush_dedup(&formatter, &mut dedup);
+                    if let Err(e) = read {
+                        eprintln!("{}", formatter.format_error(&e.to_string(), Some(file_path)));
                     }
                 }
             }
         } else {
             // Single file or quiet mode
             for file_path in &args.files {
+                if exit_code.is_some() {
+                    break;
+                }
                 if file_path.exists() {
-                    match monitor.read_initial_lines(file_path, args.initial_lines) {
-                        Ok(entries) => {
-                            for entry in entries {
-                                println!("{}", formatter.format_entry(&entry));
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("{}", formatter.format_error(&e.to_string(), Some(file_path)));
-                        }
+                    let read = read_startup_lines(&mut monitor, file_path, args.line_selection(), &resumed, |entry| {
+                        exit_code = print_entry(&formatter, &mut dedup, &mut counter, &mut quit, entry);
+                        exit_code.is_none()
+                    });
+                    flush_dedup(&formatter, &mut dedup);
+                    if let Err(e) = read {
+                        eprintln!("{}", formatter.format_error(&e.to_string(), Some(file_path)));
                     }
                 }
             }
         }
     }
 
-    // Start following if requested
-    if args.follow {
+    // Start following if requested, unless the startup lines already met
+    // a quit condition
+    if args.follow && exit_code.is_none() {
         if args.verbose {
             eprintln!("Starting real-time monitoring...");
         }
 
         let (tx, mut rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
         let poll_interval = Duration::from_millis(args.poll_interval_ms);
 
         // Start monitoring in a separate task
         let monitor_handle = tokio::spawn(async move {
-            if let Err(e) = monitor.start_monitoring(tx, poll_interval).await {
+            if let Err(e) = monitor.start_monitoring(tx, poll_interval, shutdown_rx).await {
                 eprintln!("Monitoring error: {}", e);
             }
         });
 
-        // Handle Ctrl+C gracefully
+        // Handle Ctrl+C and SIGTERM gracefully: the monitor checkpoints and
+        // stops, and the entries it already sent are still printed below
         let formatter_clone = formatter;
+        let verbose = args.verbose;
+        let stop_tx = shutdown_tx.clone();
         tokio::spawn(async move {
-            tokio::signal::ctrl_c().await.unwrap();
-            if args.verbose {
+            wait_for_shutdown_signal().await;
+            if verbose {
                 eprintln!("\nShutting down...");
             }
-            std::process::exit(0);
+            let _ = shutdown_tx.send(true);
         });
 
+        // Count windows run from when following starts
+        let count_period = args.count_interval.map(Duration::from_secs);
+        let mut count_ticks = count_period.map(|period| tokio::time::interval_at(tokio::time::Instant::now() + period, period));
+
         // Process new entries as they arrive
-        while let Some(entry) = rx.recv().await {
-            println!("{}", formatter_clone.format_entry(&entry));
+        loop {
+            let entry = if let Some(ticks) = count_ticks.as_mut() {
+                tokio::select! {
+                    entry = rx.recv() => entry,
+                    _ = ticks.tick() => {
+                        print_sample(&formatter_clone, &mut counter);
+                        continue;
+                    }
+                }
+            } else if dedup.is_some() {
+                match tokio::time::timeout(DEDUP_IDLE_FLUSH, rx.recv()).await {
+                    Ok(entry) => entry,
+                    Err(_) => {
+                        flush_dedup(&formatter_clone, &mut dedup);
+                        continue;
+                    }
+                }
+            } else {
+                rx.recv().await
+            };
+            match entry {
+                Some(entry) => {
+                    exit_code = print_entry(&formatter_clone, &mut dedup, &mut counter, &mut quit, entry);
+                    if exit_code.is_some() {
+                        let _ = stop_tx.send(true);
+                        break;
+                    }
+                }
+                None => break,
+            }
         }
+        flush_dedup(&formatter_clone, &mut dedup);
+        print_sample(&formatter_clone, &mut counter);
 
         monitor_handle.await?;
+    } else {
+        print_sample(&formatter, &mut counter);
+        monitor.save_state()?;
+    }
+
+    match exit_code.or_else(|| quit.as_ref().map(QuitCondition::unmet_code)) {
+        Some(code) if code != 0 => {
+            let _ = std::io::stdout().flush();
+            std::process::exit(code.into());
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Print `entry`, or with `--count-interval` count it; returns the exit
+/// code when it meets a quit condition
+fn print_entry(
+    formatter: &OutputFormatter,
+    dedup: &mut Option<Deduplicator>,
+    counter: &mut Option<WindowCounter>,
+    quit: &mut Option<QuitCondition>,
+    entry: LogEntry,
+) -> Option<u8> {
+    let exit_code = quit.as_mut().and_then(|quit| quit.observe(&entry));
+    if let Some(counter) = counter {
+        counter.observe(&entry);
+        return exit_code;
     }
+    match dedup {
+        Some(dedup) => {
+            for entry in dedup.process(entry) {
+                println!("{}", formatter.format_entry(&entry));
+            }
+        }
+        None => println!("{}", formatter.format_entry(&entry)),
+    }
+    exit_code
+}
+
+fn flush_dedup(formatter: &OutputFormatter, dedup: &mut Option<Deduplicator>) {
+    if let Some(dedup) = dedup {
+        for entry in dedup.flush() {
+            println!("{}", formatter.format_entry(&entry));
+        }
+    }
+}
 
+/// Print the `--count-interval` window ending now and start the next
+fn print_sample(formatter: &OutputFormatter, counter: &mut Option<WindowCounter>) {
+    if let Some(counter) = counter {
+        println!("{}", formatter.format_sample(&counter.sample(chrono::Local::now())));
+    }
+}
+
+fn read_startup_lines(
+    monitor: &mut FileMonitor,
+    file_path: &std::path::Path,
+    selection: LineSelection,
+    resumed: &HashSet<std::path::PathBuf>,
+    mut emit: impl FnMut(LogEntry) -> bool,
+) -> Result<()> {
+    // A resumed backlog can be any length, so it is printed as it is read
+    if resumed.contains(file_path) {
+        return monitor.read_pending_lines(file_path, emit);
+    }
+    let entries = match selection {
+        LineSelection::Last(count) => monitor.read_initial_lines(file_path, count)?,
+        LineSelection::Range { start, end } => monitor.read_line_range(file_path, start, end)?,
+    };
+    for entry in entries {
+        if !emit(entry) {
+            break;
+        }
+    }
     Ok(())
 }
 
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,10 +389,17 @@ mod tests {
 
         let args = Args {
             files: vec![temp_file.path().to_path_buf()],
-            initial_lines: 2,
+            lines: LineSelection::Last(2),
+            head: None,
             follow: false,
             follow_name: false,
-            pattern: None,
+            read_rotated: false,
+            follow_symlink_target: false,
+            pattern: vec![],
+            match_any: false,
+            match_all: false,
+            exclude: vec![],
+            pattern_colors: vec![],
             use_regex: false,
             ignore_case: false,
             invert_match: false,
@@ -176,10 +408,22 @@ mod tests {
             color: cli::ColorOption::Never,
             format: cli::OutputFormat::Text,
             timestamp: false,
+            template: None,
+            encoding: cli::InputEncoding::Auto,
             buffer_size_kb: 64,
             poll_interval_ms: 100,
             max_buffer_lines: 10000,
+            state_file: None,
+            state_interval_secs: 5,
+            dedup: None,
+            dedup_key: None,
+            count_interval: None,
+            quit_on_match: None,
+            quit_on_pattern: vec![],
+            quit_code: 1,
+            restricted: false,
             verbose: false,
+            version: None,
         };
 
         // This would normally run the main logic, but we can't easily test the full async flow
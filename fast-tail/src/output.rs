@@ -3,6 +3,21 @@ use colored::*;
 use serde::Serialize;
 use std::path::Path;
 
+use crate::counter::Sample;
+use crate::pattern_matcher::PatternMatcher;
+use crate::template::{self, Placeholder, Segment, Template};
+
+/// Highlight colors for the first, second, ... `--grep` pattern, repeating
+/// when there are more patterns
+pub const DEFAULT_PATTERN_COLORS: [Color; 6] = [
+    Color::Yellow,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
 #[derive(Debug, Clone, Serialize)]
 pub struct LogEntry {
     pub timestamp: Option<DateTime<Local>>,
@@ -40,6 +55,12 @@ pub struct OutputFormatter {
     show_filenames: bool,
     show_timestamps: bool,
     json_output: bool,
+    /// Colors each pattern's matches in matched lines, and gives
+    /// `--template` its capture groups
+    highlighter: Option<PatternMatcher>,
+    pattern_colors: Vec<Color>,
+    /// Lays out text lines instead of the prefix flags
+    template: Option<Template>,
 }
 
 impl OutputFormatter {
@@ -56,9 +77,29 @@ impl OutputFormatter {
             show_filenames,
             show_timestamps,
             json_output,
+            highlighter: None,
+            pattern_colors: DEFAULT_PATTERN_COLORS.to_vec(),
+            template: None,
         }
     }
 
+    /// Highlight the matches of `matcher`'s patterns instead of whole
+    /// matched lines, the Nth pattern in the Nth of `colors`
+    pub fn with_highlights(mut self, matcher: Option<PatternMatcher>, colors: Vec<Color>) -> Self {
+        self.highlighter = matcher;
+        if !colors.is_empty() {
+            self.pattern_colors = colors;
+        }
+        self
+    }
+
+    /// Format text lines by `template`, which says where the timestamp,
+    /// file and line number go in place of `-t`, the filename rule and `-N`
+    pub fn with_template(mut self, template: Option<Template>) -> Self {
+        self.template = template;
+        self
+    }
+
     pub fn format_entry(&self, entry: &LogEntry) -> String {
         if self.json_output {
             self.format_json(entry)
@@ -77,6 +118,9 @@ impl OutputFormatter {
     }
 
     fn format_text(&self, entry: &LogEntry) -> String {
+        if let Some(ref template) = self.template {
+            return self.format_template(template, entry);
+        }
         let mut output = String::new();
 
         // Timestamp
@@ -128,8 +172,7 @@ impl OutputFormatter {
 
         // Content
         if entry.matched && self.use_colors {
-            // For matched lines, highlight the entire line
-            output.push_str(&entry.content.yellow().to_string());
+            self.push_highlighted(&mut output, &entry.content);
         } else {
             output.push_str(&entry.content);
         }
@@ -137,6 +180,119 @@ impl OutputFormatter {
         output
     }
 
+    /// Fill in `template` for `entry`; placeholders without a value, like
+    /// the line number of stdin or a group that did not take part in the
+    /// match, are left empty
+    fn format_template(&self, template: &Template, entry: &LogEntry) -> String {
+        let captures = if template.uses_captures() {
+            self.highlighter.as_ref().and_then(|matcher| matcher.captures(&entry.content))
+        } else {
+            None
+        };
+
+        let mut output = String::new();
+        for segment in template.segments() {
+            let placeholder = match segment {
+                Segment::Literal(text) => {
+                    output.push_str(text);
+                    continue;
+                }
+                Segment::Field(Placeholder::Line) => {
+                    if entry.matched && self.use_colors {
+                        self.push_highlighted(&mut output, &entry.content);
+                    } else {
+                        output.push_str(&entry.content);
+                    }
+                    continue;
+                }
+                Segment::Field(placeholder) => placeholder,
+            };
+
+            let (text, style): (String, fn(&str) -> ColoredString) = match placeholder {
+                Placeholder::Time(format) => (
+                    entry.timestamp.unwrap_or_else(Local::now).format(format).to_string(),
+                    |text| text.blue(),
+                ),
+                Placeholder::File => (
+                    Path::new(&entry.file)
+                        .file_name()
+                        .map_or_else(|| entry.file.clone(), |name| name.to_string_lossy().into_owned()),
+                    |text| text.magenta().bold(),
+                ),
+                Placeholder::Path => (entry.file.clone(), |text| text.magenta()),
+                Placeholder::LineNumber => (
+                    entry.line_number.map(|number| number.to_string()).unwrap_or_default(),
+                    |text| text.green(),
+                ),
+                Placeholder::Level => (template::detect_level(&entry.content).unwrap_or_default().to_string(), |text| {
+                    match text {
+                        "FATAL" | "ERROR" => text.red().bold(),
+                        "WARN" => text.yellow().bold(),
+                        "INFO" => text.green(),
+                        _ => text.dimmed(),
+                    }
+                }),
+                Placeholder::Group(group) => (
+                    captures.as_ref().and_then(|captures| captures.get(*group)).map_or("", |m| m.as_str()).to_string(),
+                    |text| text.normal(),
+                ),
+                Placeholder::NamedGroup(name) => (
+                    captures.as_ref().and_then(|captures| captures.name(name)).map_or("", |m| m.as_str()).to_string(),
+                    |text| text.normal(),
+                ),
+                Placeholder::Line => unreachable!("handled above"),
+            };
+            if self.use_colors && !text.is_empty() {
+                output.push_str(&style(&text).to_string());
+            } else {
+                output.push_str(&text);
+            }
+        }
+        output
+    }
+
+    /// Color each pattern's matches; lines matched by no span, such as
+    /// those `--invert-match` keeps, are highlighted whole
+    fn push_highlighted(&self, output: &mut String, content: &str) {
+        let highlights = self
+            .highlighter
+            .as_ref()
+            .map(|matcher| matcher.highlights(content))
+            .unwrap_or_default();
+        if highlights.is_empty() {
+            output.push_str(&content.yellow().to_string());
+            return;
+        }
+
+        let mut position = 0;
+        for highlight in highlights {
+            output.push_str(&content[position..highlight.start]);
+            let color = self.pattern_colors[highlight.group % self.pattern_colors.len()];
+            output.push_str(&content[highlight.start..highlight.end].color(color).bold().to_string());
+            position = highlight.end;
+        }
+        output.push_str(&content[position..]);
+    }
+
+    /// One `--count-interval` window: `pattern=count` pairs after the
+    /// window's times, or a "counts" event in JSON
+    pub fn format_sample(&self, sample: &Sample) -> String {
+        if self.json_output {
+            let mut value = serde_json::to_value(sample).unwrap_or_default();
+            if let Some(object) = value.as_object_mut() {
+                object.insert("event".to_string(), "counts".into());
+            }
+            return value.to_string();
+        }
+
+        let window = format!("{} - {}", sample.start.format("%Y-%m-%d %H:%M:%S"), sample.end.format("%H:%M:%S"));
+        let mut output = if self.use_colors { window.blue().to_string() } else { window };
+        for count in &sample.counts {
+            output.push_str(&format!(" {}={}", count.pattern, count.count));
+        }
+        output
+    }
+
     pub fn format_file_header(&self, file_path: &Path) -> String {
         if self.json_output {
             format!(
@@ -192,7 +348,6 @@ impl OutputFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     #[test]
     fn test_text_formatting() {
@@ -211,4 +366,61 @@ mod tests {
         assert!(result.contains(r#""line_number":42"#));
         assert!(result.contains(r#""content":"hello world""#));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sample_formatting() {
+        use crate::counter::PatternCount;
+        use chrono::TimeZone;
+
+        let sample = Sample {
+            start: Local.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2026, 3, 1, 12, 1, 0).unwrap(),
+            lines: 7,
+            counts: vec![PatternCount { pattern: "ERROR".to_string(), count: 5 }],
+        };
+        let text = OutputFormatter::new(false, false, false, false, false).format_sample(&sample);
+        assert_eq!(text, "2026-03-01 12:00:00 - 12:01:00 ERROR=5");
+
+        let json = OutputFormatter::new(false, false, false, false, true).format_sample(&sample);
+        assert!(json.contains(r#""event":"counts""#));
+        assert!(json.contains(r#""counts":[{"count":5,"pattern":"ERROR"}]"#));
+        assert!(json.contains(r#""lines":7"#));
+    }
+
+    #[test]
+    fn test_template_formatting() {
+        use chrono::TimeZone;
+
+        let patterns = [r"user=(?P<user>\w+) status=(\d+)".to_string()];
+        let matcher = PatternMatcher::with_patterns(&patterns, true, false, false).unwrap();
+        let template = "{time:%H:%M:%S} [{file}:{lineno}] {level} {cap:user}/{2}: {line}".parse().ok();
+        let formatter = OutputFormatter::new(false, false, false, false, false)
+            .with_highlights(Some(matcher), Vec::new())
+            .with_template(template);
+
+        let mut entry = LogEntry::new("/var/log/app.log", "WARN user=ann status=503", Some(7), true, false);
+        entry.timestamp = Some(Local.with_ymd_and_hms(2026, 3, 1, 12, 30, 5).unwrap());
+        assert_eq!(formatter.format_entry(&entry), "12:30:05 [app.log:7] WARN ann/503: WARN user=ann status=503");
+
+        // Missing values are left out
+        let mut other = LogEntry::new("-", "plain", None, false, false);
+        other.timestamp = entry.timestamp;
+        assert_eq!(formatter.format_entry(&other), "12:30:05 [-:]  /: plain");
+    }
+
+    #[test]
+    fn test_pattern_highlights() {
+        colored::control::set_override(true);
+        let patterns = ["ERROR".to_string(), "disk".to_string()];
+        let matcher = PatternMatcher::with_patterns(&patterns, false, false, false).unwrap();
+        let formatter = OutputFormatter::new(true, false, false, false, false)
+            .with_highlights(Some(matcher), vec![Color::Red, Color::Blue]);
+        let entry = LogEntry::new("test.log", "ERROR: disk full", None, true, false);
+        let result = formatter.format_entry(&entry);
+        colored::control::unset_override();
+
+        assert!(result.contains(&"ERROR".red().bold().to_string()));
+        assert!(result.contains(&"disk".blue().bold().to_string()));
+        assert!(result.ends_with(" full"));
+    }
+}
@@ -1,29 +1,23 @@
 use crate::errors::{FastTailError, Result};
 use memchr::memchr;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use std::sync::OnceLock;
 
+/// One `--grep` or `--exclude` pattern
 #[derive(Debug, Clone)]
-pub struct PatternMatcher {
+struct Pattern {
+    /// Lowercased for case-insensitive literal matching
     pattern: String,
     regex: Option<Regex>,
-    use_regex: bool,
     ignore_case: bool,
-    invert_match: bool,
+    /// Finds match spans to highlight, compiled on first use
+    finder: OnceLock<Option<Regex>>,
 }
 
-impl PatternMatcher {
-    pub fn new(
-        pattern: &str,
-        use_regex: bool,
-        ignore_case: bool,
-        invert_match: bool,
-    ) -> Result<Self> {
+impl Pattern {
+    fn new(pattern: &str, use_regex: bool, ignore_case: bool) -> Result<Self> {
         let regex = if use_regex {
-            let mut builder = regex::RegexBuilder::new(pattern);
-            builder.case_insensitive(ignore_case);
-            Some(builder.build().map_err(|e| {
-                FastTailError::pattern_compilation(pattern.to_string(), e)
-            })?)
+            Some(build_regex(pattern, ignore_case)?)
         } else {
             None
         };
@@ -35,23 +29,16 @@ impl PatternMatcher {
                 pattern.to_string()
             },
             regex,
-            use_regex,
             ignore_case,
-            invert_match,
+            finder: OnceLock::new(),
         })
     }
 
-    pub fn matches(&self, line: &str) -> bool {
-        let found = if let Some(ref regex) = self.regex {
+    fn is_match(&self, line: &str) -> bool {
+        if let Some(ref regex) = self.regex {
             regex.is_match(line)
         } else {
             self.literal_match(line)
-        };
-
-        if self.invert_match {
-            !found
-        } else {
-            found
         }
     }
 
@@ -66,10 +53,10 @@ impl PatternMatcher {
                 
                 while let Some(idx) = memchr(*first_byte, &line_bytes[pos..]) {
                     let start = pos + idx;
-                    if start + self.pattern.len() <= line_bytes.len() {
-                        if &line_bytes[start..start + self.pattern.len()] == self.pattern.as_bytes() {
-                            return true;
-                        }
+                    if start + self.pattern.len() <= line_bytes.len()
+                        && &line_bytes[start..start + self.pattern.len()] == self.pattern.as_bytes()
+                    {
+                        return true;
                     }
                     pos = start + 1;
                 }
@@ -80,8 +67,164 @@ impl PatternMatcher {
         }
     }
 
+    /// The pattern as a regex, a literal one escaped
+    fn finder(&self) -> Option<&Regex> {
+        self.regex.as_ref().or_else(|| {
+            self.finder
+                .get_or_init(|| build_regex(&regex::escape(&self.pattern), self.ignore_case).ok())
+                .as_ref()
+        })
+    }
+
+    /// Byte ranges of the matches in `line`
+    fn spans(&self, line: &str) -> Vec<(usize, usize)> {
+        self.finder()
+            .map(|regex| regex.find_iter(line).filter(|m| !m.is_empty()).map(|m| (m.start(), m.end())).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn build_regex(pattern: &str, ignore_case: bool) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| FastTailError::pattern_compilation(pattern.to_string(), e))
+}
+
+/// A part of a line matched by the include pattern at `group`, for
+/// highlighting each pattern in its own color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+    pub group: usize,
+}
+
+/// Line filter built from `--grep` patterns, any or all of which must
+/// match, and `--exclude` patterns, none of which may
+#[derive(Debug, Clone)]
+pub struct PatternMatcher {
+    patterns: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+    match_all: bool,
+    use_regex: bool,
+    ignore_case: bool,
+    invert_match: bool,
+    /// Lines matching any of these pass whatever the other patterns say
+    always_shown: Vec<Regex>,
+}
+
+impl PatternMatcher {
+    pub fn new(
+        pattern: &str,
+        use_regex: bool,
+        ignore_case: bool,
+        invert_match: bool,
+    ) -> Result<Self> {
+        Self::with_patterns(&[pattern.to_string()], use_regex, ignore_case, invert_match)
+    }
+
+    /// Match lines containing any of `patterns` (see `with_match_all`)
+    pub fn with_patterns(
+        patterns: &[String],
+        use_regex: bool,
+        ignore_case: bool,
+        invert_match: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern, use_regex, ignore_case))
+                .collect::<Result<_>>()?,
+            excludes: Vec::new(),
+            match_all: false,
+            use_regex,
+            ignore_case,
+            invert_match,
+            always_shown: Vec::new(),
+        })
+    }
+
+    /// Require every pattern to match instead of any one
+    pub fn with_match_all(mut self, match_all: bool) -> Self {
+        self.match_all = match_all;
+        self
+    }
+
+    /// Drop lines matching any of `excludes`, whatever the other patterns
+    /// say; they follow the same regex and case settings
+    pub fn with_excludes(mut self, excludes: &[String]) -> Result<Self> {
+        self.excludes = excludes
+            .iter()
+            .map(|pattern| Pattern::new(pattern, self.use_regex, self.ignore_case))
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Let lines matching any of `patterns` through, including excluded
+    /// ones, so `--quit-on-pattern` markers are seen
+    pub fn with_always_shown(mut self, patterns: &[Regex]) -> Self {
+        self.always_shown = patterns.to_vec();
+        self
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        if self.always_shown.iter().any(|pattern| pattern.is_match(line)) {
+            return true;
+        }
+        let found = if self.patterns.is_empty() {
+            true
+        } else if self.match_all {
+            self.patterns.iter().all(|pattern| pattern.is_match(line))
+        } else {
+            self.patterns.iter().any(|pattern| pattern.is_match(line))
+        };
+
+        found != self.invert_match && !self.excludes.iter().any(|pattern| pattern.is_match(line))
+    }
+
+    /// Where each include pattern matched `line`, in order and without
+    /// overlaps; an earlier match wins over one starting inside it
+    pub fn highlights(&self, line: &str) -> Vec<Highlight> {
+        let mut highlights: Vec<Highlight> = self
+            .patterns
+            .iter()
+            .enumerate()
+            .flat_map(|(group, pattern)| {
+                pattern.spans(line).into_iter().map(move |(start, end)| Highlight { start, end, group })
+            })
+            .collect();
+        highlights.sort_by_key(|highlight| (highlight.start, std::cmp::Reverse(highlight.end)));
+
+        let mut end = 0;
+        highlights.retain(|highlight| {
+            let keep = highlight.start >= end;
+            if keep {
+                end = highlight.end;
+            }
+            keep
+        });
+        highlights
+    }
+
+    /// Capture groups of the first include pattern matching `line`, for
+    /// `--template`; a literal pattern only has the whole match
+    pub fn captures<'t>(&self, line: &'t str) -> Option<regex::Captures<'t>> {
+        self.patterns.iter().find_map(|pattern| pattern.finder()?.captures(line))
+    }
+
+    /// Indexes of the include patterns that match `line`
+    pub fn matching_patterns<'a>(&'a self, line: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.patterns
+            .iter()
+            .enumerate()
+            .filter(move |(_, pattern)| pattern.is_match(line))
+            .map(|(index, _)| index)
+    }
+
+    /// The first include pattern
     pub fn pattern(&self) -> &str {
-        &self.pattern
+        self.patterns.first().map_or("", |pattern| pattern.pattern.as_str())
     }
 
     pub fn is_regex(&self) -> bool {
@@ -125,4 +268,55 @@ mod tests {
         assert!(!matcher.matches("hello world"));
         assert!(matcher.matches("goodbye world"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_any_all_and_exclude() {
+        let patterns = ["error".to_string(), "disk".to_string()];
+        let any = PatternMatcher::with_patterns(&patterns, false, true, false).unwrap();
+        assert!(any.matches("ERROR: timeout"));
+        assert!(any.matches("disk full"));
+        assert!(!any.matches("all good"));
+
+        let all = any.clone().with_match_all(true);
+        assert!(all.matches("error: disk full"));
+        assert!(!all.matches("error: timeout"));
+
+        let quiet = any.with_excludes(&["healthcheck".to_string()]).unwrap();
+        assert!(quiet.matches("disk check"));
+        assert!(!quiet.matches("Error in HEALTHCHECK"));
+
+        // Excludes alone filter without any include pattern
+        let only_exclude = PatternMatcher::with_patterns(&[], false, false, false)
+            .unwrap()
+            .with_excludes(&["DEBUG".to_string()])
+            .unwrap();
+        assert!(only_exclude.matches("INFO up"));
+        assert!(!only_exclude.matches("DEBUG noise"));
+
+        // Quit markers get through both
+        let marked = quiet.with_always_shown(&[Regex::new("BUILD FAILED").unwrap()]);
+        assert!(marked.matches("BUILD FAILED (healthcheck)"));
+        assert!(!marked.matches("BUILD OK"));
+    }
+
+    #[test]
+    fn test_highlights() {
+        let patterns = ["err".to_string(), r"\d+".to_string(), "error".to_string()];
+        let matcher = PatternMatcher::with_patterns(&patterns, true, false, false).unwrap();
+        let spans: Vec<(usize, usize, usize)> = matcher
+            .highlights("error 404 err")
+            .iter()
+            .map(|h| (h.start, h.end, h.group))
+            .collect();
+        // "error" outranks "err" at the same start by being longer
+        assert_eq!(spans, vec![(0, 5, 2), (6, 9, 1), (10, 13, 0)]);
+
+        let literal = PatternMatcher::new("a.b", false, true, false).unwrap();
+        assert_eq!(literal.highlights("A.B axb"), vec![Highlight { start: 0, end: 3, group: 0 }]);
+
+        let captures = matcher.captures("code 404").unwrap();
+        assert_eq!(&captures[0], "404");
+        assert_eq!(literal.captures("x A.B").unwrap().get(0).map(|m| m.start()), Some(2));
+        assert!(literal.captures("axb").is_none());
+    }
+}
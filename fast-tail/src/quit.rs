@@ -0,0 +1,93 @@
+use crate::errors::{FastTailError, Result};
+use crate::output::LogEntry;
+use regex::Regex;
+
+/// When ftail stops on its own: after `--quit-on-match` shown lines, or at
+/// the first line matching a `--quit-on-pattern` marker, whichever comes
+/// first.
+///
+/// Markers are checked against every line read, filters or not (the
+/// pattern matcher lets them through, see `PatternMatcher::with_always_shown`).
+#[derive(Debug)]
+pub struct QuitCondition {
+    /// Matches to see before exiting with 0
+    after_matches: Option<usize>,
+    markers: Vec<Regex>,
+    marker_code: u8,
+    matches: usize,
+}
+
+impl QuitCondition {
+    /// `None` when neither condition was asked for
+    pub fn new(after_matches: Option<usize>, markers: &[String], marker_code: u8) -> Result<Option<Self>> {
+        if after_matches == Some(0) {
+            return Err(FastTailError::invalid_config("--quit-on-match needs at least 1 match"));
+        }
+        if after_matches.is_none() && markers.is_empty() {
+            return Ok(None);
+        }
+
+        let markers = markers
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| FastTailError::pattern_compilation(pattern.to_string(), e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self {
+            after_matches,
+            markers,
+            marker_code,
+            matches: 0,
+        }))
+    }
+
+    /// The markers, for letting their lines through the filters
+    pub fn markers(&self) -> &[Regex] {
+        &self.markers
+    }
+
+    /// Count a shown line; returns the exit code once ftail should stop
+    pub fn observe(&mut self, entry: &LogEntry) -> Option<u8> {
+        if self.markers.iter().any(|marker| marker.is_match(&entry.content)) {
+            return Some(self.marker_code);
+        }
+        self.matches += 1;
+        self.after_matches.filter(|&limit| self.matches >= limit).map(|_| 0)
+    }
+
+    /// The exit code when input ends, or following is interrupted, before
+    /// either condition was met: 1 if matches were being waited for, like
+    /// grep finding nothing; markers alone are optional
+    pub fn unmet_code(&self) -> u8 {
+        if self.after_matches.is_some() { 1 } else { 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str) -> LogEntry {
+        LogEntry::new("build.log", content, None, true, false)
+    }
+
+    #[test]
+    fn test_quit_condition() {
+        assert!(QuitCondition::new(None, &[], 3).unwrap().is_none());
+        assert!(QuitCondition::new(Some(0), &[], 3).is_err());
+        assert!(QuitCondition::new(None, &["(".to_string()], 3).is_err());
+
+        let mut quit = QuitCondition::new(Some(2), &["BUILD (FAILED|ABORTED)".to_string()], 3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(quit.unmet_code(), 1);
+        assert_eq!(quit.observe(&entry("compiling")), None);
+        assert_eq!(quit.observe(&entry("BUILD FAILED: 2 errors")), Some(3));
+        assert_eq!(quit.observe(&entry("linking")), Some(0));
+
+        let mut markers_only = QuitCondition::new(None, &["DONE".to_string()], 2).unwrap().unwrap();
+        assert_eq!(markers_only.unmet_code(), 0);
+        assert_eq!(markers_only.observe(&entry("step 1")), None);
+        assert_eq!(markers_only.observe(&entry("DONE")), Some(2));
+    }
+}
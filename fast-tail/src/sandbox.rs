@@ -0,0 +1,412 @@
+use std::fmt;
+use std::path::Path;
+
+/// How one layer of `--restricted` ended up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    Enforced,
+    /// The kernel or platform lacks it, or installing it failed; ftail
+    /// carries on without it
+    Unavailable(String),
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Enforced => write!(f, "enforced"),
+            Self::Unavailable(reason) => write!(f, "unavailable ({})", reason),
+        }
+    }
+}
+
+/// What `--restricted` managed to lock down
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxReport {
+    /// No writing, creating, removing or executing files, except for the
+    /// state file
+    pub landlock: Layer,
+    /// No new programs, network sockets, debugging other processes,
+    /// mounts, kernel modules or BPF
+    pub seccomp: Layer,
+}
+
+impl SandboxReport {
+    pub fn is_complete(&self) -> bool {
+        self.landlock == Layer::Enforced && self.seccomp == Layer::Enforced
+    }
+}
+
+/// Lock ftail down for following sensitive logs, once the files to follow
+/// are open: files stay readable, so rotated logs are still picked up, but
+/// nothing can be written except `--state-file` checkpoints in
+/// `state_dir`, and the syscalls an attacker would need to escape are
+/// refused.
+///
+/// Restrictions apply to the calling thread and the threads it starts from
+/// then on, so ftail runs its runtime on this thread under `--restricted`.
+/// Each layer the kernel does not support is reported and skipped rather
+/// than treated as an error.
+pub fn restrict(state_dir: Option<&Path>) -> SandboxReport {
+    if let Err(reason) = imp::no_new_privs() {
+        return SandboxReport {
+            landlock: Layer::Unavailable(reason.clone()),
+            seccomp: Layer::Unavailable(reason),
+        };
+    }
+    SandboxReport {
+        landlock: imp::landlock(state_dir),
+        seccomp: imp::seccomp(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::Layer;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    // Landlock's ABI, from <linux/landlock.h>, which libc does not carry
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Every file-changing access Landlock knows at `abi`; reading is left
+    /// alone
+    pub(super) fn handled_access(abi: i64) -> u64 {
+        let mut access = ACCESS_FS_EXECUTE
+            | ACCESS_FS_WRITE_FILE
+            | ACCESS_FS_REMOVE_DIR
+            | ACCESS_FS_REMOVE_FILE
+            | ACCESS_FS_MAKE_CHAR
+            | ACCESS_FS_MAKE_DIR
+            | ACCESS_FS_MAKE_REG
+            | ACCESS_FS_MAKE_SOCK
+            | ACCESS_FS_MAKE_FIFO
+            | ACCESS_FS_MAKE_BLOCK
+            | ACCESS_FS_MAKE_SYM;
+        if abi >= 2 {
+            access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_FS_TRUNCATE;
+        }
+        access
+    }
+
+    /// What saving the state file takes: writing `<state>.tmp` and renaming
+    /// it over the old one
+    pub(super) fn state_access(abi: i64) -> u64 {
+        let access = ACCESS_FS_WRITE_FILE | ACCESS_FS_MAKE_REG | ACCESS_FS_REMOVE_FILE;
+        if abi >= 3 {
+            access | ACCESS_FS_TRUNCATE
+        } else {
+            access
+        }
+    }
+
+    fn last_error(what: &str) -> String {
+        format!("{}: {}", what, io::Error::last_os_error())
+    }
+
+    pub(super) fn no_new_privs() -> Result<(), String> {
+        // SAFETY: PR_SET_NO_NEW_PRIVS takes only integer arguments
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } == 0 {
+            Ok(())
+        } else {
+            Err(last_error("cannot set no_new_privs"))
+        }
+    }
+
+    pub(super) fn landlock(state_dir: Option<&Path>) -> Layer {
+        // SAFETY: asking for the ABI version takes no attribute
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Layer::Unavailable(last_error("kernel has no Landlock"));
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: handled_access(abi),
+        };
+        // SAFETY: `attr` is a valid ruleset attribute of the size given
+        let ruleset = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if ruleset < 0 {
+            return Layer::Unavailable(last_error("cannot create Landlock ruleset"));
+        }
+        let ruleset = ruleset as libc::c_int;
+
+        let layer = match state_dir.map(|dir| allow_beneath(ruleset, dir, state_access(abi))) {
+            Some(Err(reason)) => Layer::Unavailable(reason),
+            // SAFETY: `ruleset` is the descriptor created above
+            _ if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32) } != 0 => {
+                Layer::Unavailable(last_error("cannot enforce Landlock ruleset"))
+            }
+            _ => Layer::Enforced,
+        };
+        // SAFETY: `ruleset` is ours and closed once
+        unsafe { libc::close(ruleset) };
+        layer
+    }
+
+    fn allow_beneath(ruleset: libc::c_int, dir: &Path, access: u64) -> Result<(), String> {
+        let path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|_| format!("state directory '{}' contains a NUL byte", dir.display()))?;
+        // SAFETY: `path` is a valid NUL-terminated string for the whole call
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(last_error(&format!("cannot open state directory '{}'", dir.display())));
+        }
+        let rule = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: fd,
+        };
+        // SAFETY: `rule` is a valid path-beneath rule and `fd` stays open until after the call
+        let added = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset,
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0u32,
+            )
+        };
+        let result = if added == 0 {
+            Ok(())
+        } else {
+            Err(last_error("cannot allow state file writes"))
+        };
+        // SAFETY: `fd` is ours and closed once
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(super) fn seccomp() -> Layer {
+        let filter = seccomp_filter::program();
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // TSYNC puts the threads that already exist under the filter too
+        // SAFETY: `program` points at `filter`, which outlives the call
+        let installed = unsafe {
+            libc::syscall(
+                libc::SYS_seccomp,
+                libc::SECCOMP_SET_MODE_FILTER,
+                libc::SECCOMP_FILTER_FLAG_TSYNC,
+                &program as *const libc::sock_fprog,
+            )
+        };
+        if installed == 0 {
+            Layer::Enforced
+        } else {
+            Layer::Unavailable(last_error("cannot install seccomp filter"))
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub(super) fn seccomp() -> Layer {
+        Layer::Unavailable("no seccomp filter for this architecture".to_string())
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(super) mod seccomp_filter {
+        // `struct seccomp_data` offsets and audit architectures, from
+        // <linux/seccomp.h> and <linux/audit.h>
+        const SYSCALL_NR: u32 = 0;
+        const ARCH: u32 = 4;
+        #[cfg(target_arch = "x86_64")]
+        const AUDIT_ARCH: u32 = 0xC000_003E;
+        #[cfg(target_arch = "aarch64")]
+        const AUDIT_ARCH: u32 = 0xC000_00B7;
+        /// Set in syscall numbers of the x32 ABI, which shares x86_64's arch
+        #[cfg(target_arch = "x86_64")]
+        const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+        /// Refused with EPERM: running programs, reaching other processes,
+        /// the network, mounts, kernel modules, BPF and io_uring, which
+        /// would get around the filter. Threads, pipes, socketpairs and
+        /// inotify stay allowed for the runtime and the file watcher.
+        pub(in super::super) const DENIED: &[libc::c_long] = &[
+            libc::SYS_execve,
+            libc::SYS_execveat,
+            libc::SYS_ptrace,
+            libc::SYS_process_vm_readv,
+            libc::SYS_process_vm_writev,
+            libc::SYS_socket,
+            libc::SYS_mount,
+            libc::SYS_umount2,
+            libc::SYS_pivot_root,
+            libc::SYS_chroot,
+            libc::SYS_open_tree,
+            libc::SYS_move_mount,
+            libc::SYS_fsopen,
+            libc::SYS_fsmount,
+            libc::SYS_fspick,
+            libc::SYS_unshare,
+            libc::SYS_setns,
+            libc::SYS_init_module,
+            libc::SYS_finit_module,
+            libc::SYS_delete_module,
+            libc::SYS_kexec_load,
+            libc::SYS_kexec_file_load,
+            libc::SYS_reboot,
+            libc::SYS_swapon,
+            libc::SYS_swapoff,
+            libc::SYS_bpf,
+            libc::SYS_perf_event_open,
+            libc::SYS_userfaultfd,
+            libc::SYS_keyctl,
+            libc::SYS_add_key,
+            libc::SYS_request_key,
+            libc::SYS_io_uring_setup,
+        ];
+
+        fn statement(code: u32, k: u32) -> libc::sock_filter {
+            libc::sock_filter {
+                code: code as u16,
+                jt: 0,
+                jf: 0,
+                k,
+            }
+        }
+
+        fn jump_if_equal(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+            libc::sock_filter {
+                code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+                jt,
+                jf,
+                k,
+            }
+        }
+
+        /// The filter: other architectures and every denied syscall jump to
+        /// the final EPERM; everything else is allowed
+        pub(in super::super) fn program() -> Vec<libc::sock_filter> {
+            let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+            let deny = libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32 & libc::SECCOMP_RET_DATA);
+            let mut program = vec![
+                statement(load, ARCH),
+                jump_if_equal(AUDIT_ARCH, 1, 0),
+                statement(libc::BPF_RET | libc::BPF_K, deny),
+                statement(load, SYSCALL_NR),
+            ];
+            let first_check = program.len();
+            #[cfg(target_arch = "x86_64")]
+            program.push(libc::sock_filter {
+                code: (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 0,
+                k: X32_SYSCALL_BIT,
+            });
+            program.extend(DENIED.iter().map(|&nr| jump_if_equal(nr as u32, 0, 0)));
+            program.push(statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+            program.push(statement(libc::BPF_RET | libc::BPF_K, deny));
+
+            // Point each check at the final instruction
+            let deny_at = program.len() - 1;
+            for (index, instruction) in program.iter_mut().enumerate().take(deny_at - 1).skip(first_check) {
+                instruction.jt = (deny_at - index - 1) as u8;
+            }
+            program
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::Layer;
+    use std::path::Path;
+
+    pub(super) fn no_new_privs() -> Result<(), String> {
+        Err("only supported on Linux".to_string())
+    }
+
+    pub(super) fn landlock(_state_dir: Option<&Path>) -> Layer {
+        Layer::Unavailable("only supported on Linux".to_string())
+    }
+
+    pub(super) fn seccomp() -> Layer {
+        Layer::Unavailable("only supported on Linux".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let report = SandboxReport {
+            landlock: Layer::Enforced,
+            seccomp: Layer::Unavailable("kernel has no seccomp".to_string()),
+        };
+        assert!(!report.is_complete());
+        assert_eq!(report.seccomp.to_string(), "unavailable (kernel has no seccomp)");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_landlock_access_grows_with_abi() {
+        assert_eq!(imp::handled_access(1) & (1 << 13), 0);
+        assert_ne!(imp::handled_access(3) & (1 << 14), 0);
+        // The state file directory only gets rights the ruleset handles
+        for abi in 1..=5 {
+            assert_eq!(imp::state_access(abi) & !imp::handled_access(abi), 0);
+        }
+    }
+
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    #[test]
+    fn test_seccomp_checks_jump_to_deny() {
+        let program = imp::seccomp_filter::program();
+        let deny_at = program.len() - 1;
+        assert_eq!(program[deny_at - 1].k, libc::SECCOMP_RET_ALLOW);
+        assert_eq!(program[deny_at].k, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32);
+
+        // Everything after the architecture check and syscall number load
+        let checks: Vec<usize> = (4..deny_at - 1).collect();
+        assert!(checks.len() >= imp::seccomp_filter::DENIED.len());
+        for index in checks {
+            assert_eq!(index + 1 + program[index].jt as usize, deny_at);
+            assert_eq!(program[index].jf, 0);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::errors::{FastTailError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const STATE_VERSION: u32 = 1;
+
+/// Read position of a single monitored file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub inode: Option<u64>,
+    pub offset: u64,
+    pub line_count: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateData {
+    version: u32,
+    files: BTreeMap<PathBuf, Checkpoint>,
+}
+
+/// Persistent per-file checkpoints, so a restarted ftail resumes where the
+/// previous run stopped instead of re-emitting or skipping lines.
+///
+/// Entries for files that are not monitored in the current run are kept, so
+/// several ftail invocations with different file sets can share one state file.
+#[derive(Debug)]
+pub struct StateFile {
+    path: PathBuf,
+    data: StateData,
+    save_interval: Duration,
+    last_save: Instant,
+}
+
+impl StateFile {
+    /// Load checkpoints from `path`; a missing file yields an empty state
+    pub fn load(path: PathBuf, save_interval: Duration) -> Result<Self> {
+        let data = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let data: StateData = serde_json::from_str(&contents)
+                    .map_err(|e| FastTailError::state_file(path.clone(), e.to_string()))?;
+                if data.version != STATE_VERSION {
+                    return Err(FastTailError::state_file(
+                        path,
+                        format!("unsupported state version {}", data.version),
+                    ));
+                }
+                data
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StateData {
+                version: STATE_VERSION,
+                files: BTreeMap::new(),
+            },
+            Err(e) => return Err(FastTailError::state_file(path, e.to_string())),
+        };
+
+        Ok(Self {
+            path,
+            data,
+            save_interval,
+            last_save: Instant::now(),
+        })
+    }
+
+    pub fn get(&self, file: &Path) -> Option<&Checkpoint> {
+        self.data.files.get(&state_key(file))
+    }
+
+    pub fn update(&mut self, file: &Path, checkpoint: Checkpoint) {
+        self.data.files.insert(state_key(file), checkpoint);
+    }
+
+    pub fn is_save_due(&self) -> bool {
+        self.last_save.elapsed() >= self.save_interval
+    }
+
+    /// Write the state atomically: a crash mid-save leaves the previous
+    /// checkpoint intact rather than a truncated file
+    pub fn save(&mut self) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.data)?;
+        let mut tmp_name = self.path.clone().into_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+
+        std::fs::write(&tmp_path, contents)
+            .and_then(|_| std::fs::rename(&tmp_path, &self.path))
+            .map_err(|e| FastTailError::state_file(self.path.clone(), e.to_string()))?;
+
+        self.last_save = Instant::now();
+        Ok(())
+    }
+}
+
+/// Key checkpoints by absolute path so a restart from another working
+/// directory still finds them; symlinks are deliberately not resolved, since
+/// `-F` users usually point at a name that gets re-targeted on rotation.
+fn state_key(file: &Path) -> PathBuf {
+    std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_state_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let state = StateFile::load(dir.path().join("state.json"), Duration::from_secs(5)).unwrap();
+        assert!(state.get(Path::new("/var/log/app.log")).is_none());
+    }
+
+    #[test]
+    fn test_round_trip_preserves_other_entries() {
+        let dir = TempDir::new().unwrap();
+        let state_path = dir.path().join("state.json");
+        let checkpoint = Checkpoint {
+            inode: Some(42),
+            offset: 1024,
+            line_count: 17,
+        };
+
+        let mut state = StateFile::load(state_path.clone(), Duration::from_secs(5)).unwrap();
+        state.update(Path::new("/var/log/a.log"), checkpoint.clone());
+        state.update(Path::new("/var/log/b.log"), checkpoint.clone());
+        state.save().unwrap();
+
+        let mut state = StateFile::load(state_path.clone(), Duration::from_secs(5)).unwrap();
+        state.update(
+            Path::new("/var/log/a.log"),
+            Checkpoint {
+                offset: 2048,
+                ..checkpoint.clone()
+            },
+        );
+        state.save().unwrap();
+
+        let state = StateFile::load(state_path, Duration::from_secs(5)).unwrap();
+        assert_eq!(state.get(Path::new("/var/log/a.log")).unwrap().offset, 2048);
+        assert_eq!(state.get(Path::new("/var/log/b.log")), Some(&checkpoint));
+    }
+
+    #[test]
+    fn test_corrupt_state_file_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let state_path = dir.path().join("state.json");
+        std::fs::write(&state_path, "not json").unwrap();
+        assert!(StateFile::load(state_path, Duration::from_secs(5)).is_err());
+    }
+}
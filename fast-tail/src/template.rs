@@ -0,0 +1,191 @@
+use chrono::format::{Item, StrftimeItems};
+use std::str::FromStr;
+
+/// How `{time}` is shown without a format of its own, as in the text output
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// Severity words `{level}` looks for, and the name each is shown as
+const LEVELS: [(&str, &str); 11] = [
+    ("FATAL", "FATAL"),
+    ("CRITICAL", "FATAL"),
+    ("CRIT", "FATAL"),
+    ("ERROR", "ERROR"),
+    ("ERR", "ERROR"),
+    ("WARNING", "WARN"),
+    ("WARN", "WARN"),
+    ("NOTICE", "INFO"),
+    ("INFO", "INFO"),
+    ("DEBUG", "DEBUG"),
+    ("TRACE", "TRACE"),
+];
+
+/// A piece of an output line that `--template` fills in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placeholder {
+    /// `{time}` or `{time:FORMAT}`: when the line was read; startup lines,
+    /// read before following began, show when they are printed
+    Time(String),
+    /// `{file}`: the file's name
+    File,
+    /// `{path}`: the file as given on the command line
+    Path,
+    /// `{lineno}`
+    LineNumber,
+    /// `{line}`: the line itself
+    Line,
+    /// `{level}`: the first severity word in the line, such as ERROR or WARN
+    Level,
+    /// `{1}`, `{2}`, ...: a capture group of the first `--grep` pattern
+    /// that matches, `{0}` the whole match
+    Group(usize),
+    /// `{cap:NAME}`: a named capture group
+    NamedGroup(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Literal(String),
+    Field(Placeholder),
+}
+
+/// `--template`: the layout of each output line, text with `{placeholder}`s
+/// and `{{` and `}}` for literal braces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Whether any placeholder needs the `--grep` pattern's captures
+    pub fn uses_captures(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Field(Placeholder::Group(_) | Placeholder::NamedGroup(_))))
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = spec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| format!("unclosed '{{' in template '{}'", spec))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(parse_placeholder(name)?));
+                    chars = rest.chars();
+                }
+                '}' => return Err(format!("unmatched '}}' in template '{}'; write '}}}}' for a brace", spec)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+}
+
+fn parse_placeholder(name: &str) -> Result<Placeholder, String> {
+    let placeholder = match name {
+        "time" => Placeholder::Time(DEFAULT_TIME_FORMAT.to_string()),
+        "file" => Placeholder::File,
+        "path" => Placeholder::Path,
+        "lineno" => Placeholder::LineNumber,
+        "line" => Placeholder::Line,
+        "level" => Placeholder::Level,
+        _ => {
+            if let Some(format) = name.strip_prefix("time:") {
+                if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                    return Err(format!("invalid time format '{}'", format));
+                }
+                Placeholder::Time(format.to_string())
+            } else if let Some(group) = name.strip_prefix("cap:").filter(|group| !group.is_empty()) {
+                Placeholder::NamedGroup(group.to_string())
+            } else if let Ok(group) = name.parse() {
+                Placeholder::Group(group)
+            } else {
+                return Err(format!(
+                    "unknown placeholder '{{{}}}'; use time, time:FORMAT, file, path, lineno, line, level, a group number or cap:NAME",
+                    name
+                ));
+            }
+        }
+    };
+    Ok(placeholder)
+}
+
+/// The severity of `line`: its first word, ignoring case, that names a
+/// log level, so "[warn] disk" gives WARN
+pub fn detect_level(line: &str) -> Option<&'static str> {
+    line.split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|word| !word.is_empty())
+        .find_map(|word| {
+            LEVELS
+                .iter()
+                .find(|(level, _)| word.eq_ignore_ascii_case(level))
+                .map(|&(_, shown)| shown)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let template: Template = "{time:%H:%M} [{file}] {{{1}}} {line}".parse().unwrap();
+        assert_eq!(
+            template.segments(),
+            [
+                Segment::Field(Placeholder::Time("%H:%M".to_string())),
+                Segment::Literal(" [".to_string()),
+                Segment::Field(Placeholder::File),
+                Segment::Literal("] {".to_string()),
+                Segment::Field(Placeholder::Group(1)),
+                Segment::Literal("} ".to_string()),
+                Segment::Field(Placeholder::Line),
+            ]
+        );
+        assert!(template.uses_captures());
+        assert!(!"{level}: {line}".parse::<Template>().unwrap().uses_captures());
+
+        assert!("{line".parse::<Template>().is_err());
+        assert!("line}".parse::<Template>().is_err());
+        assert!("{host}".parse::<Template>().is_err());
+        assert!("{time:%Q}".parse::<Template>().is_err());
+        assert_eq!(
+            "{cap:user}".parse::<Template>().unwrap().segments(),
+            [Segment::Field(Placeholder::NamedGroup("user".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_detect_level() {
+        assert_eq!(detect_level("2026-03-01 [warn] disk 91% full"), Some("WARN"));
+        assert_eq!(detect_level("E: ERROR: timeout after WARNING"), Some("ERROR"));
+        assert_eq!(detect_level("level=debug msg=x"), Some("DEBUG"));
+        assert_eq!(detect_level("informational errors"), None);
+    }
+}